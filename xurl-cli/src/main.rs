@@ -1,113 +1,2202 @@
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
 use std::path::{Path, PathBuf};
 use std::process::ExitCode;
+use std::time::{Duration, Instant};
 use std::{fs, io};
 
-use std::io::{Read, Write};
+use std::io::{BufRead, IsTerminal, Read, Write};
 
-use clap::Parser;
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use tracing_subscriber::EnvFilter;
+use xurl_core::render::{DEFAULT_PI_PREVIEW_CHARS, DEFAULT_TIMELINE_CONTEXT};
+use xurl_core::status::{
+    STATUS_COMPLETED, STATUS_ERRORED, STATUS_NOT_FOUND, STATUS_PENDING_INIT, STATUS_RUNNING,
+};
+use xurl_core::timestamp::parse_age;
+use xurl_core::{
+    AnnotateRequest, AtomicFile, CopyRequest, ExportFormat, ExportRequest, ExtractionMode, GcMode,
+    GcRequest, HeadDocument, HeadFields, ImportRequest, MessageRole, Notification, NotifyConfig,
+    NotifyEvent, OutputTemplate, ProviderKind, ProviderRoots, ReadOptions, SnapshotRequest,
+    StreamSink, SubagentView, Summarizer, TableFormat, ThreadUri, TimeWindow, TimelineEntryKind,
+    TimelineFormat, TimelinePage, WaitRequest, Warning, WarningSeverity, WriteEventSink,
+    WriteRequest, WriteResult, XurlError, annotate_thread, build_thread_head, check_storage,
+    collect_garbage, copy_thread, diff_pi_branches, diff_thread_head, expand_share_uri,
+    expand_tag_uri, export_thread, find_duplicate_threads, follow_subagent, fork_thread,
+    import_thread, list_recent_threads, locate_thread, notification, notify, notify_config,
+    pack_thread, render_dedupe_report_markdown, render_doctor_report_json,
+    render_doctor_report_markdown, render_gc_report_markdown, render_head_diff_json,
+    render_head_diff_markdown, render_packed_thread_markdown, render_pi_diff_markdown,
+    render_recent_threads_markdown, render_subagent_view_json, render_subagent_view_markdown,
+    render_subagent_view_template, render_summary_markdown, render_thread, render_thread_context,
+    render_thread_entry_json, render_thread_lineage_json, render_thread_lineage_markdown,
+    render_thread_location_json, render_thread_stats_json, render_thread_stats_markdown,
+    render_thread_template, render_trace_otlp_json, render_verify_report_json,
+    render_verify_report_markdown, resolve_any_uri, resolve_session_prefix, resolve_subagent_view,
+    resolve_tag, resolve_thread, send_notification, set_notify_config, snapshot_thread,
+    summarize_thread, tag_thread, thread_lineage, thread_stats, trace_thread, verify_thread,
+    wait_for_subagent, write_atomic, write_thread,
+};
+#[cfg(feature = "semantic-search")]
 use xurl_core::{
-    ProviderKind, ProviderRoots, ThreadUri, WriteEventSink, WriteRequest, WriteResult, XurlError,
-    render_subagent_view_markdown, render_thread_head_markdown, render_thread_markdown,
-    resolve_subagent_view, resolve_thread, write_thread,
+    SemanticSearchRequest, render_semantic_search_json, render_semantic_search_markdown,
+    semantic_search,
 };
 
-#[derive(Debug, Parser)]
-#[command(name = "xurl", version, about = "Resolve and read code-agent threads")]
-struct Cli {
-    /// Thread URI like agents://codex/<session_id>, agents://claude/<session_id>, agents://pi/<session_id>/<entry_id>, or legacy forms like codex://<session_id>
-    uri: String,
+#[derive(Debug, Parser)]
+#[command(
+    name = "xurl",
+    version,
+    about = "Resolve and read code-agent threads",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Thread URI like agents://codex/<session_id>, agents://claude/<session_id>, agents://pi/<session_id>/<entry_id>, any://<session_id> to find the provider, or legacy forms like codex://<session_id>
+    #[arg(required = true)]
+    uri: Option<String>,
+
+    /// Output frontmatter only (header mode)
+    #[arg(short = 'I', long)]
+    head: bool,
+
+    /// With -I, emit only these comma-separated top-level keys (e.g. uri,provider,subagents), or all but the `-`-prefixed ones (e.g. -warnings)
+    #[arg(
+        long = "head-fields",
+        value_name = "FIELDS",
+        requires = "head",
+        allow_hyphen_values = true
+    )]
+    head_fields: Option<String>,
+
+    /// Add `fingerprint`, a hash of a main conversation's messages, to the frontmatter
+    #[arg(long = "fingerprint", conflicts_with_all = ["data", "json"])]
+    fingerprint: bool,
+
+    /// Print the subagent index or detail view as JSON; with a #msg-N URI, that timeline entry
+    #[arg(long, conflicts_with = "data")]
+    json: bool,
+
+    /// Hide warnings in head and JSON output
+    #[arg(short = 'q', long, conflicts_with = "warnings")]
+    quiet: bool,
+
+    /// In write mode, print only the thread's URI on stdout; the reply goes to --output or is dropped
+    #[arg(long = "print-uri", requires = "data", conflicts_with = "porcelain")]
+    print_uri: bool,
+
+    /// Keep stdout for the rendered content and report on stderr in line records with stable prefixes: `uri:`, `warning:`, and a final `status:`
+    #[arg(long = "porcelain", conflicts_with = "quiet")]
+    porcelain: bool,
+
+    /// Least severe warning to show in head and JSON output
+    #[arg(long = "warnings", value_name = "LEVEL", value_enum, default_value_t = WarningLevel::Info)]
+    warnings: WarningLevel,
+
+    /// Send write-mode payload data; may be repeated. Prefix with @file or @- for stdin.
+    #[arg(short = 'd', long = "data", value_name = "DATA")]
+    data: Vec<String>,
+
+    /// Prepend another thread's conversation to the write-mode prompt; may be repeated
+    #[arg(long = "context", value_name = "URI", requires = "data")]
+    context: Vec<String>,
+
+    /// Only include the last N messages of each --context thread
+    #[arg(long = "context-tail", value_name = "N", requires = "context")]
+    context_tail: Option<usize>,
+
+    /// Write output to a file instead of stdout
+    #[arg(short = 'o', long = "output", value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    /// Run this binary for the write-mode provider (overrides XURL_<PROVIDER>_BIN and PATH)
+    #[arg(long = "bin", value_name = "PATH", requires = "data")]
+    bin: Option<PathBuf>,
+
+    /// Append the write-mode agent's full stderr to this file
+    #[arg(long = "debug-write-log", value_name = "PATH", requires = "data")]
+    debug_write_log: Option<PathBuf>,
+
+    #[command(flatten)]
+    project: ProjectArgs,
+
+    /// How many levels of Amp handoff chains to follow in the subagent index
+    #[arg(long = "handoff-depth", value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    handoff_depth: Option<u16>,
+
+    /// Read every Codex rollout of the session, oldest first, instead of only the latest
+    #[arg(long = "merge-rollouts")]
+    merge_rollouts: bool,
+
+    /// Render every branch of a Pi session's entry tree, not only the latest path
+    #[arg(long = "all-branches", conflicts_with_all = ["data", "json", "head", "page", "page_size"])]
+    all_branches: bool,
+
+    /// Render only this page of the timeline (1-based); the frontmatter links the previous and next pages
+    #[arg(long = "page", value_name = "N", value_parser = clap::value_parser!(u64).range(1..), conflicts_with_all = ["data", "json"])]
+    page: Option<u64>,
+
+    /// Timeline entries per page (default: 100)
+    #[arg(long = "page-size", value_name = "M", value_parser = clap::value_parser!(u64).range(1..), conflicts_with_all = ["data", "json"])]
+    page_size: Option<u64>,
+
+    /// Entries shown before and after the entry a #msg-<N> URI fragment points at
+    #[arg(long = "around", value_name = "N", default_value_t = DEFAULT_TIMELINE_CONTEXT, conflicts_with = "data")]
+    around: usize,
+
+    /// Longest entry preview in a Pi session's -I entry index
+    #[arg(long = "preview-chars", value_name = "N", value_parser = clap::value_parser!(u64).range(1..), default_value_t = DEFAULT_PI_PREVIEW_CHARS as u64, conflicts_with = "data")]
+    preview_chars: u64,
+
+    /// Show whole entry texts instead of previews in a Pi session's -I entry index
+    #[arg(long = "full-previews", conflicts_with_all = ["data", "preview_chars"])]
+    full_previews: bool,
+
+    /// Only render timeline entries of this kind; may be repeated or comma-separated
+    #[arg(long = "only", value_name = "KIND", value_enum, value_delimiter = ',', conflicts_with_all = ["data", "json", "head", "all_branches"])]
+    only: Vec<EntryKind>,
+
+    /// Show under each timeline entry the file and line (or JSON pointer) it was read from
+    #[arg(long = "provenance", conflicts_with_all = ["data", "json", "head", "all_branches", "follow"])]
+    provenance: bool,
+
+    /// Collapse runs of entries with the same role and (nearly) the same text, e.g. replies logged again after a retry, into one entry with a repeat count
+    #[arg(long = "dedupe", conflicts_with_all = ["data", "json", "head", "all_branches"])]
+    dedupe: bool,
+
+    /// Show the summary each compaction left (e.g. a Codex `compacted` record's message) instead of a placeholder (or set XURL_EXPAND_COMPACTIONS=1)
+    #[arg(long = "expand-compactions", conflicts_with = "data")]
+    expand_compactions: bool,
+
+    /// Text shown for compactions without a summary, instead of "Context was compacted." (or set XURL_COMPACT_PLACEHOLDER)
+    #[arg(
+        long = "compact-placeholder",
+        value_name = "TEXT",
+        conflicts_with = "data"
+    )]
+    compact_placeholder: Option<String>,
+
+    /// Show each failed Claude tool call's result as a "Tool Error" entry with the start of its output (or set XURL_TOOL_ERRORS=1)
+    #[arg(long = "tool-errors", conflicts_with = "data")]
+    tool_errors: bool,
+
+    /// Show each Amp tool call as a collapsed "Tool Call" entry with its input and the result paired with it (or set XURL_AMP_TOOLS=1)
+    #[arg(long = "amp-tools", conflicts_with = "data")]
+    amp_tools: bool,
+
+    /// Fail on the first transcript line that does not parse (the default unless XURL_EXTRACTION=lenient)
+    #[arg(long = "strict", conflicts_with = "lenient")]
+    strict: bool,
+
+    /// Skip transcript lines that do not parse, reporting each as a warning in the head
+    #[arg(long = "lenient")]
+    lenient: bool,
+
+    /// Let xurl download threads it cannot read locally, such as https://opencode.ai/s/<id> share links (or set XURL_ALLOW_NETWORK=1)
+    #[arg(long = "allow-network")]
+    allow_network: bool,
+
+    /// Re-read a thread file until two reads in a row see the same length, for threads an agent is still writing
+    #[arg(long = "consistent", conflicts_with = "data")]
+    consistent: bool,
+
+    /// Only list subagents active at or after this time (RFC3339, YYYY-MM-DD, or epoch seconds)
+    #[arg(long = "since", value_name = "TIME")]
+    since: Option<String>,
+
+    /// Only list subagents active at or before this time (a bare date covers the whole day)
+    #[arg(long = "until", value_name = "TIME")]
+    until: Option<String>,
+
+    /// Exit 3 if the thread is not found, 4 if a subagent errored, 5 while one has not finished
+    #[arg(long = "exit-status", conflicts_with = "data")]
+    exit_status: bool,
+
+    /// Stream a Claude subagent's messages as they are written until it completes, errors, or shuts down
+    #[arg(long = "follow", conflicts_with_all = ["data", "json", "head", "all_branches", "page", "page_size", "only"])]
+    follow: bool,
+
+    /// Print the timeline as Markdown, one JSON document, a standalone HTML page, plain text, or CSV/TSV with one row per entry (index, role, timestamp, chars, text)
+    #[arg(long = "format", value_name = "FORMAT", value_enum, default_value_t = ReadFormat::Markdown, conflicts_with_all = ["data", "json", "head", "all_branches", "follow", "exit_status"])]
+    format: ReadFormat,
+
+    /// Render the read through a Tera template: a thread gets the document --format json prints, a subagent view (a subagent URI, or --json on a main thread) the one --json prints
+    #[arg(long = "template", value_name = "PATH", conflicts_with_all = ["data", "head", "all_branches", "follow", "format", "exit_status"])]
+    template: Option<PathBuf>,
+
+    /// Log how xurl resolves threads to stderr: -v for debug, -vv for trace. Overrides RUST_LOG.
+    #[arg(short = 'v', long = "verbose", action = ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Never look for threads in the home directory: only search provider roots set through their variables (CODEX_HOME, CLAUDE_CONFIG_DIR, XURL_<PROVIDER>_ROOTS, ...), for CI and tests (or set XURL_NO_DEFAULT_ROOTS=1)
+    #[arg(long = "no-default-roots", global = true)]
+    no_default_roots: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ReadFormat {
+    Markdown,
+    Json,
+    Html,
+    Text,
+    Csv,
+    Tsv,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum WarningLevel {
+    Info,
+    Warn,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum TraceFormat {
+    /// OTLP JSON, as accepted by collectors at /v1/traces
+    OtlpJson,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ExportTarget {
+    /// Claude Code session transcript, continued with `claude --resume <id>`
+    ClaudeSession,
+    /// Codex rollout, continued with `codex resume <id>`
+    CodexRollout,
+}
+
+impl From<ExportTarget> for ExportFormat {
+    fn from(target: ExportTarget) -> Self {
+        match target {
+            ExportTarget::ClaudeSession => Self::ClaudeSession,
+            ExportTarget::CodexRollout => Self::CodexRollout,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum EntryKind {
+    User,
+    Assistant,
+    Compact,
+    ToolError,
+    ToolCall,
+}
+
+impl From<EntryKind> for TimelineEntryKind {
+    fn from(kind: EntryKind) -> Self {
+        match kind {
+            EntryKind::User => Self::User,
+            EntryKind::Assistant => Self::Assistant,
+            EntryKind::Compact => Self::Compact,
+            EntryKind::ToolError => Self::ToolError,
+            EntryKind::ToolCall => Self::ToolCall,
+        }
+    }
+}
+
+impl From<WarningLevel> for WarningSeverity {
+    fn from(level: WarningLevel) -> Self {
+        match level {
+            WarningLevel::Info => Self::Info,
+            WarningLevel::Warn => Self::Warn,
+            WarningLevel::Error => Self::Error,
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Summarize a thread into goals, decisions, and open TODOs
+    Summarize(SummarizeArgs),
+    /// Copy an external transcript into local provider storage so it resolves by URI
+    Import(ImportArgs),
+    /// Archive or delete sessions with no activity for a while (dry run by default)
+    Gc(GcArgs),
+    /// List the most recently updated threads across every provider
+    Recent(RecentArgs),
+    /// Fit a thread into a token budget, keeping the newest messages verbatim
+    Pack(PackArgs),
+    /// Attach a note to one timeline message; it is shown under that message on later reads
+    Annotate(AnnotateArgs),
+    /// Check a thread file for broken, truncated, or out-of-order records (exits 1 on problems)
+    Verify(VerifyArgs),
+    /// List every provider's storage roots with the on-disk layout and format version found in each
+    Doctor(DoctorArgs),
+    /// Compare a thread's -I head with a stored baseline file (exits 1 when they differ)
+    HeadDiff(HeadDiffArgs),
+    /// Show per-turn response latency and its p50/p95 for a thread
+    Stats(StatsArgs),
+    /// Show the family tree of sessions a Claude or Codex thread was resumed or forked from, and into
+    Lineage(LineageArgs),
+    /// Find the messages closest in meaning to a query across every local thread
+    #[cfg(feature = "semantic-search")]
+    Search(SearchArgs),
+    /// Keep writing to one thread: each stdin line is sent as the next prompt and the reply streamed back
+    Chat(ChatArgs),
+    /// Poll a subagent until it completes, errors, or shuts down, printing each status change
+    Wait(WaitArgs),
+    /// Post write-mode results and `xurl wait` outcomes to a webhook, such as a Slack incoming webhook
+    Notify(NotifyArgs),
+    /// Find threads whose messages are identical, e.g. copied or re-imported rollouts
+    Dedupe(DedupeArgs),
+    /// Export a thread and its subagents as trace spans (OTLP JSON) for Jaeger or Tempo
+    Trace(TraceArgs),
+    /// Convert a thread from any provider into a Claude or Codex session that agent can resume
+    Export(ExportArgs),
+    /// Fork a Pi or Codex thread at its latest entry to continue it in another direction, and print the fork's URI
+    Fork(ForkArgs),
+    /// Copy a thread into another root of its provider, such as a synced home on a server, keeping its layout
+    Cp(CpArgs),
+    /// Compare two branches of a Pi session side by side, from the entry where they split
+    PiDiff(PiDiffArgs),
+    /// Give a thread a short name, usable afterwards as tag://<name> wherever a URI is accepted
+    Tag(TagArgs),
+    /// Print the thread URI a tag names
+    ResolveTag(ResolveTagArgs),
+    /// Print a URI's canonical agents:// form and the file it is read from, tab-separated
+    Resolve(ResolveArgs),
+    /// Copy a thread and its subagent files into a read-only snapshot directory and print its file:// URI
+    Snapshot(SnapshotArgs),
+}
+
+/// `--project`, shared by every command that resolves thread URIs.
+#[derive(Debug, Args)]
+struct ProjectArgs {
+    /// Restrict Claude session lookup to one project (directory name under projects/ or a working-directory path)
+    #[arg(
+        long = "project",
+        value_name = "NAME_OR_PATH",
+        allow_hyphen_values = true
+    )]
+    project: Option<String>,
+}
+
+#[derive(Debug, Args)]
+struct SnapshotArgs {
+    /// Thread URI to snapshot
+    uri: String,
+
+    /// Directory to create the snapshot in (default: snapshots/ under the xurl data directory)
+    #[arg(long = "out", value_name = "DIR")]
+    out: Option<PathBuf>,
+
+    #[command(flatten)]
+    project: ProjectArgs,
+}
+
+#[derive(Debug, Args)]
+struct ResolveArgs {
+    /// Thread, subagent, or Pi entry URI in any accepted form
+    uri: String,
+
+    /// Print the location as JSON (uri, provider, session_id, agent_id, path)
+    #[arg(long)]
+    json: bool,
+
+    #[command(flatten)]
+    project: ProjectArgs,
+}
+
+#[derive(Debug, Args)]
+struct TagArgs {
+    /// Thread URI to tag
+    uri: String,
+
+    /// Tag name (letters, digits, `-`, and `_`); an existing tag of that name is moved
+    name: String,
+
+    #[command(flatten)]
+    project: ProjectArgs,
+}
+
+#[derive(Debug, Args)]
+struct ResolveTagArgs {
+    /// Tag name given to `xurl tag`
+    name: String,
+}
+
+#[derive(Debug, Args)]
+struct PiDiffArgs {
+    /// First branch, e.g. agents://pi/<session_id>/<entry_id> (without an entry id: the latest branch)
+    left: String,
+
+    /// Second branch of the same session
+    right: String,
+
+    /// Write the comparison to a file instead of stdout
+    #[arg(short = 'o', long = "output", value_name = "PATH")]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+struct ExportArgs {
+    /// Main thread URI to export
+    uri: String,
+
+    /// Session format to write
+    #[arg(long = "format", value_enum, default_value_t = ExportTarget::ClaudeSession)]
+    format: ExportTarget,
+
+    /// Working directory to resume the session in (default: the one the thread recorded, else the current directory)
+    #[arg(long = "cwd", value_name = "PATH")]
+    cwd: Option<PathBuf>,
+
+    /// Write the transcript to a file instead of the target agent's session storage
+    #[arg(short = 'o', long = "output", value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    #[command(flatten)]
+    project: ProjectArgs,
+}
+
+#[derive(Debug, Args)]
+struct ForkArgs {
+    /// Codex thread URI, or Pi thread or entry URI to fork
+    uri: String,
+
+    /// First prompt of the fork, sent right after forking; may be repeated. Prefix with @file or @- for stdin.
+    #[arg(short = 'd', long = "data", value_name = "DATA")]
+    data: Vec<String>,
+
+    /// Run this binary for the provider (overrides XURL_<PROVIDER>_BIN and PATH)
+    #[arg(long = "bin", value_name = "PATH")]
+    bin: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+struct CpArgs {
+    /// Main thread URI to copy
+    uri: String,
+
+    /// Root of the same provider to copy into, e.g. another CODEX_HOME
+    destination: PathBuf,
+
+    /// Replace the thread if the destination already has it
+    #[arg(long = "force")]
+    force: bool,
+
+    #[command(flatten)]
+    project: ProjectArgs,
+}
+
+#[derive(Debug, Args)]
+struct TraceArgs {
+    /// Main thread URI to export
+    uri: String,
+
+    /// Trace encoding
+    #[arg(long = "format", value_enum, default_value_t = TraceFormat::OtlpJson)]
+    format: TraceFormat,
+
+    /// Write the trace to a file instead of stdout
+    #[arg(short = 'o', long = "output", value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    #[command(flatten)]
+    project: ProjectArgs,
+}
+
+#[derive(Debug, Args)]
+struct DedupeArgs {
+    /// Provider whose local threads to compare
+    #[arg(long = "provider", value_name = "PROVIDER")]
+    provider: String,
+
+    /// Only compare threads whose recorded working directory is this directory or inside it
+    #[arg(long = "project", value_name = "PATH")]
+    project: Option<PathBuf>,
+
+    /// Write the report to a file instead of stdout
+    #[arg(short = 'o', long = "output", value_name = "PATH")]
+    output: Option<PathBuf>,
+}
+
+#[cfg(feature = "semantic-search")]
+#[derive(Debug, Args)]
+struct SearchArgs {
+    /// What to look for, in plain words
+    #[arg(long = "semantic", value_name = "QUERY")]
+    semantic: String,
+
+    /// Only search this provider's threads; may be repeated (default: all)
+    #[arg(long = "provider", value_name = "PROVIDER")]
+    provider: Vec<String>,
+
+    /// Number of messages to show
+    #[arg(long = "limit", value_name = "N", default_value_t = 10)]
+    limit: usize,
+
+    /// Shell command that embeds texts: one JSON string per stdin line in, one JSON array of numbers per line out (defaults to $XURL_EMBEDDING_CMD)
+    #[arg(long = "embed-cmd", value_name = "CMD")]
+    embed_cmd: Option<String>,
+
+    /// Print the results as JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Write the results to a file instead of stdout
+    #[arg(short = 'o', long = "output", value_name = "PATH")]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+struct WaitArgs {
+    /// Subagent URI like agents://codex/<main_thread_id>/<agent_id>
+    uri: String,
+
+    /// Give up after this many seconds (0 waits indefinitely)
+    #[arg(long = "timeout", value_name = "SECS", default_value_t = 300)]
+    timeout: u64,
+
+    /// Seconds between status checks
+    #[arg(long = "interval", value_name = "SECS", default_value_t = 2, value_parser = clap::value_parser!(u64).range(1..))]
+    interval: u64,
+
+    #[command(flatten)]
+    project: ProjectArgs,
+}
+
+#[derive(Debug, Args)]
+struct NotifyArgs {
+    /// Webhook to POST a JSON notification to, saved in xurl's config.toml
+    #[arg(long = "webhook", value_name = "URL", conflicts_with = "clear")]
+    webhook: Option<String>,
+
+    /// Only post these events: write (a write-mode run finished or failed), wait (`xurl wait` saw a terminal status)
+    #[arg(
+        long = "events",
+        value_name = "EVENT",
+        value_enum,
+        value_delimiter = ',',
+        conflicts_with = "clear"
+    )]
+    events: Vec<NotifyEventArg>,
+
+    /// Remove the webhook, so nothing is posted
+    #[arg(long = "clear")]
+    clear: bool,
+
+    /// Post a test notification to the webhook
+    #[arg(long = "test", conflicts_with = "clear")]
+    test: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum NotifyEventArg {
+    Write,
+    Wait,
+}
+
+impl From<NotifyEventArg> for NotifyEvent {
+    fn from(event: NotifyEventArg) -> Self {
+        match event {
+            NotifyEventArg::Write => Self::Write,
+            NotifyEventArg::Wait => Self::Wait,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+struct ChatArgs {
+    /// agents://<provider> to start a new thread, or a thread URI to continue
+    uri: String,
+
+    /// Run this binary for the provider (overrides XURL_<PROVIDER>_BIN and PATH)
+    #[arg(long = "bin", value_name = "PATH")]
+    bin: Option<PathBuf>,
+
+    /// Append the agent's full stderr to this file
+    #[arg(long = "debug-write-log", value_name = "PATH")]
+    debug_write_log: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+struct VerifyArgs {
+    /// Thread URI to verify
+    uri: String,
+
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Write the report to a file instead of stdout
+    #[arg(short = 'o', long = "output", value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    #[command(flatten)]
+    project: ProjectArgs,
+}
+
+#[derive(Debug, Args)]
+struct DoctorArgs {
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Write the report to a file instead of stdout
+    #[arg(short = 'o', long = "output", value_name = "PATH")]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+struct HeadDiffArgs {
+    /// Thread or subagent URI whose head to compare
+    uri: String,
+
+    /// Head frontmatter to compare against, as written by -I or --update
+    #[arg(long, value_name = "FILE")]
+    baseline: PathBuf,
+
+    /// Compare only these comma-separated top-level keys (e.g. subagents,warnings), or all but the `-`-prefixed ones (e.g. -thread_source)
+    #[arg(
+        long = "head-fields",
+        value_name = "FIELDS",
+        allow_hyphen_values = true
+    )]
+    head_fields: Option<String>,
+
+    /// Write the current head to the baseline file instead of comparing
+    #[arg(long, conflicts_with_all = ["json", "output"])]
+    update: bool,
+
+    /// Print the comparison as JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Write the comparison to a file instead of stdout
+    #[arg(short = 'o', long = "output", value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    #[command(flatten)]
+    project: ProjectArgs,
+}
+
+#[derive(Debug, Args)]
+struct StatsArgs {
+    /// Thread URI to measure
+    uri: String,
+
+    /// Print the stats as JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Write the stats to a file instead of stdout
+    #[arg(short = 'o', long = "output", value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    #[command(flatten)]
+    project: ProjectArgs,
+}
+
+#[derive(Debug, Args)]
+struct LineageArgs {
+    /// Thread URI whose lineage to show
+    uri: String,
+
+    /// Print the lineage as JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Write the lineage to a file instead of stdout
+    #[arg(short = 'o', long = "output", value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    #[command(flatten)]
+    project: ProjectArgs,
+}
+
+#[derive(Debug, Args)]
+struct AnnotateArgs {
+    /// Thread URI to annotate
+    uri: String,
+
+    /// Timeline message number, as shown in the rendered thread (`## 12. ...`)
+    #[arg(long = "message", value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    message: u64,
+
+    /// Note text
+    #[arg(long = "note", value_name = "TEXT")]
+    note: String,
+
+    #[command(flatten)]
+    project: ProjectArgs,
+}
+
+#[derive(Debug, Args)]
+struct PackArgs {
+    /// Thread URI to pack
+    uri: String,
+
+    /// Approximate token budget for the packed output
+    #[arg(
+        long = "max-tokens",
+        value_name = "N",
+        default_value_t = 8000,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    max_tokens: u64,
+
+    /// Write output to a file instead of stdout
+    #[arg(short = 'o', long = "output", value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    #[command(flatten)]
+    project: ProjectArgs,
+}
+
+#[derive(Debug, Args)]
+struct RecentArgs {
+    /// How many threads to list
+    #[arg(short = 'n', long = "limit", value_name = "N", default_value_t = 10)]
+    limit: usize,
+
+    /// Only list threads whose recorded working directory is this directory or inside it
+    #[arg(long = "project", value_name = "PATH")]
+    project: Option<PathBuf>,
+
+    /// Write the list to a file instead of stdout
+    #[arg(short = 'o', long = "output", value_name = "PATH")]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+struct GcArgs {
+    /// Provider whose local sessions to clean up
+    #[arg(long = "provider", value_name = "PROVIDER")]
+    provider: String,
+
+    /// Sessions with no activity for this long are stale (e.g. 30d, 12h, 2w)
+    #[arg(long = "older-than", value_name = "AGE")]
+    older_than: String,
+
+    /// Move stale sessions into the provider's archive
+    #[arg(long, conflicts_with = "delete")]
+    archive: bool,
+
+    /// Remove stale sessions
+    #[arg(long)]
+    delete: bool,
+
+    /// Report what --archive/--delete would do without changing anything
+    #[arg(long = "dry-run")]
+    dry_run: bool,
+
+    /// Write the report to a file instead of stdout
+    #[arg(short = 'o', long = "output", value_name = "PATH")]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+struct ImportArgs {
+    /// Transcript file (Codex rollout, Claude/Pi JSONL, Amp/Gemini JSON)
+    file: PathBuf,
+
+    /// Provider whose storage layout to import into
+    #[arg(long = "provider", value_name = "PROVIDER")]
+    provider: String,
+
+    /// Session id to assign; must match the id recorded in the transcript, if any
+    #[arg(long = "session-id", value_name = "ID")]
+    session_id: Option<String>,
+
+    /// Claude project, Gemini project hash, or Pi session directory to import into
+    #[arg(
+        long = "project",
+        value_name = "NAME_OR_PATH",
+        allow_hyphen_values = true
+    )]
+    project: Option<String>,
+
+    /// Overwrite an existing local thread with the same session id
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Debug, Args)]
+struct SummarizeArgs {
+    /// Thread URI to summarize
+    uri: String,
+
+    /// Shell command that reads the prompt on stdin and prints the summary (defaults to $XURL_SUMMARIZER_CMD)
+    #[arg(long = "command", value_name = "CMD", conflicts_with = "provider")]
+    command: Option<String>,
+
+    /// Summarize with a provider's write mode instead (defaults to the thread's own provider)
+    #[arg(long = "provider", value_name = "PROVIDER")]
+    provider: Option<String>,
+
+    /// Run this binary for the summarizing provider (overrides XURL_<PROVIDER>_BIN and PATH)
+    #[arg(long = "bin", value_name = "PATH", conflicts_with = "command")]
+    bin: Option<PathBuf>,
+
+    /// Write output to a file instead of stdout
+    #[arg(short = 'o', long = "output", value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    #[command(flatten)]
+    project: ProjectArgs,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    init_logging(cli.verbose);
+    let exit_status =
+        cli.exit_status || cli.follow || matches!(cli.command, Some(Command::Wait(_)));
+    let porcelain = cli.porcelain;
+
+    match run(cli) {
+        Ok(()) => {
+            if porcelain {
+                porcelain_record("status", "ok");
+            }
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            if porcelain {
+                porcelain_record("status", &format!("error {}", user_facing_error(&err)));
+            } else {
+                eprintln!("error: {}", user_facing_error(&err));
+            }
+            ExitCode::from(match &err {
+                // A changed head is a result, not a failure, as for `diff`.
+                XurlError::HeadChanged { .. } => 6,
+                err if exit_status => status_exit_code(err),
+                _ => 1,
+            })
+        }
+    }
+}
+
+/// The provider roots of this run: from the environment and the home
+/// directory, or with `--no-default-roots` (or `XURL_NO_DEFAULT_ROOTS`) from
+/// the environment only.
+fn provider_roots(no_default_roots: bool) -> xurl_core::Result<ProviderRoots> {
+    let no_default_roots = no_default_roots
+        || std::env::var_os("XURL_NO_DEFAULT_ROOTS")
+            .is_some_and(|value| !matches!(value.to_str().map(str::trim), Some("" | "0")));
+    if no_default_roots {
+        Ok(ProviderRoots::from_env_without_home())
+    } else {
+        ProviderRoots::from_env_or_home()
+    }
+}
+
+/// Sends xurl's logs to stderr. `-v` and `-vv` turn on debug and trace logs
+/// of xurl itself; without them, `RUST_LOG` picks what is logged, and nothing
+/// is by default.
+fn init_logging(verbose: u8) {
+    let filter = match verbose {
+        0 => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("off")),
+        1 => EnvFilter::new("xurl_core=debug,xurl=debug"),
+        _ => EnvFilter::new("xurl_core=trace,xurl=trace"),
+    };
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(io::stderr)
+        .without_time()
+        .with_ansi(false)
+        .init();
+}
+
+/// Exit codes of `--exit-status`, `--follow`, and `xurl wait`; any other
+/// failure exits 1.
+fn status_exit_code(err: &XurlError) -> u8 {
+    match err {
+        XurlError::ThreadNotFound { .. } | XurlError::TagNotFound { .. } => 3,
+        XurlError::SubagentStatus { status, .. } if status == STATUS_NOT_FOUND => 3,
+        XurlError::SubagentStatus { status, .. } if status == STATUS_ERRORED => 4,
+        XurlError::SubagentStatus { .. } => 5,
+        XurlError::WaitTimedOut { status, .. } if status == STATUS_NOT_FOUND => 3,
+        XurlError::WaitTimedOut { .. } => 5,
+        _ => 1,
+    }
+}
+
+fn run(cli: Cli) -> xurl_core::Result<()> {
+    let Cli {
+        command,
+        uri,
+        head,
+        head_fields,
+        json,
+        quiet,
+        print_uri,
+        porcelain,
+        warnings,
+        data,
+        context,
+        context_tail,
+        output,
+        bin,
+        debug_write_log,
+        project,
+        handoff_depth,
+        merge_rollouts,
+        all_branches,
+        page,
+        page_size,
+        around,
+        preview_chars,
+        full_previews,
+        only,
+        provenance,
+        dedupe,
+        expand_compactions,
+        compact_placeholder,
+        tool_errors,
+        amp_tools,
+        strict,
+        lenient,
+        allow_network,
+        consistent,
+        fingerprint,
+        since,
+        until,
+        exit_status,
+        follow,
+        format,
+        template,
+        verbose: _,
+        no_default_roots,
+    } = cli;
+    match command {
+        Some(Command::Summarize(args)) => return run_summarize(args, no_default_roots),
+        Some(Command::Import(args)) => return run_import(args, no_default_roots),
+        Some(Command::Gc(args)) => return run_gc(args, no_default_roots),
+        Some(Command::Recent(args)) => return run_recent(args, no_default_roots),
+        Some(Command::Pack(args)) => return run_pack(args, no_default_roots),
+        Some(Command::Annotate(args)) => return run_annotate(args, no_default_roots),
+        Some(Command::Verify(args)) => return run_verify(args, no_default_roots),
+        Some(Command::Doctor(args)) => return run_doctor(args, no_default_roots),
+        Some(Command::HeadDiff(args)) => return run_head_diff(args, no_default_roots),
+        Some(Command::Stats(args)) => return run_stats(args, no_default_roots),
+        Some(Command::Lineage(args)) => return run_lineage(args, no_default_roots),
+        #[cfg(feature = "semantic-search")]
+        Some(Command::Search(args)) => return run_search(args, no_default_roots),
+        Some(Command::Chat(args)) => return run_chat(args, no_default_roots),
+        Some(Command::Wait(args)) => return run_wait(args, no_default_roots),
+        Some(Command::Notify(args)) => return run_notify(args, no_default_roots),
+        Some(Command::Dedupe(args)) => return run_dedupe(args, no_default_roots),
+        Some(Command::Trace(args)) => return run_trace(args, no_default_roots),
+        Some(Command::Export(args)) => return run_export(args, no_default_roots),
+        Some(Command::Fork(args)) => return run_fork(args, no_default_roots),
+        Some(Command::Cp(args)) => return run_cp(args, no_default_roots),
+        Some(Command::PiDiff(args)) => return run_pi_diff(args, no_default_roots),
+        Some(Command::Tag(args)) => return run_tag(args, no_default_roots),
+        Some(Command::ResolveTag(args)) => return run_resolve_tag(args, no_default_roots),
+        Some(Command::Resolve(args)) => return run_resolve(args, no_default_roots),
+        Some(Command::Snapshot(args)) => return run_snapshot(args, no_default_roots),
+        None => {}
+    }
+    let uri = uri.unwrap_or_default();
+    let (uri, uri_page) = TimelinePage::split_uri(&uri)?;
+    let page = timeline_page(page, page_size, uri_page)?;
+    if page.is_some() && (json || !data.is_empty()) {
+        return Err(XurlError::InvalidMode(
+            "paging only applies to reading a thread timeline".to_string(),
+        ));
+    }
+    let window = TimeWindow::parse(since.as_deref(), until.as_deref())?;
+    let head_fields = head_fields.as_deref().map(HeadFields::parse).transpose()?;
+    let mut roots = provider_roots(no_default_roots)?
+        .with_claude_project(project.project)
+        .with_codex_merge_rollouts(merge_rollouts)
+        .with_write_debug_log(debug_write_log);
+    if allow_network {
+        roots = roots.with_allow_network(true);
+    }
+    let mut options = ReadOptions::from_env()
+        .with_subagent_window(window)
+        .with_timeline_page(page)
+        .with_pi_all_branches(all_branches)
+        .with_timeline_context(around)
+        .with_pi_preview_chars(
+            (!full_previews).then(|| usize::try_from(preview_chars).unwrap_or(usize::MAX)),
+        )
+        .with_timeline_kinds(only.iter().copied().map(Into::into).collect())
+        .with_timeline_provenance(provenance)
+        .with_timeline_dedupe(dedupe)
+        .with_consistent_reads(consistent)
+        .with_fingerprint(fingerprint);
+    if let Some(depth) = handoff_depth {
+        options = options.with_amp_handoff_depth(usize::from(depth));
+    }
+    if expand_compactions {
+        options = options.with_expand_compactions(true);
+    }
+    if compact_placeholder.is_some() {
+        options = options.with_compact_placeholder(compact_placeholder);
+    }
+    if tool_errors {
+        options = options.with_tool_errors(true);
+    }
+    if amp_tools {
+        options = options.with_amp_tools(true);
+    }
+    if strict {
+        options = options.with_extraction_mode(ExtractionMode::Strict);
+    } else if lenient {
+        options = options.with_extraction_mode(ExtractionMode::Lenient);
+    }
+    let output = output.as_deref();
+    let warning_report = Report {
+        level: (!quiet).then(|| warnings.into()),
+        porcelain,
+    };
+    if data.is_empty() {
+        let uri = parse_uri(uri, &roots)?;
+        if porcelain {
+            porcelain_record("uri", &uri.as_agents_string());
+        }
+        if uri.fragment.is_some() && page.is_some() {
+            return Err(XurlError::InvalidMode(
+                "a #msg fragment focuses on one timeline entry and cannot be combined with paging"
+                    .to_string(),
+            ));
+        }
+        if all_branches
+            && (uri.provider != ProviderKind::Pi
+                || uri.agent_id.is_some()
+                || uri.fragment.is_some())
+        {
+            return Err(XurlError::InvalidMode(
+                "--all-branches renders a whole Pi session: agents://pi/<session_id>".to_string(),
+            ));
+        }
+        if !only.is_empty() && uri.agent_id.is_some() && uri.provider != ProviderKind::Pi {
+            return Err(XurlError::InvalidMode(
+                "--only filters a thread timeline; subagent views have none".to_string(),
+            ));
+        }
+        if let Some(template) = template {
+            return write_template_output(
+                &uri,
+                &roots,
+                &options,
+                json,
+                &template,
+                output,
+                warning_report,
+            );
+        }
+        if let Some(timeline_format) = timeline_format(format) {
+            if uri.agent_id.is_some() && uri.provider != ProviderKind::Pi {
+                return Err(XurlError::InvalidMode(format!(
+                    "--format {} renders a thread timeline; subagent views have none",
+                    format_name(format)
+                )));
+            }
+            if matches!(timeline_format, TimelineFormat::Table(_)) && uri.fragment.is_some() {
+                return Err(XurlError::InvalidMode(
+                    "--format csv/tsv exports whole timelines; drop the #msg fragment or use --page"
+                        .to_string(),
+                ));
+            }
+            let resolved = resolve_thread(&uri, &roots)?;
+            return write_output(
+                output,
+                &render_thread(&uri, &resolved, &roots, &options, timeline_format)?,
+            );
+        }
+
+        if follow {
+            return run_follow(&uri, &roots, output, porcelain);
+        }
+        let view = if json {
+            ReadView::Json
+        } else if head {
+            ReadView::Head(head_fields.as_ref())
+        } else {
+            ReadView::Thread
+        };
+        let head = read_thread_output(&uri, &roots, &options, view, output, warning_report)?;
+        if exit_status {
+            // JSON views print no head, so theirs is built here.
+            let head = match head {
+                Some(head) => head,
+                None => build_thread_head(&uri, &roots, &options)?,
+            };
+            return check_subagent_status(&head);
+        }
+        return Ok(());
+    }
+
+    if head {
+        return Err(XurlError::InvalidMode(
+            "head mode (-I/--head) cannot be combined with write mode (-d/--data)".to_string(),
+        ));
+    }
+
+    let mut prompt = String::new();
+    for context_uri in &context {
+        let context_uri = parse_uri(context_uri, &roots)?;
+        prompt.push_str(&render_thread_context(
+            &context_uri,
+            &roots,
+            &options,
+            context_tail,
+        )?);
+    }
+    prompt.push_str(&build_prompt(&data)?);
+    let target = parse_write_target(uri, &roots)?;
+    if let Some(bin) = bin {
+        roots = roots.with_binary(target.provider, bin);
+    }
+    let uri_report = if print_uri {
+        UriReport::Stdout
+    } else if porcelain {
+        UriReport::Porcelain
+    } else {
+        UriReport::Stderr
+    };
+    let mut sink = CliWriteSink::new(output, &target, uri_report)?;
+    let started = Instant::now();
+    let result = write_thread(
+        target.provider,
+        &roots,
+        &WriteRequest {
+            prompt,
+            session_id: target.session_id.clone(),
+            parent_entry_id: target.parent_entry_id,
+            spawn_agent: matches!(target.action, WriteAction::Spawn),
+            gemini_project: target.gemini_project.clone(),
+        },
+        &mut sink,
+    );
+    let uri = sink
+        .uri
+        .clone()
+        .unwrap_or_else(|| match &target.session_id {
+            Some(session_id) => format!("agents://{}/{session_id}", target.provider),
+            None => format!("agents://{}", target.provider),
+        });
+    let (status, output) = match &result {
+        Ok(result) => (STATUS_COMPLETED, result.final_text.clone()),
+        Err(err) => (STATUS_ERRORED, Some(err.to_string())),
+    };
+    notify_if_configured(
+        &roots,
+        &notification(
+            NotifyEvent::Write,
+            &uri,
+            status,
+            started.elapsed(),
+            output.as_deref(),
+        ),
+        porcelain,
+    );
+    result?;
+    sink.finish()
+}
+
+/// Posts `notification` to the webhook set with `xurl notify`. The run it
+/// reports is over, so a failed post is only a warning.
+fn notify_if_configured(roots: &ProviderRoots, notification: &Notification, porcelain: bool) {
+    if let Err(err) = notify(roots, notification) {
+        report_warning(&Warning::warn("notify_failed", err.to_string()), porcelain);
+    }
+}
+
+fn run_summarize(args: SummarizeArgs, no_default_roots: bool) -> xurl_core::Result<()> {
+    let SummarizeArgs {
+        uri,
+        command,
+        provider,
+        bin,
+        output,
+        project,
+    } = args;
+    let mut roots = provider_roots(no_default_roots)?.with_claude_project(project.project);
+    let uri = parse_uri(&uri, &roots)?;
+
+    let command = command.or_else(|| {
+        std::env::var("XURL_SUMMARIZER_CMD")
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+    });
+    let summarizer = match (command, provider) {
+        (Some(command), _) => Summarizer::Command(command),
+        (None, Some(provider)) => {
+            let kind =
+                parse_collection_provider(&format!("agents://{provider}")).ok_or_else(|| {
+                    XurlError::InvalidMode(format!("unknown summarizer provider: {provider}"))
+                })?;
+            Summarizer::Provider(kind)
+        }
+        (None, None) => Summarizer::Provider(uri.format()),
+    };
+    if let (Summarizer::Provider(kind), Some(bin)) = (&summarizer, bin) {
+        roots = roots.with_binary(*kind, bin);
+    }
+
+    let summary = summarize_thread(&uri, &roots, &ReadOptions::from_env(), &summarizer)?;
+    write_output(output.as_deref(), &render_summary_markdown(&summary))
+}
+
+fn run_annotate(args: AnnotateArgs, no_default_roots: bool) -> xurl_core::Result<()> {
+    let AnnotateArgs {
+        uri,
+        message,
+        note,
+        project,
+    } = args;
+    let roots = provider_roots(no_default_roots)?.with_claude_project(project.project);
+    let uri = parse_uri(&uri, &roots)?;
+    let message = usize::try_from(message)
+        .map_err(|_| XurlError::InvalidMode(format!("message {message} is out of range")))?;
+
+    let annotation = annotate_thread(
+        &uri,
+        &roots,
+        &ReadOptions::from_env(),
+        &AnnotateRequest { message, note },
+    )?;
+    println!(
+        "annotated message {} of agents://{}/{}",
+        annotation.message, annotation.provider, annotation.session_id
+    );
+    Ok(())
+}
+
+fn run_import(args: ImportArgs, no_default_roots: bool) -> xurl_core::Result<()> {
+    let ImportArgs {
+        file,
+        provider,
+        session_id,
+        project,
+        force,
+    } = args;
+    let provider = parse_collection_provider(&format!("agents://{provider}"))
+        .ok_or_else(|| XurlError::InvalidMode(format!("unknown import provider: {provider}")))?;
+    let roots = provider_roots(no_default_roots)?;
+
+    let result = import_thread(
+        &roots,
+        &ImportRequest {
+            provider,
+            source: file,
+            session_id,
+            project,
+            force,
+        },
+    )?;
+
+    eprintln!("imported: {}", result.path.display());
+    if let Some(db_path) = &result.registered_in {
+        eprintln!("registered: {}", db_path.display());
+    }
+    for warning in &result.warnings {
+        eprintln!("warning: {warning}");
+    }
+    println!("agents://{}/{}", result.provider, result.session_id);
+    Ok(())
+}
+
+fn write_subagent_json(
+    uri: &ThreadUri,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+    output: Option<&Path>,
+    warnings: Report,
+) -> xurl_core::Result<()> {
+    let view = json_subagent_view(uri, roots, options, warnings)?;
+    write_output(output, &render_subagent_view_json(&view)?)
+}
+
+/// The subagent index or detail view `--json` prints, after reporting the
+/// warnings `report` shows on stderr.
+fn json_subagent_view(
+    uri: &ThreadUri,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+    report: Report,
+) -> xurl_core::Result<SubagentView> {
+    if !uri.provider.capabilities().supports_subagents {
+        let supported = ProviderKind::all()
+            .iter()
+            .filter(|kind| kind.capabilities().supports_subagents)
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        return Err(XurlError::InvalidMode(format!(
+            "--json is only supported for subagent index/detail URIs ({}), got provider={}",
+            supported.join(", "),
+            uri.provider
+        )));
+    }
+
+    let view = resolve_subagent_view(uri, roots, options, uri.agent_id.is_none())?;
+    let warnings = match &view {
+        SubagentView::List(list) => &list.warnings,
+        SubagentView::Detail(detail) => &detail.warnings,
+    };
+    for warning in warnings
+        .iter()
+        .filter(|warning| report.level.is_some_and(|level| warning.severity >= level))
+    {
+        report_warning(warning, report.porcelain);
+    }
+    Ok(view)
+}
+
+fn write_template_output(
+    uri: &ThreadUri,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+    json: bool,
+    template: &Path,
+    output: Option<&Path>,
+    warnings: Report,
+) -> xurl_core::Result<()> {
+    let template = OutputTemplate::load(template)?;
+    let subagent_view = if uri.agent_id.is_some() {
+        uri.provider != ProviderKind::Pi
+    } else {
+        json && uri.fragment.is_none()
+    };
+    if subagent_view {
+        let view = json_subagent_view(uri, roots, options, warnings)?;
+        return write_output(output, &render_subagent_view_template(&view, &template)?);
+    }
+    let resolved = resolve_thread(uri, roots)?;
+    write_output(
+        output,
+        &render_thread_template(uri, &resolved, roots, options, &template)?,
+    )
+}
+
+fn run_recent(args: RecentArgs, no_default_roots: bool) -> xurl_core::Result<()> {
+    let roots = provider_roots(no_default_roots)?.with_project_dir(project_dir(args.project)?);
+    let view = list_recent_threads(&roots, &ReadOptions::from_env(), args.limit);
+    write_output(
+        args.output.as_deref(),
+        &render_recent_threads_markdown(&view),
+    )
+}
+
+fn run_wait(args: WaitArgs, no_default_roots: bool) -> xurl_core::Result<()> {
+    let WaitArgs {
+        uri,
+        timeout,
+        interval,
+        project,
+    } = args;
+    let roots = provider_roots(no_default_roots)?.with_claude_project(project.project);
+    let uri = parse_uri(&uri, &roots)?;
+    let req = WaitRequest {
+        timeout: (timeout > 0).then(|| Duration::from_secs(timeout)),
+        interval: Duration::from_secs(interval),
+    };
+
+    let started = Instant::now();
+    let status = wait_for_subagent(&uri, &roots, &req, |status| {
+        // Flush each change so pipes see it while the wait goes on.
+        let mut stdout = io::stdout().lock();
+        let _ = writeln!(stdout, "{} ({})", status.status, status.source);
+        let _ = stdout.flush();
+    })?;
+    // The subagent's last message, when it can still be read.
+    let last_message = match resolve_subagent_view(&uri, &roots, &ReadOptions::from_env(), false) {
+        Ok(SubagentView::Detail(detail)) => {
+            detail.excerpt.last().map(|message| message.text.clone())
+        }
+        _ => None,
+    };
+    notify_if_configured(
+        &roots,
+        &notification(
+            NotifyEvent::Wait,
+            &uri.as_agents_string(),
+            &status.status,
+            started.elapsed(),
+            last_message.as_deref(),
+        ),
+        false,
+    );
+    if status.status == STATUS_ERRORED {
+        return Err(XurlError::SubagentStatus {
+            uri: uri.as_agents_string(),
+            status: status.status,
+        });
+    }
+    Ok(())
+}
+
+fn run_notify(args: NotifyArgs, no_default_roots: bool) -> xurl_core::Result<()> {
+    let NotifyArgs {
+        webhook,
+        events,
+        clear,
+        test,
+    } = args;
+    let roots = provider_roots(no_default_roots)?;
+
+    let path = if clear {
+        Some(set_notify_config(&roots, None)?)
+    } else if webhook.is_some() || !events.is_empty() {
+        let mut config = notify_config(&roots)?;
+        if webhook.is_some() {
+            config.webhook = webhook;
+        }
+        if !events.is_empty() {
+            config.events = events.into_iter().map(Into::into).collect();
+        }
+        Some(set_notify_config(&roots, Some(&config))?)
+    } else {
+        None
+    };
+    if let Some(path) = path {
+        eprintln!("saved: {}", path.display());
+    }
+
+    let config = notify_config(&roots)?;
+    print_notify_config(&config);
+    if test {
+        let webhook = config.webhook.as_deref().ok_or_else(|| {
+            XurlError::InvalidMode(
+                "no webhook to test; set one with `xurl notify --webhook <URL>`".to_string(),
+            )
+        })?;
+        send_notification(
+            webhook,
+            &notification(
+                NotifyEvent::Write,
+                "xurl notify --test",
+                STATUS_COMPLETED,
+                Duration::ZERO,
+                Some("Test notification from `xurl notify --test`."),
+            ),
+        )?;
+        eprintln!("sent: test notification");
+    }
+    Ok(())
+}
+
+fn print_notify_config(config: &NotifyConfig) {
+    println!("webhook: {}", config.webhook.as_deref().unwrap_or("none"));
+    let events = config
+        .events
+        .iter()
+        .map(|event| match event {
+            NotifyEvent::Write => "write",
+            NotifyEvent::Wait => "wait",
+        })
+        .collect::<Vec<_>>();
+    println!("events: {}", events.join(", "));
+}
+
+/// Pause between reads of a followed transcript.
+const FOLLOW_INTERVAL: Duration = Duration::from_secs(1);
+
+/// `--follow`: writes each message of the subagent transcript as it appears.
+fn run_follow(
+    uri: &ThreadUri,
+    roots: &ProviderRoots,
+    output: Option<&Path>,
+    porcelain: bool,
+) -> xurl_core::Result<()> {
+    // Unlike other `-o` output this is not staged: messages written so far
+    // are the point, even if following stops early.
+    let (mut writer, label): (Box<dyn Write>, PathBuf) = match output {
+        Some(path) => (
+            Box::new(fs::File::create(path).map_err(|source| XurlError::Io {
+                path: path.to_path_buf(),
+                source,
+            })?),
+            path.to_path_buf(),
+        ),
+        None => (Box::new(io::stdout()), PathBuf::from("<stdout>")),
+    };
+    let req = WaitRequest {
+        timeout: None,
+        interval: FOLLOW_INTERVAL,
+    };
+
+    let status = follow_subagent(uri, roots, &req, |message| {
+        let title = match message.role {
+            MessageRole::User => "User",
+            MessageRole::Assistant => "Assistant",
+        };
+        write!(writer, "## {title}\n\n{}\n\n", message.text.trim())
+            .and_then(|()| writer.flush())
+            .map_err(|source| XurlError::Io {
+                path: label.clone(),
+                source,
+            })
+    })?;
+
+    if porcelain {
+        porcelain_record("subagent", &format!("{} {}", status.status, status.source));
+    } else {
+        eprintln!("{} ({})", status.status, status.source);
+    }
+    if status.status == STATUS_ERRORED {
+        return Err(XurlError::SubagentStatus {
+            uri: uri.as_agents_string(),
+            status: status.status,
+        });
+    }
+    Ok(())
+}
+
+/// Sends each stdin line to the thread as a new prompt. A line ending in `\`
+/// continues on the next line; `/exit`, `/quit`, or end of input stops.
+fn run_chat(args: ChatArgs, no_default_roots: bool) -> xurl_core::Result<()> {
+    let ChatArgs {
+        uri,
+        bin,
+        debug_write_log,
+    } = args;
+    let mut roots = provider_roots(no_default_roots)?.with_write_debug_log(debug_write_log);
+    let mut target = parse_write_target(&uri, &roots)?;
+    if matches!(target.action, WriteAction::Spawn) {
+        return Err(XurlError::InvalidMode(
+            "chat continues one thread; spawn a subagent with `xurl <thread_uri>/new -d <task>`"
+                .to_string(),
+        ));
+    }
+    if let Some(bin) = bin {
+        roots = roots.with_binary(target.provider, bin);
+    }
+
+    let stdin = io::stdin();
+    let interactive = stdin.is_terminal();
+    let mut lines = stdin.lock().lines();
+    loop {
+        if interactive {
+            eprint!("> ");
+        }
+        let Some(prompt) = read_chat_prompt(&mut lines)? else {
+            break;
+        };
+        let prompt = prompt.trim();
+        if prompt.is_empty() {
+            continue;
+        }
+        if matches!(prompt, "/exit" | "/quit") {
+            break;
+        }
+
+        let mut sink = CliWriteSink::new(None, &target, UriReport::Stderr)?;
+        let result = write_thread(
+            target.provider,
+            &roots,
+            &WriteRequest {
+                prompt: prompt.to_string(),
+                session_id: target.session_id.clone(),
+                parent_entry_id: target.parent_entry_id.clone(),
+                spawn_agent: false,
+                gemini_project: target.gemini_project.clone(),
+            },
+            &mut sink,
+        )?;
+        println!();
+        target.session_id = Some(result.session_id);
+        // Pi writes add an entry; the next prompt continues from it.
+        if result.entry_id.is_some() {
+            target.parent_entry_id = result.entry_id;
+        }
+        target.action = WriteAction::Append;
+    }
+    Ok(())
+}
+
+/// Reads one chat prompt, joining lines that end in `\`. `None` at end of
+/// input.
+fn read_chat_prompt(
+    lines: &mut impl Iterator<Item = io::Result<String>>,
+) -> xurl_core::Result<Option<String>> {
+    let mut prompt = String::new();
+    for line in lines {
+        let line = line.map_err(|source| XurlError::Io {
+            path: PathBuf::from("<stdin>"),
+            source,
+        })?;
+        match line.strip_suffix('\\') {
+            Some(continued) => {
+                prompt.push_str(continued);
+                prompt.push('\n');
+            }
+            None => {
+                prompt.push_str(&line);
+                return Ok(Some(prompt));
+            }
+        }
+    }
+    Ok((!prompt.is_empty()).then_some(prompt))
+}
 
-    /// Output frontmatter only (header mode)
-    #[arg(short = 'I', long)]
-    head: bool,
+fn run_pack(args: PackArgs, no_default_roots: bool) -> xurl_core::Result<()> {
+    let PackArgs {
+        uri,
+        max_tokens,
+        output,
+        project,
+    } = args;
+    let roots = provider_roots(no_default_roots)?.with_claude_project(project.project);
+    let uri = parse_uri(&uri, &roots)?;
+    let max_tokens = usize::try_from(max_tokens).unwrap_or(usize::MAX);
 
-    /// Send write-mode payload data; may be repeated. Prefix with @file or @- for stdin.
-    #[arg(short = 'd', long = "data", value_name = "DATA")]
-    data: Vec<String>,
+    let packed = pack_thread(&uri, &roots, &ReadOptions::from_env(), max_tokens)?;
+    write_output(output.as_deref(), &render_packed_thread_markdown(&packed))
+}
 
-    /// Write output to a file instead of stdout
-    #[arg(short = 'o', long = "output", value_name = "PATH")]
-    output: Option<PathBuf>,
+/// The format `--format` renders the timeline in; `None` for Markdown,
+/// which plain reads print with the head.
+fn timeline_format(format: ReadFormat) -> Option<TimelineFormat> {
+    match format {
+        ReadFormat::Markdown => None,
+        ReadFormat::Json => Some(TimelineFormat::Json),
+        ReadFormat::Html => Some(TimelineFormat::Html),
+        ReadFormat::Text => Some(TimelineFormat::Text),
+        ReadFormat::Csv => Some(TimelineFormat::Table(TableFormat::Csv)),
+        ReadFormat::Tsv => Some(TimelineFormat::Table(TableFormat::Tsv)),
+    }
 }
 
-fn main() -> ExitCode {
-    let cli = Cli::parse();
+fn format_name(format: ReadFormat) -> String {
+    format
+        .to_possible_value()
+        .map_or_else(String::new, |value| value.get_name().to_string())
+}
 
-    match run(cli) {
-        Ok(()) => ExitCode::SUCCESS,
-        Err(err) => {
-            eprintln!("error: {}", user_facing_error(&err));
-            ExitCode::from(1)
+/// What a plain read prints.
+#[derive(Debug, Clone, Copy)]
+enum ReadView<'a> {
+    /// The entry a fragment points at, or the subagent view, as JSON.
+    Json,
+    /// The head alone, with only these fields when given.
+    Head(Option<&'a HeadFields>),
+    /// The head followed by the timeline or subagent view.
+    Thread,
+}
+
+/// Renders the read-mode view of `uri`: an entry, subagent JSON, the head,
+/// or the full markdown. Returns the head it printed, if any.
+fn read_thread_output(
+    uri: &ThreadUri,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+    view: ReadView,
+    output: Option<&Path>,
+    warnings: Report,
+) -> xurl_core::Result<Option<HeadDocument>> {
+    let head_fields = match view {
+        ReadView::Json if uri.fragment.is_some() => {
+            write_output(output, &render_thread_entry_json(uri, roots, options)?)?;
+            return Ok(None);
+        }
+        ReadView::Json => {
+            write_subagent_json(uri, roots, options, output, warnings)?;
+            return Ok(None);
         }
+        ReadView::Head(fields) => fields,
+        ReadView::Thread => None,
+    };
+
+    let mut document = build_thread_head(uri, roots, options)?;
+    let head = render_head(&mut document, head_fields, warnings);
+    if matches!(view, ReadView::Head(_)) {
+        write_output(output, &head)?;
+        return Ok(Some(document));
     }
+
+    let markdown = if uri.provider.capabilities().supports_subagents && uri.agent_id.is_some() {
+        let view = resolve_subagent_view(uri, roots, options, false)?;
+        let body = render_subagent_view_markdown(&view);
+        format!("{head}\n{body}")
+    } else {
+        let resolved = resolve_thread(uri, roots)?;
+        let body = render_thread(uri, &resolved, roots, options, TimelineFormat::Markdown)?;
+        format!("{head}\n{body}")
+    };
+
+    write_output(output, &markdown)?;
+    Ok(Some(document))
 }
 
-fn run(cli: Cli) -> xurl_core::Result<()> {
-    let Cli {
+/// Which warnings of a read are shown, and where.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Report {
+    /// Least severe warning shown; `None` hides them all.
+    level: Option<WarningSeverity>,
+    /// As `warning:` records on stderr, for `--porcelain`, instead of under
+    /// `warnings` in the frontmatter.
+    porcelain: bool,
+}
+
+/// `head` as frontmatter, with only `fields` when given. Warnings `warnings`
+/// does not show are dropped from `head`.
+fn render_head(head: &mut HeadDocument, fields: Option<&HeadFields>, warnings: Report) -> String {
+    head.warnings.retain(|warning| {
+        warnings
+            .level
+            .is_some_and(|level| warning.severity >= level)
+    });
+    if warnings.porcelain {
+        // Same order and deduplication as the frontmatter `warnings` list.
+        let warnings = std::mem::take(&mut head.warnings)
+            .into_iter()
+            .map(|warning| (Reverse(warning.severity), warning.code, warning.message))
+            .collect::<BTreeSet<_>>();
+        for (Reverse(severity), code, message) in warnings {
+            porcelain_record("warning", &format!("{severity} {code} {message}"));
+        }
+    }
+    match fields {
+        Some(fields) => head.to_frontmatter_with(fields),
+        None => head.to_frontmatter(),
+    }
+}
+
+/// `--exit-status`: fails once the output is written if a subagent listed
+/// in `head` errored, is missing, or has not finished.
+fn check_subagent_status(head: &HeadDocument) -> xurl_core::Result<()> {
+    match head.subagent_status() {
+        Some(
+            status @ (STATUS_ERRORED | STATUS_NOT_FOUND | STATUS_PENDING_INIT | STATUS_RUNNING),
+        ) => Err(XurlError::SubagentStatus {
+            uri: head.uri.clone(),
+            status: status.to_string(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn run_verify(args: VerifyArgs, no_default_roots: bool) -> xurl_core::Result<()> {
+    let VerifyArgs {
         uri,
-        head,
-        data,
+        json,
         output,
-    } = cli;
-    let roots = ProviderRoots::from_env_or_home()?;
-    let output = output.as_deref();
-    if data.is_empty() {
-        let uri = ThreadUri::parse(&uri)?;
-        if head {
-            let head = render_thread_head_markdown(&uri, &roots)?;
-            return write_output(output, &head);
-        }
-
-        let markdown = if matches!(
-            uri.provider,
-            xurl_core::ProviderKind::Codex
-                | xurl_core::ProviderKind::Claude
-                | xurl_core::ProviderKind::Gemini
-                | xurl_core::ProviderKind::Amp
-        ) && uri.agent_id.is_some()
-        {
-            let head = render_thread_head_markdown(&uri, &roots)?;
-            let view = resolve_subagent_view(&uri, &roots, false)?;
-            let body = render_subagent_view_markdown(&view);
-            format!("{head}\n{body}")
-        } else {
-            let head = render_thread_head_markdown(&uri, &roots)?;
-            let resolved = resolve_thread(&uri, &roots)?;
-            let body = render_thread_markdown(&uri, &resolved)?;
-            format!("{head}\n{body}")
+        project,
+    } = args;
+    let roots = provider_roots(no_default_roots)?.with_claude_project(project.project);
+    let uri = parse_uri(&uri, &roots)?;
+
+    let report = verify_thread(&uri, &roots)?;
+    let rendered = if json {
+        render_verify_report_json(&report)?
+    } else {
+        render_verify_report_markdown(&report)
+    };
+    write_output(output.as_deref(), &rendered)?;
+
+    if report.is_ok() {
+        return Ok(());
+    }
+    Err(XurlError::VerificationFailed {
+        uri: report.uri,
+        problems: report.problems.len(),
+    })
+}
+
+fn run_doctor(args: DoctorArgs, no_default_roots: bool) -> xurl_core::Result<()> {
+    let DoctorArgs { json, output } = args;
+    let roots = provider_roots(no_default_roots)?;
+
+    let report = check_storage(&roots);
+    let rendered = if json {
+        render_doctor_report_json(&report)?
+    } else {
+        render_doctor_report_markdown(&report)
+    };
+    write_output(output.as_deref(), &rendered)
+}
+
+fn run_head_diff(args: HeadDiffArgs, no_default_roots: bool) -> xurl_core::Result<()> {
+    let HeadDiffArgs {
+        uri,
+        baseline,
+        head_fields,
+        update,
+        json,
+        output,
+        project,
+    } = args;
+    let roots = provider_roots(no_default_roots)?.with_claude_project(project.project);
+    let options = ReadOptions::from_env();
+    let uri = parse_uri(&uri, &roots)?;
+    let head_fields = head_fields.as_deref().map(HeadFields::parse).transpose()?;
+
+    if update {
+        let head = build_thread_head(&uri, &roots, &options)?;
+        let head = match &head_fields {
+            Some(fields) => head.to_frontmatter_with(fields),
+            None => head.to_frontmatter(),
         };
+        return write_output(Some(&baseline), &head);
+    }
+
+    let diff = diff_thread_head(&uri, &roots, &options, &baseline, head_fields.as_ref())?;
+    let rendered = if json {
+        render_head_diff_json(&diff)?
+    } else {
+        render_head_diff_markdown(&diff)
+    };
+    write_output(output.as_deref(), &rendered)?;
 
-        return write_output(output, &markdown);
+    if diff.is_unchanged() {
+        return Ok(());
     }
+    Err(XurlError::HeadChanged {
+        uri: diff.uri,
+        baseline: diff.baseline,
+        changes: diff.changes.len(),
+    })
+}
 
-    if head {
-        return Err(XurlError::InvalidMode(
-            "head mode (-I/--head) cannot be combined with write mode (-d/--data)".to_string(),
-        ));
+fn run_stats(args: StatsArgs, no_default_roots: bool) -> xurl_core::Result<()> {
+    let StatsArgs {
+        uri,
+        json,
+        output,
+        project,
+    } = args;
+    let roots = provider_roots(no_default_roots)?.with_claude_project(project.project);
+    let uri = parse_uri(&uri, &roots)?;
+
+    let stats = thread_stats(&uri, &roots, &ReadOptions::from_env())?;
+    let rendered = if json {
+        render_thread_stats_json(&stats)?
+    } else {
+        render_thread_stats_markdown(&stats)
+    };
+    write_output(output.as_deref(), &rendered)
+}
+
+fn run_lineage(args: LineageArgs, no_default_roots: bool) -> xurl_core::Result<()> {
+    let LineageArgs {
+        uri,
+        json,
+        output,
+        project,
+    } = args;
+    let roots = provider_roots(no_default_roots)?.with_claude_project(project.project);
+    let uri = parse_uri(&uri, &roots)?;
+
+    let lineage = thread_lineage(&uri, &roots)?;
+    let rendered = if json {
+        render_thread_lineage_json(&lineage)?
+    } else {
+        render_thread_lineage_markdown(&lineage)
+    };
+    write_output(output.as_deref(), &rendered)
+}
+
+fn run_trace(args: TraceArgs, no_default_roots: bool) -> xurl_core::Result<()> {
+    let TraceArgs {
+        uri,
+        format,
+        output,
+        project,
+    } = args;
+    let roots = provider_roots(no_default_roots)?.with_claude_project(project.project);
+    let uri = parse_uri(&uri, &roots)?;
+
+    let trace = trace_thread(&uri, &roots, &ReadOptions::from_env())?;
+    for warning in &trace.warnings {
+        eprintln!("warning: {warning}");
     }
+    let rendered = match format {
+        TraceFormat::OtlpJson => render_trace_otlp_json(&trace)?,
+    };
+    write_output(output.as_deref(), &rendered)
+}
 
-    let prompt = build_prompt(&data)?;
-    let target = parse_write_target(&uri)?;
-    let mut sink = CliWriteSink::new(output, target.action)?;
-    let result = write_thread(
+fn run_pi_diff(args: PiDiffArgs, no_default_roots: bool) -> xurl_core::Result<()> {
+    let PiDiffArgs {
+        left,
+        right,
+        output,
+    } = args;
+    let roots = provider_roots(no_default_roots)?;
+    let left = parse_uri(&left, &roots)?;
+    let right = parse_uri(&right, &roots)?;
+
+    let diff = diff_pi_branches(&left, &right, &roots, &ReadOptions::from_env())?;
+    for warning in &diff.warnings {
+        eprintln!("warning: {warning}");
+    }
+    write_output(output.as_deref(), &render_pi_diff_markdown(&diff))
+}
+
+fn run_tag(args: TagArgs, no_default_roots: bool) -> xurl_core::Result<()> {
+    let TagArgs { uri, name, project } = args;
+    let roots = provider_roots(no_default_roots)?.with_claude_project(project.project);
+    let uri = parse_uri(&uri, &roots)?;
+
+    let previous = tag_thread(&name, &uri, &roots)?;
+    println!("tagged: tag://{name} -> {}", uri.as_agents_string());
+    if let Some(previous) = previous {
+        println!("moved from: {previous}");
+    }
+    Ok(())
+}
+
+fn run_resolve_tag(args: ResolveTagArgs, no_default_roots: bool) -> xurl_core::Result<()> {
+    let roots = provider_roots(no_default_roots)?;
+    println!("{}", resolve_tag(&args.name, &roots)?);
+    Ok(())
+}
+
+fn run_resolve(args: ResolveArgs, no_default_roots: bool) -> xurl_core::Result<()> {
+    let ResolveArgs { uri, json, project } = args;
+    let roots = provider_roots(no_default_roots)?.with_claude_project(project.project);
+    let uri = parse_uri(&uri, &roots)?;
+
+    let location = locate_thread(&uri, &roots)?;
+    for warning in &location.warnings {
+        eprintln!("warning: {warning}");
+    }
+    if json {
+        print!("{}", render_thread_location_json(&location)?);
+    } else {
+        println!("{}\t{}", location.uri, location.path.display());
+    }
+    Ok(())
+}
+
+fn run_snapshot(args: SnapshotArgs, no_default_roots: bool) -> xurl_core::Result<()> {
+    let SnapshotArgs { uri, out, project } = args;
+    let roots = provider_roots(no_default_roots)?.with_claude_project(project.project);
+    let uri = parse_uri(&uri, &roots)?;
+
+    let result = snapshot_thread(&uri, &roots, &SnapshotRequest { out_dir: out })?;
+    eprintln!(
+        "snapshot: {} ({} files)",
+        result.dir.display(),
+        result.files.len()
+    );
+    for warning in &result.warnings {
+        eprintln!("warning: {warning}");
+    }
+    println!("{}", result.uri);
+    Ok(())
+}
+
+fn run_export(args: ExportArgs, no_default_roots: bool) -> xurl_core::Result<()> {
+    let ExportArgs {
+        uri,
+        format,
+        cwd,
+        output,
+        project,
+    } = args;
+    let roots = provider_roots(no_default_roots)?.with_claude_project(project.project);
+    let uri = parse_uri(&uri, &roots)?;
+    let installed = output.is_none();
+
+    let result = export_thread(
+        &uri,
+        &roots,
+        &ReadOptions::from_env(),
+        &ExportRequest {
+            format: format.into(),
+            cwd,
+            output,
+        },
+    )?;
+
+    eprintln!("exported: {}", result.path.display());
+    if let Some(db_path) = &result.registered_in {
+        eprintln!("registered: {}", db_path.display());
+    }
+    for warning in &result.warnings {
+        eprintln!("warning: {warning}");
+    }
+    if installed {
+        eprintln!(
+            "resume: run `{} {}` in {}",
+            match result.provider {
+                ProviderKind::Codex => "codex resume",
+                _ => "claude --resume",
+            },
+            result.session_id,
+            result.cwd
+        );
+        println!("agents://{}/{}", result.provider, result.session_id);
+    }
+    Ok(())
+}
+
+fn run_fork(args: ForkArgs, no_default_roots: bool) -> xurl_core::Result<()> {
+    let ForkArgs { uri, data, bin } = args;
+    let mut roots = provider_roots(no_default_roots)?;
+    let uri = parse_uri(&uri, &roots)?;
+
+    let fork = fork_thread(&uri, &roots)?;
+    for warning in &fork.warnings {
+        eprintln!("warning: {warning}");
+    }
+    if data.is_empty() {
+        println!("{}", fork.uri());
+        return Ok(());
+    }
+
+    eprintln!("forked: {}", fork.uri());
+    if let Some(bin) = bin {
+        roots = roots.with_binary(fork.provider, bin);
+    }
+    let target = WriteTarget {
+        provider: fork.provider,
+        session_id: Some(fork.session_id),
+        parent_entry_id: fork.entry_id,
+        gemini_project: None,
+        action: WriteAction::Append,
+    };
+    let mut sink = CliWriteSink::new(None, &target, UriReport::Stderr)?;
+    write_thread(
         target.provider,
         &roots,
         &WriteRequest {
-            prompt,
-            session_id: target.session_id,
+            prompt: build_prompt(&data)?,
+            session_id: target.session_id.clone(),
+            parent_entry_id: target.parent_entry_id.clone(),
+            spawn_agent: false,
+            gemini_project: target.gemini_project.clone(),
         },
         &mut sink,
     )?;
-    sink.finish(&result)?;
+    sink.finish()
+}
+
+fn run_cp(args: CpArgs, no_default_roots: bool) -> xurl_core::Result<()> {
+    let CpArgs {
+        uri,
+        destination,
+        force,
+        project,
+    } = args;
+    let roots = provider_roots(no_default_roots)?.with_claude_project(project.project);
+    let uri = parse_uri(&uri, &roots)?;
+
+    let copy = copy_thread(&uri, &roots, &CopyRequest { destination, force })?;
+    eprintln!(
+        "copied: {} -> {}",
+        copy.source.display(),
+        copy.path.display()
+    );
+    if copy.files > 1 {
+        eprintln!("files: {}", copy.files);
+    }
+    if let Some(db_path) = &copy.registered_in {
+        eprintln!("registered: {}", db_path.display());
+    }
+    for warning in &copy.warnings {
+        eprintln!("warning: {warning}");
+    }
+    println!("agents://{}/{}", copy.provider, copy.session_id);
     Ok(())
 }
 
+fn run_gc(args: GcArgs, no_default_roots: bool) -> xurl_core::Result<()> {
+    let GcArgs {
+        provider,
+        older_than,
+        archive,
+        delete,
+        dry_run,
+        output,
+    } = args;
+    let provider = parse_collection_provider(&format!("agents://{provider}"))
+        .ok_or_else(|| XurlError::InvalidMode(format!("unknown gc provider: {provider}")))?;
+    let mode = if archive {
+        GcMode::Archive
+    } else if delete {
+        GcMode::Delete
+    } else {
+        GcMode::Report
+    };
+    let request = GcRequest::older_than(
+        parse_age(&older_than)?,
+        mode,
+        dry_run || mode == GcMode::Report,
+    );
+    let roots = provider_roots(no_default_roots)?;
+
+    let report = collect_garbage(provider, &roots, &request)?;
+    write_output(output.as_deref(), &render_gc_report_markdown(&report))
+}
+
+fn run_dedupe(args: DedupeArgs, no_default_roots: bool) -> xurl_core::Result<()> {
+    let DedupeArgs {
+        provider,
+        project,
+        output,
+    } = args;
+    let provider = parse_collection_provider(&format!("agents://{provider}"))
+        .ok_or_else(|| XurlError::InvalidMode(format!("unknown dedupe provider: {provider}")))?;
+    let roots = provider_roots(no_default_roots)?.with_project_dir(project_dir(project)?);
+
+    let report = find_duplicate_threads(provider, &roots)?;
+    write_output(output.as_deref(), &render_dedupe_report_markdown(&report))
+}
+
+#[cfg(feature = "semantic-search")]
+fn run_search(args: SearchArgs, no_default_roots: bool) -> xurl_core::Result<()> {
+    let SearchArgs {
+        semantic,
+        provider,
+        limit,
+        embed_cmd,
+        json,
+        output,
+    } = args;
+    let providers = provider
+        .iter()
+        .map(|provider| {
+            parse_collection_provider(&format!("agents://{provider}")).ok_or_else(|| {
+                XurlError::InvalidMode(format!("unknown search provider: {provider}"))
+            })
+        })
+        .collect::<xurl_core::Result<Vec<_>>>()?;
+    let command = embed_cmd
+        .or_else(|| {
+            std::env::var("XURL_EMBEDDING_CMD")
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+        })
+        .unwrap_or_default();
+    let roots = provider_roots(no_default_roots)?;
+
+    let result = semantic_search(
+        &SemanticSearchRequest {
+            query: semantic,
+            providers,
+            limit,
+            command,
+        },
+        &roots,
+        &ReadOptions::from_env(),
+    )?;
+    let rendered = if json {
+        render_semantic_search_json(&result)?
+    } else {
+        render_semantic_search_markdown(&result)
+    };
+    write_output(output.as_deref(), &rendered)
+}
+
+/// `--project` of listing commands as an absolute path, so `.` means the
+/// directory xurl runs in.
+fn project_dir(path: Option<PathBuf>) -> xurl_core::Result<Option<PathBuf>> {
+    path.map(|path| std::path::absolute(&path).map_err(|source| XurlError::Io { path, source }))
+        .transpose()
+}
+
+/// `XURL_FSYNC=1` makes `-o` files durable: they are fsynced, along with
+/// their directory, before xurl exits.
+fn fsync_output() -> bool {
+    std::env::var("XURL_FSYNC").is_ok_and(|value| !matches!(value.trim(), "" | "0"))
+}
+
+/// Replaces the `-o` file only once `content` is complete, see
+/// [`write_atomic`].
 fn write_output(path: Option<&Path>, content: &str) -> xurl_core::Result<()> {
     if let Some(path) = path {
-        std::fs::write(path, content).map_err(|source| XurlError::Io {
-            path: path.to_path_buf(),
-            source,
-        })?;
+        write_atomic(path, content.as_bytes(), fsync_output())?;
     } else {
         print!("{content}");
     }
@@ -119,28 +2208,96 @@ fn write_output(path: Option<&Path>, content: &str) -> xurl_core::Result<()> {
 enum WriteAction {
     Create,
     Append,
+    /// Spawn a subagent under the session (`agents://codex/<session_id>/new`).
+    Spawn,
 }
 
 #[derive(Debug, Clone)]
 struct WriteTarget {
     provider: ProviderKind,
     session_id: Option<String>,
+    /// Pi entry to branch from (`agents://pi/<session_id>/<entry_id>`).
+    parent_entry_id: Option<String>,
+    /// Gemini project hash (`agents://gemini/<hash>/<session_id>`).
+    gemini_project: Option<String>,
     action: WriteAction,
 }
 
-fn parse_write_target(input: &str) -> xurl_core::Result<WriteTarget> {
+/// Combines `--page`/`--page-size` with a `?page=&page_size=` URI query; the
+/// flags win.
+fn timeline_page(
+    page: Option<u64>,
+    page_size: Option<u64>,
+    uri_page: Option<TimelinePage>,
+) -> xurl_core::Result<Option<TimelinePage>> {
+    if page.is_none() && page_size.is_none() {
+        return Ok(uri_page);
+    }
+
+    let to_usize = |value: u64| {
+        usize::try_from(value)
+            .map_err(|_| XurlError::InvalidMode(format!("page value {value} is out of range")))
+    };
+    Ok(Some(TimelinePage {
+        number: match page {
+            Some(page) => to_usize(page)?,
+            None => uri_page.map_or(1, |uri_page| uri_page.number),
+        },
+        size: match page_size {
+            Some(size) => to_usize(size)?,
+            None => uri_page.map_or(TimelinePage::DEFAULT_SIZE, |uri_page| uri_page.size),
+        },
+    }))
+}
+
+/// Parses a thread URI, expanding a session id prefix to the full id and an
+/// `any://` URI to the provider that stores the session.
+fn parse_uri(input: &str, roots: &ProviderRoots) -> xurl_core::Result<ThreadUri> {
+    if let Some(target) = ThreadUri::tag_target(input) {
+        return parse_uri(&expand_tag_uri(target, roots)?, roots);
+    }
+    if let Some(target) = ThreadUri::any_target(input) {
+        return resolve_any_uri(target, roots);
+    }
+    if let Some(target) = ThreadUri::opencode_share_target(input) {
+        return parse_uri(&expand_share_uri(target, roots)?, roots);
+    }
+    resolve_session_prefix(ThreadUri::parse(input)?, roots)
+}
+
+/// Agent segment of a write URI that spawns a subagent under the session.
+const SPAWN_AGENT_SEGMENT: &str = "new";
+
+fn parse_write_target(input: &str, roots: &ProviderRoots) -> xurl_core::Result<WriteTarget> {
     if let Some(provider) = parse_collection_provider(input) {
         return Ok(WriteTarget {
             provider,
             session_id: None,
+            parent_entry_id: None,
+            gemini_project: None,
             action: WriteAction::Create,
         });
     }
 
-    let uri = ThreadUri::parse(input)?;
-    if uri.agent_id.is_some() {
+    let uri = parse_uri(input, roots)?;
+    if uri.fragment.is_some() {
+        return Err(XurlError::InvalidMode(
+            "write mode does not accept #msg fragments; pass the thread URI without one"
+                .to_string(),
+        ));
+    }
+    if uri.agent_id.as_deref() == Some(SPAWN_AGENT_SEGMENT) && uri.provider != ProviderKind::Pi {
+        return Ok(WriteTarget {
+            provider: uri.provider,
+            session_id: Some(uri.session_id),
+            parent_entry_id: None,
+            gemini_project: uri.gemini_project,
+            action: WriteAction::Spawn,
+        });
+    }
+    if uri.agent_id.is_some() && uri.provider != ProviderKind::Pi {
         return Err(XurlError::InvalidMode(
-            "write mode only supports main thread URIs: agents://<provider>/<session_id>"
+            "write mode only supports main thread URIs: agents://<provider>/<session_id> (or agents://pi/<session_id>/<entry_id> to branch, agents://codex/<session_id>/new to spawn a subagent)"
                 .to_string(),
         ));
     }
@@ -148,6 +2305,8 @@ fn parse_write_target(input: &str) -> xurl_core::Result<WriteTarget> {
     Ok(WriteTarget {
         provider: uri.provider,
         session_id: Some(uri.session_id),
+        parent_entry_id: uri.agent_id,
+        gemini_project: uri.gemini_project,
         action: WriteAction::Append,
     })
 }
@@ -158,15 +2317,10 @@ fn parse_collection_provider(input: &str) -> Option<ProviderKind> {
         return None;
     }
 
-    match target {
-        "amp" => Some(ProviderKind::Amp),
-        "codex" => Some(ProviderKind::Codex),
-        "claude" => Some(ProviderKind::Claude),
-        "gemini" => Some(ProviderKind::Gemini),
-        "pi" => Some(ProviderKind::Pi),
-        "opencode" => Some(ProviderKind::Opencode),
-        _ => None,
-    }
+    ProviderKind::all()
+        .iter()
+        .copied()
+        .find(|kind| kind.to_string() == target)
 }
 
 fn build_prompt(data: &[String]) -> xurl_core::Result<String> {
@@ -197,96 +2351,156 @@ fn load_data(raw: &str) -> xurl_core::Result<String> {
     Ok(raw.to_string())
 }
 
-enum WriteDestination {
+/// Where write mode streams the reply. `-o` files are staged and only
+/// replace the target once the write succeeded.
+enum WriteOutput {
+    Stdout(io::Stdout),
+    /// Devices and pipes, which cannot be replaced.
+    InPlace(fs::File),
+    Staged(AtomicFile),
+}
+
+impl WriteOutput {
+    fn open(path: &Path) -> xurl_core::Result<Self> {
+        if fs::metadata(path).is_ok_and(|metadata| !metadata.is_file()) {
+            return fs::OpenOptions::new()
+                .write(true)
+                .open(path)
+                .map(Self::InPlace)
+                .map_err(|source| XurlError::Io {
+                    path: path.to_path_buf(),
+                    source,
+                });
+        }
+        Ok(Self::Staged(
+            AtomicFile::create(path)?.durable(fsync_output()),
+        ))
+    }
+
+    fn commit(self) -> xurl_core::Result<()> {
+        match self {
+            Self::Staged(file) => file.commit(),
+            Self::Stdout(_) | Self::InPlace(_) => Ok(()),
+        }
+    }
+}
+
+impl Write for WriteOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Stdout(stdout) => stdout.write(buf),
+            Self::InPlace(file) => file.write(buf),
+            Self::Staged(file) => file.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Stdout(stdout) => stdout.flush(),
+            Self::InPlace(file) => file.flush(),
+            Self::Staged(file) => file.flush(),
+        }
+    }
+}
+
+/// Where a write reports the URI of its thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UriReport {
+    /// A `created:`/`updated:`/`spawned:` line on stderr, next to the reply.
+    Stderr,
+    /// The bare URI on stdout, for `--print-uri` writes.
     Stdout,
-    File { path: PathBuf, file: fs::File },
+    /// A `uri:` record on stderr, for `--porcelain` writes.
+    Porcelain,
 }
 
 struct CliWriteSink {
-    destination: WriteDestination,
+    /// `None` when `--print-uri` drops the reply.
+    output: Option<StreamSink<WriteOutput>>,
     action: WriteAction,
-    uri_emitted: bool,
-    text_emitted: bool,
+    /// Session a spawned subagent belongs to.
+    spawn_parent: Option<String>,
+    uri_report: UriReport,
+    /// The URI reported for the write, once the session is known.
+    uri: Option<String>,
 }
 
 impl CliWriteSink {
-    fn new(output: Option<&Path>, action: WriteAction) -> xurl_core::Result<Self> {
-        let destination = if let Some(path) = output {
-            let file = fs::File::create(path).map_err(|source| XurlError::Io {
-                path: path.to_path_buf(),
-                source,
-            })?;
-            WriteDestination::File {
-                path: path.to_path_buf(),
-                file,
-            }
-        } else {
-            WriteDestination::Stdout
+    /// With `UriReport::Stdout`, the reply only goes to `output`, and is
+    /// dropped without one.
+    fn new(
+        output: Option<&Path>,
+        target: &WriteTarget,
+        uri_report: UriReport,
+    ) -> xurl_core::Result<Self> {
+        let output = match (output, uri_report) {
+            (Some(path), _) => Some(StreamSink::new(WriteOutput::open(path)?, path)),
+            (None, UriReport::Stderr | UriReport::Porcelain) => Some(StreamSink::new(
+                WriteOutput::Stdout(io::stdout()),
+                "<stdout>",
+            )),
+            (None, UriReport::Stdout) => None,
         };
 
         Ok(Self {
-            destination,
-            action,
-            uri_emitted: false,
-            text_emitted: false,
+            output,
+            action: target.action,
+            spawn_parent: match target.action {
+                WriteAction::Spawn => target.session_id.clone(),
+                WriteAction::Create | WriteAction::Append => None,
+            },
+            uri_report,
+            uri: None,
         })
     }
 
-    fn emit_uri_once(&mut self, provider: ProviderKind, session_id: &str) {
-        if self.uri_emitted {
-            return;
+    /// Moves a staged `-o` file into place once the write succeeded.
+    fn finish(self) -> xurl_core::Result<()> {
+        match self.output {
+            Some(output) => output.into_inner().commit(),
+            None => Ok(()),
         }
-        let verb = match self.action {
-            WriteAction::Create => "created",
-            WriteAction::Append => "updated",
-        };
-        eprintln!("{verb}: agents://{provider}/{session_id}");
-        self.uri_emitted = true;
     }
 
-    fn write_delta(&mut self, text: &str) -> xurl_core::Result<()> {
-        if text.is_empty() {
+    /// `child` is a Pi entry or a spawned subagent.
+    fn emit_uri_once(
+        &mut self,
+        provider: ProviderKind,
+        session_id: &str,
+        child: Option<&str>,
+    ) -> xurl_core::Result<()> {
+        if self.uri.is_some() {
             return Ok(());
         }
-
-        match &mut self.destination {
-            WriteDestination::Stdout => {
-                let mut stdout = io::stdout();
-                stdout
-                    .write_all(text.as_bytes())
-                    .map_err(|source| XurlError::Io {
-                        path: PathBuf::from("<stdout>"),
-                        source,
-                    })?;
-                stdout.flush().map_err(|source| XurlError::Io {
-                    path: PathBuf::from("<stdout>"),
-                    source,
-                })?;
+        let uri = match child {
+            Some(child) => format!("agents://{provider}/{session_id}/{child}"),
+            None => format!("agents://{provider}/{session_id}"),
+        };
+        self.uri = Some(uri.clone());
+        match self.uri_report {
+            UriReport::Stderr => {
+                let verb = match self.action {
+                    WriteAction::Create => "created",
+                    WriteAction::Append => "updated",
+                    WriteAction::Spawn => "spawned",
+                };
+                eprintln!("{verb}: {uri}");
+                Ok(())
             }
-            WriteDestination::File { path, file } => {
-                file.write_all(text.as_bytes())
+            UriReport::Porcelain => {
+                porcelain_record("uri", &uri);
+                Ok(())
+            }
+            UriReport::Stdout => {
+                let mut stdout = io::stdout().lock();
+                writeln!(stdout, "{uri}")
+                    .and_then(|()| stdout.flush())
                     .map_err(|source| XurlError::Io {
-                        path: path.clone(),
+                        path: PathBuf::from("<stdout>"),
                         source,
-                    })?;
-                file.flush().map_err(|source| XurlError::Io {
-                    path: path.clone(),
-                    source,
-                })?;
+                    })
             }
         }
-        self.text_emitted = true;
-        Ok(())
-    }
-
-    fn finish(&mut self, result: &WriteResult) -> xurl_core::Result<()> {
-        self.emit_uri_once(result.provider, &result.session_id);
-        if !self.text_emitted
-            && let Some(text) = result.final_text.as_deref()
-        {
-            self.write_delta(text)?;
-        }
-        Ok(())
     }
 }
 
@@ -296,22 +2510,68 @@ impl WriteEventSink for CliWriteSink {
         provider: ProviderKind,
         session_id: &str,
     ) -> xurl_core::Result<()> {
-        self.emit_uri_once(provider, session_id);
-        Ok(())
+        // A spawn reports the subagent's own thread.
+        match self.spawn_parent.clone() {
+            Some(parent) => self.emit_uri_once(provider, &parent, Some(session_id)),
+            None => self.emit_uri_once(provider, session_id, None),
+        }
     }
 
     fn on_text_delta(&mut self, text: &str) -> xurl_core::Result<()> {
-        self.write_delta(text)
+        match &mut self.output {
+            Some(output) => output.on_text_delta(text),
+            None => Ok(()),
+        }
+    }
+
+    fn on_write_finished(&mut self, result: &WriteResult) -> xurl_core::Result<()> {
+        self.emit_uri_once(
+            result.provider,
+            &result.session_id,
+            result.entry_id.as_deref().or(result.agent_id.as_deref()),
+        )?;
+        match &mut self.output {
+            Some(output) => output.on_write_finished(result),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Prints `warning` on stderr, as a `warning:` record under `--porcelain`.
+fn report_warning(warning: &Warning, porcelain: bool) {
+    if porcelain {
+        porcelain_record(
+            "warning",
+            &format!("{} {} {}", warning.severity, warning.code, warning.message),
+        );
+    } else {
+        eprintln!("warning: {warning}");
+    }
+}
+
+/// Prints one `--porcelain` record, `<kind>: <value>`, on stderr. Values
+/// stay on one line: backslashes, newlines, and carriage returns are
+/// escaped as `\\`, `\n`, and `\r`.
+fn porcelain_record(kind: &str, value: &str) {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            ch => escaped.push(ch),
+        }
     }
+    eprintln!("{kind}: {escaped}");
 }
 
 fn user_facing_error(err: &XurlError) -> String {
     match err {
-        XurlError::CommandNotFound { command } if command.contains("codex") => format!(
-            "{err}\nhint: write mode needs Codex CLI; run `codex --version`, install Codex CLI if missing, then run `codex login`."
+        XurlError::CommandNotFound { command, .. } if command.contains("codex") => format!(
+            "{err}\nhint: write mode needs Codex CLI; run `codex --version`, install Codex CLI if missing, then run `codex login`. To use a binary outside PATH, pass --bin or set XURL_CODEX_BIN."
         ),
-        XurlError::CommandNotFound { command } if command.contains("claude") => format!(
-            "{err}\nhint: write mode needs Claude CLI; run `claude --version`, install Claude Code if missing, then authenticate."
+        XurlError::CommandNotFound { command, .. } if command.contains("claude") => format!(
+            "{err}\nhint: write mode needs Claude CLI; run `claude --version`, install Claude Code if missing, then authenticate. To use a binary outside PATH, pass --bin or set XURL_CLAUDE_BIN."
         ),
         XurlError::CommandFailed { command, .. } if command.contains("codex") => {
             format!("{err}\nhint: verify authentication with `codex login` and retry.")
@@ -319,6 +2579,10 @@ fn user_facing_error(err: &XurlError) -> String {
         XurlError::CommandFailed { command, .. } if command.contains("claude") => format!(
             "{err}\nhint: verify authentication with `claude auth` (or your configured login flow) and retry."
         ),
+        XurlError::PermissionDenied { hint, .. } => format!("{err}\nhint: {hint}"),
+        XurlError::NoStorageRoot { .. } => format!(
+            "{err}\nhint: default roots are off; set the provider's root variable, such as CODEX_HOME or CLAUDE_CONFIG_DIR"
+        ),
         _ => err.to_string(),
     }
 }
@@ -13,6 +13,7 @@ const REAL_FIXTURE_MAIN_ID: &str = "55fe4488-c6bd-46fa-9390-dab3b8860b95";
 const REAL_FIXTURE_AGENT_ID: &str = "29bf19c3-b83e-401d-8f38-5660b7f67152";
 const AMP_SESSION_ID: &str = "T-019c0797-c402-7389-bd80-d785c98df295";
 const AMP_SUBAGENT_ID: &str = "T-1abc0797-c402-7389-bd80-d785c98df295";
+const AMP_GRANDCHILD_ID: &str = "T-2abc0797-c402-7389-bd80-d785c98df295";
 const GEMINI_SESSION_ID: &str = "29d207db-ca7e-40ba-87f7-e14c9de60613";
 const GEMINI_CHILD_SESSION_ID: &str = "2b112c8a-d80a-4cff-9c8a-6f3e6fbaf7fb";
 const GEMINI_MISSING_CHILD_SESSION_ID: &str = "62f9f98d-c578-4d3a-b4bf-3aaed19889d6";
@@ -93,6 +94,26 @@ fn setup_amp_subagent_tree_with_role(main_role: Option<&str>) -> tempfile::TempD
     temp
 }
 
+fn setup_amp_handoff_chain_tree() -> tempfile::TempDir {
+    let temp = setup_amp_subagent_tree();
+    let threads = temp.path().join("amp/threads");
+    fs::write(
+        threads.join(format!("{AMP_SUBAGENT_ID}.json")),
+        format!(
+            r#"{{"id":"{AMP_SUBAGENT_ID}","status":"completed","messages":[{{"role":"user","content":[{{"type":"text","text":"hello child"}}]}}],"relationships":[{{"type":"handoff","threadID":"{AMP_SESSION_ID}","role":"child"}},{{"type":"handoff","threadID":"{AMP_GRANDCHILD_ID}","role":"parent"}}]}}"#
+        ),
+    )
+    .expect("write child");
+    fs::write(
+        threads.join(format!("{AMP_GRANDCHILD_ID}.json")),
+        format!(
+            r#"{{"id":"{AMP_GRANDCHILD_ID}","status":"running","messages":[{{"role":"user","content":[{{"type":"text","text":"hello grandchild"}}]}}],"relationships":[{{"type":"handoff","threadID":"{AMP_SUBAGENT_ID}","role":"child"}},{{"type":"handoff","threadID":"{AMP_SESSION_ID}","role":"parent"}}]}}"#
+        ),
+    )
+    .expect("write grandchild");
+    temp
+}
+
 fn setup_amp_subagent_tree() -> tempfile::TempDir {
     setup_amp_subagent_tree_with_role(Some("parent"))
 }
@@ -110,7 +131,7 @@ fn setup_codex_subagent_tree() -> tempfile::TempDir {
     fs::write(
         &main_thread_path,
         format!(
-            "{{\"timestamp\":\"2026-02-23T00:00:00Z\",\"type\":\"response_item\",\"payload\":{{\"type\":\"function_call\",\"name\":\"spawn_agent\",\"arguments\":\"{{}}\",\"call_id\":\"call_spawn\"}}}}\n{{\"timestamp\":\"2026-02-23T00:00:01Z\",\"type\":\"response_item\",\"payload\":{{\"type\":\"function_call_output\",\"call_id\":\"call_spawn\",\"output\":\"{{\\\"agent_id\\\":\\\"{SUBAGENT_ID}\\\"}}\"}}}}\n{{\"timestamp\":\"2026-02-23T00:00:02Z\",\"type\":\"response_item\",\"payload\":{{\"type\":\"function_call\",\"name\":\"wait\",\"arguments\":\"{{\\\"ids\\\":[\\\"{SUBAGENT_ID}\\\"],\\\"timeout_ms\\\":120000}}\",\"call_id\":\"call_wait\"}}}}\n{{\"timestamp\":\"2026-02-23T00:00:03Z\",\"type\":\"response_item\",\"payload\":{{\"type\":\"function_call_output\",\"call_id\":\"call_wait\",\"output\":\"{{\\\"status\\\":{{\\\"running\\\":\\\"in progress\\\"}},\\\"timed_out\\\":false}}\"}}}}\n{{\"timestamp\":\"2026-02-23T00:00:04Z\",\"type\":\"response_item\",\"payload\":{{\"type\":\"function_call\",\"name\":\"close_agent\",\"arguments\":\"{{\\\"id\\\":\\\"{SUBAGENT_ID}\\\"}}\",\"call_id\":\"call_close\"}}}}\n{{\"timestamp\":\"2026-02-23T00:00:05Z\",\"type\":\"response_item\",\"payload\":{{\"type\":\"function_call_output\",\"call_id\":\"call_close\",\"output\":\"{{\\\"status\\\":{{\\\"completed\\\":\\\"done\\\"}}}}\"}}}}\n"
+            "{{\"timestamp\":\"2026-02-23T00:00:00Z\",\"type\":\"response_item\",\"payload\":{{\"type\":\"function_call\",\"name\":\"spawn_agent\",\"arguments\":\"{{\\\"message\\\":\\\"review the parser\\\"}}\",\"call_id\":\"call_spawn\"}}}}\n{{\"timestamp\":\"2026-02-23T00:00:01Z\",\"type\":\"response_item\",\"payload\":{{\"type\":\"function_call_output\",\"call_id\":\"call_spawn\",\"output\":\"{{\\\"agent_id\\\":\\\"{SUBAGENT_ID}\\\"}}\"}}}}\n{{\"timestamp\":\"2026-02-23T00:00:02Z\",\"type\":\"response_item\",\"payload\":{{\"type\":\"function_call\",\"name\":\"wait\",\"arguments\":\"{{\\\"ids\\\":[\\\"{SUBAGENT_ID}\\\"],\\\"timeout_ms\\\":120000}}\",\"call_id\":\"call_wait\"}}}}\n{{\"timestamp\":\"2026-02-23T00:00:03Z\",\"type\":\"response_item\",\"payload\":{{\"type\":\"function_call_output\",\"call_id\":\"call_wait\",\"output\":\"{{\\\"status\\\":{{\\\"running\\\":\\\"in progress\\\"}},\\\"timed_out\\\":false}}\"}}}}\n{{\"timestamp\":\"2026-02-23T00:00:04Z\",\"type\":\"response_item\",\"payload\":{{\"type\":\"function_call\",\"name\":\"close_agent\",\"arguments\":\"{{\\\"id\\\":\\\"{SUBAGENT_ID}\\\"}}\",\"call_id\":\"call_close\"}}}}\n{{\"timestamp\":\"2026-02-23T00:00:05Z\",\"type\":\"response_item\",\"payload\":{{\"type\":\"function_call_output\",\"call_id\":\"call_close\",\"output\":\"{{\\\"status\\\":{{\\\"completed\\\":\\\"done\\\"}}}}\"}}}}\n"
         ),
     )
     .expect("write main");
@@ -451,292 +472,1825 @@ fn default_outputs_markdown() {
 }
 
 #[test]
-fn output_flag_writes_markdown_to_file() {
+fn annotations_render_as_callouts_on_later_reads() {
     let temp = setup_codex_tree();
-    let output_dir = tempdir().expect("tempdir");
-    let output_path = output_dir.path().join("thread.md");
+    let data = tempdir().expect("tempdir");
 
     let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
     cmd.env("CODEX_HOME", temp.path())
-        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .env("XURL_DATA_DIR", data.path())
+        .arg("annotate")
         .arg(codex_uri())
-        .arg("-o")
-        .arg(&output_path)
+        .arg("--message")
+        .arg("1")
+        .arg("--note")
+        .arg("this is where it went wrong\nsee the retry below")
         .assert()
         .success()
-        .stdout(predicate::str::is_empty());
-
-    let written = fs::read_to_string(output_path).expect("read output");
-    assert!(written.contains("---\n"));
-    assert!(written.contains("# Thread"));
-    assert!(written.contains("hello"));
-}
+        .stdout(predicate::str::contains(format!(
+            "annotated message 1 of agents://codex/{SESSION_ID}"
+        )));
 
-#[test]
-fn output_flag_returns_error_when_parent_directory_missing() {
-    let temp = setup_codex_tree();
-    let missing_parent = temp.path().join("missing-parent");
-    let output_path = missing_parent.join("thread.md");
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("XURL_DATA_DIR", data.path())
+        .arg(codex_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "hello\n\n> [!NOTE]\n> this is where it went wrong\n> see the retry below\n\n<a id=\"msg-2\"",
+        ));
 
     let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
     cmd.env("CODEX_HOME", temp.path())
-        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .env("XURL_DATA_DIR", data.path())
+        .arg("annotate")
         .arg(codex_uri())
-        .arg("--output")
-        .arg(&output_path)
+        .arg("--message")
+        .arg("99")
+        .arg("--note")
+        .arg("nope")
         .assert()
         .failure()
-        .stderr(predicate::str::contains("error: i/o error on"));
+        .stderr(predicate::str::contains("message 99 is out of range"));
 }
 
 #[test]
-fn agents_uri_outputs_markdown() {
+fn tags_name_threads_and_resolve_as_tag_uris() {
     let temp = setup_codex_tree();
+    let data = tempdir().expect("tempdir");
 
     let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
     cmd.env("CODEX_HOME", temp.path())
-        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
-        .arg(agents_codex_uri())
+        .env("XURL_DATA_DIR", data.path())
+        .arg("tag")
+        .arg(codex_uri())
+        .arg("refactor-auth")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "tagged: tag://refactor-auth -> agents://codex/{SESSION_ID}"
+        )));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("XURL_DATA_DIR", data.path())
+        .arg("resolve-tag")
+        .arg("refactor-auth")
+        .assert()
+        .success()
+        .stdout(format!("agents://codex/{SESSION_ID}\n"));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("XURL_DATA_DIR", data.path())
+        .arg("tag://refactor-auth")
         .assert()
         .success()
         .stdout(predicate::str::contains(format!(
             "uri: 'agents://codex/{SESSION_ID}'"
         )))
-        .stdout(predicate::str::contains("## 1. User"))
         .stdout(predicate::str::contains("hello"));
-}
-
-#[test]
-fn raw_flag_is_rejected() {
-    let temp = setup_codex_tree();
 
     let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
     cmd.env("CODEX_HOME", temp.path())
-        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
-        .arg(codex_uri())
-        .arg("--raw")
+        .env("XURL_DATA_DIR", data.path())
+        .arg("tag://unknown")
         .assert()
         .failure()
-        .stderr(predicate::str::contains("unexpected argument '--raw'"));
+        .stderr(predicate::str::contains("tag not found: unknown"));
 }
 
 #[test]
-fn head_flag_outputs_frontmatter_only() {
+fn page_flags_render_one_slice_with_neighbor_uris() {
     let temp = setup_codex_tree();
 
     let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
     cmd.env("CODEX_HOME", temp.path())
         .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
         .arg(codex_uri())
-        .arg("-I")
+        .arg("--page")
+        .arg("2")
+        .arg("--page-size")
+        .arg("1")
         .assert()
         .success()
-        .stdout(predicate::str::contains("---\n"))
-        .stdout(predicate::str::contains("mode: 'subagent_index'"))
-        .stdout(predicate::str::contains("subagents:"))
-        .stdout(predicate::str::contains("# Thread").not());
-}
-
-#[test]
-fn codex_subagent_head_outputs_header_only() {
-    let temp = setup_codex_subagent_tree();
+        .stdout(predicate::str::contains(format!(
+            "page:\n  number: 2\n  size: 1\n  total_pages: 2\n  total_entries: 2\n  prev: 'agents://codex/{SESSION_ID}?page=1&page_size=1'\n---"
+        )))
+        .stdout(predicate::str::contains("## 2. Assistant"))
+        .stdout(predicate::str::contains("## 1. User").not());
 
     let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
     cmd.env("CODEX_HOME", temp.path())
         .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
-        .arg(codex_subagent_uri())
-        .arg("--head")
+        .arg(format!("agents://codex/{SESSION_ID}?page=1&page_size=1"))
         .assert()
         .success()
-        .stdout(predicate::str::contains("mode: 'subagent_detail'"))
         .stdout(predicate::str::contains(format!(
-            "agent_id: '{SUBAGENT_ID}'"
+            "  next: 'agents://codex/{SESSION_ID}?page=2&page_size=1'"
         )))
-        .stdout(predicate::str::contains("status:"))
-        .stdout(predicate::str::contains("# Subagent Thread").not());
-}
-
-#[test]
-fn codex_deeplink_outputs_markdown() {
-    let temp = setup_codex_tree();
+        .stdout(predicate::str::contains("## 1. User"))
+        .stdout(predicate::str::contains("## 2. Assistant").not());
 
     let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
     cmd.env("CODEX_HOME", temp.path())
-        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
-        .arg(codex_deeplink_uri())
+        .arg(codex_uri())
+        .arg("--page")
+        .arg("3")
+        .arg("--page-size")
+        .arg("1")
         .assert()
-        .success()
-        .stdout(predicate::str::contains("# Thread"))
-        .stdout(predicate::str::contains("## 1. User"))
-        .stdout(predicate::str::contains("hello"));
+        .failure()
+        .stderr(predicate::str::contains("page 3 is past the last page (2)"));
 }
 
 #[test]
-fn agents_codex_deeplink_outputs_markdown() {
+fn only_flag_filters_timeline_entries_by_kind() {
     let temp = setup_codex_tree();
 
     let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
     cmd.env("CODEX_HOME", temp.path())
         .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
-        .arg(agents_codex_deeplink_uri())
+        .arg(agents_codex_uri())
+        .args(["--only", "assistant"])
         .assert()
         .success()
-        .stdout(predicate::str::contains("# Thread"))
-        .stdout(predicate::str::contains("## 1. User"))
-        .stdout(predicate::str::contains("hello"));
-}
-
-#[test]
-fn codex_subagent_outputs_markdown_view() {
-    let temp = setup_codex_subagent_tree();
-    let main_uri = agents_uri("codex", SESSION_ID);
-    let subagent_uri = agents_child_uri("codex", SESSION_ID, SUBAGENT_ID);
+        .stdout(predicate::str::contains("## 2. Assistant\n\nworld"))
+        .stdout(predicate::str::contains("## 1. User").not());
 
     let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
     cmd.env("CODEX_HOME", temp.path())
         .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
-        .arg(codex_subagent_uri())
+        .arg(agents_codex_uri())
+        .args(["--only", "user,assistant"])
         .assert()
         .success()
-        .stdout(predicate::str::contains("# Subagent Thread"))
-        .stdout(predicate::str::contains(format!(
-            "- Main Thread: `{main_uri}`"
-        )))
-        .stdout(predicate::str::contains(format!(
-            "- Subagent Thread: `{subagent_uri}`"
-        )))
-        .stdout(predicate::str::contains("## Lifecycle (Parent Thread)"))
-        .stdout(predicate::str::contains("## Thread Excerpt (Child Thread)"));
-}
-
-#[test]
-fn agents_codex_subagent_outputs_markdown_view() {
-    let temp = setup_codex_subagent_tree();
-    let main_uri = agents_uri("codex", SESSION_ID);
-    let subagent_uri = agents_child_uri("codex", SESSION_ID, SUBAGENT_ID);
+        .stdout(predicate::str::contains("## 1. User"))
+        .stdout(predicate::str::contains("## 2. Assistant"));
 
     let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
     cmd.env("CODEX_HOME", temp.path())
-        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
-        .arg(agents_codex_subagent_uri())
+        .arg(agents_codex_uri())
+        .args(["--only", "tool"])
         .assert()
-        .success()
-        .stdout(predicate::str::contains(format!(
-            "- Main Thread: `{main_uri}`"
-        )))
-        .stdout(predicate::str::contains(format!(
-            "- Subagent Thread: `{subagent_uri}`"
-        )));
-}
-
-#[test]
-fn codex_outputs_no_warning_text_for_markdown() {
-    let temp = setup_codex_tree_with_sqlite_missing_threads();
+        .failure()
+        .stderr(predicate::str::contains("invalid value 'tool'"));
 
     let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
     cmd.env("CODEX_HOME", temp.path())
-        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
-        .arg(codex_uri())
+        .arg(format!(
+            "{}/019c87fb-38b9-7843-92b1-832f02598495",
+            agents_codex_uri()
+        ))
+        .args(["--only", "user"])
         .assert()
-        .success()
-        .stderr(predicate::str::contains("warning:").not());
+        .failure()
+        .stderr(predicate::str::contains("--only filters a thread timeline"));
 }
 
 #[test]
-fn codex_subagent_outputs_no_warning_text_for_markdown() {
-    let temp = setup_codex_subagent_tree_with_sqlite_missing_threads();
-
-    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
-    cmd.env("CODEX_HOME", temp.path())
-        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
-        .arg(codex_subagent_uri())
-        .assert()
+fn lenient_flag_skips_unparsable_lines_and_strict_fails() {
+    let temp = setup_codex_tree();
+    let thread_path = temp.path().join(format!(
+        "sessions/2026/02/23/rollout-2026-02-23T04-48-50-{SESSION_ID}.jsonl"
+    ));
+    let raw = fs::read_to_string(&thread_path).expect("read");
+    let (first, rest) = raw.split_once('\n').expect("two lines");
+    fs::write(&thread_path, format!("{first}\n{{\"type\":\"resp\n{rest}")).expect("write");
+
+    let read = |args: &[&str], extraction: Option<&str>| {
+        let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+        cmd.env("CODEX_HOME", temp.path())
+            .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+            .env_remove("XURL_EXTRACTION");
+        if let Some(extraction) = extraction {
+            cmd.env("XURL_EXTRACTION", extraction);
+        }
+        cmd.arg(agents_codex_uri()).args(args).assert()
+    };
+
+    read(&[], None)
+        .failure()
+        .stderr(predicate::str::contains("at line 2"));
+    read(&["--lenient"], None)
         .success()
-        .stderr(predicate::str::contains("warning:").not());
+        .stdout(predicate::str::contains("code: 'skipped_invalid_record'"))
+        .stdout(predicate::str::contains("## 2. Assistant\n\nworld"));
+    read(&[], Some("lenient"))
+        .success()
+        .stdout(predicate::str::contains("world"));
+    read(&["--strict"], Some("lenient")).failure();
 }
 
 #[test]
-fn codex_real_fixture_head_includes_subagents() {
-    let fixture_root = codex_real_fixture_root();
-    assert!(fixture_root.exists(), "fixture root must exist");
-    let subagent_uri = agents_child_uri("codex", REAL_FIXTURE_MAIN_ID, REAL_FIXTURE_AGENT_ID);
+fn reads_leave_out_a_final_line_still_being_written() {
+    let temp = setup_codex_tree();
+    let thread_path = temp.path().join(format!(
+        "sessions/2026/02/23/rollout-2026-02-23T04-48-50-{SESSION_ID}.jsonl"
+    ));
+    let mut raw = fs::read_to_string(&thread_path).expect("read");
+    raw.push_str("{\"type\":\"response_item\",\"pay");
+    fs::write(&thread_path, raw).expect("write");
+
+    for args in [&["--strict"][..], &["--consistent"]] {
+        let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+        cmd.env("CODEX_HOME", temp.path())
+            .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+            .arg(agents_codex_uri())
+            .args(args)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("code: 'pending_final_line'"))
+            .stdout(predicate::str::contains("## 2. Assistant\n\nworld"));
+    }
+}
+
+#[test]
+fn copilot_sessions_read_from_session_state() {
+    let temp = tempdir().expect("tempdir");
+    let session_id = "0f6b3c1e-8a2d-4e5f-9b7c-1d2e3f4a5b6c";
+    let sessions = temp.path().join("copilot/session-state");
+    fs::create_dir_all(&sessions).expect("mkdir");
+    fs::write(
+        sessions.join(format!("{session_id}.jsonl")),
+        concat!(
+            r#"{"type":"session.start","data":{"sessionId":"0f6b3c1e-8a2d-4e5f-9b7c-1d2e3f4a5b6c"},"id":"e1","timestamp":"2026-03-02T09:00:00.000Z"}"#,
+            "\n",
+            r#"{"type":"user.message","data":{"content":"hello"},"id":"e2","timestamp":"2026-03-02T09:00:01.000Z"}"#,
+            "\n",
+            r#"{"type":"assistant.message","data":{"content":"world"},"id":"e3","timestamp":"2026-03-02T09:00:02.000Z"}"#,
+            "\n",
+        ),
+    )
+    .expect("write");
 
     let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
-    cmd.env("CODEX_HOME", fixture_root)
-        .env("CLAUDE_CONFIG_DIR", "/tmp/missing-claude")
-        .arg(format!("codex://{REAL_FIXTURE_MAIN_ID}"))
-        .arg("--head")
+    cmd.env("XDG_STATE_HOME", temp.path())
+        .arg("agents://copilot/0f6b3c1e")
         .assert()
         .success()
-        .stdout(predicate::str::contains("mode: 'subagent_index'"))
-        .stdout(predicate::str::contains("subagents:"))
-        .stdout(predicate::str::contains(subagent_uri))
-        .stdout(predicate::str::contains("# Subagent Status").not());
+        .stdout(predicate::str::contains("## 1. User\n\nhello"))
+        .stdout(predicate::str::contains("## 2. Assistant\n\nworld"));
 }
 
 #[test]
-fn codex_real_fixture_subagent_detail_outputs_markdown() {
-    let fixture_root = codex_real_fixture_root();
-    assert!(fixture_root.exists(), "fixture root must exist");
+fn opencode_share_links_read_the_local_cache() {
+    let temp = tempdir().expect("tempdir");
+    let cache = temp.path().join("cache");
+    let read = || {
+        let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+        cmd.env("XURL_CACHE_DIR", &cache)
+            .env_remove("XURL_ALLOW_NETWORK")
+            .arg("https://opencode.ai/s/aB3dE5")
+            .assert()
+    };
+
+    read()
+        .failure()
+        .stderr(predicate::str::contains("--allow-network"));
 
-    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
-    cmd.env("CODEX_HOME", fixture_root)
-        .env("CLAUDE_CONFIG_DIR", "/tmp/missing-claude")
-        .arg(format!(
-            "codex://{REAL_FIXTURE_MAIN_ID}/{REAL_FIXTURE_AGENT_ID}"
-        ))
-        .assert()
+    let cached = cache.join("opencode-share/aB3dE5.jsonl");
+    fs::create_dir_all(cached.parent().expect("parent")).expect("mkdir");
+    fs::write(
+        &cached,
+        concat!(
+            r#"{"type":"session","sessionId":"ses_share"}"#,
+            "\n",
+            r#"{"type":"message","id":"msg_1","sessionId":"ses_share","message":{"role":"user"},"parts":[{"type":"text","text":"hello"}]}"#,
+            "\n",
+            r#"{"type":"message","id":"msg_2","sessionId":"ses_share","message":{"role":"assistant"},"parts":[{"type":"text","text":"world"}]}"#,
+            "\n",
+        ),
+    )
+    .expect("write");
+
+    read()
         .success()
-        .stdout(predicate::str::contains("# Subagent Thread"))
-        .stdout(predicate::str::contains("## Lifecycle (Parent Thread)"));
+        .stdout(predicate::str::contains("hello"))
+        .stdout(predicate::str::contains("world"));
 }
 
 #[test]
-fn list_flag_is_rejected() {
-    let temp = setup_codex_subagent_tree();
+fn provenance_flag_shows_source_lines() {
+    let temp = setup_codex_tree();
 
     let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
     cmd.env("CODEX_HOME", temp.path())
         .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
-        .arg(codex_subagent_uri())
-        .arg("--list")
+        .arg(agents_codex_uri())
+        .arg("--provenance")
         .assert()
-        .failure()
-        .stderr(predicate::str::contains("unexpected argument '--list'"));
-}
-
-#[test]
-fn missing_thread_returns_non_zero() {
-    let temp = tempdir().expect("tempdir");
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "## 2. Assistant\n\n_Source: `{}:2`_\n\nworld",
+            temp.path()
+                .join(format!(
+                    "sessions/2026/02/23/rollout-2026-02-23T04-48-50-{SESSION_ID}.jsonl"
+                ))
+                .display()
+        )));
 
     let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
     cmd.env("CODEX_HOME", temp.path())
-        .env("CLAUDE_CONFIG_DIR", temp.path())
-        .arg(codex_uri())
+        .arg(agents_codex_uri())
+        .args(["--provenance", "--json"])
         .assert()
         .failure()
-        .stderr(predicate::str::contains("thread not found"));
+        .stderr(predicate::str::contains("cannot be used with"));
 }
 
 #[test]
-fn amp_outputs_markdown() {
-    let temp = setup_amp_tree();
+fn message_fragment_focuses_on_one_entry_with_context() {
+    let temp = setup_codex_tree();
 
     let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
-    cmd.env("XDG_DATA_HOME", temp.path())
-        .env("CODEX_HOME", temp.path().join("missing-codex"))
+    cmd.env("CODEX_HOME", temp.path())
         .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
-        .arg(amp_uri())
+        .arg(agents_codex_uri())
         .assert()
         .success()
-        .stdout(predicate::str::contains("# Thread"))
-        .stdout(predicate::str::contains("## 1. User"))
-        .stdout(predicate::str::contains("hello"))
-        .stdout(predicate::str::contains("analyze"))
-        .stdout(predicate::str::contains("world"));
-}
+        .stdout(predicate::str::contains("<a id=\"msg-1\" data-entry-id=\""))
+        .stdout(predicate::str::contains("<a id=\"msg-2\" data-entry-id=\""));
+
+    let output = Command::new(assert_cmd::cargo::cargo_bin!("xurl"))
+        .env("CODEX_HOME", temp.path())
+        .arg(format!("agents://codex/{SESSION_ID}#msg-2"))
+        .arg("--json")
+        .output()
+        .expect("run");
+    assert!(output.status.success());
+    let json = String::from_utf8(output.stdout).expect("utf8");
+    assert!(json.contains(&format!("\"uri\": \"agents://codex/{SESSION_ID}#msg-2\"")));
+    assert!(json.contains("\"number\": 2"));
+    assert!(json.contains("\"kind\": \"assistant\""));
+    assert!(json.contains("\"text\": \"world\""));
+    let id = json
+        .split("\"id\": \"")
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .expect("entry id");
 
-#[test]
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg(format!("agents://codex/{SESSION_ID}#{id}"))
+        .arg("--around")
+        .arg("0")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "_Focused on entry 2 (`msg-2`); showing entries 2-2 of 2._",
+        ))
+        .stdout(predicate::str::contains("## 2. Assistant\n\nworld"))
+        .stdout(predicate::str::contains("## 1. User").not());
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg(format!("agents://codex/{SESSION_ID}#msg-2"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("showing entries 1-2 of 2"))
+        .stdout(predicate::str::contains("## 1. User"));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .arg(format!("agents://codex/{SESSION_ID}#msg-1"))
+        .arg("-d")
+        .arg("hi")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "write mode does not accept #msg fragments",
+        ));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .arg(format!("agents://codex/{SESSION_ID}#msg-3"))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("entry_id=msg-3"));
+}
+
+#[test]
+fn verify_reports_problems_and_exits_non_zero() {
+    let temp = setup_codex_tree();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .arg("verify")
+        .arg(agents_codex_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("- Result: `ok`"))
+        .stdout(predicate::str::contains("_No problems found._"));
+
+    let thread_path = temp.path().join(format!(
+        "sessions/2026/02/23/rollout-2026-02-23T04-48-50-{SESSION_ID}.jsonl"
+    ));
+    let mut content = fs::read_to_string(&thread_path).expect("read");
+    content.push_str("{\"type\":\"response_item\",\"pay");
+    fs::write(&thread_path, content).expect("write");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .arg("verify")
+        .arg("--json")
+        .arg(agents_codex_uri())
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "\"code\": \"truncated_final_line\"",
+        ))
+        .stdout(predicate::str::contains("\"line\": 3"))
+        .stderr(predicate::str::contains(format!(
+            "verification failed for agents://codex/{SESSION_ID}: 1 problem(s) found"
+        )));
+}
+
+#[test]
+fn doctor_and_head_report_the_storage_schema() {
+    let temp = setup_codex_tree();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .arg("doctor")
+        .arg("--no-default-roots")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("## codex"))
+        .stdout(predicate::str::contains(format!(
+            "- `{}`: `rollouts`",
+            temp.path().display()
+        )));
+
+    fs::write(temp.path().join("state_5.sqlite"), "").expect("write sqlite");
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .arg("doctor")
+        .arg("--json")
+        .arg("--no-default-roots")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"layout\": \"state_db\""))
+        .stdout(predicate::str::contains("\"version\": \"5\""));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .arg(agents_codex_uri())
+        .arg("-I")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "schema:\n  layout: 'state_db'\n  version: '5'\n",
+        ));
+}
+
+#[test]
+fn no_default_roots_never_reads_the_home_directory() {
+    let temp = setup_codex_tree();
+    let home = tempdir().expect("home");
+    let home_codex = home.path().join(".codex");
+    fs::create_dir_all(&home_codex).expect("mkdir");
+    fs::rename(temp.path().join("sessions"), home_codex.join("sessions")).expect("move");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("HOME", home.path())
+        .env_remove("CODEX_HOME")
+        .env_remove("XURL_CODEX_ROOTS")
+        .env_remove("XURL_NO_DEFAULT_ROOTS")
+        .arg(agents_codex_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("world"));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("HOME", home.path())
+        .env_remove("CODEX_HOME")
+        .env_remove("XURL_CODEX_ROOTS")
+        .arg(agents_codex_uri())
+        .arg("--no-default-roots")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("thread not found"));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("HOME", home.path())
+        .env("CODEX_HOME", &home_codex)
+        .env("XURL_NO_DEFAULT_ROOTS", "1")
+        .arg(agents_codex_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("world"));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("HOME", home.path())
+        .env_remove("CLAUDE_CONFIG_DIR")
+        .arg("gc")
+        .arg("--provider")
+        .arg("claude")
+        .arg("--older-than")
+        .arg("30d")
+        .arg("--dry-run")
+        .arg("--no-default-roots")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "no storage root is set for provider=claude",
+        ));
+}
+
+#[test]
+fn head_diff_compares_against_a_written_baseline() {
+    let temp = setup_codex_tree();
+    let baseline = temp.path().join("head.yaml");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .arg("head-diff")
+        .arg(agents_codex_uri())
+        .arg("--baseline")
+        .arg(&baseline)
+        .arg("--update")
+        .assert()
+        .success();
+    assert!(
+        fs::read_to_string(&baseline)
+            .expect("read baseline")
+            .contains("mode: 'subagent_index'")
+    );
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .arg("head-diff")
+        .arg(agents_codex_uri())
+        .arg("--baseline")
+        .arg(&baseline)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("- Result: `unchanged`"));
+
+    let stored = fs::read_to_string(&baseline).expect("read baseline");
+    let stored = stored.replace(
+        "subagents:\n  []\n",
+        "subagents:\n  - agent_id: 'gone'\n    status: 'completed'\n",
+    );
+    fs::write(&baseline, stored).expect("write baseline");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .arg("head-diff")
+        .arg(agents_codex_uri())
+        .arg("--baseline")
+        .arg(&baseline)
+        .arg("--head-fields")
+        .arg("subagents")
+        .assert()
+        .code(6)
+        .stdout(predicate::str::contains(
+            "- subagents[gone].agent_id: 'gone'\n- subagents[gone].status: 'completed'\n+ subagents: []\n",
+        ))
+        .stderr(predicate::str::contains(format!(
+            "head of agents://codex/{SESSION_ID} differs from baseline"
+        )))
+        .stderr(predicate::str::contains("3 change(s)"));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .arg("head-diff")
+        .arg(agents_codex_uri())
+        .arg("--baseline")
+        .arg(temp.path().join("missing.yaml"))
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn stats_reports_turn_latency() {
+    let temp = setup_codex_tree();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .arg("stats")
+        .arg(agents_codex_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("- Turns: `1`"))
+        .stdout(predicate::str::contains("| 1 | - | 1 | - | - |"))
+        .stdout(predicate::str::contains("latency is unavailable"));
+
+    let thread_path = temp.path().join(format!(
+        "sessions/2026/02/23/rollout-2026-02-23T04-48-50-{SESSION_ID}.jsonl"
+    ));
+    fs::write(
+        &thread_path,
+        concat!(
+            "{\"timestamp\":\"2026-02-23T04:48:50.000Z\",\"type\":\"response_item\",\"payload\":{\"type\":\"message\",\"role\":\"user\",\"content\":[{\"type\":\"input_text\",\"text\":\"hello\"}]}}\n",
+            "{\"timestamp\":\"2026-02-23T04:48:52.500Z\",\"type\":\"response_item\",\"payload\":{\"type\":\"message\",\"role\":\"assistant\",\"content\":[{\"type\":\"output_text\",\"text\":\"world\"}]}}\n",
+        ),
+    )
+    .expect("write");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .arg("stats")
+        .arg("--json")
+        .arg(agents_codex_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"latency_ms\": 2500"))
+        .stdout(predicate::str::contains("\"p95_ms\": 2500"))
+        .stdout(predicate::str::contains("no_timestamps").not());
+}
+
+#[test]
+fn lineage_shows_forks_of_a_codex_session() {
+    let temp = setup_codex_tree();
+    let fork_id = "019c8800-0000-7000-8000-000000000001";
+    let fork_path = temp.path().join(format!(
+        "sessions/2026/02/24/rollout-2026-02-24T08-00-00-{fork_id}.jsonl"
+    ));
+    fs::create_dir_all(fork_path.parent().expect("parent")).expect("mkdir");
+    fs::write(
+        &fork_path,
+        format!(
+            "{{\"type\":\"session_meta\",\"payload\":{{\"id\":\"{fork_id}\",\"timestamp\":\"2026-02-24T08:00:00.000Z\",\"forked_from_id\":\"{SESSION_ID}\"}}}}\n"
+        ),
+    )
+    .expect("write fork");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .arg("lineage")
+        .arg(format!("agents://codex/{fork_id}"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "- Original: `agents://codex/{SESSION_ID}`"
+        )))
+        .stdout(predicate::str::contains(format!(
+            "\n- `agents://codex/{SESSION_ID}` — updated `"
+        )))
+        .stdout(predicate::str::contains(format!(
+            "\n  - forked: `agents://codex/{fork_id}` **(this thread)** — started `2026-02-24T08:00:00.000Z`"
+        )));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .args(["lineage", "--json"])
+        .arg(agents_codex_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"relation\": \"forked\""))
+        .stdout(predicate::str::contains(format!(
+            "\"parent_id\": \"{SESSION_ID}\""
+        )));
+}
+
+#[test]
+fn output_flag_writes_markdown_to_file() {
+    let temp = setup_codex_tree();
+    let output_dir = tempdir().expect("tempdir");
+    let output_path = output_dir.path().join("thread.md");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg(codex_uri())
+        .arg("-o")
+        .arg(&output_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+
+    let written = fs::read_to_string(output_path).expect("read output");
+    assert!(written.contains("---\n"));
+    assert!(written.contains("# Thread"));
+    assert!(written.contains("hello"));
+}
+
+#[test]
+fn output_flag_returns_error_when_parent_directory_missing() {
+    let temp = setup_codex_tree();
+    let missing_parent = temp.path().join("missing-parent");
+    let output_path = missing_parent.join("thread.md");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg(codex_uri())
+        .arg("--output")
+        .arg(&output_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("error: i/o error on"));
+}
+
+#[test]
+fn agents_uri_outputs_markdown() {
+    let temp = setup_codex_tree();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg(agents_codex_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "uri: 'agents://codex/{SESSION_ID}'"
+        )))
+        .stdout(predicate::str::contains("## 1. User"))
+        .stdout(predicate::str::contains("hello"));
+}
+
+#[test]
+fn raw_flag_is_rejected() {
+    let temp = setup_codex_tree();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg(codex_uri())
+        .arg("--raw")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unexpected argument '--raw'"));
+}
+
+#[test]
+fn head_flag_outputs_frontmatter_only() {
+    let temp = setup_codex_tree();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg(codex_uri())
+        .arg("-I")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("---\n"))
+        .stdout(predicate::str::contains("mode: 'subagent_index'"))
+        .stdout(predicate::str::contains("subagents:"))
+        .stdout(predicate::str::contains("# Thread").not());
+}
+
+#[test]
+fn codex_head_filters_subagents_by_time_window() {
+    let temp = setup_codex_subagent_tree();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg(codex_uri())
+        .arg("--head")
+        .arg("--since")
+        .arg("2026-02-23")
+        .arg("--until")
+        .arg("2026-02-23")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "agent_id: '{SUBAGENT_ID}'"
+        )));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg(codex_uri())
+        .arg("--head")
+        .arg("--since")
+        .arg("2026-02-24")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("subagents:\n  []"));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg(codex_uri())
+        .arg("--head")
+        .arg("--since")
+        .arg("last week")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid timestamp: last week"));
+}
+
+#[test]
+fn codex_subagent_head_outputs_header_only() {
+    let temp = setup_codex_subagent_tree();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg(codex_subagent_uri())
+        .arg("--head")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("mode: 'subagent_detail'"))
+        .stdout(predicate::str::contains(format!(
+            "agent_id: '{SUBAGENT_ID}'"
+        )))
+        .stdout(predicate::str::contains("status:"))
+        .stdout(predicate::str::contains("# Subagent Thread").not());
+}
+
+#[test]
+fn codex_deeplink_outputs_markdown() {
+    let temp = setup_codex_tree();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg(codex_deeplink_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("# Thread"))
+        .stdout(predicate::str::contains("## 1. User"))
+        .stdout(predicate::str::contains("hello"));
+}
+
+#[cfg(unix)]
+#[test]
+fn summarize_command_outputs_structured_summary() {
+    let temp = setup_codex_tree();
+    let prompt_path = temp.path().join("prompt.txt");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg("summarize")
+        .arg(agents_codex_uri())
+        .arg("--command")
+        .arg(format!(
+            "cat > '{}'; printf '## Goals\\n- ship\\n## Decisions\\n- use rust\\n## Open TODOs\\n- none\\n'",
+            prompt_path.display()
+        ))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("summarizer: 'command:"))
+        .stdout(predicate::str::contains("# Thread Summary"))
+        .stdout(predicate::str::contains("## Goals\n\n- ship"))
+        .stdout(predicate::str::contains("## Decisions\n\n- use rust"))
+        .stdout(predicate::str::contains("## Open TODOs\n\n_None identified._"));
+
+    let prompt = fs::read_to_string(prompt_path).expect("read prompt");
+    assert!(prompt.contains("## Open TODOs"));
+    assert!(prompt.contains("hello"));
+}
+
+#[cfg(unix)]
+#[test]
+fn summarize_reports_failing_summarizer_command() {
+    let temp = setup_codex_tree();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg("summarize")
+        .arg(agents_codex_uri())
+        .arg("--command")
+        .arg("echo boom >&2; exit 3")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("boom"));
+}
+
+#[test]
+fn import_makes_external_rollout_resolvable() {
+    let source_dir = tempdir().expect("tempdir");
+    let source = source_dir.path().join("shared.jsonl");
+    fs::write(
+        &source,
+        format!(
+            "{{\"timestamp\":\"2026-02-23T04:48:50Z\",\"type\":\"session_meta\",\"payload\":{{\"id\":\"{SESSION_ID}\"}}}}\n{{\"timestamp\":\"2026-02-23T04:48:51Z\",\"type\":\"response_item\",\"payload\":{{\"type\":\"message\",\"role\":\"user\",\"content\":[{{\"type\":\"input_text\",\"text\":\"shared hello\"}}]}}}}\n"
+        ),
+    )
+    .expect("write source");
+    let codex_home = tempdir().expect("tempdir");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", codex_home.path())
+        .arg("import")
+        .arg("--provider")
+        .arg("codex")
+        .arg(&source)
+        .assert()
+        .success()
+        .stdout(predicate::eq(format!("{}\n", agents_codex_uri())))
+        .stderr(predicate::str::contains(format!(
+            "rollout-2026-02-23T04-48-50-{SESSION_ID}.jsonl"
+        )));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", codex_home.path())
+        .env(
+            "CLAUDE_CONFIG_DIR",
+            codex_home.path().join("missing-claude"),
+        )
+        .arg(agents_codex_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("shared hello"));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", codex_home.path())
+        .arg("import")
+        .arg("--provider")
+        .arg("codex")
+        .arg(&source)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("thread already exists"));
+}
+
+#[test]
+fn gc_reports_then_deletes_stale_codex_rollouts() {
+    let temp = setup_codex_tree();
+    let thread_path = temp.path().join(format!(
+        "sessions/2026/02/23/rollout-2026-02-23T04-48-50-{SESSION_ID}.jsonl"
+    ));
+    let stale = std::time::SystemTime::now() - std::time::Duration::from_secs(60 * 24 * 60 * 60);
+    fs::File::options()
+        .write(true)
+        .open(&thread_path)
+        .expect("open")
+        .set_modified(stale)
+        .expect("set mtime");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .args(["gc", "--provider", "codex", "--older-than", "30d"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("# Session GC"))
+        .stdout(predicate::str::contains(agents_codex_uri()));
+    assert!(thread_path.exists());
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .args([
+            "gc",
+            "--provider",
+            "codex",
+            "--older-than",
+            "90d",
+            "--delete",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("- Sessions: `0`"));
+    assert!(thread_path.exists());
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .args([
+            "gc",
+            "--provider",
+            "codex",
+            "--older-than",
+            "30d",
+            "--delete",
+        ])
+        .assert()
+        .success();
+    assert!(!thread_path.exists());
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .args(["gc", "--provider", "gemini", "--older-than", "30d"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("gemini"));
+}
+
+#[test]
+fn dedupe_groups_copied_codex_rollouts() {
+    let temp = setup_codex_tree();
+    let day = temp.path().join("sessions/2026/02/23");
+    let copy_id = "019c871c-b1f9-7f60-9c4f-87ed09f13593";
+    fs::copy(
+        day.join(format!("rollout-2026-02-23T04-48-50-{SESSION_ID}.jsonl")),
+        day.join(format!("rollout-2026-02-24T09-00-00-{copy_id}.jsonl")),
+    )
+    .expect("copy");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .args(["dedupe", "--provider", "codex"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("# Duplicate Threads"))
+        .stdout(predicate::str::contains("- Duplicate Groups: `1`"))
+        .stdout(predicate::str::contains(agents_codex_uri()))
+        .stdout(predicate::str::contains(format!(
+            "agents://codex/{copy_id}"
+        )));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .args(["-I", "--fingerprint"])
+        .arg(agents_codex_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match("\nfingerprint: '[0-9a-f]{32}'\n").expect("regex"));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .arg("-I")
+        .arg(agents_codex_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("fingerprint:").not());
+}
+
+#[test]
+fn bom_and_invalid_utf8_transcripts_are_read_with_a_warning() {
+    let temp = setup_codex_tree();
+    let thread_path = temp.path().join(format!(
+        "sessions/2026/02/23/rollout-2026-02-23T04-48-50-{SESSION_ID}.jsonl"
+    ));
+    let raw = fs::read_to_string(&thread_path).expect("read");
+    let (before, after) = raw.split_once("world").expect("answer");
+    let bytes = [
+        b"\xef\xbb\xbf".as_slice(),
+        before.as_bytes(),
+        b"wor\xffld",
+        after.as_bytes(),
+    ]
+    .concat();
+    fs::write(&thread_path, bytes).expect("write");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .arg(agents_codex_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("## 1. User\n\nhello"))
+        .stdout(predicate::str::contains("wor\u{fffd}ld"))
+        .stdout(predicate::str::contains("code: 'invalid_bytes'"));
+}
+
+#[test]
+fn file_uri_renders_a_transcript_outside_provider_roots() {
+    let temp = setup_codex_tree();
+    let loose = temp.path().join("loose copy.jsonl");
+    fs::copy(
+        temp.path().join(format!(
+            "sessions/2026/02/23/rollout-2026-02-23T04-48-50-{SESSION_ID}.jsonl"
+        )),
+        &loose,
+    )
+    .expect("copy");
+    let uri = format!(
+        "file://{}?format=codex",
+        loose.display().to_string().replace(' ', "%20")
+    );
+    let empty = tempdir().expect("tempdir");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", empty.path())
+        .arg(&uri)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("uri: '{uri}'")))
+        .stdout(predicate::str::contains("provider: 'file'"))
+        .stdout(predicate::str::contains("## 1. User\n\nhello"))
+        .stdout(predicate::str::contains("## 2. Assistant\n\nworld"));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.arg(format!("file://{}", loose.display()))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("?format=codex"));
+}
+
+#[test]
+fn recent_lists_newest_threads_across_providers() {
+    let codex = setup_codex_tree();
+    let claude = setup_claude_subagent_tree();
+    let empty = tempdir().expect("tempdir");
+    let rollout = codex.path().join(format!(
+        "sessions/2026/02/23/rollout-2026-02-23T04-48-50-{SESSION_ID}.jsonl"
+    ));
+    fs::File::options()
+        .write(true)
+        .open(&rollout)
+        .expect("open")
+        .set_modified(std::time::SystemTime::now() - std::time::Duration::from_secs(3600))
+        .expect("set mtime");
+
+    let recent = |limit: &str| {
+        let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+        cmd.env("CODEX_HOME", codex.path())
+            .env("CLAUDE_CONFIG_DIR", claude.path())
+            .env("XDG_DATA_HOME", empty.path())
+            .env("GEMINI_CLI_HOME", empty.path())
+            .env("PI_CODING_AGENT_DIR", empty.path().join("pi"))
+            .args(["recent", "-n", limit])
+            .assert()
+            .success()
+    };
+
+    let output = recent("5").get_output().stdout.clone();
+    let output = String::from_utf8(output).expect("utf8");
+    let claude_pos = output
+        .find(&format!("1. `{}`", agents_uri("claude", CLAUDE_SESSION_ID)))
+        .expect("claude listed first");
+    let codex_pos = output
+        .find(&format!("2. `{}`", agents_codex_uri()))
+        .expect("codex listed second");
+    assert!(claude_pos < codex_pos);
+    assert!(output.contains("   > root thread"));
+    assert!(output.contains("   > hello"));
+    assert!(!output.contains("agent-"));
+
+    recent("1").stdout(predicate::str::contains(agents_codex_uri()).not());
+}
+
+#[test]
+fn recent_project_keeps_threads_started_in_that_directory() {
+    let codex = setup_codex_tree();
+    let claude = setup_claude_subagent_tree();
+    let empty = tempdir().expect("tempdir");
+    let project = tempdir().expect("tempdir");
+    let rollout = codex.path().join(format!(
+        "sessions/2026/02/23/rollout-2026-02-23T04-48-50-{SESSION_ID}.jsonl"
+    ));
+    let raw = fs::read_to_string(&rollout).expect("read");
+    fs::write(
+        &rollout,
+        format!(
+            "{{\"type\":\"session_meta\",\"payload\":{{\"id\":\"{SESSION_ID}\",\"cwd\":\"{}\"}}}}\n{raw}",
+            project.path().join("crates/core").display()
+        ),
+    )
+    .expect("write");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.current_dir(project.path())
+        .env("CODEX_HOME", codex.path())
+        .env("CLAUDE_CONFIG_DIR", claude.path())
+        .env("XDG_DATA_HOME", empty.path())
+        .env("GEMINI_CLI_HOME", empty.path())
+        .env("PI_CODING_AGENT_DIR", empty.path().join("pi"))
+        .args(["recent", "--project", "."])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "1. `{}`",
+            agents_codex_uri()
+        )))
+        .stdout(predicate::str::contains(agents_uri("claude", CLAUDE_SESSION_ID)).not());
+}
+
+#[test]
+fn pack_keeps_newest_messages_within_budget() {
+    let temp = setup_codex_tree();
+    let rollout = temp.path().join(format!(
+        "sessions/2026/02/23/rollout-2026-02-23T04-48-50-{SESSION_ID}.jsonl"
+    ));
+    let line = |role: &str, kind: &str, text: &str| {
+        format!(
+            "{{\"type\":\"response_item\",\"payload\":{{\"type\":\"message\",\"role\":\"{role}\",\"content\":[{{\"type\":\"{kind}\",\"text\":\"{text}\"}}]}}}}"
+        )
+    };
+    let long = "older detail ".repeat(200);
+    let lines = [
+        line(
+            "user",
+            "input_text",
+            "<environment_context>\\n  <cwd>/repo</cwd>\\n</environment_context>",
+        ),
+        line("user", "input_text", &long),
+        line("assistant", "output_text", &long),
+        line("user", "input_text", "latest question"),
+        line("assistant", "output_text", "latest answer"),
+    ];
+    fs::write(&rollout, format!("{}\n", lines.join("\n"))).expect("write");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("XURL_CACHE_DIR", temp.path().join("cache"))
+        .args(["pack", &agents_codex_uri(), "--max-tokens", "150"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("# Packed Thread"))
+        .stdout(predicate::str::contains("max_tokens: 150"))
+        .stdout(predicate::str::contains("_1 earlier messages omitted._"))
+        .stdout(predicate::str::contains("## Assistant (truncated)"))
+        .stdout(predicate::str::contains("## User\n\nlatest question"))
+        .stdout(predicate::str::contains("## Assistant\n\nlatest answer"))
+        .stdout(predicate::str::contains("environment_context").not());
+}
+
+#[test]
+fn any_scheme_probes_every_provider_for_the_session() {
+    let codex = setup_codex_tree();
+    let claude = setup_claude_subagent_tree();
+    let empty = tempdir().expect("tempdir");
+    let xurl = |uri: &str| {
+        let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+        cmd.env("CODEX_HOME", codex.path())
+            .env("CLAUDE_CONFIG_DIR", claude.path())
+            .env("XDG_DATA_HOME", empty.path())
+            .env("GEMINI_CLI_HOME", empty.path())
+            .env("PI_CODING_AGENT_DIR", empty.path().join("pi"))
+            .env("XURL_CACHE_DIR", empty.path().join("cache"))
+            .arg(uri)
+            .assert()
+    };
+
+    xurl(&format!("any://{SESSION_ID}"))
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "uri: '{}'",
+            agents_codex_uri()
+        )))
+        .stdout(predicate::str::contains("hello"));
+    xurl(&format!("agents://any/{}", &CLAUDE_SESSION_ID[..8]))
+        .success()
+        .stdout(predicate::str::contains("root thread"));
+    xurl("any://ffffffff-ffff-4fff-8fff-ffffffffffff")
+        .failure()
+        .stderr(predicate::str::contains(
+            "thread not found for provider=any",
+        ));
+
+    fs::copy(
+        claude.path().join(format!(
+            "projects/project-subagent/{CLAUDE_SESSION_ID}.jsonl"
+        )),
+        claude
+            .path()
+            .join(format!("projects/project-subagent/{SESSION_ID}.jsonl")),
+    )
+    .expect("copy");
+    xurl(&format!("any://{SESSION_ID}"))
+        .failure()
+        .stderr(predicate::str::contains(format!(
+            "session id {SESSION_ID} exists for several providers"
+        )))
+        .stderr(predicate::str::contains(agents_codex_uri()))
+        .stderr(predicate::str::contains(agents_uri("claude", SESSION_ID)));
+}
+
+#[test]
+fn codex_session_id_prefix_resolves_unique_match() {
+    let temp = setup_codex_tree();
+    let sibling = temp.path().join(
+        "sessions/2026/02/23/rollout-2026-02-23T05-00-00-019c8800-0000-7000-8000-000000000000.jsonl",
+    );
+    fs::copy(
+        temp.path().join(format!(
+            "sessions/2026/02/23/rollout-2026-02-23T04-48-50-{SESSION_ID}.jsonl"
+        )),
+        &sibling,
+    )
+    .expect("copy");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .arg("codex://019c871c")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "uri: '{}'",
+            agents_codex_uri()
+        )))
+        .stdout(predicate::str::contains("hello"));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .arg("codex://019c8")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("ambiguous session id prefix"))
+        .stderr(predicate::str::contains(SESSION_ID))
+        .stderr(predicate::str::contains(
+            "019c8800-0000-7000-8000-000000000000",
+        ));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .arg("codex://ffff")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("thread not found"));
+}
+
+#[test]
+fn codex_merge_rollouts_concatenates_in_timestamp_order() {
+    let temp = setup_codex_tree();
+    let continued = temp.path().join(format!(
+        "sessions/2026/02/24/rollout-2026-02-24T09-00-00-{SESSION_ID}.jsonl"
+    ));
+    fs::create_dir_all(continued.parent().expect("parent")).expect("mkdir");
+    fs::write(
+        &continued,
+        "{\"type\":\"response_item\",\"payload\":{\"type\":\"message\",\"role\":\"user\",\"content\":[{\"type\":\"input_text\",\"text\":\"continue\"}]}}\n",
+    )
+    .expect("write");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .arg(agents_codex_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("code: 'ambiguous_session'"))
+        .stdout(predicate::str::contains("Rollout Boundary").not());
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    let output = cmd
+        .env("CODEX_HOME", temp.path())
+        .arg("--merge-rollouts")
+        .arg(agents_codex_uri())
+        .output()
+        .expect("run");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("## 1. Rollout Boundary"));
+    assert!(stdout.contains("## 4. Rollout Boundary"));
+    assert!(stdout.contains("rollout-2026-02-24T09-00-00"));
+    let hello = stdout.find("hello").expect("first rollout");
+    let cont = stdout.find("continue").expect("second rollout");
+    assert!(hello < cont);
+    assert!(stdout.contains("code: 'merged_rollouts'"));
+    assert!(stdout.contains("merged 2 rollouts"));
+}
+
+#[test]
+fn porcelain_reports_uri_warnings_and_status_on_stderr() {
+    let temp = setup_codex_tree();
+    let continued = temp.path().join(format!(
+        "sessions/2026/02/24/rollout-2026-02-24T09-00-00-{SESSION_ID}.jsonl"
+    ));
+    fs::create_dir_all(continued.parent().expect("parent")).expect("mkdir");
+    fs::write(
+        &continued,
+        "{\"type\":\"response_item\",\"payload\":{\"type\":\"message\",\"role\":\"user\",\"content\":[{\"type\":\"input_text\",\"text\":\"continue\"}]}}\n",
+    )
+    .expect("write");
+
+    let output = Command::new(assert_cmd::cargo::cargo_bin!("xurl"))
+        .env("CODEX_HOME", temp.path())
+        .arg("--porcelain")
+        .arg(codex_uri())
+        .output()
+        .expect("run");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("---\n"));
+    assert!(!stdout.contains("warnings:"));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let records = stderr.lines().collect::<Vec<_>>();
+    assert_eq!(
+        records.first(),
+        Some(&format!("uri: {}", agents_codex_uri()).as_str())
+    );
+    assert_eq!(
+        records
+            .iter()
+            .filter(|record| record.starts_with("warning: warn ambiguous_session "))
+            .count(),
+        1
+    );
+    assert_eq!(records.last(), Some(&"status: ok"));
+
+    Command::new(assert_cmd::cargo::cargo_bin!("xurl"))
+        .env("CODEX_HOME", temp.path())
+        .args(["--porcelain", "codex://ffff"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::starts_with("uri: agents://codex/ffff\n"))
+        .stderr(predicate::str::contains("status: error thread not found"))
+        .stderr(predicate::str::contains("\nerror: ").not());
+}
+
+#[test]
+fn porcelain_reports_subagent_json_warnings_as_records() {
+    let temp = setup_codex_subagent_tree();
+    let continued = temp.path().join(format!(
+        "sessions/2026/02/24/rollout-2026-02-24T09-00-00-{SESSION_ID}.jsonl"
+    ));
+    fs::create_dir_all(continued.parent().expect("parent")).expect("mkdir");
+    fs::write(
+        &continued,
+        "{\"type\":\"response_item\",\"payload\":{\"type\":\"message\",\"role\":\"user\",\"content\":[{\"type\":\"input_text\",\"text\":\"continue\"}]}}\n",
+    )
+    .expect("write");
+
+    let output = Command::new(assert_cmd::cargo::cargo_bin!("xurl"))
+        .env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .args(["--porcelain", "--json"])
+        .arg(codex_uri())
+        .output()
+        .expect("run");
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let records = stderr.lines().collect::<Vec<_>>();
+    assert!(
+        records
+            .iter()
+            .any(|record| record.starts_with("warning: warn "))
+    );
+    assert!(records.iter().all(|record| {
+        ["uri: ", "warning: ", "status: "]
+            .iter()
+            .any(|kind| record.starts_with(kind))
+    }));
+    assert_eq!(records.last(), Some(&"status: ok"));
+
+    // Subcommands print their own free-form lines, so they reject it.
+    Command::new(assert_cmd::cargo::cargo_bin!("xurl"))
+        .env("CODEX_HOME", temp.path())
+        .args(["--porcelain", "fork"])
+        .arg(codex_uri())
+        .assert()
+        .code(2)
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("cannot be used with"))
+        .stderr(predicate::str::contains("--porcelain"));
+}
+
+#[test]
+fn agents_codex_deeplink_outputs_markdown() {
+    let temp = setup_codex_tree();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg(agents_codex_deeplink_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("# Thread"))
+        .stdout(predicate::str::contains("## 1. User"))
+        .stdout(predicate::str::contains("hello"));
+}
+
+#[test]
+fn codex_subagent_outputs_markdown_view() {
+    let temp = setup_codex_subagent_tree();
+    let main_uri = agents_uri("codex", SESSION_ID);
+    let subagent_uri = agents_child_uri("codex", SESSION_ID, SUBAGENT_ID);
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg(codex_subagent_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("# Subagent Thread"))
+        .stdout(predicate::str::contains(format!(
+            "- Main Thread: `{main_uri}`"
+        )))
+        .stdout(predicate::str::contains(format!(
+            "- Subagent Thread: `{subagent_uri}`"
+        )))
+        .stdout(predicate::str::contains(
+            "## Initial Prompt\n\nreview the parser\n",
+        ))
+        .stdout(predicate::str::contains("## Lifecycle (Parent Thread)"))
+        .stdout(predicate::str::contains("## Thread Excerpt (Child Thread)"));
+}
+
+#[test]
+fn agents_codex_subagent_outputs_markdown_view() {
+    let temp = setup_codex_subagent_tree();
+    let main_uri = agents_uri("codex", SESSION_ID);
+    let subagent_uri = agents_child_uri("codex", SESSION_ID, SUBAGENT_ID);
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg(agents_codex_subagent_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "- Main Thread: `{main_uri}`"
+        )))
+        .stdout(predicate::str::contains(format!(
+            "- Subagent Thread: `{subagent_uri}`"
+        )));
+}
+
+#[test]
+fn codex_json_outputs_subagent_index_and_detail() {
+    let temp = setup_codex_subagent_tree();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg(agents_codex_uri())
+        .arg("--json")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("{"))
+        .stdout(predicate::str::contains("\"kind\": \"list\""))
+        .stdout(predicate::str::contains(format!(
+            "\"agent_id\": \"{SUBAGENT_ID}\""
+        )));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg(agents_codex_subagent_uri())
+        .arg("--json")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"kind\": \"detail\""))
+        .stdout(predicate::str::contains("\"excerpt\""));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.arg(agents_uri("pi", PI_SESSION_ID))
+        .arg("--json")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--json is only supported"));
+}
+
+#[test]
+fn codex_outputs_no_warning_text_for_markdown() {
+    let temp = setup_codex_tree_with_sqlite_missing_threads();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg(codex_uri())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("warning:").not());
+}
+
+#[test]
+fn verbose_logs_resolution_steps_to_stderr() {
+    let temp = setup_codex_tree_with_sqlite_missing_threads();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env_remove("RUST_LOG")
+        .arg(agents_codex_uri())
+        .arg("-v")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("world"))
+        .stderr(predicate::str::contains("resolving thread"))
+        .stderr(predicate::str::contains("sqlite query"))
+        .stderr(predicate::str::contains("source=\"codex:sessions\""));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env_remove("RUST_LOG")
+        .arg(agents_codex_uri())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("DEBUG").not());
+}
+
+#[test]
+fn codex_subagent_outputs_no_warning_text_for_markdown() {
+    let temp = setup_codex_subagent_tree_with_sqlite_missing_threads();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg(codex_subagent_uri())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("warning:").not());
+}
+
+#[test]
+fn codex_real_fixture_head_includes_subagents() {
+    let fixture_root = codex_real_fixture_root();
+    assert!(fixture_root.exists(), "fixture root must exist");
+    let subagent_uri = agents_child_uri("codex", REAL_FIXTURE_MAIN_ID, REAL_FIXTURE_AGENT_ID);
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", fixture_root)
+        .env("CLAUDE_CONFIG_DIR", "/tmp/missing-claude")
+        .arg(format!("codex://{REAL_FIXTURE_MAIN_ID}"))
+        .arg("--head")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("mode: 'subagent_index'"))
+        .stdout(predicate::str::contains("subagents:"))
+        .stdout(predicate::str::contains(subagent_uri))
+        .stdout(predicate::str::contains("# Subagent Status").not());
+}
+
+#[test]
+fn codex_real_fixture_subagent_detail_outputs_markdown() {
+    let fixture_root = codex_real_fixture_root();
+    assert!(fixture_root.exists(), "fixture root must exist");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", fixture_root)
+        .env("CLAUDE_CONFIG_DIR", "/tmp/missing-claude")
+        .arg(format!(
+            "codex://{REAL_FIXTURE_MAIN_ID}/{REAL_FIXTURE_AGENT_ID}"
+        ))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("# Subagent Thread"))
+        .stdout(predicate::str::contains("## Lifecycle (Parent Thread)"));
+}
+
+#[test]
+fn list_flag_is_rejected() {
+    let temp = setup_codex_subagent_tree();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg(codex_subagent_uri())
+        .arg("--list")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unexpected argument '--list'"));
+}
+
+#[test]
+fn missing_thread_returns_non_zero() {
+    let temp = tempdir().expect("tempdir");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path())
+        .arg(codex_uri())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("thread not found"));
+}
+
+#[test]
+fn amp_outputs_markdown() {
+    let temp = setup_amp_tree();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("XDG_DATA_HOME", temp.path())
+        .env("CODEX_HOME", temp.path().join("missing-codex"))
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg(amp_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("# Thread"))
+        .stdout(predicate::str::contains("## 1. User"))
+        .stdout(predicate::str::contains("hello"))
+        .stdout(predicate::str::contains("analyze"))
+        .stdout(predicate::str::contains("world"));
+}
+
+#[test]
+fn amp_web_links_and_short_ids_read_the_thread() {
+    let temp = setup_amp_tree();
+    let compact = AMP_SESSION_ID[2..].replace('-', "");
+
+    for input in [
+        format!("https://ampcode.com/threads/{AMP_SESSION_ID}"),
+        format!("amp://{compact}"),
+        "agents://amp/019c0797".to_string(),
+    ] {
+        let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+        cmd.env("XDG_DATA_HOME", temp.path())
+            .env("CODEX_HOME", temp.path().join("missing-codex"))
+            .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+            .arg(&input)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("world"));
+    }
+}
+
+#[test]
+fn resolve_prints_canonical_uri_and_thread_path() {
+    let temp = setup_codex_subagent_tree();
+    let sessions = temp.path().join("sessions/2026/02/23");
+    let xurl = || {
+        let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+        cmd.env("CODEX_HOME", temp.path())
+            .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+            .arg("resolve");
+        cmd
+    };
+
+    xurl()
+        .arg(format!("{}#msg-1", codex_uri()))
+        .assert()
+        .success()
+        .stdout(format!(
+            "{}\t{}\n",
+            agents_codex_uri(),
+            sessions
+                .join(format!("rollout-2026-02-23T04-48-50-{SESSION_ID}.jsonl"))
+                .display()
+        ));
+    xurl()
+        .arg(codex_subagent_uri())
+        .assert()
+        .success()
+        .stdout(format!(
+            "{}\t{}\n",
+            agents_codex_subagent_uri(),
+            sessions
+                .join(format!("rollout-2026-02-23T04-49-10-{SUBAGENT_ID}.jsonl"))
+                .display()
+        ));
+    xurl()
+        .arg("--json")
+        .arg(codex_subagent_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "\"uri\": \"{}\"",
+            agents_codex_subagent_uri()
+        )))
+        .stdout(predicate::str::contains("\"provider\": \"codex\""))
+        .stdout(predicate::str::contains(format!(
+            "\"agent_id\": \"{SUBAGENT_ID}\""
+        )))
+        .stdout(predicate::str::contains("\"path\": "));
+}
+
+#[test]
+fn snapshot_copies_thread_and_subagents_into_read_only_directory() {
+    let temp = setup_codex_subagent_tree();
+    let out = tempdir().expect("tempdir");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    let assert = cmd
+        .env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg("snapshot")
+        .arg(codex_uri())
+        .arg("--out")
+        .arg(out.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("(2 files)"))
+        .stdout(predicate::str::starts_with(format!(
+            "file://{}/codex-{SESSION_ID}-",
+            out.path().display()
+        )))
+        .stdout(predicate::str::ends_with("?format=codex\n"));
+    let snapshot_uri = String::from_utf8(assert.get_output().stdout.clone())
+        .expect("utf8")
+        .trim()
+        .to_string();
+
+    let child = fs::read_dir(out.path())
+        .expect("read out")
+        .next()
+        .expect("snapshot dir")
+        .expect("entry")
+        .path()
+        .join(format!(
+            "subagents/{SUBAGENT_ID}/rollout-2026-02-23T04-49-10-{SUBAGENT_ID}.jsonl"
+        ));
+    assert!(
+        fs::metadata(&child)
+            .expect("child copy")
+            .permissions()
+            .readonly()
+    );
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.arg(&snapshot_uri)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("thread_source: '"));
+}
+
+#[test]
+fn head_fields_select_frontmatter_keys() {
+    let temp = setup_codex_tree();
+    let xurl = |fields: &str| {
+        let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+        cmd.env("CODEX_HOME", temp.path())
+            .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+            .arg(codex_uri())
+            .arg("-I")
+            .arg("--head-fields")
+            .arg(fields)
+            .assert()
+    };
+
+    xurl("uri,provider").success().stdout(format!(
+        "---\nuri: 'agents://codex/{SESSION_ID}'\nprovider: 'codex'\n---\n"
+    ));
+    xurl("-subagents,-aggregate")
+        .success()
+        .stdout(predicate::str::contains("mode: 'subagent_index'"))
+        .stdout(predicate::str::contains("subagents:").not());
+    xurl("uri,subagent")
+        .failure()
+        .stderr(predicate::str::contains("unknown head field \"subagent\""));
+}
+
+#[test]
 fn amp_head_outputs_subagent_index() {
     let temp = setup_amp_subagent_tree();
     let subagent_uri = agents_child_uri("amp", AMP_SESSION_ID, AMP_SUBAGENT_ID);
@@ -749,10 +2303,216 @@ fn amp_head_outputs_subagent_index() {
         .arg("--head")
         .assert()
         .success()
-        .stdout(predicate::str::contains("mode: 'subagent_index'"))
-        .stdout(predicate::str::contains("subagents:"))
-        .stdout(predicate::str::contains(subagent_uri))
-        .stdout(predicate::str::contains("# Subagent Status").not());
+        .stdout(predicate::str::contains("mode: 'subagent_index'"))
+        .stdout(predicate::str::contains("subagents:"))
+        .stdout(predicate::str::contains(subagent_uri))
+        .stdout(predicate::str::contains("# Subagent Status").not());
+}
+
+#[test]
+fn exit_status_reports_subagent_progress() {
+    let temp = setup_amp_handoff_chain_tree();
+    let xurl = |uri: String| {
+        let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+        cmd.env("XDG_DATA_HOME", temp.path())
+            .env("CODEX_HOME", temp.path().join("missing-codex"))
+            .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+            .arg(uri)
+            .arg("--head")
+            .arg("--exit-status");
+        cmd
+    };
+
+    // The grandchild is still running, so the whole tree is.
+    xurl(agents_uri("amp", AMP_SESSION_ID))
+        .assert()
+        .code(5)
+        .stdout(predicate::str::contains("worst_status: 'running'"))
+        .stderr(predicate::str::contains("is running"));
+
+    xurl(agents_child_uri("amp", AMP_SESSION_ID, AMP_SUBAGENT_ID))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("status: 'completed'"));
+
+    xurl(agents_uri("amp", "T-ffffffff-c402-7389-bd80-d785c98df295"))
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("thread not found"));
+
+    // Without the flag, both are ordinary results.
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("XDG_DATA_HOME", temp.path())
+        .arg(agents_uri("amp", "T-ffffffff-c402-7389-bd80-d785c98df295"))
+        .assert()
+        .code(1);
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("XDG_DATA_HOME", temp.path())
+        .arg(agents_uri("amp", AMP_SESSION_ID))
+        .arg("--head")
+        .assert()
+        .success();
+}
+
+#[test]
+fn wait_polls_until_the_subagent_is_done() {
+    let temp = setup_amp_handoff_chain_tree();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("XDG_DATA_HOME", temp.path())
+        .arg("wait")
+        .arg(agents_child_uri("amp", AMP_SESSION_ID, AMP_SUBAGENT_ID))
+        .assert()
+        .success()
+        .stdout("completed (child_thread)\n");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("XDG_DATA_HOME", temp.path())
+        .args(["wait", "--timeout", "1", "--interval", "1"])
+        .arg(agents_child_uri("amp", AMP_SUBAGENT_ID, AMP_GRANDCHILD_ID))
+        .assert()
+        .code(5)
+        .stdout("running (child_thread)\n")
+        .stderr(predicate::str::contains(
+            "timed out after 1s waiting for agents://amp/",
+        ))
+        .stderr(predicate::str::contains("last status: running"));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("XDG_DATA_HOME", temp.path())
+        .arg("wait")
+        .arg(agents_uri("amp", AMP_SESSION_ID))
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("wait requires a subagent URI"));
+}
+
+#[test]
+fn notify_posts_wait_outcomes_to_the_configured_webhook() {
+    use std::io::{Read, Write};
+
+    let temp = setup_amp_handoff_chain_tree();
+    let config = tempdir().expect("tempdir");
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+    let webhook = format!(
+        "http://127.0.0.1:{}/hook",
+        listener.local_addr().expect("addr").port()
+    );
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().expect("accept");
+        let mut request = Vec::new();
+        let mut buf = [0; 4096];
+        // The JSON body is the end of the request.
+        while !request.ends_with(b"}") {
+            let read = stream.read(&mut buf).expect("read");
+            assert!(read > 0, "request ended early");
+            request.extend_from_slice(&buf[..read]);
+        }
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .expect("respond");
+        String::from_utf8(request).expect("utf8")
+    });
+    let xurl = || {
+        let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+        cmd.env("XDG_DATA_HOME", temp.path())
+            .env("XURL_CONFIG_DIR", config.path());
+        cmd
+    };
+
+    xurl()
+        .args(["notify", "--webhook", "hooks.example.com"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("http:// or https:// URL"));
+    xurl()
+        .args(["notify", "--webhook", &webhook, "--events", "wait"])
+        .assert()
+        .success()
+        .stdout(format!("webhook: {webhook}\nevents: wait\n"));
+
+    xurl()
+        .arg("wait")
+        .arg(agents_child_uri("amp", AMP_SESSION_ID, AMP_SUBAGENT_ID))
+        .assert()
+        .success()
+        .stdout("completed (child_thread)\n");
+    let request = server.join().expect("server");
+    assert!(request.starts_with("POST /hook "));
+    let uri = agents_child_uri("amp", AMP_SESSION_ID, AMP_SUBAGENT_ID);
+    assert!(request.contains(&format!("\"text\":\"{uri} completed after ")));
+    assert!(request.contains("\"event\":\"wait\""));
+
+    xurl()
+        .args(["notify", "--clear"])
+        .assert()
+        .success()
+        .stdout("webhook: none\nevents: write, wait\n");
+    xurl()
+        .args(["notify", "--test"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no webhook to test"));
+}
+
+#[test]
+fn amp_head_follows_handoff_chains_without_loops() {
+    let temp = setup_amp_handoff_chain_tree();
+    let grandchild_uri = agents_child_uri("amp", AMP_SUBAGENT_ID, AMP_GRANDCHILD_ID);
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("XDG_DATA_HOME", temp.path())
+        .env("CODEX_HOME", temp.path().join("missing-codex"))
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg(agents_uri("amp", AMP_SESSION_ID))
+        .arg("--head")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("    children:\n"))
+        .stdout(predicate::str::contains(format!(
+            "      - agent_id: '{AMP_GRANDCHILD_ID}'\n        uri: '{grandchild_uri}'"
+        )))
+        .stdout(predicate::str::contains("skipped amp handoff loop"));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("XDG_DATA_HOME", temp.path())
+        .env("CODEX_HOME", temp.path().join("missing-codex"))
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg(agents_uri("amp", AMP_SESSION_ID))
+        .arg("--head")
+        .arg("--handoff-depth")
+        .arg("1")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("children:").not())
+        .stdout(predicate::str::contains(
+            "amp handoff chain truncated at depth=1",
+        ));
+}
+
+#[test]
+fn amp_head_reports_file_modified_fallback_as_rfc3339() {
+    let temp = setup_amp_handoff_chain_tree();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("XDG_DATA_HOME", temp.path())
+        .env("CODEX_HOME", temp.path().join("missing-codex"))
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+        .arg(agents_uri("amp", AMP_SESSION_ID))
+        .arg("--head")
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::is_match(
+                r"last_update: '\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(\.\d+)?Z'",
+            )
+            .expect("regex"),
+        )
+        .stdout(
+            predicate::str::is_match(r"last_update: '\d+'")
+                .expect("regex")
+                .not(),
+        );
 }
 
 #[test]
@@ -814,6 +2574,9 @@ fn amp_subagent_outputs_markdown_view() {
             "- Subagent Thread: `{subagent_uri}`"
         )))
         .stdout(predicate::str::contains("- Relation: `validated`"))
+        .stdout(predicate::str::contains(
+            "## Initial Prompt\n\nhello child\n",
+        ))
         .stdout(predicate::str::contains("## Lifecycle (Parent Thread)"))
         .stdout(predicate::str::contains("## Thread Excerpt (Child Thread)"));
 }
@@ -833,6 +2596,53 @@ fn gemini_outputs_markdown() {
         .stdout(predicate::str::contains("world"));
 }
 
+#[test]
+fn gemini_project_hash_picks_between_projects_with_the_same_session() {
+    let temp = setup_gemini_tree();
+    let project = "0c0d7b04c22749f3687ea60b66949fd32bcea2551d4349bf72346a9ccc9a9ba4";
+    let other = "f".repeat(64);
+    let other_path = temp.path().join(format!(
+        ".gemini/tmp/{other}/chats/session-2026-01-09T08-00-00-29d207db.json"
+    ));
+    fs::create_dir_all(other_path.parent().expect("parent")).expect("mkdir");
+    fs::write(
+        &other_path,
+        format!(
+            r#"{{"sessionId": "{GEMINI_SESSION_ID}", "projectHash": "{other}", "messages": [{{ "type": "user", "content": "elsewhere" }}]}}"#
+        ),
+    )
+    .expect("write");
+
+    for (target, expected, unexpected) in [
+        (
+            format!("{project}/{GEMINI_SESSION_ID}"),
+            "world",
+            "elsewhere",
+        ),
+        (format!("{project}/29d207db"), "world", "elsewhere"),
+        (format!("{other}/{GEMINI_SESSION_ID}"), "elsewhere", "world"),
+    ] {
+        let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+        cmd.env("GEMINI_CLI_HOME", temp.path())
+            .arg(format!("agents://gemini/{target}"))
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(expected))
+            .stdout(predicate::str::contains(unexpected).not())
+            .stderr(predicate::str::contains("multiple matches").not());
+    }
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("GEMINI_CLI_HOME", temp.path())
+        .arg(format!(
+            "agents://gemini/{}/{GEMINI_SESSION_ID}",
+            "a".repeat(64)
+        ))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("thread not found"));
+}
+
 #[test]
 fn gemini_head_outputs_subagent_discovery() {
     let temp = setup_gemini_subagent_tree();
@@ -855,6 +2665,28 @@ fn gemini_head_outputs_subagent_discovery() {
         .stdout(predicate::str::contains("warnings:"));
 }
 
+#[test]
+fn head_filters_warnings_by_severity() {
+    let temp = setup_gemini_subagent_tree();
+    let main_uri = agents_uri("gemini", GEMINI_SESSION_ID);
+    let head = |extra: &[&str]| {
+        let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+        cmd.env("GEMINI_CLI_HOME", temp.path())
+            .arg(&main_uri)
+            .arg("--head")
+            .args(extra)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("subagents:"))
+    };
+
+    head(&[])
+        .stdout(predicate::str::contains("  - severity: 'warn'"))
+        .stdout(predicate::str::contains("    code: 'child_missing'"));
+    head(&["--warnings", "error"]).stdout(predicate::str::contains("warnings:").not());
+    head(&["--quiet"]).stdout(predicate::str::contains("warnings:").not());
+}
+
 #[test]
 fn gemini_head_outputs_subagent_discovery_from_ndjson_logs() {
     let temp = setup_gemini_subagent_tree_with_ndjson_logs();
@@ -883,83 +2715,375 @@ fn gemini_subagent_outputs_markdown_view() {
     let subagent_uri = agents_child_uri("gemini", GEMINI_SESSION_ID, GEMINI_CHILD_SESSION_ID);
 
     let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
-    cmd.env("GEMINI_CLI_HOME", temp.path())
-        .arg(agents_gemini_subagent_uri())
+    cmd.env("GEMINI_CLI_HOME", temp.path())
+        .arg(agents_gemini_subagent_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("# Subagent Thread"))
+        .stdout(predicate::str::contains(format!(
+            "- Main Thread: `{main_uri}`"
+        )))
+        .stdout(predicate::str::contains(format!(
+            "- Subagent Thread: `{subagent_uri}`"
+        )))
+        .stdout(predicate::str::contains("## Thread Excerpt (Child Thread)"));
+}
+
+#[test]
+fn gemini_missing_subagent_outputs_not_found_markdown() {
+    let temp = setup_gemini_subagent_tree();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("GEMINI_CLI_HOME", temp.path())
+        .arg(gemini_missing_subagent_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("# Subagent Thread"))
+        .stdout(predicate::str::contains(
+            "- Status: `notFound` (`inferred`)",
+        ))
+        .stdout(predicate::str::contains(
+            "_No child thread messages found._",
+        ));
+}
+
+#[test]
+fn pi_outputs_markdown_from_latest_leaf() {
+    let temp = setup_pi_tree();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("PI_CODING_AGENT_DIR", temp.path().join("agent"))
+        .arg(pi_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("# Thread"))
+        .stdout(predicate::str::contains("## Timeline"))
+        .stdout(predicate::str::contains("root"))
+        .stdout(predicate::str::contains("branch two done"));
+}
+
+#[test]
+fn pi_entry_outputs_markdown_from_requested_leaf() {
+    let temp = setup_pi_tree();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("PI_CODING_AGENT_DIR", temp.path().join("agent"))
+        .arg(pi_entry_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("# Thread"))
+        .stdout(predicate::str::contains("branch one done"))
+        .stdout(predicate::str::contains("branch two done").not());
+}
+
+#[test]
+fn pi_all_branches_renders_the_whole_entry_tree() {
+    let temp = setup_pi_tree();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("PI_CODING_AGENT_DIR", temp.path().join("agent"))
+        .args(["--all-branches", &pi_uri()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("## All Branches"))
+        .stdout(predicate::str::contains("- **Branch 1/2**\n"))
+        .stdout(predicate::str::contains("  - **Assistant** `d1b2c3d4`"))
+        .stdout(predicate::str::contains("- **Branch 2/2** (latest)"))
+        .stdout(predicate::str::contains("branch two done"));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("PI_CODING_AGENT_DIR", temp.path().join("agent"))
+        .args(["--all-branches", &pi_entry_uri()])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--all-branches renders a whole Pi session",
+        ));
+}
+
+#[test]
+fn fork_pins_the_pi_leaf_for_later_prompts() {
+    let temp = setup_pi_tree();
+    let agent_dir = temp.path().join("agent");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("PI_CODING_AGENT_DIR", &agent_dir)
+        .arg("fork")
+        .arg(pi_uri())
+        .assert()
+        .success()
+        .stdout(format!("agents://pi/{PI_SESSION_ID}/f1b2c3d4\n"));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("PI_CODING_AGENT_DIR", &agent_dir)
+        .arg(pi_uri())
+        .arg("-d")
+        .arg("follow up")
+        .assert()
+        .success();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    let output = cmd
+        .env("PI_CODING_AGENT_DIR", &agent_dir)
+        .arg("fork")
+        .arg(format!("agents://pi/{PI_SESSION_ID}/f1b2c3d4"))
+        .arg("-d")
+        .arg("what if")
+        .output()
+        .expect("run");
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let mut lines = stderr.lines();
+    assert_eq!(
+        lines.next(),
+        Some(format!("forked: agents://pi/{PI_SESSION_ID}/f1b2c3d4").as_str())
+    );
+    let branch_uri = lines
+        .next()
+        .and_then(|line| line.strip_prefix("updated: "))
+        .expect("updated uri");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("PI_CODING_AGENT_DIR", &agent_dir)
+        .arg(branch_uri)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("branch two done"))
+        .stdout(predicate::str::contains("what if"))
+        .stdout(predicate::str::contains("follow up").not());
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("PI_CODING_AGENT_DIR", &agent_dir)
+        .arg("fork")
+        .arg("agents://claude/2823d1df-720a-4c31-ac55-ae8ba726721f")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "provider does not support fork: claude",
+        ));
+}
+
+#[test]
+fn cp_copies_a_codex_thread_into_another_home() {
+    let laptop = setup_codex_tree();
+    let server = tempdir().expect("server");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", laptop.path())
+        .arg("cp")
+        .arg(agents_codex_uri())
+        .arg(server.path())
+        .assert()
+        .success()
+        .stdout(format!("{}\n", agents_codex_uri()))
+        .stderr(predicate::str::contains("copied: "));
+    assert!(
+        server
+            .path()
+            .join(format!(
+                "sessions/2026/02/23/rollout-2026-02-23T04-48-50-{SESSION_ID}.jsonl"
+            ))
+            .is_file()
+    );
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", server.path())
+        .arg(agents_codex_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("world"));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", laptop.path())
+        .arg("cp")
+        .arg(agents_codex_uri())
+        .arg(server.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("already exists"));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", laptop.path())
+        .arg("cp")
+        .arg("--force")
+        .arg(agents_codex_uri())
+        .arg(server.path())
+        .assert()
+        .success();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", laptop.path())
+        .arg("cp")
+        .arg(agents_codex_uri())
+        .arg(laptop.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("is already in"));
+}
+
+#[test]
+fn cp_copies_every_rollout_of_a_resumed_codex_session() {
+    let laptop = setup_codex_tree();
+    let continued = format!("sessions/2026/02/24/rollout-2026-02-24T09-00-00-{SESSION_ID}.jsonl");
+    fs::create_dir_all(laptop.path().join("sessions/2026/02/24")).expect("mkdir");
+    fs::write(
+        laptop.path().join(&continued),
+        "{\"type\":\"response_item\",\"payload\":{\"type\":\"message\",\"role\":\"user\",\"content\":[{\"type\":\"input_text\",\"text\":\"continue\"}]}}\n",
+    )
+    .expect("write");
+    let server = tempdir().expect("server");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", laptop.path())
+        .arg("cp")
+        .arg(agents_codex_uri())
+        .arg(server.path())
+        .assert()
+        .success();
+    assert!(server.path().join(&continued).is_file());
+    assert!(
+        server
+            .path()
+            .join(format!(
+                "sessions/2026/02/23/rollout-2026-02-23T04-48-50-{SESSION_ID}.jsonl"
+            ))
+            .is_file()
+    );
+}
+
+#[test]
+fn pi_write_branches_from_requested_entry() {
+    let temp = setup_pi_tree();
+    let agent_dir = temp.path().join("agent");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    let output = cmd
+        .env("PI_CODING_AGENT_DIR", &agent_dir)
+        .arg(format!("agents://pi/{PI_SESSION_ID}/b1b2c3d4"))
+        .arg("-d")
+        .arg("branch three")
+        .output()
+        .expect("run");
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let branch_uri = stderr
+        .trim()
+        .strip_prefix("updated: ")
+        .expect("updated uri")
+        .to_string();
+    assert!(branch_uri.starts_with(&format!("agents://pi/{PI_SESSION_ID}/")));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("PI_CODING_AGENT_DIR", &agent_dir)
+        .arg(&branch_uri)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("root done"))
+        .stdout(predicate::str::contains("branch three"))
+        .stdout(predicate::str::contains("branch one").not())
+        .stdout(predicate::str::contains("branch two").not());
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("PI_CODING_AGENT_DIR", &agent_dir)
+        .arg(pi_uri())
+        .arg("-d")
+        .arg("follow up")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(format!(
+            "updated: agents://pi/{PI_SESSION_ID}/"
+        )));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("PI_CODING_AGENT_DIR", &agent_dir)
+        .arg(pi_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("branch three"))
+        .stdout(predicate::str::contains("follow up"));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("PI_CODING_AGENT_DIR", &agent_dir)
+        .arg(format!("agents://pi/{PI_SESSION_ID}/ffffffff"))
+        .arg("-d")
+        .arg("nowhere")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("entry not found"));
+}
+
+#[test]
+fn pi_head_outputs_entries() {
+    let temp = setup_pi_tree();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("PI_CODING_AGENT_DIR", temp.path().join("agent"))
+        .arg(pi_uri())
+        .arg("--head")
         .assert()
         .success()
-        .stdout(predicate::str::contains("# Subagent Thread"))
-        .stdout(predicate::str::contains(format!(
-            "- Main Thread: `{main_uri}`"
-        )))
+        .stdout(predicate::str::contains("mode: 'pi_entry_index'"))
+        .stdout(predicate::str::contains("entries:"))
         .stdout(predicate::str::contains(format!(
-            "- Subagent Thread: `{subagent_uri}`"
+            "uri: 'agents://pi/{PI_SESSION_ID}/a1b2c3d4'"
         )))
-        .stdout(predicate::str::contains("## Thread Excerpt (Child Thread)"));
+        .stdout(predicate::str::contains("is_leaf: true"));
 }
 
 #[test]
-fn gemini_missing_subagent_outputs_not_found_markdown() {
-    let temp = setup_gemini_subagent_tree();
+fn pi_diff_compares_branches_from_their_common_ancestor() {
+    let temp = setup_pi_tree();
 
     let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
-    cmd.env("GEMINI_CLI_HOME", temp.path())
-        .arg(gemini_missing_subagent_uri())
+    cmd.env("PI_CODING_AGENT_DIR", temp.path().join("agent"))
+        .arg("pi-diff")
+        .arg(format!("pi://{PI_SESSION_ID}/d1b2c3d4"))
+        .arg(format!("pi://{PI_SESSION_ID}/f1b2c3d4"))
         .assert()
         .success()
-        .stdout(predicate::str::contains("# Subagent Thread"))
+        .stdout(predicate::str::contains("common_ancestor: 'b1b2c3d4'"))
         .stdout(predicate::str::contains(
-            "- Status: `notFound` (`inferred`)",
+            "| 3 | **User** `c1b2c3d4`<br>branch one | **User** `e1b2c3d4`<br>branch two |",
         ))
         .stdout(predicate::str::contains(
-            "_No child thread messages found._",
-        ));
-}
-
-#[test]
-fn pi_outputs_markdown_from_latest_leaf() {
-    let temp = setup_pi_tree();
+            "| 4 | **Assistant** `d1b2c3d4`<br>branch one done | **Assistant** `f1b2c3d4`<br>branch two done |",
+        ))
+        .stdout(predicate::str::contains("root done").not());
 
     let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
     cmd.env("PI_CODING_AGENT_DIR", temp.path().join("agent"))
-        .arg(pi_uri())
+        .arg("pi-diff")
+        .arg(format!("pi://{PI_SESSION_ID}/d1b2c3d4"))
+        .arg(agents_codex_uri())
         .assert()
-        .success()
-        .stdout(predicate::str::contains("# Thread"))
-        .stdout(predicate::str::contains("## Timeline"))
-        .stdout(predicate::str::contains("root"))
-        .stdout(predicate::str::contains("branch two done"));
+        .failure()
+        .stderr(predicate::str::contains("pi-diff compares Pi branch URIs"));
 }
 
 #[test]
-fn pi_entry_outputs_markdown_from_requested_leaf() {
+fn pi_head_previews_follow_preview_options() {
     let temp = setup_pi_tree();
 
     let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
     cmd.env("PI_CODING_AGENT_DIR", temp.path().join("agent"))
-        .arg(pi_entry_uri())
+        .arg(pi_uri())
+        .arg("--head")
+        .args(["--preview-chars", "8"])
         .assert()
         .success()
-        .stdout(predicate::str::contains("# Thread"))
-        .stdout(predicate::str::contains("branch one done"))
-        .stdout(predicate::str::contains("branch two done").not());
-}
-
-#[test]
-fn pi_head_outputs_entries() {
-    let temp = setup_pi_tree();
+        .stdout(predicate::str::contains("role: 'assistant'"))
+        .stdout(predicate::str::contains("preview: 'branch …'"))
+        .stdout(predicate::str::contains("estimated_tokens: 4"))
+        .stdout(predicate::str::contains("branch one done").not());
 
     let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
     cmd.env("PI_CODING_AGENT_DIR", temp.path().join("agent"))
         .arg(pi_uri())
         .arg("--head")
+        .arg("--full-previews")
         .assert()
         .success()
-        .stdout(predicate::str::contains("mode: 'pi_entry_index'"))
-        .stdout(predicate::str::contains("entries:"))
-        .stdout(predicate::str::contains(format!(
-            "uri: 'agents://pi/{PI_SESSION_ID}/a1b2c3d4'"
-        )))
-        .stdout(predicate::str::contains("is_leaf: true"));
+        .stdout(predicate::str::contains("preview: 'branch one done'"));
 }
 
 #[test]
@@ -994,6 +3118,125 @@ fn pi_real_fixture_outputs_markdown() {
         .stdout(predicate::str::contains("## 2. Assistant"));
 }
 
+#[test]
+fn pi_real_fixture_head_includes_session_info() {
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("PI_CODING_AGENT_DIR", pi_real_fixture_root())
+        .arg("-I")
+        .arg(pi_real_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "model: 'gpt-5.3-codex'\ncwd: '/redacted/workspace/project'\nmode: 'pi_entry_index'",
+        ));
+}
+
+#[test]
+fn claude_head_includes_agents_from_resumed_sessions() {
+    let temp = tempdir().expect("tempdir");
+    let project = temp.path().join("projects/project-resumed");
+    let original_session_id = "0a1b2c3d-720a-4c31-ac55-ae8ba726721f";
+    fs::create_dir_all(&project).expect("mkdir");
+    fs::write(
+        project.join(format!("{original_session_id}.jsonl")),
+        format!(
+            "{{\"uuid\":\"msg-1\",\"parentUuid\":null,\"timestamp\":\"2026-02-22T00:00:00Z\",\"type\":\"user\",\"sessionId\":\"{original_session_id}\",\"message\":{{\"role\":\"user\",\"content\":\"first run\"}}}}\n"
+        ),
+    )
+    .expect("write original");
+    fs::write(
+        project.join(format!("{CLAUDE_SESSION_ID}.jsonl")),
+        format!(
+            "{{\"uuid\":\"msg-2\",\"parentUuid\":\"msg-1\",\"timestamp\":\"2026-02-23T00:00:00Z\",\"type\":\"user\",\"sessionId\":\"{CLAUDE_SESSION_ID}\",\"message\":{{\"role\":\"user\",\"content\":\"resumed\"}}}}\n"
+        ),
+    )
+    .expect("write resumed");
+    let subagents_dir = project.join(original_session_id).join("subagents");
+    fs::create_dir_all(&subagents_dir).expect("mkdir");
+    fs::write(
+        subagents_dir.join(format!("agent-{CLAUDE_AGENT_ID}.jsonl")),
+        format!(
+            "{{\"timestamp\":\"2026-02-22T00:00:10Z\",\"type\":\"assistant\",\"sessionId\":\"{original_session_id}\",\"isSidechain\":true,\"agentId\":\"{CLAUDE_AGENT_ID}\",\"message\":{{\"role\":\"assistant\",\"content\":\"old agent done\"}}}}\n"
+        ),
+    )
+    .expect("write agent");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CLAUDE_CONFIG_DIR", temp.path())
+        .env("CODEX_HOME", temp.path().join("missing-codex"))
+        .arg("--json")
+        .arg(agents_uri("claude", CLAUDE_SESSION_ID))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "\"agent_id\": \"{CLAUDE_AGENT_ID}\""
+        )))
+        .stdout(predicate::str::contains(format!(
+            "matches session {original_session_id}, which main thread {CLAUDE_SESSION_ID} was resumed from"
+        )));
+}
+
+#[test]
+fn claude_agent_resolves_by_subagent_type() {
+    let temp = setup_claude_subagent_tree();
+    let project = temp.path().join("projects/project-subagent");
+    let running_agent_id = "a9f00aa";
+    fs::write(
+        project.join(format!("{CLAUDE_SESSION_ID}.jsonl")),
+        format!(
+            "{{\"timestamp\":\"2026-02-23T00:00:00Z\",\"type\":\"assistant\",\"sessionId\":\"{CLAUDE_SESSION_ID}\",\"message\":{{\"role\":\"assistant\",\"content\":[{{\"type\":\"tool_use\",\"id\":\"toolu_1\",\"name\":\"Task\",\"input\":{{\"subagent_type\":\"code-reviewer\",\"prompt\":\"review it\"}}}},{{\"type\":\"tool_use\",\"id\":\"toolu_2\",\"name\":\"Task\",\"input\":{{\"subagent_type\":\"explorer\",\"prompt\":\"look around\"}}}}]}}}}\n{{\"timestamp\":\"2026-02-23T00:00:20Z\",\"type\":\"user\",\"sessionId\":\"{CLAUDE_SESSION_ID}\",\"toolUseResult\":{{\"status\":\"completed\",\"agentId\":\"{CLAUDE_AGENT_ID}\"}},\"message\":{{\"role\":\"user\",\"content\":[{{\"type\":\"tool_result\",\"tool_use_id\":\"toolu_1\",\"content\":\"ok\"}}]}}}}\n"
+        ),
+    )
+    .expect("write main");
+    fs::write(
+        project
+            .join(CLAUDE_SESSION_ID)
+            .join(format!("subagents/agent-{running_agent_id}.jsonl")),
+        format!(
+            "{{\"timestamp\":\"2026-02-23T00:00:12Z\",\"type\":\"user\",\"sessionId\":\"{CLAUDE_SESSION_ID}\",\"isSidechain\":true,\"agentId\":\"{running_agent_id}\",\"message\":{{\"role\":\"user\",\"content\":\"look around\"}}}}\n"
+        ),
+    )
+    .expect("write running agent");
+
+    let head = |uri: String| {
+        let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+        cmd.env("CLAUDE_CONFIG_DIR", temp.path())
+            .env("CODEX_HOME", temp.path().join("missing-codex"))
+            .arg("--head")
+            .arg(uri)
+            .assert()
+            .success()
+    };
+
+    head(agents_uri("claude", CLAUDE_SESSION_ID))
+        .stdout(predicate::str::contains("agent_name: 'code-reviewer'"))
+        .stdout(predicate::str::contains("agent_name: 'explorer'"));
+    head(agents_child_uri(
+        "claude",
+        CLAUDE_SESSION_ID,
+        "code-reviewer",
+    ))
+    .stdout(predicate::str::contains(format!(
+        "child_thread_id: '{CLAUDE_AGENT_ID}'"
+    )));
+    head(agents_child_uri("claude", CLAUDE_SESSION_ID, "explorer")).stdout(
+        predicate::str::contains(format!("child_thread_id: '{running_agent_id}'")),
+    );
+
+    // The Task input wins over the agent transcript's first message.
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CLAUDE_CONFIG_DIR", temp.path())
+        .env("CODEX_HOME", temp.path().join("missing-codex"))
+        .arg(agents_child_uri(
+            "claude",
+            CLAUDE_SESSION_ID,
+            "code-reviewer",
+        ))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("## Initial Prompt\n\nreview it\n"));
+}
+
 #[test]
 fn claude_subagent_outputs_markdown_view() {
     let temp = setup_claude_subagent_tree();
@@ -1016,6 +3259,93 @@ fn claude_subagent_outputs_markdown_view() {
         .stdout(predicate::str::contains("## Agent Status Summary"));
 }
 
+#[test]
+fn claude_project_flag_disambiguates_duplicate_sessions() {
+    let temp = tempdir().expect("tempdir");
+    for (project, text) in [
+        ("-Users-dev-app", "from app"),
+        ("-Users-dev-lib", "from lib"),
+    ] {
+        let thread = temp
+            .path()
+            .join(format!("projects/{project}/{CLAUDE_SESSION_ID}.jsonl"));
+        fs::create_dir_all(thread.parent().expect("parent")).expect("mkdir");
+        fs::write(
+            &thread,
+            format!(
+                "{{\"type\":\"user\",\"sessionId\":\"{CLAUDE_SESSION_ID}\",\"message\":{{\"role\":\"user\",\"content\":\"{text}\"}}}}\n"
+            ),
+        )
+        .expect("write thread");
+    }
+
+    let mut ambiguous = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    ambiguous
+        .env("CLAUDE_CONFIG_DIR", temp.path())
+        .arg(format!("agents://claude/{CLAUDE_SESSION_ID}"))
+        .arg("-I")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("project=-Users-dev-app"))
+        .stdout(predicate::str::contains("project=-Users-dev-lib"));
+
+    let mut scoped = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    scoped
+        .env("CLAUDE_CONFIG_DIR", temp.path())
+        .arg(format!("agents://claude/{CLAUDE_SESSION_ID}"))
+        .arg("--project")
+        .arg("-Users-dev-lib")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("from lib"))
+        .stdout(predicate::str::contains("from app").not())
+        .stdout(predicate::str::contains("multiple matches").not());
+}
+
+#[test]
+fn follow_streams_a_finished_claude_subagent() {
+    let temp = setup_claude_subagent_tree();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CLAUDE_CONFIG_DIR", temp.path())
+        .env("CODEX_HOME", temp.path().join("missing-codex"))
+        .arg("--follow")
+        .arg(agents_child_uri(
+            "claude",
+            CLAUDE_SESSION_ID,
+            CLAUDE_AGENT_ID,
+        ))
+        .assert()
+        .success()
+        .stdout("## User\n\nagent task\n\n## Assistant\n\nagent done\n\n")
+        .stderr("completed (inferred)\n");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CLAUDE_CONFIG_DIR", temp.path())
+        .env("CODEX_HOME", temp.path().join("missing-codex"))
+        .args(["--follow", "--porcelain"])
+        .arg(agents_child_uri(
+            "claude",
+            CLAUDE_SESSION_ID,
+            CLAUDE_AGENT_ID,
+        ))
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "\nsubagent: completed inferred\nstatus: ok\n",
+        ));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CLAUDE_CONFIG_DIR", temp.path())
+        .arg("--follow")
+        .arg(agents_uri("claude", CLAUDE_SESSION_ID))
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "follow requires a Claude subagent URI",
+        ));
+}
+
 #[test]
 fn claude_real_fixture_head_includes_subagents() {
     let fixture_root = claude_real_fixture_root();
@@ -1051,60 +3381,228 @@ fn claude_real_fixture_subagent_detail_outputs_markdown() {
 }
 
 #[test]
-fn gemini_real_fixture_outputs_markdown() {
-    let fixture_root = gemini_real_fixture_root();
-    assert!(fixture_root.exists(), "fixture root must exist");
+fn gemini_real_fixture_outputs_markdown() {
+    let fixture_root = gemini_real_fixture_root();
+    assert!(fixture_root.exists(), "fixture root must exist");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("GEMINI_CLI_HOME", fixture_root)
+        .arg(gemini_real_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("# Thread"))
+        .stdout(predicate::str::contains("## 1. User"));
+}
+
+#[test]
+fn opencode_real_fixture_outputs_markdown() {
+    let fixture_root = opencode_real_fixture_root();
+    assert!(fixture_root.exists(), "fixture root must exist");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("XDG_DATA_HOME", fixture_root)
+        .arg(opencode_real_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("# Thread"))
+        .stdout(predicate::str::contains("## 1. User"));
+}
+
+#[cfg(unix)]
+#[test]
+fn chat_sends_each_line_and_resumes_the_session() {
+    let temp = tempdir().expect("tempdir");
+    let calls = temp.path().join("calls.txt");
+    let mock = setup_mock_bins(&[(
+        "codex",
+        &format!(
+            r#"
+printf '%s|' "$@" >> '{}'
+echo >> '{}'
+echo '{{"type":"thread.started","thread_id":"11111111-1111-4111-8111-111111111111"}}'
+n=$(grep -c '^exec|' '{}')
+echo "{{\"type\":\"item.completed\",\"item\":{{\"type\":\"agent_message\",\"text\":\"reply $n\"}}}}"
+"#,
+            calls.display(),
+            calls.display(),
+            calls.display()
+        ),
+    )]);
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("PATH", path_with_mock(mock.path()))
+        .args(["chat", "agents://codex"])
+        .write_stdin("first\n\nsecond \\\nline\n/exit\nnever sent\n")
+        .assert()
+        .success()
+        .stdout("reply 1\nreply 2\n")
+        .stderr(predicate::str::contains(
+            "created: agents://codex/11111111-1111-4111-8111-111111111111",
+        ))
+        .stderr(predicate::str::contains(
+            "updated: agents://codex/11111111-1111-4111-8111-111111111111",
+        ));
+
+    assert_eq!(
+        fs::read_to_string(&calls).expect("calls"),
+        "exec|--json|first|\nexec|resume|--json|11111111-1111-4111-8111-111111111111|second \nline|\n"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn write_failure_reports_stderr_tail_and_logs_everything() {
+    let mock = setup_mock_bins(&[(
+        "codex",
+        r#"
+i=1
+while [ "$i" -le 60 ]; do
+  echo "codex diagnostic $i" >&2
+  i=$((i + 1))
+done
+exit 1
+"#,
+    )]);
+    let log = mock.path().join("logs/write.log");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("PATH", path_with_mock(mock.path()))
+        .arg("agents://codex")
+        .args(["-d", "hello", "--debug-write-log"])
+        .arg(&log)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("(exit code: Some(1))"))
+        .stderr(predicate::str::contains(
+            "[10 earlier stderr lines omitted]",
+        ))
+        .stderr(predicate::str::contains("codex diagnostic 11\n"))
+        .stderr(predicate::str::contains("codex diagnostic 60"))
+        .stderr(predicate::str::contains("codex diagnostic 10\n").not());
+
+    let logged = fs::read_to_string(&log).expect("read log");
+    assert!(logged.starts_with("=== "), "{logged}");
+    assert!(logged.contains("codex diagnostic 1\n"), "{logged}");
+    assert!(logged.contains("codex diagnostic 60\n"), "{logged}");
+    assert!(logged.ends_with("=== exit status: 1 ===\n"), "{logged}");
+}
+
+#[cfg(unix)]
+#[test]
+fn write_create_streams_output_and_prints_uri() {
+    let mock = setup_mock_bins(&[(
+        "codex",
+        r#"
+if [ "$1" = "exec" ] && [ "$2" = "--json" ]; then
+  echo '{"type":"thread.started","thread_id":"11111111-1111-4111-8111-111111111111"}'
+  echo '{"type":"item.completed","item":{"id":"item_1","type":"agent_message","text":"hello from create"}}'
+  exit 0
+fi
+echo "unexpected args: $*" >&2
+exit 7
+"#,
+    )]);
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("PATH", path_with_mock(mock.path()))
+        .arg("agents://codex")
+        .arg("-d")
+        .arg("hello")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("hello from create"))
+        .stderr(predicate::str::contains(
+            "created: agents://codex/11111111-1111-4111-8111-111111111111",
+        ));
+}
+
+#[cfg(unix)]
+#[test]
+fn print_uri_write_prints_only_the_uri() {
+    let mock = setup_mock_bins(&[(
+        "codex",
+        r#"
+echo '{"type":"thread.started","thread_id":"11111111-1111-4111-8111-111111111111"}'
+echo '{"type":"item.completed","item":{"id":"item_1","type":"agent_message","text":"hello from create"}}'
+"#,
+    )]);
+    let reply = mock.path().join("reply.md");
 
     let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
-    cmd.env("GEMINI_CLI_HOME", fixture_root)
-        .arg(gemini_real_uri())
+    cmd.env("PATH", path_with_mock(mock.path()))
+        .args(["agents://codex", "-d", "hello", "--print-uri"])
         .assert()
         .success()
-        .stdout(predicate::str::contains("# Thread"))
-        .stdout(predicate::str::contains("## 1. User"));
-}
-
-#[test]
-fn opencode_real_fixture_outputs_markdown() {
-    let fixture_root = opencode_real_fixture_root();
-    assert!(fixture_root.exists(), "fixture root must exist");
+        .stdout("agents://codex/11111111-1111-4111-8111-111111111111\n")
+        .stderr("");
 
     let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
-    cmd.env("XDG_DATA_HOME", fixture_root)
-        .arg(opencode_real_uri())
+    cmd.env("PATH", path_with_mock(mock.path()))
+        .args(["agents://codex", "-d", "hello", "--print-uri", "-o"])
+        .arg(&reply)
         .assert()
         .success()
-        .stdout(predicate::str::contains("# Thread"))
-        .stdout(predicate::str::contains("## 1. User"));
+        .stdout("agents://codex/11111111-1111-4111-8111-111111111111\n");
+    assert_eq!(
+        fs::read_to_string(&reply).expect("reply"),
+        "hello from create"
+    );
 }
 
 #[cfg(unix)]
 #[test]
-fn write_create_streams_output_and_prints_uri() {
+fn write_context_prepends_rendered_thread_to_prompt() {
+    let temp = setup_codex_tree();
+    let prompt_path = temp.path().join("prompt.txt");
     let mock = setup_mock_bins(&[(
         "codex",
-        r#"
+        &format!(
+            r#"
 if [ "$1" = "exec" ] && [ "$2" = "--json" ]; then
-  echo '{"type":"thread.started","thread_id":"11111111-1111-4111-8111-111111111111"}'
-  echo '{"type":"item.completed","item":{"id":"item_1","type":"agent_message","text":"hello from create"}}'
+  printf '%s' "$3" > '{}'
+  echo '{{"type":"thread.started","thread_id":"11111111-1111-4111-8111-111111111111"}}'
+  echo '{{"type":"item.completed","item":{{"id":"item_1","type":"agent_message","text":"continued"}}}}'
   exit 0
 fi
 echo "unexpected args: $*" >&2
 exit 7
 "#,
+            prompt_path.display()
+        ),
     )]);
 
     let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
     cmd.env("PATH", path_with_mock(mock.path()))
+        .env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
         .arg("agents://codex")
+        .arg("--context")
+        .arg(agents_codex_uri())
+        .arg("--context-tail")
+        .arg("1")
         .arg("-d")
-        .arg("hello")
+        .arg("keep going")
         .assert()
         .success()
-        .stdout(predicate::str::contains("hello from create"))
-        .stderr(predicate::str::contains(
-            "created: agents://codex/11111111-1111-4111-8111-111111111111",
-        ));
+        .stdout(predicate::str::contains("continued"));
+
+    let prompt = fs::read_to_string(prompt_path).expect("read prompt");
+    assert!(prompt.starts_with(&format!("# Context from {}", agents_codex_uri())));
+    assert!(prompt.contains("_Showing the last 1 of 2 messages._"));
+    assert!(prompt.contains("## Assistant\n\nworld"));
+    assert!(!prompt.contains("## User\n\nhello"));
+    assert!(prompt.ends_with("---\n\nkeep going"));
+}
+
+#[test]
+fn context_requires_write_mode() {
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.arg(agents_codex_uri())
+        .arg("--context")
+        .arg(agents_codex_uri())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--data"));
 }
 
 #[cfg(unix)]
@@ -1137,6 +3635,48 @@ exit 7
         ));
 }
 
+#[cfg(unix)]
+#[test]
+fn write_to_new_agent_segment_spawns_a_codex_subagent() {
+    let mock = setup_mock_bins(&[(
+        "codex",
+        r#"
+if [ "$1" = "exec" ] && [ "$2" = "resume" ] && [ "$3" = "--json" ]; then
+  case "$5" in
+    *spawn_agent*"review the parser") ;;
+    *) echo "unexpected prompt: $5" >&2; exit 7 ;;
+  esac
+  echo "{\"type\":\"thread.started\",\"thread_id\":\"$4\"}"
+  echo '{"type":"item.completed","item":{"id":"item_1","type":"collab_tool_call","tool":"spawn_agent","sender_thread_id":"22222222-2222-4222-8222-222222222222","receiver_thread_ids":["33333333-3333-4333-8333-333333333333"],"status":"completed"}}'
+  echo '{"type":"item.completed","item":{"id":"item_2","type":"agent_message","text":"spawned it"}}'
+  exit 0
+fi
+echo "unexpected args: $*" >&2
+exit 7
+"#,
+    )]);
+
+    Command::new(assert_cmd::cargo::cargo_bin!("xurl"))
+        .env("PATH", path_with_mock(mock.path()))
+        .arg("agents://codex/22222222-2222-4222-8222-222222222222/new")
+        .args(["-d", "review the parser"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("spawned it").not())
+        .stderr(predicate::str::contains(
+            "spawned: agents://codex/22222222-2222-4222-8222-222222222222/33333333-3333-4333-8333-333333333333",
+        ));
+
+    Command::new(assert_cmd::cargo::cargo_bin!("xurl"))
+        .arg(format!("agents://claude/{CLAUDE_SESSION_ID}/new"))
+        .args(["-d", "review the parser"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "provider does not support spawning subagents from write mode: claude",
+        ));
+}
+
 #[cfg(unix)]
 #[test]
 fn write_data_file_and_stdin_are_supported() {
@@ -1237,6 +3777,64 @@ fn write_command_not_found_has_hint() {
         .stderr(predicate::str::contains("hint: write mode needs Codex CLI"));
 }
 
+#[cfg(unix)]
+#[test]
+fn write_bin_flag_and_env_override_path_lookup() {
+    let mock = setup_mock_bins(&[(
+        "claude-nightly",
+        r#"
+echo '{"type":"system","subtype":"init","session_id":"aaaaaaaa-aaaa-4aaa-8aaa-aaaaaaaaaaaa"}'
+echo '{"type":"result","subtype":"success","session_id":"aaaaaaaa-aaaa-4aaa-8aaa-aaaaaaaaaaaa","result":"hello from nightly"}'
+"#,
+    )]);
+    let bin = mock.path().join("claude-nightly");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("PATH", "")
+        .env_remove("XURL_CLAUDE_BIN")
+        .arg("agents://claude")
+        .arg("--bin")
+        .arg(&bin)
+        .arg("-d")
+        .arg("hello")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("hello from nightly"));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("PATH", path_with_mock(mock.path()))
+        .env("XURL_CLAUDE_BIN", "claude-nightly")
+        .arg("agents://claude")
+        .arg("-d")
+        .arg("hello")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("hello from nightly"));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("PATH", mock.path())
+        .env("XURL_CLAUDE_BIN", "claude-missing")
+        .arg("agents://claude")
+        .arg("-d")
+        .arg("hello")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(format!(
+            "command not found: claude-missing (set by XURL_CLAUDE_BIN; searched PATH: {})",
+            mock.path().display()
+        )));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.arg("agents://claude")
+        .arg("--bin")
+        .arg(mock.path().join("missing"))
+        .arg("-d")
+        .arg("hello")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("(set by --bin; no such file)"));
+}
+
 #[cfg(unix)]
 #[test]
 fn write_unsupported_collection_provider_returns_error() {
@@ -1316,3 +3914,343 @@ exit 7
     let written = fs::read_to_string(output).expect("read output");
     assert_eq!(written, "file target");
 }
+
+#[test]
+fn extra_codex_roots_are_searched_after_codex_home() {
+    let home = tempdir().expect("tempdir");
+    let backup_a = setup_codex_tree();
+    let backup_b = setup_codex_tree();
+    let extra_roots = std::env::join_paths([backup_a.path(), backup_b.path()]).expect("join roots");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", home.path())
+        .env("XURL_CODEX_ROOTS", &extra_roots)
+        .arg(agents_codex_uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("## 1. User\n\nhello"))
+        .stdout(predicate::str::contains("code: 'storage_root'"))
+        .stdout(predicate::str::contains(format!(
+            "found in root 2 of 3: {}",
+            backup_a.path().display()
+        )))
+        .stdout(predicate::str::contains("code: 'storage_root_copy'"));
+}
+
+#[cfg(unix)]
+#[test]
+fn failed_write_leaves_the_output_file_untouched() {
+    let mock = setup_mock_bins(&[(
+        "codex",
+        r#"
+echo '{"type":"thread.started","thread_id":"11111111-1111-4111-8111-111111111111"}'
+echo '{"type":"item.completed","item":{"id":"item_1","type":"agent_message","text":"half a reply"}}'
+echo "codex crashed" >&2
+exit 1
+"#,
+    )]);
+    let output = mock.path().join("reply.md");
+    fs::write(&output, "previous reply\n").expect("write output");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("PATH", path_with_mock(mock.path()))
+        .arg("agents://codex")
+        .args(["-d", "hello", "-o"])
+        .arg(&output)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("codex crashed"));
+
+    assert_eq!(
+        fs::read_to_string(&output).expect("read output"),
+        "previous reply\n"
+    );
+    assert_eq!(
+        fs::read_dir(mock.path())
+            .expect("list")
+            .filter(|entry| entry
+                .as_ref()
+                .is_ok_and(|entry| entry.file_name().to_string_lossy().ends_with(".tmp")))
+            .count(),
+        0
+    );
+
+    let codex = setup_codex_tree();
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+    cmd.env("CODEX_HOME", codex.path())
+        .env("XURL_FSYNC", "1")
+        .arg(agents_codex_uri())
+        .arg("-o")
+        .arg(&output)
+        .assert()
+        .success();
+    assert!(
+        fs::read_to_string(&output)
+            .expect("read output")
+            .contains("## 1. User")
+    );
+}
+
+#[test]
+fn codex_subagent_detail_summarizes_child_tool_calls() {
+    let temp = setup_codex_subagent_tree();
+    let child_thread_path = temp.path().join(format!(
+        "sessions/2026/02/23/rollout-2026-02-23T04-49-10-{SUBAGENT_ID}.jsonl"
+    ));
+    let mut raw = fs::read_to_string(&child_thread_path).expect("read child");
+    raw.push_str(concat!(
+        r#"{"timestamp":"2026-02-23T00:00:13Z","type":"response_item","payload":{"type":"function_call","name":"shell","call_id":"call_ls","arguments":"{}"}}"#,
+        "\n",
+        r#"{"timestamp":"2026-02-23T00:00:14Z","type":"response_item","payload":{"type":"function_call_output","call_id":"call_ls","output":"{\"output\":\"\",\"metadata\":{\"exit_code\":2}}"}}"#,
+        "\n",
+    ));
+    fs::write(&child_thread_path, raw).expect("write child");
+
+    let run = |json: bool| {
+        let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+        cmd.env("CODEX_HOME", temp.path())
+            .env("CLAUDE_CONFIG_DIR", temp.path().join("missing-claude"))
+            .arg(codex_subagent_uri());
+        if json {
+            cmd.arg("--json");
+        }
+        cmd.assert().success()
+    };
+
+    run(false)
+        .stdout(predicate::str::contains("## Tools (Child Thread)"))
+        .stdout(predicate::str::contains(
+            "| `shell` | 1 | 1 | `2026-02-23T00:00:13Z` |",
+        ));
+    run(true).stdout(predicate::str::contains("\"name\": \"shell\""));
+}
+
+#[test]
+fn codex_child_thread_links_back_to_its_parent() {
+    let temp = setup_codex_subagent_tree();
+    let parent_block = format!(
+        "parent:\n  uri: 'agents://codex/{SESSION_ID}'\n  subagent_uri: 'agents://codex/{SESSION_ID}/{SUBAGENT_ID}'\n  spawned_at: '2026-02-23T00:00:10Z'\n"
+    );
+
+    Command::new(assert_cmd::cargo::cargo_bin!("xurl"))
+        .env("CODEX_HOME", temp.path())
+        .arg(format!("agents://codex/{SUBAGENT_ID}"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(parent_block.clone()))
+        .stdout(predicate::str::contains(format!(
+            "## Parent\n\n- Parent Thread: `agents://codex/{SESSION_ID}`\n- Subagent View: `agents://codex/{SESSION_ID}/{SUBAGENT_ID}`\n- Spawned At: `2026-02-23T00:00:10Z`\n\n## Timeline"
+        )));
+
+    Command::new(assert_cmd::cargo::cargo_bin!("xurl"))
+        .env("CODEX_HOME", temp.path())
+        .arg(format!("agents://codex/{SESSION_ID}"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("parent:").not())
+        .stdout(predicate::str::contains("## Parent").not());
+}
+
+#[test]
+fn trace_exports_subagents_as_child_spans() {
+    let temp = setup_codex_subagent_tree();
+
+    Command::new(assert_cmd::cargo::cargo_bin!("xurl"))
+        .env("CODEX_HOME", temp.path())
+        .args(["trace", &format!("agents://codex/{SESSION_ID}")])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"resourceSpans\""))
+        .stdout(predicate::str::contains("\"name\": \"codex session\""))
+        .stdout(predicate::str::contains(format!(
+            "\"name\": \"subagent {SUBAGENT_ID}\""
+        )))
+        .stdout(predicate::str::contains(format!(
+            "\"stringValue\": \"agents://codex/{SESSION_ID}/{SUBAGENT_ID}\""
+        )))
+        .stdout(predicate::str::contains("\"parentSpanId\""));
+
+    Command::new(assert_cmd::cargo::cargo_bin!("xurl"))
+        .env("CODEX_HOME", temp.path())
+        .args(["trace", &codex_subagent_uri()])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "trace only supports main thread URIs",
+        ));
+}
+
+#[test]
+fn template_renders_thread_and_subagent_views() {
+    let temp = setup_codex_tree();
+    let templates = tempdir().expect("tempdir");
+    let thread_template = templates.path().join("digest.md");
+    fs::write(
+        &thread_template,
+        "{{ kind }} {{ uri }}\n{% for entry in entries %}{{ entry.number }}. {{ entry.kind }}: {{ entry.text }}\n{% endfor %}",
+    )
+    .expect("write template");
+
+    Command::new(assert_cmd::cargo::cargo_bin!("xurl"))
+        .env("CODEX_HOME", temp.path())
+        .arg(agents_codex_uri())
+        .arg("--template")
+        .arg(&thread_template)
+        .args(["--only", "assistant"])
+        .assert()
+        .success()
+        .stdout(format!(
+            "thread agents://codex/{SESSION_ID}\n2. assistant: world\n"
+        ));
+
+    let amp = setup_amp_subagent_tree();
+    let status_template = templates.path().join("status.md");
+    fs::write(
+        &status_template,
+        "{% for agent in agents %}{{ agent.agent_id }}={{ agent.status }}\n{% endfor %}",
+    )
+    .expect("write template");
+    Command::new(assert_cmd::cargo::cargo_bin!("xurl"))
+        .env("XDG_DATA_HOME", amp.path())
+        .arg(agents_uri("amp", AMP_SESSION_ID))
+        .arg("--json")
+        .arg("--template")
+        .arg(&status_template)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(format!("{AMP_SUBAGENT_ID}=")));
+
+    Command::new(assert_cmd::cargo::cargo_bin!("xurl"))
+        .env("CODEX_HOME", temp.path())
+        .arg(agents_codex_uri())
+        .arg("--template")
+        .arg(&status_template)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("template error:"))
+        .stderr(predicate::str::contains("agents"));
+}
+
+#[test]
+fn format_csv_prints_one_row_per_timeline_entry() {
+    let temp = setup_codex_tree();
+
+    Command::new(assert_cmd::cargo::cargo_bin!("xurl"))
+        .env("CODEX_HOME", temp.path())
+        .arg(agents_codex_uri())
+        .args(["--format", "csv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "index,role,timestamp,chars,text\n1,user,",
+        ))
+        .stdout(predicate::str::contains(",5,world\n"))
+        .stdout(predicate::str::contains("## Timeline").not());
+
+    Command::new(assert_cmd::cargo::cargo_bin!("xurl"))
+        .env("CODEX_HOME", temp.path())
+        .arg(agents_codex_uri())
+        .args(["--format", "tsv", "--only", "assistant"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("2\tassistant\t"))
+        .stdout(predicate::str::contains("\tuser\t").not());
+
+    Command::new(assert_cmd::cargo::cargo_bin!("xurl"))
+        .env("CODEX_HOME", temp.path())
+        .arg(format!("{}#msg-1", agents_codex_uri()))
+        .args(["--format", "csv"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("drop the #msg fragment"));
+}
+
+#[test]
+fn format_json_html_and_text_render_the_timeline() {
+    let temp = setup_codex_tree();
+    let read = |format: &str| {
+        let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("xurl"));
+        cmd.env("CODEX_HOME", temp.path())
+            .arg(agents_codex_uri())
+            .args(["--format", format])
+            .assert()
+    };
+
+    read("json")
+        .success()
+        .stdout(predicate::str::contains("\"total_entries\": 2"))
+        .stdout(predicate::str::contains("\"text\": \"world\""));
+    read("html")
+        .success()
+        .stdout(predicate::str::starts_with("<!DOCTYPE html>"))
+        .stdout(predicate::str::contains(
+            "<h3>2. Assistant</h3>\n<pre>world</pre>",
+        ));
+    read("text")
+        .success()
+        .stdout(predicate::str::contains("2. Assistant\n\nworld\n"))
+        .stdout(predicate::str::contains("## ").not());
+}
+
+#[test]
+fn export_turns_a_codex_thread_into_a_claude_session() {
+    let temp = setup_codex_tree();
+    let claude_home = tempdir().expect("tempdir");
+
+    let assert = Command::new(assert_cmd::cargo::cargo_bin!("xurl"))
+        .env("CODEX_HOME", temp.path())
+        .env("CLAUDE_CONFIG_DIR", claude_home.path())
+        .args(["export", &agents_codex_uri(), "--cwd", "/work/app"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("agents://claude/"))
+        .stderr(predicate::str::contains("projects/-work-app/"))
+        .stderr(predicate::str::contains("claude --resume "));
+    let claude_uri = String::from_utf8_lossy(&assert.get_output().stdout)
+        .trim()
+        .to_string();
+
+    Command::new(assert_cmd::cargo::cargo_bin!("xurl"))
+        .env("CLAUDE_CONFIG_DIR", claude_home.path())
+        .arg(&claude_uri)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("## 1. User\n\nhello"))
+        .stdout(predicate::str::contains("## 2. Assistant\n\nworld"));
+
+    Command::new(assert_cmd::cargo::cargo_bin!("xurl"))
+        .env("CODEX_HOME", temp.path())
+        .args(["export", &format!("{}#msg-1", agents_codex_uri())])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "export only supports main thread URIs",
+        ));
+}
+
+#[test]
+fn export_turns_a_claude_thread_into_a_codex_rollout() {
+    let temp = setup_claude_subagent_tree();
+    let codex_home = tempdir().expect("tempdir");
+
+    let assert = Command::new(assert_cmd::cargo::cargo_bin!("xurl"))
+        .env("CLAUDE_CONFIG_DIR", temp.path())
+        .env("CODEX_HOME", codex_home.path())
+        .args(["export", "--format", "codex-rollout", "--cwd", "/work/app"])
+        .arg(format!("agents://claude/{CLAUDE_SESSION_ID}"))
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("agents://codex/"))
+        .stderr(predicate::str::contains("codex resume "));
+    let codex_uri = String::from_utf8_lossy(&assert.get_output().stdout)
+        .trim()
+        .to_string();
+
+    Command::new(assert_cmd::cargo::cargo_bin!("xurl"))
+        .env("CODEX_HOME", codex_home.path())
+        .arg(&codex_uri)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("cwd: '/work/app'"))
+        .stdout(predicate::str::contains("## 1. User\n\nroot thread"));
+}
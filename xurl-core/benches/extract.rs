@@ -0,0 +1,483 @@
+//! Resolve, extract, and render timings on large generated threads.
+//!
+//! Run with `cargo bench -p xurl-core --bench extract [-- <filter>]`, where
+//! the filter is a criterion benchmark id such as `codex/` or
+//! `amp/render`. Fixtures are generated into a temporary directory on every
+//! run: 100k-line Codex, Claude, and Pi files, ~50 MB Amp and Gemini chats,
+//! and a 20k-message OpenCode database.
+//!
+//! - `XURL_BENCH_SCALE`: multiplies fixture sizes (default 1.0; use 0.1 for a
+//!   quick pass).
+//! - `XURL_BENCH_SAMPLES`: samples per phase (default 10, criterion's
+//!   minimum).
+
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::hint::black_box;
+use std::path::Path;
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use rusqlite::{Connection, params};
+use serde_json::json;
+use xurl_core::render::{ExtractionPolicy, extract_messages};
+use xurl_core::{
+    ProviderKind, ProviderRoots, ReadOptions, ThreadUri, TimelineFormat, render_thread,
+    resolve_thread,
+};
+
+const CODEX_SESSION_ID: &str = "019c871c-b1f9-7f60-9c4f-87ed09f13592";
+const CLAUDE_SESSION_ID: &str = "2823d1df-720a-4c31-ac55-ae8ba726721f";
+const AMP_SESSION_ID: &str = "T-019c0797-c402-7389-bd80-d785c98df295";
+const GEMINI_SESSION_ID: &str = "29d207db-ca7e-40ba-87f7-e14c9de60613";
+const PI_SESSION_ID: &str = "12cb4c19-2774-4de4-a0d0-9fa32fbae29f";
+const OPENCODE_SESSION_ID: &str = "ses_7v2md9kx3c1p";
+
+const JSONL_LINES: usize = 100_000;
+const CHAT_BYTES: usize = 50 * 1024 * 1024;
+const OPENCODE_MESSAGES: usize = 20_000;
+
+struct Fixture {
+    name: &'static str,
+    uri: String,
+    bytes: u64,
+}
+
+fn bench_extract(c: &mut Criterion) {
+    let scale = env::var("XURL_BENCH_SCALE")
+        .ok()
+        .and_then(|value| value.parse::<f64>().ok())
+        .filter(|scale| *scale > 0.0)
+        .unwrap_or(1.0);
+
+    let temp = tempfile::tempdir().expect("tempdir");
+    let roots = bench_roots(temp.path());
+    let scaled = |count: usize| scaled_count(count, scale);
+
+    let generators: [&dyn Fn() -> Fixture; 6] = [
+        &|| write_codex(&roots, scaled(JSONL_LINES)),
+        &|| write_claude(&roots, scaled(JSONL_LINES)),
+        &|| write_amp(&roots, scaled(CHAT_BYTES)),
+        &|| write_gemini(&roots, scaled(CHAT_BYTES)),
+        &|| write_pi(&roots, scaled(JSONL_LINES)),
+        &|| write_opencode(&roots, scaled(OPENCODE_MESSAGES)),
+    ];
+
+    for generate in generators {
+        let fixture = generate();
+        bench_fixture(c, &fixture, &roots);
+    }
+}
+
+fn bench_config() -> Criterion {
+    let samples = env::var("XURL_BENCH_SAMPLES")
+        .ok()
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(10)
+        .max(10);
+    Criterion::default().sample_size(samples)
+}
+
+criterion_group! {
+    name = benches;
+    config = bench_config();
+    targets = bench_extract
+}
+criterion_main!(benches);
+
+fn bench_roots(base: &Path) -> ProviderRoots {
+    let mut roots = ProviderRoots::from_map(
+        ProviderKind::all()
+            .iter()
+            .map(|kind| (*kind, base.join(kind.to_string())))
+            .collect(),
+    );
+    roots.cache_root = base.join("cache");
+    roots
+}
+
+fn scaled_count(count: usize, scale: f64) -> usize {
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    let scaled = (count as f64 * scale).round() as usize;
+    scaled.max(1)
+}
+
+fn bench_fixture(c: &mut Criterion, fixture: &Fixture, roots: &ProviderRoots) {
+    let uri = ThreadUri::parse(&fixture.uri).expect("bench uri");
+    let resolved = resolve_thread(&uri, roots).expect("resolve bench thread");
+    let raw = fs::read_to_string(&resolved.path).expect("read bench thread");
+
+    println!(
+        "{} ({:.1} MB on disk, {:.1} MB read)",
+        fixture.name,
+        megabytes(fixture.bytes),
+        megabytes(raw.len() as u64)
+    );
+
+    let mut group = c.benchmark_group(fixture.name);
+    group.throughput(Throughput::Bytes(raw.len() as u64));
+    group.bench_function("resolve", |b| {
+        b.iter(|| black_box(resolve_thread(&uri, roots).expect("resolve")));
+    });
+    group.bench_function("extract", |b| {
+        b.iter(|| {
+            let raw = fs::read_to_string(&resolved.path).expect("read");
+            black_box(
+                extract_messages(
+                    uri.provider,
+                    &resolved.path,
+                    &raw,
+                    &mut ExtractionPolicy::default(),
+                )
+                .expect("extract"),
+            )
+        });
+    });
+    group.bench_function("render", |b| {
+        b.iter(|| {
+            black_box(
+                render_thread(
+                    &uri,
+                    &resolved,
+                    roots,
+                    &ReadOptions::default(),
+                    TimelineFormat::Markdown,
+                )
+                .expect("render"),
+            )
+        });
+    });
+    group.bench_function("end_to_end", |b| {
+        b.iter(|| {
+            let resolved = resolve_thread(&uri, roots).expect("resolve");
+            black_box(
+                render_thread(
+                    &uri,
+                    &resolved,
+                    roots,
+                    &ReadOptions::default(),
+                    TimelineFormat::Markdown,
+                )
+                .expect("render"),
+            )
+        });
+    });
+    group.finish();
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn megabytes(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+/// Deterministic prose of roughly 300 bytes, varied per index so nothing
+/// compresses into a trivial pattern.
+fn sentence(idx: usize) -> String {
+    const WORDS: &[&str] = &[
+        "parser",
+        "rollout",
+        "session",
+        "context",
+        "streaming",
+        "buffer",
+        "render",
+        "budget",
+        "handoff",
+        "subagent",
+        "timeline",
+        "warning",
+        "resolve",
+        "fixture",
+        "compact",
+        "index",
+    ];
+
+    let mut text = String::with_capacity(320);
+    let mut state = idx.wrapping_mul(2_654_435_761).wrapping_add(1);
+    while text.len() < 300 {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        text.push_str(WORDS[state % WORDS.len()]);
+        text.push(' ');
+    }
+    let _ = write!(text, "#{idx}");
+    text
+}
+
+fn write_lines(path: &Path, lines: impl Iterator<Item = String>) -> u64 {
+    fs::create_dir_all(path.parent().expect("parent")).expect("mkdir");
+    let mut output = String::new();
+    for line in lines {
+        output.push_str(&line);
+        output.push('\n');
+    }
+    fs::write(path, &output).expect("write fixture");
+    output.len() as u64
+}
+
+fn write_codex(roots: &ProviderRoots, lines: usize) -> Fixture {
+    let path = roots.codex_root.join(format!(
+        "sessions/2026/02/23/rollout-2026-02-23T04-48-50-{CODEX_SESSION_ID}.jsonl"
+    ));
+    let header = json!({
+        "type": "session_meta",
+        "timestamp": "2026-02-23T04:48:50.000Z",
+        "payload": { "id": CODEX_SESSION_ID, "cwd": "/tmp/bench" },
+    });
+    let records = (1..lines).map(|idx| {
+        let timestamp = format!("2026-02-23T05:{:02}:{:02}.000Z", idx / 60 % 60, idx % 60);
+        let payload = match idx % 5 {
+            0 => json!({
+                "type": "message",
+                "role": "user",
+                "content": [{ "type": "input_text", "text": sentence(idx) }],
+            }),
+            1 => json!({
+                "type": "function_call",
+                "name": "shell",
+                "call_id": format!("call_{idx}"),
+                "arguments": json!({ "command": ["rg", sentence(idx)] }).to_string(),
+            }),
+            2 => json!({
+                "type": "function_call_output",
+                "call_id": format!("call_{}", idx - 1),
+                "output": sentence(idx).repeat(3),
+            }),
+            3 => json!({ "type": "agent_message", "message": sentence(idx) }),
+            _ => json!({
+                "type": "message",
+                "role": "assistant",
+                "content": [{ "type": "output_text", "text": sentence(idx) }],
+            }),
+        };
+        let record_type = if idx % 5 == 3 {
+            "event_msg"
+        } else {
+            "response_item"
+        };
+        json!({ "type": record_type, "timestamp": timestamp, "payload": payload }).to_string()
+    });
+
+    let bytes = write_lines(&path, std::iter::once(header.to_string()).chain(records));
+    Fixture {
+        name: "codex",
+        uri: format!("agents://codex/{CODEX_SESSION_ID}"),
+        bytes,
+    }
+}
+
+fn write_claude(roots: &ProviderRoots, lines: usize) -> Fixture {
+    let path = roots
+        .claude_root
+        .join(format!("projects/-tmp-bench/{CLAUDE_SESSION_ID}.jsonl"));
+    let records = (0..lines).map(|idx| {
+        let (record_type, content) = match idx % 4 {
+            0 => ("user", json!(sentence(idx))),
+            1 => (
+                "assistant",
+                json!([
+                    { "type": "thinking", "thinking": sentence(idx) },
+                    { "type": "tool_use", "id": format!("toolu_{idx}"), "name": "Read",
+                      "input": { "file_path": "/tmp/bench/src/lib.rs" } },
+                ]),
+            ),
+            2 => (
+                "user",
+                json!([{ "type": "tool_result", "tool_use_id": format!("toolu_{}", idx - 1),
+                         "content": sentence(idx).repeat(3) }]),
+            ),
+            _ => (
+                "assistant",
+                json!([{ "type": "text", "text": sentence(idx) }]),
+            ),
+        };
+        json!({
+            "type": record_type,
+            "uuid": format!("uuid-{idx}"),
+            "sessionId": CLAUDE_SESSION_ID,
+            "timestamp": "2026-02-23T05:00:00.000Z",
+            "message": { "role": record_type, "content": content },
+        })
+        .to_string()
+    });
+
+    let bytes = write_lines(&path, records);
+    Fixture {
+        name: "claude",
+        uri: format!("agents://claude/{CLAUDE_SESSION_ID}"),
+        bytes,
+    }
+}
+
+/// Builds chat messages until their JSON reaches `target_bytes`.
+fn chat_messages(
+    target_bytes: usize,
+    mut message: impl FnMut(usize) -> serde_json::Value,
+) -> Vec<serde_json::Value> {
+    let mut messages = Vec::new();
+    let mut size = 0;
+    while size < target_bytes {
+        let value = message(messages.len());
+        size += value.to_string().len() + 1;
+        messages.push(value);
+    }
+    messages
+}
+
+fn write_amp(roots: &ProviderRoots, target_bytes: usize) -> Fixture {
+    let path = roots
+        .amp_root
+        .join(format!("threads/{AMP_SESSION_ID}.json"));
+    let messages = chat_messages(target_bytes, |idx| {
+        if idx % 2 == 0 {
+            json!({ "role": "user", "content": [{ "type": "text", "text": sentence(idx) }] })
+        } else {
+            json!({
+                "role": "assistant",
+                "content": [
+                    { "type": "thinking", "thinking": sentence(idx) },
+                    { "type": "text", "text": sentence(idx + 1) },
+                ],
+            })
+        }
+    });
+    let thread = json!({ "id": AMP_SESSION_ID, "messages": messages }).to_string();
+
+    fs::create_dir_all(path.parent().expect("parent")).expect("mkdir");
+    fs::write(&path, &thread).expect("write fixture");
+    Fixture {
+        name: "amp",
+        uri: format!("agents://amp/{AMP_SESSION_ID}"),
+        bytes: thread.len() as u64,
+    }
+}
+
+fn write_gemini(roots: &ProviderRoots, target_bytes: usize) -> Fixture {
+    let path = roots.gemini_root.join(
+        "tmp/0c0d7b04c22749f3687ea60b66949fd32bcea2551d4349bf72346a9ccc9a9ba4/chats/session-2026-01-08T11-55-29-29d207db.json",
+    );
+    let messages = chat_messages(target_bytes, |idx| {
+        let message_type = if idx % 2 == 0 { "user" } else { "gemini" };
+        json!({ "id": format!("msg-{idx}"), "type": message_type, "content": sentence(idx) })
+    });
+    let chat = json!({
+        "sessionId": GEMINI_SESSION_ID,
+        "projectHash": "0c0d7b04c22749f3687ea60b66949fd32bcea2551d4349bf72346a9ccc9a9ba4",
+        "startTime": "2026-01-08T11:55:12.379Z",
+        "lastUpdated": "2026-01-08T12:31:14.881Z",
+        "messages": messages,
+    })
+    .to_string();
+
+    fs::create_dir_all(path.parent().expect("parent")).expect("mkdir");
+    fs::write(&path, &chat).expect("write fixture");
+    Fixture {
+        name: "gemini",
+        uri: format!("agents://gemini/{GEMINI_SESSION_ID}"),
+        bytes: chat.len() as u64,
+    }
+}
+
+fn write_pi(roots: &ProviderRoots, lines: usize) -> Fixture {
+    let path = roots.pi_root.join(format!(
+        "sessions/--tmp-bench--/2026-02-23T13-00-12-780Z_{PI_SESSION_ID}.jsonl"
+    ));
+    let header = json!({
+        "type": "session",
+        "version": 3,
+        "id": PI_SESSION_ID,
+        "timestamp": "2026-02-23T13:00:12.780Z",
+        "cwd": "/tmp/bench",
+    });
+    let entries = (1..lines).map(|idx| {
+        let role = if idx % 2 == 1 { "user" } else { "assistant" };
+        let parent_id = (idx > 1).then(|| format!("{:08x}", idx - 1));
+        json!({
+            "type": "message",
+            "id": format!("{idx:08x}"),
+            "parentId": parent_id,
+            "timestamp": "2026-02-23T13:00:13.000Z",
+            "message": { "role": role, "content": [{ "type": "text", "text": sentence(idx) }] },
+        })
+        .to_string()
+    });
+
+    let bytes = write_lines(&path, std::iter::once(header.to_string()).chain(entries));
+    Fixture {
+        name: "pi",
+        uri: format!("agents://pi/{PI_SESSION_ID}"),
+        bytes,
+    }
+}
+
+fn write_opencode(roots: &ProviderRoots, messages: usize) -> Fixture {
+    fs::create_dir_all(&roots.opencode_root).expect("mkdir");
+    let path = roots.opencode_root.join("opencode.db");
+    let mut conn = Connection::open(&path).expect("open sqlite");
+    conn.execute_batch(
+        "
+        CREATE TABLE session (id TEXT PRIMARY KEY);
+        CREATE TABLE message (
+            id TEXT PRIMARY KEY,
+            session_id TEXT NOT NULL,
+            time_created INTEGER NOT NULL,
+            data TEXT NOT NULL
+        );
+        CREATE TABLE part (
+            id TEXT PRIMARY KEY,
+            message_id TEXT NOT NULL,
+            session_id TEXT NOT NULL,
+            time_created INTEGER NOT NULL,
+            data TEXT NOT NULL
+        );
+        ",
+    )
+    .expect("create schema");
+
+    let tx = conn.transaction().expect("transaction");
+    tx.execute(
+        "INSERT INTO session (id) VALUES (?1)",
+        [OPENCODE_SESSION_ID],
+    )
+    .expect("insert session");
+    for idx in 0..messages {
+        let role = if idx % 2 == 0 { "user" } else { "assistant" };
+        let created = i64::try_from(idx).unwrap_or(i64::MAX);
+        tx.execute(
+            "INSERT INTO message (id, session_id, time_created, data) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                format!("msg_{idx}"),
+                OPENCODE_SESSION_ID,
+                created,
+                json!({ "role": role, "time": { "created": created } }).to_string()
+            ],
+        )
+        .expect("insert message");
+        tx.execute(
+            "INSERT INTO part (id, message_id, session_id, time_created, data) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                format!("prt_{idx}"),
+                format!("msg_{idx}"),
+                OPENCODE_SESSION_ID,
+                created,
+                json!({ "type": "text", "text": sentence(idx) }).to_string()
+            ],
+        )
+        .expect("insert part");
+    }
+    tx.commit().expect("commit");
+    drop(conn);
+
+    Fixture {
+        name: "opencode",
+        uri: format!("agents://opencode/{OPENCODE_SESSION_ID}"),
+        bytes: fs::metadata(&path)
+            .map(|meta| meta.len())
+            .unwrap_or_default(),
+    }
+}
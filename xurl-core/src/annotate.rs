@@ -0,0 +1,211 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::atomic::write_atomic;
+use crate::error::{Result, XurlError};
+use crate::model::{AnnotateRequest, Annotation, ProviderKind};
+use crate::provider::ProviderRoots;
+use crate::render::{self, ReadOptions};
+use crate::service::{TIMELINE_LINES, read_thread_lines, resolve_thread};
+use crate::timestamp::format_rfc3339;
+use crate::uri::ThreadUri;
+
+const ANNOTATIONS_FILE: &str = "annotations.json";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AnnotationStore {
+    annotations: Vec<Annotation>,
+}
+
+/// Attaches a note to one timeline entry of a main thread. `req.message` is
+/// the entry number shown in the rendered timeline (`## 12. ...`).
+pub fn annotate_thread(
+    uri: &ThreadUri,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+    req: &AnnotateRequest,
+) -> Result<Annotation> {
+    if uri.agent_id.is_some() {
+        return Err(XurlError::InvalidMode(
+            "annotations only support main thread URIs: agents://<provider>/<session_id>"
+                .to_string(),
+        ));
+    }
+    if req.note.trim().is_empty() {
+        return Err(XurlError::InvalidMode(
+            "annotation note cannot be empty".to_string(),
+        ));
+    }
+
+    let resolved = resolve_thread(uri, roots)?;
+    let raw = read_thread_lines(&resolved, roots, options, TIMELINE_LINES)?;
+    let entries = render::count_timeline_entries(
+        uri,
+        &resolved.path,
+        &raw,
+        &mut options.extraction_policy(),
+    )?;
+    if req.message == 0 || req.message > entries {
+        return Err(XurlError::InvalidMode(format!(
+            "message {} is out of range; the timeline has {entries} entries",
+            req.message
+        )));
+    }
+
+    let path = store_path(roots);
+    let mut store = read_store(&path)?;
+    let annotation = Annotation {
+        provider: uri.provider.to_string(),
+        session_id: resolved.session_id,
+        message: req.message,
+        note: req.note.trim().to_string(),
+        created_at: format_rfc3339(Utc::now()),
+    };
+    store.annotations.push(annotation.clone());
+    write_store(&path, &store)?;
+
+    Ok(annotation)
+}
+
+/// Annotations recorded for one thread, ordered by message number and then
+/// by creation order.
+pub fn thread_annotations(
+    roots: &ProviderRoots,
+    provider: ProviderKind,
+    session_id: &str,
+) -> Result<Vec<Annotation>> {
+    let provider = provider.to_string();
+    let mut annotations = read_store(&store_path(roots))?
+        .annotations
+        .into_iter()
+        .filter(|annotation| annotation.provider == provider && annotation.session_id == session_id)
+        .collect::<Vec<_>>();
+    annotations.sort_by_key(|annotation| annotation.message);
+    Ok(annotations)
+}
+
+fn store_path(roots: &ProviderRoots) -> PathBuf {
+    roots.data_root.join(ANNOTATIONS_FILE)
+}
+
+fn read_store(path: &Path) -> Result<AnnotationStore> {
+    let raw = match fs::read(path) {
+        Ok(raw) => raw,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(AnnotationStore::default());
+        }
+        Err(source) => {
+            return Err(XurlError::Io {
+                path: path.to_path_buf(),
+                source,
+            });
+        }
+    };
+
+    serde_json::from_slice(&raw).map_err(|err| {
+        XurlError::Serialization(format!("invalid annotations in {}: {err}", path.display()))
+    })
+}
+
+fn write_store(path: &Path, store: &AnnotationStore) -> Result<()> {
+    let parent = path.parent().unwrap_or(path);
+    fs::create_dir_all(parent).map_err(|source| XurlError::Io {
+        path: parent.to_path_buf(),
+        source,
+    })?;
+    let mut encoded = serde_json::to_vec_pretty(store)
+        .map_err(|err| XurlError::Serialization(err.to_string()))?;
+    encoded.push(b'\n');
+
+    // An interrupted write must never truncate the store.
+    write_atomic(path, &encoded, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use crate::annotate::{annotate_thread, thread_annotations};
+    use crate::error::XurlError;
+    use crate::model::{AnnotateRequest, ProviderKind};
+    use crate::provider::{ProviderRoots, write_test_rollout};
+    use crate::render::ReadOptions;
+    use crate::uri::ThreadUri;
+
+    const SESSION_ID: &str = "019c871c-b1f9-7f60-9c4f-87ed09f13592";
+
+    const ROLLOUT: &str = concat!(
+        r#"{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"hello"}]}}"#,
+        "\n",
+        r#"{"type":"response_item","payload":{"type":"message","role":"assistant","content":[{"type":"output_text","text":"world"}]}}"#,
+        "\n",
+    );
+
+    fn request(message: usize, note: &str) -> AnnotateRequest {
+        AnnotateRequest {
+            message,
+            note: note.to_string(),
+        }
+    }
+
+    #[test]
+    fn annotations_round_trip_per_thread() {
+        let temp = tempdir().expect("tempdir");
+        let roots = ProviderRoots::for_test(temp.path());
+        write_test_rollout(&roots.codex_root, SESSION_ID, ROLLOUT);
+        let uri = ThreadUri::parse(&format!("agents://codex/{SESSION_ID}")).expect("uri");
+
+        annotate_thread(
+            &uri,
+            &roots,
+            &ReadOptions::default(),
+            &request(2, "wrong turn"),
+        )
+        .expect("annotate");
+        annotate_thread(
+            &uri,
+            &roots,
+            &ReadOptions::default(),
+            &request(1, "  good prompt \n"),
+        )
+        .expect("annotate");
+
+        let annotations =
+            thread_annotations(&roots, ProviderKind::Codex, SESSION_ID).expect("load");
+        assert_eq!(
+            annotations
+                .iter()
+                .map(|annotation| (annotation.message, annotation.note.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(1, "good prompt"), (2, "wrong turn")]
+        );
+        assert!(
+            thread_annotations(&roots, ProviderKind::Claude, SESSION_ID)
+                .expect("load")
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn rejects_messages_outside_the_timeline() {
+        let temp = tempdir().expect("tempdir");
+        let roots = ProviderRoots::for_test(temp.path());
+        write_test_rollout(&roots.codex_root, SESSION_ID, ROLLOUT);
+        let uri = ThreadUri::parse(&format!("agents://codex/{SESSION_ID}")).expect("uri");
+
+        for message in [0, 3] {
+            let err = annotate_thread(
+                &uri,
+                &roots,
+                &ReadOptions::default(),
+                &request(message, "note"),
+            )
+            .expect_err("must fail");
+            assert!(matches!(err, XurlError::InvalidMode(_)), "{err}");
+        }
+        assert!(!roots.data_root.join("annotations.json").exists());
+    }
+}
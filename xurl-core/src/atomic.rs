@@ -0,0 +1,212 @@
+//! Replacing output files without ever leaving them half written.
+//!
+//! Content goes to a staging file next to the target and is renamed over it
+//! once complete, so readers see either the old file or the new one. A
+//! failure before [`AtomicFile::commit`] removes the staging file and leaves
+//! the target untouched. Durable commits also fsync the file and its
+//! directory, so the new content survives a crash right after the rename.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::{Result, XurlError};
+
+#[derive(Debug)]
+pub struct AtomicFile {
+    path: PathBuf,
+    staging: PathBuf,
+    /// `None` once committed.
+    file: Option<File>,
+    durable: bool,
+}
+
+impl AtomicFile {
+    /// Starts replacing `path`. A symlink is followed, so the file it points
+    /// at is replaced rather than the link.
+    pub fn create(path: &Path) -> Result<Self> {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let staging = dir.join(format!(".{name}.{}.tmp", std::process::id()));
+
+        let file = File::create(&staging).map_err(|source| XurlError::Io {
+            path: staging.clone(),
+            source,
+        })?;
+        // Keep the mode of the file being replaced, e.g. a group-readable
+        // report.
+        if let Ok(metadata) = fs::metadata(&path) {
+            let _ = fs::set_permissions(&staging, metadata.permissions());
+        }
+
+        Ok(Self {
+            path,
+            staging,
+            file: Some(file),
+            durable: false,
+        })
+    }
+
+    /// Makes the file readable and writable by its owner only, whatever the
+    /// mode of the file being replaced. Call it before writing secrets.
+    pub fn private(self) -> Result<Self> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            fs::set_permissions(&self.staging, fs::Permissions::from_mode(0o600)).map_err(
+                |source| XurlError::Io {
+                    path: self.staging.clone(),
+                    source,
+                },
+            )?;
+        }
+        Ok(self)
+    }
+
+    /// Whether [`Self::commit`] fsyncs the file and its directory.
+    #[must_use]
+    pub fn durable(mut self, durable: bool) -> Self {
+        self.durable = durable;
+        self
+    }
+
+    /// Moves the written content into place.
+    pub fn commit(mut self) -> Result<()> {
+        let Some(mut file) = self.file.take() else {
+            return Ok(());
+        };
+        let staged = file.flush().and_then(|()| {
+            if self.durable {
+                file.sync_all()
+            } else {
+                Ok(())
+            }
+        });
+        drop(file);
+        if let Err(source) = staged.and_then(|()| fs::rename(&self.staging, &self.path)) {
+            let _ = fs::remove_file(&self.staging);
+            return Err(XurlError::Io {
+                path: self.path.clone(),
+                source,
+            });
+        }
+
+        if self.durable {
+            sync_parent(&self.path).map_err(|source| XurlError::Io {
+                path: self.path.clone(),
+                source,
+            })?;
+        }
+        Ok(())
+    }
+
+    fn file(&mut self) -> io::Result<&mut File> {
+        self.file
+            .as_mut()
+            .ok_or_else(|| io::Error::other("output file was already committed"))
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file()?.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file()?.flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if self.file.take().is_some() {
+            let _ = fs::remove_file(&self.staging);
+        }
+    }
+}
+
+/// Replaces `path` with `content` through an [`AtomicFile`]. Targets that
+/// exist but are not regular files, such as `/dev/stdout` or a FIFO, are
+/// written in place since they cannot be renamed over.
+pub fn write_atomic(path: &Path, content: &[u8], durable: bool) -> Result<()> {
+    if fs::metadata(path).is_ok_and(|metadata| !metadata.is_file()) {
+        return fs::write(path, content).map_err(|source| XurlError::Io {
+            path: path.to_path_buf(),
+            source,
+        });
+    }
+
+    let mut file = AtomicFile::create(path)?.durable(durable);
+    file.write_all(content).map_err(|source| XurlError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    file.commit()
+}
+
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => File::open(dir)?.sync_all(),
+        _ => File::open(".")?.sync_all(),
+    }
+}
+
+/// Windows cannot open directories as files; the rename itself is durable
+/// once the file was synced.
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Write;
+
+    use tempfile::tempdir;
+
+    use crate::atomic::{AtomicFile, write_atomic};
+
+    #[test]
+    fn target_is_replaced_only_on_commit() {
+        let temp = tempdir().expect("tempdir");
+        let path = temp.path().join("out.md");
+        fs::write(&path, "old\n").expect("write");
+
+        let mut file = AtomicFile::create(&path).expect("create");
+        file.write_all(b"partial").expect("write");
+        drop(file);
+        assert_eq!(fs::read_to_string(&path).expect("read"), "old\n");
+        assert_eq!(fs::read_dir(temp.path()).expect("list").count(), 1);
+
+        write_atomic(&path, b"new\n", true).expect("write atomic");
+        assert_eq!(fs::read_to_string(&path).expect("read"), "new\n");
+        assert_eq!(fs::read_dir(temp.path()).expect("list").count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_and_devices_keep_working() {
+        let temp = tempdir().expect("tempdir");
+        let target = temp.path().join("target.md");
+        let link = temp.path().join("link.md");
+        fs::write(&target, "old\n").expect("write");
+        std::os::unix::fs::symlink(&target, &link).expect("symlink");
+
+        write_atomic(&link, b"new\n", false).expect("write through link");
+        assert!(fs::symlink_metadata(&link).expect("stat").is_symlink());
+        assert_eq!(fs::read_to_string(&target).expect("read"), "new\n");
+
+        write_atomic(std::path::Path::new("/dev/null"), b"discarded", false)
+            .expect("write to device");
+    }
+}
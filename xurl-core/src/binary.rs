@@ -0,0 +1,308 @@
+//! Locating the agent CLIs that write mode runs.
+//!
+//! Each provider's binary is picked from, in order: an explicit override
+//! (the CLI's `--bin`), `XURL_<PROVIDER>_BIN`, and finally the provider's own
+//! command name. Bare names are searched on `PATH`; anything with a directory
+//! part is used as given.
+
+use std::collections::VecDeque;
+use std::env;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread::JoinHandle;
+
+use chrono::Utc;
+
+use crate::error::{Result, XurlError};
+use crate::model::ProviderKind;
+use crate::timestamp::format_rfc3339;
+
+/// Lines of a failed agent's stderr kept in `command failed` errors.
+pub const STDERR_TAIL_LINES: usize = 50;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BinaryLocator {
+    overrides: Vec<(ProviderKind, PathBuf)>,
+}
+
+/// Where the requested program name came from, for diagnostics.
+enum Origin {
+    Override,
+    Env(String),
+    Default,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Override => write!(f, "set by --bin"),
+            Self::Env(name) => write!(f, "set by {name}"),
+            Self::Default => write!(f, "default name"),
+        }
+    }
+}
+
+impl BinaryLocator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses `bin` for `provider`, ahead of `XURL_<PROVIDER>_BIN`.
+    #[must_use]
+    pub fn with_override(mut self, provider: ProviderKind, bin: impl Into<PathBuf>) -> Self {
+        self.overrides.retain(|(kind, _)| *kind != provider);
+        self.overrides.push((provider, bin.into()));
+        self
+    }
+
+    /// Environment variable that names `provider`'s binary, e.g. `XURL_CODEX_BIN`.
+    pub fn env_var(provider: ProviderKind) -> String {
+        format!("XURL_{}_BIN", provider.to_string().to_uppercase())
+    }
+
+    /// Resolves `provider`'s binary to an existing file.
+    pub fn locate(&self, provider: ProviderKind) -> Result<PathBuf> {
+        let (program, origin) = self.requested(provider);
+        tracing::debug!(%provider, program = %program.display(), %origin, "locating binary");
+        find_program(&program).ok_or_else(|| XurlError::CommandNotFound {
+            command: program.display().to_string(),
+            lookup: format!("{origin}; {}", describe_search(&program)),
+        })
+    }
+
+    fn requested(&self, provider: ProviderKind) -> (PathBuf, Origin) {
+        if let Some((_, bin)) = self.overrides.iter().find(|(kind, _)| *kind == provider) {
+            return (bin.clone(), Origin::Override);
+        }
+
+        let name = Self::env_var(provider);
+        if let Some(bin) = env::var_os(&name).filter(|bin| !bin.is_empty()) {
+            return (PathBuf::from(bin), Origin::Env(name));
+        }
+
+        (PathBuf::from(provider.to_string()), Origin::Default)
+    }
+}
+
+/// Finds `program` the way a shell would: paths with a directory part are
+/// checked directly, bare names are searched on `PATH`.
+pub fn find_program(program: &Path) -> Option<PathBuf> {
+    if has_directory(program) {
+        return program.is_file().then(|| program.to_path_buf());
+    }
+
+    search_dirs().into_iter().find_map(|dir| {
+        let candidate = dir.join(program);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        let suffix = env::consts::EXE_SUFFIX;
+        if suffix.is_empty() {
+            return None;
+        }
+        let mut with_suffix = OsString::from(candidate);
+        with_suffix.push(suffix);
+        let with_suffix = PathBuf::from(with_suffix);
+        with_suffix.is_file().then_some(with_suffix)
+    })
+}
+
+/// Says where [`find_program`] looked, for `command not found` errors.
+pub(crate) fn describe_search(program: &Path) -> String {
+    if has_directory(program) {
+        return "no such file".to_string();
+    }
+
+    let dirs = search_dirs();
+    if dirs.is_empty() {
+        return "PATH is empty".to_string();
+    }
+    let listed = dirs
+        .iter()
+        .map(|dir| dir.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("searched PATH: {listed}")
+}
+
+/// Starts `bin` with piped stdout/stderr and no stdin.
+pub(crate) fn spawn_piped(bin: &Path, args: &[&str]) -> Result<Child> {
+    tracing::debug!(bin = %bin.display(), ?args, "spawning");
+    Command::new(bin)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|source| {
+            if source.kind() == std::io::ErrorKind::NotFound {
+                XurlError::CommandNotFound {
+                    command: bin.display().to_string(),
+                    lookup: "found, but it could not be started".to_string(),
+                }
+            } else {
+                XurlError::Io {
+                    path: bin.to_path_buf(),
+                    source,
+                }
+            }
+        })
+}
+
+/// Drains a child's stderr on a background thread. The last
+/// [`STDERR_TAIL_LINES`] lines are kept for error messages; with a debug log,
+/// every line is also appended to that file between a header naming the
+/// command and a footer with its exit status.
+pub(crate) struct StderrCapture {
+    handle: JoinHandle<String>,
+    log: Option<File>,
+}
+
+impl StderrCapture {
+    pub(crate) fn start(child: &mut Child, command: &str, log: Option<&Path>) -> Result<Self> {
+        let stderr = child.stderr.take().ok_or_else(|| {
+            XurlError::WriteProtocol(format!("{command}: stderr pipe is unavailable"))
+        })?;
+
+        let (log, mut thread_log) = match log {
+            Some(path) => {
+                let (file, clone) = open_log(path, command)?;
+                (Some(file), Some(clone))
+            }
+            None => (None, None),
+        };
+
+        let handle = std::thread::spawn(move || {
+            let mut reader = BufReader::new(stderr);
+            let mut tail = VecDeque::with_capacity(STDERR_TAIL_LINES);
+            let mut omitted = 0usize;
+            let mut line = Vec::new();
+            while reader
+                .read_until(b'\n', &mut line)
+                .is_ok_and(|read| read > 0)
+            {
+                // The log is best effort; a full disk must not stall the agent.
+                if let Some(file) = thread_log.as_mut() {
+                    let _ = file.write_all(&line);
+                }
+                if tail.len() == STDERR_TAIL_LINES {
+                    tail.pop_front();
+                    omitted += 1;
+                }
+                tail.push_back(String::from_utf8_lossy(&line).trim_end().to_string());
+                line.clear();
+            }
+
+            let mut content = tail.into_iter().collect::<Vec<_>>().join("\n");
+            if omitted > 0 {
+                content = format!("[{omitted} earlier stderr lines omitted]\n{content}");
+            }
+            content
+        });
+
+        Ok(Self { handle, log })
+    }
+
+    /// Waits for stderr to close and returns its tail.
+    pub(crate) fn finish(self, status: &ExitStatus) -> String {
+        let tail = self.handle.join().unwrap_or_default();
+        if let Some(mut file) = self.log {
+            let _ = writeln!(file, "=== {status} ===");
+        }
+        tail.trim().to_string()
+    }
+}
+
+/// Opens `path` for appending, writes the header, and returns the file plus
+/// a handle for the reader thread.
+fn open_log(path: &Path, command: &str) -> Result<(File, File)> {
+    let io_error = |source| XurlError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent).map_err(io_error)?;
+    }
+    let mut file = File::options()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(io_error)?;
+    writeln!(file, "=== {} {command} ===", format_rfc3339(Utc::now())).map_err(io_error)?;
+    let clone = file.try_clone().map_err(io_error)?;
+    Ok((file, clone))
+}
+
+fn has_directory(program: &Path) -> bool {
+    program.components().count() > 1 || program.is_absolute()
+}
+
+fn search_dirs() -> Vec<PathBuf> {
+    env::var_os("PATH")
+        .map(|path| {
+            env::split_paths(&path)
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::binary::{BinaryLocator, find_program};
+    use crate::error::XurlError;
+    use crate::model::ProviderKind;
+
+    #[test]
+    fn env_var_names_follow_provider() {
+        assert_eq!(
+            BinaryLocator::env_var(ProviderKind::Codex),
+            "XURL_CODEX_BIN"
+        );
+        assert_eq!(
+            BinaryLocator::env_var(ProviderKind::Opencode),
+            "XURL_OPENCODE_BIN"
+        );
+    }
+
+    #[test]
+    fn override_paths_are_used_as_given() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let bin = temp.path().join("my-claude");
+        fs::write(&bin, "").expect("write");
+
+        let locator = BinaryLocator::new().with_override(ProviderKind::Claude, &bin);
+        assert_eq!(
+            locator.locate(ProviderKind::Claude).expect("locate"),
+            bin.clone()
+        );
+        assert_eq!(find_program(&bin), Some(bin));
+    }
+
+    #[test]
+    fn missing_override_reports_origin() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let bin = temp.path().join("missing-codex");
+
+        let err = BinaryLocator::new()
+            .with_override(ProviderKind::Codex, &bin)
+            .locate(ProviderKind::Codex)
+            .expect_err("must fail");
+        match err {
+            XurlError::CommandNotFound { command, lookup } => {
+                assert_eq!(command, bin.display().to_string());
+                assert_eq!(lookup, "set by --bin; no such file");
+            }
+            other => panic!("unexpected error: {other}"),
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Compressed thread files.
+//!
+//! Archived Codex rollouts are often gzipped to save space
+//! (`rollout-<ts>-<id>.jsonl.gz`). Readers go through [`open_thread_file`]
+//! so they see the decompressed lines either way. Zstandard files
+//! (`.jsonl.zst`) are decoded when the `zstd` feature is on; without it they
+//! are still recognized, and reading one fails with a message saying so
+//! instead of parsing compressed bytes as JSON.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use flate2::read::MultiGzDecoder;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// The compression of a file named `name`, from its extension.
+    pub fn of_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        if name.ends_with(".gz") {
+            Some(Self::Gzip)
+        } else if name.ends_with(".zst") {
+            Some(Self::Zstd)
+        } else {
+            None
+        }
+    }
+
+    pub fn of_path(path: &Path) -> Option<Self> {
+        Self::of_name(path.file_name()?.to_str()?)
+    }
+
+    fn extension_len(self) -> usize {
+        match self {
+            Self::Gzip => ".gz".len(),
+            Self::Zstd => ".zst".len(),
+        }
+    }
+}
+
+/// `name` without a compression extension: `a.jsonl.gz` becomes `a.jsonl`.
+pub fn strip_compression(name: &str) -> &str {
+    match Compression::of_name(name) {
+        Some(compression) => &name[..name.len() - compression.extension_len()],
+        None => name,
+    }
+}
+
+/// Opens the thread file at `path`, decompressing it when its extension says
+/// it is compressed.
+pub fn open_thread_file(path: &Path) -> io::Result<Box<dyn Read>> {
+    match Compression::of_path(path) {
+        None => Ok(Box::new(File::open(path)?)),
+        Some(Compression::Gzip) => Ok(Box::new(MultiGzDecoder::new(File::open(path)?))),
+        #[cfg(feature = "zstd")]
+        Some(Compression::Zstd) => Ok(Box::new(zstd::stream::read::Decoder::new(File::open(
+            path,
+        )?)?)),
+        #[cfg(not(feature = "zstd"))]
+        Some(Compression::Zstd) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "zstd-compressed thread files are not supported by this build; decompress with `zstd -d`",
+        )),
+    }
+}
+
+/// Reads the thread file at `path` whole, decompressed.
+pub fn read_thread_file(path: &Path) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    open_thread_file(path)?.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Write;
+
+    use flate2::Compression as Level;
+    use flate2::write::GzEncoder;
+    use tempfile::tempdir;
+
+    use crate::compression::{Compression, read_thread_file, strip_compression};
+
+    #[test]
+    fn reads_gzip_and_plain_files_alike() {
+        let temp = tempdir().expect("tempdir");
+        let plain = temp.path().join("thread.jsonl");
+        let gzipped = temp.path().join("thread.jsonl.GZ");
+        fs::write(&plain, "{}\n").expect("write plain");
+        let mut encoder = GzEncoder::new(Vec::new(), Level::default());
+        encoder.write_all(b"{}\n").expect("compress");
+        fs::write(&gzipped, encoder.finish().expect("finish")).expect("write gzip");
+
+        assert_eq!(read_thread_file(&plain).expect("plain"), b"{}\n");
+        assert_eq!(read_thread_file(&gzipped).expect("gzip"), b"{}\n");
+        assert_eq!(strip_compression("thread.jsonl.GZ"), "thread.jsonl");
+        assert_eq!(strip_compression("thread.jsonl"), "thread.jsonl");
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    #[test]
+    fn zstd_files_fail_without_the_feature() {
+        let temp = tempdir().expect("tempdir");
+        let zstd = temp.path().join("thread.jsonl.zst");
+        fs::write(&zstd, b"\x28\xb5\x2f\xfd").expect("write zstd");
+        assert_eq!(Compression::of_path(&zstd), Some(Compression::Zstd));
+        let err = read_thread_file(&zstd).expect_err("no zstd decoder");
+        assert!(err.to_string().contains("zstd -d"));
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn reads_zstd_files_round_trip() {
+        let temp = tempdir().expect("tempdir");
+        let zstd = temp.path().join("thread.jsonl.zst");
+        let lines = b"{\"type\":\"session_meta\"}\n{}\n";
+        fs::write(&zstd, zstd::encode_all(&lines[..], 0).expect("compress")).expect("write zstd");
+
+        assert_eq!(Compression::of_path(&zstd), Some(Compression::Zstd));
+        assert_eq!(read_thread_file(&zstd).expect("zstd"), lines);
+        assert_eq!(strip_compression("thread.jsonl.zst"), "thread.jsonl");
+    }
+}
@@ -0,0 +1,273 @@
+//! Copying threads between roots of one provider (`xurl cp`), e.g. from a
+//! laptop's Codex home into a home synced to a server.
+//!
+//! The thread keeps its path relative to the root it was found in, so the
+//! provider finds it in the destination the way it found it in the source.
+//! Files the thread keeps beside its transcript come along:
+//! - a thread stored as a directory of its own (Amp's `threads/<id>/`,
+//!   Copilot's `session-state/<id>/`) is copied whole;
+//! - a `<id>/` directory next to the transcript (Claude's subagent and tool
+//!   result files) is copied with it;
+//! - every rollout of a Codex session that was resumed is copied.
+//!
+//! Codex rollouts are also registered in the destination's sqlite thread
+//! index, when it has one.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use serde_json::Value;
+use walkdir::WalkDir;
+
+use crate::compression::read_thread_file;
+use crate::error::{Result, XurlError};
+use crate::import::{codex_session_meta, transcript_started_at};
+use crate::model::{CopyRequest, CopyResult, ProviderKind, Warning};
+use crate::provider::ProviderRoots;
+use crate::provider::codex::CodexProvider;
+use crate::service::{resolve_thread_files, thread_files};
+use crate::uri::ThreadUri;
+
+/// Copies the thread of `uri` into `req.destination`, another root of the
+/// same provider.
+pub fn copy_thread(
+    uri: &ThreadUri,
+    roots: &ProviderRoots,
+    req: &CopyRequest,
+) -> Result<CopyResult> {
+    if matches!(uri.provider, ProviderKind::Opencode | ProviderKind::File) {
+        return Err(XurlError::UnsupportedProviderCopy(uri.provider.to_string()));
+    }
+    if uri.agent_id.is_some() || uri.fragment.is_some() {
+        return Err(XurlError::InvalidMode(
+            "cp copies whole threads: agents://<provider>/<session_id>".to_string(),
+        ));
+    }
+
+    let resolved = resolve_thread_files(uri, roots)?;
+    // A resumed Codex session brings every one of its rollouts.
+    let transcripts = thread_files(&resolved);
+    let mut warnings = resolved.metadata.warnings;
+    // Where each file of the thread goes: its path relative to the root it
+    // was found in, under the destination.
+    let destination_of = |path: &Path| {
+        let source_root = roots
+            .roots_for(uri.provider)
+            .into_iter()
+            .find(|root| path.starts_with(root))
+            .ok_or_else(|| {
+                XurlError::InvalidMode(format!(
+                    "{} was not read from a {} root: {}",
+                    uri.as_agents_string(),
+                    uri.provider,
+                    path.display()
+                ))
+            })?;
+        if same_dir(&source_root, &req.destination) {
+            return Err(XurlError::InvalidMode(format!(
+                "{} is already in {}",
+                uri.as_agents_string(),
+                req.destination.display()
+            )));
+        }
+        let relative = path.strip_prefix(&source_root).unwrap_or(path);
+        Ok((source_root.clone(), req.destination.join(relative)))
+    };
+
+    let mut copies = Vec::new();
+    for transcript in &transcripts {
+        let (source_root, target) = destination_of(transcript)?;
+        if target.exists() && !req.force {
+            return Err(XurlError::ThreadAlreadyExists {
+                provider: uri.provider.to_string(),
+                session_id: resolved.session_id,
+                path: target,
+            });
+        }
+
+        // The thread's own directory, or the transcript plus its `<id>/`.
+        let parent = transcript.parent().unwrap_or(&source_root);
+        if parent != source_root && parent.file_name() == Some(resolved.session_id.as_ref()) {
+            copies.push((parent.to_path_buf(), destination_of(parent)?.1));
+        } else {
+            copies.push((transcript.clone(), target));
+            let companion = parent.join(&resolved.session_id);
+            if companion.is_dir() {
+                let target = destination_of(&companion)?.1;
+                copies.push((companion, target));
+            }
+        }
+    }
+    let path = destination_of(&resolved.path)?.1;
+
+    let mut files = 0;
+    for (source, target) in &copies {
+        files += copy_tree(source, target)?;
+    }
+
+    let registered_in = if uri.provider == ProviderKind::Codex {
+        register_codex_copy(&resolved.session_id, &path, &req.destination, &mut warnings)
+    } else {
+        None
+    };
+
+    Ok(CopyResult {
+        provider: uri.provider,
+        session_id: resolved.session_id,
+        source: resolved.path,
+        path,
+        files,
+        registered_in,
+        warnings,
+    })
+}
+
+/// Copies the file or directory `source` to `destination`, replacing files
+/// already there, and returns how many files were copied.
+fn copy_tree(source: &Path, destination: &Path) -> Result<usize> {
+    let mut files = 0;
+    for entry in WalkDir::new(source).follow_links(false) {
+        let entry = entry.map_err(|err| {
+            let path = err.path().unwrap_or(source).to_path_buf();
+            XurlError::io(&path, err.into())
+        })?;
+        let target = match entry.path().strip_prefix(source) {
+            Ok(relative) if !relative.as_os_str().is_empty() => destination.join(relative),
+            _ => destination.to_path_buf(),
+        };
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target).map_err(|source| XurlError::io(&target, source))?;
+            continue;
+        }
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|source| XurlError::io(parent, source))?;
+        }
+        fs::copy(entry.path(), &target).map_err(|source| XurlError::io(&target, source))?;
+        files += 1;
+    }
+    Ok(files)
+}
+
+fn register_codex_copy(
+    session_id: &str,
+    path: &Path,
+    destination: &Path,
+    warnings: &mut Vec<Warning>,
+) -> Option<PathBuf> {
+    let content = read_thread_file(path)
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+        .unwrap_or_default();
+    let started_at = transcript_started_at(ProviderKind::Codex, &content).unwrap_or_else(Utc::now);
+    let cwd = codex_session_meta(&content).and_then(|payload| {
+        payload
+            .get("cwd")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    });
+    match CodexProvider::new(destination).register_rollout(
+        session_id,
+        path,
+        started_at.timestamp(),
+        cwd.as_deref(),
+    ) {
+        Ok(db_path) => db_path,
+        Err(err) => {
+            warnings.push(Warning::warn(
+                "index_update_failed",
+                format!("copied rollout was not registered in the sqlite thread index: {err}"),
+            ));
+            None
+        }
+    }
+}
+
+/// Whether `left` and `right` name the same directory, comparing canonical
+/// paths when both exist.
+fn same_dir(left: &Path, right: &Path) -> bool {
+    match (left.canonicalize(), right.canonicalize()) {
+        (Ok(left), Ok(right)) => left == right,
+        _ => left == right,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use crate::copy::copy_thread;
+    use crate::error::XurlError;
+    use crate::model::{CopyRequest, ProviderKind};
+    use crate::provider::ProviderRoots;
+    use crate::service::resolve_thread;
+    use crate::uri::ThreadUri;
+
+    const SESSION_ID: &str = "2823d1df-720a-4c31-ac55-ae8ba726721f";
+
+    #[test]
+    fn claude_copy_keeps_layout_and_subagent_files() {
+        let laptop = tempdir().expect("laptop");
+        let server = tempdir().expect("server");
+        let project = laptop.path().join("projects/-work-app");
+        fs::create_dir_all(project.join(SESSION_ID).join("subagents")).expect("mkdir");
+        fs::write(
+            project.join(format!("{SESSION_ID}.jsonl")),
+            format!(
+                r#"{{"type":"user","sessionId":"{SESSION_ID}","message":{{"role":"user","content":"hello"}}}}"#
+            ) + "\n",
+        )
+        .expect("write");
+        fs::write(
+            project.join(SESSION_ID).join("subagents/agent-a1.jsonl"),
+            "{}\n",
+        )
+        .expect("write subagent");
+
+        let roots = ProviderRoots::from_map(HashMap::from([(
+            ProviderKind::Claude,
+            laptop.path().to_path_buf(),
+        )]));
+        let uri = ThreadUri::parse(&format!("agents://claude/{SESSION_ID}")).expect("uri");
+        let req = CopyRequest {
+            destination: server.path().to_path_buf(),
+            force: false,
+        };
+
+        let copy = copy_thread(&uri, &roots, &req).expect("copy");
+        assert_eq!(copy.files, 2);
+        assert_eq!(
+            copy.path,
+            server
+                .path()
+                .join(format!("projects/-work-app/{SESSION_ID}.jsonl"))
+        );
+        assert!(
+            server
+                .path()
+                .join(format!(
+                    "projects/-work-app/{SESSION_ID}/subagents/agent-a1.jsonl"
+                ))
+                .is_file()
+        );
+
+        let on_server = ProviderRoots::from_map(HashMap::from([(
+            ProviderKind::Claude,
+            server.path().to_path_buf(),
+        )]));
+        assert_eq!(
+            resolve_thread(&uri, &on_server).expect("resolve copy").path,
+            copy.path
+        );
+        assert!(matches!(
+            copy_thread(&uri, &roots, &req),
+            Err(XurlError::ThreadAlreadyExists { .. })
+        ));
+        assert!(copy_thread(&uri, &roots, &CopyRequest { force: true, ..req }).is_ok());
+    }
+}
@@ -0,0 +1,311 @@
+//! Thread fingerprints and duplicate detection (`xurl dedupe`).
+//!
+//! A fingerprint hashes a thread's messages after normalizing them (role plus
+//! whitespace-collapsed text), so copies of a rollout that differ only in
+//! file name, session id, timestamps, or tool noise share one fingerprint.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::error::Result;
+use crate::model::{
+    DedupeReport, DuplicateGroup, DuplicateThread, ProviderKind, ResolvedThread, ThreadMessage,
+    Warning,
+};
+use crate::provider::ProviderRoots;
+use crate::render::{self, ExtractionPolicy, ReadOptions};
+use crate::service::{
+    TIMELINE_LINES, agents_thread_uri, in_project_scope, provider_for, read_thread_lines,
+    resolve_listing,
+};
+use crate::timestamp::format_rfc3339;
+
+const FNV_OFFSET: u128 = 0x6c62_272e_07bb_0142_62b8_2175_6295_c58d;
+const FNV_PRIME: u128 = 0x0000_0000_0100_0000_0000_0000_0000_013b;
+
+/// Stable 128-bit FNV-1a hash of the normalized messages, as 32 hex digits.
+/// Messages without text are skipped.
+pub fn thread_fingerprint(messages: &[ThreadMessage]) -> String {
+    let mut hash = FNV_OFFSET;
+    let mut feed = |bytes: &[u8]| {
+        for byte in bytes {
+            hash ^= u128::from(*byte);
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    };
+
+    for message in messages {
+        let mut words = message.text.split_whitespace().peekable();
+        if words.peek().is_none() {
+            continue;
+        }
+        feed(message.role.to_string().as_bytes());
+        feed(b":");
+        for (index, word) in words.enumerate() {
+            if index > 0 {
+                feed(b" ");
+            }
+            feed(word.as_bytes());
+        }
+        // Record separator, so message boundaries are part of the hash.
+        feed(&[0x1e]);
+    }
+
+    format!("{hash:032x}")
+}
+
+/// Fingerprint of a resolved thread, read the way its timeline is, with the
+/// number of messages that went into it.
+pub fn fingerprint_thread(
+    resolved: &ResolvedThread,
+    roots: &ProviderRoots,
+) -> Result<(String, usize)> {
+    let raw = read_thread_lines(resolved, roots, &ReadOptions::default(), TIMELINE_LINES)?;
+    let messages = render::extract_messages(
+        resolved.provider,
+        &resolved.path,
+        &raw,
+        &mut ExtractionPolicy::default(),
+    )?;
+    let counted = messages
+        .iter()
+        .filter(|message| !message.text.trim().is_empty())
+        .count();
+    Ok((thread_fingerprint(&messages), counted))
+}
+
+/// Groups `provider`'s main threads (inside `roots.project_dir` when set) by
+/// fingerprint and reports every group with more than one thread. Threads
+/// without messages are not grouped, and threads that cannot be read are
+/// reported as warnings.
+pub fn find_duplicate_threads(
+    provider: ProviderKind,
+    roots: &ProviderRoots,
+) -> Result<DedupeReport> {
+    let mut listings = provider_for(provider, roots).list_threads()?;
+    listings.retain(|listing| in_project_scope(listing, roots));
+    let mut warnings = Vec::new();
+    let mut by_fingerprint = BTreeMap::<String, (usize, Vec<DuplicateThread>)>::new();
+    let scanned = listings.len();
+
+    for listing in listings {
+        let fingerprint = resolve_listing(&listing, roots)
+            .and_then(|resolved| fingerprint_thread(&resolved, roots));
+        let (fingerprint, messages) = match fingerprint {
+            Ok(found) => found,
+            Err(err) => {
+                warnings.push(Warning::info(
+                    "fingerprint_failed",
+                    format!("failed fingerprinting {}: {err}", listing.path.display()),
+                ));
+                continue;
+            }
+        };
+        if messages == 0 {
+            continue;
+        }
+
+        by_fingerprint
+            .entry(fingerprint)
+            .or_insert_with(|| (messages, Vec::new()))
+            .1
+            .push(DuplicateThread {
+                uri: agents_thread_uri(&provider.to_string(), &listing.session_id, None),
+                session_id: listing.session_id,
+                path: listing.path,
+                last_update: Some(format_rfc3339(DateTime::<Utc>::from(listing.updated_at))),
+            });
+    }
+
+    let mut groups = by_fingerprint
+        .into_iter()
+        .filter(|(_, (_, threads))| threads.len() > 1)
+        .map(|(fingerprint, (messages, mut threads))| {
+            // Oldest first: that copy is usually the original.
+            threads.sort_by(|a, b| {
+                a.last_update
+                    .cmp(&b.last_update)
+                    .then_with(|| a.path.cmp(&b.path))
+            });
+            DuplicateGroup {
+                fingerprint,
+                messages,
+                threads,
+            }
+        })
+        .collect::<Vec<_>>();
+    groups.sort_by(|a, b| a.threads[0].path.cmp(&b.threads[0].path));
+
+    Ok(DedupeReport {
+        provider,
+        scanned,
+        groups,
+        warnings,
+    })
+}
+
+pub fn render_dedupe_report_markdown(report: &DedupeReport) -> String {
+    let mut output = String::new();
+    output.push_str("# Duplicate Threads\n\n");
+    output.push_str(&format!("- Provider: `{}`\n", report.provider));
+    output.push_str(&format!("- Threads Scanned: `{}`\n", report.scanned));
+    output.push_str(&format!(
+        "- Duplicate Groups: `{}` (`{}` extra copies)\n\n",
+        report.groups.len(),
+        report
+            .groups
+            .iter()
+            .map(|group| group.threads.len() - 1)
+            .sum::<usize>()
+    ));
+
+    if report.groups.is_empty() {
+        output.push_str("_No duplicate threads found._\n");
+    }
+    for group in &report.groups {
+        output.push_str(&format!(
+            "## `{}` ({} messages)\n\n",
+            group.fingerprint, group.messages
+        ));
+        for thread in &group.threads {
+            output.push_str(&format!(
+                "- `{}` last update `{}`: `{}`\n",
+                thread.uri,
+                thread.last_update.as_deref().unwrap_or("unknown"),
+                thread.path.display()
+            ));
+        }
+        output.push('\n');
+    }
+
+    if !report.warnings.is_empty() {
+        output.push_str("## Warnings\n\n");
+        for warning in &report.warnings {
+            output.push_str(&format!("- {warning}\n"));
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use std::time::{Duration, SystemTime};
+
+    use tempfile::tempdir;
+
+    use crate::dedupe::{
+        find_duplicate_threads, render_dedupe_report_markdown, thread_fingerprint,
+    };
+    use crate::model::{MessageRole, ProviderKind, ThreadMessage};
+    use crate::provider::{ProviderRoots, write_test_rollout};
+
+    fn message(role: MessageRole, text: &str) -> ThreadMessage {
+        ThreadMessage {
+            role,
+            text: text.to_string(),
+            timestamp: None,
+        }
+    }
+
+    fn write_rollout(codex_root: &Path, session_id: &str, answer: &str, modified: SystemTime) {
+        let path = write_test_rollout(
+            codex_root,
+            session_id,
+            &format!(
+                concat!(
+                    r#"{{"type":"session_meta","payload":{{"id":"{}"}}}}"#,
+                    "\n",
+                    r#"{{"type":"response_item","payload":{{"type":"message","role":"user","content":[{{"type":"input_text","text":"hello"}}]}}}}"#,
+                    "\n",
+                    r#"{{"type":"response_item","payload":{{"type":"message","role":"assistant","content":[{{"type":"output_text","text":"{}"}}]}}}}"#,
+                    "\n",
+                ),
+                session_id, answer
+            ),
+        );
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(modified))
+            .expect("set mtime");
+    }
+
+    #[test]
+    fn fingerprints_ignore_whitespace_but_not_roles() {
+        let base = thread_fingerprint(&[
+            message(MessageRole::User, "fix the  build"),
+            message(MessageRole::Assistant, "done"),
+        ]);
+        assert_eq!(base.len(), 32);
+        assert_eq!(
+            base,
+            thread_fingerprint(&[
+                message(MessageRole::User, "\nfix the build "),
+                message(MessageRole::Assistant, "   "),
+                message(MessageRole::Assistant, "done"),
+            ])
+        );
+        assert_ne!(
+            base,
+            thread_fingerprint(&[
+                message(MessageRole::Assistant, "fix the build"),
+                message(MessageRole::Assistant, "done"),
+            ])
+        );
+        assert_ne!(
+            base,
+            thread_fingerprint(&[message(MessageRole::User, "fix the build done")])
+        );
+    }
+
+    #[test]
+    fn groups_copied_codex_rollouts() {
+        let temp = tempdir().expect("tempdir");
+        let roots = ProviderRoots::for_test(temp.path());
+        let now = SystemTime::now();
+        write_rollout(
+            &roots.codex_root,
+            "019c871c-b1f9-7f60-9c4f-87ed09f13592",
+            "world",
+            now - Duration::from_secs(60),
+        );
+        write_rollout(
+            &roots.codex_root,
+            "019c871c-b1f9-7f60-9c4f-87ed09f13593",
+            "world",
+            now,
+        );
+        write_rollout(
+            &roots.codex_root,
+            "019c871c-b1f9-7f60-9c4f-87ed09f13594",
+            "other",
+            now,
+        );
+
+        let report = find_duplicate_threads(ProviderKind::Codex, &roots).expect("dedupe");
+
+        assert_eq!(report.scanned, 3);
+        assert_eq!(report.groups.len(), 1);
+        let group = &report.groups[0];
+        assert_eq!(group.messages, 2);
+        assert_eq!(
+            group
+                .threads
+                .iter()
+                .map(|thread| thread.session_id.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "019c871c-b1f9-7f60-9c4f-87ed09f13592",
+                "019c871c-b1f9-7f60-9c4f-87ed09f13593"
+            ]
+        );
+
+        let markdown = render_dedupe_report_markdown(&report);
+        assert!(markdown.contains("- Duplicate Groups: `1` (`1` extra copies)"));
+        assert!(markdown.contains(&format!("## `{}` (2 messages)", group.fingerprint)));
+    }
+}
@@ -0,0 +1,53 @@
+//! Storage roots and the schema found in each (`xurl doctor`).
+
+use crate::error::{Result, XurlError};
+use crate::model::{DoctorReport, ProviderKind, ProviderStorage, StorageRoot};
+use crate::provider::ProviderRoots;
+use crate::service::{Lookup, provider_at};
+
+/// Lists every provider's storage roots in search order, with the layout
+/// and format version detected in each.
+pub fn check_storage(roots: &ProviderRoots) -> DoctorReport {
+    let providers = ProviderKind::all()
+        .iter()
+        .map(|&kind| ProviderStorage {
+            provider: kind.to_string(),
+            roots: roots
+                .roots_for(kind)
+                .into_iter()
+                .map(|path| StorageRoot {
+                    exists: path.exists(),
+                    schema: provider_at(kind, &path, roots, Lookup::default()).detect_schema(None),
+                    path,
+                })
+                .collect(),
+        })
+        .collect();
+    DoctorReport { providers }
+}
+
+pub fn render_doctor_report_markdown(report: &DoctorReport) -> String {
+    let mut output = String::new();
+    output.push_str("# Storage\n");
+    for provider in &report.providers {
+        output.push_str(&format!("\n## {}\n\n", provider.provider));
+        if provider.roots.is_empty() {
+            output.push_str("_No storage roots._\n");
+        }
+        for root in &provider.roots {
+            let state = match (&root.schema, root.exists) {
+                (Some(schema), _) => format!("`{schema}`"),
+                (None, true) => "no sessions".to_string(),
+                (None, false) => "missing".to_string(),
+            };
+            output.push_str(&format!("- `{}`: {state}\n", root.path.display()));
+        }
+    }
+    output
+}
+
+pub fn render_doctor_report_json(report: &DoctorReport) -> Result<String> {
+    serde_json::to_string_pretty(report)
+        .map(|json| format!("{json}\n"))
+        .map_err(|err| XurlError::Serialization(err.to_string()))
+}
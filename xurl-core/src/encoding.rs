@@ -0,0 +1,168 @@
+//! Decoding transcript files that are not plain UTF-8.
+//!
+//! Agents on Windows sometimes write a byte order mark or UTF-16, and an
+//! interrupted write can leave invalid bytes behind. Such files are decoded
+//! anyway, with a warning whenever the decoding was not a plain UTF-8 read.
+
+use std::char::REPLACEMENT_CHARACTER;
+use std::path::Path;
+
+use crate::model::Warning;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Utf16 {
+    Le,
+    Be,
+}
+
+impl Utf16 {
+    fn unit(self, pair: [u8; 2]) -> u16 {
+        match self {
+            Self::Le => u16::from_le_bytes(pair),
+            Self::Be => u16::from_be_bytes(pair),
+        }
+    }
+}
+
+/// Whether `prefix` (the first bytes of a file) marks it as UTF-16.
+pub fn is_utf16(prefix: &[u8]) -> bool {
+    detect_utf16(prefix).is_some()
+}
+
+fn detect_utf16(bytes: &[u8]) -> Option<(Utf16, &[u8])> {
+    match bytes {
+        [0xff, 0xfe, rest @ ..] => Some((Utf16::Le, rest)),
+        [0xfe, 0xff, rest @ ..] => Some((Utf16::Be, rest)),
+        // Transcripts are JSON, so a NUL next to the opening `{` or `[` means
+        // UTF-16 written without a byte order mark.
+        [b'{' | b'[', 0, ..] => Some((Utf16::Le, bytes)),
+        [0, b'{' | b'[', ..] => Some((Utf16::Be, bytes)),
+        _ => None,
+    }
+}
+
+/// Decodes a transcript read from `path`. A UTF-8 byte order mark is
+/// dropped, UTF-16 is converted, and invalid sequences become U+FFFD; the
+/// last two are reported as warnings.
+pub fn decode_transcript(path: &Path, bytes: &[u8]) -> (String, Vec<Warning>) {
+    let mut warnings = Vec::new();
+    let (text, replaced) = match detect_utf16(bytes) {
+        Some((utf16, body)) => {
+            warnings.push(Warning::info(
+                "utf16_decoded",
+                format!(
+                    "{} is UTF-16{}; decoded it to UTF-8",
+                    path.display(),
+                    if utf16 == Utf16::Le { "LE" } else { "BE" }
+                ),
+            ));
+            decode_utf16(utf16, body)
+        }
+        None => decode_utf8(bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes)),
+    };
+
+    if replaced > 0 {
+        warnings.push(Warning::warn(
+            "invalid_bytes",
+            format!(
+                "replaced {replaced} invalid byte sequence(s) in {} with U+FFFD",
+                path.display()
+            ),
+        ));
+    }
+    (text, warnings)
+}
+
+fn decode_utf8(bytes: &[u8]) -> (String, usize) {
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return (text.to_string(), 0);
+    }
+
+    let mut text = String::with_capacity(bytes.len());
+    let mut replaced = 0usize;
+    for chunk in bytes.utf8_chunks() {
+        text.push_str(chunk.valid());
+        if !chunk.invalid().is_empty() {
+            text.push(REPLACEMENT_CHARACTER);
+            replaced += 1;
+        }
+    }
+    (text, replaced)
+}
+
+fn decode_utf16(utf16: Utf16, bytes: &[u8]) -> (String, usize) {
+    let pairs = bytes.chunks_exact(2);
+    let odd_byte = !pairs.remainder().is_empty();
+    let units = pairs.map(|pair| utf16.unit([pair[0], pair[1]]));
+
+    let mut text = String::with_capacity(bytes.len() / 2);
+    let mut replaced = 0usize;
+    for decoded in char::decode_utf16(units) {
+        text.push(decoded.unwrap_or_else(|_| {
+            replaced += 1;
+            REPLACEMENT_CHARACTER
+        }));
+    }
+    if odd_byte {
+        text.push(REPLACEMENT_CHARACTER);
+        replaced += 1;
+    }
+    (text, replaced)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::encoding::{decode_transcript, is_utf16};
+
+    fn utf16le(text: &str, bom: bool) -> Vec<u8> {
+        let mut bytes = if bom { vec![0xff, 0xfe] } else { Vec::new() };
+        bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        bytes
+    }
+
+    #[test]
+    fn plain_and_bom_utf8_decode_without_warnings() {
+        let path = Path::new("thread.jsonl");
+        assert_eq!(
+            decode_transcript(path, b"{\"a\":1}\n"),
+            ("{\"a\":1}\n".to_string(), Vec::new())
+        );
+        assert_eq!(
+            decode_transcript(path, b"\xef\xbb\xbf{\"a\":1}\n"),
+            ("{\"a\":1}\n".to_string(), Vec::new())
+        );
+    }
+
+    #[test]
+    fn utf16_is_detected_with_or_without_a_bom() {
+        let path = Path::new("thread.json");
+        for bom in [true, false] {
+            let bytes = utf16le("{\"text\":\"héllo\"}", bom);
+            assert!(is_utf16(&bytes));
+            let (text, warnings) = decode_transcript(path, &bytes);
+            assert_eq!(text, "{\"text\":\"héllo\"}");
+            assert_eq!(warnings.len(), 1);
+            assert_eq!(warnings[0].code, "utf16_decoded");
+        }
+
+        let mut bytes = vec![0xfe, 0xff];
+        bytes.extend("[1]".encode_utf16().flat_map(u16::to_be_bytes));
+        assert_eq!(decode_transcript(path, &bytes).0, "[1]");
+    }
+
+    #[test]
+    fn invalid_sequences_are_replaced_and_counted() {
+        let (text, warnings) =
+            decode_transcript(Path::new("thread.jsonl"), b"{\"a\":\"x\xff\xfey\"}\n\xc3");
+        assert_eq!(text, "{\"a\":\"x\u{fffd}\u{fffd}y\"}\n\u{fffd}");
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, "invalid_bytes");
+        assert!(
+            warnings[0]
+                .message
+                .starts_with("replaced 3 invalid byte sequence(s)")
+        );
+    }
+}
@@ -1,4 +1,6 @@
-use std::path::PathBuf;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
 
 use thiserror::Error;
 
@@ -16,14 +18,32 @@ pub enum XurlError {
     #[error("invalid mode: {0}")]
     InvalidMode(String),
 
+    #[error("invalid timestamp: {0}")]
+    InvalidTimestamp(String),
+
     #[error("provider does not support subagent queries: {0}")]
     UnsupportedSubagentProvider(String),
 
     #[error("provider does not support write mode: {0}")]
     UnsupportedProviderWrite(String),
 
-    #[error("command not found: {command}")]
-    CommandNotFound { command: String },
+    #[error("provider does not support spawning subagents from write mode: {0}")]
+    UnsupportedSubagentSpawn(String),
+
+    #[error("provider does not support import: {0}")]
+    UnsupportedProviderImport(String),
+
+    #[error("provider does not support maintenance commands: {0}")]
+    UnsupportedProviderMaintenance(String),
+
+    #[error("provider does not support fork: {0}")]
+    UnsupportedProviderFork(String),
+
+    #[error("provider does not support copy: {0}")]
+    UnsupportedProviderCopy(String),
+
+    #[error("command not found: {command} ({lookup})")]
+    CommandNotFound { command: String, lookup: String },
 
     #[error("command failed: {command} (exit code: {code:?}): {stderr}")]
     CommandFailed {
@@ -32,15 +52,53 @@ pub enum XurlError {
         stderr: String,
     },
 
+    #[error("remote fetch failed: {url}: {message}")]
+    RemoteFetch { url: String, message: String },
+
+    #[error("webhook notification failed: {url}: {message}")]
+    Notify { url: String, message: String },
+
+    #[error("verification failed for {uri}: {problems} problem(s) found")]
+    VerificationFailed { uri: String, problems: usize },
+
+    #[error("head of {uri} differs from baseline {baseline}: {changes} change(s)")]
+    HeadChanged {
+        uri: String,
+        baseline: String,
+        changes: usize,
+    },
+
+    #[error("subagent status of {uri} is {status}")]
+    SubagentStatus { uri: String, status: String },
+
+    #[error("timed out after {seconds}s waiting for {uri}; last status: {status}")]
+    WaitTimedOut {
+        uri: String,
+        status: String,
+        seconds: u64,
+    },
+
     #[error("write protocol error: {0}")]
     WriteProtocol(String),
 
+    #[error("cancelled: {0}")]
+    Cancelled(String),
+
+    #[error("embedding command error: {0}")]
+    Embedding(String),
+
     #[error("serialization error: {0}")]
     Serialization(String),
 
+    #[error("template error: {0}")]
+    Template(String),
+
     #[error("cannot determine home directory")]
     HomeDirectoryNotFound,
 
+    #[error("no storage root is set for provider={provider}")]
+    NoStorageRoot { provider: String },
+
     #[error("thread not found for provider={provider} session_id={session_id}")]
     ThreadNotFound {
         provider: String,
@@ -48,6 +106,32 @@ pub enum XurlError {
         searched_roots: Vec<PathBuf>,
     },
 
+    #[error(
+        "ambiguous session id prefix for provider={provider} prefix={prefix}; candidates: {}",
+        candidates.join(", ")
+    )]
+    AmbiguousSessionId {
+        provider: String,
+        prefix: String,
+        candidates: Vec<String>,
+    },
+
+    #[error(
+        "session id {session_id} exists for several providers; candidates: {}",
+        candidates.join(", ")
+    )]
+    AmbiguousProvider {
+        session_id: String,
+        candidates: Vec<String>,
+    },
+
+    #[error("thread already exists for provider={provider} session_id={session_id}: {path}")]
+    ThreadAlreadyExists {
+        provider: String,
+        session_id: String,
+        path: PathBuf,
+    },
+
     #[error("entry not found for provider={provider} session_id={session_id} entry_id={entry_id}")]
     EntryNotFound {
         provider: String,
@@ -55,12 +139,23 @@ pub enum XurlError {
         entry_id: String,
     },
 
+    #[error("tag not found: {name} (tags are kept in {path})")]
+    TagNotFound { name: String, path: PathBuf },
+
     #[error("thread file is empty: {path}")]
     EmptyThreadFile { path: PathBuf },
 
     #[error("thread file is not valid UTF-8: {path}")]
     NonUtf8ThreadFile { path: PathBuf },
 
+    /// `hint` says what to do about it, e.g. which user can read the file.
+    #[error("permission denied on {path}: {reason}")]
+    PermissionDenied {
+        path: PathBuf,
+        reason: String,
+        hint: String,
+    },
+
     #[error("i/o error on {path}: {source}")]
     Io {
         path: PathBuf,
@@ -68,6 +163,7 @@ pub enum XurlError {
         source: std::io::Error,
     },
 
+    #[cfg(feature = "fs")]
     #[error("sqlite error on {path}: {source}")]
     Sqlite {
         path: PathBuf,
@@ -82,6 +178,130 @@ pub enum XurlError {
         #[source]
         source: serde_json::Error,
     },
+
+    #[error("invalid {provider} record in {path} at line {line}, field {field}: {message}")]
+    InvalidRecord {
+        provider: String,
+        path: PathBuf,
+        line: usize,
+        field: String,
+        message: String,
+    },
 }
 
 pub type Result<T> = std::result::Result<T, XurlError>;
+
+/// Variables that move a provider's storage away from the home directory.
+const HOME_VARS: &str = "HOME and the provider's home variable (CODEX_HOME, CLAUDE_CONFIG_DIR, GEMINI_CLI_HOME, PI_CODING_AGENT_DIR, XDG_DATA_HOME)";
+
+impl XurlError {
+    /// An I/O error on a thread file. Permission errors become
+    /// [`XurlError::PermissionDenied`], explaining who may access the file.
+    pub fn io(path: &Path, source: std::io::Error) -> Self {
+        if source.kind() != ErrorKind::PermissionDenied {
+            return Self::Io {
+                path: path.to_path_buf(),
+                source,
+            };
+        }
+
+        let (reason, hint) = permission_denied(path);
+        Self::PermissionDenied {
+            path: path.to_path_buf(),
+            reason,
+            hint,
+        }
+    }
+}
+
+#[cfg(unix)]
+fn permission_denied(path: &Path) -> (String, String) {
+    use std::os::unix::fs::MetadataExt;
+
+    let Ok(metadata) = fs::metadata(path) else {
+        return (
+            "a parent directory cannot be searched".to_string(),
+            format!("the path may be inside another user's home or a sandbox; check {HOME_VARS}"),
+        );
+    };
+    let mode = metadata.mode() & 0o7777;
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    if metadata.uid() == unsafe { libc::geteuid() } {
+        return (
+            format!("mode {mode:04o} does not allow it"),
+            format!("run `chmod u+rw {}`", path.display()),
+        );
+    }
+    (
+        format!("owned by uid {} with mode {mode:04o}", metadata.uid()),
+        format!(
+            "run xurl as that user (e.g. `sudo -u '#{}' xurl ...`) or take the file over with `sudo chown $USER {}`; if this is not your agent data, check {HOME_VARS}",
+            metadata.uid(),
+            path.display()
+        ),
+    )
+}
+
+#[cfg(not(unix))]
+fn permission_denied(path: &Path) -> (String, String) {
+    let reason = if fs::metadata(path).is_ok() {
+        "access to the file is denied"
+    } else {
+        "access to a parent directory is denied"
+    };
+    (
+        reason.to_string(),
+        format!("check the file's security settings and {HOME_VARS}"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::{Error, ErrorKind};
+
+    use tempfile::tempdir;
+
+    use crate::error::XurlError;
+
+    #[test]
+    fn permission_errors_say_who_may_access_the_file() {
+        let temp = tempdir().expect("tempdir");
+        let path = temp.path().join("thread.jsonl");
+        fs::write(&path, "{}\n").expect("write");
+
+        let err = XurlError::io(&path, Error::from(ErrorKind::NotFound));
+        assert!(matches!(err, XurlError::Io { .. }));
+
+        let err = XurlError::io(&path, Error::from(ErrorKind::PermissionDenied));
+        let XurlError::PermissionDenied { reason, hint, .. } = err else {
+            panic!("expected permission denied, got {err:?}");
+        };
+        assert!(!reason.is_empty() && !hint.is_empty());
+
+        #[cfg(unix)]
+        {
+            assert!(reason.starts_with("mode "), "{reason}");
+            assert!(hint.contains("chmod u+rw"), "{hint}");
+
+            let missing = temp.path().join("locked/thread.jsonl");
+            let err = XurlError::io(&missing, Error::from(ErrorKind::PermissionDenied));
+            assert!(
+                err.to_string()
+                    .ends_with("a parent directory cannot be searched")
+            );
+
+            // Handing the file to another user needs root; skip that case
+            // otherwise.
+            if std::os::unix::fs::chown(&path, Some(1), None).is_ok() {
+                let err = XurlError::io(&path, Error::from(ErrorKind::PermissionDenied));
+                let XurlError::PermissionDenied { reason, hint, .. } = err else {
+                    panic!("expected permission denied");
+                };
+                assert!(reason.starts_with("owned by uid 1 "), "{reason}");
+                assert!(hint.contains("sudo chown"), "{hint}");
+                assert!(hint.contains("CODEX_HOME"), "{hint}");
+            }
+        }
+    }
+}
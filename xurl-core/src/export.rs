@@ -0,0 +1,427 @@
+//! Rewriting threads as another agent's sessions (`xurl export`).
+//!
+//! The timeline of a thread from any provider becomes a new session of the
+//! target agent, one record per entry:
+//!
+//! - Claude Code: `user` and `assistant` records chained through
+//!   `parentUuid` and stamped with a working directory, so `claude --resume`
+//!   run in that directory lists the session and can continue it.
+//! - Codex: a rollout that opens with `session_meta` followed by
+//!   `response_item` messages, stored under `sessions/YYYY/MM/DD/` and
+//!   registered in the thread index, so `codex resume` finds it.
+//!
+//! Tool calls and reasoning are not part of the timeline and are left behind.
+
+use std::path::PathBuf;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::atomic::write_atomic;
+use crate::error::{Result, XurlError};
+use crate::model::{
+    ExportFormat, ExportRequest, ExportResult, ProviderKind, SessionInfo, TimelineEntryKind,
+    TimelineEntryView, Warning,
+};
+use crate::provider::ProviderRoots;
+use crate::provider::claude::ClaudeProvider;
+use crate::provider::codex::CodexProvider;
+use crate::render::{self, ReadOptions};
+use crate::service::{TIMELINE_LINES, read_thread_lines, resolve_thread};
+use crate::timestamp::parse_timestamp;
+use crate::uri::ThreadUri;
+
+/// Model name Claude Code gives assistant messages it did not get from the
+/// API; exported replies carry it too.
+const SYNTHETIC_MODEL: &str = "<synthetic>";
+/// `originator` of exported Codex rollouts.
+const CODEX_ORIGINATOR: &str = "xurl_export";
+
+/// Exports the thread at `uri` as a new session in `req.format`.
+pub fn export_thread(
+    uri: &ThreadUri,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+    req: &ExportRequest,
+) -> Result<ExportResult> {
+    if uri.agent_id.is_some() || uri.fragment.is_some() {
+        return Err(XurlError::InvalidMode(
+            "export only supports main thread URIs: agents://<provider>/<session_id>".to_string(),
+        ));
+    }
+
+    let resolved = resolve_thread(uri, roots)?;
+    let raw = read_thread_lines(&resolved, roots, options, TIMELINE_LINES)?;
+    let entries =
+        render::timeline_entries(uri, &resolved.path, &raw, &mut options.extraction_policy())?
+            .into_iter()
+            .filter(|entry| {
+                !matches!(
+                    entry.kind,
+                    TimelineEntryKind::RolloutBoundary
+                        | TimelineEntryKind::ToolError
+                        | TimelineEntryKind::ToolCall
+                )
+            })
+            .collect::<Vec<_>>();
+    if entries.is_empty() {
+        return Err(XurlError::InvalidMode(format!(
+            "{} has no messages to export",
+            uri.as_agents_string()
+        )));
+    }
+
+    let mut warnings = resolved.metadata.warnings.clone();
+    let session = &resolved.metadata.session;
+    let cwd = match (&req.cwd, &session.cwd) {
+        (Some(cwd), _) => cwd.display().to_string(),
+        (None, Some(cwd)) => cwd.clone(),
+        (None, None) => {
+            let cwd = std::env::current_dir()
+                .map_err(|source| XurlError::Io {
+                    path: PathBuf::from("."),
+                    source,
+                })?
+                .display()
+                .to_string();
+            warnings.push(Warning::info(
+                "export_cwd_fallback",
+                format!("the thread records no working directory; the session belongs to {cwd}"),
+            ));
+            cwd
+        }
+    };
+
+    let times = entry_times(&entries);
+    let started_at = times.first().copied().unwrap_or_else(Utc::now);
+    let (provider, session_id) = match req.format {
+        ExportFormat::ClaudeSession => (ProviderKind::Claude, Uuid::new_v4().to_string()),
+        ExportFormat::CodexRollout => (ProviderKind::Codex, Uuid::now_v7().to_string()),
+    };
+    let content = match req.format {
+        ExportFormat::ClaudeSession => claude_session(&entries, &times, &session_id, &cwd, session),
+        ExportFormat::CodexRollout => codex_rollout(&entries, &times, &session_id, &cwd, session),
+    };
+
+    let path = match &req.output {
+        Some(path) => path.clone(),
+        None => {
+            roots.ensure_own_root(provider)?;
+            storage_path(roots, provider, &session_id, &cwd, started_at)
+        }
+    };
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent).map_err(|source| XurlError::io(parent, source))?;
+    }
+    write_atomic(&path, content.as_bytes(), true)?;
+
+    let mut registered_in = None;
+    if provider == ProviderKind::Codex && req.output.is_none() {
+        match CodexProvider::new(&roots.codex_root).register_rollout(
+            &session_id,
+            &path,
+            started_at.timestamp(),
+            Some(&cwd),
+        ) {
+            Ok(db_path) => registered_in = db_path,
+            Err(err) => warnings.push(Warning::warn(
+                "index_update_failed",
+                format!("exported rollout was not registered in the sqlite thread index: {err}"),
+            )),
+        }
+    }
+
+    Ok(ExportResult {
+        provider,
+        session_id,
+        path,
+        cwd,
+        messages: entries.len(),
+        registered_in,
+        warnings,
+    })
+}
+
+/// Where `provider` keeps a session it started in `cwd` at `started_at`.
+fn storage_path(
+    roots: &ProviderRoots,
+    provider: ProviderKind,
+    session_id: &str,
+    cwd: &str,
+    started_at: DateTime<Utc>,
+) -> PathBuf {
+    if provider == ProviderKind::Codex {
+        return roots
+            .codex_root
+            .join("sessions")
+            .join(started_at.format("%Y/%m/%d").to_string())
+            .join(format!(
+                "rollout-{}-{session_id}.jsonl",
+                started_at.format("%Y-%m-%dT%H-%M-%S")
+            ));
+    }
+    let projects = roots.claude_root.join("projects");
+    projects
+        .join(ClaudeProvider::project_dir_name(&projects, cwd))
+        .join(format!("{session_id}.jsonl"))
+}
+
+/// When each entry happened. Entries without a timestamp take the previous
+/// one; a thread with none is stamped with the current time.
+fn entry_times(entries: &[TimelineEntryView]) -> Vec<DateTime<Utc>> {
+    let mut time = entries
+        .iter()
+        .find_map(|entry| entry.timestamp.as_deref().and_then(parse_timestamp))
+        .unwrap_or_else(Utc::now);
+    entries
+        .iter()
+        .map(|entry| {
+            time = entry
+                .timestamp
+                .as_deref()
+                .and_then(parse_timestamp)
+                .unwrap_or(time);
+            time
+        })
+        .collect()
+}
+
+/// Claude Code transcript lines for `entries`.
+fn claude_session(
+    entries: &[TimelineEntryView],
+    times: &[DateTime<Utc>],
+    session_id: &str,
+    cwd: &str,
+    session: &SessionInfo,
+) -> String {
+    let mut parent: Option<String> = None;
+    let mut output = String::new();
+
+    for (entry, time) in entries.iter().zip(times) {
+        let uuid = Uuid::new_v4().to_string();
+        let mut line = json!({
+            "parentUuid": parent,
+            "isSidechain": false,
+            "userType": "external",
+            "cwd": cwd,
+            "sessionId": session_id,
+            "uuid": uuid,
+            "timestamp": record_timestamp(*time),
+        });
+        if let Some(branch) = &session.git_branch {
+            line["gitBranch"] = json!(branch);
+        }
+        let text = entry.text.trim();
+        match entry.kind {
+            TimelineEntryKind::Assistant => {
+                line["type"] = json!("assistant");
+                line["message"] = json!({
+                    "id": format!("msg_{}", Uuid::new_v4().simple()),
+                    "type": "message",
+                    "role": "assistant",
+                    "model": SYNTHETIC_MODEL,
+                    "content": [{ "type": "text", "text": text }],
+                    "stop_reason": "end_turn",
+                    "stop_sequence": Value::Null,
+                    "usage": { "input_tokens": 0, "output_tokens": 0 },
+                });
+            }
+            kind => {
+                line["type"] = json!("user");
+                line["message"] = json!({ "role": "user", "content": text });
+                if kind == TimelineEntryKind::Compact {
+                    line["isCompactSummary"] = json!(true);
+                }
+            }
+        }
+        output.push_str(&line.to_string());
+        output.push('\n');
+        parent = Some(uuid);
+    }
+    output
+}
+
+/// Codex rollout lines for `entries`: the `session_meta` record, then one
+/// `response_item` message (or `compacted` record) per entry.
+fn codex_rollout(
+    entries: &[TimelineEntryView],
+    times: &[DateTime<Utc>],
+    session_id: &str,
+    cwd: &str,
+    session: &SessionInfo,
+) -> String {
+    let started_at = record_timestamp(times.first().copied().unwrap_or_else(Utc::now));
+    let mut meta = json!({
+        "id": session_id,
+        "timestamp": started_at,
+        "cwd": cwd,
+        "originator": CODEX_ORIGINATOR,
+        "cli_version": env!("CARGO_PKG_VERSION"),
+        "source": "cli",
+    });
+    if let Some(branch) = &session.git_branch {
+        meta["git"] = json!({ "branch": branch });
+    }
+    let mut output = json!({
+        "timestamp": started_at,
+        "type": "session_meta",
+        "payload": meta,
+    })
+    .to_string();
+    output.push('\n');
+
+    for (entry, time) in entries.iter().zip(times) {
+        let text = entry.text.trim();
+        let (record_type, payload) = match entry.kind {
+            TimelineEntryKind::Compact => ("compacted", json!({ "message": text })),
+            kind => {
+                let (role, content_type) = if kind == TimelineEntryKind::Assistant {
+                    ("assistant", "output_text")
+                } else {
+                    ("user", "input_text")
+                };
+                (
+                    "response_item",
+                    json!({
+                        "type": "message",
+                        "role": role,
+                        "content": [{ "type": content_type, "text": text }],
+                    }),
+                )
+            }
+        };
+        let line = json!({
+            "timestamp": record_timestamp(*time),
+            "type": record_type,
+            "payload": payload,
+        });
+        output.push_str(&line.to_string());
+        output.push('\n');
+    }
+    output
+}
+
+/// Millisecond RFC3339, as both Claude Code and Codex write it.
+fn record_timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use serde_json::Value;
+    use tempfile::tempdir;
+
+    use crate::export::export_thread;
+    use crate::model::{ExportFormat, ExportRequest, ProviderKind, TimelineEntryKind};
+    use crate::provider::{ProviderRoots, write_test_rollout};
+    use crate::render::{ExtractionPolicy, ReadOptions, timeline_entries};
+    use crate::service::resolve_thread;
+    use crate::uri::ThreadUri;
+
+    const SESSION_ID: &str = "019c871c-b1f9-7f60-9c4f-87ed09f13592";
+
+    #[test]
+    fn threads_round_trip_between_codex_and_claude() {
+        let temp = tempdir().expect("tempdir");
+        let roots = ProviderRoots::for_test(temp.path());
+        write_test_rollout(
+            &roots.codex_root,
+            SESSION_ID,
+            &format!(
+                concat!(
+                    r#"{{"timestamp":"2026-02-23T00:00:00Z","type":"session_meta","payload":{{"id":"{id}","cwd":"/work/app","git":{{"branch":"main"}}}}}}"#,
+                    "\n",
+                    r#"{{"timestamp":"2026-02-23T00:00:01Z","type":"response_item","payload":{{"type":"message","role":"user","content":[{{"type":"input_text","text":"fix the parser"}}]}}}}"#,
+                    "\n",
+                    r#"{{"timestamp":"2026-02-23T00:00:02Z","type":"response_item","payload":{{"type":"function_call","name":"shell","arguments":"{{}}","call_id":"c1"}}}}"#,
+                    "\n",
+                    r#"{{"timestamp":"2026-02-23T00:00:09Z","type":"response_item","payload":{{"type":"message","role":"assistant","content":[{{"type":"output_text","text":"fixed"}}]}}}}"#,
+                    "\n",
+                ),
+                id = SESSION_ID
+            ),
+        );
+        let uri = ThreadUri::parse(&format!("agents://codex/{SESSION_ID}")).expect("uri");
+
+        let result = export_thread(
+            &uri,
+            &roots,
+            &ReadOptions::default(),
+            &ExportRequest {
+                format: ExportFormat::ClaudeSession,
+                cwd: None,
+                output: None,
+            },
+        )
+        .expect("export");
+        assert_eq!(result.cwd, "/work/app");
+        assert_eq!(result.messages, 2);
+        assert_eq!(
+            result.path,
+            temp.path()
+                .join("claude/projects/-work-app")
+                .join(format!("{}.jsonl", result.session_id))
+        );
+
+        let lines = fs::read_to_string(&result.path)
+            .expect("read")
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).expect("json"))
+            .collect::<Vec<_>>();
+        assert_eq!(lines[0]["parentUuid"], Value::Null);
+        assert_eq!(lines[1]["parentUuid"], lines[0]["uuid"]);
+        assert_eq!(lines[1]["timestamp"], "2026-02-23T00:00:09.000Z");
+        assert_eq!(lines[1]["gitBranch"], "main");
+
+        let claude = ThreadUri::parse(&format!("agents://claude/{}", result.session_id))
+            .expect("claude uri");
+        let resolved = resolve_thread(&claude, &roots).expect("resolve export");
+        assert_eq!(resolved.provider, ProviderKind::Claude);
+        let entries = |uri: &ThreadUri| {
+            let resolved = resolve_thread(uri, &roots).expect("resolve export");
+            let raw = fs::read_to_string(&resolved.path).expect("read export");
+            timeline_entries(uri, &resolved.path, &raw, &mut ExtractionPolicy::default())
+                .expect("entries")
+                .into_iter()
+                .map(|entry| (entry.kind, entry.text))
+                .collect::<Vec<_>>()
+        };
+        let expected = [
+            (TimelineEntryKind::User, "fix the parser".to_string()),
+            (TimelineEntryKind::Assistant, "fixed".to_string()),
+        ];
+        assert_eq!(entries(&claude), expected);
+
+        let back = export_thread(
+            &claude,
+            &roots,
+            &ReadOptions::default(),
+            &ExportRequest {
+                format: ExportFormat::CodexRollout,
+                cwd: None,
+                output: None,
+            },
+        )
+        .expect("export back");
+        assert_eq!(back.provider, ProviderKind::Codex);
+        assert!(
+            back.path
+                .starts_with(temp.path().join("codex/sessions/2026/02/23"))
+        );
+        let codex =
+            ThreadUri::parse(&format!("agents://codex/{}", back.session_id)).expect("codex uri");
+        assert_eq!(entries(&codex), expected);
+        let resolved = resolve_thread(&codex, &roots).expect("resolve rollout");
+        assert_eq!(resolved.metadata.session.cwd.as_deref(), Some("/work/app"));
+        assert_eq!(
+            resolved.metadata.session.git_branch.as_deref(),
+            Some("main")
+        );
+    }
+}
@@ -0,0 +1,297 @@
+//! Forking threads to continue them in another direction (`xurl fork`).
+//!
+//! - Pi sessions are entry trees, so a fork is a branch point: the fork's
+//!   URI names the entry it starts from, and every prompt written to that
+//!   URI becomes a new child of the entry. The session file is left as it is
+//!   until the first prompt.
+//! - Codex threads are linear, so a fork is a copy: the rollout is written
+//!   again under a new session id, its `session_meta` carrying the original
+//!   id as `forked_from_id` the way Codex records its own forks, and it is
+//!   registered in the thread index so `codex resume` finds it. A session
+//!   resumed into several rollouts is forked as one rollout holding them
+//!   all.
+
+use std::fs;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::atomic::write_atomic;
+use crate::error::{Result, XurlError};
+use crate::model::{ForkResult, ProviderKind, Warning};
+use crate::provider::ProviderRoots;
+use crate::provider::codex::CodexProvider;
+use crate::provider::pi::PiProvider;
+use crate::service::{read_thread_text, resolve_thread, resolve_thread_files, thread_files};
+use crate::uri::ThreadUri;
+
+/// Forks the thread at `uri` at its latest entry, or for a Pi entry URI at
+/// that entry.
+pub fn fork_thread(uri: &ThreadUri, roots: &ProviderRoots) -> Result<ForkResult> {
+    if uri.fragment.is_some() {
+        return Err(XurlError::InvalidMode(
+            "fork starts from the latest entry; drop the #msg fragment".to_string(),
+        ));
+    }
+    match uri.provider {
+        ProviderKind::Pi => fork_pi(uri, roots),
+        ProviderKind::Codex if uri.agent_id.is_none() => fork_codex(uri, roots),
+        ProviderKind::Codex => Err(XurlError::InvalidMode(
+            "codex fork only supports main thread URIs: agents://codex/<session_id>".to_string(),
+        )),
+        provider => Err(XurlError::UnsupportedProviderFork(provider.to_string())),
+    }
+}
+
+fn fork_pi(uri: &ThreadUri, roots: &ProviderRoots) -> Result<ForkResult> {
+    let main = ThreadUri {
+        agent_id: None,
+        ..uri.clone()
+    };
+    let resolved = resolve_thread(&main, roots)?;
+    let (raw, decode_warnings) = read_thread_text(&resolved.path)?;
+    let entry_ids = PiProvider::entry_ids(&raw);
+
+    let entry_id = match uri.agent_id.as_deref() {
+        Some(entry_id) if !entry_ids.iter().any(|id| id == entry_id) => {
+            return Err(XurlError::EntryNotFound {
+                provider: ProviderKind::Pi.to_string(),
+                session_id: uri.session_id.clone(),
+                entry_id: entry_id.to_string(),
+            });
+        }
+        Some(entry_id) => entry_id.to_string(),
+        None => entry_ids.last().cloned().ok_or_else(|| {
+            XurlError::InvalidMode(format!(
+                "{} has no entries to fork from",
+                main.as_agents_string()
+            ))
+        })?,
+    };
+
+    Ok(ForkResult {
+        provider: ProviderKind::Pi,
+        session_id: uri.session_id.clone(),
+        entry_id: Some(entry_id),
+        forked_from: main.as_agents_string(),
+        path: resolved.path,
+        registered_in: None,
+        warnings: [resolved.metadata.warnings, decode_warnings].concat(),
+    })
+}
+
+fn fork_codex(uri: &ThreadUri, roots: &ProviderRoots) -> Result<ForkResult> {
+    roots.ensure_own_root(ProviderKind::Codex)?;
+    let resolved = resolve_thread_files(uri, roots)?;
+    let files = thread_files(&resolved);
+    let mut warnings = resolved.metadata.warnings;
+    let mut raw = String::new();
+    for path in files {
+        let (text, decode_warnings) = read_thread_text(&path)?;
+        warnings.extend(decode_warnings);
+        if !raw.is_empty() && !raw.ends_with('\n') {
+            raw.push('\n');
+        }
+        raw.push_str(&text);
+    }
+
+    let session_id = Uuid::now_v7().to_string();
+    let forked_at = Utc::now();
+    let (content, cwd) = fork_rollout(&raw, &session_id, &uri.session_id, forked_at, &mut warnings)
+        .ok_or_else(|| {
+            XurlError::InvalidMode(format!(
+                "{} has no session_meta record to fork",
+                uri.as_agents_string()
+            ))
+        })?;
+
+    let path = roots
+        .codex_root
+        .join("sessions")
+        .join(forked_at.format("%Y/%m/%d").to_string())
+        .join(format!(
+            "rollout-{}-{session_id}.jsonl",
+            forked_at.format("%Y-%m-%dT%H-%M-%S")
+        ));
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|source| XurlError::io(parent, source))?;
+    }
+    write_atomic(&path, content.as_bytes(), true)?;
+
+    let registered_in = match CodexProvider::new(&roots.codex_root).register_rollout(
+        &session_id,
+        &path,
+        forked_at.timestamp(),
+        cwd.as_deref(),
+    ) {
+        Ok(db_path) => db_path,
+        Err(err) => {
+            warnings.push(Warning::warn(
+                "index_update_failed",
+                format!("forked rollout was not registered in the sqlite thread index: {err}"),
+            ));
+            None
+        }
+    };
+
+    Ok(ForkResult {
+        provider: ProviderKind::Codex,
+        session_id,
+        entry_id: None,
+        forked_from: uri.as_agents_string(),
+        path,
+        registered_in,
+        warnings,
+    })
+}
+
+/// The records of `raw` with its first `session_meta` moved to `session_id`
+/// and pointing back at `forked_from`, plus the working directory it
+/// records. `None` when there is no `session_meta` to move. The
+/// `session_meta` records that later rollouts of a resumed session start
+/// with are dropped, and a last line that is still being written is left
+/// out.
+fn fork_rollout(
+    raw: &str,
+    session_id: &str,
+    forked_from: &str,
+    forked_at: DateTime<Utc>,
+    warnings: &mut Vec<Warning>,
+) -> Option<(String, Option<String>)> {
+    let forked_at = forked_at.to_rfc3339_opts(SecondsFormat::Millis, true);
+    let mut lines = raw.lines().collect::<Vec<_>>();
+    if !raw.ends_with('\n')
+        && lines
+            .last()
+            .is_some_and(|line| serde_json::from_str::<Value>(line).is_err())
+    {
+        lines.pop();
+        warnings.push(Warning::info(
+            "pending_final_line",
+            "the last line of the rollout was still being written and was not copied",
+        ));
+    }
+
+    let mut output = String::with_capacity(raw.len());
+    let mut meta = None;
+    for line in lines {
+        let record = serde_json::from_str::<Value>(line.trim_start_matches('\u{feff}'))
+            .ok()
+            .filter(|record| record["type"] == "session_meta" && record["payload"].is_object());
+        if meta.is_some()
+            && record
+                .as_ref()
+                .is_some_and(|record| record["payload"]["id"] == forked_from)
+        {
+            continue;
+        }
+        if meta.is_none()
+            && let Some(mut record) = record
+        {
+            let payload = &mut record["payload"];
+            payload["id"] = json!(session_id);
+            payload["timestamp"] = json!(forked_at);
+            payload["forked_from_id"] = json!(forked_from);
+            meta = Some(payload["cwd"].as_str().map(str::to_string));
+            record["timestamp"] = json!(forked_at);
+            output.push_str(&record.to_string());
+        } else {
+            output.push_str(line);
+        }
+        output.push('\n');
+    }
+    meta.map(|cwd| (output, cwd))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use serde_json::Value;
+    use tempfile::tempdir;
+
+    use crate::fork::fork_thread;
+    use crate::model::ProviderKind;
+    use crate::provider::{ProviderRoots, write_test_rollout};
+    use crate::service::resolve_thread;
+    use crate::uri::ThreadUri;
+
+    const SESSION_ID: &str = "019c871c-b1f9-7f60-9c4f-87ed09f13592";
+
+    #[test]
+    fn codex_fork_copies_the_rollout_under_a_new_id() {
+        let temp = tempdir().expect("tempdir");
+        let roots = ProviderRoots::for_test(temp.path());
+        let rollout = write_test_rollout(
+            &roots.codex_root,
+            SESSION_ID,
+            &format!(
+                concat!(
+                    r#"{{"timestamp":"2026-02-23T00:00:00Z","type":"session_meta","payload":{{"id":"{id}","cwd":"/work/app"}}}}"#,
+                    "\n",
+                    r#"{{"timestamp":"2026-02-23T00:00:01Z","type":"response_item","payload":{{"type":"message","role":"user","content":[{{"type":"input_text","text":"fix the parser"}}]}}}}"#,
+                    "\n",
+                    r#"{{"timestamp":"2026-02-23T00:00:02Z","type":"response_item","pay"#,
+                ),
+                id = SESSION_ID
+            ),
+        );
+        let uri = ThreadUri::parse(&format!("agents://codex/{SESSION_ID}")).expect("uri");
+
+        let fork = fork_thread(&uri, &roots).expect("fork");
+        assert_eq!(fork.provider, ProviderKind::Codex);
+        assert_ne!(fork.session_id, SESSION_ID);
+        assert_eq!(fork.forked_from, format!("agents://codex/{SESSION_ID}"));
+        assert_eq!(fork.uri(), format!("agents://codex/{}", fork.session_id));
+        assert_eq!(fork.warnings[0].code, "pending_final_line");
+
+        let lines = fs::read_to_string(&fork.path)
+            .expect("read fork")
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).expect("json"))
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["payload"]["id"], fork.session_id.as_str());
+        assert_eq!(lines[0]["payload"]["forked_from_id"], SESSION_ID);
+        assert_eq!(lines[0]["payload"]["cwd"], "/work/app");
+
+        let forked = ThreadUri::parse(&fork.uri()).expect("fork uri");
+        let resolved = resolve_thread(&forked, &roots).expect("resolve fork");
+        assert_eq!(resolved.path, fork.path);
+        assert!(rollout.exists());
+    }
+
+    #[test]
+    fn codex_fork_of_a_resumed_session_holds_every_rollout() {
+        let temp = tempdir().expect("tempdir");
+        for (day, text) in [("23", "fix the parser"), ("24", "now the lexer")] {
+            let rollout = temp.path().join(format!(
+                "codex/sessions/2026/02/{day}/rollout-2026-02-{day}T04-48-50-{SESSION_ID}.jsonl"
+            ));
+            fs::create_dir_all(rollout.parent().expect("parent")).expect("mkdir");
+            fs::write(
+                &rollout,
+                format!(
+                    concat!(
+                        r#"{{"type":"session_meta","payload":{{"id":"{id}","cwd":"/work/app"}}}}"#,
+                        "\n",
+                        r#"{{"type":"response_item","payload":{{"type":"message","role":"user","content":[{{"type":"input_text","text":"{text}"}}]}}}}"#,
+                        "\n",
+                    ),
+                    id = SESSION_ID,
+                    text = text,
+                ),
+            )
+            .expect("write");
+        }
+        let roots = ProviderRoots::for_test(temp.path());
+        let uri = ThreadUri::parse(&format!("agents://codex/{SESSION_ID}")).expect("uri");
+
+        let fork = fork_thread(&uri, &roots).expect("fork");
+        let content = fs::read_to_string(&fork.path).expect("read fork");
+        assert_eq!(content.lines().count(), 3);
+        assert_eq!(content.matches("session_meta").count(), 1);
+        assert!(content.find("fix the parser") < content.find("now the lexer"));
+    }
+}
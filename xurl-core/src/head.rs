@@ -0,0 +1,634 @@
+//! Typed model of the `-I` head frontmatter.
+//!
+//! Head output is built as a [`HeadDocument`] and emitted through
+//! [`crate::yaml`], so every value is quoted correctly and key order stays
+//! fixed.
+
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+
+use crate::error::{Result, XurlError};
+use crate::model::{ProviderKind, SessionInfo, StorageSchema, ThreadParent, Warning};
+use crate::status::{KNOWN_STATUSES, worst_status};
+use crate::yaml::{YamlMapping, YamlValue};
+
+/// Every top-level key a head can have, in the order they are emitted.
+pub const HEAD_KEYS: &[&str] = &[
+    "uri",
+    "provider",
+    "session_id",
+    "thread_source",
+    "schema",
+    "fingerprint",
+    "model",
+    "cwd",
+    "branch",
+    "parent",
+    "mode",
+    "aggregate",
+    "subagents",
+    "agent_id",
+    "subagent_uri",
+    "status",
+    "status_source",
+    "child_thread_id",
+    "child_thread_source",
+    "child_last_updated_at",
+    "entries",
+    "entry_id",
+    "page",
+    "warnings",
+];
+
+/// Which top-level keys a head keeps (`--head-fields`). Keys a head does not
+/// have are simply absent, so one selection works across head modes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeadFields {
+    /// Only these keys, e.g. `uri,provider,subagents`.
+    Only(Vec<&'static str>),
+    /// Every key except these, e.g. `-warnings,-subagents`.
+    Except(Vec<&'static str>),
+}
+
+impl HeadFields {
+    /// Parses a comma-separated key list. Keys prefixed with `-` are
+    /// excluded; a list must either name keys to keep or keys to drop.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut only = Vec::new();
+        let mut except = Vec::new();
+        for field in spec
+            .split(',')
+            .map(str::trim)
+            .filter(|field| !field.is_empty())
+        {
+            let (list, name) = match field.strip_prefix('-') {
+                Some(name) => (&mut except, name),
+                None => (&mut only, field),
+            };
+            let key = HEAD_KEYS.iter().find(|key| **key == name).ok_or_else(|| {
+                XurlError::InvalidMode(format!(
+                    "unknown head field {name:?}; expected one of: {}",
+                    HEAD_KEYS.join(", ")
+                ))
+            })?;
+            list.push(*key);
+        }
+
+        match (only.is_empty(), except.is_empty()) {
+            (false, true) => Ok(Self::Only(only)),
+            (true, false) => Ok(Self::Except(except)),
+            (true, true) => Err(XurlError::InvalidMode(
+                "--head-fields needs at least one field".to_string(),
+            )),
+            (false, false) => Err(XurlError::InvalidMode(
+                "--head-fields either lists fields to keep or `-`-prefixed fields to drop, not both"
+                    .to_string(),
+            )),
+        }
+    }
+
+    pub fn keeps(&self, key: &str) -> bool {
+        match self {
+            Self::Only(keys) => keys.contains(&key),
+            Self::Except(keys) => !keys.contains(&key),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeadDocument {
+    pub uri: String,
+    pub provider: ProviderKind,
+    pub session_id: String,
+    pub thread_source: String,
+    /// Storage layout and format version detected for `thread_source`.
+    pub schema: Option<StorageSchema>,
+    /// [`crate::dedupe::thread_fingerprint`] of a main thread's messages.
+    pub fingerprint: Option<String>,
+    /// Model, cwd, and git branch recorded in the thread.
+    pub session: SessionInfo,
+    /// For a subagent's own thread, the thread that spawned it.
+    pub parent: Option<ThreadParent>,
+    pub body: HeadBody,
+    /// Present when only one page of the timeline is rendered.
+    pub page: Option<HeadPage>,
+    pub warnings: Vec<Warning>,
+}
+
+/// Mode-specific part of the head; each variant maps to one `mode` value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeadBody {
+    Thread,
+    SubagentIndex { subagents: Vec<HeadSubagent> },
+    SubagentDetail(HeadSubagentDetail),
+    PiEntryIndex { entries: Vec<HeadPiEntry> },
+    PiEntry { entry_id: String },
+}
+
+impl HeadBody {
+    pub fn mode(&self) -> &'static str {
+        match self {
+            Self::Thread => "thread",
+            Self::SubagentIndex { .. } => "subagent_index",
+            Self::SubagentDetail(_) => "subagent_detail",
+            Self::PiEntryIndex { .. } => "pi_entry_index",
+            Self::PiEntry { .. } => "pi_entry",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeadSubagent {
+    pub agent_id: String,
+    pub uri: String,
+    pub agent_name: Option<String>,
+    pub status: String,
+    pub status_source: String,
+    pub last_update: Option<String>,
+    pub thread_source: Option<String>,
+    pub children: Vec<HeadSubagent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeadSubagentDetail {
+    pub agent_id: Option<String>,
+    pub subagent_uri: Option<String>,
+    pub status: String,
+    pub status_source: String,
+    pub child_thread_id: Option<String>,
+    pub child_thread_source: Option<String>,
+    pub child_last_updated_at: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeadPage {
+    pub number: usize,
+    pub size: usize,
+    pub total_pages: usize,
+    pub total_entries: usize,
+    /// URI of the previous page, absent on the first page.
+    pub prev: Option<String>,
+    /// URI of the next page, absent on the last page.
+    pub next: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeadPiEntry {
+    pub entry_id: String,
+    pub uri: String,
+    pub entry_type: String,
+    pub parent_id: Option<String>,
+    pub timestamp: Option<String>,
+    pub role: Option<String>,
+    pub preview: Option<String>,
+    pub estimated_tokens: Option<usize>,
+    pub is_leaf: bool,
+}
+
+impl HeadDocument {
+    pub fn to_yaml_mapping(&self) -> YamlMapping {
+        let mut mapping = YamlMapping::new();
+        mapping.insert("uri", &self.uri);
+        mapping.insert("provider", self.provider.to_string());
+        mapping.insert("session_id", &self.session_id);
+        mapping.insert("thread_source", &self.thread_source);
+        if let Some(schema) = &self.schema {
+            let mut schema_mapping = YamlMapping::new();
+            schema_mapping.insert("layout", schema.layout);
+            schema_mapping.insert_opt("version", schema.version.as_ref());
+            mapping.insert("schema", schema_mapping);
+        }
+        mapping.insert_opt("fingerprint", self.fingerprint.as_ref());
+        mapping.insert_opt("model", self.session.model.as_ref());
+        mapping.insert_opt("cwd", self.session.cwd.as_ref());
+        mapping.insert_opt("branch", self.session.git_branch.as_ref());
+        if let Some(parent) = &self.parent {
+            let mut parent_mapping = YamlMapping::new();
+            parent_mapping.insert("uri", &parent.uri);
+            parent_mapping.insert("subagent_uri", &parent.subagent_uri);
+            parent_mapping.insert_opt("spawned_at", parent.spawned_at.as_ref());
+            mapping.insert("parent", parent_mapping);
+        }
+        mapping.insert("mode", self.body.mode());
+
+        match &self.body {
+            HeadBody::Thread => {}
+            HeadBody::SubagentIndex { subagents } => {
+                mapping.insert("aggregate", aggregate_yaml(subagents));
+                mapping.insert("subagents", subagents_yaml(subagents));
+            }
+            HeadBody::SubagentDetail(detail) => {
+                mapping.insert_opt("agent_id", detail.agent_id.as_ref());
+                mapping.insert_opt("subagent_uri", detail.subagent_uri.as_ref());
+                mapping.insert("status", &detail.status);
+                mapping.insert("status_source", &detail.status_source);
+                mapping.insert_opt("child_thread_id", detail.child_thread_id.as_ref());
+                mapping.insert_opt("child_thread_source", detail.child_thread_source.as_ref());
+                mapping.insert_opt(
+                    "child_last_updated_at",
+                    detail.child_last_updated_at.as_ref(),
+                );
+            }
+            HeadBody::PiEntryIndex { entries } => {
+                mapping.insert(
+                    "entries",
+                    entries.iter().map(pi_entry_yaml).collect::<Vec<_>>(),
+                );
+            }
+            HeadBody::PiEntry { entry_id } => mapping.insert("entry_id", entry_id),
+        }
+
+        if let Some(page) = &self.page {
+            let mut page_mapping = YamlMapping::new();
+            page_mapping.insert("number", page.number);
+            page_mapping.insert("size", page.size);
+            page_mapping.insert("total_pages", page.total_pages);
+            page_mapping.insert("total_entries", page.total_entries);
+            page_mapping.insert_opt("prev", page.prev.as_ref());
+            page_mapping.insert_opt("next", page.next.as_ref());
+            mapping.insert("page", page_mapping);
+        }
+
+        insert_warnings(&mut mapping, &self.warnings);
+        mapping
+    }
+
+    pub fn to_frontmatter(&self) -> String {
+        self.to_yaml_mapping().to_frontmatter()
+    }
+
+    /// The frontmatter with only the top-level keys `fields` keeps.
+    pub fn to_frontmatter_with(&self, fields: &HeadFields) -> String {
+        let mut mapping = self.to_yaml_mapping();
+        mapping.retain(|key| fields.keeps(key));
+        mapping.to_frontmatter()
+    }
+
+    /// The drilled-down subagent's status, or the worst status across a
+    /// subagent index. `None` for views without subagents.
+    pub fn subagent_status(&self) -> Option<&str> {
+        match &self.body {
+            HeadBody::SubagentIndex { subagents } => worst_status(all_statuses(subagents)),
+            HeadBody::SubagentDetail(detail) => Some(&detail.status),
+            HeadBody::Thread | HeadBody::PiEntryIndex { .. } | HeadBody::PiEntry { .. } => None,
+        }
+    }
+}
+
+/// Statuses of `agents` and all their nested children.
+fn all_statuses(agents: &[HeadSubagent]) -> Vec<&str> {
+    fn collect<'a>(agents: &'a [HeadSubagent], statuses: &mut Vec<&'a str>) {
+        for agent in agents {
+            statuses.push(&agent.status);
+            collect(&agent.children, statuses);
+        }
+    }
+    let mut statuses = Vec::new();
+    collect(agents, &mut statuses);
+    statuses
+}
+
+/// Rolls up the statuses of every listed subagent, nested children included,
+/// so callers can poll one field.
+fn aggregate_yaml(agents: &[HeadSubagent]) -> YamlMapping {
+    let statuses = all_statuses(agents);
+
+    let mut counts = YamlMapping::new();
+    for &known in KNOWN_STATUSES {
+        let count = statuses.iter().filter(|status| **status == known).count();
+        if count > 0 {
+            counts.insert(known, count);
+        }
+    }
+    let other = statuses
+        .iter()
+        .filter(|status| !KNOWN_STATUSES.contains(status))
+        .count();
+    if other > 0 {
+        counts.insert("other", other);
+    }
+
+    let mut mapping = YamlMapping::new();
+    mapping.insert("total", statuses.len());
+    mapping.insert_opt("worst_status", worst_status(statuses.iter().copied()));
+    mapping.insert("counts", counts);
+    mapping
+}
+
+fn subagents_yaml(agents: &[HeadSubagent]) -> Vec<YamlMapping> {
+    agents
+        .iter()
+        .map(|agent| {
+            let mut mapping = YamlMapping::new();
+            mapping.insert("agent_id", &agent.agent_id);
+            mapping.insert("uri", &agent.uri);
+            mapping.insert_opt("agent_name", agent.agent_name.as_ref());
+            mapping.insert("status", &agent.status);
+            mapping.insert("status_source", &agent.status_source);
+            mapping.insert_opt("last_update", agent.last_update.as_ref());
+            mapping.insert_opt("thread_source", agent.thread_source.as_ref());
+            if !agent.children.is_empty() {
+                mapping.insert("children", subagents_yaml(&agent.children));
+            }
+            mapping
+        })
+        .collect()
+}
+
+fn pi_entry_yaml(entry: &HeadPiEntry) -> YamlMapping {
+    let mut mapping = YamlMapping::new();
+    mapping.insert("entry_id", &entry.entry_id);
+    mapping.insert("uri", &entry.uri);
+    mapping.insert("entry_type", &entry.entry_type);
+    mapping.insert_opt("parent_id", entry.parent_id.as_ref());
+    mapping.insert_opt("timestamp", entry.timestamp.as_ref());
+    mapping.insert_opt("role", entry.role.as_ref());
+    mapping.insert_opt("preview", entry.preview.as_ref());
+    mapping.insert_opt("estimated_tokens", entry.estimated_tokens);
+    mapping.insert("is_leaf", entry.is_leaf);
+    mapping
+}
+
+/// Adds a `warnings` list, most severe first and without duplicates. Nothing
+/// is added when there are no warnings.
+pub(crate) fn insert_warnings(mapping: &mut YamlMapping, warnings: &[Warning]) {
+    let unique = warnings
+        .iter()
+        .map(|warning| {
+            (
+                Reverse(warning.severity),
+                warning.code,
+                warning.message.as_str(),
+            )
+        })
+        .collect::<BTreeSet<_>>();
+
+    if unique.is_empty() {
+        return;
+    }
+
+    let items = unique
+        .into_iter()
+        .map(|(Reverse(severity), code, message)| {
+            let mut item = YamlMapping::new();
+            item.insert("severity", severity.to_string());
+            item.insert("code", code);
+            item.insert("message", message);
+            YamlValue::from(item)
+        })
+        .collect::<Vec<_>>();
+    mapping.insert("warnings", items);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::head::{
+        HEAD_KEYS, HeadBody, HeadDocument, HeadFields, HeadPage, HeadPiEntry, HeadSubagent,
+        HeadSubagentDetail,
+    };
+    use crate::model::{ProviderKind, SessionInfo, StorageSchema, ThreadParent, Warning};
+
+    fn document(provider: ProviderKind, body: HeadBody, warnings: Vec<Warning>) -> HeadDocument {
+        HeadDocument {
+            uri: "agents://codex/019c871c-b1f9-7f60-9c4f-87ed09f13592".to_string(),
+            provider,
+            session_id: "019c871c-b1f9-7f60-9c4f-87ed09f13592".to_string(),
+            thread_source: "/home/dev/.codex/sessions/rollout-it's.jsonl".to_string(),
+            schema: None,
+            fingerprint: None,
+            session: SessionInfo::default(),
+            parent: None,
+            body,
+            page: None,
+            warnings,
+        }
+    }
+
+    fn subagent(agent_id: &str, children: Vec<HeadSubagent>) -> HeadSubagent {
+        HeadSubagent {
+            agent_id: agent_id.to_string(),
+            uri: format!("agents://amp/T-main/{agent_id}"),
+            agent_name: None,
+            status: "completed".to_string(),
+            status_source: "child_rollout".to_string(),
+            last_update: Some("2026-02-23T04:49:10Z".to_string()),
+            thread_source: None,
+            children,
+        }
+    }
+
+    #[test]
+    fn thread_head_matches_snapshot() {
+        let mut head = document(
+            ProviderKind::Codex,
+            HeadBody::Thread,
+            vec![
+                Warning::info("candidates", "other candidate: /tmp/a"),
+                Warning::error("child_missing", "child thread not found"),
+                Warning::info("candidates", "other candidate: /tmp/a"),
+            ],
+        );
+        head.fingerprint = Some("3f1c0a9d2b7e4c5a8f6d1e0b9a2c3d4e".to_string());
+        head.session = SessionInfo {
+            model: Some("o4-mini".to_string()),
+            cwd: Some("/repo".to_string()),
+            git_branch: Some("main".to_string()),
+        };
+
+        assert_eq!(
+            head.to_frontmatter(),
+            include_str!("snapshots/head_thread.yaml")
+        );
+    }
+
+    #[test]
+    fn subagent_index_head_matches_snapshot() {
+        let mut errored = subagent("T-grandchild", Vec::new());
+        errored.status = "errored".to_string();
+        let mut named = subagent("T-child", vec![errored]);
+        named.agent_name = Some("code-reviewer".to_string());
+        named.thread_source = Some("/tmp/child.json".to_string());
+        let head = document(
+            ProviderKind::Amp,
+            HeadBody::SubagentIndex {
+                subagents: vec![named],
+            },
+            Vec::new(),
+        );
+
+        assert_eq!(
+            head.to_frontmatter(),
+            include_str!("snapshots/head_subagent_index.yaml")
+        );
+
+        let empty = document(
+            ProviderKind::Amp,
+            HeadBody::SubagentIndex {
+                subagents: Vec::new(),
+            },
+            Vec::new(),
+        );
+        assert!(empty.to_frontmatter().contains("subagents:\n  []\n"));
+        assert!(
+            empty
+                .to_frontmatter()
+                .contains("aggregate:\n  total: 0\n  counts: {}\n")
+        );
+    }
+
+    #[test]
+    fn subagent_detail_head_matches_snapshot() {
+        let head = document(
+            ProviderKind::Codex,
+            HeadBody::SubagentDetail(HeadSubagentDetail {
+                agent_id: Some("019c87fb".to_string()),
+                subagent_uri: Some("agents://codex/019c871c/019c87fb".to_string()),
+                status: "running".to_string(),
+                status_source: "parent_rollout".to_string(),
+                child_thread_id: Some("019c87fb".to_string()),
+                child_thread_source: None,
+                child_last_updated_at: Some("2026-02-23T04:49:10Z".to_string()),
+            }),
+            vec![Warning::warn("child_missing", "no child rollout\nfound")],
+        );
+
+        assert_eq!(
+            head.to_frontmatter(),
+            include_str!("snapshots/head_subagent_detail.yaml")
+        );
+    }
+
+    #[test]
+    fn pi_entry_index_head_matches_snapshot() {
+        let head = document(
+            ProviderKind::Pi,
+            HeadBody::PiEntryIndex {
+                entries: vec![HeadPiEntry {
+                    entry_id: "d1b2c3d4".to_string(),
+                    uri: "agents://pi/12cb4c19/d1b2c3d4".to_string(),
+                    entry_type: "message".to_string(),
+                    parent_id: Some("a1b2c3d4".to_string()),
+                    timestamp: None,
+                    role: Some("assistant".to_string()),
+                    preview: Some("line one\nline \"two\"\ttabbed".to_string()),
+                    estimated_tokens: Some(7),
+                    is_leaf: true,
+                }],
+            },
+            Vec::new(),
+        );
+
+        assert_eq!(
+            head.to_frontmatter(),
+            include_str!("snapshots/head_pi_entry_index.yaml")
+        );
+    }
+
+    #[test]
+    fn head_fields_keep_or_drop_top_level_keys() {
+        let head = document(
+            ProviderKind::Amp,
+            HeadBody::SubagentIndex {
+                subagents: vec![subagent("T-child", Vec::new())],
+            },
+            vec![Warning::info("candidates", "other candidate: /tmp/a")],
+        );
+
+        let only = HeadFields::parse("uri, provider,subagents").expect("parse");
+        let frontmatter = head.to_frontmatter_with(&only);
+        assert!(frontmatter.starts_with(
+            "---\nuri: 'agents://codex/019c871c-b1f9-7f60-9c4f-87ed09f13592'\nprovider: 'amp'\nsubagents:\n"
+        ));
+        assert!(!frontmatter.contains("aggregate:"));
+        assert!(!frontmatter.contains("warnings:"));
+
+        let except = HeadFields::parse("-warnings,-subagents").expect("parse");
+        let frontmatter = head.to_frontmatter_with(&except);
+        assert!(frontmatter.contains("aggregate:\n"));
+        assert!(frontmatter.contains("mode: 'subagent_index'\n"));
+        assert!(!frontmatter.contains("subagents:"));
+        assert!(!frontmatter.contains("warnings:"));
+
+        for spec in ["uri,-warnings", "uri,agentid", ","] {
+            assert!(HeadFields::parse(spec).is_err(), "{spec} must be rejected");
+        }
+    }
+
+    #[test]
+    fn head_keys_cover_every_emitted_key() {
+        let mut thread = document(
+            ProviderKind::Codex,
+            HeadBody::Thread,
+            vec![Warning::info("candidates", "other candidate: /tmp/a")],
+        );
+        thread.fingerprint = Some("3f1c".to_string());
+        thread.schema = Some(StorageSchema::new("state_db", Some("5".to_string())));
+        thread.session = SessionInfo {
+            model: Some("o4-mini".to_string()),
+            cwd: Some("/repo".to_string()),
+            git_branch: Some("main".to_string()),
+        };
+        thread.parent = Some(ThreadParent {
+            uri: "agents://codex/019c871c".to_string(),
+            subagent_uri: "agents://codex/019c871c/019c87fb".to_string(),
+            spawned_at: None,
+        });
+        thread.page = Some(HeadPage {
+            number: 1,
+            size: 100,
+            total_pages: 1,
+            total_entries: 1,
+            prev: None,
+            next: None,
+        });
+        let bodies = [
+            HeadBody::SubagentIndex {
+                subagents: Vec::new(),
+            },
+            HeadBody::SubagentDetail(HeadSubagentDetail {
+                agent_id: Some("019c87fb".to_string()),
+                subagent_uri: Some("agents://codex/019c871c/019c87fb".to_string()),
+                status: "running".to_string(),
+                status_source: "parent_rollout".to_string(),
+                child_thread_id: Some("019c87fb".to_string()),
+                child_thread_source: Some("/tmp/child.jsonl".to_string()),
+                child_last_updated_at: Some("2026-02-23T04:49:10Z".to_string()),
+            }),
+            HeadBody::PiEntryIndex {
+                entries: Vec::new(),
+            },
+            HeadBody::PiEntry {
+                entry_id: "d1b2c3d4".to_string(),
+            },
+        ];
+
+        let mut heads = vec![thread.clone()];
+        heads.extend(bodies.into_iter().map(|body| HeadDocument {
+            body,
+            ..thread.clone()
+        }));
+        for head in heads {
+            for key in head.to_yaml_mapping().keys() {
+                assert!(HEAD_KEYS.contains(&key), "{key} missing from HEAD_KEYS");
+            }
+        }
+    }
+
+    #[test]
+    fn paged_thread_head_matches_snapshot() {
+        let mut head = document(ProviderKind::Codex, HeadBody::Thread, Vec::new());
+        head.page = Some(HeadPage {
+            number: 2,
+            size: 100,
+            total_pages: 3,
+            total_entries: 250,
+            prev: Some(format!("{}?page=1&page_size=100", head.uri)),
+            next: Some(format!("{}?page=3&page_size=100", head.uri)),
+        });
+
+        assert_eq!(
+            head.to_frontmatter(),
+            include_str!("snapshots/head_thread_page.yaml")
+        );
+    }
+}
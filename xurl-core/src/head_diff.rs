@@ -0,0 +1,326 @@
+//! Head frontmatter compared against a stored baseline (`xurl head-diff`).
+//!
+//! Both sides are flattened into `path: value` leaves, e.g.
+//! `subagents[agent-1].status: 'running'`, and compared leaf by leaf.
+//! Subagents and Pi entries are addressed by their id rather than their
+//! position, so a reordered index is not a change; plain lists such as
+//! `warnings` are compared as sets of items.
+//!
+//! The baseline is expected to be a head written by xurl itself (`-I` or
+//! `head-diff --update`): the flattener reads the block layout
+//! [`crate::yaml`] emits and compares scalars in their quoted form.
+
+use std::fs;
+use std::path::Path;
+
+use crate::error::{Result, XurlError};
+use crate::head::HeadFields;
+use crate::model::{HeadChange, HeadDiff};
+use crate::provider::ProviderRoots;
+use crate::render::ReadOptions;
+use crate::service::build_thread_head;
+use crate::uri::ThreadUri;
+
+/// Keys that identify an item of a listed sequence, checked on its first key.
+const ITEM_ID_KEYS: &[&str] = &["agent_id", "entry_id"];
+
+/// Compares the head of `uri` with the frontmatter stored at `baseline`.
+/// With `fields`, only the selected top-level keys are compared.
+pub fn diff_thread_head(
+    uri: &ThreadUri,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+    baseline: &Path,
+    fields: Option<&HeadFields>,
+) -> Result<HeadDiff> {
+    let stored = fs::read_to_string(baseline).map_err(|source| XurlError::Io {
+        path: baseline.to_path_buf(),
+        source,
+    })?;
+    let head = build_thread_head(uri, roots, options)?;
+    let current = head.to_frontmatter();
+
+    let keeps = |leaf: &Leaf| fields.is_none_or(|fields| fields.keeps(&leaf.key));
+    let stored = flatten_frontmatter(&stored).map_err(|message| {
+        XurlError::InvalidMode(format!(
+            "baseline {} is not head frontmatter: {message}",
+            baseline.display()
+        ))
+    })?;
+    let current = flatten_frontmatter(&current).map_err(XurlError::InvalidMode)?;
+    let stored = stored.into_iter().filter(keeps).collect::<Vec<_>>();
+    let current = current.into_iter().filter(keeps).collect::<Vec<_>>();
+
+    Ok(HeadDiff {
+        uri: head.uri,
+        baseline: baseline.display().to_string(),
+        changes: compare_leaves(&stored, &current),
+    })
+}
+
+pub fn render_head_diff_markdown(diff: &HeadDiff) -> String {
+    let mut output = String::new();
+    output.push_str("# Head Diff\n\n");
+    output.push_str(&format!("- URI: `{}`\n", diff.uri));
+    output.push_str(&format!("- Baseline: `{}`\n", diff.baseline));
+    if diff.is_unchanged() {
+        output.push_str("- Result: `unchanged`\n");
+        return output;
+    }
+    output.push_str(&format!("- Result: `{} change(s)`\n", diff.changes.len()));
+
+    output.push_str("\n```diff\n");
+    for change in &diff.changes {
+        if let Some(value) = &change.baseline {
+            output.push_str(&format!("- {}: {value}\n", change.path));
+        }
+        if let Some(value) = &change.current {
+            output.push_str(&format!("+ {}: {value}\n", change.path));
+        }
+    }
+    output.push_str("```\n");
+    output
+}
+
+pub fn render_head_diff_json(diff: &HeadDiff) -> Result<String> {
+    serde_json::to_string_pretty(diff)
+        .map(|json| format!("{json}\n"))
+        .map_err(|err| XurlError::Serialization(err.to_string()))
+}
+
+/// One scalar of the frontmatter with its full path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Leaf {
+    /// Top-level key the leaf belongs to, for `--head-fields`.
+    key: String,
+    path: String,
+    value: String,
+}
+
+/// An open mapping key or sequence item while flattening.
+struct Frame {
+    indent: usize,
+    segment: String,
+    /// Sequence items seen directly under this frame.
+    items: usize,
+}
+
+/// Flattens frontmatter emitted by [`crate::yaml`] into its leaves, in
+/// document order. Items of plain lists share the path `key[]`.
+fn flatten_frontmatter(text: &str) -> std::result::Result<Vec<Leaf>, String> {
+    let mut leaves = Vec::new();
+    let mut stack: Vec<Frame> = Vec::new();
+
+    for (idx, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line == "---" {
+            continue;
+        }
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        let content = &line[indent..];
+        while stack.last().is_some_and(|frame| frame.indent >= indent) {
+            stack.pop();
+        }
+
+        if let Some(item) = content.strip_prefix('-') {
+            let item = item.trim_start();
+            let parent = stack
+                .last_mut()
+                .ok_or_else(|| format!("line {}: list item outside a key", idx + 1))?;
+            let position = parent.items;
+            parent.items += 1;
+
+            match split_key(item) {
+                Some((key, value)) => {
+                    let segment = if ITEM_ID_KEYS.contains(&key) {
+                        format!("[{}]", unquote(value))
+                    } else {
+                        format!("[{position}]")
+                    };
+                    stack.push(Frame {
+                        indent,
+                        segment,
+                        items: 0,
+                    });
+                    push_key(&mut leaves, &mut stack, indent + 2, key, value);
+                }
+                None if item.is_empty() => stack.push(Frame {
+                    indent,
+                    segment: format!("[{position}]"),
+                    items: 0,
+                }),
+                None => {
+                    leaves.push(leaf(&stack, "[]", item));
+                }
+            }
+            continue;
+        }
+
+        match split_key(content) {
+            Some((key, value)) => push_key(&mut leaves, &mut stack, indent, key, value),
+            None if content == "[]" && !stack.is_empty() => leaves.push(leaf(&stack, "", "[]")),
+            None => return Err(format!("line {}: unexpected {content:?}", idx + 1)),
+        }
+    }
+
+    Ok(leaves)
+}
+
+/// Records `key: value`, or opens `key` when its value follows on later lines.
+fn push_key(leaves: &mut Vec<Leaf>, stack: &mut Vec<Frame>, indent: usize, key: &str, value: &str) {
+    if value.is_empty() {
+        stack.push(Frame {
+            indent,
+            segment: key.to_string(),
+            items: 0,
+        });
+    } else {
+        leaves.push(leaf(stack, key, value));
+    }
+}
+
+fn leaf(stack: &[Frame], last: &str, value: &str) -> Leaf {
+    let mut path = String::new();
+    for segment in stack
+        .iter()
+        .map(|frame| frame.segment.as_str())
+        .chain([last])
+    {
+        if !path.is_empty() && !segment.is_empty() && !segment.starts_with('[') {
+            path.push('.');
+        }
+        path.push_str(segment);
+    }
+    let key = path
+        .split(['.', '['])
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    Leaf {
+        key,
+        path,
+        value: value.to_string(),
+    }
+}
+
+/// Splits `key: value` or `key:`. Keys are plain identifiers; scalars are
+/// quoted, booleans, or integers, so neither is mistaken for the other.
+fn split_key(content: &str) -> Option<(&str, &str)> {
+    let (key, value) = content.split_once(':')?;
+    let is_key = key
+        .chars()
+        .next()
+        .is_some_and(|ch| ch.is_ascii_alphabetic() || ch == '_')
+        && key
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '_');
+    (is_key && (value.is_empty() || value.starts_with(' '))).then(|| (key, value.trim()))
+}
+
+/// A single-quoted scalar without its quotes, for use in a path.
+fn unquote(value: &str) -> String {
+    value
+        .strip_prefix('\'')
+        .and_then(|value| value.strip_suffix('\''))
+        .map_or_else(|| value.to_string(), |value| value.replace("''", "'"))
+}
+
+/// Pairs up the leaves of both sides by path, in baseline order followed by
+/// paths only the current head has. A path holding one value on each side
+/// is a change; otherwise values only one side has are listed as removed or
+/// added.
+fn compare_leaves(stored: &[Leaf], current: &[Leaf]) -> Vec<HeadChange> {
+    let mut paths: Vec<&str> = Vec::new();
+    for leaf in stored.iter().chain(current) {
+        if !paths.contains(&leaf.path.as_str()) {
+            paths.push(&leaf.path);
+        }
+    }
+
+    let values = |leaves: &[Leaf], path: &str| {
+        leaves
+            .iter()
+            .filter(|leaf| leaf.path == path)
+            .map(|leaf| leaf.value.clone())
+            .collect::<Vec<_>>()
+    };
+    let mut changes = Vec::new();
+    for path in paths {
+        let mut before = values(stored, path);
+        let mut after = values(current, path);
+        if let ([before], [after]) = (before.as_slice(), after.as_slice()) {
+            if before != after {
+                changes.push(HeadChange {
+                    path: path.to_string(),
+                    baseline: Some(before.clone()),
+                    current: Some(after.clone()),
+                });
+            }
+            continue;
+        }
+
+        before.retain(|value| {
+            let Some(idx) = after.iter().position(|other| other == value) else {
+                return true;
+            };
+            after.remove(idx);
+            false
+        });
+        changes.extend(before.into_iter().map(|value| HeadChange {
+            path: path.to_string(),
+            baseline: Some(value),
+            current: None,
+        }));
+        changes.extend(after.into_iter().map(|value| HeadChange {
+            path: path.to_string(),
+            baseline: None,
+            current: Some(value),
+        }));
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::head_diff::{compare_leaves, flatten_frontmatter};
+    use crate::model::HeadChange;
+
+    #[test]
+    fn compares_subagents_by_id_and_warnings_as_sets() {
+        let baseline = "---\nuri: 'agents://codex/s'\nmode: 'subagent_index'\nsubagents:\n  - agent_id: 'a'\n    status: 'running'\n  - agent_id: 'b'\n    status: 'completed'\nwarnings:\n  - 'first'\n  - 'second'\n---\n";
+        let current = "---\nuri: 'agents://codex/s'\nmode: 'subagent_index'\nsubagents:\n  - agent_id: 'b'\n    status: 'completed'\n  - agent_id: 'a'\n    status: 'errored'\nwarnings:\n  - 'second'\n  - 'third'\n---\n";
+
+        let stored = flatten_frontmatter(baseline).expect("baseline");
+        let current = flatten_frontmatter(current).expect("current");
+        let change = |path: &str, baseline: Option<&str>, current: Option<&str>| HeadChange {
+            path: path.to_string(),
+            baseline: baseline.map(str::to_string),
+            current: current.map(str::to_string),
+        };
+        assert_eq!(
+            compare_leaves(&stored, &current),
+            vec![
+                change("subagents[a].status", Some("'running'"), Some("'errored'")),
+                change("warnings[]", Some("'first'"), None),
+                change("warnings[]", None, Some("'third'")),
+            ]
+        );
+    }
+
+    #[test]
+    fn flattens_nested_mappings_and_empty_lists() {
+        let leaves = flatten_frontmatter(
+            "---\naggregate:\n  total: 0\n  counts: {}\nsubagents:\n  []\n---\n",
+        )
+        .expect("flatten");
+        let paths = leaves
+            .iter()
+            .map(|leaf| format!("{}={}", leaf.path, leaf.value))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            ["aggregate.total=0", "aggregate.counts={}", "subagents=[]"]
+        );
+        assert!(leaves.iter().all(|leaf| leaf.key != "uri"));
+        assert!(flatten_frontmatter("- 'stray'\n").is_err());
+    }
+}
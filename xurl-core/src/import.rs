@@ -0,0 +1,452 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+use crate::error::{Result, XurlError};
+use crate::jsonl;
+use crate::model::{ImportRequest, ImportResult, ProviderKind, Warning};
+use crate::provider::ProviderRoots;
+use crate::provider::claude::ClaudeProvider;
+use crate::provider::codex::CodexProvider;
+use crate::service::{read_thread_raw, resolve_thread};
+use crate::timestamp::parse_timestamp;
+use crate::uri::ThreadUri;
+
+const DEFAULT_IMPORT_PROJECT: &str = "xurl-imported";
+
+/// Copies an external transcript into the local storage layout of
+/// `req.provider` so it resolves like any other local thread.
+pub fn import_thread(roots: &ProviderRoots, req: &ImportRequest) -> Result<ImportResult> {
+    if matches!(req.provider, ProviderKind::Opencode | ProviderKind::File) {
+        return Err(XurlError::UnsupportedProviderImport(
+            req.provider.to_string(),
+        ));
+    }
+    roots.ensure_own_root(req.provider)?;
+
+    let raw = read_thread_raw(&req.source)?;
+    let embedded = embedded_session_id(req.provider, &req.source, &raw)?;
+    let session_id = match (req.session_id.as_deref(), embedded.as_deref()) {
+        (Some(requested), Some(embedded)) if !requested.eq_ignore_ascii_case(embedded) => {
+            return Err(XurlError::InvalidSessionId(format!(
+                "{requested} does not match session id {embedded} recorded in {}",
+                req.source.display()
+            )));
+        }
+        (Some(requested), _) => requested.to_string(),
+        (None, Some(embedded)) => embedded.to_string(),
+        (None, None) => generate_session_id(req.provider),
+    };
+
+    let uri = ThreadUri::parse(&format!("{}://{session_id}", req.provider))?;
+    let session_id = uri.session_id.clone();
+    let content = if embedded.is_some() {
+        raw
+    } else {
+        inject_session_id(req.provider, &req.source, &raw, &session_id)?
+    };
+
+    let existing = resolve_thread(&uri, roots).ok();
+    if let Some(existing) = &existing
+        && !req.force
+    {
+        return Err(XurlError::ThreadAlreadyExists {
+            provider: req.provider.to_string(),
+            session_id,
+            path: existing.path.clone(),
+        });
+    }
+
+    let started_at = transcript_started_at(req.provider, &content).unwrap_or_else(Utc::now);
+    let path = match existing {
+        Some(existing) => existing.path,
+        None => destination_path(roots, req, &session_id, &content, started_at)?,
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|source| XurlError::Io {
+            path: parent.to_path_buf(),
+            source,
+        })?;
+    }
+    fs::write(&path, &content).map_err(|source| XurlError::Io {
+        path: path.clone(),
+        source,
+    })?;
+
+    let mut warnings = Vec::new();
+    let mut registered_in = None;
+    if req.provider == ProviderKind::Codex {
+        let cwd = codex_session_meta(&content).and_then(|payload| {
+            payload
+                .get("cwd")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        });
+        match CodexProvider::new(&roots.codex_root).register_rollout(
+            &session_id,
+            &path,
+            started_at.timestamp(),
+            cwd.as_deref(),
+        ) {
+            Ok(db_path) => registered_in = db_path,
+            Err(err) => warnings.push(Warning::warn(
+                "index_update_failed",
+                format!("imported rollout was not registered in the sqlite thread index: {err}"),
+            )),
+        }
+    }
+
+    let resolved = resolve_thread(&uri, roots)?;
+    warnings.extend(resolved.metadata.warnings);
+
+    Ok(ImportResult {
+        provider: req.provider,
+        session_id,
+        path,
+        registered_in,
+        warnings,
+    })
+}
+
+fn embedded_session_id(provider: ProviderKind, path: &Path, raw: &str) -> Result<Option<String>> {
+    let id = match provider {
+        ProviderKind::Codex => codex_session_meta(raw).and_then(|payload| {
+            payload
+                .get("id")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        }),
+        ProviderKind::Claude => jsonl_values(raw).find_map(|value| {
+            value
+                .get("sessionId")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        }),
+        ProviderKind::Copilot => jsonl_values(raw)
+            .find(|value| value.get("type").and_then(Value::as_str) == Some("session.start"))
+            .and_then(|value| {
+                value
+                    .pointer("/data/sessionId")
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+            }),
+        ProviderKind::Pi => jsonl_values(raw)
+            .next()
+            .filter(|value| value.get("type").and_then(Value::as_str) == Some("session"))
+            .and_then(|value| value.get("id").and_then(Value::as_str).map(str::to_string)),
+        ProviderKind::Amp | ProviderKind::Gemini => {
+            let value = parse_json_document(path, raw)?;
+            let key = if provider == ProviderKind::Amp {
+                "id"
+            } else {
+                "sessionId"
+            };
+            value.get(key).and_then(Value::as_str).map(str::to_string)
+        }
+        ProviderKind::Opencode | ProviderKind::File => None,
+    };
+
+    Ok(id.filter(|id| !id.trim().is_empty()))
+}
+
+fn inject_session_id(
+    provider: ProviderKind,
+    path: &Path,
+    raw: &str,
+    session_id: &str,
+) -> Result<String> {
+    match provider {
+        // Gemini and Pi match sessions by the id recorded inside the file,
+        // so it has to be written there; the others resolve by file name.
+        ProviderKind::Gemini => {
+            let mut value = parse_json_document(path, raw)?;
+            let Some(object) = value.as_object_mut() else {
+                return Err(XurlError::InvalidMode(format!(
+                    "gemini transcript {} is not a JSON object",
+                    path.display()
+                )));
+            };
+            object.insert(
+                "sessionId".to_string(),
+                Value::String(session_id.to_string()),
+            );
+            serde_json::to_string_pretty(&value)
+                .map_err(|err| XurlError::Serialization(err.to_string()))
+        }
+        ProviderKind::Pi => {
+            let header = serde_json::json!({
+                "type": "session",
+                "id": session_id,
+                "timestamp": Utc::now().to_rfc3339(),
+            });
+            let mut lines = raw.lines();
+            let first = lines.next().unwrap_or_default();
+            let rest = lines.collect::<Vec<_>>().join("\n");
+            match serde_json::from_str::<Value>(first) {
+                Ok(mut value) if value.get("type").and_then(Value::as_str) == Some("session") => {
+                    value["id"] = Value::String(session_id.to_string());
+                    Ok(format!("{value}\n{rest}\n"))
+                }
+                _ => Ok(format!("{header}\n{raw}")),
+            }
+        }
+        ProviderKind::Amp
+        | ProviderKind::Codex
+        | ProviderKind::Claude
+        | ProviderKind::Opencode
+        | ProviderKind::Copilot
+        | ProviderKind::File => Ok(raw.to_string()),
+    }
+}
+
+fn destination_path(
+    roots: &ProviderRoots,
+    req: &ImportRequest,
+    session_id: &str,
+    content: &str,
+    started_at: DateTime<Utc>,
+) -> Result<PathBuf> {
+    let path = match req.provider {
+        ProviderKind::Codex => roots
+            .codex_root
+            .join("sessions")
+            .join(started_at.format("%Y").to_string())
+            .join(started_at.format("%m").to_string())
+            .join(started_at.format("%d").to_string())
+            .join(format!(
+                "rollout-{}-{session_id}.jsonl",
+                started_at.format("%Y-%m-%dT%H-%M-%S")
+            )),
+        ProviderKind::Claude => {
+            let projects = roots.claude_root.join("projects");
+            let project = req
+                .project
+                .clone()
+                .or_else(|| {
+                    jsonl_values(content).find_map(|value| {
+                        value.get("cwd").and_then(Value::as_str).map(str::to_string)
+                    })
+                })
+                .map_or_else(
+                    || DEFAULT_IMPORT_PROJECT.to_string(),
+                    |project| ClaudeProvider::project_dir_name(&projects, &project),
+                );
+            projects.join(project).join(format!("{session_id}.jsonl"))
+        }
+        ProviderKind::Gemini => roots
+            .gemini_root
+            .join("tmp")
+            .join(req.project.as_deref().unwrap_or(DEFAULT_IMPORT_PROJECT))
+            .join("chats")
+            .join(format!(
+                "session-{}-{}.json",
+                started_at.format("%Y-%m-%dT%H-%M"),
+                session_id.chars().take(8).collect::<String>()
+            )),
+        ProviderKind::Pi => roots
+            .pi_root
+            .join("sessions")
+            .join(req.project.as_deref().unwrap_or(DEFAULT_IMPORT_PROJECT))
+            .join(format!(
+                "{}_{session_id}.jsonl",
+                started_at.format("%Y-%m-%dT%H-%M-%S-%3fZ")
+            )),
+        ProviderKind::Amp => roots
+            .amp_root
+            .join("threads")
+            .join(format!("{session_id}.json")),
+        ProviderKind::Copilot => roots
+            .copilot_root
+            .join("session-state")
+            .join(format!("{session_id}.jsonl")),
+        ProviderKind::Opencode | ProviderKind::File => {
+            return Err(XurlError::UnsupportedProviderImport(
+                req.provider.to_string(),
+            ));
+        }
+    };
+
+    Ok(path)
+}
+
+pub(crate) fn transcript_started_at(
+    provider: ProviderKind,
+    content: &str,
+) -> Option<DateTime<Utc>> {
+    let value = match provider {
+        ProviderKind::Amp | ProviderKind::Gemini => {
+            let value = serde_json::from_str::<Value>(content).ok()?;
+            ["startTime", "created", "createdAt"]
+                .iter()
+                .find_map(|key| value.get(*key).and_then(json_timestamp_string))?
+        }
+        _ => jsonl_values(content)
+            .find_map(|value| value.get("timestamp").and_then(json_timestamp_string))?,
+    };
+    parse_timestamp(&value)
+}
+
+fn json_timestamp_string(value: &Value) -> Option<String> {
+    value
+        .as_str()
+        .map(str::to_string)
+        .or_else(|| value.as_i64().map(|number| number.to_string()))
+}
+
+pub(crate) fn codex_session_meta(raw: &str) -> Option<Value> {
+    jsonl_values(raw)
+        .find(|value| value.get("type").and_then(Value::as_str) == Some("session_meta"))
+        .and_then(|value| value.get("payload").cloned())
+}
+
+fn jsonl_values(raw: &str) -> impl Iterator<Item = Value> + '_ {
+    raw.lines().enumerate().filter_map(|(index, line)| {
+        jsonl::parse_json_line(Path::new("<import>"), index + 1, line)
+            .ok()
+            .flatten()
+    })
+}
+
+fn parse_json_document(path: &Path, raw: &str) -> Result<Value> {
+    serde_json::from_str::<Value>(raw).map_err(|source| XurlError::InvalidJsonLine {
+        path: path.to_path_buf(),
+        line: 1,
+        source,
+    })
+}
+
+fn generate_session_id(provider: ProviderKind) -> String {
+    let id = uuid::Uuid::new_v4().to_string();
+    if provider == ProviderKind::Amp {
+        format!("T-{id}")
+    } else {
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use tempfile::tempdir;
+
+    use crate::error::XurlError;
+    use crate::import::import_thread;
+    use crate::model::{ImportRequest, ProviderKind};
+    use crate::provider::ProviderRoots;
+
+    const SESSION_ID: &str = "019c871c-b1f9-7f60-9c4f-87ed09f13592";
+
+    fn request(provider: ProviderKind, source: &Path) -> ImportRequest {
+        ImportRequest {
+            provider,
+            source: source.to_path_buf(),
+            session_id: None,
+            project: None,
+            force: false,
+        }
+    }
+
+    #[test]
+    fn imports_codex_rollout_into_dated_sessions_dir() {
+        let temp = tempdir().expect("tempdir");
+        let source = temp.path().join("shared.jsonl");
+        fs::write(
+            &source,
+            format!(
+                "{{\"timestamp\":\"2026-02-23T04:48:50Z\",\"type\":\"session_meta\",\"payload\":{{\"id\":\"{SESSION_ID}\",\"cwd\":\"/work\"}}}}\n"
+            ),
+        )
+        .expect("write");
+
+        let roots = ProviderRoots::for_test(temp.path());
+        let result = import_thread(&roots, &request(ProviderKind::Codex, &source)).expect("import");
+        assert_eq!(result.session_id, SESSION_ID);
+        assert_eq!(
+            result.path,
+            temp.path().join(format!(
+                "codex/sessions/2026/02/23/rollout-2026-02-23T04-48-50-{SESSION_ID}.jsonl"
+            ))
+        );
+
+        let err = import_thread(&roots, &request(ProviderKind::Codex, &source))
+            .expect_err("second import must not overwrite");
+        assert!(matches!(err, XurlError::ThreadAlreadyExists { .. }));
+    }
+
+    #[test]
+    fn registers_codex_rollout_in_state_db() {
+        let temp = tempdir().expect("tempdir");
+        let roots = ProviderRoots::for_test(temp.path());
+        fs::create_dir_all(&roots.codex_root).expect("mkdir");
+        let db_path = roots.codex_root.join("state.sqlite");
+        let conn = rusqlite::Connection::open(&db_path).expect("open");
+        conn.execute(
+            "CREATE TABLE threads (id TEXT PRIMARY KEY, rollout_path TEXT NOT NULL, archived INTEGER NOT NULL, title TEXT NOT NULL, tokens_used INTEGER NOT NULL)",
+            [],
+        )
+        .expect("create");
+        drop(conn);
+
+        let source = temp.path().join("shared.jsonl");
+        fs::write(
+            &source,
+            format!(
+                "{{\"timestamp\":\"2026-02-23T04:48:50Z\",\"type\":\"session_meta\",\"payload\":{{\"id\":\"{SESSION_ID}\"}}}}\n"
+            ),
+        )
+        .expect("write");
+
+        let result = import_thread(&roots, &request(ProviderKind::Codex, &source)).expect("import");
+        assert_eq!(result.registered_in.as_deref(), Some(db_path.as_path()));
+
+        let conn = rusqlite::Connection::open(&db_path).expect("open");
+        let rollout_path: String = conn
+            .query_row(
+                "SELECT rollout_path FROM threads WHERE id = ?1",
+                [SESSION_ID],
+                |row| row.get(0),
+            )
+            .expect("row");
+        assert_eq!(rollout_path, result.path.display().to_string());
+    }
+
+    #[test]
+    fn rejects_session_id_that_conflicts_with_transcript() {
+        let temp = tempdir().expect("tempdir");
+        let source = temp.path().join("claude.jsonl");
+        fs::write(
+            &source,
+            format!("{{\"type\":\"user\",\"sessionId\":\"{SESSION_ID}\"}}\n"),
+        )
+        .expect("write");
+
+        let mut req = request(ProviderKind::Claude, &source);
+        req.session_id = Some("2823d1df-720a-4c31-ac55-ae8ba726721f".to_string());
+        let err =
+            import_thread(&ProviderRoots::for_test(temp.path()), &req).expect_err("must fail");
+        assert!(matches!(err, XurlError::InvalidSessionId(_)));
+    }
+
+    #[test]
+    fn assigns_session_id_to_gemini_chat_without_one() {
+        let temp = tempdir().expect("tempdir");
+        let source = temp.path().join("chat.json");
+        fs::write(&source, r#"{"messages":[]}"#).expect("write");
+
+        let mut req = request(ProviderKind::Gemini, &source);
+        req.session_id = Some("29d207db-ca7e-40ba-87f7-e14c9de60613".to_string());
+        let result = import_thread(&ProviderRoots::for_test(temp.path()), &req).expect("import");
+
+        let written = fs::read_to_string(&result.path).expect("read");
+        assert!(written.contains("\"sessionId\": \"29d207db-ca7e-40ba-87f7-e14c9de60613\""));
+        assert!(
+            result
+                .path
+                .starts_with(temp.path().join("gemini/tmp/xurl-imported/chats"))
+        );
+    }
+}
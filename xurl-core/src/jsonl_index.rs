@@ -0,0 +1,349 @@
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{Result, XurlError};
+
+const INDEX_VERSION: u32 = 3;
+const FINGERPRINT_BYTES: u64 = 4096;
+
+/// What a rollout line carries, so readers can skip lines they do not need.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineKind {
+    Message,
+    /// A chunk of a streamed reply (`agent_message_delta`).
+    MessageDelta,
+    ToolCall,
+    ToolOutput,
+    Compact,
+    Other,
+    /// Not valid JSON; always handed to readers so they report it as before.
+    Invalid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexedLine {
+    pub offset: u64,
+    pub len: u64,
+    pub kind: LineKind,
+}
+
+/// Byte offsets and kinds of every line in a Codex rollout, cached under
+/// `<cache_root>/jsonl-index` and extended in place as the rollout grows.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonlIndex {
+    version: u32,
+    source: PathBuf,
+    /// Bytes of the source covered by complete (newline-terminated) lines.
+    indexed_len: u64,
+    /// Length and modification time of the source when it was indexed.
+    source_len: u64,
+    source_modified: Option<SystemTime>,
+    /// Hash of the first and last indexed bytes, used to notice a rewritten
+    /// file.
+    fingerprint: u64,
+    lines: Vec<IndexedLine>,
+    /// Trailing line without a newline yet; indexed but never persisted.
+    #[serde(skip)]
+    partial: Option<IndexedLine>,
+}
+
+impl JsonlIndex {
+    /// Loads the cached index for `source`, reparsing only bytes appended since
+    /// it was stored. The cache is kept while the source has the length and
+    /// modification time it was indexed at, or has only grown since; a
+    /// shrunk, touched, or rewritten source is reindexed from scratch.
+    pub fn load_or_update(cache_root: &Path, source: &Path) -> Result<Self> {
+        let meta = fs::metadata(source).map_err(|err| io_error(source, err))?;
+        let source_len = meta.len();
+        let source_modified = meta.modified().ok();
+        let cache_path = cache_path(cache_root, source);
+
+        let mut index = read_cached(&cache_path)
+            .filter(|index| {
+                let unchanged_or_grown = match source_len.cmp(&index.source_len) {
+                    Ordering::Equal => source_modified == index.source_modified,
+                    Ordering::Greater => true,
+                    Ordering::Less => false,
+                };
+                index.version == INDEX_VERSION
+                    && index.source == source
+                    && index.indexed_len <= source_len
+                    && unchanged_or_grown
+                    && fingerprint(source, index.indexed_len)
+                        .is_ok_and(|fingerprint| fingerprint == index.fingerprint)
+            })
+            .unwrap_or_else(|| Self {
+                version: INDEX_VERSION,
+                source: source.to_path_buf(),
+                indexed_len: 0,
+                source_len: 0,
+                source_modified: None,
+                fingerprint: 0,
+                lines: Vec::new(),
+                partial: None,
+            });
+
+        if index.indexed_len < source_len || index.source_len != source_len {
+            let before = (index.indexed_len, index.source_len, index.source_modified);
+            if index.indexed_len < source_len {
+                index.extend(source)?;
+            }
+            index.source_len = source_len;
+            index.source_modified = source_modified;
+            if (index.indexed_len, index.source_len, index.source_modified) != before {
+                index.fingerprint = fingerprint(source, index.indexed_len)?;
+                // The cache only saves work; a read-only cache dir must not
+                // break reading the thread.
+                let _ = write_cached(&cache_path, &index);
+            }
+        }
+
+        Ok(index)
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = &IndexedLine> {
+        self.lines.iter().chain(self.partial.as_ref())
+    }
+
+    /// Reads the lines of the given kinds (plus invalid ones). Skipped lines
+    /// are left blank so line numbers in parse errors still match the file,
+    /// and a trailing line still being written keeps its missing newline.
+    pub fn read_selected(&self, kinds: &[LineKind]) -> Result<String> {
+        let mut file = File::open(&self.source).map_err(|err| io_error(&self.source, err))?;
+        let mut output = String::new();
+        let mut buffer = Vec::new();
+
+        for line in self.lines() {
+            if line.kind != LineKind::Invalid && !kinds.contains(&line.kind) {
+                output.push('\n');
+                continue;
+            }
+
+            buffer.clear();
+            file.seek(SeekFrom::Start(line.offset))
+                .map_err(|err| io_error(&self.source, err))?;
+            (&mut file)
+                .take(line.len)
+                .read_to_end(&mut buffer)
+                .map_err(|err| io_error(&self.source, err))?;
+            let text = std::str::from_utf8(&buffer).map_err(|_| XurlError::NonUtf8ThreadFile {
+                path: self.source.clone(),
+            })?;
+            output.push_str(text.trim_end_matches(['\n', '\r']));
+            output.push('\n');
+        }
+        if self.partial.is_some() {
+            output.pop();
+        }
+
+        Ok(output)
+    }
+
+    fn extend(&mut self, source: &Path) -> Result<()> {
+        let mut file = File::open(source).map_err(|err| io_error(source, err))?;
+        file.seek(SeekFrom::Start(self.indexed_len))
+            .map_err(|err| io_error(source, err))?;
+        let mut reader = BufReader::new(file);
+        let mut offset = self.indexed_len;
+        let mut buffer = Vec::new();
+        self.partial = None;
+
+        loop {
+            buffer.clear();
+            let read = reader
+                .read_until(b'\n', &mut buffer)
+                .map_err(|err| io_error(source, err))?;
+            if read == 0 {
+                break;
+            }
+
+            let line = IndexedLine {
+                offset,
+                len: read as u64,
+                kind: classify_codex_line(&buffer),
+            };
+            offset += read as u64;
+
+            if buffer.ends_with(b"\n") {
+                self.lines.push(line);
+                self.indexed_len = offset;
+            } else {
+                self.partial = Some(line);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn classify_codex_line(line: &[u8]) -> LineKind {
+    let Ok(text) = std::str::from_utf8(line) else {
+        return LineKind::Invalid;
+    };
+    if text.trim().is_empty() {
+        return LineKind::Other;
+    }
+    let Ok(value) = serde_json::from_str::<Value>(text.trim()) else {
+        return LineKind::Invalid;
+    };
+
+    let payload_type = value
+        .get("payload")
+        .and_then(|payload| payload.get("type"))
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+
+    match value.get("type").and_then(Value::as_str) {
+        Some("response_item") if payload_type == "message" => LineKind::Message,
+        Some("response_item") if payload_type.ends_with("_output") => LineKind::ToolOutput,
+        Some("response_item") if payload_type.ends_with("_call") => LineKind::ToolCall,
+        Some("event_msg") if payload_type == "agent_message" => LineKind::Message,
+        Some("event_msg") if payload_type == "agent_message_delta" => LineKind::MessageDelta,
+        Some("event_msg") if payload_type == "context_compacted" => LineKind::Compact,
+        Some("compacted") => LineKind::Compact,
+        _ => LineKind::Other,
+    }
+}
+
+/// Hashes the first and the last `FINGERPRINT_BYTES` of the indexed bytes.
+fn fingerprint(source: &Path, indexed_len: u64) -> Result<u64> {
+    let mut file = File::open(source).map_err(|err| io_error(source, err))?;
+    let tail_start = indexed_len.saturating_sub(FINGERPRINT_BYTES);
+    let mut hasher = DefaultHasher::new();
+    for start in [0, tail_start] {
+        let mut chunk = Vec::new();
+        file.seek(SeekFrom::Start(start))
+            .map_err(|err| io_error(source, err))?;
+        (&mut file)
+            .take(FINGERPRINT_BYTES.min(indexed_len - start))
+            .read_to_end(&mut chunk)
+            .map_err(|err| io_error(source, err))?;
+        chunk.hash(&mut hasher);
+    }
+    Ok(hasher.finish())
+}
+
+fn cache_path(cache_root: &Path, source: &Path) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    cache_root
+        .join("jsonl-index")
+        .join(format!("{:016x}.json", hasher.finish()))
+}
+
+fn read_cached(cache_path: &Path) -> Option<JsonlIndex> {
+    let raw = fs::read(cache_path).ok()?;
+    serde_json::from_slice(&raw).ok()
+}
+
+fn write_cached(cache_path: &Path, index: &JsonlIndex) -> Result<()> {
+    let parent = cache_path.parent().unwrap_or(cache_path);
+    fs::create_dir_all(parent).map_err(|err| io_error(parent, err))?;
+    let encoded =
+        serde_json::to_vec(index).map_err(|err| XurlError::Serialization(err.to_string()))?;
+
+    // Write then rename so a concurrent reader never sees a torn index.
+    let staging = cache_path.with_extension(format!("json.{}", std::process::id()));
+    fs::write(&staging, encoded).map_err(|err| io_error(&staging, err))?;
+    fs::rename(&staging, cache_path).map_err(|err| io_error(cache_path, err))
+}
+
+fn io_error(path: &Path, source: std::io::Error) -> XurlError {
+    XurlError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File, OpenOptions};
+    use std::io::Write;
+    use std::time::{Duration, SystemTime};
+
+    use tempfile::tempdir;
+
+    use crate::jsonl_index::{JsonlIndex, LineKind};
+
+    const USER: &str = r#"{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"hello"}]}}"#;
+    const CALL: &str = r#"{"type":"response_item","payload":{"type":"function_call","call_id":"c1","name":"spawn_agent","arguments":"{}"}}"#;
+    const TOKENS: &str = r#"{"type":"event_msg","payload":{"type":"token_count"}}"#;
+
+    #[test]
+    fn indexes_appended_lines_incrementally() {
+        let temp = tempdir().expect("tempdir");
+        let cache = temp.path().join("cache");
+        let source = temp.path().join("rollout.jsonl");
+        fs::write(&source, format!("{USER}\n{TOKENS}\n")).expect("write");
+
+        let index = JsonlIndex::load_or_update(&cache, &source).expect("index");
+        assert_eq!(
+            index.lines().map(|line| line.kind).collect::<Vec<_>>(),
+            vec![LineKind::Message, LineKind::Other]
+        );
+
+        let mut file = OpenOptions::new().append(true).open(&source).expect("open");
+        write!(file, "{CALL}\n{USER}").expect("append");
+
+        let index = JsonlIndex::load_or_update(&cache, &source).expect("index");
+        assert_eq!(index.lines().count(), 4);
+        assert_eq!(
+            index.read_selected(&[LineKind::Message]).expect("read"),
+            format!("{USER}\n\n\n{USER}")
+        );
+        assert_eq!(
+            index.read_selected(&[LineKind::ToolCall]).expect("read"),
+            format!("\n\n{CALL}\n")
+        );
+    }
+
+    #[test]
+    fn rewritten_source_is_reindexed() {
+        let temp = tempdir().expect("tempdir");
+        let cache = temp.path().join("cache");
+        let source = temp.path().join("rollout.jsonl");
+        fs::write(&source, format!("{USER}\n{CALL}\n")).expect("write");
+        JsonlIndex::load_or_update(&cache, &source).expect("index");
+
+        fs::write(&source, format!("{TOKENS}\nnot json\n")).expect("rewrite");
+        let index = JsonlIndex::load_or_update(&cache, &source).expect("index");
+        assert_eq!(
+            index.lines().map(|line| line.kind).collect::<Vec<_>>(),
+            vec![LineKind::Other, LineKind::Invalid]
+        );
+    }
+
+    #[test]
+    fn same_length_rewrite_is_reindexed() {
+        let temp = tempdir().expect("tempdir");
+        let cache = temp.path().join("cache");
+        let source = temp.path().join("rollout.jsonl");
+        let padding = format!("{USER}\n").repeat(64);
+        fs::write(&source, format!("{padding}{CALL}\n{padding}")).expect("write");
+        JsonlIndex::load_or_update(&cache, &source).expect("index");
+
+        // Same length, first and last bytes; only the mtime tells.
+        let middle = format!("{:<width$}", "not json", width = CALL.len());
+        fs::write(&source, format!("{padding}{middle}\n{padding}")).expect("rewrite");
+        File::options()
+            .write(true)
+            .open(&source)
+            .and_then(|file| file.set_modified(SystemTime::now() + Duration::from_secs(60)))
+            .expect("touch");
+
+        let index = JsonlIndex::load_or_update(&cache, &source).expect("index");
+        assert_eq!(
+            index.lines().nth(64).map(|line| line.kind),
+            Some(LineKind::Invalid)
+        );
+    }
+}
@@ -1,19 +1,173 @@
+#[cfg(feature = "fs")]
+pub mod annotate;
+#[cfg(feature = "fs")]
+pub mod atomic;
+#[cfg(feature = "write")]
+pub mod binary;
+#[cfg(feature = "fs")]
+pub mod compression;
+#[cfg(feature = "fs")]
+pub mod copy;
+#[cfg(feature = "fs")]
+pub mod dedupe;
+#[cfg(feature = "fs")]
+pub mod doctor;
+pub mod encoding;
 pub mod error;
+#[cfg(feature = "fs")]
+pub mod export;
+#[cfg(feature = "fs")]
+pub mod fork;
+pub mod head;
+#[cfg(feature = "fs")]
+pub mod head_diff;
+#[cfg(feature = "fs")]
+pub mod import;
 pub mod jsonl;
+#[cfg(feature = "fs")]
+pub mod jsonl_index;
+#[cfg(feature = "fs")]
+pub mod lineage;
+#[cfg(feature = "fs")]
+pub mod metrics;
 pub mod model;
+#[cfg(feature = "async")]
+pub mod nonblocking;
+#[cfg(feature = "notify")]
+pub mod notify;
+#[cfg(feature = "fs")]
+pub mod pack;
+#[cfg(feature = "fs")]
+pub mod pi_diff;
+#[cfg(feature = "fs")]
 pub mod provider;
+pub mod records;
 pub mod render;
+pub mod renderer;
+#[cfg(feature = "semantic-search")]
+pub mod semantic;
+#[cfg(feature = "fs")]
 pub mod service;
+#[cfg(feature = "fs")]
+pub mod session;
+#[cfg(feature = "fs")]
+pub mod sink;
+#[cfg(feature = "fs")]
+pub mod snapshot;
+pub mod status;
+#[cfg(feature = "write")]
+pub mod summarize;
+#[cfg(feature = "fs")]
+pub mod tags;
+#[cfg(feature = "templates")]
+pub mod template;
+pub mod timestamp;
+pub mod tools;
+#[cfg(feature = "fs")]
+pub mod trace;
 pub mod uri;
+#[cfg(feature = "fs")]
+pub mod verify;
+#[cfg(feature = "fs")]
+pub mod wait;
+#[cfg(feature = "fs")]
+pub mod watch;
+pub mod yaml;
 
+#[cfg(feature = "fs")]
+pub use annotate::{annotate_thread, thread_annotations};
+#[cfg(feature = "fs")]
+pub use atomic::{AtomicFile, write_atomic};
+#[cfg(feature = "write")]
+pub use binary::BinaryLocator;
+#[cfg(feature = "fs")]
+pub use copy::copy_thread;
+#[cfg(feature = "fs")]
+pub use dedupe::{find_duplicate_threads, render_dedupe_report_markdown, thread_fingerprint};
+#[cfg(feature = "fs")]
+pub use doctor::{check_storage, render_doctor_report_json, render_doctor_report_markdown};
 pub use error::{Result, XurlError};
+#[cfg(feature = "fs")]
+pub use export::export_thread;
+#[cfg(feature = "fs")]
+pub use fork::fork_thread;
+pub use head::{HeadDocument, HeadFields};
+#[cfg(feature = "fs")]
+pub use head_diff::{diff_thread_head, render_head_diff_json, render_head_diff_markdown};
+#[cfg(feature = "fs")]
+pub use import::import_thread;
+#[cfg(feature = "fs")]
+pub use lineage::{render_thread_lineage_json, render_thread_lineage_markdown, thread_lineage};
+#[cfg(feature = "fs")]
+pub use metrics::{render_thread_stats_json, render_thread_stats_markdown, thread_stats};
 pub use model::{
-    MessageRole, PiEntryListView, ProviderKind, ResolutionMeta, ResolvedThread, SubagentDetailView,
-    SubagentListView, SubagentView, ThreadMessage, WriteRequest, WriteResult,
+    AnnotateRequest, Annotation, CopyRequest, CopyResult, DedupeReport, DoctorReport,
+    DuplicateGroup, DuplicateThread, ExportFormat, ExportRequest, ExportResult, ExtractionMode,
+    ForkResult, GcEntry, GcMode, GcReport, GcRequest, HeadChange, HeadDiff, ImportRequest,
+    ImportResult, LineageNode, LineageRelation, MessageRole, Notification, NotifyEvent,
+    PackedMessage, PackedThread, Percentiles, PiBranchDiff, PiBranchSide, PiEntryListView,
+    ProviderKind, ProviderStorage, RecentThread, RecentThreadsView, ResolutionMeta, ResolvedThread,
+    SemanticHit, SemanticSearchRequest, SemanticSearchResult, SessionInfo, SnapshotRequest,
+    SnapshotResult, StorageRoot, StorageSchema, SubagentDetailView, SubagentListView, SubagentView,
+    TableFormat, ThreadLineage, ThreadLocation, ThreadMessage, ThreadParent, ThreadStats,
+    ThreadSummary, ThreadTrace, TimelineEntryKind, TimelineEntryView, TimelineFormat, ToolUsage,
+    TraceSpan, TurnStats, VerifyProblem, VerifyReport, WaitRequest, Warning, WarningSeverity,
+    WatchState, WriteRequest, WriteResult,
 };
-pub use provider::{ProviderRoots, WriteEventSink};
+#[cfg(feature = "async")]
+pub use nonblocking::{AsyncWriteEventSink, resolve_thread_async, write_thread_async};
+#[cfg(feature = "notify")]
+pub use notify::{
+    NotifyConfig, config_path, notification, notify, notify_config, send_notification,
+    set_notify_config,
+};
+#[cfg(feature = "fs")]
+pub use pack::{pack_thread, render_packed_thread_markdown};
+#[cfg(feature = "fs")]
+pub use pi_diff::{diff_pi_branches, render_pi_diff_markdown};
+#[cfg(feature = "fs")]
+pub use provider::opencode_share::expand_share_uri;
+#[cfg(feature = "fs")]
+pub use provider::{
+    AgentProcess, MaintenanceProvider, ProviderRoots, StorageLayout, WriteEventSink,
+};
+pub use render::ReadOptions;
+pub use renderer::{
+    HtmlRenderer, JsonRenderer, MarkdownRenderer, Renderer, TableRenderer, TextRenderer,
+    TimelineHead, renderer_for,
+};
+#[cfg(feature = "semantic-search")]
+pub use semantic::{render_semantic_search_json, render_semantic_search_markdown, semantic_search};
+#[cfg(feature = "fs")]
 pub use service::{
-    render_subagent_view_markdown, render_thread_head_markdown, render_thread_markdown,
-    resolve_subagent_view, resolve_thread, write_thread,
+    build_thread_head, collect_garbage, list_recent_threads, locate_thread,
+    render_gc_report_markdown, render_recent_threads_markdown, render_subagent_view_json,
+    render_subagent_view_markdown, render_thread, render_thread_context, render_thread_entry_json,
+    render_thread_head_markdown, render_thread_location_json, render_thread_markdown,
+    render_thread_table, resolve_any_uri, resolve_session_prefix, resolve_subagent_view,
+    resolve_thread, write_thread,
 };
-pub use uri::ThreadUri;
+#[cfg(feature = "fs")]
+pub use session::{read_session_info, read_thread_parent};
+#[cfg(feature = "fs")]
+pub use sink::{BufferedSink, ChannelSink, StreamSink, TeeSink, WriteEvent};
+#[cfg(feature = "fs")]
+pub use snapshot::snapshot_thread;
+pub use status::{InferredStatus, ProviderStatusInference};
+#[cfg(feature = "write")]
+pub use summarize::{Summarizer, render_summary_markdown, summarize_thread};
+#[cfg(feature = "fs")]
+pub use tags::{expand_tag_uri, resolve_tag, tag_thread};
+#[cfg(feature = "templates")]
+pub use template::{OutputTemplate, render_subagent_view_template, render_thread_template};
+pub use timestamp::TimeWindow;
+pub use tools::summarize_tools;
+#[cfg(feature = "fs")]
+pub use trace::{render_trace_otlp_json, trace_thread};
+pub use uri::{MessageAnchor, ThreadUri, TimelinePage};
+#[cfg(feature = "fs")]
+pub use verify::{render_verify_report_json, render_verify_report_markdown, verify_thread};
+#[cfg(feature = "fs")]
+pub use wait::{follow_subagent, wait_for_subagent};
+#[cfg(feature = "fs")]
+pub use watch::WatchHandle;
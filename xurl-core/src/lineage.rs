@@ -0,0 +1,519 @@
+//! Resume and fork lineage of Claude and Codex sessions (`xurl lineage`).
+//!
+//! A resumed Claude session is a new transcript that links back to the one
+//! it continued: a `resumedFrom` field, history lines that still carry the
+//! old `sessionId`, or a first `parentUuid` naming a message of the old
+//! transcript. Forked sessions link back the same way, so every Claude link
+//! is reported as a resume. Codex keeps the session id when it resumes a
+//! rollout, and records `forked_from_id` in the `session_meta` of a fork.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+use crate::compression::open_thread_file;
+use crate::error::{Result, XurlError};
+use crate::jsonl;
+use crate::model::{
+    LineageNode, LineageRelation, ProviderKind, ThreadLineage, ThreadListing, Warning,
+};
+use crate::provider::ProviderRoots;
+use crate::service::{agents_thread_uri, provider_for, read_thread_raw, resolve_thread};
+use crate::session::{SESSION_SCAN_LINES, codex_spawn_parent};
+use crate::timestamp::format_rfc3339;
+use crate::uri::ThreadUri;
+
+/// What a session file says about where the session came from.
+#[derive(Debug, Clone, Default)]
+struct SessionScan {
+    path: PathBuf,
+    /// Parent the session names outright: Claude `resumedFrom` or Codex
+    /// `forked_from_id`.
+    declared_parent: Option<String>,
+    /// Other sessions the transcript links to. A session resumed several
+    /// times carries the ids of all its ancestors; the nearest is the parent.
+    linked: BTreeSet<String>,
+    /// Claude `parentUuid`s that point at no message of this transcript.
+    dangling_uuids: BTreeSet<String>,
+    started_at: Option<String>,
+    updated_at: Option<String>,
+}
+
+/// Walks the resume and fork links of `uri` back to the original session and
+/// forward to every session descending from it.
+pub fn thread_lineage(uri: &ThreadUri, roots: &ProviderRoots) -> Result<ThreadLineage> {
+    if uri.agent_id.is_some() {
+        return Err(XurlError::InvalidMode(
+            "lineage only supports main thread URIs: agents://<provider>/<session_id>".to_string(),
+        ));
+    }
+    let relation = match uri.provider {
+        ProviderKind::Claude => LineageRelation::Resumed,
+        ProviderKind::Codex => LineageRelation::Forked,
+        provider => {
+            return Err(XurlError::InvalidMode(format!(
+                "lineage only supports Claude and Codex threads, not {provider}"
+            )));
+        }
+    };
+
+    let resolved = resolve_thread(uri, roots)?;
+    let provider = uri.provider;
+    let current = resolved.session_id.to_ascii_lowercase();
+    let mut warnings = resolved.metadata.warnings.clone();
+    let mut scans = scan_family(provider, &resolved.path, roots)?;
+    if !scans.contains_key(&current)
+        && let Some(scan) = scan_session(provider, &resolved.path)
+    {
+        scans.insert(current.clone(), scan);
+    }
+    link_dangling_uuids(&mut scans);
+
+    // Walk up to the original session, resolving ancestors the listing did
+    // not cover, such as archived Codex rollouts.
+    let mut root = current.clone();
+    let mut visited = BTreeSet::from([root.clone()]);
+    while let Some(parent) = parent_of(&root, &scans) {
+        if !scans.contains_key(&parent) {
+            match resolve_ancestor(provider, &parent, roots) {
+                Some(scan) => {
+                    scans.insert(parent.clone(), scan);
+                }
+                None => {
+                    warnings.push(Warning::warn(
+                        "lineage_missing",
+                        format!(
+                            "{provider} session {root} was {relation} from session_id={parent}, but its transcript was not found"
+                        ),
+                    ));
+                    break;
+                }
+            }
+        }
+        if !visited.insert(parent.clone()) {
+            warnings.push(Warning::warn(
+                "lineage_cycle",
+                format!("{provider} sessions {root} and {parent} name each other as parents"),
+            ));
+            break;
+        }
+        root = parent;
+    }
+
+    let mut children = BTreeMap::<String, Vec<String>>::new();
+    for session_id in scans.keys() {
+        if let Some(parent) = parent_of(session_id, &scans)
+            && scans.contains_key(&parent)
+        {
+            children.entry(parent).or_default().push(session_id.clone());
+        }
+    }
+    for siblings in children.values_mut() {
+        siblings.sort_by(|a, b| (&scans[a].started_at, a).cmp(&(&scans[b].started_at, b)));
+    }
+
+    let provider_name = provider.to_string();
+    let mut sessions = Vec::new();
+    let mut pending = vec![(root.clone(), 0)];
+    let mut emitted = BTreeSet::new();
+    while let Some((session_id, depth)) = pending.pop() {
+        if !emitted.insert(session_id.clone()) {
+            continue;
+        }
+        let scan = &scans[&session_id];
+        let parent_id = (depth > 0)
+            .then(|| parent_of(&session_id, &scans))
+            .flatten();
+        sessions.push(LineageNode {
+            uri: agents_thread_uri(&provider_name, &session_id, None),
+            parent_id: parent_id.clone(),
+            relation: parent_id.map(|_| relation),
+            depth,
+            started_at: scan.started_at.clone(),
+            updated_at: scan.updated_at.clone(),
+            thread_source: scan.path.display().to_string(),
+            current: session_id == current,
+            session_id: session_id.clone(),
+        });
+        if let Some(siblings) = children.get(&session_id) {
+            pending.extend(
+                siblings
+                    .iter()
+                    .rev()
+                    .map(|child| (child.clone(), depth + 1)),
+            );
+        }
+    }
+
+    Ok(ThreadLineage {
+        uri: agents_thread_uri(&provider_name, &current, None),
+        root_uri: agents_thread_uri(&provider_name, &root, None),
+        sessions,
+        warnings,
+    })
+}
+
+pub fn render_thread_lineage_markdown(lineage: &ThreadLineage) -> String {
+    let mut output = String::new();
+    output.push_str("# Thread Lineage\n\n");
+    output.push_str(&format!("- URI: `{}`\n", lineage.uri));
+    output.push_str(&format!("- Original: `{}`\n", lineage.root_uri));
+    output.push_str(&format!("- Sessions: `{}`\n", lineage.sessions.len()));
+
+    output.push_str("\n## Sessions\n\n");
+    for node in &lineage.sessions {
+        output.push_str(&"  ".repeat(node.depth));
+        output.push_str("- ");
+        if let Some(relation) = node.relation {
+            output.push_str(&format!("{relation}: "));
+        }
+        output.push_str(&format!("`{}`", node.uri));
+        if node.current {
+            output.push_str(" **(this thread)**");
+        }
+        let times = [("started", &node.started_at), ("updated", &node.updated_at)]
+            .into_iter()
+            .filter_map(|(label, time)| Some(format!("{label} `{}`", time.as_deref()?)))
+            .collect::<Vec<_>>();
+        if !times.is_empty() {
+            output.push_str(&format!(" — {}", times.join(", ")));
+        }
+        output.push('\n');
+    }
+
+    if !lineage.warnings.is_empty() {
+        output.push_str("\n## Warnings\n\n");
+        for warning in &lineage.warnings {
+            output.push_str(&format!("- {warning}\n"));
+        }
+    }
+
+    output
+}
+
+pub fn render_thread_lineage_json(lineage: &ThreadLineage) -> Result<String> {
+    serde_json::to_string_pretty(lineage)
+        .map(|json| format!("{json}\n"))
+        .map_err(|err| XurlError::Serialization(err.to_string()))
+}
+
+/// Scans the sessions that can be related to the one at `path`: the other
+/// transcripts of its Claude project, or every Codex rollout. A Codex
+/// session split over several rollouts is scanned by its latest one.
+fn scan_family(
+    provider: ProviderKind,
+    path: &Path,
+    roots: &ProviderRoots,
+) -> Result<BTreeMap<String, SessionScan>> {
+    let mut listings = provider_for(provider, roots).list_threads()?;
+    if provider == ProviderKind::Claude {
+        listings.retain(|listing| listing.path.parent() == path.parent());
+    }
+    listings.sort_by_key(|listing| std::cmp::Reverse(listing.updated_at));
+
+    let mut scans = BTreeMap::new();
+    for ThreadListing {
+        session_id, path, ..
+    } in listings
+    {
+        let session_id = session_id.to_ascii_lowercase();
+        if scans.contains_key(&session_id) {
+            continue;
+        }
+        if let Some(scan) = scan_session(provider, &path) {
+            scans.insert(session_id, scan);
+        }
+    }
+    Ok(scans)
+}
+
+fn resolve_ancestor(
+    provider: ProviderKind,
+    session_id: &str,
+    roots: &ProviderRoots,
+) -> Option<SessionScan> {
+    let uri = ThreadUri::parse(&agents_thread_uri(&provider.to_string(), session_id, None)).ok()?;
+    let resolved = resolve_thread(&uri, roots).ok()?;
+    scan_session(provider, &resolved.path)
+}
+
+/// `None` for files that are not sessions of their own, such as Codex
+/// subagent rollouts.
+fn scan_session(provider: ProviderKind, path: &Path) -> Option<SessionScan> {
+    let mut scan = match provider {
+        ProviderKind::Claude => scan_claude_session(path)?,
+        _ => scan_codex_session(path)?,
+    };
+    scan.path = path.to_path_buf();
+    scan.updated_at = std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .map(|modified| format_rfc3339(DateTime::<Utc>::from(modified)));
+    Some(scan)
+}
+
+fn scan_claude_session(path: &Path) -> Option<SessionScan> {
+    let own_id = path.file_stem()?.to_str()?.to_ascii_lowercase();
+    let raw = read_thread_raw(path).ok()?;
+    let mut scan = SessionScan::default();
+    let mut uuids = BTreeSet::new();
+    let mut parent_uuids = BTreeSet::new();
+    for (line_idx, line) in raw.lines().enumerate() {
+        let Ok(Some(value)) = jsonl::parse_json_line(path, line_idx + 1, line) else {
+            continue;
+        };
+        if let Some(uuid) = value.get("uuid").and_then(Value::as_str) {
+            uuids.insert(uuid.to_string());
+        }
+        if let Some(parent_uuid) = value.get("parentUuid").and_then(Value::as_str) {
+            parent_uuids.insert(parent_uuid.to_string());
+        }
+        if scan.declared_parent.is_none() {
+            scan.declared_parent = value
+                .get("resumedFrom")
+                .and_then(Value::as_str)
+                .map(str::to_ascii_lowercase);
+        }
+        let session_id = value
+            .get("sessionId")
+            .and_then(Value::as_str)
+            .map(str::to_ascii_lowercase);
+        match session_id {
+            Some(session_id) if session_id != own_id => {
+                scan.linked.insert(session_id);
+            }
+            _ => {
+                if scan.started_at.is_none() {
+                    scan.started_at = value
+                        .get("timestamp")
+                        .and_then(Value::as_str)
+                        .map(str::to_string);
+                }
+            }
+        }
+    }
+    scan.dangling_uuids = parent_uuids.difference(&uuids).cloned().collect();
+    Some(scan)
+}
+
+fn scan_codex_session(path: &Path) -> Option<SessionScan> {
+    let file = open_thread_file(path).ok()?;
+    let meta = BufReader::new(file)
+        .split(b'\n')
+        .take(SESSION_SCAN_LINES)
+        .map_while(std::result::Result::ok)
+        .find_map(|line| {
+            let line = String::from_utf8_lossy(&line);
+            let value = serde_json::from_str::<Value>(line.trim_start_matches('\u{feff}')).ok()?;
+            (value["type"] == "session_meta").then_some(value)
+        });
+
+    let Some(meta) = meta else {
+        return Some(SessionScan::default());
+    };
+    if codex_spawn_parent(&meta).is_some() {
+        return None;
+    }
+    let payload = &meta["payload"];
+    let declared_parent = payload["forked_from_id"]
+        .as_str()
+        .map(str::to_ascii_lowercase);
+    Some(SessionScan {
+        linked: declared_parent.iter().cloned().collect(),
+        declared_parent,
+        started_at: payload["timestamp"]
+            .as_str()
+            .or_else(|| meta["timestamp"].as_str())
+            .map(str::to_string),
+        ..SessionScan::default()
+    })
+}
+
+/// Links each Claude transcript to the sessions that wrote the messages its
+/// dangling `parentUuid`s point at. A message copied into later transcripts
+/// keeps the `sessionId` of the session that wrote it.
+fn link_dangling_uuids(scans: &mut BTreeMap<String, SessionScan>) {
+    if scans.values().all(|scan| scan.dangling_uuids.is_empty()) {
+        return;
+    }
+
+    let mut writers = HashMap::<String, String>::new();
+    for (session_id, scan) in scans.iter() {
+        let Ok(raw) = read_thread_raw(&scan.path) else {
+            continue;
+        };
+        for (line_idx, line) in raw.lines().enumerate() {
+            if let Ok(Some(value)) = jsonl::parse_json_line(&scan.path, line_idx + 1, line)
+                && let Some(uuid) = value.get("uuid").and_then(Value::as_str)
+            {
+                let writer = value
+                    .get("sessionId")
+                    .and_then(Value::as_str)
+                    .map_or_else(|| session_id.clone(), str::to_ascii_lowercase);
+                writers.insert(uuid.to_string(), writer);
+            }
+        }
+    }
+
+    for (session_id, scan) in scans.iter_mut() {
+        for uuid in &scan.dangling_uuids {
+            if let Some(writer) = writers.get(uuid)
+                && writer != session_id
+            {
+                scan.linked.insert(writer.clone());
+            }
+        }
+    }
+}
+
+/// The declared parent, else the linked session with the most links of its
+/// own: it carries the history of the others, so it was resumed last.
+fn parent_of(session_id: &str, scans: &BTreeMap<String, SessionScan>) -> Option<String> {
+    let scan = scans.get(session_id)?;
+    if let Some(parent) = &scan.declared_parent {
+        return Some(parent.clone());
+    }
+    scan.linked
+        .iter()
+        .filter(|linked| linked.as_str() != session_id)
+        .max_by_key(|linked| {
+            let linked_scan = scans.get(linked.as_str());
+            (
+                linked_scan.map_or(0, |scan| scan.linked.len()),
+                linked_scan.and_then(|scan| scan.started_at.clone()),
+            )
+        })
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use crate::lineage::thread_lineage;
+    use crate::model::LineageRelation;
+    use crate::provider::ProviderRoots;
+    use crate::uri::ThreadUri;
+
+    const A: &str = "aaaaaaaa-aaaa-4aaa-8aaa-aaaaaaaaaaaa";
+    const B: &str = "bbbbbbbb-bbbb-4bbb-8bbb-bbbbbbbbbbbb";
+    const C: &str = "cccccccc-cccc-4ccc-8ccc-cccccccccccc";
+    const D: &str = "dddddddd-dddd-4ddd-8ddd-dddddddddddd";
+
+    fn line(session_id: &str, uuid: &str, parent_uuid: Option<&str>, timestamp: &str) -> String {
+        serde_json::json!({
+            "type": "user",
+            "sessionId": session_id,
+            "uuid": uuid,
+            "parentUuid": parent_uuid,
+            "timestamp": timestamp,
+            "message": {"role": "user", "content": "hi"},
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn claude_lineage_follows_copied_history_and_dangling_parents() {
+        let temp = tempdir().expect("tempdir");
+        let project = temp.path().join("projects/-work-app");
+        fs::create_dir_all(&project).expect("mkdir");
+        let write = |id: &str, lines: &[String]| {
+            fs::write(project.join(format!("{id}.jsonl")), lines.join("\n") + "\n").expect("write");
+        };
+        // B copied A's history; C copied both. D continues B by pointing its
+        // first message at B's last one.
+        write(A, &[line(A, "a1", None, "2026-03-01T09:00:00Z")]);
+        write(
+            B,
+            &[
+                line(A, "a1", None, "2026-03-01T09:00:00Z"),
+                line(B, "b1", Some("a1"), "2026-03-02T09:00:00Z"),
+            ],
+        );
+        write(
+            C,
+            &[
+                line(A, "a1", None, "2026-03-01T09:00:00Z"),
+                line(B, "b1", Some("a1"), "2026-03-02T09:00:00Z"),
+                line(C, "c1", Some("b1"), "2026-03-03T09:00:00Z"),
+            ],
+        );
+        write(D, &[line(D, "d1", Some("b1"), "2026-03-04T09:00:00Z")]);
+
+        let mut roots = ProviderRoots::for_test(temp.path());
+        roots.claude_root = temp.path().to_path_buf();
+        let uri = ThreadUri::parse(&format!("agents://claude/{C}")).expect("uri");
+        let lineage = thread_lineage(&uri, &roots).expect("lineage");
+
+        assert_eq!(lineage.root_uri, format!("agents://claude/{A}"));
+        let tree = lineage
+            .sessions
+            .iter()
+            .map(|node| (node.session_id.as_str(), node.depth, node.current))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tree,
+            [(A, 0, false), (B, 1, false), (C, 2, true), (D, 2, false)]
+        );
+        assert_eq!(lineage.sessions[0].relation, None);
+        assert_eq!(lineage.sessions[2].parent_id.as_deref(), Some(B));
+        assert_eq!(lineage.sessions[2].relation, Some(LineageRelation::Resumed));
+        assert_eq!(
+            lineage.sessions[2].started_at.as_deref(),
+            Some("2026-03-03T09:00:00Z")
+        );
+        assert!(lineage.warnings.is_empty());
+    }
+
+    #[test]
+    fn codex_lineage_reaches_archived_forks() {
+        let temp = tempdir().expect("tempdir");
+        let rollout = |dir: &str, id: &str, forked_from: Option<&str>| {
+            let path = temp
+                .path()
+                .join(format!("{dir}/rollout-2026-03-01T09-00-00-{id}.jsonl"));
+            fs::create_dir_all(path.parent().expect("parent")).expect("mkdir");
+            let meta = serde_json::json!({
+                "type": "session_meta",
+                "payload": {"id": id, "timestamp": "2026-03-01T09:00:00Z", "forked_from_id": forked_from},
+            });
+            fs::write(path, format!("{meta}\n")).expect("write");
+        };
+        rollout("archived_sessions", A, None);
+        rollout("sessions/2026/03/01", B, Some(A));
+        rollout("sessions/2026/03/01", C, Some(B));
+        rollout(
+            "sessions/2026/03/01",
+            D,
+            Some("eeeeeeee-eeee-4eee-8eee-eeeeeeeeeeee"),
+        );
+
+        let mut roots = ProviderRoots::for_test(temp.path());
+        roots.codex_root = temp.path().to_path_buf();
+        let uri = ThreadUri::parse(&format!("agents://codex/{B}")).expect("uri");
+        let lineage = thread_lineage(&uri, &roots).expect("lineage");
+        assert_eq!(lineage.root_uri, format!("agents://codex/{A}"));
+        assert_eq!(
+            lineage
+                .sessions
+                .iter()
+                .map(|node| (node.session_id.as_str(), node.relation))
+                .collect::<Vec<_>>(),
+            [
+                (A, None),
+                (B, Some(LineageRelation::Forked)),
+                (C, Some(LineageRelation::Forked)),
+            ]
+        );
+
+        let uri = ThreadUri::parse(&format!("agents://codex/{D}")).expect("uri");
+        let lineage = thread_lineage(&uri, &roots).expect("lineage");
+        assert_eq!(lineage.sessions.len(), 1);
+        assert_eq!(lineage.warnings[0].code, "lineage_missing");
+    }
+}
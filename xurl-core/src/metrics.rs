@@ -0,0 +1,238 @@
+//! Response-time metrics of a thread (`xurl stats`).
+//!
+//! A turn starts at a user message and holds the assistant messages up to
+//! the next one. Its latency runs from the user message to the first reply,
+//! its duration to the last. Both need message timestamps, so turns of
+//! providers that do not record them only count replies.
+
+use chrono::{DateTime, Utc};
+
+use crate::error::{Result, XurlError};
+use crate::model::{MessageRole, Percentiles, ThreadMessage, ThreadStats, TurnStats, Warning};
+use crate::provider::ProviderRoots;
+use crate::render::{self, ReadOptions};
+use crate::service::{TIMELINE_LINES, read_thread_lines, resolve_thread};
+use crate::timestamp::parse_timestamp;
+use crate::uri::ThreadUri;
+
+/// Collects per-turn latency and duration of a main thread, with their
+/// p50/p95 over the session.
+pub fn thread_stats(
+    uri: &ThreadUri,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+) -> Result<ThreadStats> {
+    if uri.agent_id.is_some() {
+        return Err(XurlError::InvalidMode(
+            "stats only supports main thread URIs: agents://<provider>/<session_id>".to_string(),
+        ));
+    }
+
+    let resolved = resolve_thread(uri, roots)?;
+    let raw = read_thread_lines(&resolved, roots, options, TIMELINE_LINES)?;
+    let mut policy = options.extraction_policy();
+    let messages = render::extract_messages(uri.format(), &resolved.path, &raw, &mut policy)?;
+    let mut warnings = resolved.metadata.warnings.clone();
+    warnings.extend(policy.into_warnings());
+
+    let times = messages
+        .iter()
+        .map(|message| message.timestamp.as_deref().and_then(parse_timestamp))
+        .collect::<Vec<_>>();
+    if !messages.is_empty() && times.iter().all(Option::is_none) {
+        warnings.push(Warning::info(
+            "no_timestamps",
+            "no message in the thread has a timestamp; latency is unavailable",
+        ));
+    }
+
+    let turns = turn_stats(&messages, &times);
+    let first = times.iter().flatten().min();
+    let last = times.iter().flatten().max();
+    Ok(ThreadStats {
+        uri: uri.as_agents_string(),
+        thread_source: resolved.path.display().to_string(),
+        messages: messages.len(),
+        duration_ms: first
+            .zip(last)
+            .and_then(|(first, last)| elapsed_ms(*first, *last)),
+        latency: percentiles(turns.iter().filter_map(|turn| turn.latency_ms).collect()),
+        turn_duration: percentiles(turns.iter().filter_map(|turn| turn.duration_ms).collect()),
+        turns,
+        warnings,
+    })
+}
+
+pub fn render_thread_stats_markdown(stats: &ThreadStats) -> String {
+    let mut output = String::new();
+    output.push_str("# Thread Stats\n\n");
+    output.push_str(&format!("- URI: `{}`\n", stats.uri));
+    output.push_str(&format!("- Source: `{}`\n", stats.thread_source));
+    output.push_str(&format!("- Messages: `{}`\n", stats.messages));
+    output.push_str(&format!("- Turns: `{}`\n", stats.turns.len()));
+    if let Some(duration) = stats.duration_ms {
+        output.push_str(&format!("- Duration: `{duration} ms`\n"));
+    }
+    for (label, summary) in [
+        ("Latency", stats.latency),
+        ("Turn duration", stats.turn_duration),
+    ] {
+        if let Some(summary) = summary {
+            output.push_str(&format!(
+                "- {label}: p50 `{} ms`, p95 `{} ms`, max `{} ms` over {} turns\n",
+                summary.p50_ms, summary.p95_ms, summary.max_ms, summary.samples
+            ));
+        }
+    }
+
+    output.push_str("\n## Turns\n\n");
+    if stats.turns.is_empty() {
+        output.push_str("_No turns found._\n");
+    } else {
+        output.push_str("| Turn | Started | Replies | Latency (ms) | Duration (ms) |\n");
+        output.push_str("| ---: | --- | ---: | ---: | ---: |\n");
+    }
+    let cell = |value: Option<u64>| value.map_or_else(|| "-".to_string(), |ms| ms.to_string());
+    for turn in &stats.turns {
+        output.push_str(&format!(
+            "| {} | {} | {} | {} | {} |\n",
+            turn.turn,
+            turn.started_at.as_deref().unwrap_or("-"),
+            turn.replies,
+            cell(turn.latency_ms),
+            cell(turn.duration_ms),
+        ));
+    }
+
+    if !stats.warnings.is_empty() {
+        output.push_str("\n## Warnings\n\n");
+        for warning in &stats.warnings {
+            output.push_str(&format!("- {warning}\n"));
+        }
+    }
+
+    output
+}
+
+pub fn render_thread_stats_json(stats: &ThreadStats) -> Result<String> {
+    serde_json::to_string_pretty(stats)
+        .map(|json| format!("{json}\n"))
+        .map_err(|err| XurlError::Serialization(err.to_string()))
+}
+
+/// Splits `messages` into turns. Assistant messages before the first user
+/// message belong to no turn.
+fn turn_stats(messages: &[ThreadMessage], times: &[Option<DateTime<Utc>>]) -> Vec<TurnStats> {
+    let mut turns = Vec::<TurnStats>::new();
+    let mut started = None;
+    for (message, time) in messages.iter().zip(times) {
+        match message.role {
+            MessageRole::User => {
+                turns.push(TurnStats {
+                    turn: turns.len() + 1,
+                    started_at: message.timestamp.clone(),
+                    replies: 0,
+                    latency_ms: None,
+                    duration_ms: None,
+                });
+                started = *time;
+            }
+            MessageRole::Assistant => {
+                let Some(turn) = turns.last_mut() else {
+                    continue;
+                };
+                turn.replies += 1;
+                let elapsed = started
+                    .zip(*time)
+                    .and_then(|(started, replied)| elapsed_ms(started, replied));
+                if turn.latency_ms.is_none() {
+                    turn.latency_ms = elapsed;
+                }
+                turn.duration_ms = elapsed.or(turn.duration_ms);
+            }
+        }
+    }
+    turns
+}
+
+/// Milliseconds from `start` to `end`; `None` when the clock went backwards.
+fn elapsed_ms(start: DateTime<Utc>, end: DateTime<Utc>) -> Option<u64> {
+    u64::try_from((end - start).num_milliseconds()).ok()
+}
+
+/// Nearest-rank percentiles of `samples`; `None` when there are none.
+fn percentiles(mut samples: Vec<u64>) -> Option<Percentiles> {
+    if samples.is_empty() {
+        return None;
+    }
+    samples.sort_unstable();
+    let rank = |percent: usize| {
+        let index = (percent * samples.len()).div_ceil(100).max(1) - 1;
+        samples[index]
+    };
+    Some(Percentiles {
+        samples: samples.len(),
+        p50_ms: rank(50),
+        p95_ms: rank(95),
+        max_ms: samples[samples.len() - 1],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics::{percentiles, turn_stats};
+    use crate::model::{MessageRole, ThreadMessage};
+    use crate::timestamp::parse_timestamp;
+
+    fn message(role: MessageRole, timestamp: Option<&str>) -> ThreadMessage {
+        ThreadMessage {
+            role,
+            text: "text".to_string(),
+            timestamp: timestamp.map(ToString::to_string),
+        }
+    }
+
+    #[test]
+    fn turns_measure_first_and_last_reply() {
+        let messages = [
+            message(MessageRole::Assistant, Some("2026-03-02T08:59:00Z")),
+            message(MessageRole::User, Some("2026-03-02T09:00:00Z")),
+            message(MessageRole::Assistant, Some("2026-03-02T09:00:01.500Z")),
+            message(MessageRole::Assistant, Some("2026-03-02T09:00:04Z")),
+            message(MessageRole::User, Some("2026-03-02T09:01:00Z")),
+            message(MessageRole::Assistant, Some("2026-03-02T09:00:59Z")),
+            message(MessageRole::User, None),
+            message(MessageRole::Assistant, Some("2026-03-02T09:02:00Z")),
+        ];
+        let times = messages
+            .iter()
+            .map(|message| message.timestamp.as_deref().and_then(parse_timestamp))
+            .collect::<Vec<_>>();
+
+        let turns = turn_stats(&messages, &times);
+        assert_eq!(turns.len(), 3);
+        assert_eq!(turns[0].started_at.as_deref(), Some("2026-03-02T09:00:00Z"));
+        assert_eq!(turns[0].replies, 2);
+        assert_eq!(turns[0].latency_ms, Some(1500));
+        assert_eq!(turns[0].duration_ms, Some(4000));
+        // A reply stamped before its prompt has no meaningful latency.
+        assert_eq!(turns[1].replies, 1);
+        assert_eq!(turns[1].latency_ms, None);
+        assert_eq!(turns[2].started_at, None);
+        assert_eq!(turns[2].latency_ms, None);
+    }
+
+    #[test]
+    fn percentiles_use_nearest_rank() {
+        assert_eq!(percentiles(Vec::new()), None);
+
+        let single = percentiles(vec![7]).expect("one sample");
+        assert_eq!((single.p50_ms, single.p95_ms, single.max_ms), (7, 7, 7));
+
+        let spread = percentiles((1..=20).rev().map(|ms| ms * 100).collect()).expect("samples");
+        assert_eq!(spread.samples, 20);
+        assert_eq!(spread.p50_ms, 1000);
+        assert_eq!(spread.p95_ms, 1900);
+        assert_eq!(spread.max_ms, 2000);
+    }
+}
@@ -1,9 +1,14 @@
+use std::collections::BTreeMap;
 use std::fmt;
 use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
 
-use serde::Serialize;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+use crate::status::TERMINAL_STATUSES;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
 pub enum ProviderKind {
     Amp,
     Codex,
@@ -11,6 +16,69 @@ pub enum ProviderKind {
     Gemini,
     Pi,
     Opencode,
+    /// GitHub Copilot CLI sessions.
+    Copilot,
+    /// A transcript file named by a `file://` URI, outside provider storage.
+    File,
+}
+
+impl ProviderKind {
+    /// Every provider with storage of its own, in the order cross-provider
+    /// listings report them. [`ProviderKind::File`] is not one.
+    pub fn all() -> &'static [Self] {
+        &[
+            Self::Amp,
+            Self::Codex,
+            Self::Claude,
+            Self::Gemini,
+            Self::Pi,
+            Self::Opencode,
+            Self::Copilot,
+        ]
+    }
+
+    /// What xurl can do with this provider's threads in this build.
+    pub fn capabilities(self) -> ProviderCapabilities {
+        let (supports_subagents, supports_write, storage) = match self {
+            Self::Amp => (true, false, StorageKind::Json),
+            Self::Codex | Self::Claude => (true, cfg!(feature = "write"), StorageKind::Jsonl),
+            Self::Gemini => (true, false, StorageKind::Json),
+            Self::Pi => (false, true, StorageKind::Jsonl),
+            Self::Copilot => (false, false, StorageKind::Jsonl),
+            Self::Opencode => (false, false, StorageKind::Sqlite),
+            Self::File => (false, false, StorageKind::External),
+        };
+        ProviderCapabilities {
+            supports_subagents,
+            supports_write,
+            storage,
+        }
+    }
+}
+
+/// What xurl supports for a provider, from [`ProviderKind::capabilities`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ProviderCapabilities {
+    /// Main threads list subagents (`agents://<provider>/<id>/<agent_id>`).
+    /// Pi entries share the URI form but are branches of one session.
+    pub supports_subagents: bool,
+    /// Write mode can start or continue threads.
+    pub supports_write: bool,
+    pub storage: StorageKind,
+}
+
+/// How a provider keeps its threads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageKind {
+    /// One JSON Lines file per thread.
+    Jsonl,
+    /// One JSON document per thread (Amp directory threads add shards).
+    Json,
+    /// Rows of a SQLite database.
+    Sqlite,
+    /// A file outside provider storage, in another provider's format.
+    External,
 }
 
 impl fmt::Display for ProviderKind {
@@ -22,18 +90,140 @@ impl fmt::Display for ProviderKind {
             Self::Gemini => write!(f, "gemini"),
             Self::Pi => write!(f, "pi"),
             Self::Opencode => write!(f, "opencode"),
+            Self::Copilot => write!(f, "copilot"),
+            Self::File => write!(f, "file"),
         }
     }
 }
 
-#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningSeverity {
+    /// Context that does not change the result (e.g. other candidates).
+    Info,
+    /// Something was skipped or guessed; the result may be incomplete.
+    Warn,
+    /// The requested data could not be found or read.
+    Error,
+}
+
+impl fmt::Display for WarningSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Info => write!(f, "info"),
+            Self::Warn => write!(f, "warn"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct Warning {
+    pub severity: WarningSeverity,
+    /// Stable snake_case identifier, e.g. `ambiguous_session`.
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl Warning {
+    pub fn new(severity: WarningSeverity, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn info(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(WarningSeverity::Info, code, message)
+    }
+
+    pub fn warn(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(WarningSeverity::Warn, code, message)
+    }
+
+    pub fn error(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(WarningSeverity::Error, code, message)
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
 pub struct ResolutionMeta {
     pub source: String,
     pub candidate_count: usize,
-    pub warnings: Vec<String>,
+    pub warnings: Vec<Warning>,
+    /// Rollouts concatenated into this thread, oldest first (Codex merge mode).
+    /// Empty when the thread is a single file.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub merged_paths: Vec<PathBuf>,
+    /// Model, working directory, and git branch recorded in the transcript.
+    #[serde(skip_serializing_if = "SessionInfo::is_empty")]
+    pub session: SessionInfo,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Where a session ran and with which model, as its transcript records it.
+/// Each value is the first one recorded.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SessionInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_branch: Option<String>,
+}
+
+impl SessionInfo {
+    pub fn is_empty(&self) -> bool {
+        self.model.is_none() && self.cwd.is_none() && self.git_branch.is_none()
+    }
+}
+
+/// Which on-disk format a provider's storage uses: a layout, e.g. Amp's
+/// `thread_directory`, and the format version when the storage records one,
+/// e.g. `5` for a Codex `state_5.sqlite` index.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StorageSchema {
+    /// Stable snake_case layout name.
+    pub layout: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+impl StorageSchema {
+    pub fn new(layout: &'static str, version: Option<String>) -> Self {
+        Self { layout, version }
+    }
+}
+
+impl fmt::Display for StorageSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.version {
+            Some(version) => write!(f, "{} v{version}", self.layout),
+            None => write!(f, "{}", self.layout),
+        }
+    }
+}
+
+/// The thread that spawned a subagent thread, as the child records it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ThreadParent {
+    /// `agents://<provider>/<parent_thread_id>`.
+    pub uri: String,
+    /// The child as a subagent of its parent,
+    /// `agents://<provider>/<parent_thread_id>/<child_id>`.
+    pub subagent_uri: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spawned_at: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct ResolvedThread {
     pub provider: ProviderKind,
     pub session_id: String,
@@ -41,20 +231,569 @@ pub struct ResolvedThread {
     pub metadata: ResolutionMeta,
 }
 
+/// Where a URI is stored, as printed by `xurl resolve`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ThreadLocation {
+    /// Canonical `agents://` form, without fragment or page.
+    pub uri: String,
+    pub provider: String,
+    pub session_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_id: Option<String>,
+    /// File holding the thread: a subagent's own thread file, or the session
+    /// file of a Pi entry.
+    pub path: PathBuf,
+    #[serde(skip_serializing)]
+    pub warnings: Vec<Warning>,
+}
+
+/// A session found while listing a provider's local storage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadListing {
+    pub provider: ProviderKind,
+    pub session_id: String,
+    pub path: PathBuf,
+    pub updated_at: SystemTime,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct WriteRequest {
     pub prompt: String,
     pub session_id: Option<String>,
+    /// Pi entry to branch from; the prompt becomes its child instead of
+    /// following the latest entry.
+    pub parent_entry_id: Option<String>,
+    /// Ask the agent of `session_id` to spawn a subagent with the prompt as
+    /// its task instead of answering it (Codex only).
+    pub spawn_agent: bool,
+    /// Gemini project hash the session is in, from
+    /// `agents://gemini/<hash>/<session_id>`.
+    pub gemini_project: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct WriteResult {
     pub provider: ProviderKind,
     pub session_id: String,
     pub final_text: Option<String>,
+    /// Entry created by the write (Pi only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entry_id: Option<String>,
+    /// Subagent spawned by a [`WriteRequest::spawn_agent`] write, readable
+    /// at `agents://<provider>/<session_id>/<agent_id>`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportRequest {
+    pub provider: ProviderKind,
+    pub source: PathBuf,
+    pub session_id: Option<String>,
+    /// Claude project, Gemini project hash, or Pi session directory to import into.
+    pub project: Option<String>,
+    pub force: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ImportResult {
+    pub provider: ProviderKind,
+    pub session_id: String,
+    pub path: PathBuf,
+    /// State database the thread was registered in (Codex only).
+    pub registered_in: Option<PathBuf>,
+    pub warnings: Vec<Warning>,
+}
+
+/// Transcript layouts a thread can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// A Claude Code session that `claude --resume` can continue.
+    ClaudeSession,
+    /// A Codex rollout that `codex resume` can continue.
+    CodexRollout,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportRequest {
+    pub format: ExportFormat,
+    /// Working directory the session belongs to; defaults to the one the
+    /// thread recorded, then the current directory.
+    pub cwd: Option<PathBuf>,
+    /// Write the transcript here instead of into the target provider's storage.
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExportResult {
+    /// Provider the transcript was written for.
+    pub provider: ProviderKind,
+    pub session_id: String,
+    pub path: PathBuf,
+    pub cwd: String,
+    /// Timeline entries written, compactions included.
+    pub messages: usize,
+    /// State database the thread was registered in (Codex only).
+    pub registered_in: Option<PathBuf>,
+    pub warnings: Vec<Warning>,
+}
+
+/// Where `xurl cp` copies a thread to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopyRequest {
+    /// Another root of the thread's provider, e.g. a synced `~/.codex`.
+    pub destination: PathBuf,
+    /// Replace the thread when the destination already has it.
+    pub force: bool,
+}
+
+/// A thread copied by `xurl cp`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CopyResult {
+    pub provider: ProviderKind,
+    pub session_id: String,
+    /// The transcript that was copied.
+    pub source: PathBuf,
+    /// The transcript's copy in the destination root.
+    pub path: PathBuf,
+    /// Files copied, counting those kept beside the transcript.
+    pub files: usize,
+    /// State database the copy was registered in (Codex only).
+    pub registered_in: Option<PathBuf>,
+    pub warnings: Vec<Warning>,
+}
+
+/// A fork made by `xurl fork`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ForkResult {
+    pub provider: ProviderKind,
+    pub session_id: String,
+    /// Pi entry the fork branches from; prompts written to the fork become
+    /// its children.
+    pub entry_id: Option<String>,
+    /// `agents://` URI of the thread that was forked.
+    pub forked_from: String,
+    /// The new Codex rollout, or the Pi session file the branch lives in.
+    pub path: PathBuf,
+    /// State database the fork was registered in (Codex only).
+    pub registered_in: Option<PathBuf>,
+    pub warnings: Vec<Warning>,
+}
+
+impl ForkResult {
+    /// `agents://` URI to read or write the fork.
+    pub fn uri(&self) -> String {
+        match &self.entry_id {
+            Some(entry_id) => format!("agents://{}/{}/{entry_id}", self.provider, self.session_id),
+            None => format!("agents://{}/{}", self.provider, self.session_id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SnapshotRequest {
+    /// Directory the snapshot directory is created in; defaults to
+    /// `snapshots/` under xurl's data directory.
+    pub out_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotResult {
+    /// `file://` URI of the copied thread file.
+    pub uri: String,
+    /// The new snapshot directory.
+    pub dir: PathBuf,
+    /// Every copied file, the thread file first.
+    pub files: Vec<PathBuf>,
+    pub warnings: Vec<Warning>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnotateRequest {
+    /// Timeline entry number, as shown in the rendered thread.
+    pub message: usize,
+    pub note: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaitRequest {
+    /// Give up after this long; `None` waits indefinitely.
+    pub timeout: Option<Duration>,
+    /// Pause between status checks.
+    pub interval: Duration,
+}
+
+/// What a webhook notification reports, see [`crate::notify`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyEvent {
+    /// A write-mode run finished, or failed.
+    Write,
+    /// `xurl wait` saw a subagent reach a terminal status.
+    Wait,
+}
+
+impl NotifyEvent {
+    pub const ALL: [Self; 2] = [Self::Write, Self::Wait];
+}
+
+/// The JSON document posted to the notification webhook.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Notification {
+    /// One-line summary, which is what Slack incoming webhooks display.
+    pub text: String,
+    pub event: NotifyEvent,
+    pub uri: String,
+    pub status: String,
+    pub duration_ms: u64,
+    /// The last characters of the reply or the subagent's last message.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_tail: Option<String>,
+}
+
+/// What a [`crate::watch::WatchHandle`] last saw of a thread or subagent.
+/// Two polls that return equal states saw no change.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WatchState {
+    pub uri: String,
+    /// The subagent's status, or for a main thread the worst status of its
+    /// subagents. `None` for a thread without subagents.
+    pub status: Option<String>,
+    /// When the watched thread, or the subagent's own thread, last changed.
+    pub last_update: Option<String>,
+    /// Status of each subagent of a main thread, nested ones included, by
+    /// agent id.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub subagents: BTreeMap<String, String>,
+}
+
+impl WatchState {
+    /// Whether the watched subagent, or every subagent of the watched thread,
+    /// has finished, so there is nothing left to watch for.
+    pub fn is_terminal(&self) -> bool {
+        if !self.subagents.is_empty() {
+            return self
+                .subagents
+                .values()
+                .all(|status| TERMINAL_STATUSES.contains(&status.as_str()));
+        }
+        self.status
+            .as_deref()
+            .is_some_and(|status| TERMINAL_STATUSES.contains(&status))
+    }
+}
+
+/// A note attached to one timeline entry of a thread.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub provider: String,
+    pub session_id: String,
+    pub message: usize,
+    pub note: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GcMode {
+    /// Only report stale sessions.
+    Report,
+    /// Move stale sessions into the provider's archive.
+    Archive,
+    /// Remove stale sessions.
+    Delete,
+}
+
+impl fmt::Display for GcMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Report => write!(f, "report"),
+            Self::Archive => write!(f, "archive"),
+            Self::Delete => write!(f, "delete"),
+        }
+    }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GcRequest {
+    /// Sessions whose last activity is before this instant are stale.
+    pub cutoff: DateTime<Utc>,
+    pub mode: GcMode,
+    pub dry_run: bool,
+}
+
+impl GcRequest {
+    /// Targets sessions with no activity within `age` of now.
+    pub fn older_than(age: chrono::Duration, mode: GcMode, dry_run: bool) -> Self {
+        Self {
+            cutoff: Utc::now() - age,
+            mode,
+            dry_run,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GcEntry {
+    pub session_id: String,
+    pub path: PathBuf,
+    pub last_activity: Option<String>,
+    pub size_bytes: u64,
+    /// Where the session was (or would be) moved for archive mode.
+    pub destination: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GcReport {
+    pub provider: ProviderKind,
+    pub mode: GcMode,
+    pub dry_run: bool,
+    pub cutoff: String,
+    pub entries: Vec<GcEntry>,
+    #[serde(skip_serializing)]
+    pub warnings: Vec<Warning>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DuplicateThread {
+    pub uri: String,
+    pub session_id: String,
+    pub path: PathBuf,
+    pub last_update: Option<String>,
+}
+
+/// Threads whose messages share one fingerprint, oldest first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DuplicateGroup {
+    pub fingerprint: String,
+    /// Messages with text in each copy.
+    pub messages: usize,
+    pub threads: Vec<DuplicateThread>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DedupeReport {
+    pub provider: ProviderKind,
+    pub scanned: usize,
+    pub groups: Vec<DuplicateGroup>,
+    #[serde(skip_serializing)]
+    pub warnings: Vec<Warning>,
+}
+
+/// One integrity problem found by `xurl verify`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VerifyProblem {
+    pub severity: WarningSeverity,
+    pub code: &'static str,
+    /// 1-based line of the thread file, when the problem is tied to one.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+/// The storage roots of every provider and the schema found in each, as
+/// `xurl doctor` reports them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DoctorReport {
+    pub providers: Vec<ProviderStorage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProviderStorage {
+    pub provider: String,
+    /// Search order; the first is the root the provider writes to.
+    pub roots: Vec<StorageRoot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StorageRoot {
+    pub path: PathBuf,
+    pub exists: bool,
+    /// `None` when the root holds nothing of the provider's yet.
+    pub schema: Option<StorageSchema>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VerifyReport {
+    pub uri: String,
+    pub provider: String,
+    pub session_id: String,
+    pub thread_source: String,
+    /// Non-empty lines (JSONL threads) or messages (JSON documents) checked.
+    pub records: usize,
+    pub checks: Vec<&'static str>,
+    pub problems: Vec<VerifyProblem>,
+}
+
+impl VerifyReport {
+    /// No problem more severe than `info` was found.
+    pub fn is_ok(&self) -> bool {
+        self.problems
+            .iter()
+            .all(|problem| problem.severity == WarningSeverity::Info)
+    }
+}
+
+/// How a thread's head frontmatter differs from a stored baseline
+/// (`xurl head-diff`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HeadDiff {
+    pub uri: String,
+    pub baseline: String,
+    pub changes: Vec<HeadChange>,
+}
+
+impl HeadDiff {
+    pub fn is_unchanged(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// One differing leaf, e.g. `subagents[<agent_id>].status`. Values keep
+/// their YAML quoting; a side without the leaf has no value.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HeadChange {
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current: Option<String>,
+}
+
+/// Response times of a thread's turns (`xurl stats`). A turn is a user
+/// message and the assistant messages up to the next user message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ThreadStats {
+    pub uri: String,
+    pub thread_source: String,
+    pub messages: usize,
+    /// Time from the first to the last timestamped message.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// Spread of [`TurnStats::latency_ms`] over the turns that have one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency: Option<Percentiles>,
+    /// Spread of [`TurnStats::duration_ms`] over the turns that have one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub turn_duration: Option<Percentiles>,
+    pub turns: Vec<TurnStats>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TurnStats {
+    /// 1-based position among the thread's turns.
+    pub turn: usize,
+    /// Timestamp of the user message, as the provider wrote it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<String>,
+    /// Assistant messages in the turn.
+    pub replies: usize,
+    /// From the user message to the first reply.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// From the user message to the last reply.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+}
+
+/// Nearest-rank percentiles of a set of millisecond durations.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Percentiles {
+    pub samples: usize,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub max_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemanticSearchRequest {
+    pub query: String,
+    /// Providers to search; all of them when empty.
+    pub providers: Vec<ProviderKind>,
+    pub limit: usize,
+    /// Shell command that embeds texts: one JSON string per stdin line in,
+    /// one JSON array of numbers per stdout line out.
+    pub command: String,
+}
+
+/// Messages closest in meaning to a query (`xurl search --semantic`).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SemanticSearchResult {
+    pub query: String,
+    /// Best match first.
+    pub hits: Vec<SemanticHit>,
+    /// Threads in the index after it was brought up to date.
+    pub indexed_threads: usize,
+    /// Messages embedded by this search because their thread was new or
+    /// had changed since the last one.
+    pub embedded_messages: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SemanticHit {
+    /// Thread the message belongs to.
+    pub uri: String,
+    pub role: MessageRole,
+    /// Cosine similarity to the query, up to 1.
+    pub score: f32,
+    /// The start of the message.
+    pub preview: String,
+}
+
+/// The family of sessions a thread belongs to through resumes and forks
+/// (`xurl lineage`), from the original session down.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ThreadLineage {
+    pub uri: String,
+    /// The session every other one descends from.
+    pub root_uri: String,
+    /// Depth-first, parents before their children, siblings oldest first.
+    pub sessions: Vec<LineageNode>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LineageNode {
+    pub uri: String,
+    pub session_id: String,
+    /// Session this one was resumed or forked from; `None` for the root.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relation: Option<LineageRelation>,
+    /// Distance from the root.
+    pub depth: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<String>,
+    /// Last modification of the session's file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    pub thread_source: String,
+    /// Whether this is the session the lineage was asked for.
+    pub current: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineageRelation {
+    Resumed,
+    Forked,
+}
+
+impl fmt::Display for LineageRelation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Resumed => "resumed",
+            Self::Forked => "forked",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MessageRole {
     User,
     Assistant,
@@ -69,10 +808,104 @@ impl fmt::Display for MessageRole {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct ThreadMessage {
     pub role: MessageRole,
     pub text: String,
+    /// When the provider recorded the message, as it wrote it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineEntryKind {
+    User,
+    Assistant,
+    Compact,
+    RolloutBoundary,
+    /// A failed tool call's result, shown when tool errors are surfaced.
+    ToolError,
+    /// An Amp tool invocation paired with its result, shown when asked for.
+    ToolCall,
+}
+
+impl fmt::Display for TimelineEntryKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::User => write!(f, "user"),
+            Self::Assistant => write!(f, "assistant"),
+            Self::Compact => write!(f, "compact"),
+            Self::RolloutBoundary => write!(f, "rollout_boundary"),
+            Self::ToolError => write!(f, "tool_error"),
+            Self::ToolCall => write!(f, "tool_call"),
+        }
+    }
+}
+
+/// Delimited-text layouts of a timeline, one row per entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableFormat {
+    /// Comma-separated, quoted as in RFC 4180.
+    Csv,
+    /// Tab-separated; tabs, newlines, and backslashes in text are escaped.
+    Tsv,
+}
+
+/// Output format of a thread timeline (`--format`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimelineFormat {
+    /// Markdown with YAML frontmatter, as plain reads print it.
+    #[default]
+    Markdown,
+    /// One JSON document with the thread and its entries.
+    Json,
+    /// A standalone HTML page.
+    Html,
+    /// Plain text without markup.
+    Text,
+    Table(TableFormat),
+}
+
+/// What extraction does with a transcript line (or a message of a
+/// single-document thread) it cannot parse.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExtractionMode {
+    /// Fail on the first one, naming its file and line.
+    #[default]
+    Strict,
+    /// Skip it and report a `skipped_invalid_record` warning.
+    Lenient,
+}
+
+/// One rendered timeline entry with the identifiers other tools can link to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TimelineEntryView {
+    /// Thread URI with this entry's anchor, e.g. `agents://codex/<id>#msg-42`.
+    pub uri: String,
+    /// 1-based position in the timeline (`## 42. ...`).
+    pub number: usize,
+    /// `msg-<number>`; also the HTML anchor in rendered Markdown.
+    pub anchor: String,
+    /// The provider's own entry id, or a hash of the entry's number and content.
+    pub id: String,
+    pub kind: TimelineEntryKind,
+    pub text: String,
+    /// When the provider recorded the entry, as it wrote it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+    /// Where the entry was read from: `<file>:<line>` for JSONL transcripts,
+    /// `<file>#<JSON pointer>` for single-document ones (Amp, Gemini).
+    #[serde(skip)]
+    pub source: Option<String>,
+    /// An assistant reply rebuilt from the deltas it was streamed in, because
+    /// its final message was never written (e.g. the agent crashed).
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+    /// When `--dedupe` collapsed copies of this entry that followed it, how
+    /// many times it occurred in a row, itself included.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeats: Option<usize>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
@@ -96,6 +929,16 @@ pub struct SubagentLifecycleEvent {
     pub detail: String,
 }
 
+/// How often a subagent called one tool, see [`crate::tools`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ToolUsage {
+    pub name: String,
+    pub calls: usize,
+    /// Calls whose result was marked as failed.
+    pub errors: usize,
+    pub last_used_at: Option<String>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct SubagentExcerptMessage {
     pub role: MessageRole,
@@ -117,19 +960,33 @@ pub struct SubagentDetailView {
     pub status: String,
     pub status_source: String,
     pub child_thread: Option<SubagentThreadRef>,
+    /// Instruction the parent sent when spawning the subagent: Codex
+    /// `spawn_agent` message, Claude Task prompt, or Amp handoff message.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub initial_prompt: Option<String>,
+    /// Tools the child thread called, most used first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<ToolUsage>,
     pub excerpt: Vec<SubagentExcerptMessage>,
     #[serde(skip_serializing)]
-    pub warnings: Vec<String>,
+    pub warnings: Vec<Warning>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct SubagentListItem {
     pub agent_id: String,
+    /// Human-readable agent type (Claude `subagent_type`), usable in place of the id.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_name: Option<String>,
     pub status: String,
     pub status_source: String,
+    pub first_update: Option<String>,
     pub last_update: Option<String>,
     pub relation: SubagentRelation,
     pub child_thread: Option<SubagentThreadRef>,
+    /// Threads this subagent handed off to in turn (Amp handoff chains).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<SubagentListItem>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
@@ -137,9 +994,11 @@ pub struct SubagentListView {
     pub query: SubagentQuery,
     pub agents: Vec<SubagentListItem>,
     #[serde(skip_serializing)]
-    pub warnings: Vec<String>,
+    pub warnings: Vec<Warning>,
 }
 
+// One view is built per command, so the size gap does not matter.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 #[serde(tag = "kind", rename_all = "snake_case")]
 pub enum SubagentView {
@@ -161,7 +1020,13 @@ pub struct PiEntryListItem {
     pub parent_id: Option<String>,
     pub timestamp: Option<String>,
     pub is_leaf: bool,
+    /// `role` of message entries (`user`, `assistant`, `toolResult`, ...).
+    pub role: Option<String>,
+    /// Message or summary text, cut to the preview length unless full
+    /// previews were requested.
     pub preview: Option<String>,
+    /// Rough token count of the whole text, not just the preview.
+    pub estimated_tokens: Option<usize>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
@@ -169,5 +1034,104 @@ pub struct PiEntryListView {
     pub query: PiEntryQuery,
     pub entries: Vec<PiEntryListItem>,
     #[serde(skip_serializing)]
-    pub warnings: Vec<String>,
+    pub warnings: Vec<Warning>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PackedMessage {
+    pub role: MessageRole,
+    pub text: String,
+    /// Shortened to fit the budget; only the start of the message is kept.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PackedThread {
+    pub uri: String,
+    pub thread_source: String,
+    pub max_tokens: usize,
+    pub estimated_tokens: usize,
+    /// Older messages dropped entirely because the budget ran out.
+    pub omitted: usize,
+    pub messages: Vec<PackedMessage>,
+    #[serde(skip_serializing)]
+    pub warnings: Vec<Warning>,
+}
+
+/// Two branches of a Pi session compared from the entry where they split,
+/// see [`crate::pi_diff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PiBranchDiff {
+    pub session_id: String,
+    pub thread_source: PathBuf,
+    /// Last entry both branches contain; `None` when they share none.
+    pub common_ancestor: Option<String>,
+    /// Timeline entries both branches start with.
+    pub shared_entries: usize,
+    pub left: PiBranchSide,
+    pub right: PiBranchSide,
+    pub warnings: Vec<Warning>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PiBranchSide {
+    pub uri: String,
+    /// Entries after the common ancestor, keeping their branch-wide numbers.
+    pub entries: Vec<TimelineEntryView>,
+}
+
+/// A thread as trace spans, see [`crate::trace`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadTrace {
+    pub uri: String,
+    pub provider: ProviderKind,
+    /// 32 hex digits derived from the URI, so exports of one thread share it.
+    pub trace_id: String,
+    /// The session span first, then turns, then subagents.
+    pub spans: Vec<TraceSpan>,
+    pub warnings: Vec<Warning>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceSpan {
+    /// 16 hex digits.
+    pub span_id: String,
+    /// `None` for the session span.
+    pub parent_span_id: Option<String>,
+    pub name: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub attributes: Vec<(String, String)>,
+    /// Set for subagents that errored.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RecentThread {
+    pub uri: String,
+    pub provider: ProviderKind,
+    pub session_id: String,
+    pub thread_source: String,
+    pub last_update: Option<String>,
+    /// First user message, flattened to one line.
+    pub preview: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RecentThreadsView {
+    pub threads: Vec<RecentThread>,
+    #[serde(skip_serializing)]
+    pub warnings: Vec<Warning>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ThreadSummary {
+    pub uri: String,
+    pub thread_source: String,
+    pub summarizer: String,
+    pub goals: Vec<String>,
+    pub decisions: Vec<String>,
+    pub todos: Vec<String>,
+    #[serde(skip_serializing)]
+    pub warnings: Vec<Warning>,
 }
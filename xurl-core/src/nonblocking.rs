@@ -0,0 +1,352 @@
+//! Async entry points for servers embedding xurl-core (`async` feature).
+//!
+//! Resolution walks provider directories and SQLite indexes, and write mode
+//! reads an agent CLI's output line by line; both are blocking I/O. Rather
+//! than a second, async implementation of every provider on `tokio::fs` and
+//! `tokio::process`, these wrappers run the regular service functions on
+//! tokio's blocking pool, which is what `tokio::fs` does internally, so
+//! executor threads stay free and both APIs read threads the same way.
+//!
+//! Write events reach an [`AsyncWriteEventSink`] as they arrive. When that
+//! sink fails, or the future of [`write_thread_async`] is dropped, the agent
+//! CLI is killed, which ends the blocking write.
+
+use std::future::Future;
+
+use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
+use tokio::task;
+
+use crate::error::{Result, XurlError};
+use crate::model::{ProviderKind, ResolvedThread, WriteRequest, WriteResult};
+use crate::provider::{AgentProcess, ProviderRoots, WriteEventSink};
+use crate::service::{resolve_thread, write_thread};
+use crate::sink::WriteEvent;
+use crate::uri::ThreadUri;
+
+/// Receives write-mode progress in async code. Mirrors [`WriteEventSink`];
+/// implementations may use `async fn`.
+pub trait AsyncWriteEventSink: Send {
+    fn on_session_ready(
+        &mut self,
+        provider: ProviderKind,
+        session_id: &str,
+    ) -> impl Future<Output = Result<()>> + Send;
+    fn on_text_delta(&mut self, text: &str) -> impl Future<Output = Result<()>> + Send;
+    /// Called by [`write_thread_async`] once the write succeeded.
+    /// `result.final_text` holds the reply of providers that stream none.
+    fn on_write_finished(
+        &mut self,
+        result: &WriteResult,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let _ = result;
+        async { Ok(()) }
+    }
+}
+
+/// Async [`resolve_thread`].
+pub async fn resolve_thread_async(
+    uri: &ThreadUri,
+    roots: &ProviderRoots,
+) -> Result<ResolvedThread> {
+    let (uri, roots) = (uri.clone(), roots.clone());
+    blocking(move || resolve_thread(&uri, &roots)).await
+}
+
+/// Async [`write_thread`]. Returning early because `sink` failed, or
+/// dropping the future, kills the agent CLI.
+pub async fn write_thread_async<S: AsyncWriteEventSink>(
+    provider: ProviderKind,
+    roots: &ProviderRoots,
+    req: &WriteRequest,
+    sink: &mut S,
+) -> Result<WriteResult> {
+    let (sender, mut receiver) = unbounded_channel();
+    let (roots, req) = (roots.clone(), req.clone());
+    let write = blocking(move || write_thread(provider, &roots, &req, &mut ForwardSink { sender }));
+
+    // Once the write is done the agent has exited and killing it does
+    // nothing, so the guard only matters when this returns early.
+    let mut agent = KillOnDrop(None);
+    while let Some(forwarded) = receiver.recv().await {
+        let event = match forwarded {
+            Forwarded::Spawned(process) => {
+                agent.0 = Some(process);
+                continue;
+            }
+            Forwarded::Event(event) => event,
+        };
+        match event {
+            WriteEvent::SessionReady {
+                provider,
+                session_id,
+            } => sink.on_session_ready(provider, &session_id).await?,
+            WriteEvent::TextDelta(text) => sink.on_text_delta(&text).await?,
+            WriteEvent::Finished(result) => sink.on_write_finished(&result).await?,
+        }
+    }
+    write.await
+}
+
+enum Forwarded {
+    Spawned(AgentProcess),
+    Event(WriteEvent),
+}
+
+struct KillOnDrop(Option<AgentProcess>);
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        if let Some(agent) = &self.0 {
+            agent.kill();
+        }
+    }
+}
+
+/// Hands write events to the task driving [`write_thread_async`], unchanged.
+struct ForwardSink {
+    sender: UnboundedSender<Forwarded>,
+}
+
+impl ForwardSink {
+    fn send(&self, event: WriteEvent) -> Result<()> {
+        self.sender
+            .send(Forwarded::Event(event))
+            .map_err(|_| XurlError::Cancelled("write event receiver was dropped".to_string()))
+    }
+}
+
+impl WriteEventSink for ForwardSink {
+    fn on_session_ready(&mut self, provider: ProviderKind, session_id: &str) -> Result<()> {
+        self.send(WriteEvent::SessionReady {
+            provider,
+            session_id: session_id.to_string(),
+        })
+    }
+
+    fn on_text_delta(&mut self, text: &str) -> Result<()> {
+        self.send(WriteEvent::TextDelta(text.to_string()))
+    }
+
+    fn on_write_finished(&mut self, result: &WriteResult) -> Result<()> {
+        self.send(WriteEvent::Finished(result.clone()))
+    }
+
+    fn on_agent_spawned(&mut self, agent: &AgentProcess) {
+        let _ = self.sender.send(Forwarded::Spawned(agent.clone()));
+    }
+}
+
+/// Starts `work` on the blocking pool right away; the returned future yields
+/// its result. A panic in `work` resumes on the caller.
+fn blocking<T: Send + 'static>(
+    work: impl FnOnce() -> Result<T> + Send + 'static,
+) -> impl Future<Output = Result<T>> {
+    let handle = task::spawn_blocking(work);
+    async move {
+        match handle.await {
+            Ok(result) => result,
+            Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+            Err(err) => Err(XurlError::Cancelled(format!(
+                "blocking task was cancelled: {err}"
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use crate::error::Result;
+    use crate::model::{ProviderKind, WriteRequest, WriteResult};
+    use crate::nonblocking::{AsyncWriteEventSink, resolve_thread_async, write_thread_async};
+    use crate::provider::ProviderRoots;
+    use crate::uri::ThreadUri;
+
+    const SESSION_ID: &str = "12cb4c19-2774-4de4-a0d0-9fa32fbae29f";
+
+    #[derive(Default)]
+    struct RecordingSink {
+        events: Vec<String>,
+    }
+
+    impl AsyncWriteEventSink for RecordingSink {
+        async fn on_session_ready(
+            &mut self,
+            provider: ProviderKind,
+            session_id: &str,
+        ) -> Result<()> {
+            tokio::task::yield_now().await;
+            self.events.push(format!("ready {provider} {session_id}"));
+            Ok(())
+        }
+
+        async fn on_text_delta(&mut self, text: &str) -> Result<()> {
+            self.events.push(format!("delta {text}"));
+            Ok(())
+        }
+
+        async fn on_write_finished(&mut self, result: &WriteResult) -> Result<()> {
+            self.events
+                .push(format!("finished {:?}", result.entry_id.is_some()));
+            Ok(())
+        }
+    }
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .expect("runtime")
+    }
+
+    #[test]
+    fn resolves_and_writes_off_the_executor() {
+        let temp = tempdir().expect("tempdir");
+        let path = temp
+            .path()
+            .join("sessions/--repo--")
+            .join(format!("2026-02-23T13-00-12-780Z_{SESSION_ID}.jsonl"));
+        fs::create_dir_all(path.parent().expect("parent")).expect("mkdir");
+        fs::write(
+            &path,
+            format!(
+                "{{\"type\":\"session\",\"version\":3,\"id\":\"{SESSION_ID}\",\"timestamp\":\"2026-02-23T13:00:12.780Z\",\"cwd\":\"/repo\"}}\n"
+            ),
+        )
+        .expect("write");
+        let mut roots = ProviderRoots::for_test(temp.path());
+        roots.pi_root = temp.path().to_path_buf();
+        let uri = ThreadUri::parse(&format!("agents://pi/{SESSION_ID}")).expect("uri");
+
+        let runtime = runtime();
+        let resolved = runtime
+            .block_on(resolve_thread_async(&uri, &roots))
+            .expect("resolve");
+        assert_eq!(resolved.path, path);
+        assert_eq!(resolved.metadata.session.cwd.as_deref(), Some("/repo"));
+
+        let mut sink = RecordingSink::default();
+        let req = WriteRequest {
+            prompt: "hello".to_string(),
+            session_id: Some(SESSION_ID.to_string()),
+            parent_entry_id: None,
+            spawn_agent: false,
+            gemini_project: None,
+        };
+        let result = runtime
+            .block_on(write_thread_async(
+                ProviderKind::Pi,
+                &roots,
+                &req,
+                &mut sink,
+            ))
+            .expect("write");
+        assert!(result.entry_id.is_some());
+        assert_eq!(sink.events, ["finished true"]);
+        assert_eq!(fs::read_to_string(&path).expect("read").lines().count(), 2);
+    }
+
+    #[cfg(all(unix, feature = "write"))]
+    #[test]
+    fn streams_agent_events_to_the_async_sink() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempdir().expect("tempdir");
+        let bin = temp.path().join("codex");
+        fs::write(
+            &bin,
+            concat!(
+                "#!/bin/sh\n",
+                "echo '{\"type\":\"thread.started\",\"thread_id\":\"t-1\"}'\n",
+                "echo '{\"type\":\"item.completed\",\"item\":{\"type\":\"agent_message\",\"text\":\"hi\"}}'\n",
+            ),
+        )
+        .expect("write bin");
+        fs::set_permissions(&bin, fs::Permissions::from_mode(0o755)).expect("chmod");
+        let roots = ProviderRoots::for_test(&temp.path().join("roots"))
+            .with_binary(ProviderKind::Codex, &bin);
+
+        let mut sink = RecordingSink::default();
+        let req = WriteRequest {
+            prompt: "hello".to_string(),
+            session_id: None,
+            parent_entry_id: None,
+            spawn_agent: false,
+            gemini_project: None,
+        };
+        let result = runtime()
+            .block_on(write_thread_async(
+                ProviderKind::Codex,
+                &roots,
+                &req,
+                &mut sink,
+            ))
+            .expect("write");
+        assert_eq!(result.session_id, "t-1");
+        assert_eq!(
+            sink.events,
+            ["ready codex t-1", "delta hi", "finished false"]
+        );
+    }
+
+    #[cfg(all(unix, feature = "write"))]
+    #[test]
+    fn a_failing_async_sink_kills_the_agent() {
+        use std::os::unix::fs::PermissionsExt;
+        use std::time::{Duration, Instant};
+
+        use crate::error::XurlError;
+
+        struct FailingSink;
+
+        impl AsyncWriteEventSink for FailingSink {
+            async fn on_session_ready(&mut self, _: ProviderKind, _: &str) -> Result<()> {
+                Err(XurlError::Cancelled("client went away".to_string()))
+            }
+
+            async fn on_text_delta(&mut self, _: &str) -> Result<()> {
+                Ok(())
+            }
+        }
+
+        let temp = tempdir().expect("tempdir");
+        let bin = temp.path().join("codex");
+        fs::write(
+            &bin,
+            concat!(
+                "#!/bin/sh\n",
+                "echo '{\"type\":\"thread.started\",\"thread_id\":\"t-1\"}'\n",
+                "exec sleep 30\n",
+            ),
+        )
+        .expect("write bin");
+        fs::set_permissions(&bin, fs::Permissions::from_mode(0o755)).expect("chmod");
+        let roots = ProviderRoots::for_test(&temp.path().join("roots"))
+            .with_binary(ProviderKind::Codex, &bin);
+        let req = WriteRequest {
+            prompt: "hello".to_string(),
+            session_id: None,
+            parent_entry_id: None,
+            spawn_agent: false,
+            gemini_project: None,
+        };
+
+        let started = Instant::now();
+        let runtime = runtime();
+        let err = runtime
+            .block_on(write_thread_async(
+                ProviderKind::Codex,
+                &roots,
+                &req,
+                &mut FailingSink,
+            ))
+            .expect_err("sink failed");
+        assert!(matches!(err, XurlError::Cancelled(_)));
+        // Dropping the runtime waits for the blocking write, which only
+        // ends early if the agent was killed.
+        drop(runtime);
+        assert!(started.elapsed() < Duration::from_secs(20));
+    }
+}
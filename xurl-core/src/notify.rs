@@ -0,0 +1,339 @@
+//! Webhook notifications for long agent runs (`xurl notify`).
+//!
+//! The webhook is set in the `[notify]` table of `config.toml` under xurl's
+//! config directory:
+//!
+//! ```toml
+//! [notify]
+//! webhook = "https://hooks.slack.com/services/..."
+//! events = ["write", "wait"]
+//! ```
+//!
+//! Each [`Notification`] is posted as JSON. Its `text` field is a one-line
+//! summary, so Slack incoming webhooks show it as is. Webhook URLs usually
+//! carry a secret, so errors only name the webhook's host.
+
+use std::fs;
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::atomic::AtomicFile;
+use crate::error::{Result, XurlError};
+use crate::model::{Notification, NotifyEvent};
+use crate::provider::ProviderRoots;
+
+const CONFIG_FILE: &str = "config.toml";
+const NOTIFY_TABLE: &str = "notify";
+/// Characters of output a notification carries.
+const TAIL_CHARS: usize = 500;
+const POST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The `[notify]` table of `config.toml`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotifyConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<String>,
+    /// Events that are posted (default: all).
+    #[serde(default = "all_events")]
+    pub events: Vec<NotifyEvent>,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        Self {
+            webhook: None,
+            events: all_events(),
+        }
+    }
+}
+
+impl NotifyConfig {
+    /// The webhook `event` is posted to, if there is one.
+    pub fn webhook_for(&self, event: NotifyEvent) -> Option<&str> {
+        self.webhook
+            .as_deref()
+            .filter(|_| self.events.contains(&event))
+    }
+}
+
+fn all_events() -> Vec<NotifyEvent> {
+    NotifyEvent::ALL.to_vec()
+}
+
+/// Where xurl reads its settings from.
+pub fn config_path(roots: &ProviderRoots) -> PathBuf {
+    roots.config_root.join(CONFIG_FILE)
+}
+
+/// The notification settings; the defaults when `config.toml` or its
+/// `[notify]` table is missing.
+pub fn notify_config(roots: &ProviderRoots) -> Result<NotifyConfig> {
+    let path = config_path(roots);
+    let Some(notify) = read_config(&path)?.remove(NOTIFY_TABLE) else {
+        return Ok(NotifyConfig::default());
+    };
+    notify.try_into().map_err(|err| {
+        XurlError::Serialization(format!(
+            "invalid [{NOTIFY_TABLE}] table in {}: {err}",
+            path.display()
+        ))
+    })
+}
+
+/// Replaces the `[notify]` table of `config.toml`, or removes it when
+/// `config` is `None`, and returns the file's path. Other tables are kept,
+/// though comments in the file are not.
+pub fn set_notify_config(roots: &ProviderRoots, config: Option<&NotifyConfig>) -> Result<PathBuf> {
+    if let Some(webhook) = config.and_then(|config| config.webhook.as_deref())
+        && !(webhook.starts_with("https://") || webhook.starts_with("http://"))
+    {
+        return Err(XurlError::InvalidMode(format!(
+            "webhook must be an http:// or https:// URL, got {webhook:?}"
+        )));
+    }
+
+    let path = config_path(roots);
+    let mut table = read_config(&path)?;
+    match config {
+        Some(config) => {
+            let value = toml::Value::try_from(config)
+                .map_err(|err| XurlError::Serialization(err.to_string()))?;
+            table.insert(NOTIFY_TABLE.to_string(), value);
+        }
+        None => {
+            table.remove(NOTIFY_TABLE);
+        }
+    }
+
+    let parent = path.parent().unwrap_or(&path);
+    fs::create_dir_all(parent).map_err(|source| XurlError::io(parent, source))?;
+    let encoded =
+        toml::to_string(&table).map_err(|err| XurlError::Serialization(err.to_string()))?;
+    // The webhook URL usually carries a secret token.
+    let mut file = AtomicFile::create(&path)?.private()?;
+    file.write_all(encoded.as_bytes())
+        .map_err(|source| XurlError::io(&path, source))?;
+    file.commit()?;
+    Ok(path)
+}
+
+fn read_config(path: &std::path::Path) -> Result<toml::Table> {
+    let raw = match fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(toml::Table::new()),
+        Err(source) => return Err(XurlError::io(path, source)),
+    };
+    toml::from_str(&raw).map_err(|err| {
+        XurlError::Serialization(format!("invalid config in {}: {err}", path.display()))
+    })
+}
+
+/// A notification that `uri` ended in `status` after `duration`, carrying
+/// the tail of `output`.
+pub fn notification(
+    event: NotifyEvent,
+    uri: &str,
+    status: &str,
+    duration: Duration,
+    output: Option<&str>,
+) -> Notification {
+    Notification {
+        text: format!("{uri} {status} after {}", format_duration(duration)),
+        event,
+        uri: uri.to_string(),
+        status: status.to_string(),
+        duration_ms: u64::try_from(duration.as_millis()).unwrap_or(u64::MAX),
+        output_tail: output
+            .map(str::trim)
+            .filter(|output| !output.is_empty())
+            .map(output_tail),
+    }
+}
+
+/// Posts `notification` to the webhook configured for its event. Returns
+/// whether one was configured.
+pub fn notify(roots: &ProviderRoots, notification: &Notification) -> Result<bool> {
+    let config = notify_config(roots)?;
+    let Some(webhook) = config.webhook_for(notification.event) else {
+        return Ok(false);
+    };
+    send_notification(webhook, notification)?;
+    Ok(true)
+}
+
+/// Posts `notification` to `webhook` as JSON.
+pub fn send_notification(webhook: &str, notification: &Notification) -> Result<()> {
+    let body = serde_json::to_string(notification)
+        .map_err(|err| XurlError::Serialization(err.to_string()))?;
+    ureq::post(webhook)
+        .timeout(POST_TIMEOUT)
+        .set("Content-Type", "application/json")
+        .send_string(&body)
+        .map_err(|err| XurlError::Notify {
+            url: webhook_host(webhook),
+            message: match err {
+                ureq::Error::Status(code, _) => format!("HTTP {code}"),
+                ureq::Error::Transport(transport) => transport.kind().to_string(),
+            },
+        })?;
+    Ok(())
+}
+
+/// `scheme://host` of `webhook`, leaving out the path that holds its secret.
+fn webhook_host(webhook: &str) -> String {
+    let (scheme, rest) = webhook.split_once("://").unwrap_or(("", webhook));
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+    format!("{scheme}://{host}")
+}
+
+fn output_tail(output: &str) -> String {
+    let chars = output.chars().count();
+    if chars <= TAIL_CHARS {
+        return output.to_string();
+    }
+    let tail = output.chars().skip(chars - TAIL_CHARS).collect::<String>();
+    format!("...{}", tail.trim_start())
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::error::XurlError;
+    use crate::model::NotifyEvent;
+    use crate::notify::{
+        NotifyConfig, config_path, notification, notify, notify_config, set_notify_config,
+        webhook_host,
+    };
+    use crate::provider::ProviderRoots;
+
+    #[test]
+    fn config_keeps_other_tables_and_validates_the_webhook() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let roots = ProviderRoots::for_test(temp.path());
+        assert_eq!(
+            notify_config(&roots).expect("default"),
+            NotifyConfig::default()
+        );
+
+        fs::create_dir_all(&roots.config_root).expect("mkdir config");
+        fs::write(config_path(&roots), "[other]\nkey = 1\n").expect("write");
+        let config = NotifyConfig {
+            webhook: Some("https://hooks.example.com/services/secret".to_string()),
+            events: vec![NotifyEvent::Wait],
+        };
+        set_notify_config(&roots, Some(&config)).expect("set");
+        assert_eq!(notify_config(&roots).expect("read"), config);
+        assert_eq!(config.webhook_for(NotifyEvent::Write), None);
+        assert!(
+            fs::read_to_string(config_path(&roots))
+                .expect("read")
+                .contains("[other]")
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = fs::metadata(config_path(&roots))
+                .expect("metadata")
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        set_notify_config(&roots, None).expect("clear");
+        assert_eq!(
+            notify_config(&roots).expect("read"),
+            NotifyConfig::default()
+        );
+        assert!(matches!(
+            set_notify_config(
+                &roots,
+                Some(&NotifyConfig {
+                    webhook: Some("hooks.example.com".to_string()),
+                    ..NotifyConfig::default()
+                })
+            ),
+            Err(XurlError::InvalidMode(_))
+        ));
+        assert_eq!(
+            webhook_host("https://user:pw@hooks.example.com/services/secret?x=1"),
+            "https://hooks.example.com"
+        );
+    }
+
+    #[test]
+    fn posts_the_notification_as_json() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let port = listener.local_addr().expect("addr").port();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().expect("accept");
+            let mut reader = BufReader::new(stream);
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).expect("header");
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().expect("length");
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).expect("body");
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok")
+                .expect("respond");
+            serde_json::from_slice::<serde_json::Value>(&body).expect("json")
+        });
+
+        let temp = tempfile::tempdir().expect("tempdir");
+        let roots = ProviderRoots::for_test(temp.path());
+        set_notify_config(
+            &roots,
+            Some(&NotifyConfig {
+                webhook: Some(format!("http://127.0.0.1:{port}/hook")),
+                ..NotifyConfig::default()
+            }),
+        )
+        .expect("set");
+        let output = "x".repeat(600);
+        let sent = notification(
+            NotifyEvent::Write,
+            "agents://codex/s",
+            "completed",
+            Duration::from_secs(125),
+            Some(&output),
+        );
+        assert!(notify(&roots, &sent).expect("notify"));
+
+        let body = server.join().expect("server");
+        assert_eq!(body["text"], "agents://codex/s completed after 2m 5s");
+        assert_eq!(body["event"], "write");
+        assert_eq!(body["duration_ms"], 125_000);
+        assert_eq!(
+            body["output_tail"].as_str().map(str::len),
+            Some(503),
+            "tail is the last 500 characters after an ellipsis"
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::error::{Result, XurlError};
+use crate::head::insert_warnings;
+use crate::model::{MessageRole, PackedMessage, PackedThread, ThreadMessage, Warning};
+use crate::provider::ProviderRoots;
+use crate::render::{self, ReadOptions};
+use crate::service::{TIMELINE_LINES, read_thread_lines, resolve_thread};
+use crate::uri::ThreadUri;
+use crate::yaml::YamlMapping;
+
+/// Rough characters-per-token ratio; close enough for budgeting prompts.
+const CHARS_PER_TOKEN: usize = 4;
+/// How much of an older message survives truncation.
+const TRUNCATED_CHARS: usize = 280;
+/// Tokens set aside for the heading and per-message titles.
+const OVERHEAD_TOKENS: usize = 32;
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+/// Harness-injected blocks that carry no conversation content: environment
+/// dumps, system reminders, and slash-command echoes.
+const NOISE_TAGS: &[&str] = &[
+    "environment_context",
+    "user_instructions",
+    "system-reminder",
+    "local-command-stdout",
+    "local-command-stderr",
+    "command-name",
+    "command-message",
+    "command-args",
+    "user-prompt-submit-hook",
+];
+static NOISE_BLOCK_RE: Lazy<Regex> = Lazy::new(|| {
+    let blocks = NOISE_TAGS
+        .iter()
+        .map(|tag| format!("<{tag}>.*?</{tag}>"))
+        .collect::<Vec<_>>()
+        .join("|");
+    Regex::new(&format!("(?s){blocks}")).expect("valid regex")
+});
+static BLANK_RUN_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\n{3,}").expect("valid regex"));
+
+/// Packs a thread into roughly `max_tokens`: the newest messages are kept
+/// verbatim, older ones are truncated, and the oldest are dropped once the
+/// budget runs out.
+pub fn pack_thread(
+    uri: &ThreadUri,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+    max_tokens: usize,
+) -> Result<PackedThread> {
+    if uri.agent_id.is_some() {
+        return Err(XurlError::InvalidMode(
+            "pack only supports main thread URIs: agents://<provider>/<session_id>".to_string(),
+        ));
+    }
+
+    let resolved = resolve_thread(uri, roots)?;
+    let raw = read_thread_lines(&resolved, roots, options, TIMELINE_LINES)?;
+    let messages = render::extract_messages(
+        uri.format(),
+        &resolved.path,
+        &raw,
+        &mut options.extraction_policy(),
+    )?;
+
+    let mut packed = pack_messages(messages, max_tokens);
+    packed.uri = uri.as_agents_string();
+    packed.thread_source = resolved.path.display().to_string();
+    packed.warnings = resolved.metadata.warnings;
+    if packed.messages.is_empty() && packed.omitted > 0 {
+        packed.warnings.push(Warning::warn(
+            "budget_too_small",
+            format!("--max-tokens {max_tokens} is too small to keep any message"),
+        ));
+    }
+
+    Ok(packed)
+}
+
+fn pack_messages(messages: Vec<ThreadMessage>, max_tokens: usize) -> PackedThread {
+    let messages = messages
+        .into_iter()
+        .filter_map(|message| {
+            let text = strip_noise(&message.text);
+            (!text.is_empty()).then_some(ThreadMessage { text, ..message })
+        })
+        .collect::<Vec<_>>();
+
+    let mut used = OVERHEAD_TOKENS;
+    let mut verbatim = true;
+    let mut kept = Vec::new();
+    let mut remaining = messages.len();
+
+    for message in messages.into_iter().rev() {
+        let full_cost = estimate_tokens(&message.text) + MESSAGE_OVERHEAD_TOKENS;
+        if verbatim && used + full_cost <= max_tokens {
+            used += full_cost;
+            kept.push(PackedMessage {
+                role: message.role,
+                text: message.text,
+                truncated: false,
+            });
+            remaining -= 1;
+            continue;
+        }
+        // Once one message does not fit whole, every older one is truncated
+        // so the transcript never skips back and forth in detail.
+        verbatim = false;
+
+        let (text, truncated) = truncate_chars(&message.text, TRUNCATED_CHARS);
+        let cost = estimate_tokens(&text) + MESSAGE_OVERHEAD_TOKENS;
+        if used + cost > max_tokens {
+            break;
+        }
+        used += cost;
+        kept.push(PackedMessage {
+            role: message.role,
+            text,
+            truncated,
+        });
+        remaining -= 1;
+    }
+
+    kept.reverse();
+    PackedThread {
+        max_tokens,
+        estimated_tokens: used,
+        omitted: remaining,
+        messages: kept,
+        ..PackedThread::default()
+    }
+}
+
+fn strip_noise(text: &str) -> String {
+    let stripped = NOISE_BLOCK_RE.replace_all(text, "");
+    BLANK_RUN_RE
+        .replace_all(stripped.trim(), "\n\n")
+        .into_owned()
+}
+
+pub(crate) fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+fn truncate_chars(text: &str, limit: usize) -> (String, bool) {
+    if text.chars().count() <= limit {
+        return (text.to_string(), false);
+    }
+    let head = text.chars().take(limit).collect::<String>();
+    (format!("{}...", head.trim_end()), true)
+}
+
+pub fn render_packed_thread_markdown(packed: &PackedThread) -> String {
+    let mut frontmatter = YamlMapping::new();
+    frontmatter.insert("uri", &packed.uri);
+    frontmatter.insert("thread_source", &packed.thread_source);
+    frontmatter.insert("max_tokens", packed.max_tokens);
+    frontmatter.insert("estimated_tokens", packed.estimated_tokens);
+    insert_warnings(&mut frontmatter, &packed.warnings);
+
+    let mut output = frontmatter.to_frontmatter();
+    output.push('\n');
+    output.push_str("# Packed Thread\n\n");
+
+    if packed.omitted > 0 {
+        output.push_str(&format!(
+            "_{} earlier messages omitted._\n\n",
+            packed.omitted
+        ));
+    }
+
+    for message in &packed.messages {
+        let title = match message.role {
+            MessageRole::User => "User",
+            MessageRole::Assistant => "Assistant",
+        };
+        let note = if message.truncated {
+            " (truncated)"
+        } else {
+            ""
+        };
+        output.push_str(&format!("## {title}{note}\n\n{}\n\n", message.text));
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{MessageRole, ThreadMessage};
+    use crate::pack::{pack_messages, strip_noise};
+
+    fn message(role: MessageRole, text: &str) -> ThreadMessage {
+        ThreadMessage {
+            role,
+            text: text.to_string(),
+            timestamp: None,
+        }
+    }
+
+    #[test]
+    fn keeps_newest_verbatim_and_truncates_older() {
+        let long = "x".repeat(2000);
+        let packed = pack_messages(
+            vec![
+                message(MessageRole::User, &long),
+                message(MessageRole::Assistant, &long),
+                message(MessageRole::User, "latest question"),
+            ],
+            150,
+        );
+
+        assert_eq!(packed.omitted, 1);
+        assert_eq!(packed.messages.len(), 2);
+        assert!(packed.messages[0].truncated);
+        assert!(packed.messages[0].text.len() < 300);
+        assert_eq!(packed.messages[1].text, "latest question");
+        assert!(!packed.messages[1].truncated);
+        assert!(packed.estimated_tokens <= 150);
+    }
+
+    #[test]
+    fn strips_harness_noise_blocks() {
+        let text = "<environment_context>\n  <cwd>/repo</cwd>\n</environment_context>\n\n\n\nfix the bug<system-reminder>be nice</system-reminder>";
+        assert_eq!(strip_noise(text), "fix the bug");
+    }
+}
@@ -0,0 +1,222 @@
+//! Branch comparison for Pi sessions (`xurl pi-diff`).
+//!
+//! Both branches are read from the root to their leaf entry. The entries
+//! they start with are shared; only the entries after the last shared one
+//! (the common ancestor) are compared, row by row.
+
+use crate::error::{Result, XurlError};
+use crate::model::{PiBranchDiff, PiBranchSide, ProviderKind, TimelineEntryView};
+use crate::provider::ProviderRoots;
+use crate::render::{self, ReadOptions};
+use crate::renderer;
+use crate::service::{TIMELINE_LINES, agents_thread_uri, read_thread_lines, resolve_thread};
+use crate::uri::ThreadUri;
+use crate::yaml::YamlMapping;
+
+/// Compares the branches of one Pi session that end at `left` and `right`.
+/// A URI without an entry id stands for the branch of the latest entry.
+pub fn diff_pi_branches(
+    left: &ThreadUri,
+    right: &ThreadUri,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+) -> Result<PiBranchDiff> {
+    for uri in [left, right] {
+        if uri.provider != ProviderKind::Pi || uri.fragment.is_some() {
+            return Err(XurlError::InvalidMode(format!(
+                "pi-diff compares Pi branch URIs like agents://pi/<session_id>/<entry_id>, got {}",
+                uri.as_string()
+            )));
+        }
+    }
+    if !left.session_id.eq_ignore_ascii_case(&right.session_id) {
+        return Err(XurlError::InvalidMode(format!(
+            "pi-diff compares two branches of one session, got sessions {} and {}",
+            left.session_id, right.session_id
+        )));
+    }
+
+    let main_uri = ThreadUri {
+        agent_id: None,
+        ..left.clone()
+    };
+    let resolved = resolve_thread(&main_uri, roots)?;
+    let raw = read_thread_lines(&resolved, roots, options, TIMELINE_LINES)?;
+    let mut policy = options.extraction_policy();
+    let left_entries = render::timeline_entries(left, &resolved.path, &raw, &mut policy)?;
+    let right_entries = render::timeline_entries(right, &resolved.path, &raw, &mut policy)?;
+
+    // Entry ids are unique, so the branches agree up to the last shared id.
+    let shared_entries = left_entries
+        .iter()
+        .zip(&right_entries)
+        .take_while(|(left, right)| left.id == right.id)
+        .count();
+    let common_ancestor = shared_entries
+        .checked_sub(1)
+        .map(|idx| left_entries[idx].id.clone());
+    let side = |uri: &ThreadUri, mut entries: Vec<TimelineEntryView>| PiBranchSide {
+        uri: uri.as_agents_string(),
+        entries: entries.split_off(shared_entries),
+    };
+
+    Ok(PiBranchDiff {
+        session_id: resolved.session_id.clone(),
+        thread_source: resolved.path.clone(),
+        common_ancestor,
+        shared_entries,
+        left: side(left, left_entries),
+        right: side(right, right_entries),
+        warnings: resolved.metadata.warnings,
+    })
+}
+
+/// Renders the divergent entries of both branches side by side, one table
+/// row per timeline number.
+pub fn render_pi_diff_markdown(diff: &PiBranchDiff) -> String {
+    let mut frontmatter = YamlMapping::new();
+    frontmatter.insert("left", &diff.left.uri);
+    frontmatter.insert("right", &diff.right.uri);
+    frontmatter.insert(
+        "thread_source",
+        diff.thread_source.to_string_lossy().into_owned(),
+    );
+    frontmatter.insert_opt("common_ancestor", diff.common_ancestor.as_ref());
+    frontmatter.insert("shared_entries", diff.shared_entries);
+
+    let mut output = frontmatter.to_frontmatter();
+    output.push('\n');
+    output.push_str("# Pi Branch Diff\n\n");
+
+    match &diff.common_ancestor {
+        Some(ancestor) => output.push_str(&format!(
+            "_Both branches share {} entries, up to `{}`._\n\n",
+            diff.shared_entries,
+            agents_thread_uri("pi", &diff.session_id, Some(ancestor))
+        )),
+        None => output.push_str("_The branches share no entries._\n\n"),
+    }
+    if diff.left.entries.is_empty() && diff.right.entries.is_empty() {
+        output.push_str("_Both URIs end on the same branch entry; there is nothing to compare._\n");
+        return output;
+    }
+
+    output.push_str(&format!(
+        "| # | `{}` | `{}` |\n| --- | --- | --- |\n",
+        diff.left.uri, diff.right.uri
+    ));
+    let rows = diff.left.entries.len().max(diff.right.entries.len());
+    for row in 0..rows {
+        let left = diff.left.entries.get(row);
+        let right = diff.right.entries.get(row);
+        let number = left.or(right).map_or(0, |entry| entry.number);
+        output.push_str(&format!(
+            "| {number} | {} | {} |\n",
+            left.map(table_cell).unwrap_or_default(),
+            right.map(table_cell).unwrap_or_default()
+        ));
+    }
+
+    output
+}
+
+/// An entry as one Markdown table cell: its heading, id, and text, with
+/// line breaks as `<br>` and pipes escaped.
+fn table_cell(entry: &TimelineEntryView) -> String {
+    let text = entry
+        .text
+        .trim()
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>");
+    format!(
+        "**{}** `{}`<br>{text}",
+        renderer::entry_title(entry.kind),
+        entry.id
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use crate::pi_diff::{diff_pi_branches, render_pi_diff_markdown};
+    use crate::provider::ProviderRoots;
+    use crate::render::ReadOptions;
+    use crate::uri::ThreadUri;
+
+    const SESSION_ID: &str = "12cb4c19-2774-4de4-a0d0-9fa32fbae29f";
+
+    fn entry(id: &str, parent: Option<&str>, role: &str, text: &str) -> String {
+        let parent = parent.map_or("null".to_string(), |parent| format!("\"{parent}\""));
+        format!(
+            r#"{{"type":"message","id":"{id}","parentId":{parent},"message":{{"role":"{role}","content":[{{"type":"text","text":"{text}"}}]}}}}"#
+        ) + "\n"
+    }
+
+    fn roots_with_session(base: &std::path::Path) -> ProviderRoots {
+        let path = base.join(format!(
+            "pi/sessions/--tmp-project--/2026-02-23T13-00-12-780Z_{SESSION_ID}.jsonl"
+        ));
+        fs::create_dir_all(path.parent().expect("parent")).expect("mkdir");
+        fs::write(
+            &path,
+            format!("{{\"type\":\"session\",\"version\":3,\"id\":\"{SESSION_ID}\"}}\n")
+                + &entry("a1", None, "user", "root")
+                + &entry("b1", Some("a1"), "assistant", "root done")
+                + &entry("c1", Some("b1"), "user", "try one")
+                + &entry("d1", Some("c1"), "assistant", "one | done\\nsecond line")
+                + &entry("e1", Some("b1"), "user", "try two"),
+        )
+        .expect("write");
+        ProviderRoots::for_test(base)
+    }
+
+    fn uri(entry_id: &str) -> ThreadUri {
+        ThreadUri::parse(&format!("pi://{SESSION_ID}/{entry_id}")).expect("uri")
+    }
+
+    #[test]
+    fn only_entries_after_the_common_ancestor_are_compared() {
+        let temp = tempdir().expect("tempdir");
+        let roots = roots_with_session(temp.path());
+
+        let diff = diff_pi_branches(&uri("d1"), &uri("e1"), &roots, &ReadOptions::default())
+            .expect("diff");
+        assert_eq!(diff.common_ancestor.as_deref(), Some("b1"));
+        assert_eq!(diff.shared_entries, 2);
+        let ids = |side: &crate::model::PiBranchSide| {
+            side.entries
+                .iter()
+                .map(|entry| (entry.number, entry.id.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            ids(&diff.left),
+            [(3, "c1".to_string()), (4, "d1".to_string())]
+        );
+        assert_eq!(ids(&diff.right), [(3, "e1".to_string())]);
+
+        let markdown = render_pi_diff_markdown(&diff);
+        assert!(markdown.contains("common_ancestor: 'b1'"));
+        assert!(markdown.contains(&format!(
+            "| # | `agents://pi/{SESSION_ID}/d1` | `agents://pi/{SESSION_ID}/e1` |"
+        )));
+        assert!(markdown.contains("| 3 | **User** `c1`<br>try one | **User** `e1`<br>try two |"));
+        assert!(markdown.contains("| 4 | **Assistant** `d1`<br>one \\| done<br>second line |  |"));
+        assert!(!markdown.contains("root done"));
+    }
+
+    #[test]
+    fn rejects_branches_of_different_sessions() {
+        let temp = tempdir().expect("tempdir");
+        let roots = roots_with_session(temp.path());
+        let other = ThreadUri::parse("pi://22cb4c19-2774-4de4-a0d0-9fa32fbae29f/a1").expect("uri");
+
+        let err = diff_pi_branches(&uri("d1"), &other, &roots, &ReadOptions::default())
+            .expect_err("must fail");
+        assert!(err.to_string().contains("two branches of one session"));
+    }
+}
@@ -1,8 +1,22 @@
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 use crate::error::{Result, XurlError};
-use crate::model::{ProviderKind, ResolutionMeta, ResolvedThread};
-use crate::provider::Provider;
+use crate::model::{
+    ProviderKind, ResolutionMeta, ResolvedThread, SessionInfo, StorageSchema, ThreadListing,
+};
+use crate::provider::{MaintenanceProvider, Provider, modified_or_epoch};
+
+/// Thread file of the directory layout newer Amp builds write:
+/// `threads/<id>/thread.json`, with the messages split across
+/// `messages-<n>.json` shards next to it.
+const THREAD_DIR_FILE: &str = "thread.json";
+const SHARD_PREFIX: &str = "messages-";
+/// `threads/<id>/thread.json` plus message shards.
+const LAYOUT_DIRECTORY: &str = "thread_directory";
+/// `threads/<id>.json`, the layout of older Amp builds.
+const LAYOUT_SINGLE_FILE: &str = "single_file";
 
 #[derive(Debug, Clone)]
 pub struct AmpProvider {
@@ -26,29 +40,150 @@ impl Provider for AmpProvider {
 
     fn resolve(&self, session_id: &str) -> Result<ResolvedThread> {
         let threads_root = self.threads_root();
-        let path = threads_root.join(format!("{session_id}.json"));
+        let dir_path = threads_root.join(session_id).join(THREAD_DIR_FILE);
+        let file_path = threads_root.join(format!("{session_id}.json"));
 
-        if !path.exists() {
+        // A thread migrated to the directory layout may keep its old file;
+        // the directory is the one Amp still writes to.
+        let (path, source) = if dir_path.is_file() {
+            (dir_path, "amp:threads-v2")
+        } else if file_path.exists() {
+            (file_path, "amp:threads")
+        } else {
             return Err(XurlError::ThreadNotFound {
                 provider: ProviderKind::Amp.to_string(),
                 session_id: session_id.to_string(),
                 searched_roots: vec![threads_root],
             });
-        }
+        };
+        tracing::debug!(path = %path.display(), source, "amp thread");
 
         Ok(ResolvedThread {
             provider: ProviderKind::Amp,
             session_id: session_id.to_string(),
             path,
             metadata: ResolutionMeta {
-                source: "amp:threads".to_string(),
+                source: source.to_string(),
                 candidate_count: 1,
                 warnings: Vec::new(),
+                merged_paths: Vec::new(),
+                session: SessionInfo::default(),
             },
         })
     }
+
+    fn list_threads(&self) -> Result<Vec<ThreadListing>> {
+        let Ok(entries) = fs::read_dir(self.threads_root()) else {
+            return Ok(Vec::new());
+        };
+
+        Ok(entries
+            .filter_map(std::result::Result::ok)
+            .map(|entry| entry.path())
+            .filter_map(|path| {
+                if path.is_dir() {
+                    let thread = path.join(THREAD_DIR_FILE);
+                    if !thread.is_file() {
+                        return None;
+                    }
+                    return Some(ThreadListing {
+                        provider: ProviderKind::Amp,
+                        session_id: path.file_name()?.to_str()?.to_string(),
+                        updated_at: thread_modified(&thread),
+                        path: thread,
+                    });
+                }
+
+                let session_id = path
+                    .file_name()?
+                    .to_str()?
+                    .strip_suffix(".json")?
+                    .to_string();
+                if !path.is_file() || path.with_extension("").join(THREAD_DIR_FILE).is_file() {
+                    return None;
+                }
+                Some(ThreadListing {
+                    provider: ProviderKind::Amp,
+                    session_id,
+                    updated_at: thread_modified(&path),
+                    path,
+                })
+            })
+            .collect())
+    }
+
+    /// Without a thread, the directory layout once any thread uses it.
+    fn detect_schema(&self, thread: Option<&Path>) -> Option<StorageSchema> {
+        let layout = match thread {
+            Some(thread) => thread_layout(thread),
+            None => {
+                let mut layout = None;
+                for entry in fs::read_dir(self.threads_root()).ok()?.flatten() {
+                    let path = entry.path();
+                    if path.join(THREAD_DIR_FILE).is_file() {
+                        layout = Some(LAYOUT_DIRECTORY);
+                        break;
+                    }
+                    if path.is_file() && path.extension().is_some_and(|ext| ext == "json") {
+                        layout = Some(LAYOUT_SINGLE_FILE);
+                    }
+                }
+                layout?
+            }
+        };
+        Some(StorageSchema::new(layout, None))
+    }
 }
 
+/// Layout of the thread stored at `thread_path`, from its file name.
+fn thread_layout(thread_path: &Path) -> &'static str {
+    if thread_path.file_name().and_then(|name| name.to_str()) == Some(THREAD_DIR_FILE) {
+        LAYOUT_DIRECTORY
+    } else {
+        LAYOUT_SINGLE_FILE
+    }
+}
+
+/// When the thread at `thread_path` last changed: Amp appends to the newest
+/// shard of a directory-layout thread, not to `thread.json`.
+pub(crate) fn thread_modified(thread_path: &Path) -> SystemTime {
+    message_shards(thread_path)
+        .iter()
+        .map(|shard| modified_or_epoch(shard))
+        .fold(modified_or_epoch(thread_path), SystemTime::max)
+}
+
+/// The message shards of a directory-layout thread, in message order
+/// (`messages-2.json` before `messages-10.json`). A single-file thread has
+/// none.
+pub(crate) fn message_shards(thread_path: &Path) -> Vec<PathBuf> {
+    if thread_layout(thread_path) != LAYOUT_DIRECTORY {
+        return Vec::new();
+    }
+    let Some(Ok(entries)) = thread_path.parent().map(fs::read_dir) else {
+        return Vec::new();
+    };
+
+    let mut shards = entries
+        .filter_map(std::result::Result::ok)
+        .filter_map(|entry| {
+            let path = entry.path();
+            let index = path
+                .file_name()?
+                .to_str()?
+                .strip_prefix(SHARD_PREFIX)?
+                .strip_suffix(".json")?
+                .parse::<u64>()
+                .ok()?;
+            path.is_file().then_some((index, path))
+        })
+        .collect::<Vec<_>>();
+    shards.sort();
+    shards.into_iter().map(|(_, path)| path).collect()
+}
+
+impl MaintenanceProvider for AmpProvider {}
+
 #[cfg(test)]
 mod tests {
     use std::fs;
@@ -56,7 +191,7 @@ mod tests {
     use tempfile::tempdir;
 
     use crate::provider::Provider;
-    use crate::provider::amp::AmpProvider;
+    use crate::provider::amp::{AmpProvider, message_shards};
 
     #[test]
     fn resolves_from_threads_directory() {
@@ -74,6 +209,60 @@ mod tests {
         assert_eq!(resolved.metadata.source, "amp:threads");
     }
 
+    #[test]
+    fn resolves_and_lists_thread_directories_before_single_files() {
+        let temp = tempdir().expect("tempdir");
+        let threads = temp.path().join("threads");
+        let dir = threads.join("T-019c0797-c402-7389-bd80-d785c98df295");
+        fs::create_dir_all(&dir).expect("mkdir");
+        fs::write(dir.join("thread.json"), "{}").expect("write");
+        for shard in ["messages-10.json", "messages-2.json", "notes.json"] {
+            fs::write(dir.join(shard), "{\"messages\":[]}").expect("write");
+        }
+        fs::write(
+            threads.join("T-019c0797-c402-7389-bd80-d785c98df295.json"),
+            "{\"messages\":[]}",
+        )
+        .expect("write");
+        fs::write(
+            threads.join("T-019c0797-c402-7389-bd80-d785c98df296.json"),
+            "{\"messages\":[]}",
+        )
+        .expect("write");
+
+        let provider = AmpProvider::new(temp.path());
+        let resolved = provider
+            .resolve("T-019c0797-c402-7389-bd80-d785c98df295")
+            .expect("resolve should succeed");
+        assert_eq!(resolved.path, dir.join("thread.json"));
+        assert_eq!(resolved.metadata.source, "amp:threads-v2");
+        assert_eq!(
+            message_shards(&resolved.path),
+            [dir.join("messages-2.json"), dir.join("messages-10.json")]
+        );
+
+        let mut listed = provider
+            .list_threads()
+            .expect("list")
+            .into_iter()
+            .map(|listing| (listing.session_id, listing.path))
+            .collect::<Vec<_>>();
+        listed.sort();
+        assert_eq!(
+            listed,
+            [
+                (
+                    "T-019c0797-c402-7389-bd80-d785c98df295".to_string(),
+                    dir.join("thread.json")
+                ),
+                (
+                    "T-019c0797-c402-7389-bd80-d785c98df296".to_string(),
+                    threads.join("T-019c0797-c402-7389-bd80-d785c98df296.json")
+                ),
+            ]
+        );
+    }
+
     #[test]
     fn missing_thread_returns_not_found() {
         let temp = tempdir().expect("tempdir");
@@ -1,18 +1,30 @@
 use std::cmp::Reverse;
 use std::fs;
-use std::io::{BufRead, BufReader, Read};
+use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
 use std::time::SystemTime;
 
 use serde::Deserialize;
 use serde_json::Value;
 use walkdir::WalkDir;
 
+#[cfg(feature = "write")]
+use crate::binary::{self, BinaryLocator, StderrCapture};
 use crate::error::{Result, XurlError};
+#[cfg(feature = "write")]
 use crate::jsonl;
-use crate::model::{ProviderKind, ResolutionMeta, ResolvedThread, WriteRequest, WriteResult};
-use crate::provider::{Provider, WriteEventSink};
+use crate::model::{
+    ProviderKind, ResolutionMeta, ResolvedThread, SessionInfo, StorageSchema, ThreadListing,
+    Warning,
+};
+#[cfg(feature = "write")]
+use crate::model::{WriteRequest, WriteResult};
+#[cfg(feature = "write")]
+use crate::provider::{AgentProcess, WriteEventSink};
+use crate::provider::{MaintenanceProvider, Provider, dedupe_canonical_paths, modified_or_epoch};
+
+/// One JSONL transcript per session under `projects/<project>/`.
+const LAYOUT_PROJECT_JSONL: &str = "project_jsonl";
 
 #[derive(Debug, Deserialize)]
 struct SessionsIndex {
@@ -28,14 +40,36 @@ struct SessionIndexEntry {
     full_path: Option<PathBuf>,
 }
 
+type CandidateLookup = fn(&Path, &str) -> Vec<PathBuf>;
+
 #[derive(Debug, Clone)]
 pub struct ClaudeProvider {
     root: PathBuf,
+    project: Option<String>,
+    #[cfg(feature = "write")]
+    binaries: BinaryLocator,
+    #[cfg(feature = "write")]
+    debug_log: Option<PathBuf>,
 }
 
 impl ClaudeProvider {
     pub fn new(root: impl Into<PathBuf>) -> Self {
-        Self { root: root.into() }
+        Self {
+            root: root.into(),
+            project: None,
+            #[cfg(feature = "write")]
+            binaries: BinaryLocator::new(),
+            #[cfg(feature = "write")]
+            debug_log: None,
+        }
+    }
+
+    /// Restricts resolution to one `projects/<dir>` entry. Accepts either the
+    /// project directory name or a working-directory path, which is encoded
+    /// the same way Claude Code names its project directories.
+    pub fn with_project(mut self, project: Option<impl Into<String>>) -> Self {
+        self.project = project.map(Into::into);
+        self
     }
 
     fn projects_root(&self) -> PathBuf {
@@ -67,6 +101,7 @@ impl ClaudeProvider {
         }
 
         WalkDir::new(projects_root)
+            .follow_links(true)
             .into_iter()
             .filter_map(std::result::Result::ok)
             .filter(|entry| entry.file_type().is_file())
@@ -75,7 +110,7 @@ impl ClaudeProvider {
             .filter_map(|content| serde_json::from_str::<SessionsIndex>(&content).ok())
             .flat_map(|index| {
                 index.entries.into_iter().filter_map(|entry| {
-                    if entry.session_id == session_id {
+                    if entry.session_id.eq_ignore_ascii_case(session_id) {
                         entry.full_path
                     } else {
                         None
@@ -93,6 +128,7 @@ impl ClaudeProvider {
 
         let needle = format!("{session_id}.jsonl");
         WalkDir::new(projects_root)
+            .follow_links(true)
             .into_iter()
             .filter_map(std::result::Result::ok)
             .filter(|entry| entry.file_type().is_file())
@@ -100,7 +136,7 @@ impl ClaudeProvider {
             .filter(|path| {
                 path.file_name()
                     .and_then(|name| name.to_str())
-                    .is_some_and(|name| name == needle)
+                    .is_some_and(|name| name.eq_ignore_ascii_case(&needle))
             })
             .collect()
     }
@@ -120,7 +156,7 @@ impl ClaudeProvider {
                 && value
                     .get("sessionId")
                     .and_then(Value::as_str)
-                    .is_some_and(|id| id == session_id)
+                    .is_some_and(|id| id.eq_ignore_ascii_case(session_id))
             {
                 return true;
             }
@@ -129,12 +165,24 @@ impl ClaudeProvider {
         false
     }
 
+    /// The first `version` among the leading records of a transcript.
+    fn transcript_version(path: &Path) -> Option<String> {
+        let reader = BufReader::new(fs::File::open(path).ok()?);
+        reader
+            .lines()
+            .take(30)
+            .map_while(std::result::Result::ok)
+            .filter_map(|line| serde_json::from_str::<Value>(&line).ok())
+            .find_map(|value| value.get("version")?.as_str().map(ToString::to_string))
+    }
+
     fn find_by_header_scan(projects_root: &Path, session_id: &str) -> Vec<PathBuf> {
         if !projects_root.exists() {
             return Vec::new();
         }
 
         WalkDir::new(projects_root)
+            .follow_links(true)
             .into_iter()
             .filter_map(std::result::Result::ok)
             .filter(|entry| entry.file_type().is_file())
@@ -144,27 +192,108 @@ impl ClaudeProvider {
                     .and_then(|ext| ext.to_str())
                     .is_some_and(|ext| ext == "jsonl")
             })
-            .filter(|path| Self::file_contains_session_id(path, session_id))
+            .filter(|path| {
+                tracing::trace!(path = %path.display(), "scanning claude transcript header");
+                Self::file_contains_session_id(path, session_id)
+            })
+            .collect()
+    }
+
+    /// Maps a `--project` value to the directory name under `projects/`.
+    pub(crate) fn project_dir_name(projects_root: &Path, project: &str) -> String {
+        let looks_like_path = project.contains(['/', '\\']) || project.starts_with('.');
+        if !looks_like_path {
+            return project.to_string();
+        }
+
+        let path = fs::canonicalize(project).unwrap_or_else(|_| PathBuf::from(project));
+        // Canonical paths carry resolved symlinks and, on Windows, a `\\?\`
+        // prefix, so compare against the canonical root as well.
+        let canonical_root = fs::canonicalize(projects_root).ok();
+        let relative = path.strip_prefix(projects_root).ok().or_else(|| {
+            canonical_root
+                .as_deref()
+                .and_then(|root| path.strip_prefix(root).ok())
+        });
+        if let Some(first) = relative.and_then(|relative| relative.components().next()) {
+            return first.as_os_str().to_string_lossy().to_string();
+        }
+
+        Self::encode_project_path(&path.to_string_lossy())
+    }
+
+    /// Claude's directory name for a working directory: every character other
+    /// than ASCII letters and digits becomes `-`, so `C:\work\app` and
+    /// `/work/app` map to `C--work-app` and `-work-app`. Windows canonical
+    /// paths lose their `\\?\` prefix first, as Claude never sees it.
+    fn encode_project_path(path: &str) -> String {
+        let path = path
+            .strip_prefix(r"\\?\UNC\")
+            .map(|unc| format!(r"\\{unc}"))
+            .or_else(|| path.strip_prefix(r"\\?\").map(str::to_string))
+            .unwrap_or_else(|| path.to_string());
+        path.chars()
+            .map(|ch| if ch.is_ascii_alphanumeric() { ch } else { '-' })
+            .collect()
+    }
+
+    fn candidate_project(projects_root: &Path, path: &Path) -> Option<String> {
+        path.strip_prefix(projects_root)
+            .ok()?
+            .components()
+            .next()
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+    }
+
+    fn filter_project(&self, projects_root: &Path, paths: Vec<PathBuf>) -> Vec<PathBuf> {
+        let Some(project) = self.project.as_deref() else {
+            return paths;
+        };
+
+        let wanted = Self::project_dir_name(projects_root, project);
+        paths
+            .into_iter()
+            .filter(|path| {
+                Self::candidate_project(projects_root, path).is_some_and(|name| name == wanted)
+            })
             .collect()
     }
 
     fn make_resolved(
+        projects_root: &Path,
         session_id: &str,
+        candidates: &[PathBuf],
         selected: PathBuf,
-        count: usize,
         source: &str,
     ) -> ResolvedThread {
+        let count = candidates.len();
         let mut metadata = ResolutionMeta {
             source: source.to_string(),
             candidate_count: count,
             warnings: Vec::new(),
+            merged_paths: Vec::new(),
+            session: SessionInfo::default(),
         };
 
         if count > 1 {
-            metadata.warnings.push(format!(
-                "multiple matches found ({count}) for session_id={session_id}; selected latest: {}",
-                selected.display()
+            metadata.warnings.push(Warning::warn(
+                "ambiguous_session",
+                format!(
+                    "multiple matches found ({count}) for session_id={session_id}; selected latest: {}",
+                    selected.display()
+                ),
             ));
+            for candidate in candidates {
+                let project = Self::candidate_project(projects_root, candidate)
+                    .unwrap_or_else(|| "unknown".to_string());
+                metadata.warnings.push(Warning::info(
+                    "ambiguous_candidate",
+                    format!(
+                        "candidate for session_id={session_id} in project={project}: {}",
+                        candidate.display()
+                    ),
+                ));
+            }
         }
 
         ResolvedThread {
@@ -174,29 +303,23 @@ impl ClaudeProvider {
             metadata,
         }
     }
+}
 
-    fn claude_bin() -> String {
-        std::env::var("XURL_CLAUDE_BIN").unwrap_or_else(|_| "claude".to_string())
+/// Write mode: runs the Claude CLI and streams its reply.
+#[cfg(feature = "write")]
+impl ClaudeProvider {
+    /// Finds the Claude CLI for write mode through `binaries`.
+    #[must_use]
+    pub fn with_binaries(mut self, binaries: BinaryLocator) -> Self {
+        self.binaries = binaries;
+        self
     }
 
-    fn spawn_claude_command(args: &[&str]) -> Result<std::process::Child> {
-        let bin = Self::claude_bin();
-        Command::new(&bin)
-            .args(args)
-            .stdin(Stdio::null())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .map_err(|source| {
-                if source.kind() == std::io::ErrorKind::NotFound {
-                    XurlError::CommandNotFound { command: bin }
-                } else {
-                    XurlError::Io {
-                        path: PathBuf::from(bin),
-                        source,
-                    }
-                }
-            })
+    /// Appends the Claude CLI's stderr from write mode to `path`.
+    #[must_use]
+    pub fn with_debug_log(mut self, path: Option<PathBuf>) -> Self {
+        self.debug_log = path;
+        self
     }
 
     fn extract_assistant_text(value: &Value) -> Option<String> {
@@ -222,25 +345,22 @@ impl ClaudeProvider {
         req: &WriteRequest,
         sink: &mut dyn WriteEventSink,
     ) -> Result<WriteResult> {
-        let mut child = Self::spawn_claude_command(args)?;
+        let bin = self.binaries.locate(ProviderKind::Claude)?;
+        let command = format!("{} {}", bin.display(), args.join(" "));
+        let mut child = binary::spawn_piped(&bin, args)?;
         let stdout = child.stdout.take().ok_or_else(|| {
             XurlError::WriteProtocol("claude stdout pipe is unavailable".to_string())
         })?;
-        let stderr = child.stderr.take().ok_or_else(|| {
-            XurlError::WriteProtocol("claude stderr pipe is unavailable".to_string())
-        })?;
-        let stderr_handle = std::thread::spawn(move || {
-            let mut reader = BufReader::new(stderr);
-            let mut content = String::new();
-            let _ = reader.read_to_string(&mut content);
-            content
-        });
+        let stderr = StderrCapture::start(&mut child, &command, self.debug_log.as_deref())?;
+        let agent = AgentProcess::new(child);
+        sink.on_agent_spawned(&agent);
 
         let mut session_id = req.session_id.clone();
         let mut final_text = None::<String>;
         let stream_path = Path::new("<claude:stdout>");
         let reader = BufReader::new(stdout);
-        jsonl::parse_jsonl_reader(stream_path, reader, |_, value| {
+        let streamed = jsonl::parse_jsonl_reader(stream_path, reader, |_, value| {
+            tracing::trace!(event = %value, "claude event");
             let Some(event_type) = value.get("type").and_then(Value::as_str) else {
                 return Ok(());
             };
@@ -283,19 +403,16 @@ impl ClaudeProvider {
                 _ => {}
             }
             Ok(())
-        })?;
+        });
 
-        let status = child.wait().map_err(|source| XurlError::Io {
-            path: PathBuf::from(Self::claude_bin()),
-            source,
-        })?;
-        let stderr_content = stderr_handle.join().unwrap_or_default();
+        let status = agent.finish(streamed, &bin)?;
+        let stderr = stderr.finish(&status);
 
         if !status.success() {
             return Err(XurlError::CommandFailed {
-                command: format!("{} {}", Self::claude_bin(), args.join(" ")),
+                command,
                 code: status.code(),
-                stderr: stderr_content.trim().to_string(),
+                stderr,
             });
         }
 
@@ -311,6 +428,8 @@ impl ClaudeProvider {
             provider: ProviderKind::Claude,
             session_id,
             final_text,
+            entry_id: None,
+            agent_id: None,
         })
     }
 }
@@ -322,44 +441,86 @@ impl Provider for ClaudeProvider {
 
     fn resolve(&self, session_id: &str) -> Result<ResolvedThread> {
         let projects = self.projects_root();
-
-        let index_hits = Self::find_from_sessions_index(&projects, session_id);
-        if let Some((selected, count)) = Self::choose_latest(index_hits) {
-            return Ok(Self::make_resolved(
-                session_id,
-                selected,
-                count,
-                "claude:sessions-index",
-            ));
-        }
-
-        let filename_hits = Self::find_by_filename(&projects, session_id);
-        if let Some((selected, count)) = Self::choose_latest(filename_hits) {
-            return Ok(Self::make_resolved(
-                session_id,
-                selected,
-                count,
-                "claude:filename",
-            ));
-        }
-
-        let scanned_hits = Self::find_by_header_scan(&projects, session_id);
-        if let Some((selected, count)) = Self::choose_latest(scanned_hits) {
-            return Ok(Self::make_resolved(
-                session_id,
-                selected,
-                count,
-                "claude:header-scan",
-            ));
+        let lookups: [(&str, CandidateLookup); 3] = [
+            ("claude:sessions-index", Self::find_from_sessions_index),
+            ("claude:filename", Self::find_by_filename),
+            ("claude:header-scan", Self::find_by_header_scan),
+        ];
+
+        for (source, lookup) in lookups {
+            let hits = self.filter_project(
+                &projects,
+                dedupe_canonical_paths(lookup(&projects, session_id)),
+            );
+            tracing::debug!(source, ?hits, "claude lookup");
+            if let Some((selected, _)) = Self::choose_latest(hits.clone()) {
+                return Ok(Self::make_resolved(
+                    &projects, session_id, &hits, selected, source,
+                ));
+            }
         }
 
         Err(XurlError::ThreadNotFound {
             provider: ProviderKind::Claude.to_string(),
             session_id: session_id.to_string(),
-            searched_roots: vec![projects],
+            searched_roots: vec![match self.project.as_deref() {
+                Some(project) => projects.join(Self::project_dir_name(&projects, project)),
+                None => projects,
+            }],
         })
     }
 
+    fn list_threads(&self) -> Result<Vec<ThreadListing>> {
+        let projects_root = self.projects_root();
+        if !projects_root.exists() {
+            return Ok(Vec::new());
+        }
+
+        // Main sessions live directly in `projects/<project>/`; agent
+        // transcripts share the directory but carry an `agent-` prefix.
+        let paths = WalkDir::new(&projects_root)
+            .follow_links(true)
+            .min_depth(2)
+            .max_depth(2)
+            .into_iter()
+            .filter_map(std::result::Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .map(walkdir::DirEntry::into_path)
+            .collect();
+
+        Ok(dedupe_canonical_paths(paths)
+            .into_iter()
+            .filter_map(|path| {
+                let session_id = path.file_name()?.to_str()?.strip_suffix(".jsonl")?;
+                if session_id.starts_with("agent-") {
+                    return None;
+                }
+                Some(ThreadListing {
+                    provider: ProviderKind::Claude,
+                    session_id: session_id.to_string(),
+                    updated_at: modified_or_epoch(&path),
+                    path,
+                })
+            })
+            .collect())
+    }
+
+    /// `project_jsonl`. A transcript's version is the Claude Code release
+    /// that wrote it (its records' `version`), which decides their shape.
+    fn detect_schema(&self, thread: Option<&Path>) -> Option<StorageSchema> {
+        match thread {
+            Some(thread) => Some(StorageSchema::new(
+                LAYOUT_PROJECT_JSONL,
+                Self::transcript_version(thread),
+            )),
+            None => self
+                .projects_root()
+                .is_dir()
+                .then(|| StorageSchema::new(LAYOUT_PROJECT_JSONL, None)),
+        }
+    }
+
+    #[cfg(feature = "write")]
     fn write(&self, req: &WriteRequest, sink: &mut dyn WriteEventSink) -> Result<WriteResult> {
         let common = ["-p", "--verbose", "--output-format", "stream-json"];
         if let Some(session_id) = req.session_id.as_deref() {
@@ -392,6 +553,8 @@ impl Provider for ClaudeProvider {
     }
 }
 
+impl MaintenanceProvider for ClaudeProvider {}
+
 #[cfg(test)]
 mod tests {
     use std::fs;
@@ -401,6 +564,23 @@ mod tests {
     use crate::provider::Provider;
     use crate::provider::claude::ClaudeProvider;
 
+    #[test]
+    fn encodes_windows_project_paths_like_claude() {
+        for (path, expected) in [
+            (r"C:\Users\dev\my app", "C--Users-dev-my-app"),
+            (r"\\?\C:\Users\dev\app", "C--Users-dev-app"),
+            (r"\\?\UNC\server\share\app", "--server-share-app"),
+            ("/home/dev/app", "-home-dev-app"),
+        ] {
+            assert_eq!(ClaudeProvider::encode_project_path(path), expected);
+        }
+        let temp = tempdir().expect("tempdir");
+        assert_eq!(
+            ClaudeProvider::project_dir_name(temp.path(), r"D:\src\xurl"),
+            "D--src-xurl"
+        );
+    }
+
     #[test]
     fn resolves_from_sessions_index() {
         let temp = tempdir().expect("tempdir");
@@ -464,4 +644,91 @@ mod tests {
         assert_eq!(resolved.path, thread_file);
         assert_eq!(resolved.metadata.source, "claude:header-scan");
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolves_from_symlinked_project_dir() {
+        let temp = tempdir().expect("tempdir");
+        let external = tempdir().expect("tempdir");
+        let thread_file = external
+            .path()
+            .join("8C06E0F0-2978-48AC-BB42-90D13E3B0470.jsonl");
+        fs::write(&thread_file, "{}\n").expect("write thread");
+
+        let projects = temp.path().join("projects");
+        fs::create_dir_all(&projects).expect("mkdir");
+        std::os::unix::fs::symlink(external.path(), projects.join("project-linked"))
+            .expect("symlink");
+
+        let provider = ClaudeProvider::new(temp.path());
+        let resolved = provider
+            .resolve("8c06e0f0-2978-48ac-bb42-90d13e3b0470")
+            .expect("resolve should succeed");
+        assert_eq!(
+            fs::canonicalize(&resolved.path).expect("canonicalize"),
+            fs::canonicalize(&thread_file).expect("canonicalize")
+        );
+        assert_eq!(resolved.metadata.source, "claude:filename");
+    }
+
+    fn write_duplicate_sessions(
+        root: &std::path::Path,
+    ) -> (std::path::PathBuf, std::path::PathBuf) {
+        let session_id = "8c06e0f0-2978-48ac-bb42-90d13e3b0470";
+        let first = root.join(format!("projects/-Users-dev-app/{session_id}.jsonl"));
+        let second = root.join(format!("projects/-Users-dev-lib/{session_id}.jsonl"));
+        for path in [&first, &second] {
+            fs::create_dir_all(path.parent().expect("parent")).expect("mkdir");
+            fs::write(path, "{}\n").expect("write thread");
+        }
+        (first, second)
+    }
+
+    #[test]
+    fn duplicate_sessions_surface_every_candidate_as_warning() {
+        let temp = tempdir().expect("tempdir");
+        let (first, second) = write_duplicate_sessions(temp.path());
+
+        let provider = ClaudeProvider::new(temp.path());
+        let resolved = provider
+            .resolve("8c06e0f0-2978-48ac-bb42-90d13e3b0470")
+            .expect("resolve should succeed");
+        assert_eq!(resolved.metadata.candidate_count, 2);
+        assert_eq!(resolved.metadata.warnings[0].code, "ambiguous_session");
+        let warnings = resolved
+            .metadata
+            .warnings
+            .iter()
+            .map(|warning| warning.message.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(warnings.contains("multiple matches found (2)"));
+        assert!(warnings.contains(&format!("project=-Users-dev-app: {}", first.display())));
+        assert!(warnings.contains(&format!("project=-Users-dev-lib: {}", second.display())));
+    }
+
+    #[test]
+    fn project_filter_restricts_resolution_by_name_or_path() {
+        let temp = tempdir().expect("tempdir");
+        let (first, second) = write_duplicate_sessions(temp.path());
+
+        let by_name = ClaudeProvider::new(temp.path())
+            .with_project(Some("-Users-dev-lib"))
+            .resolve("8c06e0f0-2978-48ac-bb42-90d13e3b0470")
+            .expect("resolve should succeed");
+        assert_eq!(by_name.path, second);
+        assert!(by_name.metadata.warnings.is_empty());
+
+        let by_path = ClaudeProvider::new(temp.path())
+            .with_project(Some("/Users/dev/app"))
+            .resolve("8c06e0f0-2978-48ac-bb42-90d13e3b0470")
+            .expect("resolve should succeed");
+        assert_eq!(by_path.path, first);
+
+        let err = ClaudeProvider::new(temp.path())
+            .with_project(Some("-Users-dev-missing"))
+            .resolve("8c06e0f0-2978-48ac-bb42-90d13e3b0470")
+            .expect_err("must fail");
+        assert!(format!("{err}").contains("thread not found"));
+    }
 }
@@ -1,33 +1,198 @@
 use std::cmp::Reverse;
 use std::fs;
-use std::io::{BufReader, Read};
+#[cfg(feature = "write")]
+use std::io::BufReader;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
 use std::time::SystemTime;
 
+use chrono::{DateTime, Utc};
+use rusqlite::types::Value as SqlValue;
 use rusqlite::{Connection, OpenFlags, OptionalExtension};
+#[cfg(feature = "write")]
 use serde_json::Value;
 use walkdir::WalkDir;
 
+#[cfg(feature = "write")]
+use crate::binary::{self, BinaryLocator, StderrCapture};
+use crate::compression::strip_compression;
 use crate::error::{Result, XurlError};
+#[cfg(feature = "write")]
 use crate::jsonl;
-use crate::model::{ProviderKind, ResolutionMeta, ResolvedThread, WriteRequest, WriteResult};
-use crate::provider::{Provider, WriteEventSink};
+use crate::model::{
+    GcEntry, GcMode, GcReport, GcRequest, ProviderKind, ResolutionMeta, ResolvedThread,
+    SessionInfo, StorageSchema, ThreadListing, Warning,
+};
+#[cfg(feature = "write")]
+use crate::model::{WriteRequest, WriteResult};
+#[cfg(feature = "write")]
+use crate::provider::{AgentProcess, WriteEventSink};
+use crate::provider::{
+    MaintenanceProvider, Provider, dedupe_canonical_paths, modified_or_epoch, open_sqlite,
+};
+use crate::timestamp::format_rfc3339;
 
 #[derive(Debug, Clone)]
 pub struct CodexProvider {
     root: PathBuf,
+    merge_rollouts: bool,
+    #[cfg(feature = "write")]
+    binaries: BinaryLocator,
+    #[cfg(feature = "write")]
+    debug_log: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone)]
 struct SqliteThreadRecord {
     rollout_path: PathBuf,
-    archived: bool,
+    /// `None` when the table has no archived column.
+    archived: Option<bool>,
+}
+
+impl SqliteThreadRecord {
+    /// The archived flag, or, without one, whether the rollout lives under
+    /// `archived_sessions`.
+    fn is_archived(&self) -> bool {
+        self.archived.unwrap_or_else(|| {
+            self.rollout_path
+                .components()
+                .any(|component| component.as_os_str() == "archived_sessions")
+        })
+    }
+}
+
+/// Names Codex versions have used for each `threads` column xurl reads.
+const ID_COLUMNS: [&str; 3] = ["id", "thread_id", "session_id"];
+const ROLLOUT_PATH_COLUMNS: [&str; 3] = ["rollout_path", "path", "file_path"];
+const ARCHIVED_FLAG_COLUMNS: [&str; 2] = ["archived", "is_archived"];
+const ARCHIVED_AT_COLUMNS: [&str; 1] = ["archived_at"];
+
+/// Rollouts indexed by a `state_<N>.sqlite` database.
+const LAYOUT_STATE_DB: &str = "state_db";
+/// Rollouts without a sqlite index, as older Codex builds left them.
+const LAYOUT_ROLLOUTS: &str = "rollouts";
+
+/// Prepended to the task of a spawn write; Codex has no flag to spawn a
+/// subagent directly, so the session's agent is asked to do it.
+#[cfg(feature = "write")]
+const SPAWN_AGENT_PROMPT: &str = "Call the spawn_agent tool once to start a subagent, passing the task below verbatim as its message. Do not work on the task yourself and do not wait for the subagent to finish.\n\nTask:\n";
+
+#[derive(Debug, Clone, Copy)]
+enum ArchivedColumn {
+    /// Non-zero once archived.
+    Flag(&'static str),
+    /// Set to the archive time once archived, `NULL` before.
+    Timestamp(&'static str),
+}
+
+/// The `threads` columns of one state database, matched against the names
+/// known for each Codex version.
+#[derive(Debug, Clone, Copy)]
+struct ThreadsSchema {
+    id: &'static str,
+    rollout_path: &'static str,
+    archived: Option<ArchivedColumn>,
+}
+
+/// One row of `PRAGMA table_info(threads)`.
+struct TableColumn {
+    name: String,
+    column_type: String,
+    not_null: bool,
+    has_default: bool,
+}
+
+impl ThreadsSchema {
+    fn table_columns(conn: &Connection) -> std::result::Result<Vec<TableColumn>, rusqlite::Error> {
+        let mut stmt = conn.prepare("PRAGMA table_info(threads)")?;
+        stmt.query_map([], |row| {
+            Ok(TableColumn {
+                name: row.get(1)?,
+                column_type: row.get(2)?,
+                not_null: row.get::<_, i64>(3)? != 0,
+                has_default: row.get::<_, Option<String>>(4)?.is_some(),
+            })
+        })?
+        .collect()
+    }
+
+    /// Matches `columns` against the known names. Returns `None` when the
+    /// table has no id or rollout path column xurl recognizes; a missing
+    /// archived column is tolerated.
+    fn detect(columns: &[TableColumn]) -> Option<Self> {
+        let find = |known: &[&'static str]| {
+            known
+                .iter()
+                .copied()
+                .find(|name| columns.iter().any(|column| column.name == *name))
+        };
+        Some(Self {
+            id: find(&ID_COLUMNS)?,
+            rollout_path: find(&ROLLOUT_PATH_COLUMNS)?,
+            archived: find(&ARCHIVED_FLAG_COLUMNS)
+                .map(ArchivedColumn::Flag)
+                .or_else(|| find(&ARCHIVED_AT_COLUMNS).map(ArchivedColumn::Timestamp)),
+        })
+    }
+
+    /// Reads and matches the schema, explaining any drift in `warnings`.
+    fn read(
+        conn: &Connection,
+        db_path: &Path,
+        warnings: &mut Vec<Warning>,
+    ) -> std::result::Result<Option<Self>, rusqlite::Error> {
+        let columns = Self::table_columns(conn)?;
+        if columns.is_empty() {
+            // No threads table; let sqlite say so.
+            conn.prepare("SELECT 1 FROM threads")?;
+            return Ok(None);
+        }
+
+        let Some(schema) = Self::detect(&columns) else {
+            warnings.push(Warning::warn(
+                "index_schema",
+                format!(
+                    "skipped sqlite thread index {}: the threads table has no known id or rollout path column (columns: {})",
+                    db_path.display(),
+                    columns
+                        .iter()
+                        .map(|column| column.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            ));
+            return Ok(None);
+        };
+        if schema.archived.is_none() {
+            warnings.push(Warning::info(
+                "index_schema",
+                format!(
+                    "sqlite thread index {} has no archived column; rollouts under archived_sessions count as archived",
+                    db_path.display()
+                ),
+            ));
+        }
+        Ok(Some(schema))
+    }
 }
 
 impl CodexProvider {
     pub fn new(root: impl Into<PathBuf>) -> Self {
-        Self { root: root.into() }
+        Self {
+            root: root.into(),
+            merge_rollouts: false,
+            #[cfg(feature = "write")]
+            binaries: BinaryLocator::new(),
+            #[cfg(feature = "write")]
+            debug_log: None,
+        }
+    }
+
+    /// Reads every rollout recorded for a session as one thread instead of
+    /// picking the latest.
+    #[must_use]
+    pub fn with_merge_rollouts(mut self, merge_rollouts: bool) -> Self {
+        self.merge_rollouts = merge_rollouts;
+        self
     }
 
     fn sessions_root(&self) -> PathBuf {
@@ -59,15 +224,7 @@ impl CodexProvider {
         };
 
         paths.sort_by_key(|path| {
-            let version = path
-                .file_name()
-                .and_then(|name| name.to_str())
-                .and_then(|name| {
-                    name.strip_prefix("state_")
-                        .and_then(|name| name.strip_suffix(".sqlite"))
-                })
-                .and_then(|raw| raw.parse::<u32>().ok())
-                .unwrap_or(0);
+            let version = state_db_version(path).unwrap_or(0);
             let modified = fs::metadata(path)
                 .and_then(|meta| meta.modified())
                 .unwrap_or(SystemTime::UNIX_EPOCH);
@@ -80,33 +237,189 @@ impl CodexProvider {
     fn query_thread_record(
         db_path: &Path,
         session_id: &str,
+        warnings: &mut Vec<Warning>,
     ) -> std::result::Result<Option<SqliteThreadRecord>, rusqlite::Error> {
-        let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
-        let mut stmt =
-            conn.prepare("SELECT rollout_path, archived FROM threads WHERE id = ?1 LIMIT 1")?;
+        let conn = open_sqlite(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let Some(schema) = ThreadsSchema::read(&conn, db_path, warnings)? else {
+            return Ok(None);
+        };
+
+        let archived = match schema.archived {
+            Some(ArchivedColumn::Flag(column) | ArchivedColumn::Timestamp(column)) => column,
+            None => "NULL",
+        };
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}, {archived} FROM threads WHERE {} = ?1 LIMIT 1",
+            schema.rollout_path, schema.id
+        ))?;
         let row = stmt
             .query_row([session_id], |row| {
+                let archived = row.get::<_, SqlValue>(1)?;
                 Ok(SqliteThreadRecord {
                     rollout_path: PathBuf::from(row.get::<_, String>(0)?),
-                    archived: row.get::<_, i64>(1)? != 0,
+                    archived: schema.archived.map(|column| match (column, archived) {
+                        (ArchivedColumn::Flag(_), SqlValue::Integer(flag)) => flag != 0,
+                        (ArchivedColumn::Flag(_), SqlValue::Text(flag)) => {
+                            matches!(flag.trim(), "1" | "true")
+                        }
+                        (ArchivedColumn::Flag(_), _) => false,
+                        (ArchivedColumn::Timestamp(_), value) => value != SqlValue::Null,
+                    }),
                 })
             })
             .optional()?;
         Ok(row)
     }
 
+    /// Records an imported rollout in the newest state database so sqlite
+    /// lookups find it. Required columns xurl does not know about are filled
+    /// with empty values.
+    pub(crate) fn register_rollout(
+        &self,
+        session_id: &str,
+        rollout_path: &Path,
+        created_at: i64,
+        cwd: Option<&str>,
+    ) -> Result<Option<PathBuf>> {
+        let Some(db_path) = self.state_db_paths().into_iter().next() else {
+            return Ok(None);
+        };
+
+        Self::insert_thread_record(&db_path, session_id, rollout_path, created_at, cwd)
+            .map(|inserted| inserted.then_some(db_path.clone()))
+            .map_err(|source| XurlError::Sqlite {
+                path: db_path,
+                source,
+            })
+    }
+
+    fn insert_thread_record(
+        db_path: &Path,
+        session_id: &str,
+        rollout_path: &Path,
+        created_at: i64,
+        cwd: Option<&str>,
+    ) -> std::result::Result<bool, rusqlite::Error> {
+        let conn = open_sqlite(db_path, OpenFlags::default())?;
+        let columns = ThreadsSchema::table_columns(&conn)?;
+        let Some(schema) = ThreadsSchema::detect(&columns) else {
+            return Ok(false);
+        };
+        let archived_flag = match schema.archived {
+            Some(ArchivedColumn::Flag(column)) => Some(column),
+            _ => None,
+        };
+
+        let mut names = Vec::new();
+        let mut values = Vec::<SqlValue>::new();
+        for column in columns {
+            let name = column.name.as_str();
+            let value = match (name, cwd) {
+                _ if name == schema.id => SqlValue::Text(session_id.to_string()),
+                _ if name == schema.rollout_path => {
+                    SqlValue::Text(rollout_path.display().to_string())
+                }
+                _ if Some(name) == archived_flag => SqlValue::Integer(0),
+                ("created_at" | "updated_at", _) => SqlValue::Integer(created_at),
+                ("cwd", Some(cwd)) => SqlValue::Text(cwd.to_string()),
+                _ if column.not_null && !column.has_default => {
+                    if column.column_type.to_ascii_uppercase().contains("INT") {
+                        SqlValue::Integer(0)
+                    } else {
+                        SqlValue::Text(String::new())
+                    }
+                }
+                _ => continue,
+            };
+            names.push(column.name);
+            values.push(value);
+        }
+
+        let placeholders = (1..=names.len())
+            .map(|index| format!("?{index}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        conn.execute(
+            &format!(
+                "INSERT OR REPLACE INTO threads ({}) VALUES ({placeholders})",
+                names.join(", ")
+            ),
+            rusqlite::params_from_iter(values),
+        )?;
+        Ok(true)
+    }
+
+    /// Points (or removes) the sqlite thread rows for a session after its
+    /// rollout was archived or deleted.
+    fn update_thread_index(
+        state_dbs: &[PathBuf],
+        session_id: &str,
+        archived_path: Option<&Path>,
+        warnings: &mut Vec<Warning>,
+    ) {
+        for db_path in state_dbs {
+            let result = open_sqlite(db_path, OpenFlags::default()).and_then(|conn| {
+                let Some(schema) = ThreadsSchema::read(&conn, db_path, warnings)? else {
+                    return Ok(0);
+                };
+                let Some(path) = archived_path else {
+                    return conn.execute(
+                        &format!("DELETE FROM threads WHERE {} = ?1", schema.id),
+                        [session_id],
+                    );
+                };
+                let mark_archived = match schema.archived {
+                    Some(ArchivedColumn::Flag(column)) => format!(", {column} = 1"),
+                    Some(ArchivedColumn::Timestamp(column)) => {
+                        format!(", {column} = {}", Utc::now().timestamp())
+                    }
+                    None => String::new(),
+                };
+                conn.execute(
+                    &format!(
+                        "UPDATE threads SET {} = ?1{mark_archived} WHERE {} = ?2",
+                        schema.rollout_path, schema.id
+                    ),
+                    [path.display().to_string().as_str(), session_id],
+                )
+            });
+            if let Err(err) = result {
+                warnings.push(Warning::warn(
+                    "index_update_failed",
+                    format!(
+                        "failed updating sqlite thread index {} for session_id={session_id}: {err}",
+                        db_path.display()
+                    ),
+                ));
+            }
+        }
+    }
+
+    fn rollout_session_id(path: &Path) -> Option<String> {
+        let stem = strip_compression(path.file_name()?.to_str()?).strip_suffix(".jsonl")?;
+        if !stem.starts_with("rollout-") {
+            return None;
+        }
+        let id = stem.get(stem.len().checked_sub(36)?..)?;
+        uuid::Uuid::parse_str(id).ok()?;
+        Some(id.to_string())
+    }
+
     fn lookup_thread_from_state_db(
         state_dbs: &[PathBuf],
         session_id: &str,
-        warnings: &mut Vec<String>,
+        warnings: &mut Vec<Warning>,
     ) -> Option<SqliteThreadRecord> {
         for db_path in state_dbs {
-            match Self::query_thread_record(db_path, session_id) {
+            match Self::query_thread_record(db_path, session_id, warnings) {
                 Ok(Some(record)) => return Some(record),
                 Ok(None) => continue,
-                Err(err) => warnings.push(format!(
-                    "failed reading sqlite thread index {}: {err}",
-                    db_path.display()
+                Err(err) => warnings.push(Warning::warn(
+                    "index_unreadable",
+                    format!(
+                        "failed reading sqlite thread index {}: {err}",
+                        db_path.display()
+                    ),
                 )),
             }
         }
@@ -114,13 +427,106 @@ impl CodexProvider {
         None
     }
 
+    /// Compares every sqlite thread index row for `session_id` with the
+    /// rollout the session resolved to. Returns nothing when Codex keeps no
+    /// state database.
+    pub(crate) fn check_state_index(&self, session_id: &str, rollout: &Path) -> Vec<Warning> {
+        let state_dbs = self.state_db_paths();
+        if state_dbs.is_empty() {
+            return Vec::new();
+        }
+
+        let rollout = fs::canonicalize(rollout).unwrap_or_else(|_| rollout.to_path_buf());
+        let archived_root =
+            fs::canonicalize(self.archived_root()).unwrap_or_else(|_| self.archived_root());
+        let mut problems = Vec::new();
+        let mut indexed = false;
+        for db_path in &state_dbs {
+            let record = match Self::query_thread_record(db_path, session_id, &mut problems) {
+                Ok(Some(record)) => record,
+                Ok(None) => continue,
+                Err(err) => {
+                    problems.push(Warning::error(
+                        "index_unreadable",
+                        format!(
+                            "failed reading sqlite thread index {}: {err}",
+                            db_path.display()
+                        ),
+                    ));
+                    continue;
+                }
+            };
+            indexed = true;
+
+            let Ok(indexed_path) = fs::canonicalize(&record.rollout_path) else {
+                problems.push(Warning::error(
+                    "index_stale",
+                    format!(
+                        "{} points to a missing rollout: {}",
+                        db_path.display(),
+                        record.rollout_path.display()
+                    ),
+                ));
+                continue;
+            };
+            if indexed_path != rollout {
+                problems.push(Warning::warn(
+                    "index_mismatch",
+                    format!(
+                        "{} points to {} instead of {}",
+                        db_path.display(),
+                        record.rollout_path.display(),
+                        rollout.display()
+                    ),
+                ));
+            }
+            if let Some(archived) = record.archived
+                && archived != indexed_path.starts_with(&archived_root)
+            {
+                problems.push(Warning::warn(
+                    "index_archived_flag",
+                    format!(
+                        "{} marks the thread as {} but its rollout is {}",
+                        db_path.display(),
+                        if archived { "archived" } else { "active" },
+                        if archived {
+                            "outside archived_sessions"
+                        } else {
+                            "under archived_sessions"
+                        }
+                    ),
+                ));
+            }
+        }
+
+        if !indexed && problems.is_empty() {
+            problems.push(Warning::warn(
+                "index_missing",
+                format!(
+                    "no sqlite thread index row for session_id={session_id} in {}",
+                    state_dbs
+                        .iter()
+                        .map(|path| path.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            ));
+        }
+
+        problems
+    }
+
     fn find_candidates(root: &Path, session_id: &str) -> Vec<PathBuf> {
-        let needle = format!("{session_id}.jsonl");
+        let needle = format!("{session_id}.jsonl").to_ascii_lowercase();
         if !root.exists() {
             return Vec::new();
         }
 
-        WalkDir::new(root)
+        // Follow symlinks so symlinked date directories are discovered, and
+        // match case-insensitively because macOS volumes may preserve a
+        // different case than the URI. Archived rollouts may be compressed.
+        let candidates = WalkDir::new(root)
+            .follow_links(true)
             .into_iter()
             .filter_map(std::result::Result::ok)
             .filter(|entry| entry.file_type().is_file())
@@ -128,9 +534,16 @@ impl CodexProvider {
             .filter(|path| {
                 path.file_name()
                     .and_then(|name| name.to_str())
-                    .is_some_and(|name| name.starts_with("rollout-") && name.ends_with(&needle))
+                    .map(str::to_ascii_lowercase)
+                    .is_some_and(|name| {
+                        name.starts_with("rollout-") && strip_compression(&name).ends_with(&needle)
+                    })
             })
-            .collect()
+            .collect();
+
+        let candidates = dedupe_canonical_paths(candidates);
+        tracing::debug!(root = %root.display(), ?candidates, "codex rollout candidates");
+        candidates
     }
 
     fn choose_latest(paths: Vec<PathBuf>) -> Option<(PathBuf, usize)> {
@@ -153,28 +566,76 @@ impl CodexProvider {
         scored.into_iter().next().map(|(path, _)| (path, count))
     }
 
-    fn codex_bin() -> String {
-        std::env::var("XURL_CODEX_BIN").unwrap_or_else(|_| "codex".to_string())
-    }
-
-    fn spawn_codex_command(args: &[&str]) -> Result<std::process::Child> {
-        let bin = Self::codex_bin();
-        Command::new(&bin)
-            .args(args)
-            .stdin(Stdio::null())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .map_err(|source| {
-                if source.kind() == std::io::ErrorKind::NotFound {
-                    XurlError::CommandNotFound { command: bin }
-                } else {
-                    XurlError::Io {
-                        path: PathBuf::from(bin),
-                        source,
-                    }
-                }
+    /// Orders rollouts oldest first. Rollout file names start with their
+    /// creation timestamp, so the name decides; mtime only breaks ties.
+    fn sort_rollouts(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+        let mut keyed = paths
+            .into_iter()
+            .map(|path| {
+                let name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_ascii_lowercase())
+                    .unwrap_or_default();
+                let modified = fs::metadata(&path)
+                    .and_then(|meta| meta.modified())
+                    .unwrap_or(SystemTime::UNIX_EPOCH);
+                (name, modified, path)
             })
+            .collect::<Vec<_>>();
+
+        keyed.sort();
+        keyed.into_iter().map(|(_, _, path)| path).collect()
+    }
+
+    fn resolve_merged(session_id: &str, rollouts: Vec<PathBuf>) -> Option<ResolvedThread> {
+        if rollouts.len() < 2 {
+            return None;
+        }
+
+        let merged_paths = Self::sort_rollouts(rollouts);
+        let listed = merged_paths
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let warnings = vec![Warning::info(
+            "merged_rollouts",
+            format!(
+                "merged {} rollouts for session_id={session_id} in timestamp order: {listed}",
+                merged_paths.len()
+            ),
+        )];
+
+        Some(ResolvedThread {
+            provider: ProviderKind::Codex,
+            session_id: session_id.to_string(),
+            path: merged_paths.last()?.clone(),
+            metadata: ResolutionMeta {
+                source: "codex:sessions:merged".to_string(),
+                candidate_count: merged_paths.len(),
+                warnings,
+                merged_paths,
+                session: SessionInfo::default(),
+            },
+        })
+    }
+}
+
+/// Write mode: runs the Codex CLI and streams its reply.
+#[cfg(feature = "write")]
+impl CodexProvider {
+    /// Finds the Codex CLI for write mode through `binaries`.
+    #[must_use]
+    pub fn with_binaries(mut self, binaries: BinaryLocator) -> Self {
+        self.binaries = binaries;
+        self
+    }
+
+    /// Appends the Codex CLI's stderr from write mode to `path`.
+    #[must_use]
+    pub fn with_debug_log(mut self, path: Option<PathBuf>) -> Self {
+        self.debug_log = path;
+        self
     }
 
     fn run_write(
@@ -183,32 +644,33 @@ impl CodexProvider {
         req: &WriteRequest,
         sink: &mut dyn WriteEventSink,
     ) -> Result<WriteResult> {
-        let mut child = Self::spawn_codex_command(args)?;
+        let bin = self.binaries.locate(ProviderKind::Codex)?;
+        let command = format!("{} {}", bin.display(), args.join(" "));
+        let mut child = binary::spawn_piped(&bin, args)?;
         let stdout = child.stdout.take().ok_or_else(|| {
             XurlError::WriteProtocol("codex stdout pipe is unavailable".to_string())
         })?;
-        let stderr = child.stderr.take().ok_or_else(|| {
-            XurlError::WriteProtocol("codex stderr pipe is unavailable".to_string())
-        })?;
-        let stderr_handle = std::thread::spawn(move || {
-            let mut reader = BufReader::new(stderr);
-            let mut content = String::new();
-            let _ = reader.read_to_string(&mut content);
-            content
-        });
+        let stderr = StderrCapture::start(&mut child, &command, self.debug_log.as_deref())?;
+        let agent = AgentProcess::new(child);
+        sink.on_agent_spawned(&agent);
 
         let mut session_id = req.session_id.clone();
         let mut final_text = None::<String>;
+        let mut agent_id = None::<String>;
         let stream_path = Path::new("<codex:stdout>");
         let reader = BufReader::new(stdout);
-        jsonl::parse_jsonl_reader(stream_path, reader, |_, value| {
+        let streamed = jsonl::parse_jsonl_reader(stream_path, reader, |_, value| {
+            tracing::trace!(event = %value, "codex event");
             let Some(event_type) = value.get("type").and_then(Value::as_str) else {
                 return Ok(());
             };
 
             if event_type == "thread.started" {
                 if let Some(thread_id) = value.get("thread_id").and_then(Value::as_str) {
-                    sink.on_session_ready(ProviderKind::Codex, thread_id)?;
+                    // A spawn reports the subagent's thread instead.
+                    if !req.spawn_agent {
+                        sink.on_session_ready(ProviderKind::Codex, thread_id)?;
+                    }
                     session_id = Some(thread_id.to_string());
                 }
                 return Ok(());
@@ -221,6 +683,15 @@ impl CodexProvider {
             let Some(item) = value.get("item") else {
                 return Ok(());
             };
+            if req.spawn_agent {
+                if agent_id.is_none()
+                    && let Some(spawned) = spawned_agent_id(item)
+                {
+                    sink.on_session_ready(ProviderKind::Codex, spawned)?;
+                    agent_id = Some(spawned.to_string());
+                }
+                return Ok(());
+            }
             if item.get("type").and_then(Value::as_str) != Some("agent_message") {
                 return Ok(());
             }
@@ -230,19 +701,16 @@ impl CodexProvider {
                 final_text = Some(text.to_string());
             }
             Ok(())
-        })?;
+        });
 
-        let status = child.wait().map_err(|source| XurlError::Io {
-            path: PathBuf::from(Self::codex_bin()),
-            source,
-        })?;
-        let stderr_content = stderr_handle.join().unwrap_or_default();
+        let status = agent.finish(streamed, &bin)?;
+        let stderr = stderr.finish(&status);
 
         if !status.success() {
             return Err(XurlError::CommandFailed {
-                command: format!("{} {}", Self::codex_bin(), args.join(" ")),
+                command,
                 code: status.code(),
-                stderr: stderr_content.trim().to_string(),
+                stderr,
             });
         }
 
@@ -254,14 +722,38 @@ impl CodexProvider {
             ));
         };
 
+        if req.spawn_agent && agent_id.is_none() {
+            return Err(XurlError::WriteProtocol(format!(
+                "codex did not spawn a subagent in session {session_id}; subagents need Codex's multi-agent (collab) feature enabled"
+            )));
+        }
+
         Ok(WriteResult {
             provider: ProviderKind::Codex,
             session_id,
             final_text,
+            entry_id: None,
+            agent_id,
         })
     }
 }
 
+/// Thread of the subagent a completed `spawn_agent` collab call started.
+#[cfg(feature = "write")]
+fn spawned_agent_id(item: &Value) -> Option<&str> {
+    if item.get("type").and_then(Value::as_str) != Some("collab_tool_call")
+        || item.get("tool").and_then(Value::as_str) != Some("spawn_agent")
+        || item.get("status").and_then(Value::as_str) == Some("failed")
+    {
+        return None;
+    }
+    item.get("receiver_thread_ids")?
+        .as_array()?
+        .iter()
+        .find_map(Value::as_str)
+        .filter(|id| !id.is_empty())
+}
+
 impl Provider for CodexProvider {
     fn kind(&self) -> ProviderKind {
         ProviderKind::Codex
@@ -272,10 +764,22 @@ impl Provider for CodexProvider {
         let archived = self.archived_root();
         let state_dbs = self.state_db_paths();
         let mut warnings = Vec::new();
+
+        if self.merge_rollouts {
+            let mut rollouts = Self::find_candidates(&sessions, session_id);
+            rollouts.extend(Self::find_candidates(&archived, session_id));
+            if let Some(resolved) = Self::resolve_merged(session_id, rollouts) {
+                return Ok(resolved);
+            }
+        }
+
         let sqlite_record =
             Self::lookup_thread_from_state_db(&state_dbs, session_id, &mut warnings);
 
-        if let Some(record) = sqlite_record.as_ref().filter(|record| !record.archived) {
+        if let Some(record) = sqlite_record
+            .as_ref()
+            .filter(|record| !record.is_archived())
+        {
             if record.rollout_path.exists() {
                 return Ok(ResolvedThread {
                     provider: ProviderKind::Codex,
@@ -285,22 +789,30 @@ impl Provider for CodexProvider {
                         source: "codex:sqlite:sessions".to_string(),
                         candidate_count: 1,
                         warnings,
+                        merged_paths: Vec::new(),
+                        session: SessionInfo::default(),
                     },
                 });
             }
 
-            warnings.push(format!(
-                "sqlite thread index points to a missing rollout for session_id={session_id}: {}",
-                record.rollout_path.display()
+            warnings.push(Warning::warn(
+                "index_stale",
+                format!(
+                    "sqlite thread index points to a missing rollout for session_id={session_id}: {}",
+                    record.rollout_path.display()
+                ),
             ));
         }
 
         let active_candidates = Self::find_candidates(&sessions, session_id);
         if let Some((selected, count)) = Self::choose_latest(active_candidates) {
             if count > 1 {
-                warnings.push(format!(
-                    "multiple matches found ({count}) for session_id={session_id}; selected latest: {}",
-                    selected.display()
+                warnings.push(Warning::warn(
+                    "ambiguous_session",
+                    format!(
+                        "multiple matches found ({count}) for session_id={session_id}; selected latest: {}",
+                        selected.display()
+                    ),
                 ));
             }
 
@@ -308,6 +820,8 @@ impl Provider for CodexProvider {
                 source: "codex:sessions".to_string(),
                 candidate_count: count,
                 warnings,
+                merged_paths: Vec::new(),
+                session: SessionInfo::default(),
             };
 
             return Ok(ResolvedThread {
@@ -318,7 +832,7 @@ impl Provider for CodexProvider {
             });
         }
 
-        if let Some(record) = sqlite_record.as_ref().filter(|record| record.archived) {
+        if let Some(record) = sqlite_record.as_ref().filter(|record| record.is_archived()) {
             if record.rollout_path.exists() {
                 return Ok(ResolvedThread {
                     provider: ProviderKind::Codex,
@@ -328,22 +842,30 @@ impl Provider for CodexProvider {
                         source: "codex:sqlite:archived_sessions".to_string(),
                         candidate_count: 1,
                         warnings,
+                        merged_paths: Vec::new(),
+                        session: SessionInfo::default(),
                     },
                 });
             }
 
-            warnings.push(format!(
-                "sqlite thread index points to a missing archived rollout for session_id={session_id}: {}",
-                record.rollout_path.display()
+            warnings.push(Warning::warn(
+                "index_stale",
+                format!(
+                    "sqlite thread index points to a missing archived rollout for session_id={session_id}: {}",
+                    record.rollout_path.display()
+                ),
             ));
         }
 
         let archived_candidates = Self::find_candidates(&archived, session_id);
         if let Some((selected, count)) = Self::choose_latest(archived_candidates) {
             if count > 1 {
-                warnings.push(format!(
-                    "multiple archived matches found ({count}) for session_id={session_id}; selected latest: {}",
-                    selected.display()
+                warnings.push(Warning::warn(
+                    "ambiguous_session",
+                    format!(
+                        "multiple archived matches found ({count}) for session_id={session_id}; selected latest: {}",
+                        selected.display()
+                    ),
                 ));
             }
 
@@ -351,6 +873,8 @@ impl Provider for CodexProvider {
                 source: "codex:archived_sessions".to_string(),
                 candidate_count: count,
                 warnings,
+                merged_paths: Vec::new(),
+                session: SessionInfo::default(),
             };
 
             return Ok(ResolvedThread {
@@ -371,29 +895,180 @@ impl Provider for CodexProvider {
         })
     }
 
+    fn list_threads(&self) -> Result<Vec<ThreadListing>> {
+        let sessions = self.sessions_root();
+        if !sessions.exists() {
+            return Ok(Vec::new());
+        }
+
+        let paths = WalkDir::new(&sessions)
+            .follow_links(true)
+            .into_iter()
+            .filter_map(std::result::Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .map(walkdir::DirEntry::into_path)
+            .collect();
+
+        Ok(dedupe_canonical_paths(paths)
+            .into_iter()
+            .filter_map(|path| {
+                Some(ThreadListing {
+                    provider: ProviderKind::Codex,
+                    session_id: Self::rollout_session_id(&path)?,
+                    updated_at: modified_or_epoch(&path),
+                    path,
+                })
+            })
+            .collect())
+    }
+
+    /// `state_db` with the version of the newest sqlite index, or plain
+    /// `rollouts` without one. Rollouts carry no format version themselves.
+    fn detect_schema(&self, thread: Option<&Path>) -> Option<StorageSchema> {
+        if let Some(db_path) = self.state_db_paths().first() {
+            let version = state_db_version(db_path).map(|version| version.to_string());
+            return Some(StorageSchema::new(LAYOUT_STATE_DB, version));
+        }
+        (thread.is_some() || self.sessions_root().is_dir())
+            .then(|| StorageSchema::new(LAYOUT_ROLLOUTS, None))
+    }
+
+    #[cfg(feature = "write")]
     fn write(&self, req: &WriteRequest, sink: &mut dyn WriteEventSink) -> Result<WriteResult> {
         if let Some(session_id) = req.session_id.as_deref() {
-            self.run_write(
-                &["exec", "resume", "--json", session_id, req.prompt.as_str()],
-                req,
-                sink,
-            )
+            let spawn_prompt;
+            let prompt = if req.spawn_agent {
+                spawn_prompt = format!("{SPAWN_AGENT_PROMPT}{}", req.prompt);
+                spawn_prompt.as_str()
+            } else {
+                req.prompt.as_str()
+            };
+            self.run_write(&["exec", "resume", "--json", session_id, prompt], req, sink)
         } else {
             self.run_write(&["exec", "--json", req.prompt.as_str()], req, sink)
         }
     }
 }
 
+/// `N` of a `state_<N>.sqlite` index; `None` for the unversioned
+/// `state.sqlite`.
+fn state_db_version(path: &Path) -> Option<u32> {
+    path.file_name()?
+        .to_str()?
+        .strip_prefix("state_")?
+        .strip_suffix(".sqlite")?
+        .parse()
+        .ok()
+}
+
+impl MaintenanceProvider for CodexProvider {
+    fn collect_garbage(&self, req: &GcRequest) -> Result<GcReport> {
+        let sessions = self.sessions_root();
+        let archived = self.archived_root();
+        let state_dbs = self.state_db_paths();
+        let mut warnings = Vec::new();
+        let mut entries = Vec::new();
+
+        let rollouts = if sessions.exists() {
+            WalkDir::new(&sessions)
+                .follow_links(true)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_map(std::result::Result::ok)
+                .filter(|entry| entry.file_type().is_file())
+                .map(walkdir::DirEntry::into_path)
+                .collect::<Vec<_>>()
+        } else {
+            Vec::new()
+        };
+
+        for path in rollouts {
+            let Some(session_id) = Self::rollout_session_id(&path) else {
+                continue;
+            };
+            let Ok(meta) = fs::metadata(&path) else {
+                continue;
+            };
+            let Some(modified) = meta.modified().ok().map(DateTime::<Utc>::from) else {
+                warnings.push(Warning::info(
+                    "gc_skipped",
+                    format!(
+                        "skipped rollout without a modification time: {}",
+                        path.display()
+                    ),
+                ));
+                continue;
+            };
+            if modified >= req.cutoff {
+                continue;
+            }
+
+            let destination = (req.mode == GcMode::Archive)
+                .then(|| path.file_name().map(|name| archived.join(name)))
+                .flatten();
+
+            if !req.dry_run {
+                let applied = match (req.mode, destination.as_deref()) {
+                    (GcMode::Archive, Some(destination)) => fs::create_dir_all(&archived)
+                        .and_then(|()| fs::rename(&path, destination))
+                        .map(|()| Some(destination)),
+                    (GcMode::Delete, _) => fs::remove_file(&path).map(|()| None),
+                    _ => Ok(None),
+                };
+                match applied {
+                    Ok(archived_path) if req.mode != GcMode::Report => {
+                        Self::update_thread_index(
+                            &state_dbs,
+                            &session_id,
+                            archived_path,
+                            &mut warnings,
+                        );
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        warnings.push(Warning::error(
+                            "gc_failed",
+                            format!("failed to {} rollout {}: {err}", req.mode, path.display()),
+                        ));
+                        continue;
+                    }
+                }
+            }
+
+            entries.push(GcEntry {
+                session_id,
+                path,
+                last_activity: Some(format_rfc3339(modified)),
+                size_bytes: meta.len(),
+                destination,
+            });
+        }
+
+        Ok(GcReport {
+            provider: ProviderKind::Codex,
+            mode: req.mode,
+            dry_run: req.dry_run,
+            cutoff: format_rfc3339(req.cutoff),
+            entries,
+            warnings,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs;
     use std::path::Path;
 
+    use std::time::{Duration, SystemTime};
+
+    use chrono::{TimeZone, Utc};
     use rusqlite::Connection;
     use tempfile::tempdir;
 
-    use crate::provider::Provider;
+    use crate::model::{GcMode, GcRequest};
     use crate::provider::codex::CodexProvider;
+    use crate::provider::{MaintenanceProvider, Provider};
 
     fn prepare_state_db(path: &Path) -> Connection {
         let conn = Connection::open(path).expect("open sqlite");
@@ -443,6 +1118,71 @@ mod tests {
         assert_eq!(resolved.metadata.source, "codex:archived_sessions");
     }
 
+    #[test]
+    fn resolves_compressed_archived_rollouts() {
+        let temp = tempdir().expect("tempdir");
+        let path = temp
+            .path()
+            .join("archived_sessions/rollout-2026-02-22T01-05-36-019c8129-f668-7951-8d56-cc5513541c26.jsonl.gz");
+        fs::create_dir_all(path.parent().expect("parent")).expect("mkdir");
+        fs::write(&path, "").expect("write");
+
+        let provider = CodexProvider::new(temp.path());
+        let resolved = provider
+            .resolve("019c8129-f668-7951-8d56-cc5513541c26")
+            .expect("resolve should succeed");
+        assert_eq!(resolved.path, path);
+        assert_eq!(
+            CodexProvider::rollout_session_id(&path).as_deref(),
+            Some("019c8129-f668-7951-8d56-cc5513541c26")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolves_through_symlinked_session_dirs() {
+        let temp = tempdir().expect("tempdir");
+        let external = tempdir().expect("tempdir");
+        let path = external
+            .path()
+            .join("23/rollout-2026-02-23T04-48-50-019c871c-b1f9-7f60-9c4f-87ed09f13592.jsonl");
+        fs::create_dir_all(path.parent().expect("parent")).expect("mkdir");
+        fs::write(&path, "{}\n").expect("write");
+
+        let month = temp.path().join("sessions/2026/02");
+        fs::create_dir_all(&month).expect("mkdir");
+        std::os::unix::fs::symlink(external.path(), month.join("linked")).expect("symlink");
+        // A second link to the same directory must not count as another match.
+        std::os::unix::fs::symlink(external.path(), month.join("alias")).expect("symlink");
+
+        let provider = CodexProvider::new(temp.path());
+        let resolved = provider
+            .resolve("019c871c-b1f9-7f60-9c4f-87ed09f13592")
+            .expect("resolve should succeed");
+        assert_eq!(
+            fs::canonicalize(&resolved.path).expect("canonicalize"),
+            fs::canonicalize(&path).expect("canonicalize")
+        );
+        assert_eq!(resolved.metadata.candidate_count, 1);
+        assert!(resolved.metadata.warnings.is_empty());
+    }
+
+    #[test]
+    fn resolves_session_id_suffix_case_insensitively() {
+        let temp = tempdir().expect("tempdir");
+        let path = temp
+            .path()
+            .join("sessions/2026/02/23/rollout-2026-02-23T04-48-50-019C871C-B1F9-7F60-9C4F-87ED09F13592.jsonl");
+        fs::create_dir_all(path.parent().expect("parent")).expect("mkdir");
+        fs::write(&path, "{}\n").expect("write");
+
+        let provider = CodexProvider::new(temp.path());
+        let resolved = provider
+            .resolve("019c871c-b1f9-7f60-9c4f-87ed09f13592")
+            .expect("resolve should succeed");
+        assert_eq!(resolved.path, path);
+    }
+
     #[test]
     fn returns_not_found_when_missing() {
         let temp = tempdir().expect("tempdir");
@@ -505,6 +1245,87 @@ mod tests {
         assert_eq!(resolved.metadata.source, "codex:sqlite:archived_sessions");
     }
 
+    #[test]
+    fn reads_thread_index_with_renamed_columns() {
+        let temp = tempdir().expect("tempdir");
+        let conn = Connection::open(temp.path().join("state_7.sqlite")).expect("open sqlite");
+        conn.execute_batch(
+            "CREATE TABLE threads (
+                thread_id TEXT PRIMARY KEY,
+                path TEXT NOT NULL,
+                archived_at INTEGER,
+                model_metadata TEXT
+            );",
+        )
+        .expect("create schema");
+
+        let session_id = "019c8129-f668-7951-8d56-cc5513541c26";
+        let rollout = temp
+            .path()
+            .join("archived_sessions/custom/path/thread.jsonl");
+        fs::create_dir_all(rollout.parent().expect("parent")).expect("mkdir");
+        fs::write(&rollout, "{}\n").expect("write");
+        conn.execute(
+            "INSERT INTO threads (thread_id, path, archived_at) VALUES (?1, ?2, 1771851717)",
+            (&session_id, rollout.display().to_string()),
+        )
+        .expect("insert thread");
+
+        let provider = CodexProvider::new(temp.path());
+        let resolved = provider
+            .resolve(session_id)
+            .expect("resolve should succeed");
+        assert_eq!(resolved.path, rollout);
+        assert_eq!(resolved.metadata.source, "codex:sqlite:archived_sessions");
+        assert!(resolved.metadata.warnings.is_empty());
+        assert!(provider.check_state_index(session_id, &rollout).is_empty());
+    }
+
+    #[test]
+    fn detects_schema_from_the_newest_state_db() {
+        let temp = tempdir().expect("tempdir");
+        let provider = CodexProvider::new(temp.path());
+        assert_eq!(provider.detect_schema(None), None);
+
+        fs::create_dir_all(temp.path().join("sessions")).expect("mkdir");
+        let schema = provider.detect_schema(None).expect("rollouts");
+        assert_eq!(schema.to_string(), "rollouts");
+
+        fs::write(temp.path().join("state_5.sqlite"), "").expect("write");
+        fs::write(temp.path().join("state_7.sqlite"), "").expect("write");
+        let schema = provider.detect_schema(None).expect("state db");
+        assert_eq!(schema.to_string(), "state_db v7");
+    }
+
+    #[test]
+    fn unknown_thread_index_schema_degrades_to_a_warning() {
+        let temp = tempdir().expect("tempdir");
+        let conn = Connection::open(temp.path().join("state_9.sqlite")).expect("open sqlite");
+        conn.execute_batch("CREATE TABLE threads (uuid TEXT PRIMARY KEY, location TEXT);")
+            .expect("create schema");
+
+        let session_id = "019c871c-b1f9-7f60-9c4f-87ed09f13592";
+        let rollout = temp.path().join(
+            "sessions/2026/02/23/rollout-2026-02-23T04-48-50-019c871c-b1f9-7f60-9c4f-87ed09f13592.jsonl",
+        );
+        fs::create_dir_all(rollout.parent().expect("parent")).expect("mkdir");
+        fs::write(&rollout, "{}\n").expect("write");
+
+        let provider = CodexProvider::new(temp.path());
+        let resolved = provider
+            .resolve(session_id)
+            .expect("resolve should succeed");
+        assert_eq!(resolved.path, rollout);
+        assert_eq!(resolved.metadata.source, "codex:sessions");
+        assert_eq!(resolved.metadata.warnings.len(), 1);
+        assert_eq!(resolved.metadata.warnings[0].code, "index_schema");
+        assert!(
+            resolved.metadata.warnings[0]
+                .message
+                .contains("columns: uuid, location")
+        );
+    }
+
     #[test]
     fn falls_back_to_filesystem_when_sqlite_rollout_missing() {
         let temp = tempdir().expect("tempdir");
@@ -532,6 +1353,79 @@ mod tests {
         assert_eq!(resolved.path, fs_rollout);
         assert_eq!(resolved.metadata.source, "codex:sessions");
         assert_eq!(resolved.metadata.warnings.len(), 1);
-        assert!(resolved.metadata.warnings[0].contains("missing rollout"));
+        assert!(
+            resolved.metadata.warnings[0]
+                .message
+                .contains("missing rollout")
+        );
+    }
+
+    #[test]
+    fn gc_archives_stale_rollouts_and_updates_sqlite() {
+        let temp = tempdir().expect("tempdir");
+        let state_db = temp.path().join("state_5.sqlite");
+        let conn = prepare_state_db(&state_db);
+
+        let stale_id = "019c871c-b1f9-7f60-9c4f-87ed09f13592";
+        let fresh_id = "019c8129-f668-7951-8d56-cc5513541c26";
+        let stale = temp.path().join(format!(
+            "sessions/2026/01/01/rollout-2026-01-01T00-00-00-{stale_id}.jsonl"
+        ));
+        let fresh = temp.path().join(format!(
+            "sessions/2026/02/23/rollout-2026-02-23T00-00-00-{fresh_id}.jsonl"
+        ));
+        for path in [&stale, &fresh] {
+            fs::create_dir_all(path.parent().expect("parent")).expect("mkdir");
+            fs::write(path, "{}\n").expect("write");
+        }
+        conn.execute(
+            "INSERT INTO threads (id, rollout_path, archived) VALUES (?1, ?2, 0)",
+            (&stale_id, stale.display().to_string()),
+        )
+        .expect("insert thread");
+        fs::File::options()
+            .write(true)
+            .open(&stale)
+            .expect("open")
+            .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(1_767_225_600))
+            .expect("set mtime");
+
+        let provider = CodexProvider::new(temp.path());
+        let cutoff = Utc
+            .with_ymd_and_hms(2026, 2, 1, 0, 0, 0)
+            .single()
+            .expect("valid date");
+
+        let preview = provider
+            .collect_garbage(&GcRequest {
+                cutoff,
+                mode: GcMode::Archive,
+                dry_run: true,
+            })
+            .expect("dry run");
+        assert_eq!(preview.entries.len(), 1);
+        assert!(stale.exists());
+
+        let report = provider
+            .collect_garbage(&GcRequest {
+                cutoff,
+                mode: GcMode::Archive,
+                dry_run: false,
+            })
+            .expect("archive");
+        let archived = temp.path().join(format!(
+            "archived_sessions/rollout-2026-01-01T00-00-00-{stale_id}.jsonl"
+        ));
+        assert_eq!(report.entries[0].session_id, stale_id);
+        assert_eq!(
+            report.entries[0].destination.as_deref(),
+            Some(archived.as_path())
+        );
+        assert!(!stale.exists());
+        assert!(fresh.exists());
+
+        let resolved = provider.resolve(stale_id).expect("resolve archived");
+        assert_eq!(resolved.path, archived);
+        assert_eq!(resolved.metadata.source, "codex:sqlite:archived_sessions");
     }
 }
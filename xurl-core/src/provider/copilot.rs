@@ -0,0 +1,256 @@
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::error::{Result, XurlError};
+use crate::model::{
+    ProviderKind, ResolutionMeta, ResolvedThread, SessionInfo, StorageSchema, ThreadListing,
+    Warning,
+};
+use crate::provider::{MaintenanceProvider, Provider, modified_or_epoch};
+
+/// Name of the event log in a session directory.
+const EVENTS_FILE: &str = "events.jsonl";
+/// `session-state/<id>/events.jsonl`.
+const LAYOUT_DIRECTORY: &str = "session_directory";
+/// `session-state/<id>.jsonl`, the layout of older releases.
+const LAYOUT_FLAT: &str = "flat_jsonl";
+
+/// GitHub Copilot CLI sessions. Each one is an event log under
+/// `session-state/`: `<session_id>.jsonl`, or `<session_id>/events.jsonl`
+/// in newer releases.
+#[derive(Debug, Clone)]
+pub struct CopilotProvider {
+    root: PathBuf,
+}
+
+impl CopilotProvider {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn sessions_root(&self) -> PathBuf {
+        self.root.join("session-state")
+    }
+
+    /// Event logs of `session_id` in either layout, the directory one first.
+    fn find_candidates(sessions_root: &Path, session_id: &str) -> Vec<PathBuf> {
+        [
+            sessions_root.join(session_id).join(EVENTS_FILE),
+            sessions_root.join(format!("{session_id}.jsonl")),
+        ]
+        .into_iter()
+        .filter(|path| path.is_file())
+        .collect()
+    }
+
+    /// Every session log with its id, taken from the file or directory name.
+    /// A session with logs in both layouts is listed once, by the one
+    /// [`Self::resolve`] selects.
+    fn session_files(sessions_root: &Path) -> Vec<(String, PathBuf)> {
+        let Ok(entries) = fs::read_dir(sessions_root) else {
+            return Vec::new();
+        };
+
+        let mut sessions = BTreeMap::new();
+        for path in entries
+            .filter_map(std::result::Result::ok)
+            .map(|entry| entry.path())
+        {
+            let name = |name: Option<&OsStr>| name?.to_str().map(str::to_ascii_lowercase);
+            if path.is_dir() {
+                let events = path.join(EVENTS_FILE);
+                if let Some(id) = name(path.file_name())
+                    && events.is_file()
+                {
+                    sessions.insert(id, events);
+                }
+            } else if path.extension().and_then(|ext| ext.to_str()) == Some("jsonl")
+                && let Some(id) = name(path.file_stem())
+            {
+                sessions.entry(id).or_insert(path);
+            }
+        }
+        sessions.into_iter().collect()
+    }
+
+    /// The `copilotVersion` the log's `session.start` event records.
+    fn copilot_version(path: &Path) -> Option<String> {
+        let reader = BufReader::new(fs::File::open(path).ok()?);
+        reader
+            .lines()
+            .take(30)
+            .map_while(std::result::Result::ok)
+            .filter_map(|line| serde_json::from_str::<Value>(&line).ok())
+            .find_map(|value| {
+                value
+                    .pointer("/data/copilotVersion")?
+                    .as_str()
+                    .map(ToString::to_string)
+            })
+    }
+}
+
+impl Provider for CopilotProvider {
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Copilot
+    }
+
+    fn resolve(&self, session_id: &str) -> Result<ResolvedThread> {
+        let sessions_root = self.sessions_root();
+        let candidates = Self::find_candidates(&sessions_root, session_id);
+        tracing::debug!(root = %sessions_root.display(), ?candidates, "copilot session candidates");
+
+        let Some(selected) = candidates.first().cloned() else {
+            return Err(XurlError::ThreadNotFound {
+                provider: ProviderKind::Copilot.to_string(),
+                session_id: session_id.to_string(),
+                searched_roots: vec![sessions_root],
+            });
+        };
+
+        let mut metadata = ResolutionMeta {
+            source: "copilot:session-state".to_string(),
+            candidate_count: candidates.len(),
+            warnings: Vec::new(),
+            merged_paths: Vec::new(),
+            session: SessionInfo::default(),
+        };
+        if candidates.len() > 1 {
+            metadata.warnings.push(Warning::warn(
+                "ambiguous_session",
+                format!(
+                    "session_id={session_id} has logs in both layouts; selected {}",
+                    selected.display()
+                ),
+            ));
+        }
+
+        Ok(ResolvedThread {
+            provider: ProviderKind::Copilot,
+            session_id: session_id.to_string(),
+            path: selected,
+            metadata,
+        })
+    }
+
+    fn list_threads(&self) -> Result<Vec<ThreadListing>> {
+        Ok(Self::session_files(&self.sessions_root())
+            .into_iter()
+            .map(|(session_id, path)| ThreadListing {
+                provider: ProviderKind::Copilot,
+                session_id,
+                updated_at: modified_or_epoch(&path),
+                path,
+            })
+            .collect())
+    }
+
+    /// The log's layout with the Copilot release that started the session.
+    /// Without a thread, the directory layout once any session uses it.
+    fn detect_schema(&self, thread: Option<&Path>) -> Option<StorageSchema> {
+        let layout = |path: &Path| {
+            if path.file_name() == Some(OsStr::new(EVENTS_FILE)) {
+                LAYOUT_DIRECTORY
+            } else {
+                LAYOUT_FLAT
+            }
+        };
+        match thread {
+            Some(thread) => Some(StorageSchema::new(
+                layout(thread),
+                Self::copilot_version(thread),
+            )),
+            None => {
+                let sessions = Self::session_files(&self.sessions_root());
+                let layout = sessions
+                    .iter()
+                    .map(|(_, path)| layout(path))
+                    .max_by_key(|layout| *layout == LAYOUT_DIRECTORY)?;
+                Some(StorageSchema::new(layout, None))
+            }
+        }
+    }
+}
+
+impl MaintenanceProvider for CopilotProvider {}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use crate::model::ProviderKind;
+    use crate::provider::Provider;
+    use crate::provider::copilot::CopilotProvider;
+    use crate::render::{ExtractionPolicy, extract_messages};
+
+    const SESSION_ID: &str = "0f6b3c1e-8a2d-4e5f-9b7c-1d2e3f4a5b6c";
+    const EVENTS: &str = concat!(
+        r#"{"type":"session.start","data":{"sessionId":"0f6b3c1e-8a2d-4e5f-9b7c-1d2e3f4a5b6c","copilotVersion":"0.0.330"},"id":"e1","timestamp":"2026-03-02T09:00:00.000Z","parentId":null}"#,
+        "\n",
+        r#"{"type":"user.message","data":{"content":"hello","attachments":[]},"id":"e2","timestamp":"2026-03-02T09:00:01.000Z","parentId":"e1"}"#,
+        "\n",
+        r#"{"type":"assistant.message","data":{"messageId":"m1","content":"","toolRequests":[{"name":"bash"}]},"id":"e3","timestamp":"2026-03-02T09:00:02.000Z","parentId":"e2"}"#,
+        "\n",
+        r#"{"type":"tool.execution_complete","data":{"toolCallId":"t1","success":true},"id":"e4","timestamp":"2026-03-02T09:00:03.000Z","parentId":"e3"}"#,
+        "\n",
+        r#"{"type":"assistant.message","data":{"messageId":"m2","content":"world"},"id":"e5","timestamp":"2026-03-02T09:00:04.000Z","parentId":"e4"}"#,
+        "\n",
+    );
+
+    #[test]
+    fn resolves_both_layouts_and_extracts_messages() {
+        let temp = tempdir().expect("tempdir");
+        let sessions = temp.path().join("session-state");
+        fs::create_dir_all(sessions.join(SESSION_ID)).expect("mkdir");
+        fs::write(sessions.join(format!("{SESSION_ID}.jsonl")), EVENTS).expect("write flat");
+
+        let provider = CopilotProvider::new(temp.path());
+        let resolved = provider.resolve(SESSION_ID).expect("resolve flat");
+        assert_eq!(resolved.path, sessions.join(format!("{SESSION_ID}.jsonl")));
+        assert!(resolved.metadata.warnings.is_empty());
+
+        fs::write(sessions.join(SESSION_ID).join("events.jsonl"), EVENTS).expect("write dir");
+        let resolved = provider.resolve(SESSION_ID).expect("resolve dir");
+        assert_eq!(
+            resolved.path,
+            sessions.join(SESSION_ID).join("events.jsonl")
+        );
+        assert_eq!(resolved.metadata.warnings[0].code, "ambiguous_session");
+
+        let messages = extract_messages(
+            ProviderKind::Copilot,
+            &resolved.path,
+            EVENTS,
+            &mut ExtractionPolicy::default(),
+        )
+        .expect("extract");
+        assert_eq!(
+            messages
+                .iter()
+                .map(|message| message.text.as_str())
+                .collect::<Vec<_>>(),
+            ["hello", "world"]
+        );
+
+        let listings = provider.list_threads().expect("list");
+        assert_eq!(listings.len(), 1);
+        assert_eq!(listings[0].session_id, SESSION_ID);
+        assert_eq!(listings[0].path, resolved.path);
+    }
+
+    #[test]
+    fn returns_not_found_when_session_missing() {
+        let temp = tempdir().expect("tempdir");
+        let err = CopilotProvider::new(temp.path())
+            .resolve(SESSION_ID)
+            .expect_err("must fail");
+        assert!(format!("{err}").contains("thread not found"));
+    }
+}
@@ -0,0 +1,56 @@
+//! Transcript files outside every provider root, read through `file://` URIs.
+//!
+//! The "session id" of a file thread is its path. Which provider's parser
+//! reads it comes from the URI's `format`, see [`crate::uri::ThreadUri::format`].
+
+use std::path::PathBuf;
+
+use crate::error::{Result, XurlError};
+use crate::model::{ProviderKind, ResolutionMeta, ResolvedThread, SessionInfo, ThreadListing};
+use crate::provider::{MaintenanceProvider, Provider};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileProvider;
+
+impl FileProvider {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Provider for FileProvider {
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::File
+    }
+
+    fn resolve(&self, session_id: &str) -> Result<ResolvedThread> {
+        let path = PathBuf::from(session_id);
+        if !path.is_file() {
+            return Err(XurlError::ThreadNotFound {
+                provider: ProviderKind::File.to_string(),
+                session_id: session_id.to_string(),
+                searched_roots: vec![path],
+            });
+        }
+
+        Ok(ResolvedThread {
+            provider: ProviderKind::File,
+            session_id: session_id.to_string(),
+            path,
+            metadata: ResolutionMeta {
+                source: "file".to_string(),
+                candidate_count: 1,
+                warnings: Vec::new(),
+                merged_paths: Vec::new(),
+                session: SessionInfo::default(),
+            },
+        })
+    }
+
+    /// Files are only read when named; there is nothing to list.
+    fn list_threads(&self) -> Result<Vec<ThreadListing>> {
+        Ok(Vec::new())
+    }
+}
+
+impl MaintenanceProvider for FileProvider {}
@@ -1,29 +1,135 @@
 use std::cmp::Reverse;
+use std::fmt;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::SystemTime;
 
 use serde_json::Value;
 use walkdir::WalkDir;
 
 use crate::error::{Result, XurlError};
-use crate::model::{ProviderKind, ResolutionMeta, ResolvedThread};
-use crate::provider::Provider;
+use crate::model::{
+    ProviderKind, ResolutionMeta, ResolvedThread, SessionInfo, StorageSchema, ThreadListing,
+    Warning,
+};
+use crate::provider::{MaintenanceProvider, Provider, modified_or_epoch};
+
+/// Directory under `tmp/` that holds chats downloaded from a remote source.
+const SYNCED_PROJECT_DIR: &str = "xurl-sync";
+/// One `session-*.json` chat document per session under
+/// `tmp/<project>/chats/`.
+const LAYOUT_CHAT_JSON: &str = "chat_json";
+
+/// Somewhere Gemini chats that are missing locally can be downloaded from,
+/// such as the Google Cloud copies the Gemini CLI syncs.
+pub trait RemoteChatSource: fmt::Debug + Send + Sync {
+    /// Where chats come from, quoted in provenance warnings.
+    fn describe(&self) -> String;
+
+    /// Downloads the chat JSON of `session_id`; `Ok(None)` when the remote
+    /// has no such chat.
+    fn fetch_chat(&self, session_id: &str) -> Result<Option<Vec<u8>>>;
+}
 
 #[derive(Debug, Clone)]
 pub struct GeminiProvider {
     root: PathBuf,
+    remote: Option<Arc<dyn RemoteChatSource>>,
+    project: Option<String>,
 }
 
 impl GeminiProvider {
     pub fn new(root: impl Into<PathBuf>) -> Self {
-        Self { root: root.into() }
+        Self {
+            root: root.into(),
+            remote: None,
+            project: None,
+        }
+    }
+
+    /// Restricts resolution and listing to the chats of one project, the
+    /// `tmp/<project hash>` directory. Chats are then never downloaded.
+    #[must_use]
+    pub fn with_project(mut self, project: Option<impl Into<String>>) -> Self {
+        self.project = project.map(Into::into);
+        self
+    }
+
+    /// Downloads chats that are not found locally from `remote`, caching them
+    /// under `tmp/xurl-sync/chats`.
+    #[must_use]
+    pub fn with_remote(mut self, remote: Option<Arc<dyn RemoteChatSource>>) -> Self {
+        self.remote = remote;
+        self
     }
 
     fn tmp_root(&self) -> PathBuf {
         self.root.join("tmp")
     }
 
+    /// Where chats are searched: the project's directory, or all of `tmp/`.
+    fn search_root(&self) -> PathBuf {
+        match &self.project {
+            Some(project) => self.tmp_root().join(project),
+            None => self.tmp_root(),
+        }
+    }
+
+    fn synced_chats_dir(&self) -> PathBuf {
+        self.tmp_root().join(SYNCED_PROJECT_DIR).join("chats")
+    }
+
+    /// Fetches `session_id` from the remote source into the local cache.
+    fn fetch_remote(&self, session_id: &str) -> Result<Option<(PathBuf, String)>> {
+        let Some(remote) = self.remote.as_ref().filter(|_| self.project.is_none()) else {
+            return Ok(None);
+        };
+        // The id becomes a file name, so only full ids of the usual shape qualify.
+        if session_id.is_empty()
+            || !session_id
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '-')
+        {
+            return Ok(None);
+        }
+        let Some(body) = remote.fetch_chat(session_id)? else {
+            return Ok(None);
+        };
+
+        let source = remote.describe();
+        let value = serde_json::from_slice::<Value>(&body).map_err(|err| {
+            XurlError::Serialization(format!("invalid Gemini chat from {source}: {err}"))
+        })?;
+        let remote_id = value.get("sessionId").and_then(Value::as_str);
+        if !remote_id.is_some_and(|id| id.eq_ignore_ascii_case(session_id)) {
+            return Err(XurlError::Serialization(format!(
+                "Gemini chat from {source} has sessionId={} instead of {session_id}",
+                remote_id.unwrap_or("<missing>")
+            )));
+        }
+
+        let dir = self.synced_chats_dir();
+        fs::create_dir_all(&dir).map_err(|source| XurlError::Io {
+            path: dir.clone(),
+            source,
+        })?;
+        let path = dir.join(format!("session-{session_id}.json"));
+        // Write then rename so a failed download never leaves a partial chat
+        // that later reads would pick up.
+        let staging = dir.join(format!(".session-{session_id}.json.{}", std::process::id()));
+        fs::write(&staging, &body).map_err(|source| XurlError::Io {
+            path: staging.clone(),
+            source,
+        })?;
+        fs::rename(&staging, &path).map_err(|source| XurlError::Io {
+            path: path.clone(),
+            source,
+        })?;
+
+        Ok(Some((path, source)))
+    }
+
     fn is_session_file(path: &Path) -> bool {
         let is_session_file = path
             .file_name()
@@ -39,22 +145,20 @@ impl GeminiProvider {
         is_session_file && is_chats_entry
     }
 
-    fn has_session_id(path: &Path, session_id: &str) -> bool {
-        let Ok(raw) = fs::read_to_string(path) else {
-            return false;
-        };
-
-        let Ok(value) = serde_json::from_str::<Value>(&raw) else {
-            return false;
-        };
-
+    fn session_id_of(path: &Path) -> Option<String> {
+        let raw = fs::read_to_string(path).ok()?;
+        let value = serde_json::from_str::<Value>(&raw).ok()?;
         value
             .get("sessionId")
             .and_then(Value::as_str)
-            .is_some_and(|id| id.eq_ignore_ascii_case(session_id))
+            .map(ToString::to_string)
     }
 
-    fn find_candidates(tmp_root: &Path, session_id: &str) -> Vec<PathBuf> {
+    fn has_session_id(path: &Path, session_id: &str) -> bool {
+        Self::session_id_of(path).is_some_and(|id| id.eq_ignore_ascii_case(session_id))
+    }
+
+    fn session_files(tmp_root: &Path) -> Vec<PathBuf> {
         if !tmp_root.exists() {
             return Vec::new();
         }
@@ -65,6 +169,12 @@ impl GeminiProvider {
             .filter(|entry| entry.file_type().is_file())
             .map(|entry| entry.into_path())
             .filter(|path| Self::is_session_file(path))
+            .collect()
+    }
+
+    fn find_candidates(tmp_root: &Path, session_id: &str) -> Vec<PathBuf> {
+        Self::session_files(tmp_root)
+            .into_iter()
             .filter(|path| Self::has_session_id(path, session_id))
             .collect()
     }
@@ -96,20 +206,35 @@ impl Provider for GeminiProvider {
     }
 
     fn resolve(&self, session_id: &str) -> Result<ResolvedThread> {
-        let tmp_root = self.tmp_root();
-        let candidates = Self::find_candidates(&tmp_root, session_id);
+        let search_root = self.search_root();
+        let candidates = Self::find_candidates(&search_root, session_id);
+        tracing::debug!(root = %search_root.display(), ?candidates, "gemini chat candidates");
 
         if let Some((selected, count)) = Self::choose_latest(candidates) {
             let mut metadata = ResolutionMeta {
                 source: "gemini:chats".to_string(),
                 candidate_count: count,
                 warnings: Vec::new(),
+                merged_paths: Vec::new(),
+                session: SessionInfo::default(),
             };
 
             if count > 1 {
-                metadata.warnings.push(format!(
-                    "multiple matches found ({count}) for session_id={session_id}; selected latest: {}",
-                    selected.display()
+                metadata.warnings.push(Warning::warn(
+                    "ambiguous_session",
+                    format!(
+                        "multiple matches found ({count}) for session_id={session_id}; selected latest: {}; pick one with agents://gemini/<project hash>/{session_id}",
+                        selected.display()
+                    ),
+                ));
+            }
+            if selected.starts_with(self.synced_chats_dir()) {
+                metadata.warnings.push(Warning::info(
+                    "remote_sync",
+                    format!(
+                        "read from a cached remote copy, which may be stale: {}",
+                        selected.display()
+                    ),
                 ));
             }
 
@@ -121,24 +246,89 @@ impl Provider for GeminiProvider {
             });
         }
 
+        if let Some((path, source)) = self.fetch_remote(session_id)? {
+            return Ok(ResolvedThread {
+                provider: ProviderKind::Gemini,
+                session_id: session_id.to_string(),
+                metadata: ResolutionMeta {
+                    source: "gemini:remote".to_string(),
+                    candidate_count: 1,
+                    warnings: vec![Warning::warn(
+                        "remote_sync",
+                        format!(
+                            "not found locally; downloaded from {source} and cached at {}",
+                            path.display()
+                        ),
+                    )],
+                    merged_paths: Vec::new(),
+                    session: SessionInfo::default(),
+                },
+                path,
+            });
+        }
+
         Err(XurlError::ThreadNotFound {
             provider: ProviderKind::Gemini.to_string(),
             session_id: session_id.to_string(),
-            searched_roots: vec![tmp_root],
+            searched_roots: vec![search_root],
         })
     }
+
+    fn list_threads(&self) -> Result<Vec<ThreadListing>> {
+        Ok(Self::session_files(&self.search_root())
+            .into_iter()
+            .filter_map(|path| {
+                Some(ThreadListing {
+                    provider: ProviderKind::Gemini,
+                    session_id: Self::session_id_of(&path)?,
+                    updated_at: modified_or_epoch(&path),
+                    path,
+                })
+            })
+            .collect())
+    }
+
+    /// `chat_json`; chat files record no format version.
+    fn detect_schema(&self, thread: Option<&Path>) -> Option<StorageSchema> {
+        (thread.is_some() || !Self::session_files(&self.tmp_root()).is_empty())
+            .then(|| StorageSchema::new(LAYOUT_CHAT_JSON, None))
+    }
 }
 
+impl MaintenanceProvider for GeminiProvider {}
+
 #[cfg(test)]
 mod tests {
     use std::fs;
+    use std::sync::Arc;
     use std::thread;
     use std::time::Duration;
 
     use tempfile::tempdir;
 
+    use crate::error::Result;
+    use crate::model::WarningSeverity;
     use crate::provider::Provider;
-    use crate::provider::gemini::GeminiProvider;
+    use crate::provider::gemini::{GeminiProvider, RemoteChatSource};
+
+    #[derive(Debug)]
+    struct FakeRemote(Option<String>);
+
+    impl RemoteChatSource for FakeRemote {
+        fn describe(&self) -> String {
+            "fake remote".to_string()
+        }
+
+        fn fetch_chat(&self, _session_id: &str) -> Result<Option<Vec<u8>>> {
+            Ok(self.0.clone().map(String::into_bytes))
+        }
+    }
+
+    fn remote_chat(session_id: &str) -> Option<String> {
+        Some(format!(
+            r#"{{"sessionId":"{session_id}","messages":[{{"type":"user","content":"synced"}}]}}"#
+        ))
+    }
 
     fn write_session(
         root: &Path,
@@ -221,11 +411,51 @@ mod tests {
         assert_eq!(resolved.path, second);
         assert_eq!(resolved.metadata.candidate_count, 2);
         assert_eq!(resolved.metadata.warnings.len(), 1);
-        assert!(resolved.metadata.warnings[0].contains("multiple matches"));
+        assert!(
+            resolved.metadata.warnings[0]
+                .message
+                .contains("multiple matches")
+        );
 
         assert!(first.exists());
     }
 
+    #[test]
+    fn project_hash_limits_lookup_to_that_project() {
+        let temp = tempdir().expect("tempdir");
+        let session_id = "29d207db-ca7e-40ba-87f7-e14c9de60613";
+
+        let first = write_session(
+            temp.path(),
+            "hash-a",
+            "session-2026-01-08T11-55-29-29d207db.json",
+            session_id,
+            "first",
+        );
+        write_session(
+            temp.path(),
+            "hash-b",
+            "session-2026-01-08T12-00-00-29d207db.json",
+            session_id,
+            "second",
+        );
+
+        let provider = GeminiProvider::new(temp.path()).with_project(Some("hash-a"));
+        let resolved = provider
+            .resolve(session_id)
+            .expect("project resolve should succeed");
+        assert_eq!(resolved.path, first);
+        assert_eq!(resolved.metadata.candidate_count, 1);
+        assert!(resolved.metadata.warnings.is_empty());
+        assert_eq!(provider.list_threads().expect("list").len(), 1);
+        assert!(
+            GeminiProvider::new(temp.path())
+                .with_project(Some("hash-c"))
+                .resolve(session_id)
+                .is_err()
+        );
+    }
+
     #[test]
     fn missing_thread_returns_not_found() {
         let temp = tempdir().expect("tempdir");
@@ -235,4 +465,52 @@ mod tests {
             .expect_err("must fail");
         assert!(format!("{err}").contains("thread not found"));
     }
+
+    #[test]
+    fn missing_chat_is_fetched_from_remote_and_cached() {
+        let temp = tempdir().expect("tempdir");
+        let session_id = "29d207db-ca7e-40ba-87f7-e14c9de60613";
+
+        let provider = GeminiProvider::new(temp.path())
+            .with_remote(Some(Arc::new(FakeRemote(remote_chat(session_id)))));
+        let fetched = provider.resolve(session_id).expect("fetch");
+        assert_eq!(
+            fetched.path,
+            temp.path()
+                .join("tmp/xurl-sync/chats")
+                .join(format!("session-{session_id}.json"))
+        );
+        assert_eq!(fetched.metadata.source, "gemini:remote");
+        assert_eq!(fetched.metadata.warnings[0].code, "remote_sync");
+        assert_eq!(fetched.metadata.warnings[0].severity, WarningSeverity::Warn);
+        assert!(
+            fetched.metadata.warnings[0]
+                .message
+                .contains("downloaded from fake remote")
+        );
+
+        // Later reads use the cached copy without asking the remote again.
+        let cached = GeminiProvider::new(temp.path())
+            .with_remote(Some(Arc::new(FakeRemote(None))))
+            .resolve(session_id)
+            .expect("cached");
+        assert_eq!(cached.path, fetched.path);
+        assert_eq!(cached.metadata.source, "gemini:chats");
+        assert_eq!(cached.metadata.warnings[0].code, "remote_sync");
+        assert_eq!(cached.metadata.warnings[0].severity, WarningSeverity::Info);
+    }
+
+    #[test]
+    fn remote_chat_for_another_session_is_rejected() {
+        let temp = tempdir().expect("tempdir");
+        let provider = GeminiProvider::new(temp.path()).with_remote(Some(Arc::new(FakeRemote(
+            remote_chat("00000000-0000-0000-0000-000000000000"),
+        ))));
+
+        let err = provider
+            .resolve("29d207db-ca7e-40ba-87f7-e14c9de60613")
+            .expect_err("must fail");
+        assert!(format!("{err}").contains("instead of"), "{err}");
+        assert!(!temp.path().join("tmp/xurl-sync").exists());
+    }
 }
@@ -0,0 +1,121 @@
+//! Downloads Gemini chats that the Gemini CLI synced to Google Cloud.
+//!
+//! Requests are authorized with the user's own `gcloud` login
+//! (`gcloud auth print-access-token`); xurl never stores the token.
+
+use std::io::Read;
+use std::path::Path;
+
+use crate::binary::{describe_search, find_program, spawn_piped};
+use crate::error::{Result, XurlError};
+use crate::provider::gemini::RemoteChatSource;
+
+/// Placeholder in the endpoint template replaced by the session id.
+const SESSION_PLACEHOLDER: &str = "{session_id}";
+
+#[derive(Debug, Clone)]
+pub struct GcloudChatSource {
+    url_template: String,
+}
+
+impl GcloudChatSource {
+    /// `url_template` is the chat endpoint, with `{session_id}` where the id
+    /// goes; without the placeholder the id is appended as a path segment.
+    pub fn new(url_template: impl Into<String>) -> Self {
+        Self {
+            url_template: url_template.into(),
+        }
+    }
+
+    fn url(&self, session_id: &str) -> String {
+        if self.url_template.contains(SESSION_PLACEHOLDER) {
+            self.url_template.replace(SESSION_PLACEHOLDER, session_id)
+        } else {
+            format!("{}/{session_id}", self.url_template.trim_end_matches('/'))
+        }
+    }
+
+    fn access_token() -> Result<String> {
+        let program = Path::new("gcloud");
+        let gcloud = find_program(program).ok_or_else(|| XurlError::CommandNotFound {
+            command: "gcloud".to_string(),
+            lookup: describe_search(program),
+        })?;
+        let output = spawn_piped(&gcloud, &["auth", "print-access-token"])?
+            .wait_with_output()
+            .map_err(|source| XurlError::Io {
+                path: gcloud.clone(),
+                source,
+            })?;
+        if !output.status.success() {
+            return Err(XurlError::CommandFailed {
+                command: "gcloud auth print-access-token".to_string(),
+                code: output.status.code(),
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+
+        let token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if token.is_empty() {
+            return Err(XurlError::CommandFailed {
+                command: "gcloud auth print-access-token".to_string(),
+                code: output.status.code(),
+                stderr: "no access token printed; run `gcloud auth login`".to_string(),
+            });
+        }
+        Ok(token)
+    }
+}
+
+impl RemoteChatSource for GcloudChatSource {
+    fn describe(&self) -> String {
+        format!("Google Cloud ({})", self.url_template)
+    }
+
+    fn fetch_chat(&self, session_id: &str) -> Result<Option<Vec<u8>>> {
+        let token = Self::access_token()?;
+        let url = self.url(session_id);
+        let response = match ureq::get(&url)
+            .set("Authorization", &format!("Bearer {token}"))
+            .set("Accept", "application/json")
+            .call()
+        {
+            Ok(response) => response,
+            Err(ureq::Error::Status(404, _)) => return Ok(None),
+            Err(err) => {
+                return Err(XurlError::RemoteFetch {
+                    url,
+                    message: err.to_string(),
+                });
+            }
+        };
+
+        let mut body = Vec::new();
+        response
+            .into_reader()
+            .read_to_end(&mut body)
+            .map_err(|err| XurlError::RemoteFetch {
+                url,
+                message: err.to_string(),
+            })?;
+        Ok(Some(body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::provider::gemini_sync::GcloudChatSource;
+
+    #[test]
+    fn session_id_fills_the_url_template() {
+        assert_eq!(
+            GcloudChatSource::new("https://example.test/v1/chats/{session_id}:download")
+                .url("29d207db"),
+            "https://example.test/v1/chats/29d207db:download"
+        );
+        assert_eq!(
+            GcloudChatSource::new("https://example.test/v1/chats/").url("29d207db"),
+            "https://example.test/v1/chats/29d207db"
+        );
+    }
+}
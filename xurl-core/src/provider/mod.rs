@@ -1,32 +1,198 @@
+use std::collections::{HashMap, HashSet};
 use std::env;
-use std::path::PathBuf;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Child;
+#[cfg(feature = "write")]
+use std::process::ExitStatus;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 
 use dirs::home_dir;
+use rusqlite::trace::{TraceEvent, TraceEventCodes};
+use rusqlite::{Connection, OpenFlags};
 
+#[cfg(feature = "write")]
+use crate::binary::BinaryLocator;
 use crate::error::{Result, XurlError};
-use crate::model::{ProviderKind, ResolvedThread, WriteRequest, WriteResult};
+use crate::model::{
+    GcReport, GcRequest, ProviderKind, ResolvedThread, StorageSchema, ThreadListing, WriteRequest,
+    WriteResult,
+};
 
 pub mod amp;
 pub mod claude;
 pub mod codex;
+pub mod copilot;
+pub mod file;
 pub mod gemini;
+#[cfg(feature = "gemini-sync")]
+pub mod gemini_sync;
+pub mod multi_root;
 pub mod opencode;
+pub mod opencode_share;
 pub mod pi;
 
+/// Receives write-mode progress. See [`crate::sink`] for ready-made sinks.
 pub trait WriteEventSink {
     fn on_session_ready(&mut self, provider: ProviderKind, session_id: &str) -> Result<()>;
     fn on_text_delta(&mut self, text: &str) -> Result<()>;
+    /// Called by [`crate::service::write_thread`] once the write succeeded.
+    /// `result.final_text` holds the reply of providers that stream none.
+    fn on_write_finished(&mut self, result: &WriteResult) -> Result<()> {
+        let _ = result;
+        Ok(())
+    }
+    /// Called once the agent CLI of a write is running, with a handle that
+    /// can kill it from another thread.
+    fn on_agent_spawned(&mut self, agent: &AgentProcess) {
+        let _ = agent;
+    }
+}
+
+/// The agent CLI a write runs. Clones share the process, so a sink can keep
+/// one to kill the agent while the write is still reading its output;
+/// killing it once the write is done reading does nothing.
+#[derive(Debug, Clone)]
+pub struct AgentProcess {
+    child: Arc<Mutex<Option<Child>>>,
+}
+
+impl AgentProcess {
+    #[cfg(feature = "write")]
+    pub(crate) fn new(child: Child) -> Self {
+        Self {
+            child: Arc::new(Mutex::new(Some(child))),
+        }
+    }
+
+    pub fn kill(&self) {
+        if let Ok(mut child) = self.child.lock()
+            && let Some(child) = child.as_mut()
+        {
+            tracing::debug!(pid = child.id(), "killing agent");
+            let _ = child.kill();
+        }
+    }
+
+    /// Waits for the agent to exit once its output was `read`. A failed
+    /// read, such as a sink error, kills the agent first and is returned.
+    #[cfg(feature = "write")]
+    pub(crate) fn finish(self, read: Result<()>, bin: &Path) -> Result<ExitStatus> {
+        let child = self.child.lock().ok().and_then(|mut child| child.take());
+        let Some(mut child) = child else {
+            return Err(XurlError::WriteProtocol(format!(
+                "{}: agent process is unavailable",
+                bin.display()
+            )));
+        };
+        if let Err(err) = read {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(err);
+        }
+        child.wait().map_err(|source| XurlError::Io {
+            path: bin.to_path_buf(),
+            source,
+        })
+    }
 }
 
 pub trait Provider {
     fn kind(&self) -> ProviderKind;
     fn resolve(&self, session_id: &str) -> Result<ResolvedThread>;
+    /// Lists every main session in local storage, in no particular order.
+    fn list_threads(&self) -> Result<Vec<ThreadListing>>;
+    /// The storage format of the thread file at `thread`, or of the
+    /// provider's storage as a whole without one; `None` when there is
+    /// nothing to inspect yet.
+    fn detect_schema(&self, thread: Option<&Path>) -> Option<StorageSchema> {
+        let _ = thread;
+        None
+    }
     fn write(&self, req: &WriteRequest, sink: &mut dyn WriteEventSink) -> Result<WriteResult> {
         let _ = (req, sink);
         Err(XurlError::UnsupportedProviderWrite(self.kind().to_string()))
     }
 }
 
+/// Storage housekeeping for providers whose local session files can be
+/// archived or removed safely.
+pub trait MaintenanceProvider: Provider {
+    fn collect_garbage(&self, req: &GcRequest) -> Result<GcReport> {
+        let _ = req;
+        Err(XurlError::UnsupportedProviderMaintenance(
+            self.kind().to_string(),
+        ))
+    }
+}
+
+/// Drops candidates that point at the same file through different symlinked
+/// paths, keeping the first discovered path for each canonical target.
+pub(crate) fn dedupe_canonical_paths(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    paths
+        .into_iter()
+        .filter(|path| seen.insert(canonical_or_self(path)))
+        .collect()
+}
+
+pub(crate) fn modified_or_epoch(path: &Path) -> SystemTime {
+    fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+/// Opens a provider's sqlite database. With debug logging on, every statement
+/// it runs is logged with its bound values and duration.
+pub(crate) fn open_sqlite(path: &Path, flags: OpenFlags) -> rusqlite::Result<Connection> {
+    let conn = Connection::open_with_flags(path, flags)?;
+    if tracing::enabled!(tracing::Level::DEBUG) {
+        conn.trace_v2(
+            TraceEventCodes::SQLITE_TRACE_PROFILE,
+            Some(log_sqlite_query),
+        );
+    }
+    Ok(conn)
+}
+
+fn log_sqlite_query(event: TraceEvent<'_>) {
+    if let TraceEvent::Profile(stmt, elapsed) = event {
+        let sql = stmt
+            .expanded_sql()
+            .unwrap_or_else(|| stmt.sql().into_owned());
+        tracing::debug!(sql, ?elapsed, "sqlite query");
+    }
+}
+
+fn canonical_or_self(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Which platform's conventions xurl's own cache and data directories follow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageLayout {
+    /// XDG base directories, used on Linux and macOS.
+    Unix,
+    /// Known folders such as `%LOCALAPPDATA%`; XDG variables are ignored.
+    Windows,
+}
+
+impl StorageLayout {
+    pub fn current() -> Self {
+        if cfg!(windows) {
+            Self::Windows
+        } else {
+            Self::Unix
+        }
+    }
+}
+
+/// Directory under the system temp directory that stands in for the home
+/// directory of sandboxed roots.
+const SANDBOX_HOME: &str = "xurl-sandbox";
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ProviderRoots {
     pub amp_root: PathBuf,
@@ -35,67 +201,552 @@ pub struct ProviderRoots {
     pub gemini_root: PathBuf,
     pub pi_root: PathBuf,
     pub opencode_root: PathBuf,
+    pub copilot_root: PathBuf,
+    /// Further storage roots searched after a provider's own root, in order
+    /// (`XURL_<PROVIDER>_ROOTS`).
+    pub extra_roots: Vec<(ProviderKind, PathBuf)>,
+    /// Optional Claude project (directory name or working-directory path)
+    /// that narrows Claude session resolution.
+    pub claude_project: Option<String>,
+    /// Only list sessions whose recorded working directory is this directory
+    /// or inside it.
+    pub project_dir: Option<PathBuf>,
+    /// Read every Codex rollout of a session as one timeline instead of the latest.
+    pub codex_merge_rollouts: bool,
+    /// Finds the agent CLIs that write mode runs.
+    #[cfg(feature = "write")]
+    pub binaries: BinaryLocator,
+    /// File the agent CLIs' stderr is appended to during write mode.
+    #[cfg(feature = "write")]
+    pub write_debug_log: Option<PathBuf>,
+    /// Google Cloud endpoint that Gemini chats missing locally are downloaded
+    /// from. Only used when built with the `gemini-sync` feature.
+    pub gemini_sync_url: Option<String>,
+    /// Whether reads may download threads, such as opencode share links.
+    /// Defaults to `XURL_ALLOW_NETWORK` being set to anything but `0`.
+    pub allow_network: bool,
+    /// Endpoint opencode shares are downloaded from, with `{share_id}` where
+    /// the id goes; `None` uses opencode.ai. Only used when built with the
+    /// `opencode-share` feature.
+    pub opencode_share_url: Option<String>,
+    /// Where xurl keeps derived data such as rollout line indexes.
+    pub cache_root: PathBuf,
+    /// Where xurl keeps its own data, such as thread annotations.
+    pub data_root: PathBuf,
+    /// Where xurl reads its settings from (`config.toml`).
+    pub config_root: PathBuf,
 }
 
 impl ProviderRoots {
     pub fn from_env_or_home() -> Result<Self> {
         let home = home_dir().ok_or(XurlError::HomeDirectoryNotFound)?;
+        Ok(Self::from_vars(&home, StorageLayout::current(), |name| {
+            env::var_os(name)
+        }))
+    }
+
+    /// Roots that only reach the directories in `roots`: nothing is read
+    /// from the environment or the home directory. Providers left out have
+    /// no root, so they find no threads and cannot be written to. xurl's own
+    /// cache, data, and config directories go under `xurl-sandbox` in the
+    /// system temp directory; set `cache_root`, `data_root`, and
+    /// `config_root` to move them.
+    pub fn from_map(roots: HashMap<ProviderKind, PathBuf>) -> Self {
+        Self::from_vars(
+            &env::temp_dir().join(SANDBOX_HOME),
+            StorageLayout::current(),
+            |_| None,
+        )
+        .with_only_roots(roots)
+    }
+
+    /// Like [`Self::from_env_or_home`], except that providers only get the
+    /// roots their own variables set (`CODEX_HOME`, `CLAUDE_CONFIG_DIR`,
+    /// ...; see [`Self::from_vars`]) and their `XURL_<PROVIDER>_ROOTS`, never
+    /// one in the home directory. xurl's own directories fall back to
+    /// `xurl-sandbox` in the system temp directory instead of the home.
+    pub fn from_env_without_home() -> Self {
+        let var = |name: &str| env::var_os(name);
+        let roots = Self::explicit_roots(&var);
+        Self::from_vars(
+            &env::temp_dir().join(SANDBOX_HOME),
+            StorageLayout::current(),
+            var,
+        )
+        .with_only_roots(roots)
+    }
+
+    /// Roots for unit tests: every provider's root is `base/<provider>` and
+    /// xurl's own directories are `base/cache`, `base/data`, and
+    /// `base/config`. Nothing is read from the environment.
+    #[cfg(test)]
+    pub(crate) fn for_test(base: &Path) -> Self {
+        let roots = ProviderKind::all()
+            .iter()
+            .map(|kind| (*kind, base.join(kind.to_string())))
+            .collect();
+        let mut roots =
+            Self::from_vars(base, StorageLayout::current(), |_| None).with_only_roots(roots);
+        roots.cache_root = base.join("cache");
+        roots.data_root = base.join("data");
+        roots.config_root = base.join("config");
+        roots
+    }
+
+    /// Resolves every root from `home` and the environment variables `var`
+    /// looks up, following `layout` for xurl's own directories.
+    pub fn from_vars(
+        home: &Path,
+        layout: StorageLayout,
+        var: impl Fn(&str) -> Option<OsString>,
+    ) -> Self {
+        let non_empty = |name: &str| var(name).filter(|value| !value.is_empty());
+        let mut explicit = Self::explicit_roots(&var);
+        let mut root = |provider, default: &dyn Fn() -> PathBuf| {
+            explicit.remove(&provider).unwrap_or_else(default)
+        };
 
+        // Amp and OpenCode follow XDG on every platform, Windows included.
         // Precedence:
         // 1) XDG_DATA_HOME/amp
         // 2) ~/.local/share/amp
-        let amp_root = env::var_os("XDG_DATA_HOME")
-            .filter(|path| !path.is_empty())
-            .map(PathBuf::from)
-            .map(|path| path.join("amp"))
-            .unwrap_or_else(|| home.join(".local/share/amp"));
+        let amp_root = root(ProviderKind::Amp, &|| {
+            home.join(".local").join("share").join("amp")
+        });
 
         // Precedence:
         // 1) CODEX_HOME (official Codex home env)
-        // 2) ~/.codex (Codex default)
-        let codex_root = env::var_os("CODEX_HOME")
-            .map(PathBuf::from)
-            .unwrap_or_else(|| home.join(".codex"));
+        // 2) ~/.codex (Codex default, %USERPROFILE%\.codex on Windows)
+        let codex_root = root(ProviderKind::Codex, &|| home.join(".codex"));
 
         // Precedence:
         // 1) CLAUDE_CONFIG_DIR (official Claude Code config/data root env)
-        // 2) ~/.claude (Claude default)
-        let claude_root = env::var_os("CLAUDE_CONFIG_DIR")
-            .map(PathBuf::from)
-            .unwrap_or_else(|| home.join(".claude"));
+        // 2) ~/.claude (Claude default, %USERPROFILE%\.claude on Windows)
+        let claude_root = root(ProviderKind::Claude, &|| home.join(".claude"));
 
         // Precedence:
         // 1) GEMINI_CLI_HOME/.gemini (official Gemini CLI home env)
-        // 2) ~/.gemini (Gemini default)
-        let gemini_root = env::var_os("GEMINI_CLI_HOME")
-            .map(PathBuf::from)
-            .map(|path| path.join(".gemini"))
-            .unwrap_or_else(|| home.join(".gemini"));
+        // 2) ~/.gemini (Gemini default, %USERPROFILE%\.gemini on Windows)
+        let gemini_root = root(ProviderKind::Gemini, &|| home.join(".gemini"));
 
         // Precedence:
         // 1) PI_CODING_AGENT_DIR (official pi coding agent root env)
         // 2) ~/.pi/agent (pi default)
-        let pi_root = env::var_os("PI_CODING_AGENT_DIR")
-            .filter(|path| !path.is_empty())
-            .map(PathBuf::from)
-            .unwrap_or_else(|| home.join(".pi/agent"));
+        let pi_root = root(ProviderKind::Pi, &|| home.join(".pi").join("agent"));
 
         // Precedence:
         // 1) XDG_DATA_HOME/opencode
         // 2) ~/.local/share/opencode
-        let opencode_root = env::var_os("XDG_DATA_HOME")
-            .filter(|path| !path.is_empty())
+        let opencode_root = root(ProviderKind::Opencode, &|| {
+            home.join(".local").join("share").join("opencode")
+        });
+
+        // Precedence:
+        // 1) XDG_STATE_HOME/copilot
+        // 2) ~/.copilot (Copilot CLI default, %USERPROFILE%\.copilot on Windows)
+        let copilot_root = root(ProviderKind::Copilot, &|| home.join(".copilot"));
+
+        // Precedence:
+        // 1) XURL_CACHE_DIR
+        // 2) XDG_CACHE_HOME/xurl, or %LOCALAPPDATA%\xurl\cache on Windows
+        // 3) ~/.cache/xurl, or ~\AppData\Local\xurl\cache on Windows
+        let cache_root = non_empty("XURL_CACHE_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| match layout {
+                StorageLayout::Unix => non_empty("XDG_CACHE_HOME")
+                    .map_or_else(|| home.join(".cache"), PathBuf::from)
+                    .join("xurl"),
+                StorageLayout::Windows => non_empty("LOCALAPPDATA")
+                    .map_or_else(|| home.join("AppData").join("Local"), PathBuf::from)
+                    .join("xurl")
+                    .join("cache"),
+            });
+
+        // Precedence:
+        // 1) XURL_DATA_DIR
+        // 2) XDG_DATA_HOME/xurl, or %APPDATA%\xurl on Windows
+        // 3) ~/.local/share/xurl, or ~\AppData\Roaming\xurl on Windows
+        let data_root = non_empty("XURL_DATA_DIR")
             .map(PathBuf::from)
-            .map(|path| path.join("opencode"))
-            .unwrap_or_else(|| home.join(".local/share/opencode"));
+            .unwrap_or_else(|| match layout {
+                StorageLayout::Unix => non_empty("XDG_DATA_HOME")
+                    .map_or_else(|| home.join(".local").join("share"), PathBuf::from)
+                    .join("xurl"),
+                StorageLayout::Windows => non_empty("APPDATA")
+                    .map_or_else(|| home.join("AppData").join("Roaming"), PathBuf::from)
+                    .join("xurl"),
+            });
 
-        Ok(Self {
+        // Precedence:
+        // 1) XURL_CONFIG_DIR
+        // 2) XDG_CONFIG_HOME/xurl, or %APPDATA%\xurl on Windows
+        // 3) ~/.config/xurl, or ~\AppData\Roaming\xurl on Windows
+        let config_root = non_empty("XURL_CONFIG_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| match layout {
+                StorageLayout::Unix => non_empty("XDG_CONFIG_HOME")
+                    .map_or_else(|| home.join(".config"), PathBuf::from)
+                    .join("xurl"),
+                StorageLayout::Windows => non_empty("APPDATA")
+                    .map_or_else(|| home.join("AppData").join("Roaming"), PathBuf::from)
+                    .join("xurl"),
+            });
+
+        // Extra roots, e.g. Codex homes synced from other machines:
+        // XURL_CODEX_ROOTS=~/backups/codex-a:~/backups/codex-b (`;` on Windows).
+        let extra_roots = ProviderKind::all()
+            .iter()
+            .copied()
+            .flat_map(|provider| {
+                non_empty(&Self::extra_roots_var(provider))
+                    .map(|list| {
+                        env::split_paths(&list)
+                            .filter(|root| !root.as_os_str().is_empty())
+                            .map(|root| (provider, root))
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default()
+            })
+            .collect();
+
+        let allow_network = var("XURL_ALLOW_NETWORK")
+            .is_some_and(|value| !matches!(value.to_str().map(str::trim), Some("" | "0")));
+        let opencode_share_url = var("XURL_OPENCODE_SHARE_URL")
+            .and_then(|url| url.into_string().ok())
+            .filter(|url| !url.trim().is_empty());
+
+        let gemini_sync_url = var("XURL_GEMINI_SYNC_URL")
+            .and_then(|url| url.into_string().ok())
+            .filter(|url| !url.trim().is_empty());
+
+        Self {
             amp_root,
             codex_root,
             claude_root,
             gemini_root,
             pi_root,
             opencode_root,
+            copilot_root,
+            extra_roots,
+            claude_project: None,
+            codex_merge_rollouts: false,
+            project_dir: None,
+            #[cfg(feature = "write")]
+            binaries: BinaryLocator::new(),
+            #[cfg(feature = "write")]
+            write_debug_log: None,
+            gemini_sync_url,
+            allow_network,
+            opencode_share_url,
+            cache_root,
+            data_root,
+            config_root,
+        }
+    }
+
+    /// The provider roots `var` sets explicitly, the first source in each
+    /// provider's precedence in [`Self::from_vars`].
+    fn explicit_roots(var: &impl Fn(&str) -> Option<OsString>) -> HashMap<ProviderKind, PathBuf> {
+        let non_empty = |name: &str| var(name).filter(|value| !value.is_empty());
+        let data_home = non_empty("XDG_DATA_HOME").map(PathBuf::from);
+        [
+            (
+                ProviderKind::Amp,
+                data_home.as_ref().map(|path| path.join("amp")),
+            ),
+            (ProviderKind::Codex, var("CODEX_HOME").map(PathBuf::from)),
+            (
+                ProviderKind::Claude,
+                var("CLAUDE_CONFIG_DIR").map(PathBuf::from),
+            ),
+            (
+                ProviderKind::Gemini,
+                var("GEMINI_CLI_HOME").map(|path| PathBuf::from(path).join(".gemini")),
+            ),
+            (
+                ProviderKind::Pi,
+                non_empty("PI_CODING_AGENT_DIR").map(PathBuf::from),
+            ),
+            (
+                ProviderKind::Opencode,
+                data_home.map(|path| path.join("opencode")),
+            ),
+            (
+                ProviderKind::Copilot,
+                non_empty("XDG_STATE_HOME").map(|path| PathBuf::from(path).join("copilot")),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(provider, root)| Some((provider, root?)))
+        .collect()
+    }
+
+    /// Replaces every provider's own root with its entry in `roots`, or no
+    /// root at all.
+    fn with_only_roots(mut self, mut roots: HashMap<ProviderKind, PathBuf>) -> Self {
+        let mut root = |provider| roots.remove(&provider).unwrap_or_default();
+        self.amp_root = root(ProviderKind::Amp);
+        self.codex_root = root(ProviderKind::Codex);
+        self.claude_root = root(ProviderKind::Claude);
+        self.gemini_root = root(ProviderKind::Gemini);
+        self.pi_root = root(ProviderKind::Pi);
+        self.opencode_root = root(ProviderKind::Opencode);
+        self.copilot_root = root(ProviderKind::Copilot);
+        self
+    }
+
+    /// Fails when `provider` has no root of its own to write to, as with
+    /// [`Self::from_map`] roots that leave it out.
+    pub fn ensure_own_root(&self, provider: ProviderKind) -> Result<()> {
+        if provider == ProviderKind::File || self.own_root(provider).is_some() {
+            return Ok(());
+        }
+        Err(XurlError::NoStorageRoot {
+            provider: provider.to_string(),
+        })
+    }
+
+    /// Environment variable listing `provider`'s extra roots, e.g.
+    /// `XURL_CODEX_ROOTS`.
+    pub fn extra_roots_var(provider: ProviderKind) -> String {
+        format!("XURL_{}_ROOTS", provider.to_string().to_uppercase())
+    }
+
+    /// Every storage root of `provider`, its own root first, if it has one.
+    /// File threads live outside every root, so they have none.
+    pub fn roots_for(&self, provider: ProviderKind) -> Vec<PathBuf> {
+        if provider == ProviderKind::File {
+            return Vec::new();
+        }
+        self.own_root(provider)
+            .cloned()
+            .into_iter()
+            .chain(
+                self.extra_roots
+                    .iter()
+                    .filter(|(kind, _)| *kind == provider)
+                    .map(|(_, root)| root.clone()),
+            )
+            .collect()
+    }
+
+    /// The root `provider` writes to; `None` for File threads and providers
+    /// left without one.
+    fn own_root(&self, provider: ProviderKind) -> Option<&PathBuf> {
+        let own = match provider {
+            ProviderKind::Amp => &self.amp_root,
+            ProviderKind::Codex => &self.codex_root,
+            ProviderKind::Claude => &self.claude_root,
+            ProviderKind::Gemini => &self.gemini_root,
+            ProviderKind::Pi => &self.pi_root,
+            ProviderKind::Opencode => &self.opencode_root,
+            ProviderKind::Copilot => &self.copilot_root,
+            ProviderKind::File => return None,
+        };
+        (!own.as_os_str().is_empty()).then_some(own)
+    }
+
+    /// Searches `root` for `provider`'s sessions after the roots already set.
+    #[must_use]
+    pub fn with_extra_root(mut self, provider: ProviderKind, root: impl Into<PathBuf>) -> Self {
+        self.extra_roots.push((provider, root.into()));
+        self
+    }
+
+    #[must_use]
+    pub fn with_claude_project(mut self, project: Option<String>) -> Self {
+        self.claude_project = project;
+        self
+    }
+
+    #[must_use]
+    pub fn with_project_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.project_dir = dir;
+        self
+    }
+
+    #[must_use]
+    pub fn with_codex_merge_rollouts(mut self, merge_rollouts: bool) -> Self {
+        self.codex_merge_rollouts = merge_rollouts;
+        self
+    }
+
+    #[must_use]
+    pub fn with_allow_network(mut self, allow: bool) -> Self {
+        self.allow_network = allow;
+        self
+    }
+
+    #[cfg(feature = "write")]
+    #[must_use]
+    pub fn with_write_debug_log(mut self, path: Option<PathBuf>) -> Self {
+        self.write_debug_log = path;
+        self
+    }
+
+    /// Runs `bin` for `provider`'s write mode instead of the binary found
+    /// through `XURL_<PROVIDER>_BIN` or `PATH`.
+    #[cfg(feature = "write")]
+    #[must_use]
+    pub fn with_binary(mut self, provider: ProviderKind, bin: impl Into<PathBuf>) -> Self {
+        self.binaries = self.binaries.with_override(provider, bin);
+        self
+    }
+}
+
+/// Writes a Codex rollout for `session_id` under `codex_root` with the
+/// given content and returns its path. Shared fixture for unit tests.
+#[cfg(test)]
+pub(crate) fn write_test_rollout(codex_root: &Path, session_id: &str, content: &str) -> PathBuf {
+    let dir = codex_root.join("sessions/2026/02/23");
+    fs::create_dir_all(&dir).expect("mkdir");
+    let path = dir.join(format!("rollout-2026-02-23T04-48-50-{session_id}.jsonl"));
+    fs::write(&path, content).expect("write rollout");
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::ffi::OsString;
+    use std::path::{Path, PathBuf};
+
+    use crate::error::XurlError;
+    use crate::model::ProviderKind;
+    use crate::provider::{ProviderRoots, StorageLayout};
+
+    fn roots(layout: StorageLayout, vars: &[(&str, &str)]) -> ProviderRoots {
+        ProviderRoots::from_vars(Path::new("home"), layout, |name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| OsString::from(value))
         })
     }
+
+    fn path(parts: &[&str]) -> PathBuf {
+        parts.iter().collect()
+    }
+
+    #[test]
+    fn unix_layout_follows_xdg() {
+        let defaults = roots(StorageLayout::Unix, &[]);
+        assert_eq!(defaults.codex_root, path(&["home", ".codex"]));
+        assert_eq!(defaults.pi_root, path(&["home", ".pi", "agent"]));
+        assert_eq!(defaults.amp_root, path(&["home", ".local", "share", "amp"]));
+        assert_eq!(defaults.cache_root, path(&["home", ".cache", "xurl"]));
+        assert_eq!(defaults.config_root, path(&["home", ".config", "xurl"]));
+        assert_eq!(
+            defaults.data_root,
+            path(&["home", ".local", "share", "xurl"])
+        );
+
+        let xdg = roots(
+            StorageLayout::Unix,
+            &[("XDG_DATA_HOME", "data"), ("XDG_CACHE_HOME", "")],
+        );
+        assert_eq!(xdg.opencode_root, path(&["data", "opencode"]));
+        assert_eq!(xdg.data_root, path(&["data", "xurl"]));
+        assert_eq!(xdg.cache_root, path(&["home", ".cache", "xurl"]));
+    }
+
+    #[test]
+    fn extra_roots_follow_the_provider_root() {
+        let list =
+            std::env::join_paths([path(&["backups", "codex-a"]), path(&["backups", "codex-b"])])
+                .expect("join");
+        let roots = roots(
+            StorageLayout::Unix,
+            &[
+                ("CODEX_HOME", "codex"),
+                ("XURL_CODEX_ROOTS", list.to_str().expect("utf8")),
+                ("XURL_PI_ROOTS", ""),
+            ],
+        );
+        assert_eq!(
+            roots.roots_for(ProviderKind::Codex),
+            vec![
+                path(&["codex"]),
+                path(&["backups", "codex-a"]),
+                path(&["backups", "codex-b"])
+            ]
+        );
+        assert_eq!(
+            roots.roots_for(ProviderKind::Pi),
+            vec![path(&["home", ".pi", "agent"])]
+        );
+        assert!(roots.roots_for(ProviderKind::File).is_empty());
+    }
+
+    #[test]
+    fn mapped_roots_leave_other_providers_without_one() {
+        let roots = ProviderRoots::from_map(HashMap::from([(
+            ProviderKind::Codex,
+            path(&["sandbox", "codex"]),
+        )]));
+        assert_eq!(
+            roots.roots_for(ProviderKind::Codex),
+            vec![path(&["sandbox", "codex"])]
+        );
+        assert!(roots.roots_for(ProviderKind::Claude).is_empty());
+        assert!(roots.ensure_own_root(ProviderKind::Codex).is_ok());
+        assert!(matches!(
+            roots.ensure_own_root(ProviderKind::Claude),
+            Err(XurlError::NoStorageRoot { .. })
+        ));
+        assert!(roots.cache_root.starts_with(std::env::temp_dir()));
+
+        let with_extra = roots.with_extra_root(ProviderKind::Pi, path(&["backup"]));
+        assert_eq!(
+            with_extra.roots_for(ProviderKind::Pi),
+            vec![path(&["backup"])]
+        );
+        assert!(with_extra.ensure_own_root(ProviderKind::Pi).is_err());
+    }
+
+    #[test]
+    fn windows_layout_uses_known_folders_for_xurl_dirs() {
+        let vars = [
+            ("LOCALAPPDATA", "local"),
+            ("APPDATA", "roaming"),
+            ("XDG_CACHE_HOME", "xdg-cache"),
+        ];
+        let windows = roots(StorageLayout::Windows, &vars);
+        assert_eq!(windows.claude_root, path(&["home", ".claude"]));
+        assert_eq!(windows.gemini_root, path(&["home", ".gemini"]));
+        assert_eq!(windows.cache_root, path(&["local", "xurl", "cache"]));
+        assert_eq!(windows.data_root, path(&["roaming", "xurl"]));
+        assert_eq!(windows.config_root, path(&["roaming", "xurl"]));
+
+        let bare = roots(StorageLayout::Windows, &[]);
+        assert_eq!(
+            bare.cache_root,
+            path(&["home", "AppData", "Local", "xurl", "cache"])
+        );
+        assert_eq!(
+            bare.data_root,
+            path(&["home", "AppData", "Roaming", "xurl"])
+        );
+    }
+
+    #[test]
+    fn explicit_variables_win_on_every_layout() {
+        let vars = [
+            ("CODEX_HOME", "codex"),
+            ("GEMINI_CLI_HOME", "gemini-home"),
+            ("XURL_CACHE_DIR", "cache"),
+            ("XURL_DATA_DIR", "data"),
+            ("XURL_CONFIG_DIR", "config"),
+            ("LOCALAPPDATA", "local"),
+            ("XURL_GEMINI_SYNC_URL", " "),
+        ];
+        for layout in [StorageLayout::Unix, StorageLayout::Windows] {
+            let roots = roots(layout, &vars);
+            assert_eq!(roots.codex_root, path(&["codex"]));
+            assert_eq!(roots.gemini_root, path(&["gemini-home", ".gemini"]));
+            assert_eq!(roots.cache_root, path(&["cache"]));
+            assert_eq!(roots.data_root, path(&["data"]));
+            assert_eq!(roots.config_root, path(&["config"]));
+            assert_eq!(roots.gemini_sync_url, None);
+        }
+    }
 }
@@ -0,0 +1,189 @@
+//! One provider whose sessions are spread over several storage roots, e.g.
+//! Codex homes synced from other machines (`XURL_CODEX_ROOTS`).
+//!
+//! Roots are searched in order. The first root that stores a session wins;
+//! every resolution says which root that was, and copies in later roots are
+//! reported as warnings.
+
+use std::path::{Path, PathBuf};
+
+use crate::error::{Result, XurlError};
+use crate::model::{
+    ProviderKind, ResolvedThread, StorageSchema, ThreadListing, Warning, WriteRequest, WriteResult,
+};
+use crate::provider::{Provider, WriteEventSink};
+
+pub struct MultiRootProvider {
+    kind: ProviderKind,
+    roots: Vec<(PathBuf, Box<dyn Provider>)>,
+}
+
+impl MultiRootProvider {
+    /// `roots` pairs each storage root with the provider reading it, in
+    /// search order. The first root is the one agents write to.
+    pub fn new(kind: ProviderKind, roots: Vec<(PathBuf, Box<dyn Provider>)>) -> Self {
+        Self { kind, roots }
+    }
+}
+
+impl Provider for MultiRootProvider {
+    fn kind(&self) -> ProviderKind {
+        self.kind
+    }
+
+    fn resolve(&self, session_id: &str) -> Result<ResolvedThread> {
+        let mut found: Option<ResolvedThread> = None;
+        let mut copies = Vec::new();
+        let mut failures = Vec::new();
+        let mut searched_roots = Vec::new();
+
+        for (index, (root, provider)) in self.roots.iter().enumerate() {
+            tracing::debug!(root = %root.display(), "searching storage root");
+            match provider.resolve(session_id) {
+                Ok(mut resolved) if found.is_none() => {
+                    resolved.metadata.warnings.push(Warning::info(
+                        "storage_root",
+                        format!(
+                            "found in root {} of {}: {}",
+                            index + 1,
+                            self.roots.len(),
+                            root.display()
+                        ),
+                    ));
+                    found = Some(resolved);
+                }
+                Ok(resolved) => copies.push(resolved.path),
+                Err(XurlError::ThreadNotFound {
+                    searched_roots: searched,
+                    ..
+                }) => searched_roots.extend(searched),
+                Err(err) => failures.push((root, err)),
+            }
+        }
+
+        let Some(mut resolved) = found else {
+            return Err(match failures.into_iter().next() {
+                Some((_, err)) => err,
+                None => XurlError::ThreadNotFound {
+                    provider: self.kind.to_string(),
+                    session_id: session_id.to_string(),
+                    searched_roots,
+                },
+            });
+        };
+        for path in copies {
+            resolved.metadata.warnings.push(Warning::info(
+                "storage_root_copy",
+                format!("another copy is stored at {}", path.display()),
+            ));
+        }
+        for (root, err) in failures {
+            resolved.metadata.warnings.push(Warning::warn(
+                "storage_root_failed",
+                format!("failed searching {}: {err}", root.display()),
+            ));
+        }
+        Ok(resolved)
+    }
+
+    fn list_threads(&self) -> Result<Vec<ThreadListing>> {
+        let mut listings = Vec::new();
+        for (_, provider) in &self.roots {
+            listings.extend(provider.list_threads()?);
+        }
+        Ok(listings)
+    }
+
+    /// The schema of the root storing `thread`, or without one, of the first
+    /// root that has any storage.
+    fn detect_schema(&self, thread: Option<&Path>) -> Option<StorageSchema> {
+        match thread {
+            Some(thread) => self
+                .roots
+                .iter()
+                .find(|(root, _)| thread.starts_with(root))
+                .or_else(|| self.roots.first())
+                .and_then(|(_, provider)| provider.detect_schema(Some(thread))),
+            None => self
+                .roots
+                .iter()
+                .find_map(|(_, provider)| provider.detect_schema(None)),
+        }
+    }
+
+    /// Agents only write to their own root, the first one.
+    fn write(&self, req: &WriteRequest, sink: &mut dyn WriteEventSink) -> Result<WriteResult> {
+        match self.roots.first() {
+            Some((_, provider)) => provider.write(req, sink),
+            None => Err(XurlError::UnsupportedProviderWrite(self.kind.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use tempfile::tempdir;
+
+    use crate::error::XurlError;
+    use crate::model::ProviderKind;
+    use crate::provider::codex::CodexProvider;
+    use crate::provider::multi_root::MultiRootProvider;
+    use crate::provider::{Provider, write_test_rollout};
+
+    const SESSION_ID: &str = "019c871c-b1f9-7f60-9c4f-87ed09f13592";
+
+    fn codex_roots(roots: &[&Path]) -> MultiRootProvider {
+        MultiRootProvider::new(
+            ProviderKind::Codex,
+            roots
+                .iter()
+                .map(|root| {
+                    (
+                        root.to_path_buf(),
+                        Box::new(CodexProvider::new(root)) as Box<dyn Provider>,
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn first_root_storing_the_session_wins() {
+        let temp = tempdir().expect("tempdir");
+        let (own, backup_a, backup_b) = (
+            temp.path().join("codex"),
+            temp.path().join("codex-a"),
+            temp.path().join("codex-b"),
+        );
+        write_test_rollout(&backup_a, SESSION_ID, "{}\n");
+        write_test_rollout(&backup_b, SESSION_ID, "{}\n");
+        write_test_rollout(&backup_b, "019c87fb-38b9-7843-92b1-832f02598495", "{}\n");
+
+        let provider = codex_roots(&[&own, &backup_a, &backup_b]);
+        let resolved = provider.resolve(SESSION_ID).expect("resolve");
+        assert!(resolved.path.starts_with(&backup_a));
+        let codes = resolved
+            .metadata
+            .warnings
+            .iter()
+            .map(|warning| (warning.code, warning.message.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(codes.len(), 2);
+        assert_eq!(codes[0].0, "storage_root");
+        assert!(codes[0].1.starts_with("found in root 2 of 3: "));
+        assert_eq!(codes[1].0, "storage_root_copy");
+
+        assert_eq!(provider.list_threads().expect("list").len(), 3);
+
+        let err = provider
+            .resolve("019c0000-0000-7000-8000-000000000000")
+            .expect_err("missing");
+        assert!(matches!(
+            err,
+            XurlError::ThreadNotFound { ref searched_roots, .. }
+                if searched_roots.iter().any(|root| root.starts_with(&backup_b))
+        ));
+    }
+}
@@ -1,13 +1,30 @@
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
 
-use rusqlite::{Connection, OpenFlags};
+use rusqlite::{Connection, ErrorCode, OpenFlags};
 use serde_json::{Value, json};
 
 use crate::error::{Result, XurlError};
-use crate::model::{ProviderKind, ResolutionMeta, ResolvedThread};
-use crate::provider::Provider;
+use crate::model::{
+    ProviderKind, ResolutionMeta, ResolvedThread, SessionInfo, StorageSchema, ThreadListing,
+    Warning,
+};
+use crate::provider::{MaintenanceProvider, Provider, modified_or_epoch, open_sqlite};
+
+/// Sessions, messages and parts in the tables of `opencode.db`.
+const LAYOUT_SQLITE: &str = "sqlite";
+
+/// How long one query waits on a running opencode's lock before failing.
+const BUSY_TIMEOUT: Duration = Duration::from_millis(250);
+/// Pauses between whole read attempts while the database stays locked.
+const LOCK_RETRY_DELAYS: [Duration; 3] = [
+    Duration::from_millis(100),
+    Duration::from_millis(200),
+    Duration::from_millis(400),
+];
 
 #[derive(Debug, Clone)]
 pub struct OpencodeProvider {
@@ -29,6 +46,87 @@ impl OpencodeProvider {
             .join(format!("{session_id}.jsonl"))
     }
 
+    /// Runs `read` against the database. While opencode holds a lock that
+    /// outlasts the retries, `read` runs against a copy of the database and
+    /// its write-ahead log instead, and a `database_locked` warning is returned.
+    fn read_db<T>(
+        db_path: &Path,
+        read: impl Fn(&Connection) -> std::result::Result<T, rusqlite::Error>,
+    ) -> Result<(T, Vec<Warning>)> {
+        let sqlite_error = |source| XurlError::Sqlite {
+            path: db_path.to_path_buf(),
+            source,
+        };
+
+        let mut delays = LOCK_RETRY_DELAYS.iter();
+        loop {
+            match Self::open_read_only(db_path).and_then(|conn| read(&conn)) {
+                Ok(value) => return Ok((value, Vec::new())),
+                Err(err) if Self::is_locked(&err) => match delays.next() {
+                    Some(delay) => thread::sleep(*delay),
+                    None => break,
+                },
+                Err(source) => return Err(sqlite_error(source)),
+            }
+        }
+
+        let snapshot_dir = std::env::temp_dir()
+            .join("xurl-opencode")
+            .join(format!("snapshot-{}", uuid::Uuid::new_v4()));
+        let value = Self::copy_snapshot(db_path, &snapshot_dir).and_then(|snapshot| {
+            open_sqlite(&snapshot, OpenFlags::default())
+                .and_then(|conn| read(&conn))
+                .map_err(sqlite_error)
+        });
+        // Best effort: the snapshot is only needed for this read.
+        let _ = fs::remove_dir_all(&snapshot_dir);
+
+        let warning = Warning::warn(
+            "database_locked",
+            format!(
+                "{} is locked, likely by a running opencode; read a copy of it and its write-ahead log, so changes still being written may be missing",
+                db_path.display()
+            ),
+        );
+        Ok((value?, vec![warning]))
+    }
+
+    fn open_read_only(db_path: &Path) -> std::result::Result<Connection, rusqlite::Error> {
+        let conn = open_sqlite(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        Ok(conn)
+    }
+
+    fn is_locked(err: &rusqlite::Error) -> bool {
+        matches!(
+            err.sqlite_error_code(),
+            Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked)
+        )
+    }
+
+    /// Copies the database and, when present, its `-wal` file into `dir`.
+    /// Opening the copy replays the log, so committed changes are included.
+    fn copy_snapshot(db_path: &Path, dir: &Path) -> Result<PathBuf> {
+        fs::create_dir_all(dir).map_err(|source| XurlError::Io {
+            path: dir.to_path_buf(),
+            source,
+        })?;
+
+        let snapshot = dir.join("opencode.db");
+        fs::copy(db_path, &snapshot).map_err(|source| XurlError::Io {
+            path: db_path.to_path_buf(),
+            source,
+        })?;
+        let wal = db_path.with_file_name("opencode.db-wal");
+        match fs::copy(&wal, dir.join("opencode.db-wal")) {
+            Ok(_) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(source) => return Err(XurlError::Io { path: wal, source }),
+        }
+
+        Ok(snapshot)
+    }
+
     fn session_exists(
         conn: &Connection,
         session_id: &str,
@@ -41,7 +139,7 @@ impl OpencodeProvider {
     fn fetch_messages(
         conn: &Connection,
         session_id: &str,
-        warnings: &mut Vec<String>,
+        warnings: &mut Vec<Warning>,
     ) -> std::result::Result<Vec<(String, Value)>, rusqlite::Error> {
         let mut stmt = conn.prepare(
             "SELECT id, data
@@ -61,8 +159,9 @@ impl OpencodeProvider {
             let (id, data) = row?;
             match serde_json::from_str::<Value>(&data) {
                 Ok(value) => result.push((id, value)),
-                Err(err) => warnings.push(format!(
-                    "skipped message id={id}: invalid json payload ({err})"
+                Err(err) => warnings.push(Warning::warn(
+                    "invalid_row",
+                    format!("skipped message id={id}: invalid json payload ({err})"),
                 )),
             }
         }
@@ -73,7 +172,7 @@ impl OpencodeProvider {
     fn fetch_parts(
         conn: &Connection,
         session_id: &str,
-        warnings: &mut Vec<String>,
+        warnings: &mut Vec<Warning>,
     ) -> std::result::Result<HashMap<String, Vec<Value>>, rusqlite::Error> {
         let mut stmt = conn.prepare(
             "SELECT message_id, data
@@ -98,8 +197,11 @@ impl OpencodeProvider {
                         .or_insert_with(Vec::new)
                         .push(value);
                 }
-                Err(err) => warnings.push(format!(
-                    "skipped part for message_id={message_id}: invalid json payload ({err})"
+                Err(err) => warnings.push(Warning::warn(
+                    "invalid_row",
+                    format!(
+                        "skipped part for message_id={message_id}: invalid json payload ({err})"
+                    ),
                 )),
             }
         }
@@ -107,7 +209,7 @@ impl OpencodeProvider {
         Ok(result)
     }
 
-    fn render_jsonl(
+    pub(crate) fn render_jsonl(
         session_id: &str,
         messages: Vec<(String, Value)>,
         mut parts: HashMap<String, Vec<Value>>,
@@ -153,37 +255,23 @@ impl Provider for OpencodeProvider {
             });
         }
 
-        let conn = Connection::open_with_flags(&db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
-            .map_err(|source| XurlError::Sqlite {
-                path: db_path.clone(),
-                source,
-            })?;
-
-        if !Self::session_exists(&conn, session_id).map_err(|source| XurlError::Sqlite {
-            path: db_path.clone(),
-            source,
-        })? {
+        let (session, mut warnings) = Self::read_db(&db_path, |conn| {
+            if !Self::session_exists(conn, session_id)? {
+                return Ok(None);
+            }
+            let mut warnings = Vec::new();
+            let messages = Self::fetch_messages(conn, session_id, &mut warnings)?;
+            let parts = Self::fetch_parts(conn, session_id, &mut warnings)?;
+            Ok(Some((messages, parts, warnings)))
+        })?;
+        let Some((messages, parts, row_warnings)) = session else {
             return Err(XurlError::ThreadNotFound {
                 provider: ProviderKind::Opencode.to_string(),
                 session_id: session_id.to_string(),
                 searched_roots: vec![db_path],
             });
-        }
-
-        let mut warnings = Vec::new();
-        let messages =
-            Self::fetch_messages(&conn, session_id, &mut warnings).map_err(|source| {
-                XurlError::Sqlite {
-                    path: db_path.clone(),
-                    source,
-                }
-            })?;
-        let parts = Self::fetch_parts(&conn, session_id, &mut warnings).map_err(|source| {
-            XurlError::Sqlite {
-                path: db_path.clone(),
-                source,
-            }
-        })?;
+        };
+        warnings.extend(row_warnings);
 
         let raw = Self::render_jsonl(session_id, messages, parts);
         let path = Self::materialized_path(session_id);
@@ -208,11 +296,75 @@ impl Provider for OpencodeProvider {
                 source: "opencode:sqlite".to_string(),
                 candidate_count: 1,
                 warnings,
+                merged_paths: Vec::new(),
+                session: SessionInfo::default(),
             },
         })
     }
+
+    fn list_threads(&self) -> Result<Vec<ThreadListing>> {
+        let db_path = self.db_path();
+        if !db_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        // Listings carry no warnings, so a locked database is read silently.
+        let (rows, _) = Self::read_db(&db_path, |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT session.id, MAX(message.time_created)
+                 FROM session
+                 LEFT JOIN message ON message.session_id = session.id
+                 GROUP BY session.id",
+            )?;
+            stmt.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, Option<i64>>(1)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()
+        })?;
+
+        // Message times are epoch milliseconds; sessions without messages
+        // fall back to the database's own modification time.
+        let db_modified = modified_or_epoch(&db_path);
+        let mut listings = Vec::new();
+        for (session_id, updated_ms) in rows {
+            let updated_at = updated_ms
+                .and_then(|ms| u64::try_from(ms).ok())
+                .map_or(db_modified, |ms| {
+                    SystemTime::UNIX_EPOCH + Duration::from_millis(ms)
+                });
+            listings.push(ThreadListing {
+                provider: ProviderKind::Opencode,
+                session_id,
+                path: db_path.clone(),
+                updated_at,
+            });
+        }
+
+        Ok(listings)
+    }
+
+    /// `sqlite`, versioned by the number of migrations opencode has applied.
+    fn detect_schema(&self, _thread: Option<&Path>) -> Option<StorageSchema> {
+        let db_path = self.db_path();
+        if !db_path.exists() {
+            return None;
+        }
+        let version = Self::open_read_only(&db_path)
+            .and_then(|conn| {
+                conn.query_row("SELECT COUNT(*) FROM __drizzle_migrations", [], |row| {
+                    row.get::<_, i64>(0)
+                })
+            })
+            .ok();
+        Some(StorageSchema::new(
+            LAYOUT_SQLITE,
+            version.map(|count| count.to_string()),
+        ))
+    }
 }
 
+impl MaintenanceProvider for OpencodeProvider {}
+
 #[cfg(test)]
 mod tests {
     use std::fs;
@@ -319,6 +471,42 @@ mod tests {
         assert!(raw.contains(r#""text":"world""#));
     }
 
+    #[test]
+    fn reads_a_snapshot_while_the_database_is_locked() {
+        let temp = tempdir().expect("tempdir");
+        let db = temp.path().join("opencode.db");
+        let conn = prepare_db(&db);
+        // A running opencode: WAL mode with a lock that blocks every reader.
+        conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA locking_mode=EXCLUSIVE;")
+            .expect("lock");
+
+        let session_id = "ses_44b01f4bceffkShsUemKb59DuF";
+        conn.execute("INSERT INTO session (id) VALUES (?1)", [session_id])
+            .expect("insert session");
+        conn.execute(
+            "INSERT INTO message (id, session_id, time_created, data) VALUES (?1, ?2, ?3, ?4)",
+            params!["msg_1", session_id, 1_i64, r#"{"role":"user"}"#],
+        )
+        .expect("insert message");
+        conn.execute(
+            "INSERT INTO part (id, message_id, session_id, time_created, data) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params!["prt_1", "msg_1", session_id, 1_i64, r#"{"type":"text","text":"in the wal"}"#],
+        )
+        .expect("insert part");
+        assert!(temp.path().join("opencode.db-wal").exists());
+
+        let provider = OpencodeProvider::new(temp.path());
+        let resolved = provider.resolve(session_id).expect("resolve from snapshot");
+        assert_eq!(resolved.metadata.warnings.len(), 1);
+        assert_eq!(resolved.metadata.warnings[0].code, "database_locked");
+        let raw = fs::read_to_string(&resolved.path).expect("read materialized");
+        assert!(raw.contains(r#""text":"in the wal""#));
+
+        let listings = provider.list_threads().expect("list from snapshot");
+        assert_eq!(listings.len(), 1);
+        assert_eq!(listings[0].session_id, session_id);
+    }
+
     #[test]
     fn returns_not_found_when_db_missing() {
         let temp = tempdir().expect("tempdir");
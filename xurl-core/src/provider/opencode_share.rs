@@ -0,0 +1,264 @@
+//! Sessions shared from opencode as `https://opencode.ai/s/<share id>` links.
+//!
+//! A shared session is downloaded only when network access is allowed,
+//! converted to the JSONL xurl materializes for local OpenCode sessions, and
+//! cached under xurl's cache directory. Later reads of the link find the
+//! cached copy without the network and render it like any OpenCode thread.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::atomic::write_atomic;
+use crate::error::{Result, XurlError};
+use crate::model::ProviderKind;
+use crate::provider::ProviderRoots;
+use crate::provider::opencode::OpencodeProvider;
+use crate::uri::ThreadUri;
+
+/// Endpoint serving a share's session, messages, and parts.
+pub const DEFAULT_SHARE_API_URL: &str = "https://opencode.ai/api/share/{share_id}/data";
+/// Placeholder in the endpoint template replaced by the share id.
+const SHARE_PLACEHOLDER: &str = "{share_id}";
+const SHARE_CACHE_DIR: &str = "opencode-share";
+
+/// Replaces the share id that starts the target of a share link with the
+/// `file://` URI of its cached transcript, keeping a `#msg-<N>` fragment.
+/// The share is downloaded first when `roots.allow_network` is set.
+pub fn expand_share_uri(target: &str, roots: &ProviderRoots) -> Result<String> {
+    let (share_id, rest) = target.split_at(target.find('#').unwrap_or(target.len()));
+    let share_id = share_id.trim_end_matches('/');
+    if share_id.is_empty()
+        || !share_id
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_'))
+    {
+        return Err(XurlError::InvalidUri(format!(
+            "https://opencode.ai/s/{target}"
+        )));
+    }
+
+    let path = cache_path(roots, share_id);
+    if roots.allow_network {
+        let url = share_api_url(roots, share_id);
+        let body = fetch_share(&url)?;
+        let jsonl = share_to_jsonl(share_id, &body).map_err(|message| {
+            XurlError::Serialization(format!("unrecognized opencode share from {url}: {message}"))
+        })?;
+        store(&path, &jsonl)?;
+        tracing::debug!(share_id, url, path = %path.display(), "downloaded opencode share");
+    } else if path.is_file() {
+        tracing::debug!(share_id, path = %path.display(), "cached opencode share");
+    } else {
+        return Err(XurlError::InvalidMode(format!(
+            "opencode share {share_id} is not cached; pass --allow-network (or set XURL_ALLOW_NETWORK=1) to download it"
+        )));
+    }
+
+    let uri = ThreadUri {
+        provider: ProviderKind::File,
+        session_id: path.to_string_lossy().into_owned(),
+        agent_id: None,
+        fragment: None,
+        file_format: Some(ProviderKind::Opencode),
+        gemini_project: None,
+    };
+    Ok(format!("{}{rest}", uri.as_string()))
+}
+
+fn cache_path(roots: &ProviderRoots, share_id: &str) -> PathBuf {
+    roots
+        .cache_root
+        .join(SHARE_CACHE_DIR)
+        .join(format!("{share_id}.jsonl"))
+}
+
+fn share_api_url(roots: &ProviderRoots, share_id: &str) -> String {
+    roots
+        .opencode_share_url
+        .as_deref()
+        .unwrap_or(DEFAULT_SHARE_API_URL)
+        .replace(SHARE_PLACEHOLDER, share_id)
+}
+
+fn store(path: &Path, jsonl: &str) -> Result<()> {
+    let parent = path.parent().unwrap_or(path);
+    fs::create_dir_all(parent).map_err(|source| XurlError::io(parent, source))?;
+    write_atomic(path, jsonl.as_bytes(), false)
+}
+
+/// Converts shared session data to OpenCode JSONL. Two layouts are read: a
+/// list of `{"type": "session" | "message" | "part", "data": ...}` items, and
+/// the older `{"info": ..., "messages": ...}` object whose messages carry
+/// their parts inline.
+fn share_to_jsonl(share_id: &str, body: &[u8]) -> std::result::Result<String, String> {
+    let value = serde_json::from_slice::<Value>(body).map_err(|err| err.to_string())?;
+    let mut session_id = None;
+    let mut messages = Vec::new();
+    let mut parts = HashMap::<String, Vec<Value>>::new();
+
+    match value {
+        Value::Array(items) => {
+            for item in items {
+                let data = item.get("data").cloned().unwrap_or(Value::Null);
+                match item.get("type").and_then(Value::as_str) {
+                    Some("session") => session_id = string_field(&data, "id"),
+                    Some("message") => {
+                        if let Some(id) = string_field(&data, "id") {
+                            messages.push((id, data));
+                        }
+                    }
+                    Some("part") => {
+                        if let Some(message_id) = string_field(&data, "messageID") {
+                            parts.entry(message_id).or_default().push(data);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Value::Object(mut object) => {
+            let Some(shared) = object.remove("messages") else {
+                return Err("neither a list of items nor an object with messages".to_string());
+            };
+            session_id = object.get("info").and_then(|info| string_field(info, "id"));
+            let shared = match shared {
+                Value::Object(map) => map.into_iter().map(|(_, message)| message).collect(),
+                Value::Array(list) => list,
+                _ => return Err("messages is neither an object nor a list".to_string()),
+            };
+            for mut message in shared {
+                let Some(id) = string_field(&message, "id") else {
+                    continue;
+                };
+                if let Some(Value::Array(inline)) = message
+                    .as_object_mut()
+                    .and_then(|message| message.remove("parts"))
+                {
+                    parts.entry(id.clone()).or_default().extend(inline);
+                }
+                messages.push((id, message));
+            }
+        }
+        _ => return Err("neither a list of items nor an object with messages".to_string()),
+    }
+
+    // Message ids sort by creation time; shares do not list them in order.
+    messages.sort_by(|(left, _), (right, _)| left.cmp(right));
+    let session_id = session_id.unwrap_or_else(|| share_id.to_string());
+    Ok(OpencodeProvider::render_jsonl(&session_id, messages, parts))
+}
+
+fn string_field(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .map(ToString::to_string)
+}
+
+#[cfg(feature = "opencode-share")]
+fn fetch_share(url: &str) -> Result<Vec<u8>> {
+    use std::io::Read;
+
+    let response = ureq::get(url)
+        .set("Accept", "application/json")
+        .call()
+        .map_err(|err| XurlError::RemoteFetch {
+            url: url.to_string(),
+            message: err.to_string(),
+        })?;
+    let mut body = Vec::new();
+    response
+        .into_reader()
+        .read_to_end(&mut body)
+        .map_err(|err| XurlError::RemoteFetch {
+            url: url.to_string(),
+            message: err.to_string(),
+        })?;
+    Ok(body)
+}
+
+#[cfg(not(feature = "opencode-share"))]
+fn fetch_share(url: &str) -> Result<Vec<u8>> {
+    Err(XurlError::RemoteFetch {
+        url: url.to_string(),
+        message: "xurl was built without the `opencode-share` feature".to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use tempfile::tempdir;
+
+    use crate::error::XurlError;
+    use crate::model::ProviderKind;
+    use crate::provider::ProviderRoots;
+    use crate::provider::opencode_share::{expand_share_uri, share_to_jsonl};
+    use crate::render::{ExtractionPolicy, extract_messages};
+
+    #[test]
+    fn both_share_layouts_convert_to_opencode_jsonl() {
+        let items = r#"[
+            {"type":"session","data":{"id":"ses_7v2md9kx3c1p"}},
+            {"type":"message","data":{"id":"msg_2","role":"assistant"}},
+            {"type":"message","data":{"id":"msg_1","role":"user"}},
+            {"type":"part","data":{"messageID":"msg_1","type":"text","text":"hello"}},
+            {"type":"part","data":{"messageID":"msg_2","type":"text","text":"world"}}
+        ]"#;
+        let legacy = r#"{"info":{"id":"ses_7v2md9kx3c1p"},"messages":{
+            "msg_1":{"id":"msg_1","role":"user","parts":[{"type":"text","text":"hello"}]},
+            "msg_2":{"id":"msg_2","role":"assistant","parts":[{"type":"text","text":"world"}]}
+        }}"#;
+
+        for body in [items, legacy] {
+            let jsonl = share_to_jsonl("aB3dE5", body.as_bytes()).expect("convert");
+            let header = jsonl.lines().next().expect("header");
+            assert!(header.contains(r#""sessionId":"ses_7v2md9kx3c1p""#));
+            let messages = extract_messages(
+                ProviderKind::Opencode,
+                Path::new("/tmp/share.jsonl"),
+                &jsonl,
+                &mut ExtractionPolicy::default(),
+            )
+            .expect("extract");
+            assert_eq!(
+                messages
+                    .iter()
+                    .map(|message| message.text.as_str())
+                    .collect::<Vec<_>>(),
+                ["hello", "world"]
+            );
+        }
+        assert!(share_to_jsonl("aB3dE5", b"{}").is_err());
+    }
+
+    #[test]
+    fn share_links_read_the_cache_without_network() {
+        let temp = tempdir().expect("tempdir");
+        let mut roots = ProviderRoots::for_test(temp.path());
+        roots.cache_root = temp.path().to_path_buf();
+        roots.allow_network = false;
+
+        let err = expand_share_uri("aB3dE5", &roots).expect_err("not cached");
+        assert!(
+            matches!(err, XurlError::InvalidMode(message) if message.contains("--allow-network"))
+        );
+
+        let cached = temp.path().join("opencode-share/aB3dE5.jsonl");
+        fs::create_dir_all(cached.parent().expect("parent")).expect("mkdir");
+        fs::write(&cached, "{}\n").expect("write");
+        let uri = expand_share_uri("aB3dE5#msg-2", &roots).expect("expand");
+        assert!(uri.starts_with("file://"));
+        assert!(uri.ends_with("aB3dE5.jsonl?format=opencode#msg-2"));
+
+        assert!(matches!(
+            expand_share_uri("a/b", &roots),
+            Err(XurlError::InvalidUri(_))
+        ));
+    }
+}
@@ -1,15 +1,25 @@
 use std::cmp::Reverse;
 use std::fs;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
-use serde_json::Value;
+use chrono::Utc;
+use serde_json::{Value, json};
 use walkdir::WalkDir;
 
 use crate::error::{Result, XurlError};
-use crate::model::{ProviderKind, ResolutionMeta, ResolvedThread};
-use crate::provider::Provider;
+use crate::model::{
+    ProviderKind, ResolutionMeta, ResolvedThread, SessionInfo, StorageSchema, ThreadListing,
+    Warning, WriteRequest, WriteResult,
+};
+use crate::provider::{MaintenanceProvider, Provider, WriteEventSink, modified_or_epoch};
+use crate::records::PiEntry;
+use crate::render::pi_session_version;
+
+/// One JSONL file per session under `sessions/<cwd>/`, opened by a
+/// `session` header.
+const LAYOUT_SESSION_JSONL: &str = "session_jsonl";
 
 #[derive(Debug, Clone)]
 pub struct PiProvider {
@@ -25,34 +35,31 @@ impl PiProvider {
         self.root.join("sessions")
     }
 
-    fn has_session_id(path: &Path, session_id: &str) -> bool {
-        let file = match fs::File::open(path) {
-            Ok(file) => file,
-            Err(_) => return false,
-        };
+    fn session_id_of(path: &Path) -> Option<String> {
+        let file = fs::File::open(path).ok()?;
         let reader = BufReader::new(file);
 
-        let Some(first_non_empty) = reader
+        let first_non_empty = reader
             .lines()
             .take(20)
             .filter_map(std::result::Result::ok)
-            .find(|line| !line.trim().is_empty())
-        else {
-            return false;
-        };
+            .find(|line| !line.trim().is_empty())?;
+        let header = serde_json::from_str::<Value>(&first_non_empty).ok()?;
 
-        let Ok(header) = serde_json::from_str::<Value>(&first_non_empty) else {
-            return false;
-        };
+        if header.get("type").and_then(Value::as_str) != Some("session") {
+            return None;
+        }
+        header
+            .get("id")
+            .and_then(Value::as_str)
+            .map(ToString::to_string)
+    }
 
-        header.get("type").and_then(Value::as_str) == Some("session")
-            && header
-                .get("id")
-                .and_then(Value::as_str)
-                .is_some_and(|id| id.eq_ignore_ascii_case(session_id))
+    fn has_session_id(path: &Path, session_id: &str) -> bool {
+        Self::session_id_of(path).is_some_and(|id| id.eq_ignore_ascii_case(session_id))
     }
 
-    fn find_candidates(sessions_root: &Path, session_id: &str) -> Vec<PathBuf> {
+    fn session_files(sessions_root: &Path) -> Vec<PathBuf> {
         if !sessions_root.exists() {
             return Vec::new();
         }
@@ -67,6 +74,12 @@ impl PiProvider {
                     .and_then(|ext| ext.to_str())
                     .is_some_and(|ext| ext == "jsonl")
             })
+            .collect()
+    }
+
+    fn find_candidates(sessions_root: &Path, session_id: &str) -> Vec<PathBuf> {
+        Self::session_files(sessions_root)
+            .into_iter()
             .filter(|path| Self::has_session_id(path, session_id))
             .collect()
     }
@@ -89,6 +102,25 @@ impl PiProvider {
         let count = scored.len();
         scored.into_iter().next().map(|(path, _)| (path, count))
     }
+
+    /// Ids of the entries in a session file, in file order.
+    pub(crate) fn entry_ids(raw: &str) -> Vec<String> {
+        raw.lines()
+            .filter_map(|line| serde_json::from_str::<PiEntry>(line.trim()).ok())
+            .filter(|entry| entry.entry_type != "session")
+            .filter_map(|entry| entry.id)
+            .collect()
+    }
+
+    /// Short hex id in the style pi uses, unique within the session.
+    fn new_entry_id(existing: &[String]) -> String {
+        loop {
+            let id = uuid::Uuid::new_v4().simple().to_string()[..8].to_string();
+            if !existing.contains(&id) {
+                return id;
+            }
+        }
+    }
 }
 
 impl Provider for PiProvider {
@@ -99,18 +131,24 @@ impl Provider for PiProvider {
     fn resolve(&self, session_id: &str) -> Result<ResolvedThread> {
         let sessions_root = self.sessions_root();
         let candidates = Self::find_candidates(&sessions_root, session_id);
+        tracing::debug!(root = %sessions_root.display(), ?candidates, "pi session candidates");
 
         if let Some((selected, count)) = Self::choose_latest(candidates) {
             let mut metadata = ResolutionMeta {
                 source: "pi:sessions".to_string(),
                 candidate_count: count,
                 warnings: Vec::new(),
+                merged_paths: Vec::new(),
+                session: SessionInfo::default(),
             };
 
             if count > 1 {
-                metadata.warnings.push(format!(
-                    "multiple matches found ({count}) for session_id={session_id}; selected latest: {}",
-                    selected.display()
+                metadata.warnings.push(Warning::warn(
+                    "ambiguous_session",
+                    format!(
+                        "multiple matches found ({count}) for session_id={session_id}; selected latest: {}",
+                        selected.display()
+                    ),
                 ));
             }
 
@@ -128,8 +166,110 @@ impl Provider for PiProvider {
             searched_roots: vec![sessions_root],
         })
     }
+
+    fn list_threads(&self) -> Result<Vec<ThreadListing>> {
+        Ok(Self::session_files(&self.sessions_root())
+            .into_iter()
+            .filter_map(|path| {
+                Some(ThreadListing {
+                    provider: ProviderKind::Pi,
+                    session_id: Self::session_id_of(&path)?,
+                    updated_at: modified_or_epoch(&path),
+                    path,
+                })
+            })
+            .collect())
+    }
+
+    /// `session_jsonl` with the version its `session` header records.
+    fn detect_schema(&self, thread: Option<&Path>) -> Option<StorageSchema> {
+        let Some(thread) = thread else {
+            return self
+                .sessions_root()
+                .is_dir()
+                .then(|| StorageSchema::new(LAYOUT_SESSION_JSONL, None));
+        };
+        let mut header = String::new();
+        BufReader::new(fs::File::open(thread).ok()?)
+            .read_line(&mut header)
+            .ok()?;
+        Some(StorageSchema::new(
+            LAYOUT_SESSION_JSONL,
+            Some(pi_session_version(&header).to_string()),
+        ))
+    }
+
+    /// Records the prompt as a user entry in an existing session. The entry
+    /// becomes a child of `parent_entry_id` (a new branch) or of the latest
+    /// entry, and is the leaf pi continues from when the session is resumed.
+    fn write(&self, req: &WriteRequest, _sink: &mut dyn WriteEventSink) -> Result<WriteResult> {
+        let Some(session_id) = req.session_id.as_deref() else {
+            return Err(XurlError::InvalidMode(
+                "pi write mode needs an existing session: agents://pi/<session_id>[/<entry_id>]"
+                    .to_string(),
+            ));
+        };
+
+        let resolved = self.resolve(session_id)?;
+        let raw = fs::read_to_string(&resolved.path)
+            .map_err(|source| XurlError::io(&resolved.path, source))?;
+        let entry_ids = Self::entry_ids(&raw);
+
+        let parent_id = match req.parent_entry_id.as_deref() {
+            Some(parent_id) if !entry_ids.iter().any(|id| id == parent_id) => {
+                return Err(XurlError::EntryNotFound {
+                    provider: ProviderKind::Pi.to_string(),
+                    session_id: session_id.to_string(),
+                    entry_id: parent_id.to_string(),
+                });
+            }
+            Some(parent_id) => Some(parent_id.to_string()),
+            None => entry_ids.last().cloned(),
+        };
+
+        let entry_id = Self::new_entry_id(&entry_ids);
+        let now = Utc::now();
+        let entry = json!({
+            "type": "message",
+            "id": entry_id,
+            "parentId": parent_id,
+            "timestamp": now.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+            "message": {
+                "role": "user",
+                "content": [{ "type": "text", "text": req.prompt }],
+                "timestamp": now.timestamp_millis(),
+            },
+        });
+
+        let mut line = String::new();
+        if !raw.is_empty() && !raw.ends_with('\n') {
+            line.push('\n');
+        }
+        line.push_str(&entry.to_string());
+        line.push('\n');
+
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .open(&resolved.path)
+            .map_err(|source| XurlError::io(&resolved.path, source))?;
+        file.write_all(line.as_bytes())
+            .map_err(|source| XurlError::Io {
+                path: resolved.path.clone(),
+                source,
+            })?;
+
+        Ok(WriteResult {
+            provider: ProviderKind::Pi,
+            session_id: session_id.to_string(),
+            final_text: None,
+            entry_id: Some(entry_id),
+            agent_id: None,
+        })
+    }
 }
 
+impl MaintenanceProvider for PiProvider {}
+
 #[cfg(test)]
 mod tests {
     use std::fs;
@@ -202,7 +342,11 @@ mod tests {
         assert_eq!(resolved.path, second);
         assert_eq!(resolved.metadata.candidate_count, 2);
         assert_eq!(resolved.metadata.warnings.len(), 1);
-        assert!(resolved.metadata.warnings[0].contains("multiple matches"));
+        assert!(
+            resolved.metadata.warnings[0]
+                .message
+                .contains("multiple matches")
+        );
         assert!(first.exists());
     }
 
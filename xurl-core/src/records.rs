@@ -0,0 +1,275 @@
+//! Typed views of the records each provider writes to disk.
+//!
+//! Only the fields xurl reads are declared; everything else is ignored so new
+//! provider fields never break parsing. Envelopes whose body shape depends on a
+//! discriminator keep that body as a raw [`Value`] and decode it with
+//! [`decode`] once the discriminator is known, so errors name the exact field.
+
+use std::path::Path;
+
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::error::{Result, XurlError};
+use crate::model::ProviderKind;
+
+/// Message content as used by Codex, Claude, Gemini, and Pi: either plain text
+/// or a list of content blocks.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum ContentPart {
+    Text(String),
+    Block(Box<ContentBlock>),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ContentBlock {
+    #[serde(rename = "type")]
+    pub block_type: Option<String>,
+    pub text: Option<String>,
+    pub input_text: Option<String>,
+    pub output_text: Option<String>,
+    pub thinking: Option<String>,
+    /// Whether a Claude `tool_result` block reports a failed call.
+    #[serde(default)]
+    pub is_error: bool,
+    /// Body of a Claude `tool_result`: a string or a list of text blocks.
+    pub content: Option<Value>,
+    /// Id of a `tool_use` block, which Amp `tool_result`s refer to.
+    pub id: Option<String>,
+    /// Tool a `tool_use` block calls.
+    pub name: Option<String>,
+    /// Arguments of a `tool_use` block.
+    pub input: Option<Value>,
+    /// The `tool_use` an Amp `tool_result` answers.
+    #[serde(rename = "toolUseID")]
+    pub tool_use_id: Option<String>,
+    /// Outcome of an Amp `tool_result`: its `status`, and `result` or `error`.
+    pub run: Option<Value>,
+}
+
+/// One line of a Codex rollout. `payload` is decoded per record type.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct CodexRolloutLine {
+    #[serde(rename = "type", default)]
+    pub record_type: String,
+    pub timestamp: Option<String>,
+    #[serde(default)]
+    pub payload: Value,
+}
+
+impl CodexRolloutLine {
+    pub fn payload_type(&self) -> Option<&str> {
+        self.payload.get("type").and_then(Value::as_str)
+    }
+}
+
+/// Record type of the line xurl inserts before each rollout when several
+/// rollouts of one session are merged. Codex never writes it.
+pub const CODEX_ROLLOUT_BOUNDARY: &str = "xurl_rollout_boundary";
+
+/// Payload of a [`CODEX_ROLLOUT_BOUNDARY`] line.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CodexRolloutBoundary {
+    pub path: String,
+}
+
+/// Payload of a `compacted` record: the summary Codex replaced the earlier
+/// history with.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct CodexCompactedPayload {
+    #[serde(default)]
+    pub message: String,
+}
+
+/// `response_item` payload with `type: "message"`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CodexMessagePayload {
+    pub role: String,
+    pub content: Option<MessageContent>,
+}
+
+/// `event_msg` payload with `type: "agent_message"`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CodexAgentMessagePayload {
+    #[serde(default)]
+    pub message: String,
+}
+
+/// One line of a Claude transcript.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ClaudeTranscriptLine {
+    #[serde(rename = "type", default)]
+    pub record_type: String,
+    pub subtype: Option<String>,
+    #[serde(rename = "isCompactSummary", default)]
+    pub is_compact_summary: bool,
+    pub message: Option<ClaudeMessage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ClaudeMessage {
+    pub role: Option<String>,
+    pub content: Option<MessageContent>,
+}
+
+/// An Amp thread file. Messages are decoded one by one so errors carry an index.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct AmpThread {
+    #[serde(default)]
+    pub messages: Vec<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AmpMessage {
+    pub role: String,
+    #[serde(default)]
+    pub content: Vec<ContentBlock>,
+}
+
+/// A Gemini chat file. Messages are decoded one by one so errors carry an index.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct GeminiChat {
+    #[serde(default)]
+    pub messages: Vec<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct GeminiMessage {
+    #[serde(rename = "type", default)]
+    pub message_type: String,
+    #[serde(rename = "displayContent")]
+    pub display_content: Option<MessageContent>,
+    pub content: Option<MessageContent>,
+}
+
+/// One line of a Pi session file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct PiEntry {
+    #[serde(rename = "type", default)]
+    pub entry_type: String,
+    pub id: Option<String>,
+    #[serde(rename = "parentId")]
+    pub parent_id: Option<String>,
+    pub timestamp: Option<String>,
+    pub message: Option<PiMessage>,
+    pub summary: Option<String>,
+    /// First entry a compaction keeps verbatim; earlier ones are summarized.
+    #[serde(rename = "firstKeptEntryId")]
+    pub first_kept_entry_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct PiMessage {
+    pub role: String,
+    pub content: Option<MessageContent>,
+}
+
+/// One line of the JSONL xurl materializes from the OpenCode database.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct OpencodeRow {
+    #[serde(rename = "type", default)]
+    pub row_type: String,
+    pub message: Option<OpencodeMessage>,
+    #[serde(default)]
+    pub parts: Vec<OpencodePart>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct OpencodeMessage {
+    pub role: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct OpencodePart {
+    #[serde(rename = "type", default)]
+    pub part_type: String,
+    pub text: Option<String>,
+}
+
+/// One event of a Copilot CLI session log. `data` is decoded per event type.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct CopilotEvent {
+    #[serde(rename = "type", default)]
+    pub event_type: String,
+    #[serde(default)]
+    pub data: Value,
+}
+
+/// `data` of `user.message` and `assistant.message` events.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CopilotMessageData {
+    #[serde(default)]
+    pub content: String,
+}
+
+/// Decodes `value` as `T`, reporting failures against `field` (a JSON path
+/// such as `$.payload` or `$.messages[3]`) on the given line.
+pub fn decode<T: DeserializeOwned>(
+    provider: ProviderKind,
+    path: &Path,
+    line: usize,
+    field: &str,
+    value: Value,
+) -> Result<T> {
+    serde_json::from_value(value).map_err(|err| XurlError::InvalidRecord {
+        provider: provider.to_string(),
+        path: path.to_path_buf(),
+        line,
+        field: field.to_string(),
+        message: err.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use serde_json::json;
+
+    use crate::model::ProviderKind;
+    use crate::records::{ClaudeTranscriptLine, CodexMessagePayload, MessageContent, decode};
+
+    #[test]
+    fn ignores_unknown_fields() {
+        let line: ClaudeTranscriptLine = decode(
+            ProviderKind::Claude,
+            Path::new("/tmp/mock"),
+            1,
+            "$",
+            json!({"type":"user","uuid":"u1","message":{"role":"user","content":"hi","model":"x"}}),
+        )
+        .expect("decode");
+
+        let message = line.message.expect("message");
+        assert_eq!(
+            message.content,
+            Some(MessageContent::Text("hi".to_string()))
+        );
+    }
+
+    #[test]
+    fn reports_field_path_and_line() {
+        let err = decode::<CodexMessagePayload>(
+            ProviderKind::Codex,
+            Path::new("/tmp/mock"),
+            7,
+            "$.payload",
+            json!({"type":"message","content":[]}),
+        )
+        .expect_err("must fail");
+
+        let message = format!("{err}");
+        assert!(message.contains("line 7"));
+        assert!(message.contains("$.payload"));
+        assert!(message.contains("missing field `role`"));
+    }
+}
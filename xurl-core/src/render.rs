@@ -1,12 +1,29 @@
 use std::collections::{HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
 use std::path::Path;
 
 use serde_json::Value;
 
 use crate::error::{Result, XurlError};
 use crate::jsonl;
-use crate::model::{MessageRole, ProviderKind, ThreadMessage};
-use crate::uri::ThreadUri;
+use crate::model::{
+    Annotation, ExtractionMode, MessageRole, ProviderKind, TableFormat, ThreadMessage,
+    ThreadParent, TimelineEntryKind, TimelineEntryView, Warning,
+};
+#[cfg(feature = "fs")]
+use crate::provider::amp;
+use crate::records::{
+    self, AmpMessage, AmpThread, CODEX_ROLLOUT_BOUNDARY, ClaudeTranscriptLine,
+    CodexAgentMessagePayload, CodexCompactedPayload, CodexMessagePayload, CodexRolloutBoundary,
+    CodexRolloutLine, ContentBlock, ContentPart, CopilotEvent, CopilotMessageData, GeminiChat,
+    GeminiMessage, MessageContent, OpencodeRow, PiEntry,
+};
+use crate::renderer::{MarkdownRenderer, Renderer, TableRenderer, TimelineHead};
+#[cfg(feature = "fs")]
+use crate::service::read_thread_raw;
+use crate::timestamp::TimeWindow;
+use crate::uri::{MessageAnchor, ThreadUri, TimelinePage};
+use crate::yaml::YamlMapping;
 
 const TOOL_TYPES: &[&str] = &[
     "tool_call",
@@ -17,77 +34,896 @@ const TOOL_TYPES: &[&str] = &[
     "function_response",
 ];
 const COMPACT_PLACEHOLDER: &str = "Context was compacted.";
+/// Longest part of a tool's input or result shown in its timeline entry.
+const TOOL_OUTPUT_MAX_CHARS: usize = 1000;
 
 enum TimelineEntry {
     Message(ThreadMessage),
-    Compact { summary: Option<String> },
+    Compact {
+        summary: Option<String>,
+    },
+    /// Start of the next rollout when several Codex rollouts are merged.
+    Boundary {
+        path: String,
+    },
+    /// Failed Claude tool results, truncated.
+    ToolError {
+        text: String,
+    },
+    /// An Amp tool invocation and the result answering it.
+    ToolCall(AmpToolCall),
+}
+
+/// An Amp `tool_use` block with the `run` of the `tool_result` whose
+/// `toolUseID` names it, once one is found.
+struct AmpToolCall {
+    id: Option<String>,
+    name: String,
+    input: Option<Value>,
+    run: Option<Value>,
+}
+
+impl AmpToolCall {
+    /// `<name> (<status>)`, then the input and the result (or error), each
+    /// cut to [`TOOL_OUTPUT_MAX_CHARS`].
+    fn text(&self) -> String {
+        let status = match &self.run {
+            Some(run) => run
+                .get("status")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown"),
+            None => "no result",
+        };
+        let mut sections = vec![format!("{} ({status})", self.name)];
+        if let Some(input) = self.input.as_ref().filter(|input| !input.is_null()) {
+            sections.push(format!(
+                "Input:\n{}",
+                truncate_tool_output(&tool_value_text(input))
+            ));
+        }
+        if let Some(run) = &self.run {
+            let (label, value) = match (run.get("result"), run.get("error")) {
+                (_, Some(error)) if !error.is_null() => ("Error", error),
+                (Some(result), _) => ("Result", result),
+                _ => ("Result", &Value::Null),
+            };
+            sections.push(format!(
+                "{label}:\n{}",
+                truncate_tool_output(&tool_value_text(value))
+            ));
+        }
+        sections.join("\n\n")
+    }
+}
+
+/// A tool input or result as text: strings as they are, anything else as
+/// pretty-printed JSON.
+fn tool_value_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.trim().to_string(),
+        _ => serde_json::to_string_pretty(value).unwrap_or_default(),
+    }
+}
+
+/// A timeline entry with the id and timestamp its provider recorded for it,
+/// if any, and where in the thread file it was read from.
+struct SourcedEntry {
+    entry: TimelineEntry,
+    source_id: Option<String>,
+    timestamp: Option<String>,
+    origin: Option<String>,
+    /// Rebuilt from streaming deltas because the final message is missing.
+    partial: bool,
+}
+
+/// What extraction does with records it cannot parse, and the warnings for
+/// the ones it skipped; also how compaction entries are shown and whether
+/// failed tool results and Amp tool calls get entries of their own.
+#[derive(Debug, Clone, Default)]
+pub struct ExtractionPolicy {
+    mode: ExtractionMode,
+    warnings: Vec<Warning>,
+    compact_placeholder: Option<String>,
+    expand_compactions: bool,
+    tool_errors: bool,
+    amp_tools: bool,
+}
+
+impl ExtractionPolicy {
+    pub fn new(mode: ExtractionMode) -> Self {
+        Self {
+            mode,
+            ..Self::default()
+        }
+    }
+
+    /// Text of compaction entries that carry no summary, instead of
+    /// "Context was compacted.".
+    #[must_use]
+    pub fn with_compact_placeholder(mut self, placeholder: Option<String>) -> Self {
+        self.compact_placeholder = placeholder;
+        self
+    }
+
+    /// Show the summary a compaction left instead of a placeholder: Codex
+    /// `compacted` records contribute their `message`, and a compaction
+    /// written as several records (a Codex event and record, a Claude
+    /// boundary and summary) becomes one entry.
+    #[must_use]
+    pub fn with_expanded_compactions(mut self, expand: bool) -> Self {
+        self.expand_compactions = expand;
+        self
+    }
+
+    /// Give each Claude `tool_result` marked `is_error` a "Tool Error"
+    /// entry with the start of its payload, instead of leaving it out.
+    #[must_use]
+    pub fn with_tool_errors(mut self, tool_errors: bool) -> Self {
+        self.tool_errors = tool_errors;
+        self
+    }
+
+    /// Give each Amp `tool_use` a "Tool Call" entry holding its input and
+    /// the result of the `tool_result` paired with it by `toolUseID`,
+    /// instead of leaving both out.
+    #[must_use]
+    pub fn with_amp_tools(mut self, amp_tools: bool) -> Self {
+        self.amp_tools = amp_tools;
+        self
+    }
+
+    fn compact_text(&self, summary: Option<String>) -> String {
+        summary.unwrap_or_else(|| {
+            self.compact_placeholder
+                .clone()
+                .unwrap_or_else(|| COMPACT_PLACEHOLDER.to_string())
+        })
+    }
+
+    /// One `skipped_invalid_record` warning per record skipped so far.
+    pub fn into_warnings(self) -> Vec<Warning> {
+        self.warnings
+    }
+
+    /// Passes `result` through, except that in lenient mode a record that
+    /// does not parse becomes `None` and a warning.
+    fn recover<T>(&mut self, result: Result<T>) -> Result<Option<T>> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(err @ (XurlError::InvalidJsonLine { .. } | XurlError::InvalidRecord { .. }))
+                if self.mode == ExtractionMode::Lenient =>
+            {
+                self.warnings.push(Warning::warn(
+                    "skipped_invalid_record",
+                    format!("skipped {err}"),
+                ));
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Parses line `line_no` of a JSONL transcript. When `pending` (the last
+    /// line, with no newline yet) does not parse, the agent is still writing
+    /// it: in either mode it is left out with a `pending_final_line` warning.
+    fn parse_line(
+        &mut self,
+        path: &Path,
+        line_no: usize,
+        line: &str,
+        pending: bool,
+    ) -> Result<Option<Value>> {
+        match jsonl::parse_json_line(path, line_no, line) {
+            Err(XurlError::InvalidJsonLine { .. }) if pending => {
+                self.warnings.push(Warning::warn(
+                    "pending_final_line",
+                    format!(
+                        "left out line {line_no} of {}: it is still being written",
+                        path.display()
+                    ),
+                ));
+                Ok(None)
+            }
+            result => Ok(self.recover(result)?.flatten()),
+        }
+    }
+}
+
+/// Number of the last line of `raw` when it has no newline yet.
+pub(crate) fn unterminated_line(raw: &str) -> Option<usize> {
+    (!raw.ends_with('\n') && !raw.trim().is_empty()).then(|| raw.lines().count())
+}
+
+/// `<file>:<line>` of an entry read from a JSONL transcript.
+fn line_origin(path: &Path, line_no: usize) -> Option<String> {
+    Some(format!("{}:{line_no}", path.display()))
+}
+
+/// `<file>#/messages/<idx>` (a JSON pointer) of an entry read from a
+/// single-document transcript.
+fn message_pointer_origin(path: &Path, idx: usize) -> Option<String> {
+    Some(format!("{}#/messages/{idx}", path.display()))
 }
 
 pub fn render_markdown(uri: &ThreadUri, source_path: &Path, raw_jsonl: &str) -> Result<String> {
-    let entries = extract_timeline_entries(
-        uri.provider,
+    render_markdown_with(uri, source_path, raw_jsonl, &TimelineOptions::default())
+}
+
+/// Extras applied while rendering a timeline.
+#[derive(Debug, Clone, Default)]
+pub struct TimelineOptions<'a> {
+    /// Shown as callouts under the entries they refer to.
+    pub annotations: &'a [Annotation],
+    /// Render only this page; entries keep their thread-wide numbers.
+    pub page: Option<TimelinePage>,
+    /// When the URI has a fragment, entries shown on each side of the entry
+    /// it points at. Takes precedence over `page`.
+    pub context: usize,
+    /// Render only entries of these kinds, keeping their thread-wide
+    /// numbers; empty renders every entry. Applied after `page` and the
+    /// fragment pick the entries.
+    pub kinds: &'a [TimelineEntryKind],
+    /// For a subagent's own thread, the thread that spawned it.
+    pub parent: Option<&'a ThreadParent>,
+    /// Show the file and line (or JSON pointer) each entry was read from.
+    pub provenance: bool,
+    /// How records are extracted into entries; each render starts from a
+    /// copy of it.
+    pub extraction: ExtractionPolicy,
+    /// Collapse runs of entries with the same kind and text into their first
+    /// entry; see [`collapse_repeats`].
+    pub dedupe: bool,
+}
+
+pub const DEFAULT_TIMELINE_CONTEXT: usize = 2;
+pub const DEFAULT_PI_PREVIEW_CHARS: usize = 96;
+pub const DEFAULT_AMP_HANDOFF_DEPTH: usize = 3;
+
+/// How threads are read and rendered once resolved; which files a URI names
+/// is up to `ProviderRoots`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadOptions {
+    /// Render every branch of a Pi session's entry tree instead of one path.
+    pub pi_all_branches: bool,
+    /// Longest preview in the Pi entry index; `None` keeps previews whole.
+    pub pi_preview_chars: Option<usize>,
+    /// How many levels of Amp handoff chains (main -> child -> grandchild)
+    /// the subagent index follows.
+    pub amp_handoff_depth: usize,
+    /// Only list subagents active inside this window.
+    pub subagent_window: TimeWindow,
+    /// Render only this page of thread timelines.
+    pub timeline_page: Option<TimelinePage>,
+    /// Entries shown on each side of the entry a URI fragment points at.
+    pub timeline_context: usize,
+    /// Only render timeline entries of these kinds; empty renders all.
+    pub timeline_kinds: Vec<TimelineEntryKind>,
+    /// Show under each rendered timeline entry the file and line (or JSON
+    /// pointer) it was read from.
+    pub timeline_provenance: bool,
+    /// Collapse runs of repeated timeline entries, such as assistant replies
+    /// logged again after a retry, into one entry with a repeat count.
+    pub timeline_dedupe: bool,
+    /// What reads do with transcript records that do not parse.
+    pub extraction_mode: ExtractionMode,
+    /// Text of compaction entries without a summary, instead of "Context
+    /// was compacted.".
+    pub compact_placeholder: Option<String>,
+    /// Show the summaries compactions left instead of placeholders.
+    pub expand_compactions: bool,
+    /// Give failed Claude tool results timeline entries of their own.
+    pub tool_errors: bool,
+    /// Give Amp tool calls timeline entries paired with their results.
+    pub amp_tools: bool,
+    /// Read thread files again until two reads in a row see the same length,
+    /// for files an agent is still appending to.
+    pub consistent_reads: bool,
+    /// Add the `fingerprint` of a main thread's messages to its head, which
+    /// extracts every message once more.
+    pub fingerprint: bool,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        Self {
+            pi_all_branches: false,
+            pi_preview_chars: Some(DEFAULT_PI_PREVIEW_CHARS),
+            amp_handoff_depth: DEFAULT_AMP_HANDOFF_DEPTH,
+            subagent_window: TimeWindow::default(),
+            timeline_page: None,
+            timeline_context: DEFAULT_TIMELINE_CONTEXT,
+            timeline_kinds: Vec::new(),
+            timeline_provenance: false,
+            timeline_dedupe: false,
+            extraction_mode: ExtractionMode::Strict,
+            compact_placeholder: None,
+            expand_compactions: false,
+            tool_errors: false,
+            amp_tools: false,
+            consistent_reads: false,
+            fingerprint: false,
+        }
+    }
+}
+
+impl ReadOptions {
+    /// The defaults, with extraction settings from the environment:
+    /// `XURL_EXTRACTION` (`strict` or `lenient`), `XURL_COMPACT_PLACEHOLDER`,
+    /// and `XURL_EXPAND_COMPACTIONS`, `XURL_TOOL_ERRORS`, and
+    /// `XURL_AMP_TOOLS` set to anything but `0`.
+    pub fn from_env() -> Self {
+        Self::from_vars(|name| std::env::var_os(name))
+    }
+
+    /// Like [`Self::from_env`], with the variables `var` looks up.
+    pub fn from_vars(var: impl Fn(&str) -> Option<OsString>) -> Self {
+        let flag = |name: &str| {
+            var(name).is_some_and(|value| !matches!(value.to_str().map(str::trim), Some("" | "0")))
+        };
+        Self {
+            extraction_mode: match var("XURL_EXTRACTION").as_deref().and_then(OsStr::to_str) {
+                Some("lenient") => ExtractionMode::Lenient,
+                _ => ExtractionMode::Strict,
+            },
+            compact_placeholder: var("XURL_COMPACT_PLACEHOLDER")
+                .and_then(|text| text.into_string().ok())
+                .filter(|text| !text.trim().is_empty()),
+            expand_compactions: flag("XURL_EXPAND_COMPACTIONS"),
+            tool_errors: flag("XURL_TOOL_ERRORS"),
+            amp_tools: flag("XURL_AMP_TOOLS"),
+            ..Self::default()
+        }
+    }
+
+    #[must_use]
+    pub fn with_pi_all_branches(mut self, all_branches: bool) -> Self {
+        self.pi_all_branches = all_branches;
+        self
+    }
+
+    #[must_use]
+    pub fn with_pi_preview_chars(mut self, max_chars: Option<usize>) -> Self {
+        self.pi_preview_chars = max_chars;
+        self
+    }
+
+    #[must_use]
+    pub fn with_amp_handoff_depth(mut self, depth: usize) -> Self {
+        self.amp_handoff_depth = depth.max(1);
+        self
+    }
+
+    #[must_use]
+    pub fn with_subagent_window(mut self, window: TimeWindow) -> Self {
+        self.subagent_window = window;
+        self
+    }
+
+    #[must_use]
+    pub fn with_timeline_page(mut self, page: Option<TimelinePage>) -> Self {
+        self.timeline_page = page;
+        self
+    }
+
+    #[must_use]
+    pub fn with_timeline_context(mut self, context: usize) -> Self {
+        self.timeline_context = context;
+        self
+    }
+
+    #[must_use]
+    pub fn with_timeline_kinds(mut self, kinds: Vec<TimelineEntryKind>) -> Self {
+        self.timeline_kinds = kinds;
+        self
+    }
+
+    #[must_use]
+    pub fn with_timeline_provenance(mut self, provenance: bool) -> Self {
+        self.timeline_provenance = provenance;
+        self
+    }
+
+    #[must_use]
+    pub fn with_timeline_dedupe(mut self, dedupe: bool) -> Self {
+        self.timeline_dedupe = dedupe;
+        self
+    }
+
+    #[must_use]
+    pub fn with_extraction_mode(mut self, mode: ExtractionMode) -> Self {
+        self.extraction_mode = mode;
+        self
+    }
+
+    #[must_use]
+    pub fn with_compact_placeholder(mut self, placeholder: Option<String>) -> Self {
+        self.compact_placeholder = placeholder;
+        self
+    }
+
+    #[must_use]
+    pub fn with_expand_compactions(mut self, expand: bool) -> Self {
+        self.expand_compactions = expand;
+        self
+    }
+
+    #[must_use]
+    pub fn with_tool_errors(mut self, tool_errors: bool) -> Self {
+        self.tool_errors = tool_errors;
+        self
+    }
+
+    #[must_use]
+    pub fn with_amp_tools(mut self, amp_tools: bool) -> Self {
+        self.amp_tools = amp_tools;
+        self
+    }
+
+    #[must_use]
+    pub fn with_consistent_reads(mut self, consistent: bool) -> Self {
+        self.consistent_reads = consistent;
+        self
+    }
+
+    #[must_use]
+    pub fn with_fingerprint(mut self, fingerprint: bool) -> Self {
+        self.fingerprint = fingerprint;
+        self
+    }
+
+    /// A fresh policy for one extraction under this `extraction_mode`,
+    /// showing compactions, tool errors, and Amp tool calls as configured.
+    pub fn extraction_policy(&self) -> ExtractionPolicy {
+        ExtractionPolicy::new(self.extraction_mode)
+            .with_compact_placeholder(self.compact_placeholder.clone())
+            .with_expanded_compactions(self.expand_compactions)
+            .with_tool_errors(self.tool_errors)
+            .with_amp_tools(self.amp_tools)
+    }
+
+    /// These options as [`TimelineOptions`] for rendering with `annotations`
+    /// under `parent`.
+    pub fn timeline_options<'a>(
+        &'a self,
+        annotations: &'a [Annotation],
+        parent: Option<&'a ThreadParent>,
+    ) -> TimelineOptions<'a> {
+        TimelineOptions {
+            annotations,
+            page: self.timeline_page,
+            context: self.timeline_context,
+            kinds: &self.timeline_kinds,
+            parent,
+            provenance: self.timeline_provenance,
+            extraction: self.extraction_policy(),
+            dedupe: self.timeline_dedupe,
+        }
+    }
+}
+
+/// Like [`render_markdown`], with annotations and paging from `options`.
+pub fn render_markdown_with(
+    uri: &ThreadUri,
+    source_path: &Path,
+    raw_jsonl: &str,
+    options: &TimelineOptions<'_>,
+) -> Result<String> {
+    render_timeline(
+        uri,
         source_path,
         raw_jsonl,
-        &uri.session_id,
-        uri.agent_id.as_deref(),
-    )?;
+        options,
+        &mut MarkdownRenderer::new(options.provenance),
+    )
+}
 
-    let mut output = String::new();
-    let thread_uri = uri.as_agents_string();
-    let source = source_path.to_string_lossy();
-    output.push_str("---\n");
-    output.push_str(&format!("uri: '{}'\n", yaml_single_quoted(&thread_uri)));
-    output.push_str(&format!(
-        "thread_source: '{}'\n",
-        yaml_single_quoted(source.as_ref())
-    ));
-    output.push_str("---\n\n");
+/// Picks the timeline entries `options` and the URI fragment select and
+/// renders them with `renderer`.
+pub fn render_timeline(
+    uri: &ThreadUri,
+    source_path: &Path,
+    raw_jsonl: &str,
+    options: &TimelineOptions<'_>,
+    renderer: &mut dyn Renderer,
+) -> Result<String> {
+    let mut policy = options.extraction.clone();
+    let mut entries = timeline_entries(uri, source_path, raw_jsonl, &mut policy)?;
+
+    let (range, focus) = match (&uri.fragment, options.page) {
+        _ if entries.is_empty() => (0..0, None),
+        (Some(anchor), _) => {
+            let idx = entry_position(uri, &entries, anchor)?;
+            let range = idx.saturating_sub(options.context)
+                ..idx
+                    .saturating_add(options.context.saturating_add(1))
+                    .min(entries.len());
+            (range, Some(&entries[idx]))
+        }
+        (None, Some(page)) => (page.range(entries.len()), None),
+        (None, None) => (0..entries.len(), None),
+    };
+    renderer.render_head(&TimelineHead {
+        uri,
+        thread_source: source_path,
+        parent: options.parent,
+        total: entries.len(),
+        range: range.clone(),
+        focus,
+    });
+
+    let mut shown = entries.drain(range).collect::<Vec<_>>();
+    if options.dedupe {
+        shown = collapse_repeats(shown);
+    }
+    for entry in shown
+        .iter()
+        .filter(|entry| options.kinds.is_empty() || options.kinds.contains(&entry.kind))
+    {
+        // Notes left on collapsed copies stay with the entry they became.
+        let numbers = entry.number..entry.number + entry.repeats.unwrap_or(1);
+        let notes = options
+            .annotations
+            .iter()
+            .filter(|annotation| numbers.contains(&annotation.message))
+            .collect::<Vec<_>>();
+        renderer.render_entry(entry, &notes);
+    }
+
+    Ok(renderer.finish())
+}
+
+/// Renders every branch of a Pi session's entry tree, parents before
+/// children. Where the tree forks, each branch becomes a nested item; the
+/// branch holding the latest entry (the one plain reads follow) is marked.
+pub fn render_pi_branches_markdown(
+    uri: &ThreadUri,
+    source_path: &Path,
+    raw_jsonl: &str,
+    policy: &mut ExtractionPolicy,
+) -> Result<String> {
+    let entries = read_pi_entries(source_path, raw_jsonl, policy)?
+        .into_iter()
+        .map(|(id, _, entry)| (id, entry))
+        .collect::<Vec<_>>();
+    let placeholder = policy.compact_text(None);
+
+    let mut frontmatter = YamlMapping::new();
+    frontmatter.insert("uri", uri.as_agents_string());
+    frontmatter.insert("thread_source", source_path.to_string_lossy().into_owned());
+
+    let mut output = frontmatter.to_frontmatter();
+    output.push('\n');
     output.push_str("# Thread\n\n");
-    output.push_str("## Timeline\n\n");
+    output.push_str("## All Branches\n\n");
 
     if entries.is_empty() {
-        output.push_str("_No user/assistant messages or compact events found._\n");
+        output.push_str("_No entries found._\n");
         return Ok(output);
     }
 
-    for (idx, entry) in entries.iter().enumerate() {
-        let title = match entry {
-            TimelineEntry::Message(message) => match message.role {
-                MessageRole::User => "User",
-                MessageRole::Assistant => "Assistant",
+    let positions = entries
+        .iter()
+        .enumerate()
+        .map(|(idx, (id, _))| (id.as_str(), idx))
+        .collect::<HashMap<_, _>>();
+    let parent_of = |idx: usize| {
+        entries[idx]
+            .1
+            .parent_id
+            .as_deref()
+            .and_then(|parent| positions.get(parent).copied())
+            .filter(|parent| *parent != idx)
+    };
+
+    // Entries whose parent is missing start trees of their own.
+    let mut tree_roots = Vec::new();
+    let mut children = vec![Vec::new(); entries.len()];
+    for idx in 0..entries.len() {
+        match parent_of(idx) {
+            Some(parent) => children[parent].push(idx),
+            None => tree_roots.push(idx),
+        }
+    }
+
+    let mut latest = vec![false; entries.len()];
+    let mut current = Some(entries.len() - 1);
+    while let Some(idx) = current.filter(|idx| !latest[*idx]) {
+        latest[idx] = true;
+        current = parent_of(idx);
+    }
+
+    output.push_str(&format!(
+        "_{} entries in {} branches. Read one branch with `agents://pi/{}/<entry_id>`._\n\n",
+        entries.len(),
+        children
+            .iter()
+            .filter(|children| children.is_empty())
+            .count(),
+        uri.session_id
+    ));
+
+    let mut stack = Vec::new();
+    push_pi_branches(&mut stack, &tree_roots, 0);
+    while let Some(item) = stack.pop() {
+        match item {
+            PiTreeItem::Branch {
+                idx,
+                number,
+                total,
+                depth,
+            } => {
+                let marker = if latest[idx] { " (latest)" } else { "" };
+                output.push_str(&format!(
+                    "{}- **Branch {number}/{total}**{marker}\n\n",
+                    "  ".repeat(depth)
+                ));
+                stack.push(PiTreeItem::Entry {
+                    idx,
+                    depth: depth + 1,
+                });
+            }
+            PiTreeItem::Entry { idx, depth } => {
+                let (id, entry) = &entries[idx];
+                push_pi_branch_entry(&mut output, id, entry, depth, &placeholder);
+                push_pi_branches(&mut stack, &children[idx], depth);
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// Pending work while walking a Pi entry tree depth-first.
+enum PiTreeItem {
+    Entry {
+        idx: usize,
+        depth: usize,
+    },
+    Branch {
+        idx: usize,
+        number: usize,
+        total: usize,
+        depth: usize,
+    },
+}
+
+/// Queues `children` so they pop in file order: a single child continues the
+/// current branch, several children each open a numbered branch.
+fn push_pi_branches(stack: &mut Vec<PiTreeItem>, children: &[usize], depth: usize) {
+    if let [idx] = children {
+        stack.push(PiTreeItem::Entry { idx: *idx, depth });
+        return;
+    }
+    for (number, idx) in children.iter().enumerate().rev() {
+        stack.push(PiTreeItem::Branch {
+            idx: *idx,
+            number: number + 1,
+            total: children.len(),
+            depth,
+        });
+    }
+}
+
+fn push_pi_branch_entry(
+    output: &mut String,
+    id: &str,
+    entry: &PiEntry,
+    depth: usize,
+    placeholder: &str,
+) {
+    let (title, text) = match extract_pi_entry(entry) {
+        Some(TimelineEntry::Message(message)) => match message.role {
+            MessageRole::User => ("User", message.text),
+            MessageRole::Assistant => ("Assistant", message.text),
+        },
+        Some(TimelineEntry::Compact { summary }) => (
+            if entry.entry_type == "branch_summary" {
+                "Branch Summary"
+            } else {
+                "Context Compacted"
             },
-            TimelineEntry::Compact { .. } => "Context Compacted",
-        };
+            summary.unwrap_or_else(|| placeholder.to_string()),
+        ),
+        Some(
+            TimelineEntry::Boundary { .. }
+            | TimelineEntry::ToolError { .. }
+            | TimelineEntry::ToolCall(_),
+        )
+        | None => return,
+    };
+
+    let indent = "  ".repeat(depth);
+    output.push_str(&format!("{indent}- **{title}** `{id}`"));
+    if let Some(kept) = &entry.first_kept_entry_id {
+        output.push_str(&format!(" (keeps entries from `{kept}`)"));
+    }
+    output.push_str("\n\n");
+    for line in text.trim().lines() {
+        if !line.trim().is_empty() {
+            output.push_str(&format!("{indent}  {line}"));
+        }
+        output.push('\n');
+    }
+    output.push('\n');
+}
+
+/// Renders `entries` as a table; see [`TableRenderer`].
+pub fn render_timeline_table(entries: &[TimelineEntryView], format: TableFormat) -> String {
+    let mut renderer = TableRenderer::new(format);
+    for entry in entries {
+        renderer.render_entry(entry, &[]);
+    }
+    renderer.finish()
+}
 
-        output.push_str(&format!("## {}. {}\n\n", idx + 1, title));
-        match entry {
-            TimelineEntry::Message(message) => output.push_str(message.text.trim()),
+/// Every entry of the rendered timeline of `uri`, with its number, anchor,
+/// and stable id.
+pub fn timeline_entries(
+    uri: &ThreadUri,
+    source_path: &Path,
+    raw_jsonl: &str,
+    policy: &mut ExtractionPolicy,
+) -> Result<Vec<TimelineEntryView>> {
+    let base_uri = uri.as_agents_string();
+    Ok(extract_timeline_entries(
+        uri.format(),
+        source_path,
+        raw_jsonl,
+        &uri.session_id,
+        uri.agent_id.as_deref(),
+        policy,
+    )?
+    .into_iter()
+    .enumerate()
+    .map(|(idx, sourced)| {
+        let number = idx + 1;
+        let (kind, text) = match sourced.entry {
+            TimelineEntry::Message(message) => match message.role {
+                MessageRole::User => (TimelineEntryKind::User, message.text),
+                MessageRole::Assistant => (TimelineEntryKind::Assistant, message.text),
+            },
             TimelineEntry::Compact { summary } => {
-                let summary = summary.as_deref().unwrap_or(COMPACT_PLACEHOLDER);
-                output.push_str(summary.trim());
+                (TimelineEntryKind::Compact, policy.compact_text(summary))
             }
+            TimelineEntry::Boundary { path } => (TimelineEntryKind::RolloutBoundary, path),
+            TimelineEntry::ToolError { text } => (TimelineEntryKind::ToolError, text),
+            TimelineEntry::ToolCall(call) => (TimelineEntryKind::ToolCall, call.text()),
+        };
+        let anchor = MessageAnchor::Number(number).to_string();
+        let id = sourced
+            .source_id
+            .unwrap_or_else(|| content_id(number, kind, &text));
+        TimelineEntryView {
+            uri: format!("{base_uri}#{anchor}"),
+            number,
+            anchor,
+            id,
+            kind,
+            text,
+            timestamp: sourced.timestamp,
+            source: sourced.origin,
+            partial: sourced.partial,
+            repeats: None,
+        }
+    })
+    .collect())
+}
+
+/// The timeline entry of `uri` that `anchor` points at.
+pub fn find_timeline_entry(
+    uri: &ThreadUri,
+    source_path: &Path,
+    raw_jsonl: &str,
+    anchor: &MessageAnchor,
+    policy: &mut ExtractionPolicy,
+) -> Result<TimelineEntryView> {
+    let mut entries = timeline_entries(uri, source_path, raw_jsonl, policy)?;
+    let idx = entry_position(uri, &entries, anchor)?;
+    Ok(entries.swap_remove(idx))
+}
+
+fn entry_position(
+    uri: &ThreadUri,
+    entries: &[TimelineEntryView],
+    anchor: &MessageAnchor,
+) -> Result<usize> {
+    let position = match anchor {
+        MessageAnchor::Number(number) => (*number <= entries.len()).then(|| number - 1),
+        MessageAnchor::Id(id) => entries.iter().position(|entry| entry.id == *id),
+    };
+    position.ok_or_else(|| XurlError::EntryNotFound {
+        provider: uri.provider.to_string(),
+        session_id: uri.session_id.clone(),
+        entry_id: anchor.to_string(),
+    })
+}
+
+/// Id for entries whose provider records none: FNV-1a over the entry's
+/// number, kind, and text, so it is the same on every read of the thread.
+fn content_id(number: usize, kind: TimelineEntryKind, text: &str) -> String {
+    format!(
+        "{:016x}",
+        fnv1a(format!("{number}\0{kind}\0{text}").bytes())
+    )
+}
+
+fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    bytes.into_iter().fold(OFFSET, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// Collapses each run of consecutive entries with the same kind and nearly
+/// the same text, as providers log when a turn is retried, into the run's
+/// first entry with [`TimelineEntryView::repeats`] set. Texts are compared
+/// by a hash that ignores case and differences in whitespace. Entries keep
+/// their numbers, so the collapsed copies leave gaps. Rollout boundaries
+/// are never collapsed.
+pub fn collapse_repeats(entries: Vec<TimelineEntryView>) -> Vec<TimelineEntryView> {
+    let mut collapsed: Vec<(Option<u64>, TimelineEntryView)> = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let key = (entry.kind != TimelineEntryKind::RolloutBoundary)
+            .then(|| repeat_key(entry.kind, &entry.text));
+        if let Some((Some(previous), first)) = collapsed.last_mut()
+            && key == Some(*previous)
+        {
+            first.repeats = Some(first.repeats.unwrap_or(1) + 1);
+            continue;
         }
-        output.push_str("\n\n");
+        collapsed.push((key, entry));
     }
+    collapsed.into_iter().map(|(_, entry)| entry).collect()
+}
 
-    Ok(output)
+fn repeat_key(kind: TimelineEntryKind, text: &str) -> u64 {
+    let normalized = text
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ");
+    fnv1a(format!("{kind}\0{normalized}").bytes())
 }
 
-fn yaml_single_quoted(value: &str) -> String {
-    value.replace('\'', "''")
+/// Number of entries in the rendered timeline of `uri`.
+pub fn count_timeline_entries(
+    uri: &ThreadUri,
+    source_path: &Path,
+    raw_jsonl: &str,
+    policy: &mut ExtractionPolicy,
+) -> Result<usize> {
+    Ok(extract_timeline_entries(
+        uri.format(),
+        source_path,
+        raw_jsonl,
+        &uri.session_id,
+        uri.agent_id.as_deref(),
+        policy,
+    )?
+    .len())
 }
 
 pub fn extract_messages(
     provider: ProviderKind,
     path: &Path,
     raw_jsonl: &str,
+    policy: &mut ExtractionPolicy,
 ) -> Result<Vec<ThreadMessage>> {
     Ok(
-        extract_timeline_entries(provider, path, raw_jsonl, "", None)?
+        extract_timeline_entries(provider, path, raw_jsonl, "", None, policy)?
             .into_iter()
-            .filter_map(|entry| match entry {
-                TimelineEntry::Message(message) => Some(message),
-                TimelineEntry::Compact { .. } => None,
+            .filter_map(|sourced| match sourced.entry {
+                TimelineEntry::Message(message) => Some(ThreadMessage {
+                    timestamp: sourced.timestamp,
+                    ..message
+                }),
+                TimelineEntry::Compact { .. }
+                | TimelineEntry::Boundary { .. }
+                | TimelineEntry::ToolError { .. }
+                | TimelineEntry::ToolCall(_) => None,
             })
             .collect(),
     )
@@ -99,20 +935,25 @@ fn extract_timeline_entries(
     raw_jsonl: &str,
     session_id: &str,
     target_entry_id: Option<&str>,
-) -> Result<Vec<TimelineEntry>> {
+    policy: &mut ExtractionPolicy,
+) -> Result<Vec<SourcedEntry>> {
     if provider == ProviderKind::Amp {
-        return Ok(messages_to_entries(extract_amp_messages(path, raw_jsonl)?));
+        return extract_amp_entries(path, raw_jsonl, policy);
     }
     if provider == ProviderKind::Gemini {
-        return Ok(messages_to_entries(extract_gemini_messages(
-            path, raw_jsonl,
-        )?));
+        return extract_gemini_entries(path, raw_jsonl, policy);
     }
     if provider == ProviderKind::Pi {
-        return extract_pi_entries(path, raw_jsonl, session_id, target_entry_id);
+        return extract_pi_entries(path, raw_jsonl, session_id, target_entry_id, policy);
     }
 
     let mut entries = Vec::new();
+    // Merged Codex rollouts are concatenated after boundary lines; entries
+    // after one are located in the rollout it names.
+    let mut file = (path.to_path_buf(), 0);
+    let pending = unterminated_line(raw_jsonl);
+    // Codex reply streamed as deltas whose final message has not been seen.
+    let mut partial: Option<SourcedEntry> = None;
 
     for (line_idx, line) in raw_jsonl.lines().enumerate() {
         let line_no = line_idx + 1;
@@ -121,29 +962,110 @@ fn extract_timeline_entries(
             continue;
         }
 
-        let Some(value) = jsonl::parse_json_line(path, line_no, trimmed)? else {
+        let Some(value) = policy.parse_line(path, line_no, trimmed, pending == Some(line_no))?
+        else {
             continue;
         };
 
-        let extracted = match provider {
-            ProviderKind::Amp => None,
-            ProviderKind::Codex => extract_codex_entry(&value),
-            ProviderKind::Claude => extract_claude_entry(&value),
-            ProviderKind::Gemini => None,
-            ProviderKind::Pi => None,
-            ProviderKind::Opencode => extract_opencode_message(&value).map(TimelineEntry::Message),
-        };
+        let source_id = match provider {
+            ProviderKind::Claude => value.get("uuid"),
+            ProviderKind::Opencode | ProviderKind::Copilot => value.get("id"),
+            _ => None,
+        }
+        .and_then(Value::as_str)
+        .map(ToString::to_string);
+        let timestamp = value
+            .get("timestamp")
+            .and_then(Value::as_str)
+            .map(ToString::to_string);
+        if provider == ProviderKind::Codex
+            && let Some(delta) = codex_message_delta(&value)
+        {
+            let reply = partial.get_or_insert_with(|| SourcedEntry {
+                entry: TimelineEntry::Message(ThreadMessage {
+                    role: MessageRole::Assistant,
+                    text: String::new(),
+                    timestamp: None,
+                }),
+                source_id: None,
+                timestamp: timestamp.clone(),
+                origin: line_origin(&file.0, line_no - file.1),
+                partial: true,
+            });
+            if let TimelineEntry::Message(message) = &mut reply.entry {
+                message.text.push_str(delta);
+            }
+            continue;
+        }
+        let extracted = policy.recover(match provider {
+            ProviderKind::Amp | ProviderKind::Gemini | ProviderKind::Pi | ProviderKind::File => {
+                Ok(None)
+            }
+            ProviderKind::Codex => {
+                extract_codex_entry(path, line_no, value, policy.expand_compactions)
+            }
+            ProviderKind::Claude => extract_claude_entry(path, line_no, value, policy.tool_errors),
+            ProviderKind::Opencode => extract_opencode_message(path, line_no, value)
+                .map(|message| message.map(TimelineEntry::Message)),
+            ProviderKind::Copilot => extract_copilot_message(path, line_no, value)
+                .map(|message| message.map(TimelineEntry::Message)),
+        })?;
 
-        if let Some(entry) = extracted {
-            entries.push(entry);
+        let Some(entry) = extracted.flatten() else {
+            continue;
+        };
+        // The final message replaces its deltas; anything else means the
+        // reply was never finished.
+        match &entry {
+            TimelineEntry::Message(message) if message.role == MessageRole::Assistant => {
+                partial = None;
+            }
+            _ => entries.extend(partial.take().filter(has_text)),
+        }
+        if policy.expand_compactions
+            && let TimelineEntry::Compact { summary } = &entry
+            && let Some(SourcedEntry {
+                entry: TimelineEntry::Compact { summary: previous },
+                ..
+            }) = entries.last_mut()
+        {
+            // Records of one compaction follow each other with no message
+            // in between; keep the first and the summary whichever has.
+            if previous.is_none() {
+                previous.clone_from(summary);
+            }
+            continue;
         }
+        let origin = match &entry {
+            TimelineEntry::Boundary { path } => {
+                file = (path.into(), line_no);
+                None
+            }
+            _ => line_origin(&file.0, line_no - file.1),
+        };
+        entries.push(SourcedEntry {
+            entry,
+            source_id,
+            timestamp,
+            origin,
+            partial: false,
+        });
     }
+    entries.extend(partial.filter(has_text));
 
     Ok(entries)
 }
 
-fn messages_to_entries(messages: Vec<ThreadMessage>) -> Vec<TimelineEntry> {
-    messages.into_iter().map(TimelineEntry::Message).collect()
+fn has_text(sourced: &SourcedEntry) -> bool {
+    matches!(&sourced.entry, TimelineEntry::Message(message) if !message.text.trim().is_empty())
+}
+
+fn non_empty_message(role: MessageRole, text: String) -> Option<ThreadMessage> {
+    (!text.trim().is_empty()).then_some(ThreadMessage {
+        role,
+        text,
+        timestamp: None,
+    })
 }
 
 fn extract_pi_entries(
@@ -151,35 +1073,14 @@ fn extract_pi_entries(
     raw_jsonl: &str,
     session_id: &str,
     target_entry_id: Option<&str>,
-) -> Result<Vec<TimelineEntry>> {
-    let mut entries_by_id = HashMap::<String, Value>::new();
+    policy: &mut ExtractionPolicy,
+) -> Result<Vec<SourcedEntry>> {
+    let mut entries_by_id = HashMap::<String, (usize, PiEntry)>::new();
     let mut last_entry_id = None::<String>;
 
-    for (line_idx, line) in raw_jsonl.lines().enumerate() {
-        let line_no = line_idx + 1;
-        let trimmed = line.trim();
-        if trimmed.is_empty() {
-            continue;
-        }
-
-        let Some(value) = jsonl::parse_json_line(path, line_no, trimmed)? else {
-            continue;
-        };
-
-        if value.get("type").and_then(Value::as_str) == Some("session") {
-            continue;
-        }
-
-        let Some(id) = value
-            .get("id")
-            .and_then(Value::as_str)
-            .map(ToString::to_string)
-        else {
-            continue;
-        };
-
+    for (id, line_no, entry) in read_pi_entries(path, raw_jsonl, policy)? {
         last_entry_id = Some(id.clone());
-        entries_by_id.insert(id, value);
+        entries_by_id.insert(id, (line_no, entry));
     }
 
     if entries_by_id.is_empty() {
@@ -208,324 +1109,514 @@ fn extract_pi_entries(
             break;
         }
 
-        let Some(entry) = entries_by_id.get(&entry_id) else {
+        let Some((_, entry)) = entries_by_id.get(&entry_id) else {
             break;
         };
         path_ids.push(entry_id);
 
-        current = entry
-            .get("parentId")
-            .and_then(Value::as_str)
-            .map(ToString::to_string);
+        current = entry.parent_id.clone();
     }
 
     path_ids.reverse();
 
     let mut entries = Vec::new();
     for entry_id in path_ids {
-        let Some(entry) = entries_by_id.get(&entry_id) else {
+        let Some((line_no, entry)) = entries_by_id.get(&entry_id) else {
             continue;
         };
         if let Some(timeline_entry) = extract_pi_entry(entry) {
-            entries.push(timeline_entry);
+            entries.push(SourcedEntry {
+                entry: timeline_entry,
+                source_id: Some(entry_id),
+                timestamp: entry.timestamp.clone(),
+                origin: line_origin(path, *line_no),
+                partial: false,
+            });
         }
     }
 
     Ok(entries)
 }
 
-fn extract_pi_entry(value: &Value) -> Option<TimelineEntry> {
-    let entry_type = value.get("type").and_then(Value::as_str)?;
+/// Format version of a Pi session, from the `version` of its `session`
+/// header. Version 1 headers have none.
+pub(crate) fn pi_session_version(raw_jsonl: &str) -> u64 {
+    raw_jsonl
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .and_then(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|header| header.get("type").and_then(Value::as_str) == Some("session"))
+        .and_then(|header| header.get("version")?.as_u64())
+        .unwrap_or(1)
+}
 
-    if entry_type == "message" {
-        let message = value.get("message")?;
-        let role = message
-            .get("role")
-            .and_then(Value::as_str)
-            .and_then(parse_role)?;
-        let text = extract_text(message.get("content"));
-        if text.trim().is_empty() {
-            return None;
+/// Entries of a Pi session file that carry an id, with their line numbers, in
+/// file order, without the leading `session` header. Version 1 sessions
+/// predate entry ids; as in Pi's own migration, each of their entries
+/// continues the one before it, under the id `line-<N>`.
+fn read_pi_entries(
+    path: &Path,
+    raw_jsonl: &str,
+    policy: &mut ExtractionPolicy,
+) -> Result<Vec<(String, usize, PiEntry)>> {
+    let mut entries = Vec::new();
+    let linear = pi_session_version(raw_jsonl) < 2;
+    let mut previous_id = None;
+    let pending = unterminated_line(raw_jsonl);
+    for (line_idx, line) in raw_jsonl.lines().enumerate() {
+        let line_no = line_idx + 1;
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
         }
 
-        return Some(TimelineEntry::Message(ThreadMessage { role, text }));
+        let Some(value) = policy.parse_line(path, line_no, trimmed, pending == Some(line_no))?
+        else {
+            continue;
+        };
+        let Some(entry) = policy.recover(records::decode::<PiEntry>(
+            ProviderKind::Pi,
+            path,
+            line_no,
+            "$",
+            value,
+        ))?
+        else {
+            continue;
+        };
+
+        if entry.entry_type == "session" {
+            continue;
+        }
+        let mut entry = entry;
+        if linear && entry.id.is_none() {
+            entry.id = Some(format!("line-{line_no}"));
+            entry.parent_id.clone_from(&previous_id);
+        }
+        if let Some(id) = entry.id.clone() {
+            previous_id = Some(id.clone());
+            entries.push((id, line_no, entry));
+        }
     }
+    Ok(entries)
+}
 
-    if entry_type == "compaction" || entry_type == "branch_summary" {
-        let summary = value
-            .get("summary")
-            .and_then(Value::as_str)
-            .map(ToString::to_string);
-        return Some(TimelineEntry::Compact { summary });
+fn extract_pi_entry(entry: &PiEntry) -> Option<TimelineEntry> {
+    match entry.entry_type.as_str() {
+        "message" => {
+            let message = entry.message.as_ref()?;
+            let role = parse_role(&message.role)?;
+            non_empty_message(role, extract_text(message.content.as_ref()))
+                .map(TimelineEntry::Message)
+        }
+        "compaction" | "branch_summary" => Some(TimelineEntry::Compact {
+            summary: entry.summary.clone(),
+        }),
+        _ => None,
     }
+}
 
-    None
+fn parse_json_document(path: &Path, raw_json: &str) -> Result<Value> {
+    serde_json::from_str::<Value>(raw_json).map_err(|source| XurlError::InvalidJsonLine {
+        path: path.to_path_buf(),
+        line: 1,
+        source,
+    })
 }
 
-fn extract_amp_messages(path: &Path, raw_json: &str) -> Result<Vec<ThreadMessage>> {
-    let value =
-        serde_json::from_str::<Value>(raw_json).map_err(|source| XurlError::InvalidJsonLine {
-            path: path.to_path_buf(),
-            line: 1,
-            source,
-        })?;
+/// Messages of an Amp thread. A thread in the directory layout keeps them in
+/// shards next to its `thread.json`, which are read in order after any the
+/// thread file itself holds. Without the `fs` feature only `raw_json` is
+/// read. Tool calls are paired with their results once every message is
+/// read, since a result may land in a later shard than its call.
+fn extract_amp_entries(
+    path: &Path,
+    raw_json: &str,
+    policy: &mut ExtractionPolicy,
+) -> Result<Vec<SourcedEntry>> {
+    let mut runs = HashMap::new();
+    #[cfg_attr(not(feature = "fs"), allow(unused_mut))]
+    let mut entries = extract_amp_document_entries(path, raw_json, policy, &mut runs)?;
+    #[cfg(feature = "fs")]
+    for shard in amp::message_shards(path) {
+        let raw = read_thread_raw(&shard)?;
+        entries.extend(extract_amp_document_entries(
+            &shard, &raw, policy, &mut runs,
+        )?);
+    }
 
-    let mut messages = Vec::new();
-    for message in value
-        .get("messages")
-        .and_then(Value::as_array)
-        .into_iter()
-        .flatten()
-    {
-        let Some(role) = message
-            .get("role")
-            .and_then(Value::as_str)
-            .and_then(parse_role)
+    for sourced in &mut entries {
+        if let TimelineEntry::ToolCall(call) = &mut sourced.entry
+            && let Some(id) = &call.id
+        {
+            call.run = runs.remove(id);
+        }
+    }
+    Ok(entries)
+}
+
+/// With `amp_tools`, also records the `run` of each `tool_result` in `runs`
+/// by the `toolUseID` it answers.
+fn extract_amp_document_entries(
+    path: &Path,
+    raw_json: &str,
+    policy: &mut ExtractionPolicy,
+    runs: &mut HashMap<String, Value>,
+) -> Result<Vec<SourcedEntry>> {
+    let value = parse_json_document(path, raw_json)?;
+    let thread: AmpThread = records::decode(ProviderKind::Amp, path, 1, "$", value)?;
+
+    let mut entries = Vec::new();
+    for (idx, message) in thread.messages.into_iter().enumerate() {
+        let Some(message) = policy.recover(records::decode::<AmpMessage>(
+            ProviderKind::Amp,
+            path,
+            1,
+            &format!("$.messages[{idx}]"),
+            message,
+        ))?
         else {
             continue;
         };
+        let Some(role) = parse_role(&message.role) else {
+            continue;
+        };
 
-        let text = extract_amp_text(message.get("content"));
-        if text.trim().is_empty() {
+        entries.extend(
+            non_empty_message(role, extract_amp_text(&message.content)).map(|message| {
+                SourcedEntry {
+                    entry: TimelineEntry::Message(message),
+                    source_id: None,
+                    timestamp: None,
+                    origin: message_pointer_origin(path, idx),
+                    partial: false,
+                }
+            }),
+        );
+        if !policy.amp_tools {
             continue;
         }
-
-        messages.push(ThreadMessage { role, text });
+        for block in message.content {
+            match block.block_type.as_deref() {
+                Some("tool_use") => entries.push(SourcedEntry {
+                    source_id: block.id.clone(),
+                    entry: TimelineEntry::ToolCall(AmpToolCall {
+                        id: block.id,
+                        name: block.name.unwrap_or_else(|| "tool".to_string()),
+                        input: block.input,
+                        run: None,
+                    }),
+                    timestamp: None,
+                    origin: message_pointer_origin(path, idx),
+                    partial: false,
+                }),
+                Some("tool_result") => {
+                    if let (Some(id), Some(run)) = (block.tool_use_id, block.run) {
+                        runs.insert(id, run);
+                    }
+                }
+                _ => {}
+            }
+        }
     }
 
-    Ok(messages)
+    Ok(entries)
 }
 
-fn extract_gemini_messages(path: &Path, raw_json: &str) -> Result<Vec<ThreadMessage>> {
-    let value =
-        serde_json::from_str::<Value>(raw_json).map_err(|source| XurlError::InvalidJsonLine {
-            path: path.to_path_buf(),
-            line: 1,
-            source,
-        })?;
+fn extract_gemini_entries(
+    path: &Path,
+    raw_json: &str,
+    policy: &mut ExtractionPolicy,
+) -> Result<Vec<SourcedEntry>> {
+    let value = parse_json_document(path, raw_json)?;
+    let chat: GeminiChat = records::decode(ProviderKind::Gemini, path, 1, "$", value)?;
 
-    let mut messages = Vec::new();
-    for message in value
-        .get("messages")
-        .and_then(Value::as_array)
-        .into_iter()
-        .flatten()
-    {
-        let Some(role) = message
-            .get("type")
-            .and_then(Value::as_str)
-            .and_then(parse_gemini_role)
+    let mut entries = Vec::new();
+    for (idx, message) in chat.messages.into_iter().enumerate() {
+        let field = |key: &str| {
+            message
+                .get(key)
+                .and_then(Value::as_str)
+                .map(ToString::to_string)
+        };
+        let (source_id, timestamp) = (field("id"), field("timestamp"));
+        let Some(message) = policy.recover(records::decode::<GeminiMessage>(
+            ProviderKind::Gemini,
+            path,
+            1,
+            &format!("$.messages[{idx}]"),
+            message,
+        ))?
         else {
             continue;
         };
+        let Some(role) = parse_gemini_role(&message.message_type) else {
+            continue;
+        };
 
-        let text = extract_text(message.get("displayContent"));
+        let text = extract_text(message.display_content.as_ref());
         let text = if text.trim().is_empty() {
-            extract_text(message.get("content"))
+            extract_text(message.content.as_ref())
         } else {
             text
         };
 
-        if text.trim().is_empty() {
-            continue;
-        }
-
-        messages.push(ThreadMessage { role, text });
+        entries.extend(non_empty_message(role, text).map(|message| SourcedEntry {
+            entry: TimelineEntry::Message(message),
+            source_id: source_id.clone(),
+            timestamp: timestamp.clone(),
+            origin: message_pointer_origin(path, idx),
+            partial: false,
+        }));
     }
 
-    Ok(messages)
+    Ok(entries)
 }
 
-fn extract_codex_message(value: &Value) -> Option<ThreadMessage> {
-    let record_type = value.get("type").and_then(Value::as_str)?;
-
-    if record_type == "response_item" {
-        let payload = value.get("payload")?;
-        let payload_type = payload.get("type").and_then(Value::as_str)?;
-        if payload_type != "message" {
-            return None;
+fn extract_codex_message(
+    path: &Path,
+    line_no: usize,
+    line: CodexRolloutLine,
+) -> Result<Option<ThreadMessage>> {
+    match (line.record_type.as_str(), line.payload_type()) {
+        ("response_item", Some("message")) => {
+            let payload: CodexMessagePayload = records::decode(
+                ProviderKind::Codex,
+                path,
+                line_no,
+                "$.payload",
+                line.payload,
+            )?;
+            Ok(parse_role(&payload.role)
+                .and_then(|role| non_empty_message(role, extract_text(payload.content.as_ref()))))
         }
-
-        let role = payload.get("role").and_then(Value::as_str)?;
-        let role = parse_role(role)?;
-        let text = extract_text(payload.get("content"));
-        if text.trim().is_empty() {
-            return None;
+        ("event_msg", Some("agent_message")) => {
+            let payload: CodexAgentMessagePayload = records::decode(
+                ProviderKind::Codex,
+                path,
+                line_no,
+                "$.payload",
+                line.payload,
+            )?;
+            Ok(non_empty_message(MessageRole::Assistant, payload.message))
         }
+        _ => Ok(None),
+    }
+}
 
-        return Some(ThreadMessage { role, text });
+/// With `expand_compactions`, a `compacted` record yields the summary it
+/// replaced the history with.
+fn extract_codex_entry(
+    path: &Path,
+    line_no: usize,
+    value: Value,
+    expand_compactions: bool,
+) -> Result<Option<TimelineEntry>> {
+    let line: CodexRolloutLine = records::decode(ProviderKind::Codex, path, line_no, "$", value)?;
+
+    if line.record_type == CODEX_ROLLOUT_BOUNDARY {
+        let boundary: CodexRolloutBoundary = records::decode(
+            ProviderKind::Codex,
+            path,
+            line_no,
+            "$.payload",
+            line.payload,
+        )?;
+        return Ok(Some(TimelineEntry::Boundary {
+            path: boundary.path,
+        }));
     }
 
-    if record_type == "event_msg"
-        && value
-            .get("payload")
-            .and_then(|payload| payload.get("type"))
-            .and_then(Value::as_str)
-            .is_some_and(|t| t == "agent_message")
-    {
-        let text = value
-            .get("payload")
-            .and_then(|payload| payload.get("message"))
-            .and_then(Value::as_str)
-            .unwrap_or_default()
-            .to_string();
+    if expand_compactions && line.record_type == "compacted" {
+        let payload: CodexCompactedPayload = records::decode(
+            ProviderKind::Codex,
+            path,
+            line_no,
+            "$.payload",
+            line.payload,
+        )?;
+        let summary = Some(payload.message).filter(|message| !message.trim().is_empty());
+        return Ok(Some(TimelineEntry::Compact { summary }));
+    }
+    if is_codex_compact_event(&line) {
+        return Ok(Some(TimelineEntry::Compact { summary: None }));
+    }
 
-        if text.trim().is_empty() {
-            return None;
-        }
+    Ok(extract_codex_message(path, line_no, line)?.map(TimelineEntry::Message))
+}
 
-        return Some(ThreadMessage {
-            role: MessageRole::Assistant,
-            text,
-        });
+/// Text of an `agent_message_delta` event, one chunk of a streamed reply.
+fn codex_message_delta(value: &Value) -> Option<&str> {
+    if value.get("type").and_then(Value::as_str) != Some("event_msg") {
+        return None;
+    }
+    let payload = value.get("payload")?;
+    if payload.get("type").and_then(Value::as_str) != Some("agent_message_delta") {
+        return None;
     }
+    payload.get("delta").and_then(Value::as_str)
+}
 
-    None
+fn is_codex_compact_event(line: &CodexRolloutLine) -> bool {
+    line.record_type == "compacted"
+        || (line.record_type == "event_msg" && line.payload_type() == Some("context_compacted"))
 }
 
-fn extract_codex_entry(value: &Value) -> Option<TimelineEntry> {
-    if let Some(message) = extract_codex_message(value) {
-        return Some(TimelineEntry::Message(message));
+fn extract_claude_message(line: &ClaudeTranscriptLine) -> Option<ThreadMessage> {
+    if line.record_type != "user" && line.record_type != "assistant" {
+        return None;
     }
 
-    if is_codex_compact_event(value) {
-        return Some(TimelineEntry::Compact { summary: None });
-    }
+    let message = line.message.as_ref()?;
+    let role = parse_role(message.role.as_deref().unwrap_or(&line.record_type))?;
 
-    None
+    non_empty_message(role, extract_text(message.content.as_ref()))
 }
 
-fn is_codex_compact_event(value: &Value) -> bool {
-    let record_type = value.get("type").and_then(Value::as_str);
-
-    if record_type == Some("compacted") {
-        return true;
+fn extract_claude_entry(
+    path: &Path,
+    line_no: usize,
+    value: Value,
+    tool_errors: bool,
+) -> Result<Option<TimelineEntry>> {
+    let line: ClaudeTranscriptLine =
+        records::decode(ProviderKind::Claude, path, line_no, "$", value)?;
+
+    if tool_errors && let Some(text) = claude_tool_errors(&line) {
+        return Ok(Some(TimelineEntry::ToolError { text }));
     }
 
-    record_type == Some("event_msg")
-        && value
-            .get("payload")
-            .and_then(|payload| payload.get("type"))
-            .and_then(Value::as_str)
-            .is_some_and(|payload_type| payload_type == "context_compacted")
-}
+    if is_claude_compact_boundary(&line) {
+        return Ok(Some(TimelineEntry::Compact { summary: None }));
+    }
 
-fn extract_claude_message(value: &Value) -> Option<ThreadMessage> {
-    let record_type = value.get("type").and_then(Value::as_str)?;
-    if record_type != "user" && record_type != "assistant" {
-        return None;
+    if is_claude_compact_summary(&line) {
+        let summary = extract_claude_message(&line).map(|message| message.text);
+        return Ok(Some(TimelineEntry::Compact { summary }));
     }
 
-    let message = value.get("message")?;
-    let role = message
-        .get("role")
-        .and_then(Value::as_str)
-        .or(Some(record_type))?;
-    let role = parse_role(role)?;
+    Ok(extract_claude_message(&line).map(TimelineEntry::Message))
+}
 
-    let text = extract_text(message.get("content"));
-    if text.trim().is_empty() {
+/// The payloads of the failed `tool_result` blocks of a user line, each cut
+/// to [`TOOL_OUTPUT_MAX_CHARS`]; `None` when none of them failed.
+fn claude_tool_errors(line: &ClaudeTranscriptLine) -> Option<String> {
+    if line.record_type != "user" {
         return None;
     }
+    let Some(MessageContent::Parts(parts)) = line.message.as_ref()?.content.as_ref() else {
+        return None;
+    };
 
-    Some(ThreadMessage { role, text })
+    let errors = parts
+        .iter()
+        .filter_map(|part| match part {
+            ContentPart::Block(block)
+                if block.is_error && block.block_type.as_deref() == Some("tool_result") =>
+            {
+                Some(truncate_tool_output(&tool_result_text(
+                    block.content.as_ref(),
+                )))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    (!errors.is_empty()).then(|| errors.join("\n\n"))
 }
 
-fn extract_claude_entry(value: &Value) -> Option<TimelineEntry> {
-    if is_claude_compact_boundary(value) {
-        return Some(TimelineEntry::Compact { summary: None });
+/// Text of a `tool_result` body: the string itself, or its text blocks.
+fn tool_result_text(content: Option<&Value>) -> String {
+    match content {
+        Some(Value::String(text)) => text.trim().to_string(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|item| item.get("text").and_then(Value::as_str))
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        _ => String::new(),
     }
+}
 
-    if is_claude_compact_summary(value) {
-        let summary = extract_claude_message(value).map(|message| message.text);
-        return Some(TimelineEntry::Compact { summary });
+fn truncate_tool_output(text: &str) -> String {
+    if text.is_empty() {
+        return "(no output)".to_string();
+    }
+    if text.chars().count() <= TOOL_OUTPUT_MAX_CHARS {
+        return text.to_string();
     }
+    let head = text.chars().take(TOOL_OUTPUT_MAX_CHARS).collect::<String>();
+    format!("{}...", head.trim_end())
+}
 
-    extract_claude_message(value).map(TimelineEntry::Message)
+fn is_claude_compact_boundary(line: &ClaudeTranscriptLine) -> bool {
+    line.record_type == "system" && line.subtype.as_deref() == Some("compact_boundary")
 }
 
-fn is_claude_compact_boundary(value: &Value) -> bool {
-    value.get("type").and_then(Value::as_str) == Some("system")
-        && value.get("subtype").and_then(Value::as_str) == Some("compact_boundary")
+fn is_claude_compact_summary(line: &ClaudeTranscriptLine) -> bool {
+    line.record_type == "user" && line.is_compact_summary
 }
 
-fn is_claude_compact_summary(value: &Value) -> bool {
-    value.get("type").and_then(Value::as_str) == Some("user")
-        && value
-            .get("isCompactSummary")
-            .and_then(Value::as_bool)
-            .unwrap_or(false)
+fn extract_copilot_message(
+    path: &Path,
+    line_no: usize,
+    value: Value,
+) -> Result<Option<ThreadMessage>> {
+    let event: CopilotEvent = records::decode(ProviderKind::Copilot, path, line_no, "$", value)?;
+    let role = match event.event_type.as_str() {
+        "user.message" => MessageRole::User,
+        "assistant.message" => MessageRole::Assistant,
+        _ => return Ok(None),
+    };
+    let data: CopilotMessageData =
+        records::decode(ProviderKind::Copilot, path, line_no, "$.data", event.data)?;
+    Ok(non_empty_message(role, data.content))
 }
 
-fn extract_opencode_message(value: &Value) -> Option<ThreadMessage> {
-    let record_type = value.get("type").and_then(Value::as_str)?;
-    if record_type != "message" {
-        return None;
+fn extract_opencode_message(
+    path: &Path,
+    line_no: usize,
+    value: Value,
+) -> Result<Option<ThreadMessage>> {
+    let row: OpencodeRow = records::decode(ProviderKind::Opencode, path, line_no, "$", value)?;
+    if row.row_type != "message" {
+        return Ok(None);
     }
 
-    let message = value.get("message")?;
-    let role = message.get("role").and_then(Value::as_str)?;
-    let role = parse_role(role)?;
-
-    let mut chunks = Vec::new();
-    for part in value
-        .get("parts")
-        .and_then(Value::as_array)
-        .into_iter()
-        .flatten()
-    {
-        let Some(part_type) = part.get("type").and_then(Value::as_str) else {
-            continue;
-        };
-
-        if part_type != "text" && part_type != "reasoning" {
-            continue;
-        }
-
-        if let Some(text) = part.get("text").and_then(Value::as_str)
-            && !text.trim().is_empty()
-        {
-            chunks.push(text.trim().to_string());
-        }
-    }
+    let Some(role) = row
+        .message
+        .as_ref()
+        .and_then(|message| parse_role(&message.role))
+    else {
+        return Ok(None);
+    };
 
-    if chunks.is_empty() {
-        return None;
-    }
+    let chunks = row
+        .parts
+        .iter()
+        .filter(|part| part.part_type == "text" || part.part_type == "reasoning")
+        .filter_map(|part| part.text.as_deref())
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>();
 
-    Some(ThreadMessage {
-        role,
-        text: chunks.join("\n\n"),
-    })
+    Ok(non_empty_message(role, chunks.join("\n\n")))
 }
 
-fn extract_amp_text(content: Option<&Value>) -> String {
-    let Some(items) = content.and_then(Value::as_array) else {
-        return String::new();
-    };
-
+fn extract_amp_text(content: &[ContentBlock]) -> String {
     let mut chunks = Vec::new();
-    for item in items {
-        let Some(item_type) = item.get("type").and_then(Value::as_str) else {
-            continue;
+    for block in content {
+        let text = match block.block_type.as_deref() {
+            Some("text") => block.text.as_deref(),
+            Some("thinking") => block.thinking.as_deref(),
+            _ => None,
         };
 
-        match item_type {
-            "text" => {
-                if let Some(text) = item.get("text").and_then(Value::as_str)
-                    && !text.trim().is_empty()
-                {
-                    chunks.push(text.trim().to_string());
-                }
-            }
-            "thinking" => {
-                if let Some(thinking) = item.get("thinking").and_then(Value::as_str)
-                    && !thinking.trim().is_empty()
-                {
-                    chunks.push(thinking.trim().to_string());
-                }
-            }
-            _ => {}
+        if let Some(text) = text
+            && !text.trim().is_empty()
+        {
+            chunks.push(text.trim().to_string());
         }
     }
 
@@ -548,51 +1639,39 @@ fn parse_gemini_role(role: &str) -> Option<MessageRole> {
     }
 }
 
-fn extract_text(content: Option<&Value>) -> String {
+fn extract_text(content: Option<&MessageContent>) -> String {
     let Some(content) = content else {
         return String::new();
     };
 
-    if let Some(text) = content.as_str() {
-        return text.to_string();
-    }
-
-    let Some(items) = content.as_array() else {
-        return String::new();
+    let parts = match content {
+        MessageContent::Text(text) => return text.clone(),
+        MessageContent::Parts(parts) => parts,
     };
 
     let mut chunks = Vec::new();
 
-    for item in items {
-        if let Some(text) = item.as_str()
-            && !text.trim().is_empty()
-        {
-            chunks.push(text.trim().to_string());
-            continue;
-        }
-
-        if let Some(item_type) = item.get("type").and_then(Value::as_str)
-            && TOOL_TYPES.contains(&item_type)
-        {
-            continue;
-        }
-
-        if let Some(text) = item.get("text").and_then(Value::as_str)
-            && !text.trim().is_empty()
-        {
-            chunks.push(text.trim().to_string());
-            continue;
-        }
+    for part in parts {
+        let block = match part {
+            ContentPart::Text(text) => {
+                if !text.trim().is_empty() {
+                    chunks.push(text.trim().to_string());
+                }
+                continue;
+            }
+            ContentPart::Block(block) => block,
+        };
 
-        if let Some(text) = item.get("input_text").and_then(Value::as_str)
-            && !text.trim().is_empty()
+        if let Some(block_type) = block.block_type.as_deref()
+            && TOOL_TYPES.contains(&block_type)
         {
-            chunks.push(text.trim().to_string());
             continue;
         }
 
-        if let Some(text) = item.get("output_text").and_then(Value::as_str)
-            && !text.trim().is_empty()
+        if let Some(text) = [&block.text, &block.input_text, &block.output_text]
+            .into_iter()
+            .flatten()
+            .find(|text| !text.trim().is_empty())
         {
             chunks.push(text.trim().to_string());
         }
@@ -605,8 +1684,11 @@ fn extract_text(content: Option<&Value>) -> String {
 mod tests {
     use std::path::Path;
 
-    use crate::model::ProviderKind;
-    use crate::render::{extract_messages, render_markdown};
+    use crate::model::{Annotation, ExtractionMode, ProviderKind, TableFormat, TimelineEntryKind};
+    use crate::render::{
+        ExtractionPolicy, TimelineOptions, extract_messages, pi_session_version, render_markdown,
+        render_markdown_with, render_pi_branches_markdown, render_timeline_table, timeline_entries,
+    };
     use crate::uri::ThreadUri;
 
     #[test]
@@ -622,38 +1704,332 @@ mod tests {
         assert!(output.contains("## Timeline"));
     }
 
+    #[test]
+    fn kind_filter_keeps_thread_wide_numbers() {
+        let raw = r#"{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"first prompt"}]}}
+{"type":"response_item","payload":{"type":"message","role":"assistant","content":[{"type":"output_text","text":"first answer"}]}}
+{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"second prompt"}]}}"#;
+        let uri =
+            ThreadUri::parse("codex://019c871c-b1f9-7f60-9c4f-87ed09f13592").expect("parse uri");
+
+        let render = |kinds: &[TimelineEntryKind]| {
+            let options = TimelineOptions {
+                kinds,
+                ..TimelineOptions::default()
+            };
+            render_markdown_with(&uri, Path::new("/tmp/mock"), raw, &options).expect("render")
+        };
+
+        let users = render(&[TimelineEntryKind::User]);
+        assert!(users.contains("## 1. User\n\nfirst prompt"));
+        assert!(users.contains("## 3. User\n\nsecond prompt"));
+        assert!(!users.contains("first answer"));
+
+        let compacts = render(&[TimelineEntryKind::Compact]);
+        assert!(compacts.contains("_No entries of the requested kinds found._"));
+        assert!(!compacts.contains("prompt"));
+    }
+
+    #[test]
+    fn dedupe_collapses_retried_replies_into_the_first() {
+        let raw = r#"{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"hello"}]}}
+{"type":"response_item","payload":{"type":"message","role":"assistant","content":[{"type":"output_text","text":"Hi there."}]}}
+{"type":"response_item","payload":{"type":"message","role":"assistant","content":[{"type":"output_text","text":"hi  there.\n"}]}}
+{"type":"response_item","payload":{"type":"message","role":"assistant","content":[{"type":"output_text","text":"Hi there."}]}}
+{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"Hi there."}]}}"#;
+        let uri =
+            ThreadUri::parse("codex://019c871c-b1f9-7f60-9c4f-87ed09f13592").expect("parse uri");
+        let annotations = [Annotation {
+            provider: "codex".to_string(),
+            session_id: uri.session_id.clone(),
+            message: 3,
+            note: "retried".to_string(),
+            created_at: "2026-02-23T00:00:00Z".to_string(),
+        }];
+        let options = TimelineOptions {
+            dedupe: true,
+            annotations: &annotations,
+            ..TimelineOptions::default()
+        };
+
+        let output =
+            render_markdown_with(&uri, Path::new("/tmp/mock"), raw, &options).expect("render");
+        assert!(output.contains(
+            "## 2. Assistant\n\n_Repeated 3 times in a row._\n\nHi there.\n\n> [!NOTE]\n> retried\n"
+        ));
+        assert!(!output.contains("## 3."));
+        assert!(!output.contains("## 4."));
+        assert!(output.contains("## 5. User\n\nHi there."));
+    }
+
+    #[test]
+    fn provenance_points_at_source_lines_and_json_pointers() {
+        let raw = r#"{"type":"xurl_rollout_boundary","payload":{"path":"/tmp/old.jsonl"}}
+{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"first prompt"}]}}
+{"type":"xurl_rollout_boundary","payload":{"path":"/tmp/new.jsonl"}}
+
+{"type":"response_item","payload":{"type":"message","role":"assistant","content":[{"type":"output_text","text":"resumed"}]}}"#;
+        let uri =
+            ThreadUri::parse("codex://019c871c-b1f9-7f60-9c4f-87ed09f13592").expect("parse uri");
+        let options = TimelineOptions {
+            provenance: true,
+            ..TimelineOptions::default()
+        };
+        let output =
+            render_markdown_with(&uri, Path::new("/tmp/mock"), raw, &options).expect("render");
+        assert!(output.contains("## 2. User\n\n_Source: `/tmp/old.jsonl:1`_\n\nfirst prompt"));
+        assert!(output.contains("_Source: `/tmp/new.jsonl:2`_"));
+        assert!(
+            !render_markdown(&uri, Path::new("/tmp/mock"), raw)
+                .expect("render")
+                .contains("_Source:")
+        );
+
+        let raw = r#"{"messages":[{"type":"info","content":"ignored"},{"type":"user","content":"hello"}]}"#;
+        let uri = ThreadUri::parse("gemini://29d207db-ca7e-40ba-87f7-e14c9de60613").expect("uri");
+        let output =
+            render_markdown_with(&uri, Path::new("/tmp/chat.json"), raw, &options).expect("render");
+        assert!(output.contains("_Source: `/tmp/chat.json#/messages/1`_"));
+    }
+
+    #[test]
+    fn tables_escape_text_for_their_delimiter() {
+        let raw = r#"{"timestamp":"2026-02-23T00:00:00Z","type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"say \"hi\", then\n\tstop"}]}}
+{"type":"response_item","payload":{"type":"message","role":"assistant","content":[{"type":"output_text","text":"ok"}]}}"#;
+        let uri =
+            ThreadUri::parse("codex://019c871c-b1f9-7f60-9c4f-87ed09f13592").expect("parse uri");
+        let entries = timeline_entries(
+            &uri,
+            Path::new("/tmp/mock"),
+            raw,
+            &mut ExtractionPolicy::default(),
+        )
+        .expect("entries");
+
+        assert_eq!(
+            render_timeline_table(&entries, TableFormat::Csv),
+            "index,role,timestamp,chars,text\n1,user,2026-02-23T00:00:00Z,20,\"say \"\"hi\"\", then\n\tstop\"\n2,assistant,,2,ok\n"
+        );
+        assert_eq!(
+            render_timeline_table(&entries, TableFormat::Tsv),
+            "index\trole\ttimestamp\tchars\ttext\n1\tuser\t2026-02-23T00:00:00Z\t20\tsay \"hi\", then\\n\\tstop\n2\tassistant\t\t2\tok\n"
+        );
+    }
+
     #[test]
     fn codex_filters_function_calls() {
         let raw = r#"{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"hello"}]}}
 {"type":"response_item","payload":{"type":"function_call","name":"ls"}}
 {"type":"response_item","payload":{"type":"message","role":"assistant","content":[{"type":"output_text","text":"world"}]}}"#;
 
-        let messages =
-            extract_messages(ProviderKind::Codex, Path::new("/tmp/mock"), raw).expect("extract");
+        let messages = extract_messages(
+            ProviderKind::Codex,
+            Path::new("/tmp/mock"),
+            raw,
+            &mut ExtractionPolicy::default(),
+        )
+        .expect("extract");
         assert_eq!(messages.len(), 2);
         assert_eq!(messages[0].text, "hello");
         assert_eq!(messages[1].text, "world");
     }
 
+    #[test]
+    fn codex_reports_malformed_message_field() {
+        let raw = r#"{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"hello"}]}}
+{"type":"response_item","payload":{"type":"message","role":"assistant","content":[{"type":"output_text","text":42}]}}"#;
+
+        let err = extract_messages(
+            ProviderKind::Codex,
+            Path::new("/tmp/mock"),
+            raw,
+            &mut ExtractionPolicy::default(),
+        )
+        .expect_err("must fail");
+        let message = format!("{err}");
+        assert!(message.contains("invalid codex record"));
+        assert!(message.contains("at line 2, field $.payload"));
+    }
+
+    #[test]
+    fn unterminated_final_line_is_pending_in_strict_mode() {
+        let complete = r#"{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"hello"}]}}"#;
+        let partial = r#"{"type":"response_item","payload":{"type":"mess"#;
+
+        let mut policy = ExtractionPolicy::new(ExtractionMode::Strict);
+        let messages = extract_messages(
+            ProviderKind::Codex,
+            Path::new("/tmp/mock"),
+            &format!("{complete}\n{partial}"),
+            &mut policy,
+        )
+        .expect("extract");
+        assert_eq!(messages.len(), 1);
+        let warnings = policy.into_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, "pending_final_line");
+
+        // Once the line ends, a record that still does not parse is broken.
+        let err = extract_messages(
+            ProviderKind::Codex,
+            Path::new("/tmp/mock"),
+            &format!("{complete}\n{partial}\n"),
+            &mut ExtractionPolicy::new(ExtractionMode::Strict),
+        )
+        .expect_err("must fail");
+        assert!(format!("{err}").contains("at line 2"));
+    }
+
+    #[test]
+    fn lenient_extraction_skips_unparsable_records_with_warnings() {
+        let raw = r#"{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"hello"}]}}
+{"type":"response_item","payload":{"type":"mess
+{"type":"response_item","payload":{"type":"message","role":"assistant","content":[{"type":"output_text","text":42}]}}
+{"type":"response_item","payload":{"type":"message","role":"assistant","content":[{"type":"output_text","text":"world"}]}}"#;
+
+        let mut policy = ExtractionPolicy::new(ExtractionMode::Lenient);
+        let messages = extract_messages(
+            ProviderKind::Codex,
+            Path::new("/tmp/mock"),
+            raw,
+            &mut policy,
+        )
+        .expect("extract");
+        assert_eq!(
+            messages
+                .iter()
+                .map(|message| message.text.as_str())
+                .collect::<Vec<_>>(),
+            ["hello", "world"]
+        );
+        let warnings = policy.into_warnings();
+        assert_eq!(warnings.len(), 2);
+        assert!(
+            warnings
+                .iter()
+                .all(|warning| warning.code == "skipped_invalid_record")
+        );
+        assert!(
+            warnings[0]
+                .message
+                .contains("invalid json line in /tmp/mock at line 2")
+        );
+        assert!(warnings[1].message.contains("at line 3, field $.payload"));
+
+        let raw = r#"{"messages":[{"type":"user","content":"hello"},{"type":7}]}"#;
+        let mut policy = ExtractionPolicy::new(ExtractionMode::Lenient);
+        let messages = extract_messages(
+            ProviderKind::Gemini,
+            Path::new("/tmp/mock"),
+            raw,
+            &mut policy,
+        )
+        .expect("extract");
+        assert_eq!(messages.len(), 1);
+        assert_eq!(policy.into_warnings().len(), 1);
+    }
+
     #[test]
     fn claude_filters_tool_use() {
         let raw = r#"{"type":"user","message":{"role":"user","content":[{"type":"text","text":"hello"}]}}
 {"type":"assistant","message":{"role":"assistant","content":[{"type":"tool_use","name":"search"},{"type":"text","text":"done"}]}}"#;
 
-        let messages =
-            extract_messages(ProviderKind::Claude, Path::new("/tmp/mock"), raw).expect("extract");
+        let messages = extract_messages(
+            ProviderKind::Claude,
+            Path::new("/tmp/mock"),
+            raw,
+            &mut ExtractionPolicy::default(),
+        )
+        .expect("extract");
         assert_eq!(messages.len(), 2);
         assert_eq!(messages[1].text, "done");
     }
 
+    #[test]
+    fn claude_tool_errors_get_entries_when_asked() {
+        let long = "x".repeat(1200);
+        let raw = format!(
+            r#"{{"type":"assistant","message":{{"role":"assistant","content":[{{"type":"tool_use","id":"t1","name":"Bash"}}]}}}}
+{{"type":"user","message":{{"role":"user","content":[{{"type":"tool_result","tool_use_id":"t1","is_error":true,"content":"Exit code 2\nno such file"}}]}}}}
+{{"type":"user","message":{{"role":"user","content":[{{"type":"tool_result","tool_use_id":"t2","content":"ok"}}]}}}}
+{{"type":"user","message":{{"role":"user","content":[{{"type":"tool_result","tool_use_id":"t3","is_error":true,"content":[{{"type":"text","text":"{long}"}}]}}]}}}}
+{{"type":"assistant","message":{{"role":"assistant","content":[{{"type":"text","text":"Retrying."}}]}}}}"#
+        );
+        let uri =
+            ThreadUri::parse("claude://2823d1df-720a-4c31-ac55-ae8ba726721f").expect("parse uri");
+        let entries = |policy: ExtractionPolicy| {
+            timeline_entries(&uri, Path::new("/tmp/mock"), &raw, &mut { policy })
+                .expect("entries")
+                .into_iter()
+                .map(|entry| (entry.kind, entry.text))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            entries(ExtractionPolicy::default()),
+            [(TimelineEntryKind::Assistant, "Retrying.".to_string())]
+        );
+        let shown = entries(ExtractionPolicy::default().with_tool_errors(true));
+        assert_eq!(
+            shown[0],
+            (
+                TimelineEntryKind::ToolError,
+                "Exit code 2\nno such file".to_string()
+            )
+        );
+        assert_eq!(shown[1].0, TimelineEntryKind::ToolError);
+        assert_eq!(shown[1].1, format!("{}...", "x".repeat(1000)));
+        assert_eq!(shown.len(), 3);
+    }
+
+    #[test]
+    fn timeline_entry_ids_prefer_provider_ids_and_are_stable() {
+        let raw = r#"{"type":"user","uuid":"6f1d2c3b-0a4e-4d2f-9a7e-1c2b3d4e5f60","message":{"role":"user","content":"hello"}}
+{"type":"assistant","message":{"role":"assistant","content":"done"}}"#;
+        let uri =
+            ThreadUri::parse("claude://2823d1df-720a-4c31-ac55-ae8ba726721f").expect("parse uri");
+
+        let entries = timeline_entries(
+            &uri,
+            Path::new("/tmp/mock"),
+            raw,
+            &mut ExtractionPolicy::default(),
+        )
+        .expect("entries");
+        assert_eq!(entries[0].id, "6f1d2c3b-0a4e-4d2f-9a7e-1c2b3d4e5f60");
+        assert_eq!(entries[1].anchor, "msg-2");
+        assert_eq!(entries[1].kind, TimelineEntryKind::Assistant);
+        assert_eq!(
+            entries[1].uri,
+            "agents://claude/2823d1df-720a-4c31-ac55-ae8ba726721f#msg-2"
+        );
+        assert_eq!(entries[1].id.len(), 16);
+        assert_eq!(
+            timeline_entries(
+                &uri,
+                Path::new("/tmp/mock"),
+                raw,
+                &mut ExtractionPolicy::default()
+            )
+            .expect("entries")[1]
+                .id,
+            entries[1].id
+        );
+    }
+
     #[test]
     fn opencode_extracts_text_and_reasoning_parts() {
         let raw = r#"{"type":"session","sessionId":"ses_43a90e3adffejRgrTdlJa48CtE"}
 {"type":"message","id":"msg_1","sessionId":"ses_43a90e3adffejRgrTdlJa48CtE","message":{"role":"user","time":{"created":1}},"parts":[{"type":"text","text":"hello"}]}
 {"type":"message","id":"msg_2","sessionId":"ses_43a90e3adffejRgrTdlJa48CtE","message":{"role":"assistant","time":{"created":2}},"parts":[{"type":"reasoning","text":"thinking"},{"type":"tool","tool":"read"},{"type":"text","text":"world"}]}"#;
 
-        let messages =
-            extract_messages(ProviderKind::Opencode, Path::new("/tmp/mock"), raw).expect("extract");
+        let messages = extract_messages(
+            ProviderKind::Opencode,
+            Path::new("/tmp/mock"),
+            raw,
+            &mut ExtractionPolicy::default(),
+        )
+        .expect("extract");
         assert_eq!(messages.len(), 2);
         assert_eq!(messages[0].text, "hello");
         assert_eq!(messages[1].text, "thinking\n\nworld");
@@ -663,19 +2039,110 @@ mod tests {
     fn amp_extracts_text_and_thinking_content() {
         let raw = r#"{"id":"T-019c0797-c402-7389-bd80-d785c98df295","messages":[{"role":"user","content":[{"type":"text","text":"hello"}]},{"role":"assistant","content":[{"type":"thinking","thinking":"step by step"},{"type":"tool_use","name":"finder"},{"type":"text","text":"done"}]},{"role":"user","content":[{"type":"tool_result","toolUseID":"tool_1","run":{"status":"done","result":"ignored"}}]}]}"#;
 
-        let messages =
-            extract_messages(ProviderKind::Amp, Path::new("/tmp/mock"), raw).expect("extract");
+        let messages = extract_messages(
+            ProviderKind::Amp,
+            Path::new("/tmp/mock"),
+            raw,
+            &mut ExtractionPolicy::default(),
+        )
+        .expect("extract");
         assert_eq!(messages.len(), 2);
         assert_eq!(messages[0].text, "hello");
         assert_eq!(messages[1].text, "step by step\n\ndone");
     }
 
+    #[test]
+    fn amp_tools_pair_calls_with_their_results() {
+        let raw = r#"{"id":"T-019c0797-c402-7389-bd80-d785c98df295","messages":[{"role":"user","content":[{"type":"text","text":"list files"}]},{"role":"assistant","content":[{"type":"text","text":"Looking."},{"type":"tool_use","id":"toolu_1","name":"Bash","input":{"cmd":"ls"}},{"type":"tool_use","id":"toolu_2","name":"Read","input":{"path":"a.md"}}]},{"role":"user","content":[{"type":"tool_result","toolUseID":"toolu_1","run":{"status":"done","result":{"output":"a.md\n```","exitCode":0}}},{"type":"tool_result","toolUseID":"toolu_2","run":{"status":"error","error":{"message":"not found"}}}]},{"role":"assistant","content":[{"type":"tool_use","id":"toolu_3","name":"finder"}]}]}"#;
+        let uri = ThreadUri::parse("amp://T-019c0797-c402-7389-bd80-d785c98df295").expect("uri");
+        let entries = |policy: ExtractionPolicy| {
+            timeline_entries(&uri, Path::new("/tmp/mock"), raw, &mut { policy })
+                .expect("entries")
+                .into_iter()
+                .map(|entry| (entry.kind, entry.id, entry.text))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(entries(ExtractionPolicy::default()).len(), 2);
+        let shown = entries(ExtractionPolicy::default().with_amp_tools(true));
+        assert_eq!(shown.len(), 5);
+        assert_eq!(shown[1].2, "Looking.");
+        assert_eq!(shown[2].0, TimelineEntryKind::ToolCall);
+        assert_eq!(shown[2].1, "toolu_1");
+        assert_eq!(
+            shown[2].2,
+            "Bash (done)\n\nInput:\n{\n  \"cmd\": \"ls\"\n}\n\nResult:\n{\n  \"exitCode\": 0,\n  \"output\": \"a.md\\n```\"\n}"
+        );
+        assert!(shown[3].2.starts_with("Read (error)\n\n"));
+        assert!(
+            shown[3]
+                .2
+                .ends_with("Error:\n{\n  \"message\": \"not found\"\n}")
+        );
+        assert_eq!(shown[4].2, "finder (no result)");
+
+        let options = TimelineOptions {
+            extraction: ExtractionPolicy::default().with_amp_tools(true),
+            ..TimelineOptions::default()
+        };
+        let output =
+            render_markdown_with(&uri, Path::new("/tmp/mock"), raw, &options).expect("render");
+        assert!(output.contains(
+            "## 3. Tool Call\n\n<details>\n<summary>Bash (done)</summary>\n\n````\nInput:\n"
+        ));
+        assert!(output.contains("\n````\n\n</details>\n\n"));
+        assert!(output.contains(
+            "## 5. Tool Call\n\n<details>\n<summary>finder (no result)</summary>\n\n</details>"
+        ));
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn amp_thread_directories_append_shards_in_order() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let thread = temp.path().join("thread.json");
+        let message = |role: &str, text: &str| {
+            format!(
+                r#"{{"messages":[{{"role":"{role}","content":[{{"type":"text","text":"{text}"}}]}}]}}"#
+            )
+        };
+        let raw = message("user", "first");
+        std::fs::write(&thread, &raw).expect("write");
+        std::fs::write(
+            temp.path().join("messages-10.json"),
+            message("assistant", "third"),
+        )
+        .expect("write");
+        std::fs::write(
+            temp.path().join("messages-2.json"),
+            message("user", "second"),
+        )
+        .expect("write");
+
+        let texts = extract_messages(
+            ProviderKind::Amp,
+            &thread,
+            &raw,
+            &mut ExtractionPolicy::default(),
+        )
+        .expect("extract")
+        .into_iter()
+        .map(|message| message.text)
+        .collect::<Vec<_>>();
+        assert_eq!(texts, ["first", "second", "third"]);
+    }
+
     #[test]
     fn gemini_extracts_user_and_assistant_messages() {
         let raw = r#"{"sessionId":"29d207db-ca7e-40ba-87f7-e14c9de60613","messages":[{"type":"info","content":"ignored"},{"type":"user","content":"hello"},{"type":"gemini","content":"world"},{"type":"gemini","content":[{"type":"thinking","text":"step by step"},{"type":"tool_call","name":"list_directory"},{"type":"text","text":"done"}]}]}"#;
 
-        let messages =
-            extract_messages(ProviderKind::Gemini, Path::new("/tmp/mock"), raw).expect("extract");
+        let messages = extract_messages(
+            ProviderKind::Gemini,
+            Path::new("/tmp/mock"),
+            raw,
+            &mut ExtractionPolicy::default(),
+        )
+        .expect("extract");
         assert_eq!(messages.len(), 3);
         assert_eq!(messages[0].text, "hello");
         assert_eq!(messages[1].text, "world");
@@ -702,6 +2169,23 @@ mod tests {
         assert!(!output.contains("branch one done"));
     }
 
+    #[test]
+    fn pi_v1_session_renders_as_a_linear_thread() {
+        let raw = r#"{"type":"session","id":"12cb4c19-2774-4de4-a0d0-9fa32fbae29f","timestamp":"2026-02-23T13:00:12.780Z","cwd":"/tmp/project"}
+{"type":"message","timestamp":"2026-02-23T13:00:13.000Z","message":{"role":"user","content":[{"type":"text","text":"first question"}]}}
+{"type":"message","timestamp":"2026-02-23T13:00:14.000Z","message":{"role":"assistant","content":[{"type":"text","text":"first answer"}]}}
+{"type":"message","timestamp":"2026-02-23T13:00:15.000Z","message":{"role":"user","content":[{"type":"text","text":"second question"}]}}"#;
+
+        assert_eq!(pi_session_version(raw), 1);
+        let uri = ThreadUri::parse("pi://12cb4c19-2774-4de4-a0d0-9fa32fbae29f").expect("parse uri");
+        let output = render_markdown(&uri, Path::new("/tmp/mock"), raw).expect("render");
+
+        let first = output.find("first question").expect("first question");
+        let answer = output.find("first answer").expect("first answer");
+        let second = output.find("second question").expect("second question");
+        assert!(first < answer && answer < second);
+    }
+
     #[test]
     fn pi_entry_leaf_renders_requested_branch() {
         let raw = r#"{"type":"session","version":3,"id":"12cb4c19-2774-4de4-a0d0-9fa32fbae29f","timestamp":"2026-02-23T13:00:12.780Z","cwd":"/tmp/project"}
@@ -720,6 +2204,46 @@ mod tests {
         assert!(!output.contains("branch two done"));
     }
 
+    #[test]
+    fn pi_all_branches_renders_every_path_in_order() {
+        let raw = r#"{"type":"session","version":3,"id":"12cb4c19-2774-4de4-a0d0-9fa32fbae29f","timestamp":"2026-02-23T13:00:12.780Z","cwd":"/tmp/project"}
+{"type":"message","id":"a1b2c3d4","parentId":null,"timestamp":"2026-02-23T13:00:13.000Z","message":{"role":"user","content":[{"type":"text","text":"root"}]}}
+{"type":"message","id":"b1b2c3d4","parentId":"a1b2c3d4","timestamp":"2026-02-23T13:00:14.000Z","message":{"role":"assistant","content":[{"type":"text","text":"root done"}]}}
+{"type":"message","id":"c1b2c3d4","parentId":"b1b2c3d4","timestamp":"2026-02-23T13:00:15.000Z","message":{"role":"user","content":[{"type":"text","text":"branch one"}]}}
+{"type":"message","id":"d1b2c3d4","parentId":"c1b2c3d4","timestamp":"2026-02-23T13:00:16.000Z","message":{"role":"assistant","content":[{"type":"text","text":"branch one done\nsecond line"}]}}
+{"type":"message","id":"e1b2c3d4","parentId":"b1b2c3d4","timestamp":"2026-02-23T13:00:17.000Z","message":{"role":"user","content":[{"type":"text","text":"branch two"}]}}
+{"type":"compaction","id":"f1b2c3d4","parentId":"e1b2c3d4","timestamp":"2026-02-23T13:00:18.000Z","summary":"compact summary","firstKeptEntryId":"b1b2c3d4","tokensBefore":128}
+{"type":"message","id":"g1b2c3d4","parentId":"f1b2c3d4","timestamp":"2026-02-23T13:00:19.000Z","message":{"role":"assistant","content":[{"type":"text","text":"branch two done"}]}}"#;
+
+        let uri = ThreadUri::parse("pi://12cb4c19-2774-4de4-a0d0-9fa32fbae29f").expect("parse uri");
+        let output = render_pi_branches_markdown(
+            &uri,
+            Path::new("/tmp/mock"),
+            raw,
+            &mut ExtractionPolicy::default(),
+        )
+        .expect("render");
+
+        assert!(output.contains("_7 entries in 2 branches."));
+        let expected = [
+            "- **User** `a1b2c3d4`\n\n  root\n",
+            "- **Assistant** `b1b2c3d4`\n\n  root done\n",
+            "- **Branch 1/2**\n\n",
+            "  - **User** `c1b2c3d4`\n\n    branch one\n",
+            "  - **Assistant** `d1b2c3d4`\n\n    branch one done\n    second line\n",
+            "- **Branch 2/2** (latest)\n\n",
+            "  - **Context Compacted** `f1b2c3d4` (keeps entries from `b1b2c3d4`)\n\n    compact summary\n",
+            "  - **Assistant** `g1b2c3d4`\n\n    branch two done\n",
+        ];
+        let mut cursor = 0;
+        for fragment in expected {
+            let found = output[cursor..]
+                .find(fragment)
+                .unwrap_or_else(|| panic!("missing {fragment:?} in order:\n{output}"));
+            cursor += found + fragment.len();
+        }
+    }
+
     #[test]
     fn pi_entry_leaf_reports_not_found() {
         let raw = r#"{"type":"session","version":3,"id":"12cb4c19-2774-4de4-a0d0-9fa32fbae29f","timestamp":"2026-02-23T13:00:12.780Z","cwd":"/tmp/project"}
@@ -747,6 +2271,88 @@ mod tests {
         assert!(output.contains("## 3. Assistant"));
     }
 
+    #[test]
+    fn codex_rebuilds_unfinished_replies_from_deltas() {
+        let raw = r#"{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"first"}]}}
+{"type":"event_msg","payload":{"type":"agent_message_delta","delta":"Done "}}
+{"type":"event_msg","payload":{"type":"agent_message_delta","delta":"already."}}
+{"type":"event_msg","payload":{"type":"agent_message","message":"Done already."}}
+{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"second"}]}}
+{"type":"event_msg","payload":{"type":"agent_message_delta","delta":"Working "}}
+{"type":"event_msg","payload":{"type":"token_count","info":null}}
+{"type":"event_msg","payload":{"type":"agent_message_delta","delta":"on it"}}"#;
+        let uri =
+            ThreadUri::parse("codex://019c871c-b1f9-7f60-9c4f-87ed09f13592").expect("parse uri");
+
+        let entries = timeline_entries(
+            &uri,
+            Path::new("/tmp/mock"),
+            raw,
+            &mut ExtractionPolicy::default(),
+        )
+        .expect("entries");
+        let texts = entries
+            .iter()
+            .map(|entry| (entry.text.as_str(), entry.partial))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            texts,
+            [
+                ("first", false),
+                ("Done already.", false),
+                ("second", false),
+                ("Working on it", true)
+            ]
+        );
+        assert_eq!(entries[3].source.as_deref(), Some("/tmp/mock:6"));
+
+        let output = render_markdown(&uri, Path::new("/tmp/mock"), raw).expect("render");
+        assert!(output.contains("## 4. Assistant (partial)\n\nWorking on it"));
+    }
+
+    #[test]
+    fn expanded_compactions_show_the_codex_summary_once() {
+        let raw = r#"{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"hello"}]}}
+{"type":"event_msg","payload":{"type":"context_compacted"}}
+{"type":"turn_context","payload":{"cwd":"/tmp"}}
+{"type":"compacted","payload":{"message":"Earlier: the user said hello.","replacement_history":[]}}
+{"type":"response_item","payload":{"type":"message","role":"assistant","content":[{"type":"output_text","text":"world"}]}}
+{"type":"event_msg","payload":{"type":"context_compacted"}}"#;
+        let uri =
+            ThreadUri::parse("codex://019c871c-b1f9-7f60-9c4f-87ed09f13592").expect("parse uri");
+        let entries = |policy: ExtractionPolicy| {
+            timeline_entries(&uri, Path::new("/tmp/mock"), raw, &mut { policy })
+                .expect("entries")
+                .into_iter()
+                .map(|entry| entry.text)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            entries(ExtractionPolicy::default()),
+            [
+                "hello",
+                "Context was compacted.",
+                "Context was compacted.",
+                "world",
+                "Context was compacted."
+            ]
+        );
+        assert_eq!(
+            entries(
+                ExtractionPolicy::default()
+                    .with_expanded_compactions(true)
+                    .with_compact_placeholder(Some("[compacted]".to_string()))
+            ),
+            [
+                "hello",
+                "Earlier: the user said hello.",
+                "world",
+                "[compacted]"
+            ]
+        );
+    }
+
     #[test]
     fn claude_compact_summary_renders_as_compact_entry() {
         let raw = r#"{"type":"user","isCompactSummary":true,"message":{"role":"user","content":[{"type":"text","text":"Summary: old conversation"}]}}
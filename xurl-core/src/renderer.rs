@@ -0,0 +1,618 @@
+//! Output formats of a thread timeline.
+//!
+//! [`render::render_timeline`](crate::render::render_timeline) picks the
+//! entries a read shows and hands them to a [`Renderer`]: the head once,
+//! then each entry in order, then `finish` for the output. A new format is
+//! a new `Renderer` and a [`TimelineFormat`] variant that selects it.
+
+use std::ops::Range;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::model::{
+    Annotation, TableFormat, ThreadParent, TimelineEntryKind, TimelineEntryView, TimelineFormat,
+};
+use crate::uri::ThreadUri;
+use crate::yaml::YamlMapping;
+
+const NO_ENTRIES: &str = "No user/assistant messages or compact events found.";
+const NO_MATCHING_ENTRIES: &str = "No entries of the requested kinds found.";
+
+/// What a timeline shows, before its entries.
+#[derive(Debug, Clone)]
+pub struct TimelineHead<'a> {
+    pub uri: &'a ThreadUri,
+    pub thread_source: &'a Path,
+    /// For a subagent's own thread, the thread that spawned it.
+    pub parent: Option<&'a ThreadParent>,
+    /// Entries in the whole timeline.
+    pub total: usize,
+    /// Positions of the entries shown, before filtering by kind.
+    pub range: Range<usize>,
+    /// The entry a URI fragment points at, when there is one.
+    pub focus: Option<&'a TimelineEntryView>,
+}
+
+/// Renders one timeline. Called with `render_head` once, `render_entry` for
+/// each entry shown, then `finish`.
+pub trait Renderer {
+    fn render_head(&mut self, head: &TimelineHead<'_>);
+
+    /// `notes` are the annotations attached to `entry`, oldest first.
+    fn render_entry(&mut self, entry: &TimelineEntryView, notes: &[&Annotation]);
+
+    /// Returns the rendered timeline.
+    fn finish(&mut self) -> String;
+}
+
+/// The renderer for `format`. `provenance` adds the file and line each entry
+/// was read from, where the format has room for it.
+pub fn renderer_for(format: TimelineFormat, provenance: bool) -> Box<dyn Renderer> {
+    match format {
+        TimelineFormat::Markdown => Box::new(MarkdownRenderer::new(provenance)),
+        TimelineFormat::Json => Box::new(JsonRenderer::default()),
+        TimelineFormat::Html => Box::new(HtmlRenderer::new(provenance)),
+        TimelineFormat::Text => Box::new(TextRenderer::new(provenance)),
+        TimelineFormat::Table(format) => Box::new(TableRenderer::new(format)),
+    }
+}
+
+/// Heading of a timeline entry of `kind`.
+pub(crate) fn entry_title(kind: TimelineEntryKind) -> &'static str {
+    match kind {
+        TimelineEntryKind::User => "User",
+        TimelineEntryKind::Assistant => "Assistant",
+        TimelineEntryKind::Compact => "Context Compacted",
+        TimelineEntryKind::RolloutBoundary => "Rollout Boundary",
+        TimelineEntryKind::ToolError => "Tool Error",
+        TimelineEntryKind::ToolCall => "Tool Call",
+    }
+}
+
+/// Heading of `entry`: its kind's title, marked when the entry is partial.
+fn entry_heading(entry: &TimelineEntryView) -> String {
+    let title = entry_title(entry.kind);
+    if entry.partial {
+        format!("{title} (partial)")
+    } else {
+        title.to_string()
+    }
+}
+
+/// Body of an entry: its text, or the file a rollout boundary starts.
+fn entry_body(entry: &TimelineEntryView) -> String {
+    if entry.kind == TimelineEntryKind::RolloutBoundary {
+        format!("Rollout file: {}", entry.text)
+    } else {
+        entry.text.trim().to_string()
+    }
+}
+
+/// A tool call entry as a collapsed `<details>` block: its first line is
+/// the summary, and the input and result follow in a code block fenced
+/// with more backticks than any run of them inside.
+fn collapsed_tool_call(text: &str) -> String {
+    let text = text.trim();
+    let (summary, body) = text.split_once("\n\n").unwrap_or((text, ""));
+    let mut block = format!("<details>\n<summary>{}</summary>\n", escape_html(summary));
+    if !body.is_empty() {
+        let longest_run = body
+            .split(|ch| ch != '`')
+            .map(str::len)
+            .max()
+            .unwrap_or_default();
+        let fence = "`".repeat(longest_run.max(2) + 1);
+        block.push_str(&format!("\n{fence}\n{body}\n{fence}\n"));
+    }
+    block.push_str("\n</details>");
+    block
+}
+
+/// `Repeated N times in a row.` for an entry `--dedupe` collapsed copies into.
+fn repeat_notice(entry: &TimelineEntryView) -> Option<String> {
+    entry
+        .repeats
+        .map(|repeats| format!("Repeated {repeats} times in a row."))
+}
+
+/// `Focused on entry N (msg-N); showing entries A-B of T.` for a head with
+/// a focus.
+fn focus_notice(head: &TimelineHead<'_>, code: fn(&str) -> String) -> Option<String> {
+    let focus = head.focus?;
+    Some(format!(
+        "Focused on entry {} ({}); showing entries {}-{} of {}.",
+        focus.number,
+        code(&focus.anchor),
+        head.range.start + 1,
+        head.range.end,
+        head.total
+    ))
+}
+
+/// Counts the entries a renderer was given, for the empty-result notices.
+#[derive(Debug, Default)]
+struct Shown {
+    total: usize,
+    entries: usize,
+}
+
+impl Shown {
+    fn head(&mut self, head: &TimelineHead<'_>) {
+        self.total = head.total;
+    }
+
+    /// The notice for a timeline that turned out to show nothing, if any.
+    fn empty_notice(&self) -> Option<&'static str> {
+        match (self.total, self.entries) {
+            (0, _) => Some(NO_ENTRIES),
+            (_, 0) => Some(NO_MATCHING_ENTRIES),
+            _ => None,
+        }
+    }
+}
+
+/// The Markdown timeline of plain reads, with YAML frontmatter, `## N.`
+/// headings, and an HTML anchor before each entry.
+#[derive(Debug, Default)]
+pub struct MarkdownRenderer {
+    provenance: bool,
+    shown: Shown,
+    output: String,
+}
+
+impl MarkdownRenderer {
+    pub fn new(provenance: bool) -> Self {
+        Self {
+            provenance,
+            ..Self::default()
+        }
+    }
+}
+
+impl Renderer for MarkdownRenderer {
+    fn render_head(&mut self, head: &TimelineHead<'_>) {
+        self.shown.head(head);
+        let mut frontmatter = YamlMapping::new();
+        frontmatter.insert("uri", head.uri.as_agents_string());
+        frontmatter.insert(
+            "thread_source",
+            head.thread_source.to_string_lossy().into_owned(),
+        );
+
+        let output = &mut self.output;
+        output.push_str(&frontmatter.to_frontmatter());
+        output.push('\n');
+        output.push_str("# Thread\n\n");
+        if let Some(parent) = head.parent {
+            output.push_str("## Parent\n\n");
+            output.push_str(&format!("- Parent Thread: `{}`\n", parent.uri));
+            output.push_str(&format!("- Subagent View: `{}`\n", parent.subagent_uri));
+            if let Some(spawned_at) = &parent.spawned_at {
+                output.push_str(&format!("- Spawned At: `{spawned_at}`\n"));
+            }
+            output.push('\n');
+        }
+        output.push_str("## Timeline\n\n");
+        if let Some(notice) = focus_notice(head, |anchor| format!("`{anchor}`")) {
+            output.push_str(&format!("_{notice}_\n\n"));
+        }
+    }
+
+    fn render_entry(&mut self, entry: &TimelineEntryView, notes: &[&Annotation]) {
+        self.shown.entries += 1;
+        let output = &mut self.output;
+        output.push_str(&format!(
+            "<a id=\"{}\" data-entry-id=\"{}\"></a>\n\n",
+            entry.anchor,
+            escape_attribute(&entry.id)
+        ));
+        output.push_str(&format!(
+            "## {}. {}\n\n",
+            entry.number,
+            entry_heading(entry)
+        ));
+        if self.provenance
+            && let Some(source) = &entry.source
+        {
+            output.push_str(&format!("_Source: `{source}`_\n\n"));
+        }
+        if let Some(notice) = repeat_notice(entry) {
+            output.push_str(&format!("_{notice}_\n\n"));
+        }
+        match entry.kind {
+            TimelineEntryKind::RolloutBoundary => {
+                output.push_str(&format!("Rollout file: `{}`", entry.text));
+            }
+            TimelineEntryKind::ToolCall => output.push_str(&collapsed_tool_call(&entry.text)),
+            _ => output.push_str(entry.text.trim()),
+        }
+        output.push_str("\n\n");
+
+        for annotation in notes {
+            output.push_str("> [!NOTE]\n");
+            for line in annotation.note.lines() {
+                output.push_str(format!("> {line}").trim_end());
+                output.push('\n');
+            }
+            output.push('\n');
+        }
+    }
+
+    fn finish(&mut self) -> String {
+        if let Some(notice) = self.shown.empty_notice() {
+            self.output.push_str(&format!("_{notice}_\n"));
+        }
+        std::mem::take(&mut self.output)
+    }
+}
+
+/// One JSON document: the thread's URI, source, and parent, and the entries
+/// shown with their notes.
+#[derive(Debug, Default)]
+pub struct JsonRenderer {
+    document: Option<JsonTimeline>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonTimeline {
+    uri: String,
+    thread_source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent: Option<ThreadParent>,
+    total_entries: usize,
+    entries: Vec<JsonEntry>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonEntry {
+    #[serde(flatten)]
+    entry: TimelineEntryView,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    notes: Vec<String>,
+}
+
+impl Renderer for JsonRenderer {
+    fn render_head(&mut self, head: &TimelineHead<'_>) {
+        self.document = Some(JsonTimeline {
+            uri: head.uri.as_agents_string(),
+            thread_source: head.thread_source.display().to_string(),
+            parent: head.parent.cloned(),
+            total_entries: head.total,
+            entries: Vec::new(),
+        });
+    }
+
+    fn render_entry(&mut self, entry: &TimelineEntryView, notes: &[&Annotation]) {
+        if let Some(document) = &mut self.document {
+            document.entries.push(JsonEntry {
+                entry: entry.clone(),
+                notes: notes.iter().map(|note| note.note.clone()).collect(),
+            });
+        }
+    }
+
+    fn finish(&mut self) -> String {
+        let json = serde_json::to_string_pretty(&self.document.take())
+            .expect("json serialization should succeed");
+        format!("{json}\n")
+    }
+}
+
+/// A standalone HTML page. Each entry is a `<section>` whose id is its
+/// anchor, so `page.html#msg-N` links to it.
+#[derive(Debug, Default)]
+pub struct HtmlRenderer {
+    provenance: bool,
+    shown: Shown,
+    output: String,
+}
+
+impl HtmlRenderer {
+    pub fn new(provenance: bool) -> Self {
+        Self {
+            provenance,
+            ..Self::default()
+        }
+    }
+}
+
+impl Renderer for HtmlRenderer {
+    fn render_head(&mut self, head: &TimelineHead<'_>) {
+        self.shown.head(head);
+        let uri = escape_html(&head.uri.as_agents_string());
+        let output = &mut self.output;
+        output.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n");
+        output.push_str("<meta charset=\"utf-8\">\n");
+        output.push_str(&format!("<title>{uri}</title>\n"));
+        output.push_str("</head>\n<body>\n<h1>Thread</h1>\n<ul>\n");
+        output.push_str(&format!("<li>URI: <code>{uri}</code></li>\n"));
+        output.push_str(&format!(
+            "<li>Source: <code>{}</code></li>\n",
+            escape_html(&head.thread_source.to_string_lossy())
+        ));
+        if let Some(parent) = head.parent {
+            output.push_str(&format!(
+                "<li>Parent Thread: <code>{}</code></li>\n",
+                escape_html(&parent.uri)
+            ));
+            if let Some(spawned_at) = &parent.spawned_at {
+                output.push_str(&format!(
+                    "<li>Spawned At: <code>{}</code></li>\n",
+                    escape_html(spawned_at)
+                ));
+            }
+        }
+        output.push_str("</ul>\n<h2>Timeline</h2>\n");
+        if let Some(notice) = focus_notice(head, |anchor| {
+            format!("<a href=\"#{anchor}\"><code>{anchor}</code></a>")
+        }) {
+            output.push_str(&format!("<p><em>{notice}</em></p>\n"));
+        }
+    }
+
+    fn render_entry(&mut self, entry: &TimelineEntryView, notes: &[&Annotation]) {
+        self.shown.entries += 1;
+        let output = &mut self.output;
+        output.push_str(&format!(
+            "<section id=\"{}\" class=\"entry {}\" data-entry-id=\"{}\">\n",
+            entry.anchor,
+            entry.kind,
+            escape_attribute(&entry.id)
+        ));
+        output.push_str(&format!(
+            "<h3>{}. {}</h3>\n",
+            entry.number,
+            entry_heading(entry)
+        ));
+        if let Some(timestamp) = &entry.timestamp {
+            output.push_str(&format!("<p><time>{}</time></p>\n", escape_html(timestamp)));
+        }
+        if self.provenance
+            && let Some(source) = &entry.source
+        {
+            output.push_str(&format!(
+                "<p><em>Source: <code>{}</code></em></p>\n",
+                escape_html(source)
+            ));
+        }
+        if let Some(notice) = repeat_notice(entry) {
+            output.push_str(&format!("<p><em>{notice}</em></p>\n"));
+        }
+        output.push_str(&format!("<pre>{}</pre>\n", escape_html(&entry_body(entry))));
+        for annotation in notes {
+            output.push_str(&format!(
+                "<aside class=\"note\"><pre>{}</pre></aside>\n",
+                escape_html(annotation.note.trim())
+            ));
+        }
+        output.push_str("</section>\n");
+    }
+
+    fn finish(&mut self) -> String {
+        if let Some(notice) = self.shown.empty_notice() {
+            self.output.push_str(&format!("<p><em>{notice}</em></p>\n"));
+        }
+        self.output.push_str("</body>\n</html>\n");
+        std::mem::take(&mut self.output)
+    }
+}
+
+/// Plain text without markup, for pasting or piping into other tools.
+#[derive(Debug, Default)]
+pub struct TextRenderer {
+    provenance: bool,
+    shown: Shown,
+    output: String,
+}
+
+impl TextRenderer {
+    pub fn new(provenance: bool) -> Self {
+        Self {
+            provenance,
+            ..Self::default()
+        }
+    }
+}
+
+impl Renderer for TextRenderer {
+    fn render_head(&mut self, head: &TimelineHead<'_>) {
+        self.shown.head(head);
+        let output = &mut self.output;
+        output.push_str(&format!("Thread: {}\n", head.uri.as_agents_string()));
+        output.push_str(&format!("Source: {}\n", head.thread_source.display()));
+        if let Some(parent) = head.parent {
+            output.push_str(&format!("Parent: {}\n", parent.uri));
+        }
+        output.push('\n');
+        if let Some(notice) = focus_notice(head, ToString::to_string) {
+            output.push_str(&format!("{notice}\n\n"));
+        }
+    }
+
+    fn render_entry(&mut self, entry: &TimelineEntryView, notes: &[&Annotation]) {
+        self.shown.entries += 1;
+        let output = &mut self.output;
+        output.push_str(&format!("{}. {}\n", entry.number, entry_heading(entry)));
+        if self.provenance
+            && let Some(source) = &entry.source
+        {
+            output.push_str(&format!("Source: {source}\n"));
+        }
+        if let Some(notice) = repeat_notice(entry) {
+            output.push_str(&format!("{notice}\n"));
+        }
+        output.push('\n');
+        output.push_str(&entry_body(entry));
+        output.push_str("\n\n");
+        for annotation in notes {
+            for (idx, line) in annotation.note.lines().enumerate() {
+                let prefix = if idx == 0 { "Note: " } else { "      " };
+                output.push_str(format!("{prefix}{line}").trim_end());
+                output.push('\n');
+            }
+            output.push('\n');
+        }
+    }
+
+    fn finish(&mut self) -> String {
+        if let Some(notice) = self.shown.empty_notice() {
+            self.output.push_str(&format!("{notice}\n"));
+        }
+        std::mem::take(&mut self.output)
+    }
+}
+
+/// A CSV or TSV table with a header row and one row per entry: its number,
+/// kind, recorded timestamp (empty when there is none), character count,
+/// and text.
+#[derive(Debug)]
+pub struct TableRenderer {
+    separator: &'static str,
+    field: fn(&str) -> String,
+    output: String,
+}
+
+impl TableRenderer {
+    pub fn new(format: TableFormat) -> Self {
+        let (separator, field): (&str, fn(&str) -> String) = match format {
+            TableFormat::Csv => (",", csv_field),
+            TableFormat::Tsv => ("\t", tsv_field),
+        };
+        let mut output = ["index", "role", "timestamp", "chars", "text"].join(separator);
+        output.push('\n');
+        Self {
+            separator,
+            field,
+            output,
+        }
+    }
+}
+
+impl Renderer for TableRenderer {
+    fn render_head(&mut self, _head: &TimelineHead<'_>) {}
+
+    fn render_entry(&mut self, entry: &TimelineEntryView, _notes: &[&Annotation]) {
+        let text = entry.text.trim();
+        let row = [
+            entry.number.to_string(),
+            entry.kind.to_string(),
+            (self.field)(entry.timestamp.as_deref().unwrap_or_default()),
+            text.chars().count().to_string(),
+            (self.field)(text),
+        ];
+        self.output.push_str(&row.join(self.separator));
+        self.output.push('\n');
+    }
+
+    fn finish(&mut self) -> String {
+        std::mem::take(&mut self.output)
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn tsv_field(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+pub(crate) fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use serde_json::Value;
+
+    use crate::model::{Annotation, TimelineFormat};
+    use crate::render::{TimelineOptions, render_timeline};
+    use crate::renderer::renderer_for;
+    use crate::uri::ThreadUri;
+
+    const RAW: &str = r#"{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"is 1 < 2?"}]}}
+{"type":"response_item","payload":{"type":"message","role":"assistant","content":[{"type":"output_text","text":"yes"}]}}
+"#;
+
+    fn render(format: TimelineFormat, raw: &str, options: &TimelineOptions<'_>) -> String {
+        let uri = ThreadUri::parse("codex://019c871c-b1f9-7f60-9c4f-87ed09f13592").expect("uri");
+        render_timeline(
+            &uri,
+            Path::new("/tmp/mock"),
+            raw,
+            options,
+            renderer_for(format, false).as_mut(),
+        )
+        .expect("render")
+    }
+
+    #[test]
+    fn every_format_renders_the_same_entries() {
+        let annotations = [Annotation {
+            provider: "codex".to_string(),
+            session_id: "019c871c-b1f9-7f60-9c4f-87ed09f13592".to_string(),
+            message: 2,
+            note: "checked".to_string(),
+            created_at: "2026-02-23T00:00:00Z".to_string(),
+        }];
+        let options = TimelineOptions {
+            annotations: &annotations,
+            ..TimelineOptions::default()
+        };
+
+        let json = render(TimelineFormat::Json, RAW, &options);
+        let json = serde_json::from_str::<Value>(&json).expect("json");
+        assert_eq!(json["total_entries"], 2);
+        assert_eq!(json["entries"][0]["kind"], "user");
+        assert_eq!(json["entries"][1]["anchor"], "msg-2");
+        assert_eq!(json["entries"][1]["notes"][0], "checked");
+
+        let html = render(TimelineFormat::Html, RAW, &options);
+        assert!(html.starts_with("<!DOCTYPE html>\n"));
+        assert!(html.contains("<section id=\"msg-1\" class=\"entry user\""));
+        assert!(html.contains("<pre>is 1 &lt; 2?</pre>"));
+        assert!(html.contains("<aside class=\"note\"><pre>checked</pre></aside>"));
+        assert!(html.ends_with("</body>\n</html>\n"));
+
+        let text = render(TimelineFormat::Text, RAW, &options);
+        assert!(text.starts_with("Thread: agents://codex/019c871c-b1f9-7f60-9c4f-87ed09f13592\n"));
+        assert!(text.contains("1. User\n\nis 1 < 2?\n\n2. Assistant\n\nyes\n\nNote: checked\n"));
+    }
+
+    #[test]
+    fn empty_timelines_say_so() {
+        let options = TimelineOptions::default();
+        assert!(
+            render(TimelineFormat::Text, "", &options)
+                .ends_with("No user/assistant messages or compact events found.\n")
+        );
+        assert!(
+            render(TimelineFormat::Html, "", &options)
+                .contains("<p><em>No user/assistant messages or compact events found.</em></p>")
+        );
+        let json = render(TimelineFormat::Json, "", &options);
+        assert!(json.contains("\"entries\": []"));
+    }
+}
@@ -0,0 +1,456 @@
+//! Semantic search over extracted messages (`xurl search --semantic`).
+//!
+//! Texts are embedded by an external command (`XURL_EMBEDDING_CMD`), so any
+//! local model works, e.g. a small script around an ONNX MiniLM. The command
+//! reads one JSON string per line on stdin and prints one JSON array of
+//! numbers per line, in the same order. Message vectors are kept per
+//! provider in `semantic/<provider>.json` under the cache directory; later
+//! searches only embed threads that are new or changed. Changing the command
+//! rebuilds the index, since vectors of different models do not compare.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use crate::atomic::write_atomic;
+use crate::error::{Result, XurlError};
+use crate::model::{
+    MessageRole, ProviderKind, SemanticHit, SemanticSearchRequest, SemanticSearchResult,
+    ThreadListing, ThreadMessage, Warning,
+};
+use crate::provider::ProviderRoots;
+use crate::render::{self, ReadOptions};
+use crate::service::{
+    agents_thread_uri, keep_newest_listings, provider_for, read_thread_raw, resolve_listing,
+    session_key,
+};
+use crate::summarize::run_shell_command;
+
+/// Directory under the cache root holding the per-provider indexes.
+const INDEX_DIR: &str = "semantic";
+const INDEX_VERSION: u32 = 1;
+/// Longest prefix of a message that is embedded.
+const EMBED_MAX_CHARS: usize = 2000;
+const PREVIEW_CHARS: usize = 160;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SemanticIndex {
+    version: u32,
+    command: String,
+    threads: Vec<IndexedThread>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedThread {
+    session_id: String,
+    path: PathBuf,
+    /// The listing's update time when the thread was embedded.
+    updated_ms: u64,
+    messages: Vec<IndexedMessage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedMessage {
+    role: MessageRole,
+    preview: String,
+    vector: Vec<f32>,
+}
+
+/// Brings the index of every requested provider up to date, then ranks the
+/// indexed messages by cosine similarity to the query.
+pub fn semantic_search(
+    req: &SemanticSearchRequest,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+) -> Result<SemanticSearchResult> {
+    if req.command.trim().is_empty() {
+        return Err(XurlError::InvalidMode(
+            "semantic search needs an embedding command: pass --embed-cmd or set XURL_EMBEDDING_CMD"
+                .to_string(),
+        ));
+    }
+    let providers = if req.providers.is_empty() {
+        ProviderKind::all()
+            .iter()
+            .copied()
+            .filter(|kind| *kind != ProviderKind::File)
+            .collect()
+    } else {
+        req.providers.clone()
+    };
+
+    let mut warnings = Vec::new();
+    let mut embedded_messages = 0;
+    let mut indexes = Vec::new();
+    for provider in providers {
+        let (index, embedded) =
+            update_index(provider, &req.command, roots, options, &mut warnings)?;
+        embedded_messages += embedded;
+        indexes.push((provider, index));
+    }
+
+    let query = embed(&req.command, std::slice::from_ref(&req.query))?
+        .pop()
+        .unwrap_or_default();
+    let mut hits = Vec::new();
+    for (provider, index) in &indexes {
+        let provider = provider.to_string();
+        for thread in &index.threads {
+            for message in &thread.messages {
+                if let Some(score) = cosine(&query, &message.vector) {
+                    hits.push(SemanticHit {
+                        uri: agents_thread_uri(&provider, &thread.session_id, None),
+                        role: message.role,
+                        score,
+                        preview: message.preview.clone(),
+                    });
+                }
+            }
+        }
+    }
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(req.limit);
+
+    Ok(SemanticSearchResult {
+        query: req.query.clone(),
+        hits,
+        indexed_threads: indexes.iter().map(|(_, index)| index.threads.len()).sum(),
+        embedded_messages,
+        warnings,
+    })
+}
+
+pub fn render_semantic_search_markdown(result: &SemanticSearchResult) -> String {
+    let mut output = String::new();
+    output.push_str("# Semantic Search\n\n");
+    output.push_str(&format!("- Query: `{}`\n", result.query));
+    output.push_str(&format!(
+        "- Threads indexed: `{}`\n",
+        result.indexed_threads
+    ));
+    output.push_str(&format!(
+        "- Messages embedded: `{}`\n",
+        result.embedded_messages
+    ));
+
+    output.push_str("\n## Hits\n\n");
+    if result.hits.is_empty() {
+        output.push_str("_No messages found._\n");
+    }
+    for (idx, hit) in result.hits.iter().enumerate() {
+        output.push_str(&format!(
+            "{}. `{}` ({}, score `{:.3}`)\n   > {}\n",
+            idx + 1,
+            hit.uri,
+            hit.role,
+            hit.score,
+            hit.preview
+        ));
+    }
+
+    if !result.warnings.is_empty() {
+        output.push_str("\n## Warnings\n\n");
+        for warning in &result.warnings {
+            output.push_str(&format!("- {warning}\n"));
+        }
+    }
+
+    output
+}
+
+pub fn render_semantic_search_json(result: &SemanticSearchResult) -> Result<String> {
+    serde_json::to_string_pretty(result)
+        .map(|json| format!("{json}\n"))
+        .map_err(|err| XurlError::Serialization(err.to_string()))
+}
+
+/// Re-embeds the threads of `provider` that are new or changed since its
+/// index was written, drops those that are gone, and returns the index with
+/// the number of messages embedded.
+fn update_index(
+    provider: ProviderKind,
+    command: &str,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<(SemanticIndex, usize)> {
+    let path = index_path(roots, provider);
+    let mut previous = load_index(&path)
+        .filter(|index| index.version == INDEX_VERSION && index.command == command)
+        .map(|index| index.threads)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|thread| (session_key(provider, &thread.session_id), thread))
+        .collect::<HashMap<_, _>>();
+    let previous_count = previous.len();
+
+    let mut listings = match provider_for(provider, roots).list_threads() {
+        Ok(listings) => listings,
+        Err(err) => {
+            warnings.push(Warning::warn(
+                "list_failed",
+                format!("failed listing {provider} threads: {err}"),
+            ));
+            Vec::new()
+        }
+    };
+    keep_newest_listings(&mut listings);
+
+    let mut threads = Vec::new();
+    let mut texts = Vec::new();
+    let mut reused = 0;
+    for listing in listings {
+        let updated_ms = listing
+            .updated_at
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| {
+                u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX)
+            });
+        if let Some(thread) = previous.remove(&session_key(provider, &listing.session_id))
+            && thread.path == listing.path
+            && thread.updated_ms == updated_ms
+        {
+            threads.push(thread);
+            reused += 1;
+            continue;
+        }
+
+        let messages = match listing_messages(&listing, roots, options) {
+            Ok(messages) => messages,
+            Err(err) => {
+                warnings.push(Warning::info(
+                    "index_failed",
+                    format!("failed indexing {}: {err}", listing.path.display()),
+                ));
+                continue;
+            }
+        };
+        let messages = messages
+            .into_iter()
+            .filter(|message| !message.text.trim().is_empty())
+            .map(|message| {
+                texts.push(
+                    message
+                        .text
+                        .chars()
+                        .take(EMBED_MAX_CHARS)
+                        .collect::<String>(),
+                );
+                IndexedMessage {
+                    role: message.role,
+                    preview: preview(&message.text),
+                    vector: Vec::new(),
+                }
+            })
+            .collect();
+        threads.push(IndexedThread {
+            session_id: listing.session_id,
+            path: listing.path,
+            updated_ms,
+            messages,
+        });
+    }
+
+    let embedded = texts.len();
+    if embedded > 0 {
+        let mut vectors = embed(command, &texts)?.into_iter();
+        for message in threads
+            .iter_mut()
+            .flat_map(|thread| thread.messages.iter_mut())
+            .filter(|message| message.vector.is_empty())
+        {
+            message.vector = vectors.next().unwrap_or_default();
+        }
+    }
+
+    let index = SemanticIndex {
+        version: INDEX_VERSION,
+        command: command.to_string(),
+        threads,
+    };
+    if reused != index.threads.len() || reused != previous_count {
+        save_index(&path, &index)?;
+    }
+    Ok((index, embedded))
+}
+
+fn index_path(roots: &ProviderRoots, provider: ProviderKind) -> PathBuf {
+    roots
+        .cache_root
+        .join(INDEX_DIR)
+        .join(format!("{provider}.json"))
+}
+
+/// `None` when there is no index yet or it cannot be read; it is then
+/// rebuilt.
+fn load_index(path: &Path) -> Option<SemanticIndex> {
+    let raw = fs::read(path).ok()?;
+    serde_json::from_slice(&raw).ok()
+}
+
+fn save_index(path: &Path, index: &SemanticIndex) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|source| XurlError::io(dir, source))?;
+    }
+    let encoded =
+        serde_json::to_vec(index).map_err(|err| XurlError::Serialization(err.to_string()))?;
+    write_atomic(path, &encoded, false)
+}
+
+fn listing_messages(
+    listing: &ThreadListing,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+) -> Result<Vec<ThreadMessage>> {
+    let path = resolve_listing(listing, roots)?.path;
+    let raw = read_thread_raw(&path)?;
+    render::extract_messages(
+        listing.provider,
+        &path,
+        &raw,
+        &mut options.extraction_policy(),
+    )
+}
+
+/// Runs the embedding command over `texts`, one vector per text.
+fn embed(command: &str, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+    let mut input = String::new();
+    for text in texts {
+        let line =
+            serde_json::to_string(text).map_err(|err| XurlError::Serialization(err.to_string()))?;
+        input.push_str(&line);
+        input.push('\n');
+    }
+
+    let output = run_shell_command(command, &input)?;
+    let vectors = output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(idx, line)| {
+            serde_json::from_str::<Vec<f32>>(line).map_err(|err| {
+                XurlError::Embedding(format!(
+                    "output line {} is not a JSON array of numbers: {err}",
+                    idx + 1
+                ))
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if vectors.len() != texts.len() {
+        return Err(XurlError::Embedding(format!(
+            "expected {} vectors, got {}",
+            texts.len(),
+            vectors.len()
+        )));
+    }
+    Ok(vectors)
+}
+
+/// `None` when the vectors differ in length or one of them is zero.
+fn cosine(a: &[f32], b: &[f32]) -> Option<f32> {
+    if a.len() != b.len() {
+        return None;
+    }
+    let dot = a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norms = norm(a) * norm(b);
+    (norms > 0.0).then(|| dot / norms)
+}
+
+fn preview(text: &str) -> String {
+    let flattened = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flattened.chars().count() <= PREVIEW_CHARS {
+        return flattened;
+    }
+    let truncated = flattened.chars().take(PREVIEW_CHARS).collect::<String>();
+    format!("{}...", truncated.trim_end())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use crate::model::{MessageRole, ProviderKind, SemanticSearchRequest};
+    use crate::provider::ProviderRoots;
+    use crate::render::ReadOptions;
+    use crate::semantic::semantic_search;
+
+    /// Embeds texts mentioning a schema along one axis, everything else
+    /// along the other, and counts its runs in `runs`.
+    const EMBED: &str = r#"echo run >> runs
+while IFS= read -r line; do
+  case "$line" in
+    *schema*) echo '[1, 0.1]' ;;
+    *) echo '[0, 1]' ;;
+  esac
+done
+"#;
+
+    fn rollout(text_user: &str, text_assistant: &str) -> String {
+        [("user", "input_text", text_user), ("assistant", "output_text", text_assistant)]
+            .iter()
+            .map(|(role, kind, text)| {
+                serde_json::json!({
+                    "type": "response_item",
+                    "payload": {"type": "message", "role": role, "content": [{"type": kind, "text": text}]},
+                })
+                .to_string()
+                    + "\n"
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ranks_messages_by_meaning_and_reuses_the_index() {
+        let temp = tempdir().expect("tempdir");
+        let sessions = temp.path().join("codex/sessions/2026/03/01");
+        fs::create_dir_all(&sessions).expect("mkdir");
+        fs::write(
+            sessions.join("rollout-2026-03-01T09-00-00-019c8129-f668-7951-8d56-cc5513541c26.jsonl"),
+            rollout("fix the login bug", "done"),
+        )
+        .expect("write");
+        fs::write(
+            sessions.join("rollout-2026-03-01T10-00-00-019c871c-b1f9-7f60-9c4f-87ed09f13592.jsonl"),
+            rollout(
+                "which database?",
+                "we decided the schema keeps one table per provider",
+            ),
+        )
+        .expect("write");
+
+        let roots = ProviderRoots::for_test(temp.path());
+        let runs = temp.path().join("embed.runs");
+        let script = temp.path().join("embed.sh");
+        fs::write(&script, EMBED.replace("runs", &runs.display().to_string())).expect("script");
+        let req = SemanticSearchRequest {
+            query: "where did we decide the schema".to_string(),
+            providers: vec![ProviderKind::Codex],
+            limit: 2,
+            command: format!("sh '{}'", script.display()),
+        };
+
+        let result = semantic_search(&req, &roots, &ReadOptions::default()).expect("search");
+        assert_eq!(result.indexed_threads, 2);
+        assert_eq!(result.embedded_messages, 4);
+        assert_eq!(result.hits.len(), 2);
+        assert_eq!(
+            result.hits[0].uri,
+            "agents://codex/019c871c-b1f9-7f60-9c4f-87ed09f13592"
+        );
+        assert_eq!(result.hits[0].role, MessageRole::Assistant);
+        assert!(result.hits[0].score > 0.99);
+        assert!(result.hits[1].score < 0.2);
+        assert!(roots.cache_root.join("semantic/codex.json").is_file());
+
+        // Unchanged threads are not embedded again; only the query is.
+        let result = semantic_search(&req, &roots, &ReadOptions::default()).expect("search again");
+        assert_eq!(result.embedded_messages, 0);
+        assert_eq!(fs::read_to_string(&runs).expect("runs").lines().count(), 3);
+    }
+}
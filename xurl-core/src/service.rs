@@ -1,53 +1,95 @@
+use std::cmp::Reverse;
 use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::time::UNIX_EPOCH;
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
 
+use chrono::{DateTime, Utc};
 use serde_json::Value;
 
+use crate::annotate::thread_annotations;
+use crate::compression::{Compression, read_thread_file};
+use crate::dedupe::thread_fingerprint;
+use crate::encoding::{decode_transcript, is_utf16};
 use crate::error::{Result, XurlError};
+use crate::head::{
+    HeadBody, HeadDocument, HeadPage, HeadPiEntry, HeadSubagent, HeadSubagentDetail,
+};
 use crate::jsonl;
+use crate::jsonl_index::{JsonlIndex, LineKind};
 use crate::model::{
-    MessageRole, PiEntryListItem, PiEntryListView, PiEntryQuery, ProviderKind, ResolvedThread,
-    SubagentDetailView, SubagentExcerptMessage, SubagentLifecycleEvent, SubagentListItem,
-    SubagentListView, SubagentQuery, SubagentRelation, SubagentThreadRef, SubagentView,
-    WriteRequest, WriteResult,
+    ExtractionMode, GcMode, GcReport, GcRequest, MessageRole, PiEntryListItem, PiEntryListView,
+    PiEntryQuery, ProviderKind, RecentThread, RecentThreadsView, ResolutionMeta, ResolvedThread,
+    SessionInfo, StorageKind, SubagentDetailView, SubagentExcerptMessage, SubagentLifecycleEvent,
+    SubagentListItem, SubagentListView, SubagentQuery, SubagentRelation, SubagentThreadRef,
+    SubagentView, TableFormat, ThreadListing, ThreadLocation, ThreadMessage, TimelineFormat,
+    ToolUsage, Warning, WriteRequest, WriteResult,
 };
-use crate::provider::amp::AmpProvider;
+use crate::pack::estimate_tokens;
+use crate::provider::amp::{self, AmpProvider};
 use crate::provider::claude::ClaudeProvider;
 use crate::provider::codex::CodexProvider;
-use crate::provider::gemini::GeminiProvider;
+use crate::provider::copilot::CopilotProvider;
+use crate::provider::file::FileProvider;
+use crate::provider::gemini::{GeminiProvider, RemoteChatSource};
+use crate::provider::multi_root::MultiRootProvider;
 use crate::provider::opencode::OpencodeProvider;
 use crate::provider::pi::PiProvider;
-use crate::provider::{Provider, ProviderRoots, WriteEventSink};
-use crate::render;
-use crate::uri::ThreadUri;
-
-const STATUS_PENDING_INIT: &str = "pendingInit";
-const STATUS_RUNNING: &str = "running";
-const STATUS_COMPLETED: &str = "completed";
-const STATUS_ERRORED: &str = "errored";
-const STATUS_SHUTDOWN: &str = "shutdown";
-const STATUS_NOT_FOUND: &str = "notFound";
+use crate::provider::{MaintenanceProvider, Provider, ProviderRoots, WriteEventSink};
+use crate::records::CODEX_ROLLOUT_BOUNDARY;
+use crate::render::{self, ExtractionPolicy, ReadOptions};
+use crate::renderer::renderer_for;
+use crate::session::{codex_spawn_parent, cwd_in_project, read_session_info, read_thread_parent};
+use crate::status::{
+    AmpStatusInference, CodexChildStatusInference, CodexParentStatusInference,
+    GeminiStatusInference, InferredStatus, KNOWN_STATUSES, ParentLifecycle,
+    ProviderStatusInference, SOURCE_CHILD_ROLLOUT, SOURCE_INFERRED, STATUS_COMPLETED,
+    STATUS_NOT_FOUND, STATUS_PENDING_INIT, STATUS_RUNNING, STATUS_SHUTDOWN, TranscriptSignals,
+};
+use crate::timestamp::{TimeWindow, format_rfc3339, normalize_timestamp, parse_timestamp};
+use crate::tools::summarize_tools;
+use crate::uri::{ThreadUri, TimelinePage};
+
+/// Longest first-message preview shown by `xurl recent`.
+const RECENT_PREVIEW_CHARS: usize = 80;
+/// Rollouts at least this large are read through the cached line index.
+const INDEXED_READ_MIN_BYTES: u64 = 4 * 1024 * 1024;
+/// Reads `consistent_reads` makes of a file that keeps growing before it
+/// settles for the latest one.
+const CONSISTENT_READ_ATTEMPTS: usize = 20;
+/// Pause between those reads.
+const CONSISTENT_READ_INTERVAL: Duration = Duration::from_millis(50);
+/// Line kinds needed to render a conversation timeline.
+pub(crate) const TIMELINE_LINES: &[LineKind] =
+    &[LineKind::Message, LineKind::MessageDelta, LineKind::Compact];
 
 #[derive(Debug, Default, Clone)]
 struct AgentTimeline {
     events: Vec<SubagentLifecycleEvent>,
-    states: Vec<String>,
-    has_spawn: bool,
-    has_activity: bool,
+    lifecycle: ParentLifecycle,
     last_update: Option<String>,
+    /// `message` argument of the `spawn_agent` call.
+    prompt: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 struct ClaudeAgentRecord {
     agent_id: String,
+    /// `subagent_type` of the Task call that spawned the agent, if known.
+    agent_name: Option<String>,
+    /// First user message of the agent transcript (the Task prompt).
+    prompt: Option<String>,
+    /// `prompt` input of the Task call in the main transcript, when found.
+    task_prompt: Option<String>,
+    tools: Vec<ToolUsage>,
     path: PathBuf,
     status: String,
     last_update: Option<String>,
     relation: SubagentRelation,
     excerpt: Vec<SubagentExcerptMessage>,
-    warnings: Vec<String>,
+    warnings: Vec<Warning>,
 }
 
 #[derive(Debug, Clone)]
@@ -86,45 +128,538 @@ struct AmpChildAnalysis {
     thread: SubagentThreadRef,
     status: String,
     status_source: String,
+    /// First user message of the child thread, the handoff message.
+    prompt: Option<String>,
+    tools: Vec<ToolUsage>,
     excerpt: Vec<SubagentExcerptMessage>,
     lifecycle: Vec<SubagentLifecycleEvent>,
     relation_evidence: Vec<String>,
+    handoffs: Vec<AmpHandoff>,
 }
 
-pub fn resolve_thread(uri: &ThreadUri, roots: &ProviderRoots) -> Result<ResolvedThread> {
-    match uri.provider {
-        ProviderKind::Amp => AmpProvider::new(&roots.amp_root).resolve(&uri.session_id),
-        ProviderKind::Codex => CodexProvider::new(&roots.codex_root).resolve(&uri.session_id),
-        ProviderKind::Claude => ClaudeProvider::new(&roots.claude_root).resolve(&uri.session_id),
-        ProviderKind::Gemini => GeminiProvider::new(&roots.gemini_root).resolve(&uri.session_id),
-        ProviderKind::Pi => PiProvider::new(&roots.pi_root).resolve(&uri.session_id),
-        ProviderKind::Opencode => {
-            OpencodeProvider::new(&roots.opencode_root).resolve(&uri.session_id)
+/// `session_id` as sessions of `provider` are told apart: OpenCode ids are
+/// case-sensitive; every other provider's ids are not.
+pub(crate) fn session_key(provider: ProviderKind, session_id: &str) -> String {
+    if provider == ProviderKind::Opencode {
+        session_id.to_string()
+    } else {
+        session_id.to_ascii_lowercase()
+    }
+}
+
+/// Expands a session id prefix (`codex://019c87`) to the one stored session
+/// it starts. Full ids and prefixes with no match are returned unchanged so
+/// resolution reports them as usual; several matches are an error.
+pub fn resolve_session_prefix(uri: ThreadUri, roots: &ProviderRoots) -> Result<ThreadUri> {
+    if !uri.has_partial_session_id() {
+        return Ok(uri);
+    }
+
+    let normalize = |id: &str| session_key(uri.provider, id);
+    let prefix = normalize(&uri.session_id);
+    tracing::debug!(provider = %uri.provider, prefix, "expanding session id prefix");
+
+    let mut candidates = BTreeSet::new();
+    for listing in provider_with(uri.provider, roots, Lookup::of(&uri)).list_threads()? {
+        let session_id = normalize(&listing.session_id);
+        if session_id == prefix {
+            return Ok(uri);
+        }
+        // The stored id keeps its case, such as Amp's `T-` prefix.
+        if session_id.starts_with(&prefix) {
+            candidates.insert(listing.session_id);
+        }
+    }
+
+    tracing::debug!(matches = candidates.len(), "session id prefix expanded");
+    let mut candidates = candidates.into_iter();
+    match (candidates.next(), candidates.len()) {
+        (Some(session_id), 0) => Ok(ThreadUri { session_id, ..uri }),
+        (Some(first), _) => Err(XurlError::AmbiguousSessionId {
+            provider: uri.provider.to_string(),
+            prefix: uri.session_id,
+            candidates: std::iter::once(first).chain(candidates).collect(),
+        }),
+        (None, _) => Ok(uri),
+    }
+}
+
+/// Resolves the target of an `any://` URI (`<session_id>[/<child>][#fragment]`)
+/// by probing every provider's local storage in parallel. Exactly one
+/// provider must have the session; several matches are an error that lists
+/// them all.
+pub fn resolve_any_uri(target: &str, roots: &ProviderRoots) -> Result<ThreadUri> {
+    if target.is_empty() {
+        return Err(XurlError::InvalidUri(format!("any://{target}")));
+    }
+
+    // Only local storage is probed; a guess should not download Gemini chats.
+    let roots = &ProviderRoots {
+        gemini_sync_url: None,
+        ..roots.clone()
+    };
+    let mut hits = Vec::new();
+    for probe in map_concurrently(ProviderKind::all().to_vec(), |kind| {
+        probe_provider(kind, target, roots)
+    }) {
+        hits.extend(probe?);
+    }
+    tracing::debug!(
+        target,
+        hits = ?hits.iter().map(ThreadUri::as_agents_string).collect::<Vec<_>>(),
+        "probed providers for any:// URI"
+    );
+
+    match hits.len() {
+        1 => Ok(hits.remove(0)),
+        0 => Err(XurlError::ThreadNotFound {
+            provider: "any".to_string(),
+            session_id: target.to_string(),
+            searched_roots: ProviderKind::all()
+                .iter()
+                .flat_map(|kind| roots.roots_for(*kind))
+                .collect(),
+        }),
+        _ => Err(XurlError::AmbiguousProvider {
+            session_id: target.to_string(),
+            candidates: hits.iter().map(ThreadUri::as_agents_string).collect(),
+        }),
+    }
+}
+
+/// `target` as a URI of `kind` if that provider stores the session. Targets
+/// that are not a valid id for the provider, and storage that cannot be
+/// read, count as misses.
+fn probe_provider(
+    kind: ProviderKind,
+    target: &str,
+    roots: &ProviderRoots,
+) -> Result<Option<ThreadUri>> {
+    let Ok(uri) = ThreadUri::parse(&format!("agents://{kind}/{target}")) else {
+        return Ok(None);
+    };
+    let uri = match resolve_session_prefix(uri, roots) {
+        Ok(uri) => uri,
+        Err(err @ XurlError::AmbiguousSessionId { .. }) => return Err(err),
+        Err(_) => return Ok(None),
+    };
+    Ok(resolve_thread(&uri, roots).is_ok().then_some(uri))
+}
+
+/// The provider reading `kind`'s sessions from every root it has, searched
+/// in order when there are several.
+pub(crate) fn provider_for(kind: ProviderKind, roots: &ProviderRoots) -> Box<dyn Provider> {
+    provider_with(kind, roots, Lookup::default())
+}
+
+/// What one lookup narrows or widens beyond `roots`.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Lookup<'a> {
+    /// The Gemini project hash a URI names, which limits the search to
+    /// `tmp/<hash>/`.
+    gemini_project: Option<&'a str>,
+    /// Every rollout of a resumed Codex session, whether or not reads merge
+    /// them.
+    all_rollouts: bool,
+}
+
+impl<'a> Lookup<'a> {
+    pub(crate) fn of(uri: &'a ThreadUri) -> Self {
+        Self {
+            gemini_project: uri.gemini_project.as_deref(),
+            all_rollouts: false,
         }
     }
 }
 
+fn provider_with(kind: ProviderKind, roots: &ProviderRoots, lookup: Lookup) -> Box<dyn Provider> {
+    match roots.roots_for(kind).as_slice() {
+        [] if kind == ProviderKind::File => Box::new(FileProvider::new()),
+        // A provider left without roots finds no threads.
+        [] => Box::new(MultiRootProvider::new(kind, Vec::new())),
+        [root] => provider_at(kind, root, roots, lookup),
+        all => Box::new(MultiRootProvider::new(
+            kind,
+            all.iter()
+                .map(|root| (root.clone(), provider_at(kind, root, roots, lookup)))
+                .collect(),
+        )),
+    }
+}
+
+pub(crate) fn provider_at(
+    kind: ProviderKind,
+    root: &Path,
+    roots: &ProviderRoots,
+    lookup: Lookup,
+) -> Box<dyn Provider> {
+    match kind {
+        ProviderKind::Amp => Box::new(AmpProvider::new(root)),
+        ProviderKind::Codex => Box::new(
+            CodexProvider::new(root)
+                .with_merge_rollouts(roots.codex_merge_rollouts || lookup.all_rollouts),
+        ),
+        ProviderKind::Claude => {
+            Box::new(ClaudeProvider::new(root).with_project(roots.claude_project.as_deref()))
+        }
+        ProviderKind::Gemini => Box::new(
+            GeminiProvider::new(root)
+                .with_remote(gemini_remote(roots))
+                .with_project(lookup.gemini_project),
+        ),
+        ProviderKind::Pi => Box::new(PiProvider::new(root)),
+        ProviderKind::Opencode => Box::new(OpencodeProvider::new(root)),
+        ProviderKind::Copilot => Box::new(CopilotProvider::new(root)),
+        ProviderKind::File => Box::new(FileProvider::new()),
+    }
+}
+
+#[cfg(feature = "gemini-sync")]
+fn gemini_remote(roots: &ProviderRoots) -> Option<Arc<dyn RemoteChatSource>> {
+    roots.gemini_sync_url.as_ref().map(|url| {
+        Arc::new(crate::provider::gemini_sync::GcloudChatSource::new(url))
+            as Arc<dyn RemoteChatSource>
+    })
+}
+
+#[cfg(not(feature = "gemini-sync"))]
+fn gemini_remote(_roots: &ProviderRoots) -> Option<Arc<dyn RemoteChatSource>> {
+    None
+}
+
+pub fn resolve_thread(uri: &ThreadUri, roots: &ProviderRoots) -> Result<ResolvedThread> {
+    resolve_with(uri, roots, Lookup::of(uri))
+}
+
+fn resolve_with(uri: &ThreadUri, roots: &ProviderRoots, lookup: Lookup) -> Result<ResolvedThread> {
+    tracing::debug!(
+        provider = %uri.provider,
+        session_id = uri.session_id,
+        roots = ?roots.roots_for(uri.provider),
+        "resolving thread"
+    );
+    let mut resolved = provider_with(uri.provider, roots, lookup).resolve(&uri.session_id)?;
+    tracing::debug!(
+        path = %resolved.path.display(),
+        source = resolved.metadata.source,
+        candidates = resolved.metadata.candidate_count,
+        "resolved thread"
+    );
+    resolved.metadata.session = read_session_info(uri.format(), &resolved.path);
+    Ok(resolved)
+}
+
+/// Like [`resolve_thread`], but a Codex session resumed into several
+/// rollouts always lists them all in `merged_paths`, for commands that
+/// work on every file of a thread rather than on what a read shows.
+pub(crate) fn resolve_thread_files(
+    uri: &ThreadUri,
+    roots: &ProviderRoots,
+) -> Result<ResolvedThread> {
+    let lookup = Lookup {
+        all_rollouts: true,
+        ..Lookup::of(uri)
+    };
+    resolve_with(uri, roots, lookup)
+}
+
+/// The files `resolved` was read from, oldest first.
+pub(crate) fn thread_files(resolved: &ResolvedThread) -> Vec<PathBuf> {
+    if resolved.metadata.merged_paths.is_empty() {
+        vec![resolved.path.clone()]
+    } else {
+        resolved.metadata.merged_paths.clone()
+    }
+}
+
+/// Writes go to `provider`'s own root; extra roots are only read.
 pub fn write_thread(
     provider: ProviderKind,
     roots: &ProviderRoots,
     req: &WriteRequest,
     sink: &mut dyn WriteEventSink,
 ) -> Result<WriteResult> {
-    match provider {
+    if req.spawn_agent && provider != ProviderKind::Codex {
+        return Err(XurlError::UnsupportedSubagentSpawn(provider.to_string()));
+    }
+    if req.spawn_agent && req.session_id.is_none() {
+        return Err(XurlError::InvalidMode(
+            "spawning a subagent needs the session to spawn it under".to_string(),
+        ));
+    }
+    roots.ensure_own_root(provider)?;
+    let result = match provider {
         ProviderKind::Amp => AmpProvider::new(&roots.amp_root).write(req, sink),
-        ProviderKind::Codex => CodexProvider::new(&roots.codex_root).write(req, sink),
-        ProviderKind::Claude => ClaudeProvider::new(&roots.claude_root).write(req, sink),
-        ProviderKind::Gemini => GeminiProvider::new(&roots.gemini_root).write(req, sink),
+        #[cfg(feature = "write")]
+        ProviderKind::Codex => CodexProvider::new(&roots.codex_root)
+            .with_binaries(roots.binaries.clone())
+            .with_debug_log(roots.write_debug_log.clone())
+            .write(req, sink),
+        #[cfg(feature = "write")]
+        ProviderKind::Claude => ClaudeProvider::new(&roots.claude_root)
+            .with_binaries(roots.binaries.clone())
+            .with_debug_log(roots.write_debug_log.clone())
+            .write(req, sink),
+        #[cfg(not(feature = "write"))]
+        ProviderKind::Codex | ProviderKind::Claude => Err(XurlError::UnsupportedProviderWrite(
+            format!("{provider} (xurl-core was built without the `write` feature)"),
+        )),
+        ProviderKind::Gemini => GeminiProvider::new(&roots.gemini_root)
+            .with_project(req.gemini_project.as_deref())
+            .write(req, sink),
         ProviderKind::Pi => PiProvider::new(&roots.pi_root).write(req, sink),
         ProviderKind::Opencode => OpencodeProvider::new(&roots.opencode_root).write(req, sink),
+        ProviderKind::Copilot => CopilotProvider::new(&roots.copilot_root).write(req, sink),
+        ProviderKind::File => FileProvider::new().write(req, sink),
+    }?;
+    sink.on_write_finished(&result)?;
+    Ok(result)
+}
+
+/// Like writes, garbage collection only touches `provider`'s own root.
+pub fn collect_garbage(
+    provider: ProviderKind,
+    roots: &ProviderRoots,
+    req: &GcRequest,
+) -> Result<GcReport> {
+    roots.ensure_own_root(provider)?;
+    match provider {
+        ProviderKind::Amp => AmpProvider::new(&roots.amp_root).collect_garbage(req),
+        ProviderKind::Codex => CodexProvider::new(&roots.codex_root).collect_garbage(req),
+        ProviderKind::Claude => ClaudeProvider::new(&roots.claude_root).collect_garbage(req),
+        ProviderKind::Gemini => GeminiProvider::new(&roots.gemini_root).collect_garbage(req),
+        ProviderKind::Pi => PiProvider::new(&roots.pi_root).collect_garbage(req),
+        ProviderKind::Opencode => OpencodeProvider::new(&roots.opencode_root).collect_garbage(req),
+        ProviderKind::Copilot => CopilotProvider::new(&roots.copilot_root).collect_garbage(req),
+        ProviderKind::File => FileProvider::new().collect_garbage(req),
     }
 }
 
-fn read_thread_raw(path: &Path) -> Result<String> {
-    let bytes = fs::read(path).map_err(|source| XurlError::Io {
-        path: path.to_path_buf(),
-        source,
-    })?;
+/// Lists the `limit` most recently updated main threads across every
+/// provider, only those inside `roots.project_dir` when it is set. Providers
+/// whose storage cannot be read are reported as warnings.
+pub fn list_recent_threads(
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+    limit: usize,
+) -> RecentThreadsView {
+    let mut warnings = Vec::new();
+    let mut listings = Vec::new();
+
+    for provider in ProviderKind::all()
+        .iter()
+        .map(|kind| provider_for(*kind, roots))
+    {
+        match provider.list_threads() {
+            Ok(found) => listings.extend(found),
+            Err(err) => warnings.push(Warning::warn(
+                "list_failed",
+                format!("failed listing {} threads: {err}", provider.kind()),
+            )),
+        }
+    }
+
+    keep_newest_listings(&mut listings);
+
+    let threads = listings
+        .into_iter()
+        .filter(|listing| in_project_scope(listing, roots))
+        .take(limit)
+        .map(|listing| {
+            let uri = agents_thread_uri(&listing.provider.to_string(), &listing.session_id, None);
+            let preview = thread_preview(&listing, roots, options, &mut warnings);
+            RecentThread {
+                uri,
+                provider: listing.provider,
+                session_id: listing.session_id,
+                thread_source: listing.path.display().to_string(),
+                last_update: Some(format_rfc3339(DateTime::<Utc>::from(listing.updated_at))),
+                preview,
+            }
+        })
+        .collect();
+
+    RecentThreadsView { threads, warnings }
+}
+
+/// Sorts `listings` newest first and keeps only the newest copy of each
+/// session: a session can be stored more than once (copies, symlinks).
+pub(crate) fn keep_newest_listings(listings: &mut Vec<ThreadListing>) {
+    listings.sort_by_key(|listing| Reverse(listing.updated_at));
+    let mut seen = BTreeSet::new();
+    listings.retain(|listing| {
+        seen.insert((
+            listing.provider.to_string(),
+            session_key(listing.provider, &listing.session_id),
+        ))
+    });
+}
+
+/// The thread `listing` names, ready to read. SQLite-backed sessions only
+/// become readable files once resolved; other listings name their file.
+pub(crate) fn resolve_listing(
+    listing: &ThreadListing,
+    roots: &ProviderRoots,
+) -> Result<ResolvedThread> {
+    if listing.provider.capabilities().storage == StorageKind::Sqlite {
+        let uri = ThreadUri::parse(&agents_thread_uri(
+            &listing.provider.to_string(),
+            &listing.session_id,
+            None,
+        ))?;
+        return resolve_thread(&uri, roots);
+    }
+    Ok(ResolvedThread {
+        provider: listing.provider,
+        session_id: listing.session_id.clone(),
+        path: listing.path.clone(),
+        metadata: ResolutionMeta::default(),
+    })
+}
+
+/// Whether `listing` ran inside `roots.project_dir`, judged by the working
+/// directory its transcript records. Always true without a project; sessions
+/// that record no working directory never match one.
+pub(crate) fn in_project_scope(listing: &ThreadListing, roots: &ProviderRoots) -> bool {
+    let Some(project) = roots.project_dir.as_deref() else {
+        return true;
+    };
+    read_session_info(listing.provider, &listing.path)
+        .cwd
+        .is_some_and(|cwd| cwd_in_project(&cwd, project))
+}
+
+fn thread_preview(
+    listing: &ThreadListing,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+    warnings: &mut Vec<Warning>,
+) -> Option<String> {
+    let path = resolve_listing(listing, roots).ok()?.path;
+    let raw = read_thread_raw(&path).ok()?;
+    let messages = match render::extract_messages(
+        listing.provider,
+        &path,
+        &raw,
+        &mut options.extraction_policy(),
+    ) {
+        Ok(messages) => messages,
+        Err(err) => {
+            warnings.push(Warning::info(
+                "preview_unreadable",
+                format!("failed reading preview for {}: {err}", path.display()),
+            ));
+            return None;
+        }
+    };
+
+    let first_user = messages
+        .into_iter()
+        .find(|message| message.role == MessageRole::User)?;
+    let flattened = first_user
+        .text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if flattened.chars().count() <= RECENT_PREVIEW_CHARS {
+        return Some(flattened);
+    }
+    let truncated = flattened
+        .chars()
+        .take(RECENT_PREVIEW_CHARS)
+        .collect::<String>();
+    Some(format!("{}...", truncated.trim_end()))
+}
+
+pub fn render_recent_threads_markdown(view: &RecentThreadsView) -> String {
+    let mut output = String::new();
+    output.push_str("# Recent Threads\n\n");
+
+    if view.threads.is_empty() {
+        output.push_str("_No threads found._\n");
+    }
+    for (idx, thread) in view.threads.iter().enumerate() {
+        output.push_str(&format!(
+            "{}. `{}` ({}, updated `{}`)\n",
+            idx + 1,
+            thread.uri,
+            thread.provider,
+            thread.last_update.as_deref().unwrap_or("unknown")
+        ));
+        if let Some(preview) = &thread.preview {
+            output.push_str(&format!("   > {preview}\n"));
+        }
+    }
+
+    if !view.warnings.is_empty() {
+        output.push_str("\n## Warnings\n\n");
+        for warning in &view.warnings {
+            output.push_str(&format!("- {warning}\n"));
+        }
+    }
+
+    output
+}
+
+pub fn render_gc_report_markdown(report: &GcReport) -> String {
+    let mut output = String::new();
+    output.push_str("# Session GC\n\n");
+    output.push_str(&format!("- Provider: `{}`\n", report.provider));
+    output.push_str(&format!(
+        "- Mode: `{}`{}\n",
+        report.mode,
+        if report.dry_run && report.mode != GcMode::Report {
+            " (dry run)"
+        } else {
+            ""
+        }
+    ));
+    output.push_str(&format!("- Inactive Before: `{}`\n", report.cutoff));
+    output.push_str(&format!(
+        "- Sessions: `{}` (`{}` bytes)\n\n",
+        report.entries.len(),
+        report
+            .entries
+            .iter()
+            .map(|entry| entry.size_bytes)
+            .sum::<u64>()
+    ));
+
+    if report.entries.is_empty() {
+        output.push_str("_No stale sessions found._\n");
+    }
+    for entry in &report.entries {
+        output.push_str(&format!(
+            "- `{}` last activity `{}`: `{}`",
+            agents_thread_uri(&report.provider.to_string(), &entry.session_id, None),
+            entry.last_activity.as_deref().unwrap_or("unknown"),
+            entry.path.display()
+        ));
+        if let Some(destination) = &entry.destination {
+            output.push_str(&format!(" -> `{}`", destination.display()));
+        }
+        output.push('\n');
+    }
+
+    if !report.warnings.is_empty() {
+        output.push_str("\n## Warnings\n\n");
+        for warning in &report.warnings {
+            output.push_str(&format!("- {warning}\n"));
+        }
+    }
+
+    output
+}
+
+pub(crate) fn read_thread_raw(path: &Path) -> Result<String> {
+    read_thread_text(path).map(|(text, _)| text)
+}
+
+/// Reads a thread file as text. Byte order marks, UTF-16, and invalid UTF-8
+/// are decoded rather than rejected; the warnings say what was converted.
+/// Compressed files are decompressed first.
+pub(crate) fn read_thread_text(path: &Path) -> Result<(String, Vec<Warning>)> {
+    let bytes = read_thread_file(path).map_err(|source| XurlError::io(path, source))?;
 
     if bytes.is_empty() {
         return Err(XurlError::EmptyThreadFile {
@@ -132,135 +667,494 @@ fn read_thread_raw(path: &Path) -> Result<String> {
         });
     }
 
-    String::from_utf8(bytes).map_err(|_| XurlError::NonUtf8ThreadFile {
-        path: path.to_path_buf(),
-    })
+    Ok(decode_transcript(path, &bytes))
 }
 
-pub fn render_thread_markdown(uri: &ThreadUri, resolved: &ResolvedThread) -> Result<String> {
-    let raw = read_thread_raw(&resolved.path)?;
-    let markdown = render::render_markdown(uri, &resolved.path, &raw)?;
-    Ok(strip_frontmatter(markdown))
+/// Reads the lines of a thread that carry `kinds`. Merged Codex rollouts are
+/// concatenated oldest first, each preceded by a boundary line naming it;
+/// only the newest can end in a line that is still being written.
+pub(crate) fn read_thread_lines(
+    resolved: &ResolvedThread,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+    kinds: &[LineKind],
+) -> Result<String> {
+    read_thread_decoded(resolved, roots, options, kinds).map(|(raw, _)| raw)
 }
 
-pub fn render_thread_head_markdown(uri: &ThreadUri, roots: &ProviderRoots) -> Result<String> {
+/// [`read_thread_lines`], with the warnings decoding the files gave.
+pub(crate) fn read_thread_decoded(
+    resolved: &ResolvedThread,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+    kinds: &[LineKind],
+) -> Result<(String, Vec<Warning>)> {
+    let mut warnings = Vec::new();
+    let raw = read_thread_files(resolved, |path| {
+        let (raw, decode_warnings) =
+            read_file_lines(resolved.provider, path, roots, options, kinds)?;
+        warnings.extend(decode_warnings);
+        Ok(raw)
+    })?;
+    Ok((raw, warnings))
+}
+
+/// Reads the files of `resolved` with `read`, joining merged Codex rollouts
+/// as [`read_thread_lines`] describes.
+fn read_thread_files(
+    resolved: &ResolvedThread,
+    mut read: impl FnMut(&Path) -> Result<String>,
+) -> Result<String> {
+    if resolved.metadata.merged_paths.is_empty() {
+        return read(&resolved.path);
+    }
+
     let mut output = String::new();
-    output.push_str("---\n");
-    push_yaml_string(&mut output, "uri", &uri.as_agents_string());
-    push_yaml_string(&mut output, "provider", &uri.provider.to_string());
-    push_yaml_string(&mut output, "session_id", &uri.session_id);
-
-    match (uri.provider, uri.agent_id.as_deref()) {
-        (
-            ProviderKind::Amp | ProviderKind::Codex | ProviderKind::Claude | ProviderKind::Gemini,
-            None,
-        ) => {
-            let resolved_main = resolve_thread(uri, roots)?;
-            push_yaml_string(
-                &mut output,
-                "thread_source",
-                &resolved_main.path.display().to_string(),
-            );
-            push_yaml_string(&mut output, "mode", "subagent_index");
+    for (idx, path) in resolved.metadata.merged_paths.iter().enumerate() {
+        let boundary = serde_json::json!({
+            "type": CODEX_ROLLOUT_BOUNDARY,
+            "payload": { "path": path.to_string_lossy() },
+        });
+        output.push_str(&boundary.to_string());
+        output.push('\n');
 
-            let view = resolve_subagent_view(uri, roots, true)?;
-            let mut warnings = resolved_main.metadata.warnings.clone();
+        let raw = read(path)?;
+        output.push_str(&raw);
+        if !raw.ends_with('\n') && idx + 1 < resolved.metadata.merged_paths.len() {
+            output.push('\n');
+        }
+    }
 
-            if let SubagentView::List(list) = view {
-                render_subagents_head(&mut output, &list);
-                warnings.extend(list.warnings);
-            }
+    Ok(output)
+}
+
+/// With `options.consistent_reads`, reads `path` again until two reads in a
+/// row see the same file length, so an agent appending to it mid-read does
+/// not leave a half-written tail.
+fn read_file_lines(
+    provider: ProviderKind,
+    path: &Path,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+    kinds: &[LineKind],
+) -> Result<(String, Vec<Warning>)> {
+    let file_len = || fs::metadata(path).map(|meta| meta.len()).ok();
+    let mut raw = read_file_lines_once(provider, path, roots, kinds)?;
+    if !options.consistent_reads {
+        return Ok(raw);
+    }
 
-            render_warnings(&mut output, &warnings);
+    let mut len = file_len();
+    for attempt in 1..CONSISTENT_READ_ATTEMPTS {
+        std::thread::sleep(CONSISTENT_READ_INTERVAL);
+        raw = read_file_lines_once(provider, path, roots, kinds)?;
+        let next_len = file_len();
+        if next_len == len {
+            return Ok(raw);
         }
-        (ProviderKind::Pi, None) => {
-            let resolved = resolve_thread(uri, roots)?;
-            push_yaml_string(
-                &mut output,
-                "thread_source",
-                &resolved.path.display().to_string(),
-            );
-            push_yaml_string(&mut output, "mode", "pi_entry_index");
+        tracing::debug!(path = %path.display(), attempt, ?len, ?next_len, "thread file changed while reading");
+        len = next_len;
+    }
+    Ok(raw)
+}
+
+/// Large Codex rollouts go through the cached line index so repeat reads only
+/// parse appended lines; everything else, compressed rollouts included, is
+/// read whole.
+fn read_file_lines_once(
+    provider: ProviderKind,
+    path: &Path,
+    roots: &ProviderRoots,
+    kinds: &[LineKind],
+) -> Result<(String, Vec<Warning>)> {
+    let indexable = provider == ProviderKind::Codex
+        && Compression::of_path(path).is_none()
+        && fs::metadata(path).is_ok_and(|meta| meta.len() >= INDEXED_READ_MIN_BYTES)
+        && !starts_as_utf16(path);
+    if !indexable {
+        return read_thread_text(path);
+    }
 
-            let list = resolve_pi_entry_list_view(uri, roots)?;
-            render_pi_entries_head(&mut output, &list);
-            render_warnings(&mut output, &list.warnings);
+    // The index reads lines as UTF-8; anything else is decoded whole.
+    JsonlIndex::load_or_update(&roots.cache_root, path)
+        .and_then(|index| index.read_selected(kinds))
+        .map(|raw| (raw, Vec::new()))
+        .or_else(|_| read_thread_text(path))
+}
+
+fn starts_as_utf16(path: &Path) -> bool {
+    let mut prefix = [0u8; 2];
+    fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut prefix))
+        .is_ok_and(|()| is_utf16(&prefix))
+}
+
+/// Renders a thread's whole timeline as Markdown with default settings,
+/// reading its files directly. [`render_thread`] takes [`ReadOptions`] and
+/// reads through the line index cache.
+pub fn render_thread_markdown(uri: &ThreadUri, resolved: &ResolvedThread) -> Result<String> {
+    let raw = read_thread_files(resolved, read_thread_raw)?;
+    let markdown = render::render_markdown(uri, &resolved.path, &raw)?;
+    Ok(strip_frontmatter(markdown))
+}
+
+/// Renders a thread's timeline as CSV or TSV, one row per entry. The page
+/// and entry kinds in `options` pick the rows, as for Markdown.
+pub fn render_thread_table(
+    uri: &ThreadUri,
+    resolved: &ResolvedThread,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+    format: TableFormat,
+) -> Result<String> {
+    render_thread(uri, resolved, roots, options, TimelineFormat::Table(format))
+}
+
+/// Renders a thread's timeline in `format`. The page, fragment context, and
+/// entry kinds in `options` pick the entries. Markdown leaves out the
+/// frontmatter, which the head provides.
+pub fn render_thread(
+    uri: &ThreadUri,
+    resolved: &ResolvedThread,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+    format: TimelineFormat,
+) -> Result<String> {
+    let raw = read_thread_lines(resolved, roots, options, TIMELINE_LINES)?;
+    if options.pi_all_branches && uri.provider == ProviderKind::Pi {
+        if format != TimelineFormat::Markdown {
+            return Err(XurlError::InvalidMode(
+                "all Pi branches can only be rendered as Markdown".to_string(),
+            ));
         }
-        (
-            ProviderKind::Amp | ProviderKind::Codex | ProviderKind::Claude | ProviderKind::Gemini,
-            Some(_),
-        ) => {
-            let main_uri = main_thread_uri(uri);
-            let resolved_main = resolve_thread(&main_uri, roots)?;
+        let markdown = render::render_pi_branches_markdown(
+            uri,
+            &resolved.path,
+            &raw,
+            &mut options.extraction_policy(),
+        )?;
+        return Ok(strip_frontmatter(markdown));
+    }
+    // Annotations are keyed by main-thread timeline numbers, which entry and
+    // subagent views do not share. A broken store should not block reading.
+    let annotations = if uri.agent_id.is_none() {
+        thread_annotations(roots, uri.provider, &resolved.session_id).unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    let parent = if uri.agent_id.is_none() {
+        read_thread_parent(uri.format(), &resolved.path, &resolved.session_id)
+    } else {
+        None
+    };
+    let timeline = options.timeline_options(&annotations, parent.as_ref());
+    let mut renderer = renderer_for(format, options.timeline_provenance);
+    let rendered =
+        render::render_timeline(uri, &resolved.path, &raw, &timeline, renderer.as_mut())?;
+    Ok(match format {
+        TimelineFormat::Markdown => strip_frontmatter(rendered),
+        _ => rendered,
+    })
+}
 
-            let view = resolve_subagent_view(uri, roots, false)?;
-            if let SubagentView::Detail(detail) = view {
+/// Renders a thread's conversation (optionally only its last `tail` messages)
+/// as a context block meant to be prepended to a write-mode prompt.
+pub fn render_thread_context(
+    uri: &ThreadUri,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+    tail: Option<usize>,
+) -> Result<String> {
+    if uri.agent_id.is_some() {
+        return Err(XurlError::InvalidMode(
+            "context injection only supports main thread URIs: agents://<provider>/<session_id>"
+                .to_string(),
+        ));
+    }
+
+    let resolved = resolve_thread(uri, roots)?;
+    let raw = read_thread_lines(&resolved, roots, options, TIMELINE_LINES)?;
+    let messages = render::extract_messages(
+        uri.format(),
+        &resolved.path,
+        &raw,
+        &mut options.extraction_policy(),
+    )?;
+    let total = messages.len();
+    let skip = tail.map_or(0, |tail| total.saturating_sub(tail));
+
+    let mut output = String::new();
+    output.push_str(&format!("# Context from {}\n\n", uri.as_agents_string()));
+    if skip > 0 {
+        output.push_str(&format!(
+            "_Showing the last {} of {total} messages._\n\n",
+            total - skip
+        ));
+    }
+    for message in messages.into_iter().skip(skip) {
+        let title = match message.role {
+            MessageRole::User => "User",
+            MessageRole::Assistant => "Assistant",
+        };
+        output.push_str(&format!("## {title}\n\n{}\n\n", message.text.trim()));
+    }
+    output.push_str("---\n\n");
+
+    Ok(output)
+}
+
+pub fn render_thread_head_markdown(
+    uri: &ThreadUri,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+) -> Result<String> {
+    Ok(build_thread_head(uri, roots, options)?.to_frontmatter())
+}
+
+/// Collects the head frontmatter for `uri` without rendering it. Every
+/// warning is kept; callers pick the ones to show.
+pub fn build_thread_head(
+    uri: &ThreadUri,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+) -> Result<HeadDocument> {
+    // Subagent details describe a child thread, so they carry no session info.
+    let (thread_source, body, warnings, session, fingerprint) =
+        match (uri.provider, uri.agent_id.as_deref()) {
+            (
+                ProviderKind::Amp
+                | ProviderKind::Codex
+                | ProviderKind::Claude
+                | ProviderKind::Gemini,
+                None,
+            ) => {
+                let resolved_main = resolve_thread(uri, roots)?;
+                let view = resolve_subagent_view(uri, roots, options, true)?;
+                let mut warnings = resolved_main.metadata.warnings.clone();
+                let (fingerprint, read_warnings) =
+                    read_head_messages(uri, &resolved_main, roots, options);
+                warnings.extend(read_warnings);
+
+                let subagents = if let SubagentView::List(list) = view {
+                    warnings.extend(list.warnings);
+                    head_subagents(
+                        &list.query.provider,
+                        &list.query.main_thread_id,
+                        &list.agents,
+                    )
+                } else {
+                    Vec::new()
+                };
+
+                (
+                    resolved_main.path.display().to_string(),
+                    HeadBody::SubagentIndex { subagents },
+                    warnings,
+                    resolved_main.metadata.session,
+                    fingerprint,
+                )
+            }
+            (ProviderKind::Pi, None) => {
+                let resolved = resolve_thread(uri, roots)?;
+                let list = resolve_pi_entry_list_view(uri, roots, options)?;
+                let entries = head_pi_entries(&list);
+                let (fingerprint, read_warnings) =
+                    read_head_messages(uri, &resolved, roots, options);
+                let mut warnings = list.warnings;
+                warnings.extend(read_warnings);
+
+                (
+                    resolved.path.display().to_string(),
+                    HeadBody::PiEntryIndex { entries },
+                    warnings,
+                    resolved.metadata.session,
+                    fingerprint,
+                )
+            }
+            (
+                ProviderKind::Amp
+                | ProviderKind::Codex
+                | ProviderKind::Claude
+                | ProviderKind::Gemini,
+                Some(_),
+            ) => {
+                let main_uri = main_thread_uri(uri);
+                let resolved_main = resolve_thread(&main_uri, roots)?;
+
+                let SubagentView::Detail(detail) =
+                    resolve_subagent_view(uri, roots, options, false)?
+                else {
+                    return Err(XurlError::InvalidMode(
+                        "subagent drill-down did not return a detail view".to_string(),
+                    ));
+                };
                 let thread_source = detail
                     .child_thread
                     .as_ref()
-                    .and_then(|thread| thread.path.as_deref())
-                    .map(ToString::to_string)
+                    .and_then(|thread| thread.path.clone())
                     .unwrap_or_else(|| resolved_main.path.display().to_string());
-                push_yaml_string(&mut output, "thread_source", &thread_source);
-                push_yaml_string(&mut output, "mode", "subagent_detail");
-
-                if let Some(agent_id) = &detail.query.agent_id {
-                    push_yaml_string(&mut output, "agent_id", agent_id);
-                    push_yaml_string(
-                        &mut output,
-                        "subagent_uri",
-                        &agents_thread_uri(
+                let child_thread = detail.child_thread.as_ref();
+                let body = HeadBody::SubagentDetail(HeadSubagentDetail {
+                    subagent_uri: detail.query.agent_id.as_deref().map(|agent_id| {
+                        agents_thread_uri(
                             &detail.query.provider,
                             &detail.query.main_thread_id,
                             Some(agent_id),
-                        ),
-                    );
-                }
-                push_yaml_string(&mut output, "status", &detail.status);
-                push_yaml_string(&mut output, "status_source", &detail.status_source);
-
-                if let Some(child_thread) = &detail.child_thread {
-                    push_yaml_string(&mut output, "child_thread_id", &child_thread.thread_id);
-                    if let Some(path) = &child_thread.path {
-                        push_yaml_string(&mut output, "child_thread_source", path);
-                    }
-                    if let Some(last_updated_at) = &child_thread.last_updated_at {
-                        push_yaml_string(&mut output, "child_last_updated_at", last_updated_at);
-                    }
-                }
+                        )
+                    }),
+                    agent_id: detail.query.agent_id.clone(),
+                    status: detail.status.clone(),
+                    status_source: detail.status_source.clone(),
+                    child_thread_id: child_thread.map(|thread| thread.thread_id.clone()),
+                    child_thread_source: child_thread.and_then(|thread| thread.path.clone()),
+                    child_last_updated_at: child_thread
+                        .and_then(|thread| thread.last_updated_at.clone()),
+                });
 
-                render_warnings(&mut output, &detail.warnings);
+                (
+                    thread_source,
+                    body,
+                    detail.warnings,
+                    SessionInfo::default(),
+                    None,
+                )
             }
-        }
-        (ProviderKind::Pi, Some(entry_id)) => {
-            let resolved = resolve_thread(uri, roots)?;
-            push_yaml_string(
-                &mut output,
-                "thread_source",
-                &resolved.path.display().to_string(),
-            );
-            push_yaml_string(&mut output, "mode", "pi_entry");
-            push_yaml_string(&mut output, "entry_id", entry_id);
-        }
-        _ => {
-            let resolved = resolve_thread(uri, roots)?;
-            push_yaml_string(
-                &mut output,
-                "thread_source",
-                &resolved.path.display().to_string(),
-            );
-            push_yaml_string(&mut output, "mode", "thread");
-            render_warnings(&mut output, &resolved.metadata.warnings);
-        }
+            (ProviderKind::Pi, Some(entry_id)) => {
+                let resolved = resolve_thread(uri, roots)?;
+                (
+                    resolved.path.display().to_string(),
+                    HeadBody::PiEntry {
+                        entry_id: entry_id.to_string(),
+                    },
+                    Vec::new(),
+                    resolved.metadata.session,
+                    None,
+                )
+            }
+            _ => {
+                let resolved = resolve_thread(uri, roots)?;
+                // Subagent heads describe a child thread, not the file resolved here.
+                let (fingerprint, read_warnings) = if uri.agent_id.is_none() {
+                    read_head_messages(uri, &resolved, roots, options)
+                } else {
+                    (None, Vec::new())
+                };
+                let mut warnings = resolved.metadata.warnings;
+                warnings.extend(read_warnings);
+                (
+                    resolved.path.display().to_string(),
+                    HeadBody::Thread,
+                    warnings,
+                    resolved.metadata.session,
+                    fingerprint,
+                )
+            }
+        };
+
+    let page = match options.timeline_page {
+        Some(page) if renders_timeline(uri) => Some(head_page(uri, roots, options, page)?),
+        _ => None,
+    };
+    let parent = if uri.agent_id.is_none() {
+        read_thread_parent(uri.format(), Path::new(&thread_source), &uri.session_id)
+    } else {
+        None
+    };
+
+    let schema = provider_for(uri.provider, roots).detect_schema(Some(Path::new(&thread_source)));
+
+    Ok(HeadDocument {
+        uri: uri.as_agents_string(),
+        provider: uri.provider,
+        session_id: uri.session_id.clone(),
+        thread_source,
+        schema,
+        fingerprint,
+        session,
+        parent,
+        body,
+        page,
+        warnings,
+    })
+}
+
+/// Reads a main thread's lines for its head: the warnings decoding and
+/// parsing them gave, and the thread's fingerprint when `options.fingerprint`
+/// asks for it. Messages are only extracted for the fingerprint or when a
+/// record may be skipped: under lenient extraction, or while the last line is
+/// still being written. A thread that cannot be read gives neither; the
+/// timeline reports the error.
+fn read_head_messages(
+    uri: &ThreadUri,
+    resolved: &ResolvedThread,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+) -> (Option<String>, Vec<Warning>) {
+    let Ok((raw, mut warnings)) = read_thread_decoded(resolved, roots, options, TIMELINE_LINES)
+    else {
+        return (None, Vec::new());
+    };
+    if !options.fingerprint
+        && options.extraction_mode == ExtractionMode::Strict
+        && render::unterminated_line(&raw).is_none()
+    {
+        return (None, warnings);
+    }
+
+    let mut policy = options.extraction_policy();
+    let fingerprint = render::extract_messages(uri.format(), &resolved.path, &raw, &mut policy)
+        .ok()
+        .filter(|_| options.fingerprint)
+        .map(|messages| thread_fingerprint(&messages));
+    // The timeline skips the same records, so they are reported here.
+    warnings.extend(policy.into_warnings());
+    (fingerprint, warnings)
+}
+
+/// Whether reading `uri` renders a timeline rather than a subagent view.
+fn renders_timeline(uri: &ThreadUri) -> bool {
+    uri.agent_id.is_none() || uri.provider == ProviderKind::Pi
+}
+
+fn head_page(
+    uri: &ThreadUri,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+    page: TimelinePage,
+) -> Result<HeadPage> {
+    let resolved = resolve_thread(uri, roots)?;
+    let raw = read_thread_lines(&resolved, roots, options, TIMELINE_LINES)?;
+    let total_entries = render::count_timeline_entries(
+        uri,
+        &resolved.path,
+        &raw,
+        &mut options.extraction_policy(),
+    )?;
+    let total_pages = page.total_pages(total_entries);
+    if page.number > total_pages {
+        return Err(XurlError::InvalidMode(format!(
+            "page {} is past the last page ({total_pages}) of {} at page size {}",
+            page.number,
+            uri.as_agents_string(),
+            page.size
+        )));
     }
 
-    output.push_str("---\n");
-    Ok(output)
+    let neighbor = |number: usize| TimelinePage { number, ..page }.uri(uri);
+    Ok(HeadPage {
+        number: page.number,
+        size: page.size,
+        total_pages,
+        total_entries,
+        prev: (page.number > 1).then(|| neighbor(page.number - 1)),
+        next: (page.number < total_pages).then(|| neighbor(page.number + 1)),
+    })
 }
 
 pub fn resolve_subagent_view(
     uri: &ThreadUri,
     roots: &ProviderRoots,
+    options: &ReadOptions,
     list: bool,
 ) -> Result<SubagentView> {
     if list && uri.agent_id.is_some() {
@@ -276,116 +1170,144 @@ pub fn resolve_subagent_view(
         ));
     }
 
-    match uri.provider {
-        ProviderKind::Amp => resolve_amp_subagent_view(uri, roots, list),
-        ProviderKind::Codex => resolve_codex_subagent_view(uri, roots, list),
+    let mut view = match uri.provider {
+        ProviderKind::Amp => resolve_amp_subagent_view(uri, roots, options, list),
+        ProviderKind::Codex => resolve_codex_subagent_view(uri, roots, options, list),
         ProviderKind::Claude => resolve_claude_subagent_view(uri, roots, list),
         ProviderKind::Gemini => resolve_gemini_subagent_view(uri, roots, list),
         _ => Err(XurlError::UnsupportedSubagentProvider(
             uri.provider.to_string(),
         )),
-    }
-}
-
-fn push_yaml_string(output: &mut String, key: &str, value: &str) {
-    output.push_str(&format!("{key}: '{}'\n", yaml_single_quoted(value)));
+    }?;
+    normalize_subagent_view_timestamps(&mut view);
+
+    Ok(match view {
+        SubagentView::List(mut list_view) if !options.subagent_window.is_unbounded() => {
+            let mut undated = 0usize;
+            list_view.agents = filter_subagents_by_window(
+                list_view.agents,
+                &options.subagent_window,
+                &mut undated,
+            );
+            if undated > 0 {
+                list_view.warnings.push(Warning::info(
+                    "subagents_undated",
+                    format!(
+                        "excluded {undated} subagent(s) without lifecycle timestamps from the time window"
+                    ),
+                ));
+            }
+            SubagentView::List(list_view)
+        }
+        view => view,
+    })
 }
 
-fn yaml_single_quoted(value: &str) -> String {
-    value.replace('\'', "''")
+fn normalize_subagent_view_timestamps(view: &mut SubagentView) {
+    match view {
+        SubagentView::List(list) => normalize_list_item_timestamps(&mut list.agents),
+        SubagentView::Detail(detail) => {
+            if let Some(thread) = &mut detail.child_thread {
+                normalize_optional_timestamp(&mut thread.last_updated_at);
+            }
+        }
+    }
 }
 
-fn render_warnings(output: &mut String, warnings: &[String]) {
-    let mut unique = BTreeSet::<String>::new();
-    unique.extend(warnings.iter().cloned());
-
-    if unique.is_empty() {
-        return;
+fn normalize_list_item_timestamps(agents: &mut [SubagentListItem]) {
+    for agent in agents {
+        normalize_optional_timestamp(&mut agent.first_update);
+        normalize_optional_timestamp(&mut agent.last_update);
+        if let Some(thread) = &mut agent.child_thread {
+            normalize_optional_timestamp(&mut thread.last_updated_at);
+        }
+        normalize_list_item_timestamps(&mut agent.children);
     }
+}
 
-    output.push_str("warnings:\n");
-    for warning in unique {
-        output.push_str(&format!("  - '{}'\n", yaml_single_quoted(&warning)));
+fn normalize_optional_timestamp(value: &mut Option<String>) {
+    if let Some(timestamp) = value.as_deref() {
+        *value = Some(normalize_timestamp(timestamp));
     }
 }
 
-fn render_subagents_head(output: &mut String, list: &SubagentListView) {
-    output.push_str("subagents:\n");
-    if list.agents.is_empty() {
-        output.push_str("  []\n");
-        return;
-    }
+fn filter_subagents_by_window(
+    agents: Vec<SubagentListItem>,
+    window: &TimeWindow,
+    undated: &mut usize,
+) -> Vec<SubagentListItem> {
+    agents
+        .into_iter()
+        .filter_map(|mut agent| {
+            agent.children = filter_subagents_by_window(agent.children, window, undated);
 
-    for agent in &list.agents {
-        output.push_str(&format!(
-            "  - agent_id: '{}'\n",
-            yaml_single_quoted(&agent.agent_id)
-        ));
-        output.push_str(&format!(
-            "    uri: '{}'\n",
-            yaml_single_quoted(&agents_thread_uri(
-                &list.query.provider,
-                &list.query.main_thread_id,
-                Some(&agent.agent_id),
-            ))
-        ));
-        push_yaml_string_with_indent(output, 4, "status", &agent.status);
-        push_yaml_string_with_indent(output, 4, "status_source", &agent.status_source);
-        if let Some(last_update) = &agent.last_update {
-            push_yaml_string_with_indent(output, 4, "last_update", last_update);
-        }
-        if let Some(child_thread) = &agent.child_thread
-            && let Some(path) = &child_thread.path
-        {
-            push_yaml_string_with_indent(output, 4, "thread_source", path);
-        }
-    }
+            let start = agent
+                .first_update
+                .as_deref()
+                .or(agent.last_update.as_deref())
+                .and_then(parse_timestamp);
+            let end = agent
+                .last_update
+                .as_deref()
+                .or(agent.first_update.as_deref())
+                .and_then(parse_timestamp);
+            let active = match (start, end) {
+                (Some(start), Some(end)) => window.overlaps(start.min(end), start.max(end)),
+                _ => {
+                    *undated += 1;
+                    false
+                }
+            };
+
+            // Keep a parent whose handoff chain reaches into the window so
+            // nested children stay reachable.
+            (active || !agent.children.is_empty()).then_some(agent)
+        })
+        .collect()
 }
 
-fn render_pi_entries_head(output: &mut String, list: &PiEntryListView) {
-    output.push_str("entries:\n");
-    if list.entries.is_empty() {
-        output.push_str("  []\n");
-        return;
-    }
+fn head_subagents(
+    provider: &str,
+    parent_thread_id: &str,
+    agents: &[SubagentListItem],
+) -> Vec<HeadSubagent> {
+    agents
+        .iter()
+        .map(|agent| HeadSubagent {
+            agent_id: agent.agent_id.clone(),
+            uri: agents_thread_uri(provider, parent_thread_id, Some(&agent.agent_id)),
+            agent_name: agent.agent_name.clone(),
+            status: agent.status.clone(),
+            status_source: agent.status_source.clone(),
+            last_update: agent.last_update.clone(),
+            thread_source: agent
+                .child_thread
+                .as_ref()
+                .and_then(|thread| thread.path.clone()),
+            children: head_subagents(provider, &agent.agent_id, &agent.children),
+        })
+        .collect()
+}
 
-    for entry in &list.entries {
-        output.push_str(&format!(
-            "  - entry_id: '{}'\n",
-            yaml_single_quoted(&entry.entry_id)
-        ));
-        output.push_str(&format!(
-            "    uri: '{}'\n",
-            yaml_single_quoted(&agents_thread_uri(
+fn head_pi_entries(list: &PiEntryListView) -> Vec<HeadPiEntry> {
+    list.entries
+        .iter()
+        .map(|entry| HeadPiEntry {
+            entry_id: entry.entry_id.clone(),
+            uri: agents_thread_uri(
                 &list.query.provider,
                 &list.query.session_id,
                 Some(&entry.entry_id),
-            ))
-        ));
-        push_yaml_string_with_indent(output, 4, "entry_type", &entry.entry_type);
-        if let Some(parent_id) = &entry.parent_id {
-            push_yaml_string_with_indent(output, 4, "parent_id", parent_id);
-        }
-        if let Some(timestamp) = &entry.timestamp {
-            push_yaml_string_with_indent(output, 4, "timestamp", timestamp);
-        }
-        if let Some(preview) = &entry.preview {
-            push_yaml_string_with_indent(output, 4, "preview", preview);
-        }
-        push_yaml_bool_with_indent(output, 4, "is_leaf", entry.is_leaf);
-    }
-}
-
-fn push_yaml_string_with_indent(output: &mut String, indent: usize, key: &str, value: &str) {
-    output.push_str(&format!(
-        "{}{key}: '{}'\n",
-        " ".repeat(indent),
-        yaml_single_quoted(value)
-    ));
-}
-
-fn push_yaml_bool_with_indent(output: &mut String, indent: usize, key: &str, value: bool) {
-    output.push_str(&format!("{}{key}: {value}\n", " ".repeat(indent)));
+            ),
+            entry_type: entry.entry_type.clone(),
+            parent_id: entry.parent_id.clone(),
+            timestamp: entry.timestamp.clone(),
+            role: entry.role.clone(),
+            preview: entry.preview.clone(),
+            estimated_tokens: entry.estimated_tokens,
+            is_leaf: entry.is_leaf,
+        })
+        .collect()
 }
 
 fn strip_frontmatter(markdown: String) -> String {
@@ -405,9 +1327,86 @@ pub fn render_subagent_view_markdown(view: &SubagentView) -> String {
     }
 }
 
+pub fn render_subagent_view_json(view: &SubagentView) -> Result<String> {
+    serde_json::to_string_pretty(view)
+        .map(|json| format!("{json}\n"))
+        .map_err(|err| XurlError::Serialization(err.to_string()))
+}
+
+/// The canonical URI of `uri` and the file it is read from.
+pub fn locate_thread(uri: &ThreadUri, roots: &ProviderRoots) -> Result<ThreadLocation> {
+    let (path, warnings) = if uri.agent_id.is_some() && uri.provider != ProviderKind::Pi {
+        // Only the child's path is needed, which no read option changes.
+        let detail = match resolve_subagent_view(uri, roots, &ReadOptions::default(), false)? {
+            SubagentView::Detail(detail) => detail,
+            SubagentView::List(_) => {
+                return Err(XurlError::InvalidMode(
+                    "subagent drill-down returned an index".to_string(),
+                ));
+            }
+        };
+        let path = detail
+            .child_thread
+            .and_then(|thread| thread.path)
+            .ok_or_else(|| XurlError::ThreadNotFound {
+                provider: uri.provider.to_string(),
+                session_id: uri.as_agents_string(),
+                searched_roots: Vec::new(),
+            })?;
+        (PathBuf::from(path), detail.warnings)
+    } else {
+        let resolved = resolve_thread(uri, roots)?;
+        (resolved.path, resolved.metadata.warnings)
+    };
+
+    Ok(ThreadLocation {
+        uri: uri.as_agents_string(),
+        provider: uri.provider.to_string(),
+        session_id: uri.session_id.clone(),
+        agent_id: uri.agent_id.clone(),
+        path,
+        warnings,
+    })
+}
+
+pub fn render_thread_location_json(location: &ThreadLocation) -> Result<String> {
+    serde_json::to_string_pretty(location)
+        .map(|json| format!("{json}\n"))
+        .map_err(|err| XurlError::Serialization(err.to_string()))
+}
+
+/// The timeline entry that `uri`'s fragment points at, as JSON, including
+/// its anchor and stable id.
+pub fn render_thread_entry_json(
+    uri: &ThreadUri,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+) -> Result<String> {
+    let Some(anchor) = &uri.fragment else {
+        return Err(XurlError::InvalidMode(
+            "entry JSON requires a URI with a #msg-<N> or #<entry id> fragment".to_string(),
+        ));
+    };
+    let resolved = resolve_thread(uri, roots)?;
+    let raw = read_thread_lines(&resolved, roots, options, TIMELINE_LINES)?;
+    let entry = render::find_timeline_entry(
+        uri,
+        &resolved.path,
+        &raw,
+        anchor,
+        &mut options.extraction_policy(),
+    )?;
+    serde_json::to_string_pretty(&entry)
+        .map(|json| format!("{json}\n"))
+        .map_err(|err| XurlError::Serialization(err.to_string()))
+}
+
+/// Lists every entry of a Pi session with its role, a preview cut to
+/// `options.pi_preview_chars`, and a token estimate of its whole text.
 pub fn resolve_pi_entry_list_view(
     uri: &ThreadUri,
     roots: &ProviderRoots,
+    options: &ReadOptions,
 ) -> Result<PiEntryListView> {
     if uri.provider != ProviderKind::Pi {
         return Err(XurlError::InvalidMode(
@@ -432,9 +1431,9 @@ pub fn resolve_pi_entry_list_view(
             Ok(Some(value)) => value,
             Ok(None) => continue,
             Err(err) => {
-                warnings.push(format!(
-                    "failed to parse pi session line {}: {err}",
-                    line_idx + 1,
+                warnings.push(Warning::warn(
+                    "invalid_line",
+                    format!("failed to parse pi session line {}: {err}", line_idx + 1),
                 ));
                 continue;
             }
@@ -470,19 +1469,27 @@ pub fn resolve_pi_entry_list_view(
             .and_then(Value::as_str)
             .map(ToString::to_string);
 
-        let preview = match entry_type.as_str() {
-            "message" => value
-                .get("message")
+        let message = value.get("message");
+        let role = message
+            .and_then(|message| message.get("role"))
+            .and_then(Value::as_str)
+            .map(ToString::to_string);
+        let text = match entry_type.as_str() {
+            "message" => message
                 .and_then(|message| message.get("content"))
-                .map(|content| render_preview_text(content, 96))
-                .filter(|text| !text.is_empty()),
+                .map(preview_source_text),
             "compaction" | "branch_summary" => value
                 .get("summary")
                 .and_then(Value::as_str)
-                .map(|text| truncate_preview(text, 96))
-                .filter(|text| !text.is_empty()),
+                .map(ToString::to_string),
             _ => None,
-        };
+        }
+        .filter(|text| !text.trim().is_empty());
+        let estimated_tokens = text.as_deref().map(estimate_tokens);
+        let preview = text.map(|text| match options.pi_preview_chars {
+            Some(max_chars) => truncate_preview(&text, max_chars),
+            None => text.split_whitespace().collect::<Vec<_>>().join(" "),
+        });
 
         entries.push(PiEntryListItem {
             entry_id,
@@ -490,7 +1497,9 @@ pub fn resolve_pi_entry_list_view(
             parent_id,
             timestamp,
             is_leaf: false,
+            role,
             preview,
+            estimated_tokens,
         });
     }
 
@@ -538,9 +1547,15 @@ pub fn render_pi_entry_list_markdown(view: &PiEntryListView) -> String {
             "- Leaf: `{}`\n",
             if entry.is_leaf { "yes" } else { "no" }
         ));
+        if let Some(role) = &entry.role {
+            output.push_str(&format!("- Role: `{role}`\n"));
+        }
         if let Some(preview) = &entry.preview {
             output.push_str(&format!("- Preview: {}\n", preview));
         }
+        if let Some(tokens) = entry.estimated_tokens {
+            output.push_str(&format!("- Tokens: ~{tokens}\n"));
+        }
         output.push('\n');
     }
 
@@ -550,6 +1565,7 @@ pub fn render_pi_entry_list_markdown(view: &PiEntryListView) -> String {
 fn resolve_amp_subagent_view(
     uri: &ThreadUri,
     roots: &ProviderRoots,
+    options: &ReadOptions,
     list: bool,
 ) -> Result<SubagentView> {
     let main_uri = main_thread_uri(uri);
@@ -567,7 +1583,7 @@ fn resolve_amp_subagent_view(
 
     if list {
         return Ok(SubagentView::List(build_amp_list_view(
-            uri, roots, &handoffs, warnings,
+            uri, roots, options, &handoffs, warnings,
         )));
     }
 
@@ -577,19 +1593,56 @@ fn resolve_amp_subagent_view(
         .ok_or_else(|| XurlError::InvalidMode("missing agent id".to_string()))?;
 
     Ok(SubagentView::Detail(build_amp_detail_view(
-        uri, roots, &agent_id, &handoffs, warnings,
+        uri, roots, options, &agent_id, &handoffs, warnings,
     )))
 }
 
 fn build_amp_list_view(
     uri: &ThreadUri,
     roots: &ProviderRoots,
+    options: &ReadOptions,
     handoffs: &[AmpHandoff],
-    mut warnings: Vec<String>,
+    mut warnings: Vec<Warning>,
 ) -> SubagentListView {
+    let mut walk = AmpListWalk {
+        visited: BTreeSet::from([uri.session_id.clone()]),
+        warnings: &mut warnings,
+    };
+    let agents = build_amp_list_items(
+        &uri.session_id,
+        "main",
+        handoffs,
+        roots,
+        options,
+        options.amp_handoff_depth.max(1),
+        &mut walk,
+    );
+
+    SubagentListView {
+        query: make_query(uri, None, true),
+        agents,
+        warnings,
+    }
+}
+
+/// Threads listed so far and the warnings collected while walking a handoff chain.
+struct AmpListWalk<'a> {
+    visited: BTreeSet<String>,
+    warnings: &'a mut Vec<Warning>,
+}
+
+fn build_amp_list_items(
+    parent_thread_id: &str,
+    parent_source: &str,
+    handoffs: &[AmpHandoff],
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+    depth: usize,
+    walk: &mut AmpListWalk<'_>,
+) -> Vec<SubagentListItem> {
     let mut grouped = BTreeMap::<String, Vec<&AmpHandoff>>::new();
     for handoff in handoffs {
-        if handoff.thread_id == uri.session_id || handoff.role.as_deref() == Some("child") {
+        if handoff.thread_id == parent_thread_id || handoff.role.as_deref() == Some("child") {
             continue;
         }
         grouped
@@ -598,9 +1651,42 @@ fn build_amp_list_view(
             .push(handoff);
     }
 
+    // Claim every child of this level before descending, so a sibling that is
+    // also reachable deeper in the chain is listed once at its shallowest level.
+    grouped.retain(|agent_id, _| {
+        if walk.visited.insert(agent_id.clone()) {
+            return true;
+        }
+        walk.warnings.push(Warning::info(
+            "handoff_loop",
+            format!(
+                "skipped amp handoff loop from thread_id={parent_thread_id} to already listed thread_id={agent_id}"
+            ),
+        ));
+        false
+    });
+
+    let analyses = map_concurrently(grouped.keys().collect::<Vec<_>>(), |agent_id| {
+        let mut child_warnings = Vec::new();
+        let analysis = analyze_amp_child_thread(
+            agent_id,
+            parent_thread_id,
+            roots,
+            options,
+            &mut child_warnings,
+        );
+        (analysis, child_warnings)
+    });
+
     let mut agents = Vec::new();
-    for (agent_id, relations) in grouped {
+    for ((agent_id, relations), (analysis, child_warnings)) in grouped.into_iter().zip(analyses) {
+        walk.warnings.extend(child_warnings);
         let mut relation = SubagentRelation::default();
+        let first_update = earliest_timestamp(
+            relations
+                .iter()
+                .filter_map(|handoff| handoff.timestamp.as_deref()),
+        );
 
         for handoff in relations {
             match handoff.role.as_deref() {
@@ -608,21 +1694,25 @@ fn build_amp_list_view(
                     relation.validated = true;
                     push_unique(
                         &mut relation.evidence,
-                        "main relationships includes handoff(role=parent) to child thread"
-                            .to_string(),
+                        format!(
+                            "{parent_source} relationships includes handoff(role=parent) to child thread"
+                        ),
                     );
                 }
                 Some(role) => {
                     push_unique(
                         &mut relation.evidence,
-                        format!("main relationships includes handoff(role={role}) to child thread"),
+                        format!(
+                            "{parent_source} relationships includes handoff(role={role}) to child thread"
+                        ),
                     );
                 }
                 None => {
                     push_unique(
                         &mut relation.evidence,
-                        "main relationships includes handoff(role missing) to child thread"
-                            .to_string(),
+                        format!(
+                            "{parent_source} relationships includes handoff(role missing) to child thread"
+                        ),
                     );
                 }
             }
@@ -633,13 +1723,12 @@ fn build_amp_list_view(
         } else {
             STATUS_NOT_FOUND.to_string()
         };
-        let mut status_source = "inferred".to_string();
+        let mut status_source = SOURCE_INFERRED.to_string();
         let mut last_update = None::<String>;
         let mut child_thread = None::<SubagentThreadRef>;
+        let mut children = Vec::new();
 
-        if let Some(analysis) =
-            analyze_amp_child_thread(&agent_id, &uri.session_id, roots, &mut warnings)
-        {
+        if let Some(analysis) = analysis {
             for evidence in analysis.relation_evidence {
                 push_unique(&mut relation.evidence, evidence);
             }
@@ -651,31 +1740,55 @@ fn build_amp_list_view(
             status_source = analysis.status_source;
             last_update = analysis.thread.last_updated_at.clone();
             child_thread = Some(analysis.thread);
+
+            if depth > 1 {
+                children = build_amp_list_items(
+                    &agent_id,
+                    "parent",
+                    &analysis.handoffs,
+                    roots,
+                    options,
+                    depth - 1,
+                    walk,
+                );
+            } else if analysis.handoffs.iter().any(|handoff| {
+                handoff.thread_id != parent_thread_id
+                    && handoff.role.as_deref() != Some("child")
+                    && !walk.visited.contains(&handoff.thread_id)
+            }) {
+                walk.warnings.push(Warning::info(
+                    "handoff_truncated",
+                    format!(
+                        "amp handoff chain truncated at depth={} below thread_id={agent_id}",
+                        options.amp_handoff_depth
+                    ),
+                ));
+            }
         }
 
         agents.push(SubagentListItem {
             agent_id,
+            agent_name: None,
             status,
             status_source,
+            first_update,
             last_update,
             relation,
             child_thread,
+            children,
         });
     }
 
-    SubagentListView {
-        query: make_query(uri, None, true),
-        agents,
-        warnings,
-    }
+    agents
 }
 
 fn build_amp_detail_view(
     uri: &ThreadUri,
     roots: &ProviderRoots,
+    options: &ReadOptions,
     agent_id: &str,
     handoffs: &[AmpHandoff],
-    mut warnings: Vec<String>,
+    mut warnings: Vec<Warning>,
 ) -> SubagentDetailView {
     let mut relation = SubagentRelation::default();
     let mut lifecycle = Vec::<SubagentLifecycleEvent>::new();
@@ -686,8 +1799,9 @@ fn build_amp_detail_view(
         .collect::<Vec<_>>();
 
     if matches.is_empty() {
-        warnings.push(format!(
-            "no handoff relationship found in main thread for child_thread_id={agent_id}"
+        warnings.push(Warning::warn(
+            "relation_missing",
+            format!("no handoff relationship found in main thread for child_thread_id={agent_id}"),
         ));
     }
 
@@ -731,16 +1845,18 @@ fn build_amp_detail_view(
     }
 
     let mut child_thread = None::<SubagentThreadRef>;
+    let mut initial_prompt = None;
+    let mut tools = Vec::new();
     let mut excerpt = Vec::<SubagentExcerptMessage>::new();
     let mut status = if relation.validated {
         STATUS_PENDING_INIT.to_string()
     } else {
         STATUS_NOT_FOUND.to_string()
     };
-    let mut status_source = "inferred".to_string();
+    let mut status_source = SOURCE_INFERRED.to_string();
 
     if let Some(analysis) =
-        analyze_amp_child_thread(agent_id, &uri.session_id, roots, &mut warnings)
+        analyze_amp_child_thread(agent_id, &uri.session_id, roots, options, &mut warnings)
     {
         for evidence in analysis.relation_evidence {
             push_unique(&mut relation.evidence, evidence);
@@ -752,6 +1868,8 @@ fn build_amp_detail_view(
         status = analysis.status;
         status_source = analysis.status_source;
         child_thread = Some(analysis.thread);
+        initial_prompt = analysis.prompt;
+        tools = analysis.tools;
         excerpt = analysis.excerpt;
     }
 
@@ -762,6 +1880,8 @@ fn build_amp_detail_view(
         status,
         status_source,
         child_thread,
+        initial_prompt,
+        tools,
         excerpt,
         warnings,
     }
@@ -771,13 +1891,17 @@ fn analyze_amp_child_thread(
     child_thread_id: &str,
     main_thread_id: &str,
     roots: &ProviderRoots,
-    warnings: &mut Vec<String>,
+    options: &ReadOptions,
+    warnings: &mut Vec<Warning>,
 ) -> Option<AmpChildAnalysis> {
-    let resolved_child = match AmpProvider::new(&roots.amp_root).resolve(child_thread_id) {
+    let resolved_child = match provider_for(ProviderKind::Amp, roots).resolve(child_thread_id) {
         Ok(resolved) => resolved,
         Err(err) => {
-            warnings.push(format!(
-                "failed resolving amp child thread child_thread_id={child_thread_id}: {err}"
+            warnings.push(Warning::error(
+                "child_unresolved",
+                format!(
+                    "failed resolving amp child thread child_thread_id={child_thread_id}: {err}"
+                ),
             ));
             return None;
         }
@@ -786,8 +1910,9 @@ fn analyze_amp_child_thread(
     let child_raw = match read_thread_raw(&resolved_child.path) {
         Ok(raw) => raw,
         Err(err) => {
-            warnings.push(format!(
-                "failed reading amp child thread child_thread_id={child_thread_id}: {err}"
+            warnings.push(Warning::error(
+                "child_unreadable",
+                format!("failed reading amp child thread child_thread_id={child_thread_id}: {err}"),
             ));
             return None;
         }
@@ -796,9 +1921,12 @@ fn analyze_amp_child_thread(
     let child_value = match serde_json::from_str::<Value>(&child_raw) {
         Ok(value) => value,
         Err(err) => {
-            warnings.push(format!(
-                "failed parsing amp child thread {}: {err}",
-                resolved_child.path.display()
+            warnings.push(Warning::error(
+                "child_invalid",
+                format!(
+                    "failed parsing amp child thread {}: {err}",
+                    resolved_child.path.display()
+                ),
             ));
             return None;
         }
@@ -806,7 +1934,8 @@ fn analyze_amp_child_thread(
 
     let mut relation_evidence = Vec::<String>::new();
     let mut lifecycle = Vec::<SubagentLifecycleEvent>::new();
-    for handoff in extract_amp_handoffs(&child_value, "child", warnings) {
+    let handoffs = extract_amp_handoffs(&child_value, "child", warnings);
+    for handoff in &handoffs {
         if handoff.thread_id != main_thread_id {
             continue;
         }
@@ -852,24 +1981,36 @@ fn analyze_amp_child_thread(
         }
     }
 
-    let messages =
-        match render::extract_messages(ProviderKind::Amp, &resolved_child.path, &child_raw) {
-            Ok(messages) => messages,
-            Err(err) => {
-                warnings.push(format!(
+    let messages = match render::extract_messages(
+        ProviderKind::Amp,
+        &resolved_child.path,
+        &child_raw,
+        &mut options.extraction_policy(),
+    ) {
+        Ok(messages) => messages,
+        Err(err) => {
+            warnings.push(Warning::warn(
+                "child_invalid",
+                format!(
                     "failed extracting amp child messages from {}: {err}",
                     resolved_child.path.display()
-                ));
-                Vec::new()
-            }
-        };
-    let has_user = messages
-        .iter()
-        .any(|message| message.role == MessageRole::User);
-    let has_assistant = messages
-        .iter()
-        .any(|message| message.role == MessageRole::Assistant);
+                ),
+            ));
+            Vec::new()
+        }
+    };
+    let signals = TranscriptSignals {
+        has_user: messages
+            .iter()
+            .any(|message| message.role == MessageRole::User),
+        has_assistant: messages
+            .iter()
+            .any(|message| message.role == MessageRole::Assistant),
+        has_error: false,
+        mid_turn: false,
+    };
 
+    let prompt = first_user_prompt(&messages);
     let excerpt = messages
         .into_iter()
         .rev()
@@ -883,8 +2024,13 @@ fn analyze_amp_child_thread(
         })
         .collect::<Vec<_>>();
 
-    let (status, status_source) = infer_amp_status(&child_value, has_user, has_assistant);
-    let last_updated_at = extract_amp_last_update(&child_value)
+    let InferredStatus {
+        status,
+        source: status_source,
+    } = AmpStatusInference { signals }
+        .infer_status(&child_value)
+        .unwrap_or_else(InferredStatus::not_found);
+    let last_updated_at = extract_amp_last_update(&resolved_child.path, &child_value)
         .or_else(|| modified_timestamp_string(&resolved_child.path));
 
     Some(AmpChildAnalysis {
@@ -895,16 +2041,19 @@ fn analyze_amp_child_thread(
         },
         status,
         status_source,
+        prompt,
+        tools: summarize_tools(ProviderKind::Amp, &resolved_child.path, &child_raw),
         excerpt,
         lifecycle,
         relation_evidence,
+        handoffs,
     })
 }
 
 fn extract_amp_handoffs(
     value: &Value,
     source: &str,
-    warnings: &mut Vec<String>,
+    warnings: &mut Vec<Warning>,
 ) -> Vec<AmpHandoff> {
     let mut handoffs = Vec::new();
     for relationship in value
@@ -918,14 +2067,18 @@ fn extract_amp_handoffs(
         }
 
         let Some(thread_id_raw) = relationship.get("threadID").and_then(Value::as_str) else {
-            warnings.push(format!(
-                "{source} thread handoff relationship missing threadID field"
+            warnings.push(Warning::warn(
+                "invalid_relation",
+                format!("{source} thread handoff relationship missing threadID field"),
             ));
             continue;
         };
         let Some(thread_id) = normalize_amp_thread_id(thread_id_raw) else {
-            warnings.push(format!(
-                "{source} thread handoff relationship has invalid threadID={thread_id_raw}"
+            warnings.push(Warning::warn(
+                "invalid_relation",
+                format!(
+                    "{source} thread handoff relationship has invalid threadID={thread_id_raw}"
+                ),
             ));
             continue;
         };
@@ -957,67 +2110,37 @@ fn normalize_amp_thread_id(thread_id: &str) -> Option<String> {
         .map(|uri| uri.session_id)
 }
 
-fn infer_amp_status(value: &Value, has_user: bool, has_assistant: bool) -> (String, String) {
-    if let Some(status) = extract_amp_status(value) {
-        return (status, "child_thread".to_string());
-    }
-    if has_assistant {
-        return (STATUS_COMPLETED.to_string(), "inferred".to_string());
-    }
-    if has_user {
-        return (STATUS_RUNNING.to_string(), "inferred".to_string());
-    }
-    (STATUS_PENDING_INIT.to_string(), "inferred".to_string())
-}
-
-fn extract_amp_status(value: &Value) -> Option<String> {
-    let status = value.get("status");
-    if let Some(status) = status {
-        if let Some(status_str) = status.as_str() {
-            return Some(status_str.to_string());
-        }
-        if let Some(status_obj) = status.as_object() {
-            for key in [
-                STATUS_PENDING_INIT,
-                STATUS_RUNNING,
-                STATUS_COMPLETED,
-                STATUS_ERRORED,
-                STATUS_SHUTDOWN,
-                STATUS_NOT_FOUND,
-            ] {
-                if status_obj.contains_key(key) {
-                    return Some(key.to_string());
-                }
-            }
-        }
-    }
-
-    value
-        .get("state")
-        .and_then(Value::as_str)
-        .map(ToString::to_string)
-}
-
-fn extract_amp_last_update(value: &Value) -> Option<String> {
+/// The last update of the Amp thread `value` read from `path`: a stamp the
+/// thread records, or else its newest message timestamp, looking at the
+/// message shards of a directory-layout thread before `thread.json`.
+fn extract_amp_last_update(path: &Path, value: &Value) -> Option<String> {
     for key in ["lastUpdated", "updatedAt", "timestamp", "createdAt"] {
         if let Some(stamp) = value.get(key).and_then(Value::as_str) {
             return Some(stamp.to_string());
         }
     }
 
-    for message in value
-        .get("messages")
-        .and_then(Value::as_array)
-        .into_iter()
-        .flatten()
+    amp::message_shards(path)
+        .iter()
         .rev()
-    {
-        if let Some(stamp) = message.get("timestamp").and_then(Value::as_str) {
-            return Some(stamp.to_string());
-        }
-    }
+        .filter_map(|shard| read_thread_raw(shard).ok())
+        .find_map(|raw| {
+            serde_json::from_str::<Value>(&raw)
+                .ok()
+                .as_ref()
+                .and_then(last_message_timestamp)
+        })
+        .or_else(|| last_message_timestamp(value))
+}
 
-    None
+fn last_message_timestamp(value: &Value) -> Option<String> {
+    value
+        .get("messages")
+        .and_then(Value::as_array)?
+        .iter()
+        .rev()
+        .find_map(|message| message.get("timestamp").and_then(Value::as_str))
+        .map(str::to_string)
 }
 
 fn push_unique(values: &mut Vec<String>, value: String) {
@@ -1029,11 +2152,17 @@ fn push_unique(values: &mut Vec<String>, value: String) {
 fn resolve_codex_subagent_view(
     uri: &ThreadUri,
     roots: &ProviderRoots,
+    options: &ReadOptions,
     list: bool,
 ) -> Result<SubagentView> {
     let main_uri = main_thread_uri(uri);
     let resolved_main = resolve_thread(&main_uri, roots)?;
-    let main_raw = read_thread_raw(&resolved_main.path)?;
+    let main_raw = read_thread_lines(
+        &resolved_main,
+        roots,
+        options,
+        &[LineKind::ToolCall, LineKind::ToolOutput],
+    )?;
 
     let mut warnings = resolved_main.metadata.warnings.clone();
     let mut timelines = BTreeMap::<String, AgentTimeline>::new();
@@ -1051,7 +2180,7 @@ fn resolve_codex_subagent_view(
         .ok_or_else(|| XurlError::InvalidMode("missing agent id".to_string()))?;
 
     Ok(SubagentView::Detail(build_codex_detail_view(
-        uri, roots, &agent_id, &timelines, warnings,
+        uri, roots, options, &agent_id, &timelines, warnings,
     )))
 }
 
@@ -1059,13 +2188,17 @@ fn build_codex_list_view(
     uri: &ThreadUri,
     roots: &ProviderRoots,
     timelines: &BTreeMap<String, AgentTimeline>,
-    warnings: Vec<String>,
+    warnings: Vec<Warning>,
 ) -> SubagentListView {
+    let child_threads = map_concurrently(timelines.keys().collect::<Vec<_>>(), |agent_id| {
+        resolve_codex_child_thread(agent_id, &uri.session_id, roots)
+    });
+
     let mut agents = Vec::new();
 
-    for (agent_id, timeline) in timelines {
+    for ((agent_id, timeline), child_thread) in timelines.iter().zip(child_threads) {
         let mut relation = SubagentRelation::default();
-        if timeline.has_spawn {
+        if timeline.lifecycle.has_spawn {
             relation.validated = true;
             relation
                 .evidence
@@ -1074,9 +2207,7 @@ fn build_codex_list_view(
 
         let mut child_ref = None;
         let mut last_update = timeline.last_update.clone();
-        if let Some((thread_ref, relation_evidence, thread_last_update)) =
-            resolve_codex_child_thread(agent_id, &uri.session_id, roots)
-        {
+        if let Some((thread_ref, relation_evidence, thread_last_update)) = child_thread {
             if !relation_evidence.is_empty() {
                 relation.validated = true;
                 relation.evidence.extend(relation_evidence);
@@ -1087,15 +2218,31 @@ fn build_codex_list_view(
             child_ref = Some(thread_ref);
         }
 
-        let (status, status_source) = infer_status_from_timeline(timeline, child_ref.is_some());
+        let InferredStatus {
+            status,
+            source: status_source,
+        } = CodexParentStatusInference {
+            child_exists: child_ref.is_some(),
+            child_status: None,
+        }
+        .infer_status(&timeline.lifecycle)
+        .unwrap_or_else(InferredStatus::not_found);
 
         agents.push(SubagentListItem {
             agent_id: agent_id.clone(),
+            agent_name: None,
             status,
             status_source,
+            first_update: earliest_timestamp(
+                timeline
+                    .events
+                    .iter()
+                    .filter_map(|event| event.timestamp.as_deref()),
+            ),
             last_update,
             relation,
             child_thread: child_ref,
+            children: Vec::new(),
         });
     }
 
@@ -1106,23 +2253,64 @@ fn build_codex_list_view(
     }
 }
 
+/// Applies `f` to every item on a pool of scoped threads, returning results
+/// in input order so listings stay deterministic.
+fn map_concurrently<T, R, F>(items: Vec<T>, f: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> R + Sync,
+{
+    let workers = std::thread::available_parallelism()
+        .map_or(1, std::num::NonZeroUsize::get)
+        .min(items.len());
+    if workers <= 1 {
+        return items.into_iter().map(f).collect();
+    }
+
+    let chunk_size = items.len().div_ceil(workers);
+    let mut chunks = Vec::new();
+    let mut items = items.into_iter().peekable();
+    while items.peek().is_some() {
+        chunks.push(items.by_ref().take(chunk_size).collect::<Vec<_>>());
+    }
+
+    let f = &f;
+    std::thread::scope(|scope| {
+        let handles = chunks
+            .into_iter()
+            .map(|chunk| scope.spawn(move || chunk.into_iter().map(f).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|handle| match handle.join() {
+                Ok(results) => results,
+                Err(panic) => std::panic::resume_unwind(panic),
+            })
+            .collect()
+    })
+}
+
 fn build_codex_detail_view(
     uri: &ThreadUri,
     roots: &ProviderRoots,
+    options: &ReadOptions,
     agent_id: &str,
     timelines: &BTreeMap<String, AgentTimeline>,
-    mut warnings: Vec<String>,
+    mut warnings: Vec<Warning>,
 ) -> SubagentDetailView {
     let timeline = timelines.get(agent_id).cloned().unwrap_or_default();
     let mut relation = SubagentRelation::default();
-    if timeline.has_spawn {
+    if timeline.lifecycle.has_spawn {
         relation.validated = true;
         relation
             .evidence
             .push("parent rollout contains spawn_agent output".to_string());
     }
 
+    let mut initial_prompt = timeline.prompt;
     let mut child_thread = None;
+    let mut tools = Vec::new();
     let mut excerpt = Vec::new();
     let mut child_status = None;
 
@@ -1136,13 +2324,24 @@ fn build_codex_detail_view(
 
         match read_thread_raw(&resolved_child.path) {
             Ok(child_raw) => {
-                if let Some(inferred) = infer_codex_child_status(&child_raw, &resolved_child.path) {
-                    child_status = Some(inferred);
+                tools = summarize_tools(ProviderKind::Codex, &resolved_child.path, &child_raw);
+                if let Some(inferred) = (CodexChildStatusInference {
+                    path: &resolved_child.path,
+                })
+                .infer_status(&child_raw)
+                {
+                    child_status = Some(inferred.status);
                 }
 
-                if let Ok(messages) =
-                    render::extract_messages(ProviderKind::Codex, &resolved_child.path, &child_raw)
-                {
+                if let Ok(messages) = render::extract_messages(
+                    ProviderKind::Codex,
+                    &resolved_child.path,
+                    &child_raw,
+                    &mut options.extraction_policy(),
+                ) {
+                    // Rollouts from before `spawn_agent` took a message
+                    // start with the instruction as their first prompt.
+                    initial_prompt = initial_prompt.or_else(|| first_user_prompt(&messages));
                     excerpt = messages
                         .into_iter()
                         .rev()
@@ -1157,16 +2356,24 @@ fn build_codex_detail_view(
                         .collect();
                 }
             }
-            Err(err) => warnings.push(format!(
-                "failed reading child thread for agent_id={agent_id}: {err}"
+            Err(err) => warnings.push(Warning::error(
+                "child_unreadable",
+                format!("failed reading child thread for agent_id={agent_id}: {err}"),
             )),
         }
 
         child_thread = Some(thread_ref);
     }
 
-    let (status, status_source) =
-        infer_status_for_detail(&timeline, child_status, child_thread.is_some());
+    let InferredStatus {
+        status,
+        source: status_source,
+    } = CodexParentStatusInference {
+        child_exists: child_thread.is_some(),
+        child_status,
+    }
+    .infer_status(&timeline.lifecycle)
+    .unwrap_or_else(InferredStatus::not_found);
 
     SubagentDetailView {
         query: make_query(uri, Some(agent_id.to_string()), false),
@@ -1175,6 +2382,8 @@ fn build_codex_detail_view(
         status,
         status_source,
         child_thread,
+        initial_prompt,
+        tools,
         excerpt,
         warnings,
     }
@@ -1185,7 +2394,7 @@ fn resolve_codex_child_thread(
     main_thread_id: &str,
     roots: &ProviderRoots,
 ) -> Option<(SubagentThreadRef, Vec<String>, Option<String>)> {
-    let resolved = CodexProvider::new(&roots.codex_root)
+    let resolved = provider_for(ProviderKind::Codex, roots)
         .resolve(agent_id)
         .ok()?;
     let raw = read_thread_raw(&resolved.path).ok()?;
@@ -1213,7 +2422,7 @@ fn resolve_codex_child_resolved(
     main_thread_id: &str,
     roots: &ProviderRoots,
 ) -> Option<(ResolvedThread, Vec<String>, SubagentThreadRef)> {
-    let resolved = CodexProvider::new(&roots.codex_root)
+    let resolved = provider_for(ProviderKind::Codex, roots)
         .resolve(agent_id)
         .ok()?;
     let raw = read_thread_raw(&resolved.path).ok()?;
@@ -1235,50 +2444,10 @@ fn resolve_codex_child_resolved(
     Some((resolved, evidence, thread_ref))
 }
 
-fn infer_codex_child_status(raw: &str, path: &Path) -> Option<String> {
-    let mut has_assistant_message = false;
-    let mut has_error = false;
-
-    for (line_idx, line) in raw.lines().enumerate() {
-        let Ok(Some(value)) = jsonl::parse_json_line(path, line_idx + 1, line) else {
-            continue;
-        };
-
-        if value.get("type").and_then(Value::as_str) == Some("event_msg") {
-            let payload_type = value
-                .get("payload")
-                .and_then(|payload| payload.get("type"))
-                .and_then(Value::as_str);
-            if payload_type == Some("turn_aborted") {
-                has_error = true;
-            }
-        }
-
-        if render::extract_messages(ProviderKind::Codex, path, line)
-            .ok()
-            .is_some_and(|messages| {
-                messages
-                    .iter()
-                    .any(|message| matches!(message.role, crate::model::MessageRole::Assistant))
-            })
-        {
-            has_assistant_message = true;
-        }
-    }
-
-    if has_error {
-        Some(STATUS_ERRORED.to_string())
-    } else if has_assistant_message {
-        Some(STATUS_COMPLETED.to_string())
-    } else {
-        None
-    }
-}
-
 fn parse_codex_parent_lifecycle(
     raw: &str,
     timelines: &mut BTreeMap<String, AgentTimeline>,
-) -> Vec<String> {
+) -> Vec<Warning> {
     let mut warnings = Vec::new();
     let mut calls: HashMap<String, (String, Value, Option<String>)> = HashMap::new();
 
@@ -1293,9 +2462,12 @@ fn parse_codex_parent_lifecycle(
             Ok(Some(value)) => value,
             Ok(None) => continue,
             Err(err) => {
-                warnings.push(format!(
-                    "failed to parse parent rollout line {}: {err}",
-                    line_idx + 1
+                warnings.push(Warning::warn(
+                    "invalid_line",
+                    format!(
+                        "failed to parse parent rollout line {}: {err}",
+                        line_idx + 1
+                    ),
                 ));
                 continue;
             }
@@ -1373,16 +2545,23 @@ fn parse_codex_parent_lifecycle(
                     .and_then(Value::as_str)
                     .map(ToString::to_string)
                 else {
-                    warnings.push(
+                    warnings.push(Warning::warn(
+                        "invalid_relation",
                         "spawn_agent output did not include agent_id; skipping subagent mapping"
                             .to_string(),
-                    );
+                    ));
                     continue;
                 };
 
                 let timeline = timelines.entry(agent_id).or_default();
-                timeline.has_spawn = true;
-                timeline.has_activity = true;
+                timeline.prompt = args
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .map(str::trim)
+                    .filter(|message| !message.is_empty())
+                    .map(ToString::to_string);
+                timeline.lifecycle.has_spawn = true;
+                timeline.lifecycle.has_activity = true;
                 timeline.last_update = timestamp.clone();
                 timeline.events.push(SubagentLifecycleEvent {
                     timestamp,
@@ -1407,7 +2586,7 @@ fn parse_codex_parent_lifecycle(
 
                 for agent_id in ids {
                     let timeline = timelines.entry(agent_id).or_default();
-                    timeline.has_activity = true;
+                    timeline.lifecycle.has_activity = true;
                     timeline.last_update = timestamp.clone();
 
                     let mut detail = if timed_out {
@@ -1417,10 +2596,10 @@ fn parse_codex_parent_lifecycle(
                     };
 
                     if let Some(state) = infer_state_from_status_payload(&output_value) {
-                        timeline.states.push(state.clone());
+                        timeline.lifecycle.states.push(state.clone());
                         detail = format!("wait state={state}");
                     } else if timed_out {
-                        timeline.states.push(STATUS_RUNNING.to_string());
+                        timeline.lifecycle.states.push(STATUS_RUNNING.to_string());
                     }
 
                     timeline.events.push(SubagentLifecycleEvent {
@@ -1440,14 +2619,14 @@ fn parse_codex_parent_lifecycle(
                 };
 
                 let timeline = timelines.entry(agent_id).or_default();
-                timeline.has_activity = true;
+                timeline.lifecycle.has_activity = true;
                 timeline.last_update = timestamp.clone();
 
                 if name == "close_agent" {
                     if let Some(state) = infer_state_from_status_payload(&output_value) {
-                        timeline.states.push(state.clone());
+                        timeline.lifecycle.states.push(state.clone());
                     } else {
-                        timeline.states.push(STATUS_SHUTDOWN.to_string());
+                        timeline.lifecycle.states.push(STATUS_SHUTDOWN.to_string());
                     }
                 }
 
@@ -1469,16 +2648,7 @@ fn infer_state_from_status_payload(payload: &Value) -> Option<String> {
 
     if let Some(object) = status.as_object() {
         for key in object.keys() {
-            if [
-                STATUS_PENDING_INIT,
-                STATUS_RUNNING,
-                STATUS_COMPLETED,
-                STATUS_ERRORED,
-                STATUS_SHUTDOWN,
-                STATUS_NOT_FOUND,
-            ]
-            .contains(&key.as_str())
-            {
+            if KNOWN_STATUSES.contains(&key.as_str()) {
                 return Some(key.clone());
             }
         }
@@ -1491,63 +2661,10 @@ fn infer_state_from_status_payload(payload: &Value) -> Option<String> {
     None
 }
 
-fn infer_status_from_timeline(timeline: &AgentTimeline, child_exists: bool) -> (String, String) {
-    if timeline.states.iter().any(|state| state == STATUS_ERRORED) {
-        return (STATUS_ERRORED.to_string(), "parent_rollout".to_string());
-    }
-    if timeline.states.iter().any(|state| state == STATUS_SHUTDOWN) {
-        return (STATUS_SHUTDOWN.to_string(), "parent_rollout".to_string());
-    }
-    if timeline
-        .states
-        .iter()
-        .any(|state| state == STATUS_COMPLETED)
-    {
-        return (STATUS_COMPLETED.to_string(), "parent_rollout".to_string());
-    }
-    if timeline.states.iter().any(|state| state == STATUS_RUNNING) || timeline.has_activity {
-        return (STATUS_RUNNING.to_string(), "parent_rollout".to_string());
-    }
-    if timeline.has_spawn {
-        return (
-            STATUS_PENDING_INIT.to_string(),
-            "parent_rollout".to_string(),
-        );
-    }
-    if child_exists {
-        return (STATUS_RUNNING.to_string(), "child_rollout".to_string());
-    }
-
-    (STATUS_NOT_FOUND.to_string(), "inferred".to_string())
-}
-
-fn infer_status_for_detail(
-    timeline: &AgentTimeline,
-    child_status: Option<String>,
-    child_exists: bool,
-) -> (String, String) {
-    let (status, source) = infer_status_from_timeline(timeline, child_exists);
-    if status == STATUS_NOT_FOUND
-        && let Some(child_status) = child_status
-    {
-        return (child_status, "child_rollout".to_string());
-    }
-
-    (status, source)
-}
-
 fn extract_codex_parent_thread_id(raw: &str) -> Option<String> {
     let first = raw.lines().find(|line| !line.trim().is_empty())?;
-    let value = serde_json::from_str::<Value>(first).ok()?;
-
-    value
-        .get("payload")
-        .and_then(|payload| payload.get("source"))
-        .and_then(|source| source.get("subagent"))
-        .and_then(|subagent| subagent.get("thread_spawn"))
-        .and_then(|thread_spawn| thread_spawn.get("parent_thread_id"))
-        .and_then(Value::as_str)
-        .map(ToString::to_string)
+    let value = serde_json::from_str::<Value>(first).ok()?;
+    codex_spawn_parent(&value).map(ToString::to_string)
 }
 
 fn resolve_claude_subagent_view(
@@ -1568,8 +2685,10 @@ fn resolve_claude_subagent_view(
                 .iter()
                 .map(|record| SubagentListItem {
                     agent_id: record.agent_id.clone(),
+                    agent_name: record.agent_name.clone(),
                     status: record.status.clone(),
-                    status_source: "inferred".to_string(),
+                    status_source: SOURCE_INFERRED.to_string(),
+                    first_update: None,
                     last_update: record.last_update.clone(),
                     relation: record.relation.clone(),
                     child_thread: Some(SubagentThreadRef {
@@ -1577,6 +2696,7 @@ fn resolve_claude_subagent_view(
                         path: Some(record.path.display().to_string()),
                         last_updated_at: record.last_update.clone(),
                     }),
+                    children: Vec::new(),
                 })
                 .collect(),
             warnings,
@@ -1588,12 +2708,30 @@ fn resolve_claude_subagent_view(
         .clone()
         .ok_or_else(|| XurlError::InvalidMode("missing agent id".to_string()))?;
 
-    let normalized_requested = normalize_agent_id(&requested_agent);
+    let mut records = records;
+    let matched = match find_claude_agent(&records, &requested_agent) {
+        Ok(index) => index.map(|index| records.swap_remove(index)),
+        Err(candidates) => {
+            warnings.push(Warning::error(
+                "ambiguous_agent",
+                format!(
+                    "agent name {requested_agent} matches {} agents for main_session_id={}: {}; use an agent id instead",
+                    candidates.len(),
+                    uri.session_id,
+                    candidates.join(", ")
+                ),
+            ));
+            return Ok(claude_agent_not_found(uri, requested_agent, warnings));
+        }
+    };
 
-    if let Some(record) = records
-        .into_iter()
-        .find(|record| normalize_agent_id(&record.agent_id) == normalized_requested)
-    {
+    if let Some(mut record) = matched {
+        if normalize_agent_id(&record.agent_id) != normalize_agent_id(&requested_agent) {
+            record.relation.evidence.push(format!(
+                "agent name {requested_agent} resolved to agent_id={}",
+                record.agent_id
+            ));
+        }
         let lifecycle = vec![SubagentLifecycleEvent {
             timestamp: record.last_update.clone(),
             event: "discovered_agent_file".to_string(),
@@ -1607,32 +2745,83 @@ fn resolve_claude_subagent_view(
             relation: record.relation.clone(),
             lifecycle,
             status: record.status.clone(),
-            status_source: "inferred".to_string(),
+            status_source: SOURCE_INFERRED.to_string(),
             child_thread: Some(SubagentThreadRef {
                 thread_id: record.agent_id.clone(),
                 path: Some(record.path.display().to_string()),
                 last_updated_at: record.last_update.clone(),
             }),
+            initial_prompt: record.task_prompt.or(record.prompt),
+            tools: record.tools,
             excerpt: record.excerpt,
             warnings,
         }));
     }
 
-    warnings.push(format!(
-        "agent not found for main_session_id={} agent_id={requested_agent}",
-        uri.session_id
+    warnings.push(Warning::error(
+        "agent_not_found",
+        format!(
+            "agent not found for main_session_id={} agent_id={requested_agent}",
+            uri.session_id
+        ),
     ));
 
-    Ok(SubagentView::Detail(SubagentDetailView {
+    Ok(claude_agent_not_found(uri, requested_agent, warnings))
+}
+
+/// Finds the agent by id or, failing that, by its `subagent_type` name.
+/// A name shared by several agents yields their ids as the error.
+fn find_claude_agent(
+    records: &[ClaudeAgentRecord],
+    requested: &str,
+) -> std::result::Result<Option<usize>, Vec<String>> {
+    let normalized_requested = normalize_agent_id(requested);
+    if let Some(index) = records
+        .iter()
+        .position(|record| normalize_agent_id(&record.agent_id) == normalized_requested)
+    {
+        return Ok(Some(index));
+    }
+
+    let named = records
+        .iter()
+        .enumerate()
+        .filter(|(_, record)| {
+            record
+                .agent_name
+                .as_deref()
+                .is_some_and(|name| name.eq_ignore_ascii_case(requested))
+        })
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+
+    match named.as_slice() {
+        [] => Ok(None),
+        [index] => Ok(Some(*index)),
+        _ => Err(named
+            .iter()
+            .map(|index| records[*index].agent_id.clone())
+            .collect()),
+    }
+}
+
+fn claude_agent_not_found(
+    uri: &ThreadUri,
+    requested_agent: String,
+    warnings: Vec<Warning>,
+) -> SubagentView {
+    SubagentView::Detail(SubagentDetailView {
         query: make_query(uri, Some(requested_agent), false),
         relation: SubagentRelation::default(),
         lifecycle: Vec::new(),
         status: STATUS_NOT_FOUND.to_string(),
-        status_source: "inferred".to_string(),
+        status_source: SOURCE_INFERRED.to_string(),
         child_thread: None,
+        initial_prompt: None,
+        tools: Vec::new(),
         excerpt: Vec::new(),
         warnings,
-    }))
+    })
 }
 
 fn resolve_gemini_subagent_view(
@@ -1654,8 +2843,10 @@ fn resolve_gemini_subagent_view(
                 if let Some(chat) = chats.get(child_session_id) {
                     return SubagentListItem {
                         agent_id: child_session_id.clone(),
+                        agent_name: None,
                         status: chat.status.clone(),
-                        status_source: "child_rollout".to_string(),
+                        status_source: SOURCE_CHILD_ROLLOUT.to_string(),
+                        first_update: record.relation_timestamp.clone(),
                         last_update: chat.last_update.clone(),
                         relation: record.relation.clone(),
                         child_thread: Some(SubagentThreadRef {
@@ -1663,13 +2854,14 @@ fn resolve_gemini_subagent_view(
                             path: Some(chat.path.display().to_string()),
                             last_updated_at: chat.last_update.clone(),
                         }),
+                        children: Vec::new(),
                     };
                 }
 
                 let missing_warning = format!(
                     "child session {child_session_id} discovered from local Gemini data but chat file was not found in project chats"
                 );
-                warnings.push(missing_warning);
+                warnings.push(Warning::warn("child_missing", missing_warning));
                 let missing_evidence =
                     "child session could not be materialized to a chat file".to_string();
                 if !record.relation.evidence.contains(&missing_evidence) {
@@ -1678,11 +2870,14 @@ fn resolve_gemini_subagent_view(
 
                 SubagentListItem {
                     agent_id: child_session_id.clone(),
+                    agent_name: None,
                     status: STATUS_NOT_FOUND.to_string(),
-                    status_source: "inferred".to_string(),
+                    status_source: SOURCE_INFERRED.to_string(),
+                    first_update: record.relation_timestamp.clone(),
                     last_update: record.relation_timestamp.clone(),
                     relation: record.relation.clone(),
                     child_thread: None,
+                    children: Vec::new(),
                 }
             })
             .collect::<Vec<_>>();
@@ -1702,9 +2897,10 @@ fn resolve_gemini_subagent_view(
     let mut relation = SubagentRelation::default();
     let mut lifecycle = Vec::new();
     let mut status = STATUS_NOT_FOUND.to_string();
-    let mut status_source = "inferred".to_string();
+    let mut status_source = SOURCE_INFERRED.to_string();
     let mut child_thread = None;
     let mut excerpt = Vec::new();
+    let mut tools = Vec::new();
 
     if let Some(record) = children.get_mut(&requested_child) {
         relation = record.relation.clone();
@@ -1722,16 +2918,20 @@ fn resolve_gemini_subagent_view(
 
         if let Some(chat) = chats.get(&requested_child) {
             status = chat.status.clone();
-            status_source = "child_rollout".to_string();
+            status_source = SOURCE_CHILD_ROLLOUT.to_string();
             child_thread = Some(SubagentThreadRef {
                 thread_id: requested_child.clone(),
                 path: Some(chat.path.display().to_string()),
                 last_updated_at: chat.last_update.clone(),
             });
-            excerpt = extract_child_excerpt(ProviderKind::Gemini, &chat.path, &mut warnings);
+            (excerpt, tools) =
+                extract_child_excerpt(ProviderKind::Gemini, &chat.path, &mut warnings);
         } else {
-            warnings.push(format!(
-                "child session {requested_child} discovered from local Gemini data but chat file was not found in project chats"
+            warnings.push(Warning::warn(
+                "child_missing",
+                format!(
+                    "child session {requested_child} discovered from local Gemini data but chat file was not found in project chats"
+                ),
             ));
             let missing_evidence =
                 "child session could not be materialized to a chat file".to_string();
@@ -1740,9 +2940,12 @@ fn resolve_gemini_subagent_view(
             }
         }
     } else if let Some(chat) = chats.get(&requested_child) {
-        warnings.push(format!(
-            "unable to validate Gemini parent-child relation for main_session_id={} child_session_id={requested_child}",
-            uri.session_id
+        warnings.push(Warning::warn(
+            "relation_unvalidated",
+            format!(
+                "unable to validate Gemini parent-child relation for main_session_id={} child_session_id={requested_child}",
+                uri.session_id
+            ),
         ));
         lifecycle.push(SubagentLifecycleEvent {
             timestamp: chat.last_update.clone(),
@@ -1750,17 +2953,20 @@ fn resolve_gemini_subagent_view(
             detail: "child chat exists but relation to main thread is unknown".to_string(),
         });
         status = chat.status.clone();
-        status_source = "child_rollout".to_string();
+        status_source = SOURCE_CHILD_ROLLOUT.to_string();
         child_thread = Some(SubagentThreadRef {
             thread_id: requested_child.clone(),
             path: Some(chat.path.display().to_string()),
             last_updated_at: chat.last_update.clone(),
         });
-        excerpt = extract_child_excerpt(ProviderKind::Gemini, &chat.path, &mut warnings);
+        (excerpt, tools) = extract_child_excerpt(ProviderKind::Gemini, &chat.path, &mut warnings);
     } else {
-        warnings.push(format!(
-            "child session not found for main_session_id={} child_session_id={requested_child}",
-            uri.session_id
+        warnings.push(Warning::error(
+            "child_not_found",
+            format!(
+                "child session not found for main_session_id={} child_session_id={requested_child}",
+                uri.session_id
+            ),
         ));
     }
 
@@ -1771,6 +2977,8 @@ fn resolve_gemini_subagent_view(
         status,
         status_source,
         child_thread,
+        initial_prompt: None,
+        tools,
         excerpt,
         warnings,
     }))
@@ -1779,15 +2987,18 @@ fn resolve_gemini_subagent_view(
 fn discover_gemini_children(
     resolved_main: &ResolvedThread,
     main_session_id: &str,
-    warnings: &mut Vec<String>,
+    warnings: &mut Vec<Warning>,
 ) -> (
     BTreeMap<String, GeminiChatRecord>,
     BTreeMap<String, GeminiChildRecord>,
 ) {
     let Some(project_dir) = resolved_main.path.parent().and_then(Path::parent) else {
-        warnings.push(format!(
-            "cannot determine Gemini project directory from resolved main thread path: {}",
-            resolved_main.path.display()
+        warnings.push(Warning::error(
+            "project_dir_unknown",
+            format!(
+                "cannot determine Gemini project directory from resolved main thread path: {}",
+                resolved_main.path.display()
+            ),
         ));
         return (BTreeMap::new(), BTreeMap::new());
     };
@@ -1851,22 +3062,28 @@ fn discover_gemini_children(
 
 fn load_gemini_project_chats(
     project_dir: &Path,
-    warnings: &mut Vec<String>,
+    warnings: &mut Vec<Warning>,
 ) -> BTreeMap<String, GeminiChatRecord> {
     let chats_dir = project_dir.join("chats");
     if !chats_dir.exists() {
-        warnings.push(format!(
-            "Gemini project chats directory not found: {}",
-            chats_dir.display()
+        warnings.push(Warning::warn(
+            "chats_dir_missing",
+            format!(
+                "Gemini project chats directory not found: {}",
+                chats_dir.display()
+            ),
         ));
         return BTreeMap::new();
     }
 
     let mut chats = BTreeMap::<String, GeminiChatRecord>::new();
     let Ok(entries) = fs::read_dir(&chats_dir) else {
-        warnings.push(format!(
-            "failed to read Gemini chats directory: {}",
-            chats_dir.display()
+        warnings.push(Warning::warn(
+            "chats_dir_unreadable",
+            format!(
+                "failed to read Gemini chats directory: {}",
+                chats_dir.display()
+            ),
         ));
         return chats;
     };
@@ -1902,13 +3119,13 @@ fn load_gemini_project_chats(
     chats
 }
 
-fn parse_gemini_chat_file(path: &Path, warnings: &mut Vec<String>) -> Option<GeminiChatRecord> {
+fn parse_gemini_chat_file(path: &Path, warnings: &mut Vec<Warning>) -> Option<GeminiChatRecord> {
     let raw = match read_thread_raw(path) {
         Ok(raw) => raw,
         Err(err) => {
-            warnings.push(format!(
-                "failed to read Gemini chat {}: {err}",
-                path.display()
+            warnings.push(Warning::warn(
+                "chat_unreadable",
+                format!("failed to read Gemini chat {}: {err}", path.display()),
             ));
             return None;
         }
@@ -1917,9 +3134,9 @@ fn parse_gemini_chat_file(path: &Path, warnings: &mut Vec<String>) -> Option<Gem
     let value = match serde_json::from_str::<Value>(&raw) {
         Ok(value) => value,
         Err(err) => {
-            warnings.push(format!(
-                "failed to parse Gemini chat JSON {}: {err}",
-                path.display()
+            warnings.push(Warning::warn(
+                "invalid_chat",
+                format!("failed to parse Gemini chat JSON {}: {err}", path.display()),
             ));
             return None;
         }
@@ -1930,9 +3147,9 @@ fn parse_gemini_chat_file(path: &Path, warnings: &mut Vec<String>) -> Option<Gem
         .and_then(Value::as_str)
         .and_then(parse_session_id_like)
     else {
-        warnings.push(format!(
-            "Gemini chat missing valid sessionId: {}",
-            path.display()
+        warnings.push(Warning::warn(
+            "invalid_chat",
+            format!("Gemini chat missing valid sessionId: {}", path.display()),
         ));
         return None;
     };
@@ -1949,7 +3166,10 @@ fn parse_gemini_chat_file(path: &Path, warnings: &mut Vec<String>) -> Option<Gem
         })
         .or_else(|| modified_timestamp_string(path));
 
-    let status = infer_gemini_chat_status(&value);
+    let status = GeminiStatusInference.infer_status(&value).map_or_else(
+        || STATUS_PENDING_INIT.to_string(),
+        |inferred| inferred.status,
+    );
     let explicit_parent_ids = parse_parent_session_ids(&value);
 
     Some(GeminiChatRecord {
@@ -1961,43 +3181,7 @@ fn parse_gemini_chat_file(path: &Path, warnings: &mut Vec<String>) -> Option<Gem
     })
 }
 
-fn infer_gemini_chat_status(value: &Value) -> String {
-    let Some(messages) = value.get("messages").and_then(Value::as_array) else {
-        return STATUS_PENDING_INIT.to_string();
-    };
-
-    let mut has_error = false;
-    let mut has_assistant = false;
-    let mut has_user = false;
-
-    for message in messages {
-        let message_type = message
-            .get("type")
-            .and_then(Value::as_str)
-            .unwrap_or_default();
-        if message_type == "error" || !message.get("error").is_none_or(Value::is_null) {
-            has_error = true;
-        }
-        if message_type == "gemini" || message_type == "assistant" {
-            has_assistant = true;
-        }
-        if message_type == "user" {
-            has_user = true;
-        }
-    }
-
-    if has_error {
-        STATUS_ERRORED.to_string()
-    } else if has_assistant {
-        STATUS_COMPLETED.to_string()
-    } else if has_user {
-        STATUS_RUNNING.to_string()
-    } else {
-        STATUS_PENDING_INIT.to_string()
-    }
-}
-
-fn read_gemini_log_entries(project_dir: &Path, warnings: &mut Vec<String>) -> Vec<GeminiLogEntry> {
+fn read_gemini_log_entries(project_dir: &Path, warnings: &mut Vec<Warning>) -> Vec<GeminiLogEntry> {
     let logs_path = project_dir.join("logs.json");
     if !logs_path.exists() {
         return Vec::new();
@@ -2006,9 +3190,12 @@ fn read_gemini_log_entries(project_dir: &Path, warnings: &mut Vec<String>) -> Ve
     let raw = match read_thread_raw(&logs_path) {
         Ok(raw) => raw,
         Err(err) => {
-            warnings.push(format!(
-                "failed to read Gemini logs file {}: {err}",
-                logs_path.display()
+            warnings.push(Warning::warn(
+                "logs_unreadable",
+                format!(
+                    "failed to read Gemini logs file {}: {err}",
+                    logs_path.display()
+                ),
             ));
             return Vec::new();
         }
@@ -2034,10 +3221,13 @@ fn read_gemini_log_entries(project_dir: &Path, warnings: &mut Vec<String>) -> Ve
                     parsed.push(entry);
                 }
             }
-            Err(err) => warnings.push(format!(
-                "failed to parse Gemini logs line {} in {}: {err}",
-                index + 1,
-                logs_path.display()
+            Err(err) => warnings.push(Warning::warn(
+                "invalid_line",
+                format!(
+                    "failed to parse Gemini logs line {} in {}: {err}",
+                    index + 1,
+                    logs_path.display()
+                ),
             )),
         }
     }
@@ -2047,7 +3237,7 @@ fn read_gemini_log_entries(project_dir: &Path, warnings: &mut Vec<String>) -> Ve
 fn parse_gemini_logs_value(
     logs_path: &Path,
     value: Value,
-    warnings: &mut Vec<String>,
+    warnings: &mut Vec<Warning>,
 ) -> Vec<GeminiLogEntry> {
     match value {
         Value::Array(entries) => entries
@@ -2073,9 +3263,12 @@ fn parse_gemini_logs_value(
                 .collect()
         }
         _ => {
-            warnings.push(format!(
-                "unsupported Gemini logs format in {}: expected JSON array or object",
-                logs_path.display()
+            warnings.push(Warning::warn(
+                "invalid_logs",
+                format!(
+                    "unsupported Gemini logs format in {}: expected JSON array or object",
+                    logs_path.display()
+                ),
             ));
             Vec::new()
         }
@@ -2086,13 +3279,16 @@ fn parse_gemini_log_entry(
     logs_path: &Path,
     line: usize,
     value: &Value,
-    warnings: &mut Vec<String>,
+    warnings: &mut Vec<Warning>,
 ) -> Option<GeminiLogEntry> {
     let Some(object) = value.as_object() else {
-        warnings.push(format!(
-            "invalid Gemini log entry at {} line {}: expected JSON object",
-            logs_path.display(),
-            line
+        warnings.push(Warning::warn(
+            "invalid_line",
+            format!(
+                "invalid Gemini log entry at {} line {}: expected JSON object",
+                logs_path.display(),
+                line
+            ),
         ));
         return None;
     };
@@ -2270,68 +3466,90 @@ fn parse_session_id_like(raw: &str) -> Option<String> {
     Some(normalized)
 }
 
+/// The first user message, which starts a subagent with its instruction.
+fn first_user_prompt(messages: &[ThreadMessage]) -> Option<String> {
+    messages
+        .iter()
+        .find(|message| message.role == MessageRole::User)
+        .map(|message| message.text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
+
+/// The last messages and the tool usage of a child thread.
 fn extract_child_excerpt(
     provider: ProviderKind,
     path: &Path,
-    warnings: &mut Vec<String>,
-) -> Vec<SubagentExcerptMessage> {
+    warnings: &mut Vec<Warning>,
+) -> (Vec<SubagentExcerptMessage>, Vec<ToolUsage>) {
     let raw = match read_thread_raw(path) {
         Ok(raw) => raw,
         Err(err) => {
-            warnings.push(format!(
-                "failed reading child thread {}: {err}",
-                path.display()
+            warnings.push(Warning::error(
+                "child_unreadable",
+                format!("failed reading child thread {}: {err}", path.display()),
             ));
-            return Vec::new();
+            return (Vec::new(), Vec::new());
         }
     };
 
-    match render::extract_messages(provider, path, &raw) {
-        Ok(messages) => messages
-            .into_iter()
-            .rev()
-            .take(3)
-            .collect::<Vec<_>>()
-            .into_iter()
-            .rev()
-            .map(|message| SubagentExcerptMessage {
-                role: message.role,
-                text: message.text,
-            })
-            .collect(),
-        Err(err) => {
-            warnings.push(format!(
-                "failed extracting child messages from {}: {err}",
-                path.display()
-            ));
-            Vec::new()
-        }
-    }
+    let tools = summarize_tools(provider, path, &raw);
+    let excerpt =
+        match render::extract_messages(provider, path, &raw, &mut ExtractionPolicy::default()) {
+            Ok(messages) => messages
+                .into_iter()
+                .rev()
+                .take(3)
+                .collect::<Vec<_>>()
+                .into_iter()
+                .rev()
+                .map(|message| SubagentExcerptMessage {
+                    role: message.role,
+                    text: message.text,
+                })
+                .collect(),
+            Err(err) => {
+                warnings.push(Warning::error(
+                    "child_invalid",
+                    format!(
+                        "failed extracting child messages from {}: {err}",
+                        path.display()
+                    ),
+                ));
+                Vec::new()
+            }
+        };
+    (excerpt, tools)
 }
 
 fn discover_claude_agents(
     resolved_main: &ResolvedThread,
     main_session_id: &str,
-    warnings: &mut Vec<String>,
+    warnings: &mut Vec<Warning>,
 ) -> Vec<ClaudeAgentRecord> {
     let Some(project_dir) = resolved_main.path.parent() else {
-        warnings.push(format!(
-            "cannot determine project directory from resolved main thread path: {}",
-            resolved_main.path.display()
+        warnings.push(Warning::error(
+            "project_dir_unknown",
+            format!(
+                "cannot determine project directory from resolved main thread path: {}",
+                resolved_main.path.display()
+            ),
         ));
         return Vec::new();
     };
 
+    let ancestors = discover_claude_session_lineage(project_dir, resolved_main, warnings);
     let mut candidate_files = BTreeSet::new();
 
-    let nested_subagent_dir = project_dir.join(main_session_id).join("subagents");
-    if nested_subagent_dir.exists()
-        && let Ok(entries) = fs::read_dir(&nested_subagent_dir)
-    {
-        for entry in entries.filter_map(std::result::Result::ok) {
-            let path = entry.path();
-            if is_claude_agent_filename(&path) {
-                candidate_files.insert(path);
+    for session_id in std::iter::once(main_session_id).chain(ancestors.iter().map(String::as_str)) {
+        let nested_subagent_dir = project_dir.join(session_id).join("subagents");
+        if nested_subagent_dir.exists()
+            && let Ok(entries) = fs::read_dir(&nested_subagent_dir)
+        {
+            for entry in entries.filter_map(std::result::Result::ok) {
+                let path = entry.path();
+                if is_claude_agent_filename(&path) {
+                    candidate_files.insert(path);
+                }
             }
         }
     }
@@ -2345,12 +3563,24 @@ fn discover_claude_agents(
         }
     }
 
+    let transcripts = std::iter::once(resolved_main.path.clone())
+        .chain(
+            ancestors
+                .iter()
+                .map(|session_id| project_dir.join(format!("{session_id}.jsonl"))),
+        )
+        .collect::<Vec<_>>();
+    let names = index_claude_agent_names(&transcripts);
     let mut latest_by_agent = BTreeMap::<String, ClaudeAgentRecord>::new();
 
     for path in candidate_files {
-        let Some(record) = analyze_claude_agent_file(&path, main_session_id, warnings) else {
+        let Some(mut record) =
+            analyze_claude_agent_file(&path, main_session_id, &ancestors, warnings)
+        else {
             continue;
         };
+        record.agent_name = names.name_for(&record);
+        record.task_prompt = names.prompts_by_agent_id.get(&record.agent_id).cloned();
 
         match latest_by_agent.get(&record.agent_id) {
             Some(existing) => {
@@ -2369,17 +3599,242 @@ fn discover_claude_agents(
     latest_by_agent.into_values().collect()
 }
 
+/// Follows `resumedFrom`, carried-over `sessionId`s, and `parentUuid` links
+/// that point outside the main transcript to find the sessions it was
+/// resumed from, nearest first.
+fn discover_claude_session_lineage(
+    project_dir: &Path,
+    resolved_main: &ResolvedThread,
+    warnings: &mut Vec<Warning>,
+) -> Vec<String> {
+    let mut ancestors = Vec::new();
+    let mut visited = BTreeSet::from([resolved_main.session_id.clone()]);
+    let mut pending = vec![resolved_main.path.clone()];
+    let mut uuid_index = None::<HashMap<String, String>>;
+
+    while let Some(path) = pending.pop() {
+        let Ok(raw) = read_thread_raw(&path) else {
+            continue;
+        };
+
+        let mut uuids = BTreeSet::new();
+        let mut parent_uuids = BTreeSet::new();
+        let mut linked_sessions = Vec::new();
+        for (line_idx, line) in raw.lines().enumerate() {
+            let Ok(Some(value)) = jsonl::parse_json_line(&path, line_idx + 1, line) else {
+                continue;
+            };
+            if let Some(uuid) = value.get("uuid").and_then(Value::as_str) {
+                uuids.insert(uuid.to_string());
+            }
+            if let Some(parent_uuid) = value.get("parentUuid").and_then(Value::as_str) {
+                parent_uuids.insert(parent_uuid.to_string());
+            }
+            for key in ["resumedFrom", "sessionId"] {
+                if let Some(session_id) = value.get(key).and_then(Value::as_str) {
+                    linked_sessions.push(session_id.to_string());
+                }
+            }
+        }
+
+        let dangling = parent_uuids.difference(&uuids).cloned().collect::<Vec<_>>();
+        if !dangling.is_empty() {
+            let index = uuid_index.get_or_insert_with(|| index_claude_session_uuids(project_dir));
+            linked_sessions.extend(dangling.iter().filter_map(|uuid| index.get(uuid).cloned()));
+        }
+
+        for session_id in linked_sessions {
+            if !visited.insert(session_id.clone()) {
+                continue;
+            }
+            let session_path = project_dir.join(format!("{session_id}.jsonl"));
+            if session_path.is_file() {
+                pending.push(session_path);
+            } else {
+                warnings.push(Warning::warn(
+                    "lineage_missing",
+                    format!(
+                        "claude session {} was resumed from session_id={session_id}, but its transcript was not found in {}",
+                        resolved_main.session_id,
+                        project_dir.display()
+                    ),
+                ));
+            }
+            ancestors.push(session_id);
+        }
+    }
+
+    ancestors
+}
+
+/// `subagent_type`s of the Task calls in the main transcripts, keyed by the
+/// agent id reported in the tool result and, for agents still running, by
+/// the prompt they were started with. Also keeps each call's prompt by agent
+/// id.
+#[derive(Debug, Default)]
+struct ClaudeAgentNames {
+    by_agent_id: HashMap<String, String>,
+    by_prompt: HashMap<String, String>,
+    prompts_by_agent_id: HashMap<String, String>,
+}
+
+impl ClaudeAgentNames {
+    fn name_for(&self, record: &ClaudeAgentRecord) -> Option<String> {
+        self.by_agent_id
+            .get(&record.agent_id)
+            .or_else(|| {
+                record
+                    .prompt
+                    .as_ref()
+                    .and_then(|prompt| self.by_prompt.get(prompt))
+            })
+            .cloned()
+    }
+}
+
+fn index_claude_agent_names(transcripts: &[PathBuf]) -> ClaudeAgentNames {
+    let mut names = ClaudeAgentNames::default();
+    let mut tool_uses = HashMap::<String, (Option<String>, Option<String>)>::new();
+    let mut results = Vec::<(String, String)>::new();
+
+    for path in transcripts {
+        let Ok(raw) = read_thread_raw(path) else {
+            continue;
+        };
+        for (line_idx, line) in raw.lines().enumerate() {
+            let Ok(Some(value)) = jsonl::parse_json_line(path, line_idx + 1, line) else {
+                continue;
+            };
+            let items = value
+                .get("message")
+                .and_then(|message| message.get("content"))
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten();
+
+            for item in items {
+                match item.get("type").and_then(Value::as_str) {
+                    Some("tool_use")
+                        if matches!(
+                            item.get("name").and_then(Value::as_str),
+                            Some("Task" | "Agent")
+                        ) =>
+                    {
+                        let input = item.get("input");
+                        let name = input
+                            .and_then(|input| input.get("subagent_type"))
+                            .and_then(Value::as_str)
+                            .map(ToString::to_string);
+                        let prompt = input
+                            .and_then(|input| input.get("prompt"))
+                            .and_then(Value::as_str)
+                            .map(|prompt| prompt.trim().to_string());
+                        if let (Some(prompt), Some(name)) = (&prompt, &name) {
+                            names.by_prompt.insert(prompt.clone(), name.clone());
+                        }
+                        if let Some(id) = item.get("id").and_then(Value::as_str) {
+                            tool_uses.insert(id.to_string(), (name, prompt));
+                        }
+                    }
+                    Some("tool_result") => {
+                        if let Some(tool_use_id) = item.get("tool_use_id").and_then(Value::as_str)
+                            && let Some(agent_id) = value
+                                .get("toolUseResult")
+                                .and_then(|result| result.get("agentId"))
+                                .and_then(Value::as_str)
+                        {
+                            results.push((tool_use_id.to_string(), agent_id.to_string()));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    for (tool_use_id, agent_id) in results {
+        let Some((name, prompt)) = tool_uses.get(&tool_use_id) else {
+            continue;
+        };
+        if let Some(name) = name {
+            names.by_agent_id.insert(agent_id.clone(), name.clone());
+        }
+        if let Some(prompt) = prompt {
+            names.prompts_by_agent_id.insert(agent_id, prompt.clone());
+        }
+    }
+
+    names
+}
+
+/// Maps every message uuid in the project's main transcripts to its session.
+fn index_claude_session_uuids(project_dir: &Path) -> HashMap<String, String> {
+    let mut index = HashMap::new();
+    let Ok(entries) = fs::read_dir(project_dir) else {
+        return index;
+    };
+
+    for path in entries
+        .filter_map(std::result::Result::ok)
+        .map(|entry| entry.path())
+    {
+        if !path.is_file()
+            || is_claude_agent_filename(&path)
+            || path.extension().and_then(|ext| ext.to_str()) != Some("jsonl")
+        {
+            continue;
+        }
+        let Some(session_id) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let Ok(raw) = read_thread_raw(&path) else {
+            continue;
+        };
+        for (line_idx, line) in raw.lines().enumerate() {
+            if let Ok(Some(value)) = jsonl::parse_json_line(&path, line_idx + 1, line)
+                && let Some(uuid) = value.get("uuid").and_then(Value::as_str)
+            {
+                index.insert(uuid.to_string(), session_id.to_string());
+            }
+        }
+    }
+
+    index
+}
+
+/// Whether a Claude assistant record hands the turn to a tool rather than
+/// ending it.
+fn claude_requests_tool(value: &Value) -> bool {
+    let message = value.get("message");
+    message
+        .and_then(|message| message.get("stop_reason"))
+        .and_then(Value::as_str)
+        == Some("tool_use")
+        || message
+            .and_then(|message| message.get("content"))
+            .and_then(Value::as_array)
+            .is_some_and(|content| {
+                content
+                    .iter()
+                    .any(|block| block.get("type").and_then(Value::as_str) == Some("tool_use"))
+            })
+}
+
 fn analyze_claude_agent_file(
     path: &Path,
     main_session_id: &str,
-    warnings: &mut Vec<String>,
+    ancestors: &[String],
+    warnings: &mut Vec<Warning>,
 ) -> Option<ClaudeAgentRecord> {
     let raw = match read_thread_raw(path) {
         Ok(raw) => raw,
         Err(err) => {
-            warnings.push(format!(
-                "failed to read Claude agent transcript {}: {err}",
-                path.display()
+            warnings.push(Warning::warn(
+                "transcript_unreadable",
+                format!(
+                    "failed to read Claude agent transcript {}: {err}",
+                    path.display()
+                ),
             ));
             return None;
         }
@@ -2387,10 +3842,8 @@ fn analyze_claude_agent_file(
 
     let mut agent_id = None::<String>;
     let mut is_sidechain = false;
-    let mut session_matches = false;
-    let mut has_error = false;
-    let mut has_assistant = false;
-    let mut has_user = false;
+    let mut agent_session_id = None::<String>;
+    let mut signals = TranscriptSignals::default();
     let mut last_update = None::<String>;
 
     for (line_idx, line) in raw.lines().enumerate() {
@@ -2402,10 +3855,13 @@ fn analyze_claude_agent_file(
             Ok(Some(value)) => value,
             Ok(None) => continue,
             Err(err) => {
-                warnings.push(format!(
-                    "failed to parse Claude agent transcript line {} in {}: {err}",
-                    line_idx + 1,
-                    path.display()
+                warnings.push(Warning::warn(
+                    "invalid_line",
+                    format!(
+                        "failed to parse Claude agent transcript line {} in {}: {err}",
+                        line_idx + 1,
+                        path.display()
+                    ),
                 ));
                 continue;
             }
@@ -2420,10 +3876,10 @@ fn analyze_claude_agent_file(
                 .get("isSidechain")
                 .and_then(Value::as_bool)
                 .unwrap_or(false);
-            session_matches = value
+            agent_session_id = value
                 .get("sessionId")
                 .and_then(Value::as_str)
-                .is_some_and(|session_id| session_id == main_session_id);
+                .map(ToString::to_string);
         }
 
         if let Some(timestamp) = value
@@ -2440,42 +3896,57 @@ fn analyze_claude_agent_file(
             .unwrap_or(false)
             || !value.get("error").is_none_or(Value::is_null)
         {
-            has_error = true;
+            signals.has_error = true;
         }
 
-        if let Some(kind) = value.get("type").and_then(Value::as_str) {
-            if kind == "assistant" {
-                has_assistant = true;
+        match value.get("type").and_then(Value::as_str) {
+            Some("assistant") => {
+                signals.has_assistant = true;
+                signals.mid_turn = claude_requests_tool(&value);
             }
-            if kind == "user" {
-                has_user = true;
+            Some("user") => {
+                signals.has_user = true;
+                signals.mid_turn = true;
             }
+            _ => {}
         }
     }
 
-    if !is_sidechain || !session_matches {
+    let ancestor = match agent_session_id.as_deref() {
+        Some(session_id) if session_id == main_session_id => None,
+        Some(session_id) if ancestors.iter().any(|ancestor| ancestor == session_id) => {
+            Some(session_id.to_string())
+        }
+        _ => return None,
+    };
+    if !is_sidechain {
         return None;
     }
 
     let Some(agent_id) = agent_id else {
-        warnings.push(format!(
-            "missing agentId in Claude sidechain transcript: {}",
-            path.display()
+        warnings.push(Warning::warn(
+            "invalid_transcript",
+            format!(
+                "missing agentId in Claude sidechain transcript: {}",
+                path.display()
+            ),
         ));
         return None;
     };
 
-    let status = if has_error {
-        STATUS_ERRORED.to_string()
-    } else if has_assistant {
-        STATUS_COMPLETED.to_string()
-    } else if has_user {
-        STATUS_RUNNING.to_string()
-    } else {
-        STATUS_PENDING_INIT.to_string()
-    };
-
-    let excerpt = render::extract_messages(ProviderKind::Claude, path, &raw)
+    let status = signals.status().to_string();
+
+    let messages = render::extract_messages(
+        ProviderKind::Claude,
+        path,
+        &raw,
+        &mut ExtractionPolicy::default(),
+    )
+    .ok();
+    let prompt = messages
+        .as_ref()
+        .and_then(|messages| first_user_prompt(messages));
+    let excerpt = messages
         .map(|messages| {
             messages
                 .into_iter()
@@ -2496,12 +3967,21 @@ fn analyze_claude_agent_file(
         validated: true,
         ..SubagentRelation::default()
     };
-    relation
-        .evidence
-        .push("agent transcript is sidechain and sessionId matches main thread".to_string());
+    match ancestor {
+        None => relation
+            .evidence
+            .push("agent transcript is sidechain and sessionId matches main thread".to_string()),
+        Some(ancestor) => relation.evidence.push(format!(
+            "agent transcript is sidechain and sessionId matches session {ancestor}, which main thread {main_session_id} was resumed from"
+        )),
+    }
 
     Some(ClaudeAgentRecord {
         agent_id,
+        agent_name: None,
+        prompt,
+        task_prompt: None,
+        tools: summarize_tools(ProviderKind::Claude, path, &raw),
         path: path.to_path_buf(),
         status,
         last_update: last_update.or_else(|| modified_timestamp_string(path)),
@@ -2531,8 +4011,18 @@ fn file_modified_epoch(path: &Path) -> Option<u64> {
         .map(|duration| duration.as_secs())
 }
 
+fn earliest_timestamp<'a>(timestamps: impl Iterator<Item = &'a str>) -> Option<String> {
+    timestamps
+        .filter_map(|timestamp| parse_timestamp(timestamp).map(|parsed| (parsed, timestamp)))
+        .min_by_key(|(parsed, _)| *parsed)
+        .map(|(_, timestamp)| timestamp.to_string())
+}
+
 fn modified_timestamp_string(path: &Path) -> Option<String> {
-    file_modified_epoch(path).map(|stamp| stamp.to_string())
+    fs::metadata(path)
+        .ok()
+        .and_then(|meta| meta.modified().ok())
+        .map(|modified| format_rfc3339(DateTime::<Utc>::from(modified)))
 }
 
 fn normalize_agent_id(agent_id: &str) -> String {
@@ -2564,6 +4054,9 @@ fn main_thread_uri(uri: &ThreadUri) -> ThreadUri {
         provider: uri.provider,
         session_id: uri.session_id.clone(),
         agent_id: None,
+        fragment: None,
+        file_format: uri.file_format,
+        gemini_project: uri.gemini_project.clone(),
     }
 }
 
@@ -2576,15 +4069,16 @@ fn make_query(uri: &ThreadUri, agent_id: Option<String>, list: bool) -> Subagent
     }
 }
 
-fn agents_thread_uri(provider: &str, thread_id: &str, agent_id: Option<&str>) -> String {
+pub(crate) fn agents_thread_uri(provider: &str, thread_id: &str, agent_id: Option<&str>) -> String {
     match agent_id {
         Some(agent_id) => format!("agents://{provider}/{thread_id}/{agent_id}"),
         None => format!("agents://{provider}/{thread_id}"),
     }
 }
 
-fn render_preview_text(content: &Value, max_chars: usize) -> String {
-    let text = if content.is_string() {
+/// The text of a message's `content`, either a string or a list of parts.
+fn preview_source_text(content: &Value) -> String {
+    if content.is_string() {
         content.as_str().unwrap_or_default().to_string()
     } else if let Some(items) = content.as_array() {
         items
@@ -2598,9 +4092,7 @@ fn render_preview_text(content: &Value, max_chars: usize) -> String {
             .join(" ")
     } else {
         String::new()
-    };
-
-    truncate_preview(&text, max_chars)
+    }
 }
 
 fn truncate_preview(input: &str, max_chars: usize) -> String {
@@ -2657,12 +4149,37 @@ fn render_subagent_list_markdown(view: &SubagentListView) -> String {
         {
             output.push_str(&format!("- Thread Path: `{}`\n", path));
         }
+        render_subagent_children_markdown(&mut output, 0, &view.query.provider, agent);
         output.push('\n');
     }
 
     output
 }
 
+fn render_subagent_children_markdown(
+    output: &mut String,
+    depth: usize,
+    provider: &str,
+    parent: &SubagentListItem,
+) {
+    if parent.children.is_empty() {
+        return;
+    }
+
+    let pad = "  ".repeat(depth);
+    if depth == 0 {
+        output.push_str("- Handoffs:\n");
+    }
+    for child in &parent.children {
+        output.push_str(&format!(
+            "{pad}  - `{}` (`{}`)\n",
+            agents_thread_uri(provider, &parent.agent_id, Some(&child.agent_id)),
+            child.status
+        ));
+        render_subagent_children_markdown(output, depth + 1, provider, child);
+    }
+}
+
 fn render_subagent_detail_markdown(view: &SubagentDetailView) -> String {
     let main_thread_uri = agents_thread_uri(&view.query.provider, &view.query.main_thread_id, None);
     let mut output = String::new();
@@ -2702,6 +4219,12 @@ fn render_subagent_detail_markdown(view: &SubagentDetailView) -> String {
     }
     output.push('\n');
 
+    if let Some(prompt) = &view.initial_prompt {
+        output.push_str("## Initial Prompt\n\n");
+        output.push_str(prompt);
+        output.push_str("\n\n");
+    }
+
     output.push_str("## Lifecycle (Parent Thread)\n\n");
     if view.lifecycle.is_empty() {
         output.push_str("_No lifecycle events found in parent thread._\n\n");
@@ -2717,6 +4240,21 @@ fn render_subagent_detail_markdown(view: &SubagentDetailView) -> String {
         output.push('\n');
     }
 
+    if !view.tools.is_empty() {
+        output.push_str("## Tools (Child Thread)\n\n");
+        output.push_str("| Tool | Calls | Errors | Last Used |\n| --- | --- | --- | --- |\n");
+        for tool in &view.tools {
+            output.push_str(&format!(
+                "| `{}` | {} | {} | `{}` |\n",
+                tool.name,
+                tool.calls,
+                tool.errors,
+                tool.last_used_at.as_deref().unwrap_or("unknown")
+            ));
+        }
+        output.push('\n');
+    }
+
     output.push_str("## Thread Excerpt (Child Thread)\n\n");
     if view.excerpt.is_empty() {
         output.push_str("_No child thread messages found._\n\n");
@@ -2738,10 +4276,87 @@ fn render_subagent_detail_markdown(view: &SubagentDetailView) -> String {
 #[cfg(test)]
 mod tests {
     use std::fs;
+    use std::io::Write;
 
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
     use tempfile::tempdir;
 
-    use crate::service::{extract_last_timestamp, read_thread_raw};
+    use crate::error::XurlError;
+    use crate::model::{ProviderKind, WriteRequest};
+    use crate::provider::ProviderRoots;
+    use crate::render::ReadOptions;
+    use crate::service::{
+        extract_amp_last_update, extract_last_timestamp, list_recent_threads, map_concurrently,
+        read_thread_raw, render_thread_markdown, resolve_subagent_view, resolve_thread,
+        write_thread,
+    };
+    use crate::sink::BufferedSink;
+    use crate::uri::ThreadUri;
+
+    #[test]
+    fn amp_last_update_reads_the_newest_message_shard() {
+        let temp = tempdir().expect("tempdir");
+        let thread = temp.path().join("thread.json");
+        let value = serde_json::json!({
+            "messages": [{ "role": "user", "timestamp": "2026-02-23T00:00:00Z" }],
+        });
+        fs::write(&thread, value.to_string()).expect("write thread");
+        assert_eq!(
+            extract_amp_last_update(&thread, &value).as_deref(),
+            Some("2026-02-23T00:00:00Z")
+        );
+
+        for (idx, stamp) in [(2, "2026-02-23T02:00:00Z"), (10, "2026-02-23T10:00:00Z")] {
+            fs::write(
+                temp.path().join(format!("messages-{idx}.json")),
+                serde_json::json!({
+                    "messages": [{ "role": "assistant", "timestamp": stamp }],
+                })
+                .to_string(),
+            )
+            .expect("write shard");
+        }
+        assert_eq!(
+            extract_amp_last_update(&thread, &value).as_deref(),
+            Some("2026-02-23T10:00:00Z")
+        );
+    }
+
+    #[test]
+    fn recent_threads_keep_opencode_ids_differing_in_case() {
+        let temp = tempdir().expect("tempdir");
+        let root = temp.path().join("opencode");
+        fs::create_dir_all(&root).expect("mkdir");
+        let conn = rusqlite::Connection::open(root.join("opencode.db")).expect("open sqlite");
+        conn.execute_batch(
+            "CREATE TABLE session (id TEXT PRIMARY KEY);
+             CREATE TABLE message (id TEXT PRIMARY KEY, session_id TEXT NOT NULL,
+                                   time_created INTEGER NOT NULL, data TEXT NOT NULL);
+             INSERT INTO session (id) VALUES ('ses_abcDEF'), ('ses_ABCdef');",
+        )
+        .expect("create sessions");
+        drop(conn);
+
+        let recent = list_recent_threads(
+            &ProviderRoots::for_test(temp.path()),
+            &ReadOptions::default(),
+            10,
+        );
+        let mut ids = recent
+            .threads
+            .iter()
+            .map(|thread| thread.uri.as_str())
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        assert_eq!(
+            ids,
+            [
+                "agents://opencode/ses_ABCdef",
+                "agents://opencode/ses_abcDEF"
+            ]
+        );
+    }
 
     #[test]
     fn empty_file_returns_error() {
@@ -2753,6 +4368,31 @@ mod tests {
         assert!(format!("{err}").contains("thread file is empty"));
     }
 
+    #[test]
+    fn renders_gzipped_archived_rollouts() {
+        let temp = tempdir().expect("tempdir");
+        let path = temp.path().join(
+            "archived_sessions/rollout-2026-02-22T01-05-36-019c8129-f668-7951-8d56-cc5513541c26.jsonl.gz",
+        );
+        fs::create_dir_all(path.parent().expect("parent")).expect("mkdir");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(concat!(
+                r#"{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"hello"}]}}"#,
+                "\n",
+            ).as_bytes())
+            .expect("compress");
+        fs::write(&path, encoder.finish().expect("finish")).expect("write");
+
+        let mut roots = ProviderRoots::for_test(temp.path());
+        roots.codex_root = temp.path().to_path_buf();
+        let uri =
+            ThreadUri::parse("agents://codex/019c8129-f668-7951-8d56-cc5513541c26").expect("uri");
+        let resolved = resolve_thread(&uri, &roots).expect("resolve");
+        let markdown = render_thread_markdown(&uri, &resolved).expect("render");
+        assert!(markdown.contains("hello"));
+    }
+
     #[test]
     fn extract_last_timestamp_from_jsonl() {
         let raw =
@@ -2760,4 +4400,54 @@ mod tests {
         let timestamp = extract_last_timestamp(raw).expect("must extract timestamp");
         assert_eq!(timestamp, "2026-02-23T00:00:02Z");
     }
+
+    #[test]
+    fn map_concurrently_preserves_input_order() {
+        let items = (0..50).collect::<Vec<_>>();
+        let doubled = map_concurrently(items, |item| item * 2);
+        assert_eq!(doubled, (0..50).map(|item| item * 2).collect::<Vec<_>>());
+        assert!(map_concurrently(Vec::<u8>::new(), |item| item).is_empty());
+    }
+
+    #[test]
+    fn capabilities_match_what_providers_support() {
+        let temp = tempdir().expect("tempdir");
+        let roots = ProviderRoots::for_test(temp.path());
+
+        for kind in ProviderKind::all() {
+            let capabilities = kind.capabilities();
+            let uri = ThreadUri {
+                provider: *kind,
+                session_id: "missing".to_string(),
+                agent_id: None,
+                fragment: None,
+                file_format: None,
+                gemini_project: None,
+            };
+            let unsupported = matches!(
+                resolve_subagent_view(&uri, &roots, &ReadOptions::default(), true),
+                Err(XurlError::UnsupportedSubagentProvider(_))
+            );
+            assert_eq!(unsupported, !capabilities.supports_subagents, "{kind}");
+
+            // Supported writes would start the agent's CLI.
+            if !capabilities.supports_write {
+                let req = WriteRequest {
+                    prompt: "hello".to_string(),
+                    session_id: None,
+                    parent_entry_id: None,
+                    spawn_agent: false,
+                    gemini_project: None,
+                };
+                assert!(
+                    matches!(
+                        write_thread(*kind, &roots, &req, &mut BufferedSink::new()),
+                        Err(XurlError::UnsupportedProviderWrite(_))
+                    ),
+                    "{kind}"
+                );
+            }
+        }
+        assert!(!ProviderKind::all().contains(&ProviderKind::File));
+    }
 }
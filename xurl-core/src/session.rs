@@ -0,0 +1,282 @@
+//! Session metadata recorded in transcripts: model, working directory, and
+//! git branch.
+//!
+//! Codex writes them to `session_meta` and `turn_context` records, Claude to
+//! every transcript line (`cwd`, `gitBranch`) and assistant message
+//! (`message.model`), and Pi to its `session` header and `model_change`
+//! entries. Other providers record none of them.
+//!
+//! Subagent threads also record the thread that spawned them, see
+//! [`read_thread_parent`].
+
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::compression::open_thread_file;
+use crate::model::{ProviderKind, SessionInfo, ThreadParent};
+use crate::service::agents_thread_uri;
+
+/// Transcript lines scanned for session metadata. Providers record it in
+/// their first records, so long transcripts are not read to the end.
+pub const SESSION_SCAN_LINES: usize = 200;
+
+/// Reads the session metadata of the transcript at `path`. Missing or
+/// unreadable files yield an empty [`SessionInfo`].
+pub fn read_session_info(provider: ProviderKind, path: &Path) -> SessionInfo {
+    let mut info = SessionInfo::default();
+    if !matches!(
+        provider,
+        ProviderKind::Codex | ProviderKind::Claude | ProviderKind::Pi
+    ) {
+        return info;
+    }
+    let Ok(file) = open_thread_file(path) else {
+        return info;
+    };
+
+    for line in BufReader::new(file)
+        .split(b'\n')
+        .take(SESSION_SCAN_LINES)
+        .map_while(std::result::Result::ok)
+    {
+        let line = String::from_utf8_lossy(&line);
+        let Ok(value) = serde_json::from_str::<Value>(line.trim_start_matches('\u{feff}')) else {
+            continue;
+        };
+        match provider {
+            ProviderKind::Codex => codex_session_info(&value, &mut info),
+            ProviderKind::Claude => claude_session_info(&value, &mut info),
+            _ => pi_session_info(&value, &mut info),
+        }
+        if info.model.is_some() && info.cwd.is_some() && info.git_branch.is_some() {
+            break;
+        }
+    }
+    info
+}
+
+fn codex_session_info(value: &Value, info: &mut SessionInfo) {
+    let payload = &value["payload"];
+    match value["type"].as_str() {
+        Some("session_meta") => {
+            fill(&mut info.cwd, &payload["cwd"]);
+            fill(&mut info.git_branch, &payload["git"]["branch"]);
+        }
+        Some("turn_context") => {
+            fill(&mut info.model, &payload["model"]);
+            fill(&mut info.cwd, &payload["cwd"]);
+        }
+        _ => {}
+    }
+}
+
+fn claude_session_info(value: &Value, info: &mut SessionInfo) {
+    fill(&mut info.cwd, &value["cwd"]);
+    fill(&mut info.git_branch, &value["gitBranch"]);
+    // Claude marks messages it made up itself (e.g. after an API error) with
+    // a `<synthetic>` model.
+    if value["type"] == "assistant" && value["message"]["model"] != "<synthetic>" {
+        fill(&mut info.model, &value["message"]["model"]);
+    }
+}
+
+fn pi_session_info(value: &Value, info: &mut SessionInfo) {
+    match value["type"].as_str() {
+        Some("session") => fill(&mut info.cwd, &value["cwd"]),
+        Some("model_change") => fill(&mut info.model, &value["modelId"]),
+        Some("message") if value["message"]["role"] == "assistant" => {
+            fill(&mut info.model, &value["message"]["model"]);
+        }
+        _ => {}
+    }
+}
+
+/// Reads which thread spawned the subagent thread `session_id` stored at
+/// `path`: Codex records it in the `session_meta` source, Amp as a handoff
+/// relationship with the `parent` role. `None` for main threads.
+pub fn read_thread_parent(
+    provider: ProviderKind,
+    path: &Path,
+    session_id: &str,
+) -> Option<ThreadParent> {
+    let (parent_id, spawned_at) = match provider {
+        ProviderKind::Codex => {
+            let file = open_thread_file(path).ok()?;
+            BufReader::new(file)
+                .split(b'\n')
+                .take(SESSION_SCAN_LINES)
+                .map_while(std::result::Result::ok)
+                .find_map(|line| {
+                    let line = String::from_utf8_lossy(&line);
+                    let value =
+                        serde_json::from_str::<Value>(line.trim_start_matches('\u{feff}')).ok()?;
+                    let parent_id = codex_spawn_parent(&value)?.to_string();
+                    Some((parent_id, value["timestamp"].as_str().map(str::to_string)))
+                })?
+        }
+        ProviderKind::Amp => {
+            let value = serde_json::from_str::<Value>(&fs::read_to_string(path).ok()?).ok()?;
+            value["relationships"]
+                .as_array()?
+                .iter()
+                .find(|relationship| {
+                    relationship["type"] == "handoff"
+                        && relationship["role"]
+                            .as_str()
+                            .is_some_and(|role| role.eq_ignore_ascii_case("parent"))
+                })
+                .and_then(|relationship| {
+                    let parent_id = relationship["threadID"].as_str()?.to_string();
+                    let spawned_at = ["timestamp", "createdAt"]
+                        .iter()
+                        .find_map(|key| relationship[key].as_str())
+                        .map(str::to_string);
+                    Some((parent_id, spawned_at))
+                })?
+        }
+        _ => return None,
+    };
+
+    let provider = provider.to_string();
+    Some(ThreadParent {
+        uri: agents_thread_uri(&provider, &parent_id, None),
+        subagent_uri: agents_thread_uri(&provider, &parent_id, Some(session_id)),
+        spawned_at,
+    })
+}
+
+/// The parent thread id in a Codex `session_meta` record of a spawned
+/// subagent.
+pub(crate) fn codex_spawn_parent(value: &Value) -> Option<&str> {
+    value["payload"]["source"]["subagent"]["thread_spawn"]["parent_thread_id"].as_str()
+}
+
+/// Whether a session recorded as running in `cwd` belongs to `project`:
+/// `cwd` is `project` or a directory inside it.
+pub fn cwd_in_project(cwd: &str, project: &Path) -> bool {
+    let cwd = Path::new(cwd);
+    if cwd.starts_with(project) {
+        return true;
+    }
+    // Either path may go through a symlink, e.g. /tmp on macOS.
+    match (fs::canonicalize(cwd), fs::canonicalize(project)) {
+        (Ok(cwd), Ok(project)) => cwd.starts_with(project),
+        _ => false,
+    }
+}
+
+/// Sets `slot` to `value` unless it already holds one or `value` is not a
+/// non-empty string.
+fn fill(slot: &mut Option<String>, value: &Value) {
+    if slot.is_none() {
+        *slot = value
+            .as_str()
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+            .map(str::to_string);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use tempfile::tempdir;
+
+    use crate::model::{ProviderKind, SessionInfo};
+    use crate::session::{cwd_in_project, read_session_info, read_thread_parent};
+
+    fn info(model: &str, cwd: &str, git_branch: Option<&str>) -> SessionInfo {
+        SessionInfo {
+            model: Some(model.to_string()),
+            cwd: Some(cwd.to_string()),
+            git_branch: git_branch.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn reads_session_info_per_provider() {
+        let temp = tempdir().expect("tempdir");
+        let cases = [
+            (
+                ProviderKind::Codex,
+                concat!(
+                    r#"{"type":"session_meta","payload":{"id":"s","cwd":"/repo","git":{"branch":"main"}}}"#,
+                    "\n",
+                    r#"{"type":"turn_context","payload":{"cwd":"/repo","model":"o4-mini"}}"#,
+                    "\n",
+                    r#"{"type":"turn_context","payload":{"cwd":"/repo","model":"gpt-5"}}"#,
+                    "\n",
+                ),
+                info("o4-mini", "/repo", Some("main")),
+            ),
+            (
+                ProviderKind::Claude,
+                concat!(
+                    r#"{"type":"user","cwd":"/repo","gitBranch":"feature/x","message":{"role":"user","content":"hi"}}"#,
+                    "\n",
+                    r#"{"type":"assistant","cwd":"/repo","message":{"role":"assistant","model":"<synthetic>","content":[]}}"#,
+                    "\n",
+                    r#"{"type":"assistant","cwd":"/repo","message":{"role":"assistant","model":"claude-sonnet-4-5","content":[]}}"#,
+                    "\n",
+                ),
+                info("claude-sonnet-4-5", "/repo", Some("feature/x")),
+            ),
+            (
+                ProviderKind::Pi,
+                concat!(
+                    "\u{feff}",
+                    r#"{"type":"session","version":3,"id":"s","cwd":"/repo"}"#,
+                    "\n",
+                    r#"{"type":"model_change","provider":"openai","modelId":"gpt-5.3-codex"}"#,
+                    "\n",
+                ),
+                info("gpt-5.3-codex", "/repo", None),
+            ),
+        ];
+
+        for (provider, transcript, expected) in cases {
+            let path = temp.path().join(format!("{provider}.jsonl"));
+            fs::write(&path, transcript).expect("write");
+            assert_eq!(read_session_info(provider, &path), expected, "{provider}");
+        }
+
+        assert!(read_session_info(ProviderKind::Codex, &temp.path().join("missing")).is_empty());
+    }
+
+    #[test]
+    fn reads_the_parent_an_amp_child_records() {
+        let temp = tempdir().expect("tempdir");
+        let path = temp.path().join("T-child.json");
+        fs::write(
+            &path,
+            r#"{"id":"T-child","messages":[],"relationships":[{"type":"handoff","threadID":"T-main","role":"Parent","timestamp":"2026-02-23T00:00:10Z"}]}"#,
+        )
+        .expect("write");
+
+        let parent = read_thread_parent(ProviderKind::Amp, &path, "T-child").expect("parent");
+        assert_eq!(parent.uri, "agents://amp/T-main");
+        assert_eq!(parent.subagent_uri, "agents://amp/T-main/T-child");
+        assert_eq!(parent.spawned_at.as_deref(), Some("2026-02-23T00:00:10Z"));
+
+        fs::write(
+            &path,
+            r#"{"id":"T-main","relationships":[{"type":"handoff","threadID":"T-child","role":"child"}]}"#,
+        )
+        .expect("write");
+        assert_eq!(read_thread_parent(ProviderKind::Amp, &path, "T-main"), None);
+    }
+
+    #[test]
+    fn cwd_matches_project_and_its_subdirectories() {
+        let project = Path::new("/work/app");
+        assert!(cwd_in_project("/work/app", project));
+        assert!(cwd_in_project("/work/app/crates/core", project));
+        assert!(!cwd_in_project("/work/app-old", project));
+        assert!(!cwd_in_project("/work", project));
+    }
+}
@@ -0,0 +1,299 @@
+//! Ready-made [`WriteEventSink`]s for consuming write-mode output.
+//!
+//! Providers stream the assistant reply as text deltas, but some only report
+//! it once the write finishes (`WriteResult::final_text`). Each sink here
+//! falls back to that final text when no deltas arrived, so callers get the
+//! reply either way.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+
+use crate::error::{Result, XurlError};
+use crate::model::{ProviderKind, WriteResult};
+use crate::provider::{AgentProcess, WriteEventSink};
+
+/// Streams the reply to any writer, flushing after every delta.
+#[derive(Debug)]
+pub struct StreamSink<W> {
+    writer: W,
+    /// Names the writer in I/O errors, e.g. the output path or `<stdout>`.
+    label: PathBuf,
+    text_written: bool,
+}
+
+impl<W: Write> StreamSink<W> {
+    pub fn new(writer: W, label: impl Into<PathBuf>) -> Self {
+        Self {
+            writer,
+            label: label.into(),
+            text_written: false,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_text(&mut self, text: &str) -> Result<()> {
+        if text.is_empty() {
+            return Ok(());
+        }
+
+        self.writer
+            .write_all(text.as_bytes())
+            .and_then(|()| self.writer.flush())
+            .map_err(|source| XurlError::Io {
+                path: self.label.clone(),
+                source,
+            })?;
+        self.text_written = true;
+        Ok(())
+    }
+}
+
+impl<W: Write> WriteEventSink for StreamSink<W> {
+    fn on_session_ready(&mut self, _provider: ProviderKind, _session_id: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_text_delta(&mut self, text: &str) -> Result<()> {
+        self.write_text(text)
+    }
+
+    fn on_write_finished(&mut self, result: &WriteResult) -> Result<()> {
+        match result.final_text.as_deref() {
+            Some(text) if !self.text_written => self.write_text(text),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Collects the reply in memory.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BufferedSink {
+    session: Option<(ProviderKind, String)>,
+    text: String,
+}
+
+impl BufferedSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Provider and session id reported when the session became ready.
+    pub fn session(&self) -> Option<(ProviderKind, &str)> {
+        self.session
+            .as_ref()
+            .map(|(provider, session_id)| (*provider, session_id.as_str()))
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn into_text(self) -> String {
+        self.text
+    }
+}
+
+impl WriteEventSink for BufferedSink {
+    fn on_session_ready(&mut self, provider: ProviderKind, session_id: &str) -> Result<()> {
+        self.session = Some((provider, session_id.to_string()));
+        Ok(())
+    }
+
+    fn on_text_delta(&mut self, text: &str) -> Result<()> {
+        self.text.push_str(text);
+        Ok(())
+    }
+
+    fn on_write_finished(&mut self, result: &WriteResult) -> Result<()> {
+        if self.session.is_none() {
+            self.session = Some((result.provider, result.session_id.clone()));
+        }
+        // Whitespace-only deltas are not a reply; prefer the final text.
+        if self.text.trim().is_empty()
+            && let Some(text) = &result.final_text
+        {
+            self.text.clone_from(text);
+        }
+        Ok(())
+    }
+}
+
+/// Forwards every event to two sinks, `first` before `second`.
+#[derive(Debug, Clone, Default)]
+pub struct TeeSink<A, B> {
+    pub first: A,
+    pub second: B,
+}
+
+impl<A, B> TeeSink<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        Self { first, second }
+    }
+
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+}
+
+impl<A: WriteEventSink, B: WriteEventSink> WriteEventSink for TeeSink<A, B> {
+    fn on_session_ready(&mut self, provider: ProviderKind, session_id: &str) -> Result<()> {
+        self.first.on_session_ready(provider, session_id)?;
+        self.second.on_session_ready(provider, session_id)
+    }
+
+    fn on_text_delta(&mut self, text: &str) -> Result<()> {
+        self.first.on_text_delta(text)?;
+        self.second.on_text_delta(text)
+    }
+
+    fn on_write_finished(&mut self, result: &WriteResult) -> Result<()> {
+        self.first.on_write_finished(result)?;
+        self.second.on_write_finished(result)
+    }
+
+    fn on_agent_spawned(&mut self, agent: &AgentProcess) {
+        self.first.on_agent_spawned(agent);
+        self.second.on_agent_spawned(agent);
+    }
+}
+
+/// One write-mode event, as sent by [`ChannelSink`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WriteEvent {
+    SessionReady {
+        provider: ProviderKind,
+        session_id: String,
+    },
+    TextDelta(String),
+    /// Always the last event. Carries the final text when no deltas were
+    /// streamed.
+    Finished(WriteResult),
+}
+
+/// Sends events to another thread, e.g. an async task that drains the
+/// receiver. The write fails once the receiver is dropped.
+#[derive(Debug, Clone)]
+pub struct ChannelSink {
+    sender: Sender<WriteEvent>,
+    text_sent: bool,
+}
+
+impl ChannelSink {
+    pub fn new(sender: Sender<WriteEvent>) -> Self {
+        Self {
+            sender,
+            text_sent: false,
+        }
+    }
+
+    fn send(&self, event: WriteEvent) -> Result<()> {
+        self.sender
+            .send(event)
+            .map_err(|_| XurlError::WriteProtocol("write event receiver was dropped".to_string()))
+    }
+}
+
+impl WriteEventSink for ChannelSink {
+    fn on_session_ready(&mut self, provider: ProviderKind, session_id: &str) -> Result<()> {
+        self.send(WriteEvent::SessionReady {
+            provider,
+            session_id: session_id.to_string(),
+        })
+    }
+
+    fn on_text_delta(&mut self, text: &str) -> Result<()> {
+        if text.is_empty() {
+            return Ok(());
+        }
+        self.send(WriteEvent::TextDelta(text.to_string()))?;
+        self.text_sent = true;
+        Ok(())
+    }
+
+    fn on_write_finished(&mut self, result: &WriteResult) -> Result<()> {
+        let mut result = result.clone();
+        if self.text_sent {
+            result.final_text = None;
+        }
+        self.send(WriteEvent::Finished(result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use crate::model::{ProviderKind, WriteResult};
+    use crate::provider::WriteEventSink;
+    use crate::sink::{BufferedSink, ChannelSink, StreamSink, TeeSink, WriteEvent};
+
+    fn result(final_text: Option<&str>) -> WriteResult {
+        WriteResult {
+            provider: ProviderKind::Codex,
+            session_id: "session".to_string(),
+            final_text: final_text.map(ToString::to_string),
+            entry_id: None,
+            agent_id: None,
+        }
+    }
+
+    #[test]
+    fn sinks_fall_back_to_final_text_without_deltas() {
+        let mut sink = TeeSink::new(BufferedSink::new(), StreamSink::new(Vec::new(), "<buffer>"));
+        sink.on_text_delta("  ").expect("delta");
+        sink.on_write_finished(&result(Some("final")))
+            .expect("finish");
+
+        let (buffered, stream) = sink.into_inner();
+        assert_eq!(buffered.text(), "final");
+        assert_eq!(buffered.session(), Some((ProviderKind::Codex, "session")));
+        // The whitespace already reached the writer, so it is not replaced.
+        assert_eq!(stream.into_inner(), b"  ");
+    }
+
+    #[test]
+    fn streamed_text_wins_over_final_text() {
+        let mut sink = TeeSink::new(BufferedSink::new(), StreamSink::new(Vec::new(), "<buffer>"));
+        sink.on_session_ready(ProviderKind::Claude, "abc")
+            .expect("ready");
+        sink.on_text_delta("hello ").expect("delta");
+        sink.on_text_delta("world").expect("delta");
+        sink.on_write_finished(&result(Some("ignored")))
+            .expect("finish");
+
+        let (buffered, stream) = sink.into_inner();
+        assert_eq!(buffered.text(), "hello world");
+        assert_eq!(buffered.session(), Some((ProviderKind::Claude, "abc")));
+        assert_eq!(stream.into_inner(), b"hello world");
+    }
+
+    #[test]
+    fn channel_sink_sends_events_in_order() {
+        let (sender, receiver) = mpsc::channel();
+        let mut sink = ChannelSink::new(sender);
+        sink.on_session_ready(ProviderKind::Codex, "session")
+            .expect("ready");
+        sink.on_text_delta("hi").expect("delta");
+        sink.on_write_finished(&result(Some("hi"))).expect("finish");
+
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<_>>(),
+            vec![
+                WriteEvent::SessionReady {
+                    provider: ProviderKind::Codex,
+                    session_id: "session".to_string(),
+                },
+                WriteEvent::TextDelta("hi".to_string()),
+                WriteEvent::Finished(result(None)),
+            ]
+        );
+
+        drop(receiver);
+        let err = sink.on_text_delta("late").expect_err("receiver is gone");
+        assert!(format!("{err}").contains("receiver was dropped"));
+    }
+}
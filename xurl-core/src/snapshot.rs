@@ -0,0 +1,322 @@
+//! Read-only copies of live threads (`xurl snapshot`).
+//!
+//! The thread file and the files of its subagents are copied into a new
+//! timestamped directory and marked read-only, so later reads through the
+//! returned `file://` URI see the thread as it was, while the agent keeps
+//! appending to the original.
+//!
+//! Layout of a snapshot directory:
+//!
+//! ```text
+//! <provider>-<session_id>-<YYYYMMDDTHHMMSSZ>/
+//!   <thread file>               (Amp directory threads: with their shards;
+//!                                Codex sessions resumed into several
+//!                                rollouts: one rollout holding them all)
+//!   subagents/<agent_id>/<thread file>
+//! ```
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+
+use crate::compression::{read_thread_file, strip_compression};
+use crate::error::{Result, XurlError};
+use crate::model::{
+    ProviderKind, SnapshotRequest, SnapshotResult, SubagentListItem, SubagentView, Warning,
+};
+use crate::provider::ProviderRoots;
+use crate::provider::amp;
+use crate::render::ReadOptions;
+use crate::service::{locate_thread, resolve_subagent_view, resolve_thread_files, thread_files};
+use crate::uri::ThreadUri;
+
+const SNAPSHOTS_DIR: &str = "snapshots";
+const SUBAGENTS_DIR: &str = "subagents";
+
+/// Copies the thread of `uri`, and for a main thread the files of its
+/// subagents, into a new snapshot directory.
+pub fn snapshot_thread(
+    uri: &ThreadUri,
+    roots: &ProviderRoots,
+    req: &SnapshotRequest,
+) -> Result<SnapshotResult> {
+    let (path, rollouts, mut warnings) = if uri.agent_id.is_none() {
+        let resolved = resolve_thread_files(uri, roots)?;
+        (
+            resolved.path.clone(),
+            thread_files(&resolved),
+            resolved.metadata.warnings,
+        )
+    } else {
+        let location = locate_thread(uri, roots)?;
+        (location.path, Vec::new(), location.warnings)
+    };
+
+    let out_dir = req
+        .out_dir
+        .clone()
+        .unwrap_or_else(|| roots.data_root.join(SNAPSHOTS_DIR));
+    let name = match uri.provider {
+        ProviderKind::File => file_stem(&path),
+        _ => match &uri.agent_id {
+            Some(agent_id) => format!("{}-{}-{agent_id}", uri.provider, uri.session_id),
+            None => format!("{}-{}", uri.provider, uri.session_id),
+        },
+    };
+    let dir = create_snapshot_dir(&out_dir, &name)?;
+
+    // A resumed Codex session is written as one rollout so the snapshot URI
+    // reads all of it.
+    let mut files = if rollouts.len() > 1 {
+        vec![merge_rollouts(&rollouts, &path, &dir)?]
+    } else {
+        copy_thread_files(&path, &dir)?
+    };
+    let thread_path = files[0].clone();
+    if uri.agent_id.is_none() && uri.provider.capabilities().supports_subagents {
+        let (subagent_files, subagent_warnings) = copy_subagent_files(uri, roots, &dir)?;
+        files.extend(subagent_files);
+        warnings.extend(subagent_warnings);
+    }
+
+    let format = uri.file_format.unwrap_or(uri.provider);
+    let snapshot_uri = ThreadUri {
+        provider: ProviderKind::File,
+        session_id: thread_path.to_string_lossy().into_owned(),
+        agent_id: None,
+        fragment: None,
+        file_format: Some(format),
+        gemini_project: None,
+    };
+
+    Ok(SnapshotResult {
+        uri: snapshot_uri.as_string(),
+        dir,
+        files,
+        warnings,
+    })
+}
+
+/// Creates `<out_dir>/<name>-<timestamp>`, adding `-2`, `-3`, ... when a
+/// snapshot of the same thread was taken within the same second.
+fn create_snapshot_dir(out_dir: &Path, name: &str) -> Result<PathBuf> {
+    fs::create_dir_all(out_dir).map_err(|source| XurlError::io(out_dir, source))?;
+    let base = format!("{name}-{}", Utc::now().format("%Y%m%dT%H%M%SZ"));
+
+    let mut dir = out_dir.join(&base);
+    let mut attempt = 1;
+    loop {
+        match fs::create_dir(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                attempt += 1;
+                dir = out_dir.join(format!("{base}-{attempt}"));
+            }
+            Err(source) => return Err(XurlError::io(&dir, source)),
+        }
+    }
+}
+
+/// Copies a thread file into `dir`, with the message shards of an Amp
+/// directory thread next to it. The thread file comes first.
+fn copy_thread_files(path: &Path, dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut copied = Vec::new();
+    for source in std::iter::once(path.to_path_buf()).chain(amp::message_shards(path)) {
+        let Some(file_name) = source.file_name() else {
+            continue;
+        };
+        let target = dir.join(file_name);
+        fs::copy(&source, &target).map_err(|err| XurlError::io(&source, err))?;
+        mark_read_only(&target)?;
+        copied.push(target);
+    }
+    Ok(copied)
+}
+
+/// Writes `rollouts`, oldest first, into `dir` as one decompressed rollout
+/// named after `newest`.
+fn merge_rollouts(rollouts: &[PathBuf], newest: &Path, dir: &Path) -> Result<PathBuf> {
+    let mut merged = Vec::new();
+    for rollout in rollouts {
+        let bytes = read_thread_file(rollout).map_err(|source| XurlError::io(rollout, source))?;
+        if !merged.is_empty() && !merged.ends_with(b"\n") {
+            merged.push(b'\n');
+        }
+        merged.extend_from_slice(&bytes);
+    }
+
+    let file_name = newest
+        .file_name()
+        .map_or_else(|| "rollout.jsonl".into(), |name| name.to_string_lossy());
+    let target = dir.join(strip_compression(&file_name));
+    fs::write(&target, merged).map_err(|source| XurlError::io(&target, source))?;
+    mark_read_only(&target)?;
+    Ok(target)
+}
+
+fn copy_subagent_files(
+    uri: &ThreadUri,
+    roots: &ProviderRoots,
+    dir: &Path,
+) -> Result<(Vec<PathBuf>, Vec<Warning>)> {
+    let SubagentView::List(list) =
+        resolve_subagent_view(uri, roots, &ReadOptions::default(), true)?
+    else {
+        return Ok((Vec::new(), Vec::new()));
+    };
+
+    let mut agents = Vec::new();
+    flatten_agents(&list.agents, &mut agents);
+
+    let mut seen = HashSet::new();
+    let mut files = Vec::new();
+    let mut warnings = list.warnings;
+    for agent in agents {
+        let Some(path) = agent
+            .child_thread
+            .as_ref()
+            .and_then(|thread| thread.path.as_deref())
+            .map(PathBuf::from)
+        else {
+            continue;
+        };
+        if !seen.insert(path.clone()) {
+            continue;
+        }
+        if !path.is_file() {
+            warnings.push(Warning::warn(
+                "snapshot_missing_file",
+                format!(
+                    "subagent {} thread file is gone: {}",
+                    agent.agent_id,
+                    path.display()
+                ),
+            ));
+            continue;
+        }
+
+        let agent_dir = dir.join(SUBAGENTS_DIR).join(&agent.agent_id);
+        fs::create_dir_all(&agent_dir).map_err(|source| XurlError::io(&agent_dir, source))?;
+        files.extend(copy_thread_files(&path, &agent_dir)?);
+    }
+
+    Ok((files, warnings))
+}
+
+/// Subagents and, for Amp handoff chains, the threads they handed off to.
+fn flatten_agents<'a>(agents: &'a [SubagentListItem], out: &mut Vec<&'a SubagentListItem>) {
+    for agent in agents {
+        out.push(agent);
+        flatten_agents(&agent.children, out);
+    }
+}
+
+fn mark_read_only(path: &Path) -> Result<()> {
+    let mut permissions = fs::metadata(path)
+        .map_err(|source| XurlError::io(path, source))?
+        .permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(path, permissions).map_err(|source| XurlError::io(path, source))
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem().map_or_else(
+        || "thread".to_string(),
+        |stem| stem.to_string_lossy().into_owned(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use crate::model::SnapshotRequest;
+    use crate::provider::{ProviderRoots, write_test_rollout};
+    use crate::service::{render_thread_markdown, resolve_thread};
+    use crate::snapshot::snapshot_thread;
+    use crate::uri::ThreadUri;
+
+    const SESSION_ID: &str = "019c871c-b1f9-7f60-9c4f-87ed09f13592";
+
+    #[test]
+    fn snapshots_are_read_only_copies_readable_by_file_uri() {
+        let temp = tempdir().expect("tempdir");
+        let roots = ProviderRoots::for_test(temp.path());
+        let rollout = write_test_rollout(
+            &roots.codex_root,
+            SESSION_ID,
+            r#"{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"hello"}]}}"#,
+        );
+        let uri = ThreadUri::parse(&format!("codex://{SESSION_ID}")).expect("uri");
+
+        let first = snapshot_thread(&uri, &roots, &SnapshotRequest::default()).expect("snapshot");
+        let second = snapshot_thread(&uri, &roots, &SnapshotRequest::default()).expect("snapshot");
+        assert_ne!(first.dir, second.dir);
+        assert!(first.dir.starts_with(temp.path().join("data/snapshots")));
+        assert!(
+            first
+                .dir
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(&format!("codex-{SESSION_ID}-")))
+        );
+        assert_eq!(first.files.len(), 1);
+        assert!(
+            fs::metadata(&first.files[0])
+                .expect("metadata")
+                .permissions()
+                .readonly()
+        );
+        assert!(first.uri.starts_with("file://"));
+        assert!(first.uri.ends_with("?format=codex"));
+
+        fs::write(&rollout, "changed").expect("write");
+        let snapshot = ThreadUri::parse(&first.uri).expect("file uri");
+        let resolved = resolve_thread(&snapshot, &roots).expect("resolve snapshot");
+        assert!(
+            fs::read_to_string(resolved.path)
+                .expect("read")
+                .contains("hello")
+        );
+    }
+
+    #[test]
+    fn snapshots_of_resumed_codex_sessions_hold_every_rollout() {
+        let temp = tempdir().expect("tempdir");
+        for (day, text) in [("23", "fix the parser"), ("24", "now the lexer")] {
+            let rollout = temp.path().join(format!(
+                "codex/sessions/2026/02/{day}/rollout-2026-02-{day}T04-48-50-{SESSION_ID}.jsonl"
+            ));
+            fs::create_dir_all(rollout.parent().expect("parent")).expect("mkdir");
+            fs::write(
+                &rollout,
+                format!(
+                    r#"{{"type":"response_item","payload":{{"type":"message","role":"user","content":[{{"type":"input_text","text":"{text}"}}]}}}}"#
+                ),
+            )
+            .expect("write");
+        }
+        let roots = ProviderRoots::for_test(temp.path());
+        let uri = ThreadUri::parse(&format!("codex://{SESSION_ID}")).expect("uri");
+
+        let snapshot =
+            snapshot_thread(&uri, &roots, &SnapshotRequest::default()).expect("snapshot");
+        assert_eq!(snapshot.files.len(), 1);
+        assert!(
+            snapshot
+                .uri
+                .contains(&format!("rollout-2026-02-24T04-48-50-{SESSION_ID}"))
+        );
+
+        let snapshot = ThreadUri::parse(&snapshot.uri).expect("file uri");
+        let resolved = resolve_thread(&snapshot, &roots).expect("resolve snapshot");
+        let markdown = render_thread_markdown(&snapshot, &resolved).expect("render");
+        assert!(markdown.find("fix the parser") < markdown.find("now the lexer"));
+        assert!(markdown.contains("fix the parser"));
+    }
+}
@@ -0,0 +1,532 @@
+//! Subagent status inference.
+//!
+//! Providers rarely record a subagent's status directly, so xurl derives it
+//! from whatever the transcripts do record. Each provider's heuristic
+//! implements [`ProviderStatusInference`]; transcript-shaped providers share
+//! the [`TranscriptSignals`] ladder.
+
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::jsonl;
+use crate::model::{MessageRole, ProviderKind};
+use crate::render::{self, ExtractionPolicy};
+
+pub const STATUS_PENDING_INIT: &str = "pendingInit";
+pub const STATUS_RUNNING: &str = "running";
+pub const STATUS_COMPLETED: &str = "completed";
+pub const STATUS_ERRORED: &str = "errored";
+pub const STATUS_SHUTDOWN: &str = "shutdown";
+pub const STATUS_NOT_FOUND: &str = "notFound";
+
+/// Every status value xurl reports, as providers spell them.
+pub const KNOWN_STATUSES: &[&str] = &[
+    STATUS_PENDING_INIT,
+    STATUS_RUNNING,
+    STATUS_COMPLETED,
+    STATUS_ERRORED,
+    STATUS_SHUTDOWN,
+    STATUS_NOT_FOUND,
+];
+
+/// Statuses a subagent does not leave again.
+pub const TERMINAL_STATUSES: &[&str] = &[STATUS_COMPLETED, STATUS_ERRORED, STATUS_SHUTDOWN];
+
+/// Statuses from most to least in need of attention: failures, then agents
+/// that never finished, then finished ones.
+const STATUS_SEVERITY: &[&str] = &[
+    STATUS_ERRORED,
+    STATUS_NOT_FOUND,
+    STATUS_SHUTDOWN,
+    STATUS_PENDING_INIT,
+    STATUS_RUNNING,
+    STATUS_COMPLETED,
+];
+
+/// The status most in need of attention, e.g. `errored` over `running` over
+/// `completed`. Statuses xurl does not know rank with `notFound`.
+pub fn worst_status<'a>(statuses: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    statuses.into_iter().min_by_key(|status| {
+        STATUS_SEVERITY
+            .iter()
+            .position(|known| known == status)
+            .unwrap_or(1)
+    })
+}
+
+/// Status came from lifecycle events in the parent rollout.
+pub const SOURCE_PARENT_ROLLOUT: &str = "parent_rollout";
+/// Status came from the child's own rollout or transcript.
+pub const SOURCE_CHILD_ROLLOUT: &str = "child_rollout";
+/// Status was recorded explicitly on the child thread.
+pub const SOURCE_CHILD_THREAD: &str = "child_thread";
+/// Status was guessed from indirect evidence.
+pub const SOURCE_INFERRED: &str = "inferred";
+
+/// A status together with where it came from (`status_source` in output).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InferredStatus {
+    pub status: String,
+    pub source: String,
+}
+
+impl InferredStatus {
+    pub fn new(status: impl Into<String>, source: impl Into<String>) -> Self {
+        Self {
+            status: status.into(),
+            source: source.into(),
+        }
+    }
+
+    /// Fallback when no evidence about the subagent was found.
+    pub fn not_found() -> Self {
+        Self::new(STATUS_NOT_FOUND, SOURCE_INFERRED)
+    }
+}
+
+/// Derives a subagent status from one provider's evidence.
+///
+/// Returns `None` when the input says nothing about the status, so callers
+/// can fall back to other evidence.
+///
+/// ```
+/// use xurl_core::status::{
+///     InferredStatus, ProviderStatusInference, SOURCE_CHILD_ROLLOUT, TranscriptSignals,
+/// };
+///
+/// struct LineCount;
+///
+/// impl ProviderStatusInference for LineCount {
+///     type Input = str;
+///
+///     fn infer_status(&self, raw: &str) -> Option<InferredStatus> {
+///         let signals = TranscriptSignals {
+///             has_user: raw.lines().next().is_some(),
+///             has_assistant: raw.lines().nth(1).is_some(),
+///             has_error: false,
+///             mid_turn: false,
+///         };
+///         Some(InferredStatus::new(signals.status(), SOURCE_CHILD_ROLLOUT))
+///     }
+/// }
+///
+/// let inferred = LineCount.infer_status("prompt").unwrap();
+/// assert_eq!(inferred.status, "running");
+/// ```
+pub trait ProviderStatusInference {
+    type Input: ?Sized;
+
+    fn infer_status(&self, input: &Self::Input) -> Option<InferredStatus>;
+}
+
+/// What a child transcript shows about its own progress.
+///
+/// The ladder is: any error means `errored`, an assistant reply that ends
+/// the transcript's turn means `completed`, a prompt or an unfinished turn
+/// means `running`, and an empty transcript means `pendingInit`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TranscriptSignals {
+    pub has_user: bool,
+    pub has_assistant: bool,
+    pub has_error: bool,
+    /// The transcript ends on a prompt, a tool call, or a tool result that
+    /// the agent has not answered yet.
+    pub mid_turn: bool,
+}
+
+impl TranscriptSignals {
+    pub fn status(&self) -> &'static str {
+        if self.has_error {
+            STATUS_ERRORED
+        } else if self.has_assistant && !self.mid_turn {
+            STATUS_COMPLETED
+        } else if self.has_user || self.has_assistant {
+            STATUS_RUNNING
+        } else {
+            STATUS_PENDING_INIT
+        }
+    }
+}
+
+impl ProviderStatusInference for TranscriptSignals {
+    type Input = ();
+
+    fn infer_status(&self, _input: &()) -> Option<InferredStatus> {
+        Some(InferredStatus::new(self.status(), SOURCE_CHILD_ROLLOUT))
+    }
+}
+
+/// Amp child threads: the thread's own `status`/`state` field wins, otherwise
+/// the transcript ladder over the given message signals.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AmpStatusInference {
+    pub signals: TranscriptSignals,
+}
+
+impl ProviderStatusInference for AmpStatusInference {
+    type Input = Value;
+
+    fn infer_status(&self, thread: &Value) -> Option<InferredStatus> {
+        if let Some(status) = explicit_amp_status(thread) {
+            return Some(InferredStatus::new(status, SOURCE_CHILD_THREAD));
+        }
+        Some(InferredStatus::new(self.signals.status(), SOURCE_INFERRED))
+    }
+}
+
+fn explicit_amp_status(thread: &Value) -> Option<String> {
+    if let Some(status) = thread.get("status") {
+        if let Some(status) = status.as_str() {
+            return Some(status.to_string());
+        }
+        if let Some(object) = status.as_object()
+            && let Some(key) = KNOWN_STATUSES.iter().find(|key| object.contains_key(**key))
+        {
+            return Some((*key).to_string());
+        }
+    }
+
+    thread
+        .get("state")
+        .and_then(Value::as_str)
+        .map(ToString::to_string)
+}
+
+/// Gemini chat files: the transcript ladder over the chat's `messages`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GeminiStatusInference;
+
+impl ProviderStatusInference for GeminiStatusInference {
+    type Input = Value;
+
+    fn infer_status(&self, chat: &Value) -> Option<InferredStatus> {
+        let mut signals = TranscriptSignals::default();
+
+        for message in chat
+            .get("messages")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let message_type = message
+                .get("type")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            if message_type == "error" || !message.get("error").is_none_or(Value::is_null) {
+                signals.has_error = true;
+            }
+            if message_type == "gemini" || message_type == "assistant" {
+                signals.has_assistant = true;
+            }
+            if message_type == "user" {
+                signals.has_user = true;
+            }
+        }
+
+        Some(InferredStatus::new(signals.status(), SOURCE_CHILD_ROLLOUT))
+    }
+}
+
+/// Codex child rollouts: an aborted turn means `errored` and an assistant
+/// reply means `completed`. Anything else is left to the parent's lifecycle.
+#[derive(Debug, Clone, Copy)]
+pub struct CodexChildStatusInference<'a> {
+    /// Rollout path, used for parsing diagnostics.
+    pub path: &'a Path,
+}
+
+impl ProviderStatusInference for CodexChildStatusInference<'_> {
+    type Input = str;
+
+    fn infer_status(&self, raw: &str) -> Option<InferredStatus> {
+        let mut signals = TranscriptSignals::default();
+
+        for (line_idx, line) in raw.lines().enumerate() {
+            let Ok(Some(value)) = jsonl::parse_json_line(self.path, line_idx + 1, line) else {
+                continue;
+            };
+
+            if value.get("type").and_then(Value::as_str) == Some("event_msg")
+                && value
+                    .get("payload")
+                    .and_then(|payload| payload.get("type"))
+                    .and_then(Value::as_str)
+                    == Some("turn_aborted")
+            {
+                signals.has_error = true;
+            }
+
+            if render::extract_messages(
+                ProviderKind::Codex,
+                self.path,
+                line,
+                &mut ExtractionPolicy::default(),
+            )
+            .ok()
+            .is_some_and(|messages| {
+                messages
+                    .iter()
+                    .any(|message| message.role == MessageRole::Assistant)
+            }) {
+                signals.has_assistant = true;
+            }
+        }
+
+        match signals.status() {
+            status @ (STATUS_ERRORED | STATUS_COMPLETED) => {
+                Some(InferredStatus::new(status, SOURCE_CHILD_ROLLOUT))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// What a Codex parent rollout recorded about one subagent.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ParentLifecycle {
+    /// States reported by `wait`/`close_agent` results, in order.
+    pub states: Vec<String>,
+    /// A `spawn_agent` call returned this agent.
+    pub has_spawn: bool,
+    /// Any lifecycle call (spawn, wait, input, close) referenced this agent.
+    pub has_activity: bool,
+}
+
+impl ParentLifecycle {
+    fn saw(&self, status: &str) -> bool {
+        self.states.iter().any(|state| state == status)
+    }
+}
+
+/// Codex subagents, from the parent's lifecycle plus child-rollout evidence.
+///
+/// Terminal states reported by the parent win in the order `errored`,
+/// `shutdown`, `completed`; then `running` on any activity, `pendingInit`
+/// after a bare spawn. Without parent evidence, an existing child rollout
+/// counts as `running`; `child_status` is the last resort before `notFound`.
+#[derive(Debug, Default, Clone)]
+pub struct CodexParentStatusInference {
+    pub child_exists: bool,
+    pub child_status: Option<String>,
+}
+
+impl ProviderStatusInference for CodexParentStatusInference {
+    type Input = ParentLifecycle;
+
+    fn infer_status(&self, lifecycle: &ParentLifecycle) -> Option<InferredStatus> {
+        for status in [STATUS_ERRORED, STATUS_SHUTDOWN, STATUS_COMPLETED] {
+            if lifecycle.saw(status) {
+                return Some(InferredStatus::new(status, SOURCE_PARENT_ROLLOUT));
+            }
+        }
+        if lifecycle.saw(STATUS_RUNNING) || lifecycle.has_activity {
+            return Some(InferredStatus::new(STATUS_RUNNING, SOURCE_PARENT_ROLLOUT));
+        }
+        if lifecycle.has_spawn {
+            return Some(InferredStatus::new(
+                STATUS_PENDING_INIT,
+                SOURCE_PARENT_ROLLOUT,
+            ));
+        }
+        if self.child_exists {
+            return Some(InferredStatus::new(STATUS_RUNNING, SOURCE_CHILD_ROLLOUT));
+        }
+        if let Some(child_status) = &self.child_status {
+            return Some(InferredStatus::new(child_status, SOURCE_CHILD_ROLLOUT));
+        }
+
+        Some(InferredStatus::not_found())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use serde_json::json;
+
+    use crate::status::{
+        AmpStatusInference, CodexChildStatusInference, CodexParentStatusInference,
+        GeminiStatusInference, InferredStatus, ParentLifecycle, ProviderStatusInference,
+        TranscriptSignals, worst_status,
+    };
+
+    fn signals(has_user: bool, has_assistant: bool, has_error: bool) -> TranscriptSignals {
+        TranscriptSignals {
+            has_user,
+            has_assistant,
+            has_error,
+            mid_turn: false,
+        }
+    }
+
+    fn lifecycle(states: &[&str], has_spawn: bool, has_activity: bool) -> ParentLifecycle {
+        ParentLifecycle {
+            states: states.iter().map(ToString::to_string).collect(),
+            has_spawn,
+            has_activity,
+        }
+    }
+
+    #[test]
+    fn worst_status_ranks_failures_first() {
+        assert_eq!(
+            worst_status(["completed", "running", "pendingInit"]),
+            Some("pendingInit")
+        );
+        assert_eq!(
+            worst_status(["completed", "errored", "notFound"]),
+            Some("errored")
+        );
+        assert_eq!(worst_status(["completed", "cancelled"]), Some("cancelled"));
+        assert_eq!(worst_status([]), None);
+    }
+
+    #[test]
+    fn transcript_ladder_prefers_error_then_reply_then_prompt() {
+        assert_eq!(signals(false, false, false).status(), "pendingInit");
+        assert_eq!(signals(true, false, false).status(), "running");
+        assert_eq!(signals(true, true, false).status(), "completed");
+        assert_eq!(signals(true, true, true).status(), "errored");
+        assert_eq!(signals(false, false, true).status(), "errored");
+        let calling_tool = TranscriptSignals {
+            mid_turn: true,
+            ..signals(true, true, false)
+        };
+        assert_eq!(calling_tool.status(), "running");
+    }
+
+    #[test]
+    fn parent_lifecycle_walks_spawn_wait_close() {
+        let child = CodexParentStatusInference::default();
+        let steps = [
+            (lifecycle(&[], false, false), "notFound", "inferred"),
+            (lifecycle(&[], true, false), "pendingInit", "parent_rollout"),
+            (lifecycle(&[], true, true), "running", "parent_rollout"),
+            (
+                lifecycle(&["running"], true, true),
+                "running",
+                "parent_rollout",
+            ),
+            (
+                lifecycle(&["running", "completed"], true, true),
+                "completed",
+                "parent_rollout",
+            ),
+            (
+                lifecycle(&["completed", "shutdown"], true, true),
+                "shutdown",
+                "parent_rollout",
+            ),
+            (
+                lifecycle(&["errored", "shutdown"], true, true),
+                "errored",
+                "parent_rollout",
+            ),
+        ];
+
+        for (lifecycle, status, source) in steps {
+            assert_eq!(
+                child.infer_status(&lifecycle),
+                Some(InferredStatus::new(status, source)),
+                "{lifecycle:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn parent_evidence_outranks_child_rollout() {
+        let with_child = CodexParentStatusInference {
+            child_exists: true,
+            child_status: Some("completed".to_string()),
+        };
+        assert_eq!(
+            with_child.infer_status(&lifecycle(&[], true, false)),
+            Some(InferredStatus::new("pendingInit", "parent_rollout"))
+        );
+        assert_eq!(
+            with_child.infer_status(&ParentLifecycle::default()),
+            Some(InferredStatus::new("running", "child_rollout"))
+        );
+
+        let status_only = CodexParentStatusInference {
+            child_exists: false,
+            child_status: Some("completed".to_string()),
+        };
+        assert_eq!(
+            status_only.infer_status(&ParentLifecycle::default()),
+            Some(InferredStatus::new("completed", "child_rollout"))
+        );
+    }
+
+    #[test]
+    fn codex_child_reports_only_terminal_states() {
+        let inference = CodexChildStatusInference {
+            path: Path::new("rollout.jsonl"),
+        };
+        let user = r#"{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"go"}]}}"#;
+        let assistant = r#"{"type":"response_item","payload":{"type":"message","role":"assistant","content":[{"type":"output_text","text":"done"}]}}"#;
+        let aborted = r#"{"type":"event_msg","payload":{"type":"turn_aborted"}}"#;
+
+        assert_eq!(inference.infer_status(user), None);
+        assert_eq!(
+            inference.infer_status(&format!("{user}\n{assistant}")),
+            Some(InferredStatus::new("completed", "child_rollout"))
+        );
+        assert_eq!(
+            inference.infer_status(&format!("{user}\n{assistant}\n{aborted}")),
+            Some(InferredStatus::new("errored", "child_rollout"))
+        );
+    }
+
+    #[test]
+    fn amp_explicit_status_wins_over_messages() {
+        let inference = AmpStatusInference {
+            signals: signals(true, true, false),
+        };
+
+        assert_eq!(
+            inference.infer_status(&json!({"status": "running"})),
+            Some(InferredStatus::new("running", "child_thread"))
+        );
+        assert_eq!(
+            inference.infer_status(&json!({"status": {"errored": {}}})),
+            Some(InferredStatus::new("errored", "child_thread"))
+        );
+        assert_eq!(
+            inference.infer_status(&json!({"state": "shutdown"})),
+            Some(InferredStatus::new("shutdown", "child_thread"))
+        );
+        assert_eq!(
+            inference.infer_status(&json!({"messages": []})),
+            Some(InferredStatus::new("completed", "inferred"))
+        );
+    }
+
+    #[test]
+    fn gemini_reads_message_types() {
+        let cases = [
+            (json!({}), "pendingInit"),
+            (json!({"messages": [{"type": "user"}]}), "running"),
+            (
+                json!({"messages": [{"type": "user"}, {"type": "gemini"}]}),
+                "completed",
+            ),
+            (
+                json!({"messages": [{"type": "gemini", "error": "quota"}]}),
+                "errored",
+            ),
+        ];
+
+        for (chat, status) in cases {
+            assert_eq!(
+                GeminiStatusInference
+                    .infer_status(&chat)
+                    .map(|inferred| inferred.status),
+                Some(status.to_string()),
+                "{chat}"
+            );
+        }
+    }
+}
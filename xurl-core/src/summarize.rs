@@ -0,0 +1,314 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::binary;
+use crate::error::{Result, XurlError};
+use crate::head::insert_warnings;
+use crate::model::{
+    MessageRole, ProviderKind, ThreadMessage, ThreadSummary, Warning, WriteRequest,
+};
+use crate::provider::ProviderRoots;
+use crate::render::{self, ReadOptions};
+use crate::service::{TIMELINE_LINES, read_thread_lines, resolve_thread, write_thread};
+use crate::sink::BufferedSink;
+use crate::uri::ThreadUri;
+use crate::yaml::YamlMapping;
+
+const SUMMARY_INSTRUCTIONS: &str = "Summarize the following agent conversation.
+Reply in Markdown with exactly these three sections, each a bullet list:
+
+## Goals
+## Decisions
+## Open TODOs
+
+Write `- none` under a section when nothing applies.";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Summarizer {
+    /// Shell command that receives the prompt on stdin and prints the summary.
+    Command(String),
+    /// Start a new conversation with a provider's write mode.
+    Provider(ProviderKind),
+}
+
+impl Summarizer {
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Command(command) => format!("command:{command}"),
+            Self::Provider(provider) => format!("provider:{provider}"),
+        }
+    }
+}
+
+pub fn summarize_thread(
+    uri: &ThreadUri,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+    summarizer: &Summarizer,
+) -> Result<ThreadSummary> {
+    let resolved = resolve_thread(uri, roots)?;
+    let raw = read_thread_lines(&resolved, roots, options, TIMELINE_LINES)?;
+    let messages = render::extract_messages(
+        uri.format(),
+        &resolved.path,
+        &raw,
+        &mut options.extraction_policy(),
+    )?;
+    let prompt = build_summary_prompt(uri, &messages);
+
+    let response = match summarizer {
+        Summarizer::Command(command) => run_shell_command(command, &prompt)?,
+        Summarizer::Provider(provider) => {
+            let mut sink = BufferedSink::new();
+            write_thread(
+                *provider,
+                roots,
+                &WriteRequest {
+                    prompt,
+                    session_id: None,
+                    parent_entry_id: None,
+                    spawn_agent: false,
+                    gemini_project: None,
+                },
+                &mut sink,
+            )?;
+            sink.into_text()
+        }
+    };
+
+    let mut summary = parse_summary(&response);
+    summary.uri = uri.as_agents_string();
+    summary.thread_source = resolved.path.display().to_string();
+    summary.summarizer = summarizer.describe();
+    summary.warnings = resolved.metadata.warnings;
+    if summary.goals.is_empty() && summary.decisions.is_empty() && summary.todos.is_empty() {
+        summary.warnings.push(Warning::warn(
+            "summary_unstructured",
+            "summarizer output did not contain Goals/Decisions/Open TODOs sections".to_string(),
+        ));
+    }
+
+    Ok(summary)
+}
+
+fn build_summary_prompt(uri: &ThreadUri, messages: &[ThreadMessage]) -> String {
+    let mut prompt = String::new();
+    prompt.push_str(SUMMARY_INSTRUCTIONS);
+    prompt.push_str(&format!("\n\nConversation: {}\n\n", uri.as_agents_string()));
+
+    for message in messages {
+        let title = match message.role {
+            MessageRole::User => "User",
+            MessageRole::Assistant => "Assistant",
+        };
+        prompt.push_str(&format!("### {title}\n\n{}\n\n", message.text.trim()));
+    }
+
+    prompt
+}
+
+/// Runs `command` through the shell with `input` on stdin and returns what
+/// it printed. Summarizer and embedding commands both go through here.
+pub(crate) fn run_shell_command(command: &str, input: &str) -> Result<String> {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+
+    tracing::debug!(shell, command, "running shell command");
+    let mut child = Command::new(shell)
+        .args([flag, command])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|source| {
+            if source.kind() == std::io::ErrorKind::NotFound {
+                XurlError::CommandNotFound {
+                    command: shell.to_string(),
+                    lookup: binary::describe_search(Path::new(shell)),
+                }
+            } else {
+                XurlError::Io {
+                    path: PathBuf::from(shell),
+                    source,
+                }
+            }
+        })?;
+
+    // Input is fed from another thread: a command that answers line by line
+    // would otherwise block on a full stdout pipe while xurl is still writing.
+    // It may also exit without draining stdin; a broken pipe is then reported
+    // through its exit status instead.
+    let feeder = child.stdin.take().map(|mut stdin| {
+        let input = input.to_string();
+        std::thread::spawn(move || {
+            let _ = stdin.write_all(input.as_bytes());
+        })
+    });
+
+    let output = child.wait_with_output().map_err(|source| XurlError::Io {
+        path: PathBuf::from(shell),
+        source,
+    })?;
+    if let Some(feeder) = feeder {
+        let _ = feeder.join();
+    }
+
+    if !output.status.success() {
+        return Err(XurlError::CommandFailed {
+            command: command.to_string(),
+            code: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[derive(Debug, Clone, Copy)]
+enum SummarySection {
+    Goals,
+    Decisions,
+    Todos,
+}
+
+fn parse_section_heading(line: &str) -> Option<SummarySection> {
+    let heading = line
+        .trim_start_matches('#')
+        .trim()
+        .trim_end_matches(':')
+        .to_ascii_lowercase();
+    if !line.starts_with('#') && !line.ends_with(':') {
+        return None;
+    }
+
+    match heading.as_str() {
+        "goals" | "goal" => Some(SummarySection::Goals),
+        "decisions" | "decision" => Some(SummarySection::Decisions),
+        "open todos" | "todos" | "open todo" | "todo" => Some(SummarySection::Todos),
+        _ => None,
+    }
+}
+
+fn parse_summary(text: &str) -> ThreadSummary {
+    let mut summary = ThreadSummary::default();
+    let mut current = None::<SummarySection>;
+
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(section) = parse_section_heading(line) {
+            current = Some(section);
+            continue;
+        }
+        if line.starts_with('#') {
+            current = None;
+            continue;
+        }
+
+        let Some(section) = current else {
+            continue;
+        };
+        let item = line
+            .strip_prefix("- ")
+            .or_else(|| line.strip_prefix("* "))
+            .unwrap_or(line)
+            .trim();
+        if item.is_empty() || item.eq_ignore_ascii_case("none") {
+            continue;
+        }
+
+        let items = match section {
+            SummarySection::Goals => &mut summary.goals,
+            SummarySection::Decisions => &mut summary.decisions,
+            SummarySection::Todos => &mut summary.todos,
+        };
+        items.push(item.to_string());
+    }
+
+    summary
+}
+
+pub fn render_summary_markdown(summary: &ThreadSummary) -> String {
+    let mut frontmatter = YamlMapping::new();
+    frontmatter.insert("uri", &summary.uri);
+    frontmatter.insert("thread_source", &summary.thread_source);
+    frontmatter.insert("summarizer", &summary.summarizer);
+    insert_warnings(&mut frontmatter, &summary.warnings);
+
+    let mut output = frontmatter.to_frontmatter();
+    output.push('\n');
+    output.push_str("# Thread Summary\n\n");
+
+    for (title, items) in [
+        ("Goals", &summary.goals),
+        ("Decisions", &summary.decisions),
+        ("Open TODOs", &summary.todos),
+    ] {
+        output.push_str(&format!("## {title}\n\n"));
+        if items.is_empty() {
+            output.push_str("_None identified._\n\n");
+            continue;
+        }
+        for item in items {
+            output.push_str(&format!("- {item}\n"));
+        }
+        output.push('\n');
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{MessageRole, ThreadMessage};
+    use crate::summarize::{build_summary_prompt, parse_summary, run_shell_command};
+    use crate::uri::ThreadUri;
+
+    #[test]
+    fn parse_summary_collects_sections() {
+        let text = "Here you go.\n\n## Goals\n- ship the parser\n* add tests\n\n## Decisions\n- none\n\nOpen TODOs:\n- update docs\n\n## Notes\n- ignored";
+        let summary = parse_summary(text);
+        assert_eq!(summary.goals, vec!["ship the parser", "add tests"]);
+        assert!(summary.decisions.is_empty());
+        assert_eq!(summary.todos, vec!["update docs"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn command_that_streams_while_reading_gets_the_whole_prompt() {
+        // Larger than a pipe buffer, so writing it all before reading stdout
+        // would deadlock with `cat`.
+        let prompt = "x".repeat(1 << 20);
+        let output = run_shell_command("cat", &prompt).expect("run cat");
+        assert_eq!(output.len(), prompt.len());
+    }
+
+    #[test]
+    fn prompt_contains_instructions_and_transcript() {
+        let uri =
+            ThreadUri::parse("codex://019c871c-b1f9-7f60-9c4f-87ed09f13592").expect("parse uri");
+        let prompt = build_summary_prompt(
+            &uri,
+            &[
+                ThreadMessage {
+                    role: MessageRole::User,
+                    text: "hello".to_string(),
+                    timestamp: None,
+                },
+                ThreadMessage {
+                    role: MessageRole::Assistant,
+                    text: "world".to_string(),
+                    timestamp: None,
+                },
+            ],
+        );
+        assert!(prompt.contains("## Open TODOs"));
+        assert!(prompt.contains("### User\n\nhello"));
+        assert!(prompt.contains("### Assistant\n\nworld"));
+    }
+}
@@ -0,0 +1,186 @@
+//! Thread tags (`xurl tag`, `tag://<name>`).
+//!
+//! Tags are short names for thread URIs, kept in `tags.toml` under xurl's
+//! data directory as `name = "agents://..."` lines.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::atomic::write_atomic;
+use crate::error::{Result, XurlError};
+use crate::provider::ProviderRoots;
+use crate::service::resolve_thread;
+use crate::uri::ThreadUri;
+
+const TAGS_FILE: &str = "tags.toml";
+const TAGS_HEADER: &str = "# Thread tags written by `xurl tag`; read them with tag://<name>.\n";
+
+/// Records `name` for the thread of `uri`, replacing the thread it named
+/// before, which is returned. The thread must resolve, so a tag never starts
+/// out pointing nowhere.
+pub fn tag_thread(name: &str, uri: &ThreadUri, roots: &ProviderRoots) -> Result<Option<String>> {
+    validate_tag_name(name)?;
+    if uri.fragment.is_some() {
+        return Err(XurlError::InvalidMode(
+            "tags name whole threads; pass the URI without a #msg fragment".to_string(),
+        ));
+    }
+    resolve_thread(
+        &ThreadUri {
+            agent_id: None,
+            ..uri.clone()
+        },
+        roots,
+    )?;
+
+    let path = store_path(roots);
+    let mut tags = read_store(&path)?;
+    let previous = tags.insert(name.to_string(), uri.as_agents_string());
+    write_store(&path, &tags)?;
+    Ok(previous.filter(|previous| *previous != uri.as_agents_string()))
+}
+
+/// The thread URI recorded for `name`.
+pub fn resolve_tag(name: &str, roots: &ProviderRoots) -> Result<String> {
+    validate_tag_name(name)?;
+    let path = store_path(roots);
+    read_store(&path)?
+        .remove(name)
+        .ok_or_else(|| XurlError::TagNotFound {
+            name: name.to_string(),
+            path,
+        })
+}
+
+/// Replaces the tag name that starts the target of a `tag://` URI with the
+/// thread URI it names, keeping whatever follows it: `tag://<name>/<child>`,
+/// `tag://<name>#msg-3`, and `tag://<name>?page=2` all work.
+pub fn expand_tag_uri(target: &str, roots: &ProviderRoots) -> Result<String> {
+    let (name, rest) = target.split_at(target.find(['/', '#', '?']).unwrap_or(target.len()));
+    if name.is_empty() {
+        return Err(XurlError::InvalidUri(format!("tag://{target}")));
+    }
+    let uri = resolve_tag(name, roots)?;
+    tracing::debug!(name, uri, "expanded tag");
+    Ok(format!("{uri}{rest}"))
+}
+
+/// Tag names become TOML keys and the host part of `tag://` URIs, so they
+/// are kept to letters, digits, `-`, and `_`.
+fn validate_tag_name(name: &str) -> Result<()> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_'))
+    {
+        return Err(XurlError::InvalidMode(format!(
+            "invalid tag name {name:?}; use letters, digits, `-`, and `_`"
+        )));
+    }
+    Ok(())
+}
+
+fn store_path(roots: &ProviderRoots) -> PathBuf {
+    roots.data_root.join(TAGS_FILE)
+}
+
+fn read_store(path: &Path) -> Result<BTreeMap<String, String>> {
+    let raw = match fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(source) => {
+            return Err(XurlError::Io {
+                path: path.to_path_buf(),
+                source,
+            });
+        }
+    };
+
+    toml::from_str(&raw).map_err(|err| {
+        XurlError::Serialization(format!("invalid tags in {}: {err}", path.display()))
+    })
+}
+
+fn write_store(path: &Path, tags: &BTreeMap<String, String>) -> Result<()> {
+    let parent = path.parent().unwrap_or(path);
+    fs::create_dir_all(parent).map_err(|source| XurlError::Io {
+        path: parent.to_path_buf(),
+        source,
+    })?;
+    let encoded = toml::to_string(tags).map_err(|err| XurlError::Serialization(err.to_string()))?;
+
+    // An interrupted write must never truncate the registry.
+    write_atomic(path, format!("{TAGS_HEADER}{encoded}").as_bytes(), false)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use crate::error::XurlError;
+    use crate::provider::{ProviderRoots, write_test_rollout};
+    use crate::tags::{expand_tag_uri, resolve_tag, tag_thread};
+    use crate::uri::ThreadUri;
+
+    const SESSION_ID: &str = "019c871c-b1f9-7f60-9c4f-87ed09f13592";
+    const OTHER_SESSION_ID: &str = "019c871c-b1f9-7f60-9c4f-87ed09f13593";
+
+    const ROLLOUT: &str = r#"{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"hello"}]}}"#;
+
+    fn uri(session_id: &str) -> ThreadUri {
+        ThreadUri::parse(&format!("codex://{session_id}")).expect("uri")
+    }
+
+    #[test]
+    fn tags_resolve_and_expand_with_suffixes() {
+        let temp = tempdir().expect("tempdir");
+        let roots = ProviderRoots::for_test(temp.path());
+        write_test_rollout(&roots.codex_root, SESSION_ID, ROLLOUT);
+        write_test_rollout(&roots.codex_root, OTHER_SESSION_ID, ROLLOUT);
+
+        assert_eq!(
+            tag_thread("refactor-auth", &uri(SESSION_ID), &roots).expect("tag"),
+            None
+        );
+        assert_eq!(
+            resolve_tag("refactor-auth", &roots).expect("resolve"),
+            format!("agents://codex/{SESSION_ID}")
+        );
+        assert_eq!(
+            expand_tag_uri("refactor-auth#msg-2", &roots).expect("expand"),
+            format!("agents://codex/{SESSION_ID}#msg-2")
+        );
+
+        assert_eq!(
+            tag_thread("refactor-auth", &uri(OTHER_SESSION_ID), &roots).expect("retag"),
+            Some(format!("agents://codex/{SESSION_ID}"))
+        );
+        let stored = fs::read_to_string(temp.path().join("data/tags.toml")).expect("read");
+        assert!(stored.contains(&format!(
+            "refactor-auth = \"agents://codex/{OTHER_SESSION_ID}\""
+        )));
+    }
+
+    #[test]
+    fn rejects_unknown_tags_bad_names_and_missing_threads() {
+        let temp = tempdir().expect("tempdir");
+        let roots = ProviderRoots::for_test(temp.path());
+
+        assert!(matches!(
+            resolve_tag("nope", &roots),
+            Err(XurlError::TagNotFound { .. })
+        ));
+        assert!(matches!(
+            tag_thread("two words", &uri(SESSION_ID), &roots),
+            Err(XurlError::InvalidMode(_))
+        ));
+        assert!(matches!(
+            tag_thread("missing", &uri(SESSION_ID), &roots),
+            Err(XurlError::ThreadNotFound { .. })
+        ));
+        assert!(!temp.path().join("data/tags.toml").exists());
+    }
+}
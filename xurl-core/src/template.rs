@@ -0,0 +1,140 @@
+//! User-provided Tera templates for reads (`xurl --template`).
+//!
+//! A template sees the same document xurl prints as JSON: a thread's
+//! timeline as `--format json` writes it, with `kind` set to `thread`, or a
+//! subagent view as `--json` writes it, with `kind` set to `list` or
+//! `detail`. Teams can then produce their own reports, such as status
+//! dashboards or chat digests, from one stable shape.
+//!
+//! Templates whose file name ends in `.html`, `.htm`, or `.xml` escape
+//! values for HTML; others print them as they are.
+
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::{Value, json};
+use tera::{Context, Tera};
+
+use crate::error::{Result, XurlError};
+use crate::model::{ResolvedThread, SubagentView, TimelineFormat};
+use crate::provider::ProviderRoots;
+use crate::render::ReadOptions;
+use crate::service::render_thread;
+use crate::uri::ThreadUri;
+
+/// A parsed output template.
+#[derive(Debug, Clone)]
+pub struct OutputTemplate {
+    tera: Tera,
+    name: String,
+}
+
+impl OutputTemplate {
+    /// Parses `source`. `name` appears in error messages and picks HTML
+    /// escaping by its extension.
+    pub fn new(name: &str, source: &str) -> Result<Self> {
+        let mut tera = Tera::default();
+        tera.add_raw_template(name, source)
+            .map_err(|err| template_error(&err))?;
+        Ok(Self {
+            tera,
+            name: name.to_string(),
+        })
+    }
+
+    /// Reads and parses the template at `path`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = fs::read_to_string(path).map_err(|source| XurlError::io(path, source))?;
+        let name = path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        Self::new(&name, &source)
+    }
+
+    /// Renders the template with the fields of `context` as its variables.
+    pub fn render(&self, context: &impl Serialize) -> Result<String> {
+        let context = Context::from_serialize(context).map_err(|err| template_error(&err))?;
+        self.tera
+            .render(&self.name, &context)
+            .map_err(|err| template_error(&err))
+    }
+}
+
+/// Renders a thread's timeline through `template`. The page, fragment
+/// context, and entry kinds in `options` pick the entries, as for
+/// [`render_thread`].
+pub fn render_thread_template(
+    uri: &ThreadUri,
+    resolved: &ResolvedThread,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+    template: &OutputTemplate,
+) -> Result<String> {
+    let json = render_thread(uri, resolved, roots, options, TimelineFormat::Json)?;
+    let mut document = serde_json::from_str::<Value>(&json)
+        .map_err(|err| XurlError::Serialization(err.to_string()))?;
+    if let Value::Object(fields) = &mut document {
+        fields.insert("kind".to_string(), json!("thread"));
+    }
+    template.render(&document)
+}
+
+/// Renders a subagent index or detail view through `template`.
+pub fn render_subagent_view_template(
+    view: &SubagentView,
+    template: &OutputTemplate,
+) -> Result<String> {
+    template.render(view)
+}
+
+/// Tera reports the failing expression in the error's sources, so they are
+/// all kept.
+fn template_error(err: &tera::Error) -> XurlError {
+    let mut message = err.to_string();
+    let mut source = std::error::Error::source(err);
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    XurlError::Template(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::error::XurlError;
+    use crate::template::OutputTemplate;
+
+    #[test]
+    fn renders_fields_and_reports_template_errors() {
+        let view =
+            json!({"kind": "detail", "query": {"agent_id": "agent-1"}, "status": "completed"});
+        let template = OutputTemplate::new(
+            "digest.md",
+            "{{ kind }}: {{ query.agent_id }} is {{ status }}",
+        )
+        .expect("parse");
+        assert_eq!(
+            template.render(&view).expect("render"),
+            "detail: agent-1 is completed"
+        );
+
+        let html = OutputTemplate::new("digest.html", "{{ status }}").expect("parse");
+        assert_eq!(
+            html.render(&json!({"status": "<b>"})).expect("render"),
+            "&lt;b&gt;"
+        );
+
+        let missing = OutputTemplate::new("digest.md", "{{ no_such_field }}").expect("parse");
+        let err = missing.render(&view).expect_err("missing variable");
+        assert!(matches!(&err, XurlError::Template(message) if message.contains("no_such_field")));
+        assert!(matches!(
+            OutputTemplate::new("broken.md", "{% if %}"),
+            Err(XurlError::Template(_))
+        ));
+    }
+}
@@ -0,0 +1,193 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+
+use crate::error::{Result, XurlError};
+
+/// Parses the timestamp shapes found across provider data: RFC3339 strings,
+/// naive `YYYY-MM-DD[ T]HH:MM:SS` values (treated as UTC), bare dates, and
+/// epoch seconds or milliseconds.
+pub fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+
+    if let Ok(parsed) = DateTime::parse_from_rfc3339(value) {
+        return Some(parsed.with_timezone(&Utc));
+    }
+
+    for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"] {
+        if let Ok(parsed) = NaiveDateTime::parse_from_str(value, format) {
+            return Some(Utc.from_utc_datetime(&parsed));
+        }
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return date
+            .and_hms_opt(0, 0, 0)
+            .map(|parsed| Utc.from_utc_datetime(&parsed));
+    }
+
+    if value.bytes().all(|byte| byte.is_ascii_digit()) {
+        let number = value.parse::<i64>().ok()?;
+        // Thirteen or more digits only make sense as milliseconds.
+        return if value.len() >= 13 {
+            DateTime::from_timestamp_millis(number)
+        } else {
+            DateTime::from_timestamp(number, 0)
+        };
+    }
+
+    None
+}
+
+/// Formats a timestamp as RFC3339 in UTC, keeping sub-second precision only
+/// when the source had it.
+pub fn format_rfc3339(value: DateTime<Utc>) -> String {
+    value.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+/// Rewrites any recognized timestamp shape as RFC3339; unrecognized values
+/// are returned unchanged so no provider data is lost.
+pub fn normalize_timestamp(value: &str) -> String {
+    parse_timestamp(value).map_or_else(|| value.to_string(), format_rfc3339)
+}
+
+/// Parses an age like `30d`, `12h`, `90m`, or `2w` into a duration.
+pub fn parse_age(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let split = value
+        .find(|ch: char| !ch.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount = amount
+        .parse::<i64>()
+        .map_err(|_| XurlError::InvalidTimestamp(format!("invalid age: {value}")))?;
+
+    let duration = match unit {
+        "s" => Duration::try_seconds(amount),
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" | "" => Duration::try_days(amount),
+        "w" => Duration::try_weeks(amount),
+        _ => None,
+    };
+    duration.ok_or_else(|| {
+        XurlError::InvalidTimestamp(format!(
+            "invalid age: {value} (expected a number with s, m, h, d, or w)"
+        ))
+    })
+}
+
+/// Inclusive time window used to filter subagent indexes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeWindow {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl TimeWindow {
+    /// Builds a window from user input. A bare `--until` date covers that
+    /// whole day.
+    pub fn parse(since: Option<&str>, until: Option<&str>) -> Result<Self> {
+        let since = since.map(parse_bound).transpose()?;
+        let until = until
+            .map(|value| {
+                let bound = parse_bound(value)?;
+                if NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").is_ok() {
+                    return Ok(bound + Duration::days(1) - Duration::nanoseconds(1));
+                }
+                Ok(bound)
+            })
+            .transpose()?;
+
+        if let (Some(since), Some(until)) = (since, until)
+            && since > until
+        {
+            return Err(XurlError::InvalidTimestamp(format!(
+                "--since {} is later than --until {}",
+                since.to_rfc3339(),
+                until.to_rfc3339()
+            )));
+        }
+
+        Ok(Self { since, until })
+    }
+
+    pub fn is_unbounded(&self) -> bool {
+        self.since.is_none() && self.until.is_none()
+    }
+
+    /// Returns whether activity spanning `start..=end` overlaps the window.
+    pub fn overlaps(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+        self.since.is_none_or(|since| end >= since) && self.until.is_none_or(|until| start <= until)
+    }
+}
+
+fn parse_bound(value: &str) -> Result<DateTime<Utc>> {
+    parse_timestamp(value).ok_or_else(|| XurlError::InvalidTimestamp(value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use chrono::Duration;
+
+    use crate::timestamp::{TimeWindow, normalize_timestamp, parse_age, parse_timestamp};
+
+    #[test]
+    fn parses_provider_timestamp_shapes() {
+        let expected = Utc
+            .with_ymd_and_hms(2026, 2, 23, 0, 0, 3)
+            .single()
+            .expect("valid date");
+        assert_eq!(parse_timestamp("2026-02-23T00:00:03Z"), Some(expected));
+        assert_eq!(parse_timestamp("2026-02-23T08:00:03+08:00"), Some(expected));
+        assert_eq!(parse_timestamp("2026-02-23 00:00:03"), Some(expected));
+        assert_eq!(parse_timestamp("1771804803"), Some(expected));
+        assert_eq!(parse_timestamp("1771804803000"), Some(expected));
+        assert_eq!(parse_timestamp("not a time"), None);
+    }
+
+    #[test]
+    fn normalizes_timestamps_to_rfc3339_utc() {
+        assert_eq!(normalize_timestamp("1771804803"), "2026-02-23T00:00:03Z");
+        assert_eq!(
+            normalize_timestamp("2026-02-23T08:00:03.250+08:00"),
+            "2026-02-23T00:00:03.250Z"
+        );
+        assert_eq!(
+            normalize_timestamp("2026-02-23T00:00:03Z"),
+            "2026-02-23T00:00:03Z"
+        );
+        assert_eq!(normalize_timestamp("sometime"), "sometime");
+    }
+
+    #[test]
+    fn parses_ages_with_units() {
+        assert_eq!(parse_age("30d").expect("age"), Duration::days(30));
+        assert_eq!(parse_age("12h").expect("age"), Duration::hours(12));
+        assert_eq!(parse_age("2w").expect("age"), Duration::weeks(2));
+        assert_eq!(parse_age("7").expect("age"), Duration::days(7));
+        assert!(parse_age("soon").is_err());
+        assert!(parse_age("3y").is_err());
+    }
+
+    #[test]
+    fn window_bounds_are_inclusive_and_cover_whole_until_day() {
+        let window = TimeWindow::parse(Some("2026-02-20"), Some("2026-02-21")).expect("window");
+        let inside = Utc
+            .with_ymd_and_hms(2026, 2, 21, 23, 59, 59)
+            .single()
+            .expect("valid date");
+        let after = Utc
+            .with_ymd_and_hms(2026, 2, 22, 0, 0, 0)
+            .single()
+            .expect("valid date");
+        assert!(window.overlaps(inside, inside));
+        assert!(!window.overlaps(after, after));
+
+        assert!(TimeWindow::parse(Some("2026-02-22"), Some("2026-02-21")).is_err());
+        assert!(TimeWindow::parse(Some("yesterday"), None).is_err());
+    }
+}
@@ -0,0 +1,264 @@
+//! Tool calls of a thread, counted per tool.
+//!
+//! Codex rollouts record `function_call` / `custom_tool_call` items followed
+//! by their `*_output`, Claude and Amp pair `tool_use` blocks with
+//! `tool_result` blocks, and Gemini keeps each call with its result under
+//! `toolCalls`. Amp threads in the directory layout are counted across their
+//! `messages-<n>.json` shards. Other providers yield no usage.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::jsonl;
+use crate::model::{ProviderKind, ToolUsage};
+#[cfg(feature = "fs")]
+use crate::provider::amp;
+#[cfg(feature = "fs")]
+use crate::service::read_thread_raw;
+
+#[derive(Debug, Default)]
+struct Tally {
+    usage: Vec<ToolUsage>,
+    /// Call id → index into `usage`, to charge results to their tool.
+    calls: HashMap<String, usize>,
+}
+
+impl Tally {
+    fn call(&mut self, name: &str, call_id: Option<&str>, timestamp: Option<&str>) {
+        let index = match self.usage.iter().position(|usage| usage.name == name) {
+            Some(index) => index,
+            None => {
+                self.usage.push(ToolUsage {
+                    name: name.to_string(),
+                    calls: 0,
+                    errors: 0,
+                    last_used_at: None,
+                });
+                self.usage.len() - 1
+            }
+        };
+        let usage = &mut self.usage[index];
+        usage.calls += 1;
+        if let Some(timestamp) = timestamp {
+            usage.last_used_at = Some(timestamp.to_string());
+        }
+        if let Some(call_id) = call_id {
+            self.calls.insert(call_id.to_string(), index);
+        }
+    }
+
+    fn failed(&mut self, call_id: Option<&str>) {
+        if let Some(index) = call_id.and_then(|call_id| self.calls.get(call_id)) {
+            self.usage[*index].errors += 1;
+        }
+    }
+
+    fn finish(mut self) -> Vec<ToolUsage> {
+        self.usage
+            .sort_by(|a, b| b.calls.cmp(&a.calls).then_with(|| a.name.cmp(&b.name)));
+        self.usage
+    }
+}
+
+/// Counts the tool calls in the raw transcript at `path`.
+pub fn summarize_tools(provider: ProviderKind, path: &Path, raw: &str) -> Vec<ToolUsage> {
+    let mut tally = Tally::default();
+    match provider {
+        ProviderKind::Codex | ProviderKind::Claude => {
+            for (line_idx, line) in raw.lines().enumerate() {
+                let Ok(Some(value)) = jsonl::parse_json_line(path, line_idx + 1, line) else {
+                    continue;
+                };
+                if provider == ProviderKind::Codex {
+                    codex_tools(&value, &mut tally);
+                } else {
+                    let timestamp = value.get("timestamp").and_then(Value::as_str);
+                    content_tools(&value["message"]["content"], timestamp, &mut tally);
+                }
+            }
+        }
+        ProviderKind::Amp => {
+            let Ok(value) = serde_json::from_str::<Value>(raw) else {
+                return Vec::new();
+            };
+            amp_tools(&value, &mut tally);
+            #[cfg(feature = "fs")]
+            for shard in amp::message_shards(path) {
+                if let Some(value) = read_thread_raw(&shard)
+                    .ok()
+                    .and_then(|raw| serde_json::from_str::<Value>(&raw).ok())
+                {
+                    amp_tools(&value, &mut tally);
+                }
+            }
+        }
+        ProviderKind::Gemini => {
+            let Ok(value) = serde_json::from_str::<Value>(raw) else {
+                return Vec::new();
+            };
+            for message in value["messages"].as_array().into_iter().flatten() {
+                gemini_tools(message, &mut tally);
+            }
+        }
+        ProviderKind::Pi | ProviderKind::Opencode | ProviderKind::Copilot | ProviderKind::File => {}
+    }
+    tally.finish()
+}
+
+fn codex_tools(value: &Value, tally: &mut Tally) {
+    if value["type"] != "response_item" {
+        return;
+    }
+    let payload = &value["payload"];
+    let call_id = payload["call_id"].as_str();
+    match payload["type"].as_str() {
+        Some("function_call" | "custom_tool_call") => {
+            if let Some(name) = payload["name"].as_str() {
+                tally.call(name, call_id, value["timestamp"].as_str());
+            }
+        }
+        Some("function_call_output" | "custom_tool_call_output") => {
+            // Outputs are JSON text; shell-like tools report an exit code.
+            let output = payload["output"]
+                .as_str()
+                .and_then(|output| serde_json::from_str::<Value>(output).ok())
+                .unwrap_or(Value::Null);
+            let exit_code = output["metadata"]["exit_code"].as_i64();
+            if exit_code.is_some_and(|code| code != 0) || output["success"] == false {
+                tally.failed(call_id);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The messages of an Amp thread file or shard.
+fn amp_tools(value: &Value, tally: &mut Tally) {
+    for message in value["messages"].as_array().into_iter().flatten() {
+        let timestamp = message.get("timestamp").and_then(Value::as_str);
+        content_tools(&message["content"], timestamp, tally);
+    }
+}
+
+/// Claude and Amp message content: `tool_use` blocks and their
+/// `tool_result`s.
+fn content_tools(content: &Value, timestamp: Option<&str>, tally: &mut Tally) {
+    for block in content.as_array().into_iter().flatten() {
+        match block["type"].as_str() {
+            Some("tool_use") => {
+                if let Some(name) = block["name"].as_str() {
+                    tally.call(name, block["id"].as_str(), timestamp);
+                }
+            }
+            Some("tool_result") => {
+                let call_id = block["tool_use_id"]
+                    .as_str()
+                    .or_else(|| block["toolUseID"].as_str());
+                if block["is_error"] == true || block["run"]["status"] == "error" {
+                    tally.failed(call_id);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn gemini_tools(message: &Value, tally: &mut Tally) {
+    for call in message["toolCalls"].as_array().into_iter().flatten() {
+        let Some(name) = call["name"].as_str() else {
+            continue;
+        };
+        let timestamp = call["timestamp"]
+            .as_str()
+            .or_else(|| message["timestamp"].as_str());
+        let call_id = call["id"].as_str();
+        tally.call(name, call_id, timestamp);
+        if matches!(call["status"].as_str(), Some("error" | "cancelled")) {
+            tally.failed(call_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::model::{ProviderKind, ToolUsage};
+    use crate::tools::summarize_tools;
+
+    fn usage(name: &str, calls: usize, errors: usize, last_used_at: &str) -> ToolUsage {
+        ToolUsage {
+            name: name.to_string(),
+            calls,
+            errors,
+            last_used_at: Some(last_used_at.to_string()),
+        }
+    }
+
+    #[test]
+    fn counts_codex_calls_and_failed_commands() {
+        let raw = [
+            r#"{"timestamp":"t1","type":"response_item","payload":{"type":"function_call","name":"shell","call_id":"c1","arguments":"{}"}}"#,
+            r#"{"timestamp":"t2","type":"response_item","payload":{"type":"function_call_output","call_id":"c1","output":"{\"output\":\"\",\"metadata\":{\"exit_code\":1}}"}}"#,
+            r#"{"timestamp":"t3","type":"response_item","payload":{"type":"custom_tool_call","name":"apply_patch","call_id":"c2","input":""}}"#,
+            r#"{"timestamp":"t4","type":"response_item","payload":{"type":"custom_tool_call_output","call_id":"c2","output":"{\"metadata\":{\"exit_code\":0}}"}}"#,
+            r#"{"timestamp":"t5","type":"response_item","payload":{"type":"function_call","name":"shell","call_id":"c3","arguments":"{}"}}"#,
+        ]
+        .join("\n");
+
+        assert_eq!(
+            summarize_tools(ProviderKind::Codex, Path::new("rollout.jsonl"), &raw),
+            vec![usage("shell", 2, 1, "t5"), usage("apply_patch", 1, 0, "t3")]
+        );
+    }
+
+    #[test]
+    fn counts_claude_tool_use_blocks_and_error_results() {
+        let raw = [
+            r#"{"timestamp":"t1","type":"assistant","message":{"role":"assistant","content":[{"type":"tool_use","id":"u1","name":"Read"},{"type":"tool_use","id":"u2","name":"Edit"}]}}"#,
+            r#"{"timestamp":"t2","type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"u2","is_error":true}]}}"#,
+            r#"{"timestamp":"t3","type":"assistant","message":{"role":"assistant","content":[{"type":"tool_use","id":"u3","name":"Read"}]}}"#,
+        ]
+        .join("\n");
+
+        assert_eq!(
+            summarize_tools(ProviderKind::Claude, Path::new("agent.jsonl"), &raw),
+            vec![usage("Read", 2, 0, "t3"), usage("Edit", 1, 1, "t1")]
+        );
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn counts_amp_tool_calls_across_message_shards() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let thread = temp.path().join("thread.json");
+        std::fs::write(&thread, "{}").expect("write thread");
+        std::fs::write(
+            temp.path().join("messages-1.json"),
+            r#"{"messages":[{"role":"assistant","timestamp":"t1","content":[{"type":"tool_use","id":"a1","name":"Bash"}]}]}"#,
+        )
+        .expect("write shard");
+        std::fs::write(
+            temp.path().join("messages-2.json"),
+            r#"{"messages":[{"role":"user","timestamp":"t2","content":[{"type":"tool_result","toolUseID":"a1","run":{"status":"error"}}]},{"role":"assistant","timestamp":"t3","content":[{"type":"tool_use","id":"a2","name":"Read"}]}]}"#,
+        )
+        .expect("write shard");
+
+        assert_eq!(
+            summarize_tools(ProviderKind::Amp, &thread, "{}"),
+            vec![usage("Bash", 1, 1, "t1"), usage("Read", 1, 0, "t3")]
+        );
+    }
+
+    #[test]
+    fn counts_gemini_tool_calls_with_their_status() {
+        let raw = r#"{"messages":[{"type":"gemini","timestamp":"t1","toolCalls":[{"id":"g1","name":"read_file","status":"success","timestamp":"t2"},{"id":"g2","name":"read_file","status":"error","timestamp":"t3"}]}]}"#;
+
+        assert_eq!(
+            summarize_tools(ProviderKind::Gemini, Path::new("session.json"), raw),
+            vec![usage("read_file", 2, 1, "t3")]
+        );
+    }
+}
@@ -0,0 +1,430 @@
+//! Threads as traces (`xurl trace`).
+//!
+//! A session becomes one trace: a session span covering the whole thread, a
+//! span per turn (a user message and the replies up to the next one), and a
+//! span per subagent, nested under the turn that was running when it
+//! started. Spans are exported as OTLP JSON, the `ExportTraceServiceRequest`
+//! body that collectors such as Jaeger and Tempo accept at `/v1/traces`.
+
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde_json::{Value, json};
+
+use crate::error::{Result, XurlError};
+use crate::model::{
+    ProviderKind, SubagentListItem, SubagentView, ThreadTrace, TimelineEntryKind,
+    TimelineEntryView, TraceSpan, Warning,
+};
+use crate::provider::{ProviderRoots, modified_or_epoch};
+use crate::render::{self, ReadOptions};
+use crate::service::{
+    TIMELINE_LINES, agents_thread_uri, read_thread_lines, resolve_subagent_view, resolve_thread,
+};
+use crate::status::STATUS_ERRORED;
+use crate::timestamp::parse_timestamp;
+use crate::uri::ThreadUri;
+
+const FNV_OFFSET: u128 = 0x6c62_272e_07bb_0142_62b8_2175_6295_c58d;
+const FNV_PRIME: u128 = 0x0000_0000_0100_0000_0000_0000_0000_013b;
+
+/// Characters of a turn's prompt kept in its `xurl.turn.prompt` attribute.
+const PROMPT_CHARS: usize = 120;
+
+/// OTLP `SPAN_KIND_INTERNAL`.
+const SPAN_KIND_INTERNAL: u8 = 1;
+/// OTLP `STATUS_CODE_ERROR`.
+const STATUS_CODE_ERROR: u8 = 2;
+
+/// Builds the trace of a main thread and its subagents.
+pub fn trace_thread(
+    uri: &ThreadUri,
+    roots: &ProviderRoots,
+    options: &ReadOptions,
+) -> Result<ThreadTrace> {
+    if uri.agent_id.is_some() {
+        return Err(XurlError::InvalidMode(
+            "trace only supports main thread URIs: agents://<provider>/<session_id>".to_string(),
+        ));
+    }
+
+    let resolved = resolve_thread(uri, roots)?;
+    let raw = read_thread_lines(&resolved, roots, options, TIMELINE_LINES)?;
+    let mut policy = options.extraction_policy();
+    let entries = render::timeline_entries(uri, &resolved.path, &raw, &mut policy)?;
+    let mut warnings = resolved.metadata.warnings.clone();
+    warnings.extend(policy.into_warnings());
+
+    let uri_string = uri.as_agents_string();
+    let trace_id = format!("{:032x}", fnv1a(&[&uri_string]));
+    let span_id = |key: &str| format!("{:016x}", fnv1a(&[&uri_string, key]) as u64);
+    let session_span_id = span_id("session");
+
+    let times = entry_times(&entries, &resolved.path, &mut warnings);
+    let mut turns = Vec::<TraceSpan>::new();
+    for (entry, time) in entries.iter().zip(&times) {
+        match turns.last_mut() {
+            Some(turn) if entry.kind != TimelineEntryKind::User => {
+                turn.end = turn.end.max(*time);
+                count_message(turn);
+            }
+            _ => {
+                let number = turns.len() + 1;
+                let mut attributes = vec![
+                    ("xurl.turn.number".to_string(), number.to_string()),
+                    ("xurl.entry.uri".to_string(), entry.uri.clone()),
+                    ("xurl.turn.messages".to_string(), "1".to_string()),
+                ];
+                if entry.kind == TimelineEntryKind::User {
+                    attributes.push(("xurl.turn.prompt".to_string(), preview(&entry.text)));
+                }
+                turns.push(TraceSpan {
+                    span_id: span_id(&format!("turn:{number}")),
+                    parent_span_id: Some(session_span_id.clone()),
+                    name: format!("turn {number}"),
+                    start: *time,
+                    end: *time,
+                    attributes,
+                    error: None,
+                });
+            }
+        }
+    }
+
+    let mut subagents = Vec::new();
+    if matches!(
+        uri.provider,
+        ProviderKind::Amp | ProviderKind::Codex | ProviderKind::Claude | ProviderKind::Gemini
+    ) && let SubagentView::List(list) = resolve_subagent_view(uri, roots, options, true)?
+    {
+        warnings.extend(list.warnings);
+        for agent in &list.agents {
+            let parent = agent_start(agent)
+                .and_then(|start| turns.iter().rev().find(|turn| turn.start <= start))
+                .map_or(&session_span_id, |turn| &turn.span_id)
+                .clone();
+            subagent_spans(
+                agent,
+                &list.query.provider,
+                &list.query.main_thread_id,
+                &parent,
+                &span_id,
+                &mut subagents,
+                &mut warnings,
+            );
+        }
+    }
+
+    let fallback = times
+        .first()
+        .copied()
+        .unwrap_or_else(|| modified_or_epoch(&resolved.path).into());
+    let spans = turns.iter().chain(&subagents);
+    let start = spans
+        .clone()
+        .map(|span| span.start)
+        .min()
+        .unwrap_or(fallback);
+    let end = spans.map(|span| span.end).max().unwrap_or(fallback);
+
+    let session = &resolved.metadata.session;
+    let mut attributes = vec![
+        ("xurl.uri".to_string(), uri_string.clone()),
+        (
+            "xurl.thread_source".to_string(),
+            resolved.path.display().to_string(),
+        ),
+    ];
+    for (key, value) in [
+        ("xurl.session.model", &session.model),
+        ("xurl.session.cwd", &session.cwd),
+        ("xurl.session.branch", &session.git_branch),
+    ] {
+        if let Some(value) = value {
+            attributes.push((key.to_string(), value.clone()));
+        }
+    }
+
+    let mut spans = vec![TraceSpan {
+        span_id: session_span_id,
+        parent_span_id: None,
+        name: format!("{} session", uri.provider),
+        start,
+        end,
+        attributes,
+        error: None,
+    }];
+    spans.extend(turns);
+    spans.extend(subagents);
+
+    Ok(ThreadTrace {
+        uri: uri_string,
+        provider: uri.provider,
+        trace_id,
+        spans,
+        warnings,
+    })
+}
+
+/// Renders the trace as an OTLP JSON `ExportTraceServiceRequest`.
+pub fn render_trace_otlp_json(trace: &ThreadTrace) -> Result<String> {
+    let spans = trace
+        .spans
+        .iter()
+        .map(|span| {
+            let mut value = json!({
+                "traceId": trace.trace_id,
+                "spanId": span.span_id,
+                "name": span.name,
+                "kind": SPAN_KIND_INTERNAL,
+                "startTimeUnixNano": unix_nanos(span.start),
+                "endTimeUnixNano": unix_nanos(span.end),
+                "attributes": attributes(&span.attributes),
+            });
+            if let Some(parent) = &span.parent_span_id {
+                value["parentSpanId"] = json!(parent);
+            }
+            if let Some(message) = &span.error {
+                value["status"] = json!({ "code": STATUS_CODE_ERROR, "message": message });
+            }
+            value
+        })
+        .collect::<Vec<_>>();
+
+    let request = json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": attributes(&[
+                    ("service.name".to_string(), trace.provider.to_string()),
+                ]),
+            },
+            "scopeSpans": [{
+                "scope": { "name": "xurl", "version": env!("CARGO_PKG_VERSION") },
+                "spans": spans,
+            }],
+        }],
+    });
+    serde_json::to_string_pretty(&request)
+        .map(|json| format!("{json}\n"))
+        .map_err(|err| XurlError::Serialization(err.to_string()))
+}
+
+/// When each entry happened. Entries without a timestamp take the previous
+/// one (or the first one, at the start); a thread with none falls back to
+/// its file's modification time.
+fn entry_times(
+    entries: &[TimelineEntryView],
+    path: &Path,
+    warnings: &mut Vec<Warning>,
+) -> Vec<DateTime<Utc>> {
+    let parsed = entries
+        .iter()
+        .map(|entry| entry.timestamp.as_deref().and_then(parse_timestamp))
+        .collect::<Vec<_>>();
+    let Some(first) = parsed.iter().flatten().next().copied() else {
+        if !entries.is_empty() {
+            warnings.push(Warning::warn(
+                "trace_no_timestamps",
+                "the thread records no message timestamps; every turn is placed at the file's modification time",
+            ));
+        }
+        return vec![modified_or_epoch(path).into(); entries.len()];
+    };
+
+    let mut last = first;
+    parsed
+        .into_iter()
+        .map(|time| {
+            last = time.unwrap_or(last);
+            last
+        })
+        .collect()
+}
+
+fn count_message(turn: &mut TraceSpan) {
+    if let Some((_, count)) = turn
+        .attributes
+        .iter_mut()
+        .find(|(key, _)| key == "xurl.turn.messages")
+    {
+        *count = (count.parse::<usize>().unwrap_or_default() + 1).to_string();
+    }
+}
+
+fn agent_start(agent: &SubagentListItem) -> Option<DateTime<Utc>> {
+    agent
+        .first_update
+        .as_deref()
+        .or(agent.last_update.as_deref())
+        .and_then(parse_timestamp)
+}
+
+/// Adds spans for `agent` and the agents it handed off to. Agents without
+/// any recorded time are skipped with a warning.
+fn subagent_spans(
+    agent: &SubagentListItem,
+    provider: &str,
+    main_thread_id: &str,
+    parent: &str,
+    span_id: &dyn Fn(&str) -> String,
+    spans: &mut Vec<TraceSpan>,
+    warnings: &mut Vec<Warning>,
+) {
+    let Some(start) = agent_start(agent) else {
+        warnings.push(Warning::info(
+            "trace_subagent_skipped",
+            format!(
+                "subagent {} has no recorded timestamps; it is left out of the trace",
+                agent.agent_id
+            ),
+        ));
+        return;
+    };
+    let end = agent
+        .last_update
+        .as_deref()
+        .and_then(parse_timestamp)
+        .map_or(start, |end| end.max(start));
+
+    let mut attributes = vec![
+        (
+            "xurl.subagent.uri".to_string(),
+            agents_thread_uri(provider, main_thread_id, Some(&agent.agent_id)),
+        ),
+        ("xurl.subagent.status".to_string(), agent.status.clone()),
+        (
+            "xurl.subagent.status_source".to_string(),
+            agent.status_source.clone(),
+        ),
+    ];
+    if let Some(thread) = &agent.child_thread {
+        attributes.push((
+            "xurl.subagent.child_thread_id".to_string(),
+            thread.thread_id.clone(),
+        ));
+    }
+
+    let id = span_id(&format!("subagent:{}", agent.agent_id));
+    spans.push(TraceSpan {
+        span_id: id.clone(),
+        parent_span_id: Some(parent.to_string()),
+        name: format!(
+            "subagent {}",
+            agent.agent_name.as_deref().unwrap_or(&agent.agent_id)
+        ),
+        start,
+        end,
+        attributes,
+        error: (agent.status == STATUS_ERRORED).then(|| format!("subagent {}", agent.status)),
+    });
+    for child in &agent.children {
+        subagent_spans(
+            child,
+            provider,
+            main_thread_id,
+            &id,
+            span_id,
+            spans,
+            warnings,
+        );
+    }
+}
+
+fn preview(text: &str) -> String {
+    let flat = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match flat.char_indices().nth(PROMPT_CHARS) {
+        Some((idx, _)) => format!("{}...", &flat[..idx]),
+        None => flat,
+    }
+}
+
+/// OTLP JSON encodes 64-bit integers as strings.
+fn unix_nanos(time: DateTime<Utc>) -> String {
+    time.timestamp_nanos_opt()
+        .unwrap_or_default()
+        .max(0)
+        .to_string()
+}
+
+fn attributes(pairs: &[(String, String)]) -> Value {
+    pairs
+        .iter()
+        .map(|(key, value)| json!({ "key": key, "value": { "stringValue": value } }))
+        .collect()
+}
+
+/// 128-bit FNV-1a over `parts`, separated by NUL bytes.
+fn fnv1a(parts: &[&str]) -> u128 {
+    parts.join("\0").bytes().fold(FNV_OFFSET, |hash, byte| {
+        (hash ^ u128::from(byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use crate::provider::{ProviderRoots, write_test_rollout};
+    use crate::render::ReadOptions;
+    use crate::trace::{render_trace_otlp_json, trace_thread};
+    use crate::uri::ThreadUri;
+
+    const SESSION_ID: &str = "019c871c-b1f9-7f60-9c4f-87ed09f13592";
+
+    fn message(timestamp: &str, role: &str, text: &str) -> String {
+        let kind = if role == "user" {
+            "input_text"
+        } else {
+            "output_text"
+        };
+        format!(
+            r#"{{"timestamp":"{timestamp}","type":"response_item","payload":{{"type":"message","role":"{role}","content":[{{"type":"{kind}","text":"{text}"}}]}}}}"#
+        ) + "\n"
+    }
+
+    #[test]
+    fn turns_become_spans_under_the_session() {
+        let temp = tempdir().expect("tempdir");
+        let roots = ProviderRoots::for_test(temp.path());
+        write_test_rollout(
+            &roots.codex_root,
+            SESSION_ID,
+            &(message("2026-02-23T00:00:00Z", "user", "fix the parser")
+                + &message("2026-02-23T00:00:05Z", "assistant", "looking")
+                + &message("2026-02-23T00:00:09Z", "assistant", "fixed")
+                + &message("2026-02-23T00:01:00Z", "user", "thanks")
+                + &message("2026-02-23T00:01:02Z", "assistant", "welcome")),
+        );
+        let uri = ThreadUri::parse(&format!("agents://codex/{SESSION_ID}")).expect("uri");
+
+        let trace = trace_thread(&uri, &roots, &ReadOptions::default()).expect("trace");
+        let names = trace
+            .spans
+            .iter()
+            .map(|span| span.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["codex session", "turn 1", "turn 2"]);
+        let (session, first) = (&trace.spans[0], &trace.spans[1]);
+        assert_eq!(first.parent_span_id.as_ref(), Some(&session.span_id));
+        assert_eq!(
+            (first.end - first.start).num_seconds(),
+            9,
+            "turn 1 ends at its last reply"
+        );
+        assert_eq!((session.end - session.start).num_seconds(), 62);
+        assert!(
+            first
+                .attributes
+                .contains(&("xurl.turn.prompt".to_string(), "fix the parser".to_string()))
+        );
+        assert_eq!(trace.trace_id.len(), 32);
+        assert_eq!(
+            trace,
+            trace_thread(&uri, &roots, &ReadOptions::default()).expect("trace again")
+        );
+
+        let json = render_trace_otlp_json(&trace).expect("render");
+        assert!(json.contains(r#""startTimeUnixNano": "1771804800000000000""#));
+        assert!(json.contains(&format!(r#""parentSpanId": "{}""#, session.span_id)));
+    }
+}
@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+use std::fmt;
 use std::str::FromStr;
 
 use once_cell::sync::Lazy;
@@ -14,8 +16,27 @@ static AMP_SESSION_ID_RE: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r"(?i)^t-[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}$")
         .expect("valid regex")
 });
+/// An Amp thread id as 32 hex digits without dashes.
+static AMP_COMPACT_ID_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)^(?:t-?)?([0-9a-f]{32})$").expect("valid regex"));
+/// A Gemini project hash, the SHA-256 of the project root that names the
+/// project's directory under `tmp/`.
+static GEMINI_PROJECT_HASH_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)^[0-9a-f]{64}$").expect("valid regex"));
 static OPENCODE_SESSION_ID_RE: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"^ses_[0-9A-Za-z]+$").expect("valid regex"));
+/// A whole OpenCode session id as OpenCode generates it: 12 hex digits of
+/// timestamp and 14 random base62 characters.
+static OPENCODE_FULL_SESSION_ID_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^ses_[0-9a-f]{12}[0-9A-Za-z]{14}$").expect("valid regex"));
+/// Leading part of a session UUID (at least four characters), accepted so a
+/// thread can be opened by the start of its id.
+static SESSION_ID_PREFIX_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)^(?:[0-9a-f]{4,8}|[0-9a-f]{8}-(?:[0-9a-f]{0,4}|[0-9a-f]{4}-(?:[0-9a-f]{0,4}|[0-9a-f]{4}-(?:[0-9a-f]{0,4}|[0-9a-f]{4}-[0-9a-f]{0,12}))))$",
+    )
+    .expect("valid regex")
+});
 static PI_SHORT_ENTRY_ID_RE: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"(?i)^[0-9a-f]{8}$").expect("valid regex"));
 
@@ -24,6 +45,14 @@ pub struct ThreadUri {
     pub provider: ProviderKind,
     pub session_id: String,
     pub agent_id: Option<String>,
+    /// Timeline entry the URI points at (`#msg-42` or `#<entry id>`).
+    pub fragment: Option<MessageAnchor>,
+    /// Provider whose transcript layout a `file://` thread uses
+    /// (`?format=codex`); `None` for every other URI.
+    pub file_format: Option<ProviderKind>,
+    /// Gemini project hash that restricts resolution to one project's
+    /// chats (`agents://gemini/<project hash>/<session_id>`).
+    pub gemini_project: Option<String>,
 }
 
 impl ThreadUri {
@@ -31,20 +60,211 @@ impl ThreadUri {
         input.parse()
     }
 
+    /// Whether the session id may be only the start of a longer id and
+    /// should be expanded against local storage before resolving.
+    pub fn has_partial_session_id(&self) -> bool {
+        match self.provider {
+            ProviderKind::Amp => !AMP_SESSION_ID_RE.is_match(&self.session_id),
+            ProviderKind::Codex
+            | ProviderKind::Claude
+            | ProviderKind::Gemini
+            | ProviderKind::Pi
+            | ProviderKind::Copilot => !SESSION_ID_RE.is_match(&self.session_id),
+            ProviderKind::Opencode => !OPENCODE_FULL_SESSION_ID_RE.is_match(&self.session_id),
+            ProviderKind::File => false,
+        }
+    }
+
+    /// Provider whose parser reads the thread: the `format` of a `file://`
+    /// URI, otherwise the provider itself.
+    pub fn format(&self) -> ProviderKind {
+        self.file_format.unwrap_or(self.provider)
+    }
+
+    /// The target of an `any://<session_id>` (or `agents://any/<session_id>`)
+    /// URI, which leaves the provider to be found by probing local storage.
+    pub fn any_target(input: &str) -> Option<&str> {
+        input
+            .strip_prefix("any://")
+            .or_else(|| input.strip_prefix("agents://any/"))
+    }
+
+    /// The target of a `tag://<name>` URI, which names a thread recorded
+    /// with `xurl tag`; see [`crate::tags::expand_tag_uri`].
+    pub fn tag_target(input: &str) -> Option<&str> {
+        input.strip_prefix("tag://")
+    }
+
+    /// The share id (and any `#msg-<N>` fragment) of an opencode share link,
+    /// `https://opencode.ai/s/<share id>`; see
+    /// [`crate::provider::opencode_share::expand_share_uri`].
+    pub fn opencode_share_target(input: &str) -> Option<&str> {
+        input
+            .strip_prefix("https://opencode.ai/s/")
+            .or_else(|| input.strip_prefix("http://opencode.ai/s/"))
+    }
+
+    /// Canonical `agents://` form of the thread, without the fragment.
+    /// File threads keep their `file://` form.
     pub fn as_agents_string(&self) -> String {
-        match &self.agent_id {
-            Some(agent_id) => format!(
-                "agents://{}/{}/{}",
-                self.provider, self.session_id, agent_id
-            ),
-            None => format!("agents://{}/{}", self.provider, self.session_id),
+        if self.provider == ProviderKind::File {
+            return self.as_string();
         }
+        format!("agents://{}/{}", self.provider, self.target())
     }
 
     pub fn as_string(&self) -> String {
-        match &self.agent_id {
-            Some(agent_id) => format!("{}://{}/{}", self.provider, self.session_id, agent_id),
-            None => format!("{}://{}", self.provider, self.session_id),
+        if self.provider == ProviderKind::File {
+            let mut path = String::with_capacity(self.session_id.len());
+            for ch in self.session_id.chars() {
+                match ch {
+                    '%' | '?' | '#' | ' ' => path.push_str(&format!("%{:02X}", ch as u32)),
+                    _ => path.push(ch),
+                }
+            }
+            return format!("file://{path}?format={}", self.format());
+        }
+        format!("{}://{}", self.provider, self.target())
+    }
+
+    /// The path after the provider: the project hash, if any, the session
+    /// id, and the agent id, if any.
+    fn target(&self) -> String {
+        let mut target = String::new();
+        if let Some(project) = &self.gemini_project {
+            target.push_str(project);
+            target.push('/');
+        }
+        target.push_str(&self.session_id);
+        if let Some(agent_id) = &self.agent_id {
+            target.push('/');
+            target.push_str(agent_id);
+        }
+        target
+    }
+}
+
+/// One page of a thread timeline. URIs carry it as `?page=N&page_size=M`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimelinePage {
+    /// 1-based page number.
+    pub number: usize,
+    /// Timeline entries per page.
+    pub size: usize,
+}
+
+impl TimelinePage {
+    pub const DEFAULT_SIZE: usize = 100;
+
+    /// Splits a trailing `?page=N&page_size=M` query off `input`. Either key
+    /// may be omitted; a query with neither key is rejected. A `file://`
+    /// query opens with the file's `format`, so paging keys follow it.
+    pub fn split_uri(input: &str) -> Result<(&str, Option<Self>)> {
+        let split = if input.starts_with("file://") {
+            input
+                .find('?')
+                .and_then(|start| input[start..].find('&').map(|at| start + at))
+                .map(|at| (&input[..at], &input[at + 1..]))
+        } else {
+            input.split_once('?')
+        };
+        let Some((base, query)) = split else {
+            return Ok((input, None));
+        };
+
+        let mut number = None;
+        let mut size = None;
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let slot = match key {
+                "page" => &mut number,
+                "page_size" => &mut size,
+                _ => return Err(XurlError::InvalidUri(input.to_string())),
+            };
+            let value = value
+                .parse::<usize>()
+                .ok()
+                .filter(|value| *value > 0)
+                .ok_or_else(|| XurlError::InvalidUri(input.to_string()))?;
+            *slot = Some(value);
+        }
+
+        if number.is_none() && size.is_none() {
+            return Err(XurlError::InvalidUri(input.to_string()));
+        }
+        Ok((
+            base,
+            Some(Self {
+                number: number.unwrap_or(1),
+                size: size.unwrap_or(Self::DEFAULT_SIZE),
+            }),
+        ))
+    }
+
+    pub fn total_pages(&self, entries: usize) -> usize {
+        entries.div_ceil(self.size).max(1)
+    }
+
+    /// Zero-based timeline indexes covered by this page.
+    pub fn range(&self, entries: usize) -> std::ops::Range<usize> {
+        let start = (self.number - 1).saturating_mul(self.size).min(entries);
+        start..start.saturating_add(self.size).min(entries)
+    }
+
+    /// `uri` with this page's query appended.
+    pub fn uri(&self, uri: &ThreadUri) -> String {
+        let separator = if uri.provider == ProviderKind::File {
+            '&'
+        } else {
+            '?'
+        };
+        format!(
+            "{}{separator}page={}&page_size={}",
+            uri.as_agents_string(),
+            self.number,
+            self.size
+        )
+    }
+}
+
+/// One entry of a thread timeline. URIs carry it as a fragment: `#msg-42`
+/// for the 42nd entry, or `#<entry id>` for the id in the entry's anchor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MessageAnchor {
+    Number(usize),
+    Id(String),
+}
+
+impl MessageAnchor {
+    pub const PREFIX: &'static str = "msg-";
+
+    /// Splits a trailing `#fragment` off `input`.
+    pub fn split_uri(input: &str) -> Result<(&str, Option<Self>)> {
+        let Some((base, fragment)) = input.split_once('#') else {
+            return Ok((input, None));
+        };
+        if fragment.is_empty() || fragment.contains(['#', '?', '/']) {
+            return Err(XurlError::InvalidUri(input.to_string()));
+        }
+
+        let anchor = match fragment.strip_prefix(Self::PREFIX) {
+            Some(number) => number
+                .parse::<usize>()
+                .ok()
+                .filter(|number| *number > 0)
+                .map(Self::Number)
+                .ok_or_else(|| XurlError::InvalidUri(input.to_string()))?,
+            None => Self::Id(fragment.to_string()),
+        };
+        Ok((base, Some(anchor)))
+    }
+}
+
+impl fmt::Display for MessageAnchor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(number) => write!(f, "{}{number}", Self::PREFIX),
+            Self::Id(id) => write!(f, "{id}"),
         }
     }
 }
@@ -53,9 +273,16 @@ impl FromStr for ThreadUri {
     type Err = XurlError;
 
     fn from_str(input: &str) -> Result<Self> {
-        let (scheme, target) = input
-            .split_once("://")
-            .ok_or_else(|| XurlError::InvalidUri(input.to_string()))?;
+        let (input, fragment) = MessageAnchor::split_uri(input)?;
+        let (scheme, target) = match strip_amp_thread_link(input) {
+            Some(target) => ("amp", target),
+            None => input
+                .split_once("://")
+                .ok_or_else(|| XurlError::InvalidUri(input.to_string()))?,
+        };
+        if scheme == "file" {
+            return parse_file_target(input, target, fragment);
+        }
 
         let (provider, provider_target) = if scheme == "agents" {
             let (provider_scheme, provider_target) = target
@@ -70,14 +297,24 @@ impl FromStr for ThreadUri {
         };
 
         let normalized_target = match provider {
-            ProviderKind::Amp => provider_target,
+            ProviderKind::Amp => strip_amp_thread_link(provider_target).unwrap_or(provider_target),
             ProviderKind::Codex => provider_target
                 .strip_prefix("threads/")
                 .unwrap_or(provider_target),
             ProviderKind::Claude
             | ProviderKind::Gemini
             | ProviderKind::Pi
-            | ProviderKind::Opencode => provider_target,
+            | ProviderKind::Opencode
+            | ProviderKind::Copilot
+            | ProviderKind::File => provider_target,
+        };
+        let (gemini_project, normalized_target) = match normalized_target.split_once('/') {
+            Some((project, rest))
+                if provider == ProviderKind::Gemini && GEMINI_PROJECT_HASH_RE.is_match(project) =>
+            {
+                (Some(project.to_ascii_lowercase()), rest)
+            }
+            _ => (None, normalized_target),
         };
 
         let (id, agent_id) = match provider {
@@ -100,7 +337,7 @@ impl FromStr for ThreadUri {
 
                 (main_id, agent_id)
             }
-            ProviderKind::Opencode => {
+            ProviderKind::Opencode | ProviderKind::Copilot | ProviderKind::File => {
                 if normalized_target.contains('/') {
                     return Err(XurlError::InvalidUri(input.to_string()));
                 }
@@ -108,15 +345,26 @@ impl FromStr for ThreadUri {
             }
         };
 
+        let (id, agent_id) = if provider == ProviderKind::Amp {
+            (
+                normalize_amp_id(id),
+                agent_id.map(|agent_id| normalize_amp_id(&agent_id).into_owned()),
+            )
+        } else {
+            (Cow::Borrowed(id), agent_id)
+        };
+        let id = id.as_ref();
+
         match provider {
-            ProviderKind::Amp if !AMP_SESSION_ID_RE.is_match(id) => {
+            ProviderKind::Amp if !is_amp_session_id_or_prefix(id) => {
                 return Err(XurlError::InvalidSessionId(id.to_string()));
             }
             ProviderKind::Codex
             | ProviderKind::Claude
             | ProviderKind::Gemini
             | ProviderKind::Pi
-                if !SESSION_ID_RE.is_match(id) =>
+            | ProviderKind::Copilot
+                if !SESSION_ID_RE.is_match(id) && !SESSION_ID_PREFIX_RE.is_match(id) =>
             {
                 return Err(XurlError::InvalidSessionId(id.to_string()));
             }
@@ -138,8 +386,9 @@ impl FromStr for ThreadUri {
             ProviderKind::Codex
             | ProviderKind::Claude
             | ProviderKind::Gemini
-            | ProviderKind::Pi => id.to_ascii_lowercase(),
-            ProviderKind::Opencode => id.to_string(),
+            | ProviderKind::Pi
+            | ProviderKind::Copilot => id.to_ascii_lowercase(),
+            ProviderKind::Opencode | ProviderKind::File => id.to_string(),
         };
 
         let agent_id = agent_id.map(|agent_id| {
@@ -161,10 +410,108 @@ impl FromStr for ThreadUri {
             provider,
             session_id,
             agent_id,
+            fragment,
+            file_format: None,
+            gemini_project,
         })
     }
 }
 
+/// Parses the part after `file://` of `file:///path/to/thread.jsonl?format=codex`.
+/// The path must be absolute and may use `%XX` escapes; `format` names the
+/// provider whose transcript layout the file has.
+fn parse_file_target(
+    input: &str,
+    target: &str,
+    fragment: Option<MessageAnchor>,
+) -> Result<ThreadUri> {
+    let invalid = || XurlError::InvalidUri(input.to_string());
+    let (path, query) = target.split_once('?').ok_or_else(|| {
+        XurlError::InvalidMode(format!(
+            "file URIs need a transcript format, e.g. {input}?format=codex"
+        ))
+    })?;
+    let format = match query.split_once('=') {
+        Some(("format", format)) => parse_provider(format).map_err(|_| {
+            XurlError::InvalidMode(format!(
+                "unknown file format `{format}`: expected amp, codex, claude, gemini, pi, opencode, or copilot"
+            ))
+        })?,
+        _ => return Err(invalid()),
+    };
+    let path = percent_decode(path).ok_or_else(invalid)?;
+    if !path.starts_with('/') {
+        return Err(invalid());
+    }
+
+    Ok(ThreadUri {
+        provider: ProviderKind::File,
+        session_id: path,
+        agent_id: None,
+        fragment,
+        file_format: Some(format),
+        gemini_project: None,
+    })
+}
+
+fn percent_decode(input: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(input.len());
+    let mut rest = input.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// The id part of an Amp web UI thread link,
+/// `https://ampcode.com/threads/T-<uuid>`.
+fn strip_amp_thread_link(input: &str) -> Option<&str> {
+    let rest = input
+        .strip_prefix("https://")
+        .or_else(|| input.strip_prefix("http://"))?;
+    let rest = rest.strip_prefix("www.").unwrap_or(rest);
+    let target = rest.strip_prefix("ampcode.com/threads/")?;
+    Some(target.strip_suffix('/').unwrap_or(target))
+}
+
+/// The `T-<uuid>` form of an Amp thread id written without its `T-`
+/// prefix, including the short prefixes of ids, or without dashes.
+fn normalize_amp_id(id: &str) -> Cow<'_, str> {
+    if let Some(hex) = AMP_COMPACT_ID_RE
+        .captures(id)
+        .and_then(|captures| captures.get(1))
+    {
+        let hex = hex.as_str();
+        return Cow::Owned(format!(
+            "T-{}-{}-{}-{}-{}",
+            &hex[..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..]
+        ));
+    }
+    if SESSION_ID_RE.is_match(id) || SESSION_ID_PREFIX_RE.is_match(id) {
+        return Cow::Owned(format!("T-{id}"));
+    }
+    Cow::Borrowed(id)
+}
+
+fn is_amp_session_id_or_prefix(id: &str) -> bool {
+    AMP_SESSION_ID_RE.is_match(id)
+        || (id
+            .get(..2)
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case("t-"))
+            && SESSION_ID_PREFIX_RE.is_match(&id[2..]))
+}
+
 fn parse_provider(scheme: &str) -> Result<ProviderKind> {
     match scheme {
         "amp" => Ok(ProviderKind::Amp),
@@ -173,13 +520,14 @@ fn parse_provider(scheme: &str) -> Result<ProviderKind> {
         "gemini" => Ok(ProviderKind::Gemini),
         "pi" => Ok(ProviderKind::Pi),
         "opencode" => Ok(ProviderKind::Opencode),
+        "copilot" => Ok(ProviderKind::Copilot),
         _ => Err(XurlError::UnsupportedScheme(scheme.to_string())),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::ThreadUri;
+    use super::{MessageAnchor, ThreadUri, TimelinePage};
     use crate::model::ProviderKind;
 
     #[test]
@@ -200,6 +548,39 @@ mod tests {
         assert_eq!(uri.agent_id, None);
     }
 
+    #[test]
+    fn parse_amp_thread_links_and_slugs() {
+        for input in [
+            "https://ampcode.com/threads/T-019C0797-C402-7389-BD80-D785C98DF295",
+            "amp://https://ampcode.com/threads/T-019c0797-c402-7389-bd80-d785c98df295/",
+            "agents://amp/https://www.ampcode.com/threads/T-019c0797-c402-7389-bd80-d785c98df295",
+            "amp://019c0797-c402-7389-bd80-d785c98df295",
+            "amp://T-019c0797c4027389bd80d785c98df295",
+            "agents://amp/019c0797c4027389bd80d785c98df295",
+        ] {
+            let uri = ThreadUri::parse(input).expect(input);
+            assert_eq!(uri.provider, ProviderKind::Amp, "{input}");
+            assert_eq!(
+                uri.session_id, "T-019c0797-c402-7389-bd80-d785c98df295",
+                "{input}"
+            );
+            assert!(!uri.has_partial_session_id(), "{input}");
+        }
+
+        let uri = ThreadUri::parse(
+            "https://ampcode.com/threads/T-019c0797-c402-7389-bd80-d785c98df295#msg-3",
+        )
+        .expect("link with fragment");
+        assert_eq!(uri.fragment, Some(MessageAnchor::Number(3)));
+
+        let uri = ThreadUri::parse("amp://019c0797").expect("short slug");
+        assert_eq!(uri.session_id, "T-019c0797");
+        assert!(uri.has_partial_session_id());
+
+        assert!(ThreadUri::parse("https://ampcode.com/threads/not-a-thread").is_err());
+        assert!(ThreadUri::parse("https://example.com/threads/T-019c0797").is_err());
+    }
+
     #[test]
     fn parse_codex_deeplink_uri() {
         let uri = ThreadUri::parse("codex://threads/019c871c-b1f9-7f60-9c4f-87ed09f13592")
@@ -329,6 +710,21 @@ mod tests {
         assert!(format!("{err}").contains("unsupported scheme"));
     }
 
+    #[test]
+    fn any_target_strips_either_scheme_form() {
+        assert_eq!(
+            ThreadUri::any_target("any://019c871c/child#msg-2"),
+            Some("019c871c/child#msg-2")
+        );
+        assert_eq!(
+            ThreadUri::any_target("agents://any/019c871c"),
+            Some("019c871c")
+        );
+        assert_eq!(ThreadUri::any_target("agents://codex/019c871c"), None);
+        let err = ThreadUri::parse("any://019c871c").expect_err("any is not a provider");
+        assert!(format!("{err}").contains("unsupported scheme"));
+    }
+
     #[test]
     fn parse_rejects_invalid_session_id() {
         let err = ThreadUri::parse("codex://agent-a1b2c3").expect_err("must reject non-session id");
@@ -379,6 +775,43 @@ mod tests {
             uri.agent_id,
             Some("2b112c8a-d80a-4cff-9c8a-6f3e6fbaf7fb".to_string())
         );
+        assert_eq!(uri.gemini_project, None);
+    }
+
+    #[test]
+    fn parse_gemini_project_hash_uri() {
+        let hash = "0c0d7b04c22749f3687ea60b66949fd32bcea2551d4349bf72346a9ccc9a9ba4";
+        let uri = ThreadUri::parse(&format!(
+            "agents://gemini/{}/29d207db-ca7e-40ba-87f7-e14c9de60613",
+            hash.to_ascii_uppercase()
+        ))
+        .expect("parse should succeed");
+        assert_eq!(uri.gemini_project.as_deref(), Some(hash));
+        assert_eq!(uri.session_id, "29d207db-ca7e-40ba-87f7-e14c9de60613");
+        assert_eq!(uri.agent_id, None);
+        assert_eq!(
+            uri.as_agents_string(),
+            format!("agents://gemini/{hash}/29d207db-ca7e-40ba-87f7-e14c9de60613")
+        );
+
+        let uri = ThreadUri::parse(&format!(
+            "gemini://{hash}/29d207db-ca7e-40ba-87f7-e14c9de60613/2b112c8a-d80a-4cff-9c8a-6f3e6fbaf7fb#msg-2"
+        ))
+        .expect("child with project should parse");
+        assert_eq!(uri.gemini_project.as_deref(), Some(hash));
+        assert_eq!(
+            uri.agent_id.as_deref(),
+            Some("2b112c8a-d80a-4cff-9c8a-6f3e6fbaf7fb")
+        );
+        assert_eq!(
+            uri.as_string(),
+            format!(
+                "gemini://{hash}/29d207db-ca7e-40ba-87f7-e14c9de60613/2b112c8a-d80a-4cff-9c8a-6f3e6fbaf7fb"
+            )
+        );
+
+        assert!(ThreadUri::parse(&format!("agents://gemini/{hash}")).is_err());
+        assert!(ThreadUri::parse(&format!("agents://codex/{hash}/019c871c")).is_err());
     }
 
     #[test]
@@ -405,4 +838,131 @@ mod tests {
             .expect_err("must reject nested path");
         assert!(format!("{err}").contains("invalid uri"));
     }
+
+    #[test]
+    fn parse_accepts_session_id_prefixes() {
+        let uri = ThreadUri::parse("codex://019C87").expect("prefix should parse");
+        assert_eq!(uri.session_id, "019c87");
+        assert!(uri.has_partial_session_id());
+
+        let uri = ThreadUri::parse("agents://amp/T-019c0797-c4").expect("amp prefix should parse");
+        assert_eq!(uri.session_id, "T-019c0797-c4");
+        assert!(uri.has_partial_session_id());
+
+        let uri = ThreadUri::parse("codex://019c871c-b1f9-7f60-9c4f-87ed09f13592").expect("parse");
+        assert!(!uri.has_partial_session_id());
+
+        assert!(ThreadUri::parse("codex://019").is_err());
+        assert!(ThreadUri::parse("codex://019c871c1").is_err());
+        assert!(ThreadUri::parse("codex://019c871c--b1f9").is_err());
+
+        let uri = ThreadUri::parse("opencode://ses_43a90e3adffejRgrTdlJa48CtE").expect("parse");
+        assert!(!uri.has_partial_session_id());
+        let uri = ThreadUri::parse("opencode://ses_43a90e3a").expect("opencode prefix");
+        assert!(uri.has_partial_session_id());
+    }
+
+    #[test]
+    fn split_page_query_from_uri() {
+        let (base, page) = TimelinePage::split_uri("agents://codex/019c871c?page=3&page_size=50")
+            .expect("page query should parse");
+        assert_eq!(base, "agents://codex/019c871c");
+        assert_eq!(
+            page,
+            Some(TimelinePage {
+                number: 3,
+                size: 50
+            })
+        );
+
+        let (_, page) = TimelinePage::split_uri("agents://codex/019c871c?page=2").expect("parse");
+        assert_eq!(page.map(|page| page.size), Some(TimelinePage::DEFAULT_SIZE));
+        assert_eq!(
+            TimelinePage::split_uri("agents://codex/019c871c").expect("parse"),
+            ("agents://codex/019c871c", None)
+        );
+
+        for invalid in ["?page=0", "?page=x", "?offset=1", "?"] {
+            assert!(TimelinePage::split_uri(&format!("agents://codex/019c871c{invalid}")).is_err());
+        }
+    }
+
+    #[test]
+    fn split_message_anchor_from_uri() {
+        assert_eq!(
+            MessageAnchor::split_uri("agents://codex/019c871c#msg-42").expect("parse"),
+            ("agents://codex/019c871c", Some(MessageAnchor::Number(42)))
+        );
+        assert_eq!(
+            MessageAnchor::split_uri("agents://claude/019c871c#6f1d2c3b-0a4e").expect("parse"),
+            (
+                "agents://claude/019c871c",
+                Some(MessageAnchor::Id("6f1d2c3b-0a4e".to_string()))
+            )
+        );
+        assert_eq!(MessageAnchor::Number(7).to_string(), "msg-7");
+
+        for invalid in ["#", "#msg-0", "#msg-x", "#a#b"] {
+            assert!(
+                MessageAnchor::split_uri(&format!("agents://codex/019c871c{invalid}")).is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn parse_keeps_message_fragment() {
+        let uri =
+            ThreadUri::parse("agents://pi/12cb4c19-2774-4de4-a0d0-9fa32fbae29f/1c130174#msg-3")
+                .expect("parse should succeed");
+        assert_eq!(uri.agent_id, Some("1c130174".to_string()));
+        assert_eq!(uri.fragment, Some(MessageAnchor::Number(3)));
+        assert_eq!(
+            uri.as_agents_string(),
+            "agents://pi/12cb4c19-2774-4de4-a0d0-9fa32fbae29f/1c130174"
+        );
+
+        let uri = ThreadUri::parse("codex://019c871c-b1f9-7f60-9c4f-87ed09f13592")
+            .expect("parse should succeed");
+        assert_eq!(uri.fragment, None);
+        assert!(ThreadUri::parse("codex://019c871c-b1f9-7f60-9c4f-87ed09f13592#msg-0").is_err());
+    }
+
+    #[test]
+    fn page_ranges_cover_the_timeline() {
+        let page = |number| TimelinePage { number, size: 2 };
+        assert_eq!(page(1).range(5), 0..2);
+        assert_eq!(page(3).range(5), 4..5);
+        assert_eq!(page(4).range(5), 5..5);
+        assert_eq!(page(1).total_pages(5), 3);
+        assert_eq!(page(1).total_pages(0), 1);
+    }
+
+    #[test]
+    fn parse_file_uri_with_format() {
+        let uri = ThreadUri::parse("file:///tmp/my%20threads/rollout.jsonl?format=codex#msg-2")
+            .expect("parse should succeed");
+        assert_eq!(uri.provider, ProviderKind::File);
+        assert_eq!(uri.format(), ProviderKind::Codex);
+        assert_eq!(uri.session_id, "/tmp/my threads/rollout.jsonl");
+        assert_eq!(uri.fragment, Some(MessageAnchor::Number(2)));
+        assert_eq!(
+            uri.as_agents_string(),
+            "file:///tmp/my%20threads/rollout.jsonl?format=codex"
+        );
+
+        let (base, page) =
+            TimelinePage::split_uri("file:///tmp/rollout.jsonl?format=claude&page=2")
+                .expect("split");
+        assert_eq!(base, "file:///tmp/rollout.jsonl?format=claude");
+        assert_eq!(page.map(|page| page.number), Some(2));
+
+        for invalid in [
+            "file:///tmp/rollout.jsonl",
+            "file:///tmp/rollout.jsonl?format=file",
+            "file:///tmp/rollout.jsonl?kind=codex",
+            "file://relative/rollout.jsonl?format=codex",
+        ] {
+            assert!(ThreadUri::parse(invalid).is_err(), "{invalid}");
+        }
+    }
 }
@@ -0,0 +1,473 @@
+//! Integrity checks for thread files (`xurl verify`).
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+use crate::compression::read_thread_file;
+use crate::error::{Result, XurlError};
+use crate::model::{ProviderKind, VerifyProblem, VerifyReport, Warning, WarningSeverity};
+use crate::provider::ProviderRoots;
+use crate::provider::codex::CodexProvider;
+use crate::service::resolve_thread;
+use crate::timestamp::{format_rfc3339, parse_timestamp};
+use crate::uri::ThreadUri;
+
+const CHECK_JSON: &str = "json";
+const CHECK_TRUNCATION: &str = "truncation";
+const CHECK_TIMESTAMPS: &str = "timestamps";
+const CHECK_PI_PARENTS: &str = "pi_parents";
+const CHECK_CODEX_INDEX: &str = "codex_index";
+
+/// Checks the stored file of `uri` for problems that make reads incomplete:
+/// unparseable or truncated records, timestamps that go backwards, Pi entries
+/// whose parent is missing, and a Codex sqlite index that disagrees with the
+/// rollout on disk. Pi entry URIs check the whole session file.
+pub fn verify_thread(uri: &ThreadUri, roots: &ProviderRoots) -> Result<VerifyReport> {
+    if uri.agent_id.is_some() && uri.provider != ProviderKind::Pi {
+        return Err(XurlError::InvalidMode(
+            "verify only supports main thread URIs: agents://<provider>/<session_id>".to_string(),
+        ));
+    }
+    let main = ThreadUri {
+        agent_id: None,
+        fragment: None,
+        ..uri.clone()
+    };
+    let resolved = resolve_thread(&main, roots)?;
+    let bytes =
+        read_thread_file(&resolved.path).map_err(|source| XurlError::io(&resolved.path, source))?;
+
+    let mut verifier = Verifier::default();
+    verifier.problems.extend(
+        resolved
+            .metadata
+            .warnings
+            .iter()
+            .filter(|warning| warning.severity >= WarningSeverity::Warn)
+            .map(from_warning),
+    );
+
+    let raw = match String::from_utf8(bytes) {
+        Ok(raw) => raw,
+        Err(err) => {
+            verifier.problem(
+                WarningSeverity::Error,
+                "non_utf8",
+                None,
+                format!(
+                    "file is not valid UTF-8 after byte {}",
+                    err.utf8_error().valid_up_to()
+                ),
+            );
+            String::from_utf8_lossy(err.as_bytes()).into_owned()
+        }
+    };
+
+    match main.format() {
+        ProviderKind::Amp | ProviderKind::Gemini => verifier.check_document(main.format(), &raw),
+        ProviderKind::Codex
+        | ProviderKind::Claude
+        | ProviderKind::Pi
+        | ProviderKind::Opencode
+        | ProviderKind::Copilot
+        | ProviderKind::File => {
+            verifier.check_jsonl(main.format(), &raw);
+        }
+    }
+
+    if main.provider == ProviderKind::Codex {
+        verifier.checks.push(CHECK_CODEX_INDEX);
+        // The state index belongs to the root the rollout was found in.
+        let codex_root = roots
+            .roots_for(ProviderKind::Codex)
+            .into_iter()
+            .find(|root| resolved.path.starts_with(root))
+            .unwrap_or_else(|| roots.codex_root.clone());
+        verifier.problems.extend(
+            CodexProvider::new(&codex_root)
+                .check_state_index(&resolved.session_id, &resolved.path)
+                .iter()
+                .map(from_warning),
+        );
+    }
+
+    Ok(VerifyReport {
+        uri: main.as_agents_string(),
+        provider: main.provider.to_string(),
+        session_id: resolved.session_id,
+        thread_source: resolved.path.display().to_string(),
+        records: verifier.records,
+        checks: verifier.checks,
+        problems: verifier.problems,
+    })
+}
+
+pub fn render_verify_report_markdown(report: &VerifyReport) -> String {
+    let mut output = String::new();
+    output.push_str("# Thread Verification\n\n");
+    output.push_str(&format!("- URI: `{}`\n", report.uri));
+    output.push_str(&format!("- Source: `{}`\n", report.thread_source));
+    output.push_str(&format!("- Records: `{}`\n", report.records));
+    output.push_str(&format!(
+        "- Checks: {}\n",
+        report
+            .checks
+            .iter()
+            .map(|check| format!("`{check}`"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+    output.push_str(&format!(
+        "- Result: `{}`\n",
+        if report.is_ok() {
+            "ok"
+        } else {
+            "problems found"
+        }
+    ));
+
+    output.push_str("\n## Problems\n\n");
+    if report.problems.is_empty() {
+        output.push_str("_No problems found._\n");
+    }
+    for problem in &report.problems {
+        output.push_str(&format!("- `{}` `{}`", problem.severity, problem.code));
+        if let Some(line) = problem.line {
+            output.push_str(&format!(" line {line}"));
+        }
+        output.push_str(&format!(": {}\n", problem.message));
+    }
+
+    output
+}
+
+pub fn render_verify_report_json(report: &VerifyReport) -> Result<String> {
+    serde_json::to_string_pretty(report)
+        .map(|json| format!("{json}\n"))
+        .map_err(|err| XurlError::Serialization(err.to_string()))
+}
+
+fn from_warning(warning: &Warning) -> VerifyProblem {
+    VerifyProblem {
+        severity: warning.severity,
+        code: warning.code,
+        line: None,
+        message: warning.message.clone(),
+    }
+}
+
+#[derive(Default)]
+struct Verifier {
+    records: usize,
+    checks: Vec<&'static str>,
+    problems: Vec<VerifyProblem>,
+}
+
+impl Verifier {
+    fn problem(
+        &mut self,
+        severity: WarningSeverity,
+        code: &'static str,
+        line: Option<usize>,
+        message: impl Into<String>,
+    ) {
+        self.problems.push(VerifyProblem {
+            severity,
+            code,
+            line,
+            message: message.into(),
+        });
+    }
+
+    fn check_jsonl(&mut self, provider: ProviderKind, raw: &str) {
+        self.checks.extend([CHECK_JSON, CHECK_TRUNCATION]);
+        let has_timestamps = provider != ProviderKind::Opencode;
+        if has_timestamps {
+            self.checks.push(CHECK_TIMESTAMPS);
+        }
+
+        let lines = raw
+            .lines()
+            .enumerate()
+            .map(|(idx, line)| (idx + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty())
+            .collect::<Vec<_>>();
+        let last_line_no = lines.last().map(|(line_no, _)| *line_no);
+        let unterminated = !raw.is_empty() && !raw.ends_with('\n');
+
+        let mut timeline = TimestampOrder::default();
+        let mut values = Vec::with_capacity(lines.len());
+        for (line_no, line) in lines {
+            self.records += 1;
+            match serde_json::from_str::<Value>(line) {
+                Ok(value) => {
+                    if has_timestamps {
+                        timeline.check(self, Some(line_no), value.get("timestamp"));
+                    }
+                    values.push((line_no, value));
+                }
+                Err(err) if unterminated && Some(line_no) == last_line_no => self.problem(
+                    WarningSeverity::Error,
+                    "truncated_final_line",
+                    Some(line_no),
+                    format!("last line has no newline and does not parse ({err}); the write was likely cut off"),
+                ),
+                Err(err) => self.problem(
+                    WarningSeverity::Error,
+                    "invalid_json",
+                    Some(line_no),
+                    err.to_string(),
+                ),
+            }
+        }
+
+        if provider == ProviderKind::Pi {
+            self.check_pi_parents(&values);
+        }
+    }
+
+    fn check_document(&mut self, provider: ProviderKind, raw: &str) {
+        self.checks.extend([CHECK_JSON, CHECK_TRUNCATION]);
+        let value = match serde_json::from_str::<Value>(raw) {
+            Ok(value) => value,
+            Err(err) if err.is_eof() => {
+                self.problem(
+                    WarningSeverity::Error,
+                    "truncated_document",
+                    Some(err.line()),
+                    format!("document ends early ({err}); the write was likely cut off"),
+                );
+                return;
+            }
+            Err(err) => {
+                self.problem(
+                    WarningSeverity::Error,
+                    "invalid_json",
+                    Some(err.line()),
+                    err.to_string(),
+                );
+                return;
+            }
+        };
+
+        let messages = value
+            .get("messages")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        self.records = messages.len();
+        if provider == ProviderKind::Gemini {
+            self.checks.push(CHECK_TIMESTAMPS);
+            let mut timeline = TimestampOrder::default();
+            for message in messages {
+                timeline.check(self, None, message.get("timestamp"));
+            }
+        }
+    }
+
+    fn check_pi_parents(&mut self, values: &[(usize, Value)]) {
+        self.checks.push(CHECK_PI_PARENTS);
+        let mut ids = HashSet::new();
+        for (line_no, value) in values {
+            if let Some(id) = value.get("id").and_then(Value::as_str)
+                && !ids.insert(id)
+            {
+                self.problem(
+                    WarningSeverity::Error,
+                    "duplicate_id",
+                    Some(*line_no),
+                    format!("entry id {id} is used more than once"),
+                );
+            }
+        }
+
+        for (line_no, value) in values {
+            if let Some(parent_id) = value.get("parentId").and_then(Value::as_str)
+                && !ids.contains(parent_id)
+            {
+                self.problem(
+                    WarningSeverity::Error,
+                    "dangling_parent",
+                    Some(*line_no),
+                    format!("parent entry {parent_id} does not exist"),
+                );
+            }
+        }
+    }
+}
+
+/// Tracks the latest timestamp seen to report records that go back in time.
+#[derive(Default)]
+struct TimestampOrder {
+    latest: Option<DateTime<Utc>>,
+}
+
+impl TimestampOrder {
+    fn check(&mut self, verifier: &mut Verifier, line: Option<usize>, value: Option<&Value>) {
+        let Some(raw) = value.and_then(Value::as_str) else {
+            return;
+        };
+        let Some(timestamp) = parse_timestamp(raw) else {
+            verifier.problem(
+                WarningSeverity::Warn,
+                "invalid_timestamp",
+                line,
+                format!("cannot parse timestamp {raw:?}"),
+            );
+            return;
+        };
+
+        if let Some(latest) = self.latest
+            && timestamp < latest
+        {
+            verifier.problem(
+                WarningSeverity::Warn,
+                "timestamp_order",
+                line,
+                format!(
+                    "timestamp {raw} is earlier than a previous record ({})",
+                    format_rfc3339(latest)
+                ),
+            );
+            return;
+        }
+        self.latest = Some(timestamp);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use rusqlite::Connection;
+    use tempfile::tempdir;
+
+    use crate::provider::{ProviderRoots, write_test_rollout};
+    use crate::uri::ThreadUri;
+    use crate::verify::verify_thread;
+
+    const SESSION_ID: &str = "019c871c-b1f9-7f60-9c4f-87ed09f13592";
+    const PI_SESSION_ID: &str = "12cb4c19-2774-4de4-a0d0-9fa32fbae29f";
+
+    fn codes(report: &crate::model::VerifyReport) -> Vec<(&'static str, Option<usize>)> {
+        report
+            .problems
+            .iter()
+            .map(|problem| (problem.code, problem.line))
+            .collect()
+    }
+
+    #[test]
+    fn clean_rollout_has_no_problems() {
+        let temp = tempdir().expect("tempdir");
+        let roots = ProviderRoots::for_test(temp.path());
+        write_test_rollout(
+            &roots.codex_root,
+            SESSION_ID,
+            concat!(
+                r#"{"timestamp":"2026-02-23T04:48:50Z","type":"session_meta","payload":{}}"#,
+                "\n",
+                r#"{"timestamp":"2026-02-23T04:48:51Z","type":"response_item","payload":{"type":"message","role":"user","content":[]}}"#,
+                "\n",
+            ),
+        );
+        let uri = ThreadUri::parse(&format!("agents://codex/{SESSION_ID}")).expect("uri");
+
+        let report = verify_thread(&uri, &roots).expect("verify");
+        assert!(report.is_ok(), "{:?}", report.problems);
+        assert_eq!(report.records, 2);
+        assert_eq!(
+            report.checks,
+            vec!["json", "truncation", "timestamps", "codex_index"]
+        );
+    }
+
+    #[test]
+    fn reports_bad_lines_order_and_truncation() {
+        let temp = tempdir().expect("tempdir");
+        let roots = ProviderRoots::for_test(temp.path());
+        write_test_rollout(
+            &roots.codex_root,
+            SESSION_ID,
+            concat!(
+                r#"{"timestamp":"2026-02-23T04:48:52Z","type":"session_meta","payload":{}}"#,
+                "\n",
+                "not json\n",
+                r#"{"timestamp":"2026-02-23T04:48:51Z","type":"event_msg","payload":{}}"#,
+                "\n",
+                r#"{"timestamp":"2026-02-23T04:48:53Z","type":"event_msg","pay"#,
+            ),
+        );
+        let uri = ThreadUri::parse(&format!("agents://codex/{SESSION_ID}")).expect("uri");
+
+        let report = verify_thread(&uri, &roots).expect("verify");
+        assert!(!report.is_ok());
+        assert_eq!(
+            codes(&report),
+            vec![
+                ("invalid_json", Some(2)),
+                ("timestamp_order", Some(3)),
+                ("truncated_final_line", Some(4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_codex_index_pointing_elsewhere() {
+        let temp = tempdir().expect("tempdir");
+        let roots = ProviderRoots::for_test(temp.path());
+        write_test_rollout(
+            &roots.codex_root,
+            SESSION_ID,
+            "{\"type\":\"session_meta\",\"payload\":{}}\n",
+        );
+        let other = temp.path().join("codex/other.jsonl");
+        fs::write(&other, "").expect("write");
+        let conn = Connection::open(temp.path().join("codex/state.sqlite")).expect("open");
+        conn.execute_batch(
+            "CREATE TABLE threads (id TEXT PRIMARY KEY, rollout_path TEXT NOT NULL, archived INTEGER NOT NULL DEFAULT 0);",
+        )
+        .expect("schema");
+        conn.execute(
+            "INSERT INTO threads (id, rollout_path, archived) VALUES (?1, ?2, 1)",
+            (SESSION_ID, other.display().to_string()),
+        )
+        .expect("insert");
+        let uri = ThreadUri::parse(&format!("agents://codex/{SESSION_ID}")).expect("uri");
+
+        let report = verify_thread(&uri, &roots).expect("verify");
+        assert_eq!(
+            codes(&report),
+            vec![("index_mismatch", None), ("index_archived_flag", None)]
+        );
+    }
+
+    #[test]
+    fn reports_pi_entries_with_missing_parents() {
+        let temp = tempdir().expect("tempdir");
+        let dir = temp.path().join("pi/sessions/--tmp--");
+        fs::create_dir_all(&dir).expect("mkdir");
+        fs::write(
+            dir.join(format!("2026-02-23T13-00-12-309Z_{PI_SESSION_ID}.jsonl")),
+            format!(
+                concat!(
+                    r#"{{"type":"session","id":"{}","timestamp":"2026-02-23T13:00:12.309Z"}}"#,
+                    "\n",
+                    r#"{{"type":"message","id":"a1b2c3d4","parentId":null,"timestamp":"2026-02-23T13:00:13Z","message":{{"role":"user","content":"hi"}}}}"#,
+                    "\n",
+                    r#"{{"type":"message","id":"b2c3d4e5","parentId":"ffffffff","timestamp":"2026-02-23T13:00:14Z","message":{{"role":"assistant","content":"hello"}}}}"#,
+                    "\n",
+                ),
+                PI_SESSION_ID
+            ),
+        )
+        .expect("write");
+        let uri = ThreadUri::parse(&format!("agents://pi/{PI_SESSION_ID}/a1b2c3d4")).expect("uri");
+
+        let report = verify_thread(&uri, &ProviderRoots::for_test(temp.path())).expect("verify");
+        assert_eq!(report.uri, format!("agents://pi/{PI_SESSION_ID}"));
+        assert_eq!(codes(&report), vec![("dangling_parent", Some(3))]);
+    }
+}
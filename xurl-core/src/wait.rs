@@ -0,0 +1,319 @@
+//! Polling a subagent until it finishes (`xurl wait`, `--follow`).
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Instant;
+
+use crate::error::{Result, XurlError};
+use crate::model::{ProviderKind, SubagentDetailView, SubagentView, ThreadMessage, WaitRequest};
+use crate::provider::ProviderRoots;
+use crate::render::{self, ExtractionPolicy, ReadOptions};
+use crate::service::resolve_subagent_view;
+use crate::status::{InferredStatus, TERMINAL_STATUSES};
+use crate::uri::ThreadUri;
+
+/// Re-reads the subagent at `uri` every `req.interval` until its status is
+/// terminal (`completed`, `errored`, or `shutdown`) and returns that status.
+/// `on_change` sees the first status and every later change of status.
+/// Fails with [`XurlError::WaitTimedOut`] once `req.timeout` has passed.
+pub fn wait_for_subagent(
+    uri: &ThreadUri,
+    roots: &ProviderRoots,
+    req: &WaitRequest,
+    mut on_change: impl FnMut(&InferredStatus),
+) -> Result<InferredStatus> {
+    if uri.agent_id.is_none() {
+        return Err(XurlError::InvalidMode(
+            "wait requires a subagent URI: agents://<provider>/<main_thread_id>/<agent_id>"
+                .to_string(),
+        ));
+    }
+
+    let mut last_status = None::<String>;
+    poll_subagent(uri, req, || {
+        let detail = subagent_detail(uri, roots)?;
+        let current = InferredStatus::new(detail.status, detail.status_source);
+        if last_status.as_deref() != Some(current.status.as_str()) {
+            on_change(&current);
+        }
+        last_status = Some(current.status.clone());
+        Ok(current)
+    })
+}
+
+/// Streams the messages of a Claude subagent's `agent-*.jsonl` transcript to
+/// `on_message` as they are appended, re-reading it every `req.interval`,
+/// and returns the subagent's status once it is terminal. Messages already
+/// in the transcript are passed on first; an error from `on_message` stops
+/// following. Only complete lines are read, so a
+/// record being written is picked up on the next poll.
+pub fn follow_subagent(
+    uri: &ThreadUri,
+    roots: &ProviderRoots,
+    req: &WaitRequest,
+    mut on_message: impl FnMut(&ThreadMessage) -> Result<()>,
+) -> Result<InferredStatus> {
+    if uri.provider != ProviderKind::Claude || uri.agent_id.is_none() {
+        return Err(XurlError::InvalidMode(
+            "follow requires a Claude subagent URI: agents://claude/<main_thread_id>/<agent_id>"
+                .to_string(),
+        ));
+    }
+
+    let mut tail = None::<TranscriptTail>;
+    poll_subagent(uri, req, || {
+        // Status first: whatever it reports was written before the read below.
+        let detail = subagent_detail(uri, roots)?;
+        let path = detail
+            .child_thread
+            .as_ref()
+            .and_then(|thread| thread.path.as_deref())
+            .map(PathBuf::from);
+        if let Some(path) = path {
+            let tail = match &mut tail {
+                Some(tail) if tail.path == path => tail,
+                _ => tail.insert(TranscriptTail { path, offset: 0 }),
+            };
+            for message in tail.read_messages()? {
+                on_message(&message)?;
+            }
+        }
+        Ok(InferredStatus::new(detail.status, detail.status_source))
+    })
+}
+
+/// Calls `check` every `req.interval` until the status it returns is
+/// terminal, failing with [`XurlError::WaitTimedOut`] after `req.timeout`.
+fn poll_subagent(
+    uri: &ThreadUri,
+    req: &WaitRequest,
+    mut check: impl FnMut() -> Result<InferredStatus>,
+) -> Result<InferredStatus> {
+    let started = Instant::now();
+    loop {
+        let current = check()?;
+        if TERMINAL_STATUSES.contains(&current.status.as_str()) {
+            return Ok(current);
+        }
+
+        let elapsed = started.elapsed();
+        let pause = match req.timeout {
+            Some(timeout) if elapsed >= timeout => {
+                return Err(XurlError::WaitTimedOut {
+                    uri: uri.as_agents_string(),
+                    status: current.status,
+                    seconds: timeout.as_secs(),
+                });
+            }
+            Some(timeout) => req.interval.min(timeout - elapsed),
+            None => req.interval,
+        };
+        thread::sleep(pause);
+    }
+}
+
+/// The status and thread of the subagent at `uri`, which no read option
+/// changes.
+fn subagent_detail(uri: &ThreadUri, roots: &ProviderRoots) -> Result<SubagentDetailView> {
+    match resolve_subagent_view(uri, roots, &ReadOptions::default(), false)? {
+        SubagentView::Detail(detail) => Ok(detail),
+        SubagentView::List(_) => Err(XurlError::InvalidMode(
+            "subagent drill-down did not return a detail view".to_string(),
+        )),
+    }
+}
+
+/// Read position in a transcript that is still being appended to.
+struct TranscriptTail {
+    path: PathBuf,
+    offset: u64,
+}
+
+impl TranscriptTail {
+    /// Messages in the complete lines appended since the last read.
+    fn read_messages(&mut self) -> Result<Vec<ThreadMessage>> {
+        let io_error = |source| XurlError::io(&self.path, source);
+        let mut file = File::open(&self.path).map_err(io_error)?;
+        // A shorter file was rewritten; start over.
+        if file.metadata().map_err(io_error)?.len() < self.offset {
+            self.offset = 0;
+        }
+        file.seek(SeekFrom::Start(self.offset)).map_err(io_error)?;
+        let mut appended = Vec::new();
+        file.read_to_end(&mut appended).map_err(io_error)?;
+
+        let Some(end) = appended.iter().rposition(|byte| *byte == b'\n') else {
+            return Ok(Vec::new());
+        };
+        self.offset += end as u64 + 1;
+        let chunk = String::from_utf8_lossy(&appended[..=end]);
+        render::extract_messages(
+            ProviderKind::Claude,
+            &self.path,
+            &chunk,
+            &mut ExtractionPolicy::default(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use std::time::Duration;
+
+    use crate::error::XurlError;
+    use crate::model::WaitRequest;
+    use crate::provider::ProviderRoots;
+    use crate::uri::ThreadUri;
+    use crate::wait::{follow_subagent, wait_for_subagent};
+
+    const MAIN_ID: &str = "T-019c0797-c402-7389-bd80-d785c98df295";
+    const CHILD_ID: &str = "T-1abc0797-c402-7389-bd80-d785c98df295";
+    const CLAUDE_SESSION_ID: &str = "2823d1df-720a-4c31-ac55-ae8ba726721f";
+    const CLAUDE_AGENT_ID: &str = "a1b2c3d";
+
+    fn roots(base: &Path, child_status: &str) -> ProviderRoots {
+        let threads = base.join("amp/threads");
+        fs::create_dir_all(&threads).expect("mkdir");
+        fs::write(
+            threads.join(format!("{MAIN_ID}.json")),
+            format!(
+                r#"{{"id":"{MAIN_ID}","messages":[],"relationships":[{{"type":"handoff","threadID":"{CHILD_ID}","role":"child"}}]}}"#
+            ),
+        )
+        .expect("write main");
+        fs::write(
+            threads.join(format!("{CHILD_ID}.json")),
+            format!(
+                r#"{{"id":"{CHILD_ID}","status":"{child_status}","messages":[],"relationships":[{{"type":"handoff","threadID":"{MAIN_ID}","role":"parent"}}]}}"#
+            ),
+        )
+        .expect("write child");
+
+        ProviderRoots::for_test(base)
+    }
+
+    fn request(timeout: Option<Duration>) -> WaitRequest {
+        WaitRequest {
+            timeout,
+            interval: Duration::from_millis(10),
+        }
+    }
+
+    #[test]
+    fn returns_once_the_subagent_is_terminal() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let roots = roots(temp.path(), "completed");
+        let uri = ThreadUri::parse(&format!("agents://amp/{MAIN_ID}/{CHILD_ID}")).expect("uri");
+
+        let mut seen = Vec::new();
+        let status = wait_for_subagent(&uri, &roots, &request(None), |status| {
+            seen.push(status.status.clone());
+        })
+        .expect("wait");
+        assert_eq!(status.status, "completed");
+        assert_eq!(seen, vec!["completed"]);
+    }
+
+    #[test]
+    fn times_out_while_the_subagent_runs() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let roots = roots(temp.path(), "running");
+        let uri = ThreadUri::parse(&format!("agents://amp/{MAIN_ID}/{CHILD_ID}")).expect("uri");
+
+        let mut seen = Vec::new();
+        let err = wait_for_subagent(
+            &uri,
+            &roots,
+            &request(Some(Duration::from_millis(50))),
+            |status| seen.push(status.status.clone()),
+        )
+        .expect_err("must time out");
+        assert!(
+            matches!(&err, XurlError::WaitTimedOut { status, .. } if status == "running"),
+            "{err}"
+        );
+        // Repeated polls report the unchanged status once.
+        assert_eq!(seen, vec!["running"]);
+
+        let main = ThreadUri::parse(&format!("agents://amp/{MAIN_ID}")).expect("uri");
+        let err = wait_for_subagent(&main, &roots, &request(None), |_| {}).expect_err("main uri");
+        assert!(matches!(err, XurlError::InvalidMode(_)));
+    }
+
+    fn claude_line(kind: &str, content: &str) -> String {
+        format!(
+            r#"{{"type":"{kind}","sessionId":"{CLAUDE_SESSION_ID}","isSidechain":true,"agentId":"{CLAUDE_AGENT_ID}","message":{{"role":"{kind}","content":{content}}}}}"#
+        ) + "\n"
+    }
+
+    #[test]
+    fn follow_streams_appended_messages_until_the_agent_finishes() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let project = temp.path().join("projects/project");
+        let subagents = project.join(CLAUDE_SESSION_ID).join("subagents");
+        fs::create_dir_all(&subagents).expect("mkdir");
+        fs::write(
+            project.join(format!("{CLAUDE_SESSION_ID}.jsonl")),
+            format!(
+                r#"{{"type":"user","sessionId":"{CLAUDE_SESSION_ID}","message":{{"role":"user","content":"root"}}}}"#
+            ) + "\n",
+        )
+        .expect("write main");
+        let agent = subagents.join(format!("agent-{CLAUDE_AGENT_ID}.jsonl"));
+        fs::write(
+            &agent,
+            claude_line("user", r#""list the files""#)
+                + &claude_line(
+                    "assistant",
+                    r#"[{"type":"text","text":"looking"},{"type":"tool_use","name":"ls"}]"#,
+                ),
+        )
+        .expect("write agent");
+
+        let mut roots = ProviderRoots::for_test(temp.path());
+        roots.claude_root = temp.path().to_path_buf();
+        let uri = ThreadUri::parse(&format!(
+            "agents://claude/{CLAUDE_SESSION_ID}/{CLAUDE_AGENT_ID}"
+        ))
+        .expect("uri");
+
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            let mut file = fs::File::options().append(true).open(&agent).expect("open");
+            std::io::Write::write_all(
+                &mut file,
+                (claude_line("user", r#"[{"type":"tool_result","content":"a.rs"}]"#)
+                    + &claude_line("assistant", r#""found a.rs""#))
+                    .as_bytes(),
+            )
+            .expect("append");
+        });
+
+        let mut seen = Vec::new();
+        let status = follow_subagent(
+            &uri,
+            &roots,
+            &request(Some(Duration::from_secs(10))),
+            |message| {
+                seen.push(format!("{}: {}", message.role, message.text));
+                Ok(())
+            },
+        )
+        .expect("follow");
+        writer.join().expect("writer");
+
+        assert_eq!(status.status, "completed");
+        assert_eq!(
+            seen,
+            vec![
+                "user: list the files",
+                "assistant: looking",
+                "assistant: found a.rs"
+            ]
+        );
+    }
+}
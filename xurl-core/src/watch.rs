@@ -0,0 +1,327 @@
+//! Rate-limited status polling for integrations that keep watching a thread
+//! or subagent, such as GUIs and daemons.
+//!
+//! A [`WatchHandle`] re-reads its URI at most once per interval and reports
+//! a [`WatchState`] only when it differs from the last one. While nothing
+//! changes, the interval doubles up to a ceiling, so idle watches cost
+//! little; the first change brings it back down.
+
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+
+use crate::error::{Result, XurlError};
+use crate::model::{ProviderKind, SubagentListItem, SubagentView, WatchState};
+use crate::provider::{ProviderRoots, amp, modified_or_epoch};
+use crate::render::ReadOptions;
+use crate::service::{provider_for, resolve_subagent_view, resolve_thread};
+use crate::status::worst_status;
+use crate::timestamp::format_rfc3339;
+use crate::uri::ThreadUri;
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_MAX_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Watches the status of one thread or subagent.
+///
+/// Subagent URIs report the subagent's status and the last update of its own
+/// thread. Main thread URIs report when the thread last changed and, for
+/// providers with subagents, the status of each subagent.
+#[derive(Debug, Clone)]
+pub struct WatchHandle {
+    uri: ThreadUri,
+    roots: ProviderRoots,
+    poll_interval: Duration,
+    max_interval: Duration,
+    interval: Duration,
+    next_poll: Option<Instant>,
+    last: Option<WatchState>,
+}
+
+impl WatchHandle {
+    pub fn new(uri: ThreadUri, roots: ProviderRoots) -> Self {
+        Self {
+            uri,
+            roots,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            max_interval: DEFAULT_MAX_INTERVAL,
+            interval: DEFAULT_POLL_INTERVAL,
+            next_poll: None,
+            last: None,
+        }
+    }
+
+    /// Shortest pause between reads (default: 1s), used again after every
+    /// change.
+    #[must_use]
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self.interval = poll_interval;
+        self
+    }
+
+    /// Longest pause between reads while nothing changes (default: 30s).
+    /// Set it to the poll interval to turn backoff off.
+    #[must_use]
+    pub fn with_max_interval(mut self, max_interval: Duration) -> Self {
+        self.max_interval = max_interval;
+        self
+    }
+
+    pub fn uri(&self) -> &ThreadUri {
+        &self.uri
+    }
+
+    /// The state the last successful read saw.
+    pub fn last_state(&self) -> Option<&WatchState> {
+        self.last.as_ref()
+    }
+
+    /// How long until [`Self::poll`] reads again; zero when it is due.
+    pub fn next_poll_in(&self) -> Duration {
+        self.next_poll.map_or(Duration::ZERO, |at| {
+            at.saturating_duration_since(Instant::now())
+        })
+    }
+
+    /// Reads the watched URI if the interval has passed, without blocking.
+    /// Returns the new state when it changed; the first read always counts
+    /// as a change. A failed read still waits out the interval.
+    pub fn poll(&mut self) -> Result<Option<WatchState>> {
+        if !self.next_poll_in().is_zero() {
+            return Ok(None);
+        }
+        let state = match read_state(&self.uri, &self.roots) {
+            Ok(state) => state,
+            Err(err) => {
+                self.next_poll = Some(Instant::now() + self.interval);
+                return Err(err);
+            }
+        };
+
+        let changed = self.last.as_ref() != Some(&state);
+        self.interval = if changed {
+            self.poll_interval
+        } else {
+            self.interval
+                .saturating_mul(2)
+                .min(self.max_interval.max(self.poll_interval))
+        };
+        self.next_poll = Some(Instant::now() + self.interval);
+        if !changed {
+            return Ok(None);
+        }
+        self.last = Some(state.clone());
+        Ok(Some(state))
+    }
+
+    /// Blocks until the watched URI changes and returns its new state, or
+    /// returns `None` once `timeout` has passed without a change.
+    pub fn wait_for_change(&mut self, timeout: Option<Duration>) -> Result<Option<WatchState>> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            if let Some(state) = self.poll()? {
+                return Ok(Some(state));
+            }
+            let mut pause = self.next_poll_in();
+            if let Some(deadline) = deadline {
+                let left = deadline.saturating_duration_since(Instant::now());
+                if left.is_zero() {
+                    return Ok(None);
+                }
+                pause = pause.min(left);
+            }
+            thread::sleep(pause);
+        }
+    }
+}
+
+fn read_state(uri: &ThreadUri, roots: &ProviderRoots) -> Result<WatchState> {
+    // Statuses and update times do not depend on how threads are rendered.
+    let options = ReadOptions::default();
+    let supports_subagents = uri.provider.capabilities().supports_subagents;
+    if uri.agent_id.is_some() && supports_subagents {
+        let SubagentView::Detail(detail) = resolve_subagent_view(uri, roots, &options, false)?
+        else {
+            return Err(XurlError::InvalidMode(
+                "subagent drill-down did not return a detail view".to_string(),
+            ));
+        };
+        return Ok(WatchState {
+            uri: uri.as_agents_string(),
+            status: Some(detail.status),
+            last_update: detail
+                .child_thread
+                .and_then(|thread| thread.last_updated_at),
+            subagents: BTreeMap::new(),
+        });
+    }
+
+    // Pi entry URIs watch their whole session.
+    let main = ThreadUri {
+        agent_id: None,
+        fragment: None,
+        ..uri.clone()
+    };
+    let mut subagents = BTreeMap::new();
+    if supports_subagents
+        && let SubagentView::List(list) = resolve_subagent_view(&main, roots, &options, true)?
+    {
+        collect_statuses(&list.agents, &mut subagents);
+    }
+    Ok(WatchState {
+        uri: main.as_agents_string(),
+        status: worst_status(subagents.values().map(String::as_str)).map(str::to_string),
+        last_update: Some(thread_last_update(&main, roots)?),
+        subagents,
+    })
+}
+
+fn collect_statuses(agents: &[SubagentListItem], statuses: &mut BTreeMap<String, String>) {
+    for agent in agents {
+        statuses.insert(agent.agent_id.clone(), agent.status.clone());
+        collect_statuses(&agent.children, statuses);
+    }
+}
+
+/// When the thread last changed: its file's modification time (for Amp
+/// directory threads, that of their newest shard), or for providers whose
+/// resolved file is rewritten on every read, the update time their database
+/// records.
+fn thread_last_update(uri: &ThreadUri, roots: &ProviderRoots) -> Result<String> {
+    let updated_at = match uri.provider {
+        ProviderKind::Opencode => provider_for(uri.provider, roots)
+            .list_threads()?
+            .into_iter()
+            .find(|listing| listing.session_id == uri.session_id)
+            .map(|listing| listing.updated_at)
+            .ok_or_else(|| XurlError::ThreadNotFound {
+                provider: uri.provider.to_string(),
+                session_id: uri.session_id.clone(),
+                searched_roots: roots.roots_for(uri.provider),
+            })?,
+        ProviderKind::Amp => amp::thread_modified(&resolve_thread(uri, roots)?.path),
+        _ => modified_or_epoch(&resolve_thread(uri, roots)?.path),
+    };
+    Ok(format_rfc3339(DateTime::<Utc>::from(updated_at)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use std::time::{Duration, SystemTime};
+
+    use crate::provider::ProviderRoots;
+    use crate::uri::ThreadUri;
+    use crate::watch::WatchHandle;
+
+    const MAIN_ID: &str = "T-019c0797-c402-7389-bd80-d785c98df295";
+    const CHILD_ID: &str = "T-1abc0797-c402-7389-bd80-d785c98df295";
+
+    fn write_child(threads: &Path, status: &str) {
+        fs::write(
+            threads.join(format!("{CHILD_ID}.json")),
+            format!(
+                r#"{{"id":"{CHILD_ID}","status":"{status}","messages":[],"relationships":[{{"type":"handoff","threadID":"{MAIN_ID}","role":"child"}}]}}"#
+            ),
+        )
+        .expect("write child");
+    }
+
+    fn roots(base: &Path) -> ProviderRoots {
+        let threads = base.join("amp/threads");
+        fs::create_dir_all(&threads).expect("mkdir");
+        fs::write(
+            threads.join(format!("{MAIN_ID}.json")),
+            format!(
+                r#"{{"id":"{MAIN_ID}","messages":[],"relationships":[{{"type":"handoff","threadID":"{CHILD_ID}","role":"parent"}}]}}"#
+            ),
+        )
+        .expect("write main");
+        write_child(&threads, "running");
+
+        ProviderRoots::for_test(base)
+    }
+
+    #[test]
+    fn reports_changes_only_and_backs_off_while_idle() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let roots = roots(temp.path());
+        let uri = ThreadUri::parse(&format!("agents://amp/{MAIN_ID}/{CHILD_ID}")).expect("uri");
+        let mut watch = WatchHandle::new(uri, roots)
+            .with_poll_interval(Duration::from_millis(10))
+            .with_max_interval(Duration::from_millis(40));
+
+        let first = watch.poll().expect("poll").expect("first read");
+        assert_eq!(first.status.as_deref(), Some("running"));
+        assert!(!first.is_terminal());
+        // Not due yet: nothing is read.
+        assert_eq!(watch.poll().expect("poll"), None);
+
+        std::thread::sleep(watch.next_poll_in());
+        assert_eq!(watch.poll().expect("poll"), None);
+        assert!(watch.next_poll_in() > Duration::from_millis(10));
+
+        write_child(&temp.path().join("amp/threads"), "completed");
+        let changed = watch
+            .wait_for_change(Some(Duration::from_secs(5)))
+            .expect("wait")
+            .expect("change");
+        assert_eq!(changed.status.as_deref(), Some("completed"));
+        assert!(changed.is_terminal());
+        assert_eq!(watch.last_state(), Some(&changed));
+        assert_eq!(
+            watch
+                .wait_for_change(Some(Duration::from_millis(30)))
+                .expect("wait"),
+            None
+        );
+    }
+
+    #[test]
+    fn appended_amp_shards_update_the_last_update() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let dir = temp.path().join(format!("amp/threads/{MAIN_ID}"));
+        fs::create_dir_all(&dir).expect("mkdir");
+        fs::write(dir.join("thread.json"), format!(r#"{{"id":"{MAIN_ID}"}}"#)).expect("write");
+        let uri = ThreadUri::parse(&format!("agents://amp/{MAIN_ID}")).expect("uri");
+        let mut watch = WatchHandle::new(uri, ProviderRoots::for_test(temp.path()))
+            .with_poll_interval(Duration::from_millis(10));
+        let first = watch.poll().expect("poll").expect("first read");
+
+        let shard = dir.join("messages-1.json");
+        fs::write(&shard, r#"{"messages":[]}"#).expect("write shard");
+        fs::File::options()
+            .write(true)
+            .open(&shard)
+            .and_then(|file| file.set_modified(SystemTime::now() + Duration::from_secs(3600)))
+            .expect("set mtime");
+        let changed = watch
+            .wait_for_change(Some(Duration::from_secs(5)))
+            .expect("wait")
+            .expect("change");
+        assert!(changed.last_update > first.last_update);
+    }
+
+    #[test]
+    fn main_thread_lists_subagent_statuses() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let roots = roots(temp.path());
+        let uri = ThreadUri::parse(&format!("agents://amp/{MAIN_ID}")).expect("uri");
+
+        let state = WatchHandle::new(uri, roots)
+            .poll()
+            .expect("poll")
+            .expect("first read");
+        assert_eq!(state.uri, format!("agents://amp/{MAIN_ID}"));
+        assert_eq!(state.status.as_deref(), Some("running"));
+        assert_eq!(
+            state.subagents.get(CHILD_ID).map(String::as_str),
+            Some("running")
+        );
+        assert!(state.last_update.is_some());
+    }
+}
@@ -0,0 +1,249 @@
+//! Structured YAML emitter for frontmatter.
+//!
+//! xurl only emits block mappings, block sequences, booleans, integers, and
+//! strings, so this covers exactly that instead of pulling in a YAML library.
+//! Strings are single-quoted when that represents them exactly, and double-quoted with
+//! escapes when they hold line breaks or control characters, which single
+//! quotes would fold or cannot carry.
+
+use std::fmt::Write as _;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum YamlValue {
+    String(String),
+    Bool(bool),
+    Integer(usize),
+    Sequence(Vec<YamlValue>),
+    Mapping(YamlMapping),
+}
+
+/// A mapping that keeps keys in insertion order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct YamlMapping {
+    entries: Vec<(&'static str, YamlValue)>,
+}
+
+impl YamlMapping {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, key: &'static str, value: impl Into<YamlValue>) {
+        self.entries.push((key, value.into()));
+    }
+
+    /// Inserts `value` when present; absent keys are left out entirely.
+    pub fn insert_opt<V: Into<YamlValue>>(&mut self, key: &'static str, value: Option<V>) {
+        if let Some(value) = value {
+            self.insert(key, value);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.entries.iter().map(|(key, _)| *key)
+    }
+
+    /// Keeps only the entries whose key `keep` accepts, in their order.
+    pub fn retain(&mut self, mut keep: impl FnMut(&str) -> bool) {
+        self.entries.retain(|(key, _)| keep(key));
+    }
+
+    /// Emits the mapping as a YAML block document body.
+    pub fn to_yaml(&self) -> String {
+        let mut output = String::new();
+        write_mapping(&mut output, self, 0, false);
+        output
+    }
+
+    /// Emits the mapping wrapped in `---` fences.
+    pub fn to_frontmatter(&self) -> String {
+        format!("---\n{}---\n", self.to_yaml())
+    }
+}
+
+impl From<&str> for YamlValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<&String> for YamlValue {
+    fn from(value: &String) -> Self {
+        Self::String(value.clone())
+    }
+}
+
+impl From<String> for YamlValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<bool> for YamlValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<usize> for YamlValue {
+    fn from(value: usize) -> Self {
+        Self::Integer(value)
+    }
+}
+
+impl From<YamlMapping> for YamlValue {
+    fn from(value: YamlMapping) -> Self {
+        Self::Mapping(value)
+    }
+}
+
+impl<T: Into<YamlValue>> From<Vec<T>> for YamlValue {
+    fn from(values: Vec<T>) -> Self {
+        Self::Sequence(values.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Writes `mapping` at `indent`. With `inline_first`, the first key continues
+/// the current line (after a sequence dash) instead of starting a new one.
+fn write_mapping(output: &mut String, mapping: &YamlMapping, indent: usize, inline_first: bool) {
+    for (idx, (key, value)) in mapping.entries.iter().enumerate() {
+        if idx > 0 || !inline_first {
+            push_indent(output, indent);
+        }
+        output.push_str(key);
+        output.push(':');
+        write_value(output, value, indent);
+    }
+}
+
+/// Writes the part of `key: value` after the colon for a key at `indent`.
+fn write_value(output: &mut String, value: &YamlValue, indent: usize) {
+    match value {
+        YamlValue::String(value) => {
+            output.push(' ');
+            output.push_str(&quote(value));
+            output.push('\n');
+        }
+        YamlValue::Bool(value) => {
+            let _ = writeln!(output, " {value}");
+        }
+        YamlValue::Integer(value) => {
+            let _ = writeln!(output, " {value}");
+        }
+        YamlValue::Sequence(items) if items.is_empty() => {
+            output.push('\n');
+            push_indent(output, indent + 2);
+            output.push_str("[]\n");
+        }
+        YamlValue::Sequence(items) => {
+            output.push('\n');
+            write_sequence(output, items, indent + 2);
+        }
+        YamlValue::Mapping(mapping) if mapping.is_empty() => output.push_str(" {}\n"),
+        YamlValue::Mapping(mapping) => {
+            output.push('\n');
+            write_mapping(output, mapping, indent + 2, false);
+        }
+    }
+}
+
+fn write_sequence(output: &mut String, items: &[YamlValue], indent: usize) {
+    for item in items {
+        push_indent(output, indent);
+        output.push('-');
+        match item {
+            YamlValue::Mapping(mapping) if !mapping.is_empty() => {
+                output.push(' ');
+                write_mapping(output, mapping, indent + 2, true);
+            }
+            YamlValue::Sequence(nested) if !nested.is_empty() => {
+                output.push('\n');
+                write_sequence(output, nested, indent + 2);
+            }
+            YamlValue::Sequence(_) => output.push_str(" []\n"),
+            other => write_value(output, other, indent),
+        }
+    }
+}
+
+fn push_indent(output: &mut String, indent: usize) {
+    output.extend(std::iter::repeat_n(' ', indent));
+}
+
+fn quote(value: &str) -> String {
+    if !value.chars().any(needs_escape) {
+        return format!("'{}'", value.replace('\'', "''"));
+    }
+
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for ch in value.chars() {
+        match ch {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            '\0' => quoted.push_str("\\0"),
+            ch if needs_escape(ch) => {
+                let _ = write!(quoted, "\\u{:04X}", u32::from(ch));
+            }
+            ch => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Characters a single-quoted scalar cannot hold verbatim: control
+/// characters (including line breaks), Unicode line/paragraph separators,
+/// and the byte order mark.
+fn needs_escape(ch: char) -> bool {
+    ch.is_control() || matches!(ch, '\u{2028}' | '\u{2029}' | '\u{feff}')
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::yaml::{YamlMapping, YamlValue};
+
+    #[test]
+    fn quotes_plain_strings_with_single_quotes() {
+        let mut mapping = YamlMapping::new();
+        mapping.insert("path", "/tmp/it's here");
+        mapping.insert("flag", true);
+
+        assert_eq!(mapping.to_yaml(), "path: '/tmp/it''s here'\nflag: true\n");
+    }
+
+    #[test]
+    fn escapes_multiline_and_control_characters() {
+        let mut mapping = YamlMapping::new();
+        mapping.insert("preview", "first \"line\"\nsecond\tline\u{7}\u{2028}\\");
+
+        assert_eq!(
+            mapping.to_yaml(),
+            "preview: \"first \\\"line\\\"\\nsecond\\tline\\u0007\\u2028\\\\\"\n"
+        );
+    }
+
+    #[test]
+    fn nests_sequences_of_mappings() {
+        let mut child = YamlMapping::new();
+        child.insert("id", "b");
+        let mut parent = YamlMapping::new();
+        parent.insert("id", "a");
+        parent.insert("children", vec![child]);
+        let mut mapping = YamlMapping::new();
+        mapping.insert("items", vec![parent]);
+        mapping.insert("empty", Vec::<YamlValue>::new());
+
+        assert_eq!(
+            mapping.to_frontmatter(),
+            "---\nitems:\n  - id: 'a'\n    children:\n      - id: 'b'\nempty:\n  []\n---\n"
+        );
+    }
+}